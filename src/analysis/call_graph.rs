@@ -0,0 +1,789 @@
+use crate::analysis::hierarchy::{ClassFileHierarchy, ClassHierarchy, OBJECT};
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::{ACC_ABSTRACT, ACC_INTERFACE, ACC_STATIC};
+use crate::class_file::attribute::bootstrap_methods::BootstrapMethods;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::constant_pool::types::method_handle::ReferenceKind;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::rc::Rc;
+use std::{fmt, io};
+
+// A whole-program call graph built outward from entry points. Virtual and interface calls go to
+// every implementation in a receiver type's subtypes, either all of them (class hierarchy
+// analysis) or only those of classes some reachable code instantiates (rapid type analysis).
+// Calls into classes whose code isn't given end there, so library callbacks into the program
+// aren't seen.
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+
+/// A method by its class, name and descriptor, written `p/Main.main([Ljava/lang/String;)V`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodId {
+    class_name: String,
+    name: String,
+    descriptor: String,
+}
+
+impl MethodId {
+    pub fn new(class_name: &str, name: &str, descriptor: &str) -> MethodId {
+        MethodId {
+            class_name: class_name.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        }
+    }
+
+    /// Parses `class.name(descriptor)`, where the class may use dots or slashes
+    pub fn parse(text: &str) -> Result<MethodId, io::Error> {
+        let malformed = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expected class.name(descriptor) but found {text}"),
+            )
+        };
+        let open = text.find('(').ok_or_else(malformed)?;
+        let dot = text[..open].rfind('.').ok_or_else(malformed)?;
+        if dot == 0 || dot + 1 == open {
+            return Err(malformed());
+        }
+        Ok(MethodId::new(
+            &text[..dot].replace('.', "/"),
+            &text[dot + 1..open],
+            &text[open..],
+        ))
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+}
+
+impl fmt::Display for MethodId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}{}", self.class_name, self.name, self.descriptor)
+    }
+}
+
+/// How a call site reaches its target
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    Static,
+    Special,
+    Virtual,
+    Interface,
+    /// the implementation method of a lambda or method reference
+    Dynamic,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CallEdge {
+    caller: MethodId,
+    pc: u32,
+    callee: MethodId,
+    kind: CallKind,
+}
+
+impl CallEdge {
+    pub fn caller(&self) -> &MethodId {
+        &self.caller
+    }
+
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    pub fn callee(&self) -> &MethodId {
+        &self.callee
+    }
+
+    pub fn kind(&self) -> CallKind {
+        self.kind
+    }
+}
+
+/// Which receiver types a virtual call can dispatch on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Analysis {
+    /// every concrete subtype of the declared class
+    ClassHierarchy,
+    /// only subtypes that reachable code instantiates with `new` or a constructor reference
+    RapidType,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    methods: BTreeSet<MethodId>,
+    reachable: BTreeSet<MethodId>,
+    instantiated: BTreeSet<String>,
+    edges: Vec<CallEdge>,
+}
+
+impl CallGraph {
+    /// Builds the graph of everything reachable from the entry points through the code of
+    /// `classes`. The hierarchy has to hold `classes` and, for dispatch through library types,
+    /// the libraries too.
+    pub fn build(
+        classes: &[ClassFile],
+        hierarchy: &ClassFileHierarchy,
+        entry_points: &[MethodId],
+        analysis: Analysis,
+    ) -> Result<CallGraph, io::Error> {
+        let mut builder = Builder {
+            classes: HashMap::new(),
+            hierarchy,
+            analysis,
+            graph: CallGraph::default(),
+            seen_edges: HashSet::new(),
+            sites: HashMap::new(),
+            receivers: HashMap::new(),
+            targets: HashMap::new(),
+            worklist: Vec::new(),
+        };
+        for class in classes {
+            let constant_pool = class.constant_pool();
+            let class_name = class.class_name()?;
+            for method in class.methods() {
+                if method.code(constant_pool)?.is_some() {
+                    builder.graph.methods.insert(MethodId::new(
+                        &class_name,
+                        &method.name(constant_pool)?,
+                        &method.descriptor(constant_pool)?,
+                    ));
+                }
+            }
+            builder.classes.insert(class_name, class);
+        }
+        for entry_point in entry_points {
+            builder.initialize(entry_point.class_name());
+            builder.worklist.push(entry_point.clone());
+        }
+        builder.run()?;
+        Ok(builder.graph)
+    }
+
+    /// Every method with code in the classes the graph was built from
+    pub fn methods(&self) -> &BTreeSet<MethodId> {
+        &self.methods
+    }
+
+    /// The entry points and every method they can call, including library methods
+    pub fn reachable(&self) -> &BTreeSet<MethodId> {
+        &self.reachable
+    }
+
+    pub fn is_reachable(&self, method: &MethodId) -> bool {
+        self.reachable.contains(method)
+    }
+
+    /// Methods with code that nothing reachable calls: dead code, as far as the analysis can
+    /// tell
+    pub fn unreachable(&self) -> Vec<&MethodId> {
+        self.methods.difference(&self.reachable).collect()
+    }
+
+    /// The classes reachable code instantiates
+    pub fn instantiated(&self) -> &BTreeSet<String> {
+        &self.instantiated
+    }
+
+    /// Every call, in the order they were found
+    pub fn edges(&self) -> &[CallEdge] {
+        &self.edges
+    }
+
+    pub fn callees(&self, method: &MethodId) -> Vec<&CallEdge> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.caller == *method)
+            .collect()
+    }
+
+    pub fn callers(&self, method: &MethodId) -> Vec<&CallEdge> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.callee == *method)
+            .collect()
+    }
+
+    /// Renders the calls in Graphviz DOT format, with lambda and method reference calls dashed
+    pub fn to_dot(&self, name: &str) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "\\\""));
+        let mut dot = String::new();
+        writeln!(dot, "digraph {} {{", quote(name)).unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        let mut seen = HashSet::new();
+        for edge in self.edges.iter() {
+            let (caller, callee) = (edge.caller.to_string(), edge.callee.to_string());
+            if !seen.insert((caller.clone(), callee.clone())) {
+                continue;
+            }
+            let style = match edge.kind {
+                CallKind::Dynamic => " [style=dashed]",
+                _ => "",
+            };
+            writeln!(dot, "    {} -> {}{style};", quote(&caller), quote(&callee)).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The methods a call can select, each with the receiver types that select it
+type Targets = Vec<(MethodId, Vec<String>)>;
+
+/// A virtual call whose targets depend on which receiver types are around
+#[derive(Clone)]
+struct VirtualSite {
+    caller: MethodId,
+    pc: u32,
+    class_name: String,
+    name: String,
+    descriptor: String,
+    kind: CallKind,
+}
+
+struct Builder<'a> {
+    classes: HashMap<String, &'a ClassFile>,
+    hierarchy: &'a ClassFileHierarchy,
+    analysis: Analysis,
+    graph: CallGraph,
+    seen_edges: HashSet<(MethodId, u32, MethodId)>,
+    /// for rapid type analysis, the virtual call sites found so far by the class they name
+    sites: HashMap<String, Vec<VirtualSite>>,
+    /// a class and its concrete subtypes
+    receivers: HashMap<String, Rc<Vec<String>>>,
+    /// what a call by class, name and descriptor can select, with the receivers selecting it
+    targets: HashMap<(String, String, String), Rc<Targets>>,
+    worklist: Vec<MethodId>,
+}
+
+impl Builder<'_> {
+    fn run(&mut self) -> Result<(), io::Error> {
+        while let Some(method) = self.worklist.pop() {
+            if self.graph.reachable.insert(method.clone()) {
+                self.scan(&method)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the calls, instantiations and class initializations of a method's code
+    fn scan(&mut self, method: &MethodId) -> Result<(), io::Error> {
+        let Some(class) = self.classes.get(method.class_name()).copied() else {
+            return Ok(());
+        };
+        let Some(code) = class
+            .find_method(method.name(), method.descriptor())
+            .map(|info| info.code(class.constant_pool()))
+            .transpose()?
+            .flatten()
+        else {
+            return Ok(());
+        };
+
+        let constant_pool = class.constant_pool();
+        for instruction in code.instructions() {
+            let (pc, instruction) = instruction?;
+            match instruction {
+                Instruction::Invokestatic(index) | Instruction::Invokespecial(index) => {
+                    let (class_name, name, descriptor) = constant_pool.member_ref(index)?;
+                    let kind = match instruction {
+                        Instruction::Invokestatic(_) => {
+                            self.initialize(&class_name);
+                            CallKind::Static
+                        }
+                        _ => CallKind::Special,
+                    };
+                    let callee = self.resolve(&class_name, &name, &descriptor);
+                    self.add_edge(method, pc, callee, kind);
+                }
+                Instruction::Invokevirtual(index) | Instruction::Invokeinterface { index, .. } => {
+                    let (class_name, name, descriptor) = constant_pool.member_ref(index)?;
+                    let kind = match instruction {
+                        Instruction::Invokevirtual(_) => CallKind::Virtual,
+                        _ => CallKind::Interface,
+                    };
+                    self.add_site(VirtualSite {
+                        caller: method.clone(),
+                        pc,
+                        class_name,
+                        name,
+                        descriptor,
+                        kind,
+                    });
+                }
+                Instruction::Invokedynamic(index) => {
+                    self.lambda(class, method, pc, index)?;
+                }
+                Instruction::New(index) => {
+                    self.instantiate(&constant_pool.class_name(index)?);
+                }
+                Instruction::Getstatic(index) | Instruction::Putstatic(index) => {
+                    self.initialize(&constant_pool.member_ref(index)?.0);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Lambdas and method references made by `LambdaMetafactory` call their implementation
+    /// method, the second bootstrap argument. Other call sites are left alone.
+    fn lambda(
+        &mut self,
+        class: &ClassFile,
+        method: &MethodId,
+        pc: u32,
+        index: u16,
+    ) -> Result<(), io::Error> {
+        let constant_pool = class.constant_pool();
+        let Some(ConstantPoolData::InvokeDynamic(indy)) =
+            constant_pool.get(index).map(|i| i.data())
+        else {
+            return Ok(());
+        };
+        let Some(attribute) = class.find_attribute(BootstrapMethods::NAME) else {
+            return Ok(());
+        };
+        let bootstrap_methods = BootstrapMethods::from(&mut attribute.reader())?;
+        let Some(bootstrap) = bootstrap_methods
+            .bootstrap_methods()
+            .get(indy.bootstrap_method_attr_index() as usize)
+        else {
+            return Ok(());
+        };
+        let Some((_, bootstrap_reference)) =
+            method_handle(constant_pool, bootstrap.bootstrap_method_ref())
+        else {
+            return Ok(());
+        };
+        if constant_pool.member_ref(bootstrap_reference)?.0 != LAMBDA_METAFACTORY {
+            return Ok(());
+        }
+        let implementation = bootstrap
+            .bootstrap_arguments()
+            .get(1)
+            .and_then(|argument| method_handle(constant_pool, *argument));
+        let Some((kind, reference)) = implementation else {
+            return Ok(());
+        };
+
+        let (class_name, name, descriptor) = constant_pool.member_ref(reference)?;
+        match kind {
+            ReferenceKind::InvokeVirtual | ReferenceKind::InvokeInterface => {
+                self.add_site(VirtualSite {
+                    caller: method.clone(),
+                    pc,
+                    class_name,
+                    name,
+                    descriptor,
+                    kind: CallKind::Dynamic,
+                });
+            }
+            _ => {
+                if kind == ReferenceKind::NewInvokeSpecial {
+                    self.instantiate(&class_name);
+                } else if kind == ReferenceKind::InvokeStatic {
+                    self.initialize(&class_name);
+                }
+                let callee = self.resolve(&class_name, &name, &descriptor);
+                self.add_edge(method, pc, callee, CallKind::Dynamic);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_edge(&mut self, caller: &MethodId, pc: u32, callee: MethodId, kind: CallKind) {
+        if self.seen_edges.insert((caller.clone(), pc, callee.clone())) {
+            self.worklist.push(callee.clone());
+            self.graph.edges.push(CallEdge {
+                caller: caller.clone(),
+                pc,
+                callee,
+                kind,
+            });
+        }
+    }
+
+    fn add_site(&mut self, site: VirtualSite) {
+        self.dispatch(&site);
+        if self.analysis == Analysis::RapidType {
+            let sites = self.sites.entry(site.class_name.clone()).or_default();
+            sites.push(site);
+        }
+    }
+
+    /// Adds an edge to what the call selects (section 5.4.6) for every possible receiver type
+    fn dispatch(&mut self, site: &VirtualSite) {
+        if site.class_name.starts_with('[') {
+            let callee = self.resolve(OBJECT, &site.name, &site.descriptor);
+            self.add_edge(&site.caller, site.pc, callee, site.kind);
+            return;
+        }
+        let targets = self.targets(&site.class_name, &site.name, &site.descriptor);
+        for (callee, receivers) in targets.iter() {
+            let instantiated = |receiver: &String| self.graph.instantiated.contains(receiver);
+            if self.analysis == Analysis::RapidType && !receivers.iter().any(instantiated) {
+                continue;
+            }
+            self.add_edge(&site.caller, site.pc, callee.clone(), site.kind);
+        }
+    }
+
+    fn targets(&mut self, class_name: &str, name: &str, descriptor: &str) -> Rc<Targets> {
+        let key = (
+            class_name.to_string(),
+            name.to_string(),
+            descriptor.to_string(),
+        );
+        if let Some(targets) = self.targets.get(&key) {
+            return targets.clone();
+        }
+        let mut targets: BTreeMap<MethodId, Vec<String>> = BTreeMap::new();
+        for receiver in self.receivers(class_name).iter() {
+            if let Some(callee) = self.select(receiver, name, descriptor) {
+                targets.entry(callee).or_default().push(receiver.clone());
+            }
+        }
+        let targets = Rc::new(targets.into_iter().collect::<Vec<_>>());
+        self.targets.insert(key, targets.clone());
+        targets
+    }
+
+    fn receivers(&mut self, class_name: &str) -> Rc<Vec<String>> {
+        if let Some(receivers) = self.receivers.get(class_name) {
+            return receivers.clone();
+        }
+        let receivers: Vec<String> = [class_name.to_string()]
+            .into_iter()
+            .chain(self.hierarchy.all_subtypes(class_name))
+            .filter(|class| self.is_concrete(class))
+            .collect();
+        let receivers = Rc::new(receivers);
+        self.receivers
+            .insert(class_name.to_string(), receivers.clone());
+        receivers
+    }
+
+    fn is_concrete(&self, class_name: &str) -> bool {
+        match self.classes.get(class_name) {
+            Some(class) => class.access_flags() & (ACC_ABSTRACT | ACC_INTERFACE) == 0,
+            None => !self.hierarchy.is_interface(class_name),
+        }
+    }
+
+    fn declares(&self, class_name: &str, name: &str, descriptor: &str) -> Option<u16> {
+        self.hierarchy
+            .member_access_flags(class_name, name, descriptor)
+    }
+
+    /// The instance method a receiver of this class runs: the nearest declaration in its
+    /// superclasses, or else a default method (section 5.4.6). A superclass the hierarchy
+    /// doesn't know is assumed to declare it when no default method does, so calls into
+    /// unknown libraries still show up.
+    fn select(&self, class_name: &str, name: &str, descriptor: &str) -> Option<MethodId> {
+        let mut unknown = None;
+        for class in self.hierarchy.super_classes(class_name) {
+            if !self.hierarchy.contains(&class) {
+                unknown = Some(class);
+                break;
+            }
+            match self.declares(&class, name, descriptor) {
+                Some(flags) if flags & ACC_STATIC != 0 => {}
+                Some(flags) if flags & ACC_ABSTRACT != 0 => break,
+                Some(_) => return Some(MethodId::new(&class, name, descriptor)),
+                None => {}
+            }
+        }
+        self.hierarchy
+            .all_supertypes(class_name)
+            .into_iter()
+            .filter(|supertype| self.hierarchy.is_interface(supertype))
+            .find(|interface| {
+                self.declares(interface, name, descriptor)
+                    .is_some_and(|flags| flags & (ACC_ABSTRACT | ACC_STATIC) == 0)
+            })
+            .or(unknown)
+            .map(|class| MethodId::new(&class, name, descriptor))
+    }
+
+    /// The method a static or special call names, found in the class, its superclasses or its
+    /// superinterfaces (section 5.4.3.3)
+    fn resolve(&self, class_name: &str, name: &str, descriptor: &str) -> MethodId {
+        let superclasses = self.hierarchy.super_classes(class_name);
+        let interfaces = self.hierarchy.all_supertypes(class_name);
+        superclasses
+            .into_iter()
+            .chain(interfaces)
+            .find(|class| self.declares(class, name, descriptor).is_some())
+            .map(|class| MethodId::new(&class, name, descriptor))
+            .unwrap_or_else(|| MethodId::new(class_name, name, descriptor))
+    }
+
+    fn instantiate(&mut self, class_name: &str) {
+        if !self.graph.instantiated.insert(class_name.to_string()) {
+            return;
+        }
+        self.initialize(class_name);
+        if self.analysis != Analysis::RapidType {
+            return;
+        }
+        // the new class is a receiver for the sites already seen that name one of its supertypes
+        let mut supertypes = self.hierarchy.all_supertypes(class_name);
+        supertypes.push(class_name.to_string());
+        for supertype in supertypes {
+            let Some(sites) = self.sites.get(&supertype).cloned() else {
+                continue;
+            };
+            for site in sites {
+                if let Some(callee) = self.select(class_name, &site.name, &site.descriptor) {
+                    self.add_edge(&site.caller, site.pc, callee, site.kind);
+                }
+            }
+        }
+    }
+
+    /// Initializing a class runs its static initializer and its superclasses' (section 5.5)
+    fn initialize(&mut self, class_name: &str) {
+        for class in self.hierarchy.super_classes(class_name) {
+            let initializer = MethodId::new(&class, "<clinit>", "()V");
+            if self.graph.methods.contains(&initializer) {
+                self.worklist.push(initializer);
+            }
+        }
+    }
+}
+
+/// The kind and member reference of a MethodHandle constant
+fn method_handle(constant_pool: &ConstantPool, index: u16) -> Option<(ReferenceKind, u16)> {
+    match constant_pool.get(index).map(|item| item.data()) {
+        Some(ConstantPoolData::MethodHandle(handle)) => {
+            Some((handle.reference_kind(), handle.reference_index()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    const PROGRAM: [&str; 5] = [
+        "
+.class public app/Main
+.super java/lang/Object
+.method public static main([Ljava/lang/String;)V
+    .limit stack 2
+    new app/Circle
+    dup
+    invokespecial app/Circle/<init>()V
+    invokestatic app/Main/draw(Lapp/Shape;)V
+    invokedynamic 0 run()Ljava/lang/Runnable;
+    invokeinterface java/lang/Runnable/run()V 1
+    return
+.end method
+.method static draw(Lapp/Shape;)V
+    .limit stack 1
+    aload_0
+    invokeinterface app/Shape/area()D 1
+    pop2
+    return
+.end method
+.method private static lambda$main$0()V
+    return
+.end method
+.method static unused()V
+    return
+.end method
+.bootstrap invokestatic java/lang/invoke/LambdaMetafactory/metafactory(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite; methodtype ()V methodhandle invokestatic app/Main/lambda$main$0()V methodtype ()V
+",
+        "
+.interface public abstract app/Shape
+.super java/lang/Object
+.method public abstract area()D
+.end method
+",
+        "
+.class public app/Circle
+.super java/lang/Object
+.implements app/Shape
+.method public <init>()V
+    .limit stack 1
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method public area()D
+    .limit stack 2
+    dconst_1
+    dreturn
+.end method
+",
+        "
+.class public app/Square
+.super java/lang/Object
+.implements app/Shape
+.method public <init>()V
+    .limit stack 1
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method public area()D
+    .limit stack 2
+    dconst_0
+    dreturn
+.end method
+",
+        "
+.class public app/Cube
+.super app/Square
+.method static <clinit>()V
+    return
+.end method
+",
+    ];
+
+    fn build(analysis: Analysis) -> CallGraph {
+        let classes: Vec<ClassFile> = PROGRAM.iter().map(|s| assemble(s).unwrap()).collect();
+        let mut hierarchy = ClassFileHierarchy::new();
+        for class in classes.iter() {
+            hierarchy.add_class_file(class).unwrap();
+        }
+        let main = MethodId::parse("app.Main.main([Ljava/lang/String;)V").unwrap();
+        CallGraph::build(&classes, &hierarchy, &[main], analysis).unwrap()
+    }
+
+    fn names(methods: impl IntoIterator<Item = impl ToString>) -> Vec<String> {
+        methods.into_iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn class_hierarchy_analysis() {
+        let graph = build(Analysis::ClassHierarchy);
+        let draw = MethodId::new("app/Main", "draw", "(Lapp/Shape;)V");
+        assert_eq!(
+            names(graph.callees(&draw).iter().map(|edge| edge.callee())),
+            ["app/Circle.area()D", "app/Square.area()D"]
+        );
+        assert!(graph
+            .callees(&draw)
+            .iter()
+            .all(|edge| edge.kind() == CallKind::Interface && edge.pc() == 1));
+
+        let main = MethodId::new("app/Main", "main", "([Ljava/lang/String;)V");
+        let lambda = &graph.callees(&main)[2];
+        assert_eq!(lambda.callee().to_string(), "app/Main.lambda$main$0()V");
+        assert_eq!(lambda.kind(), CallKind::Dynamic);
+        // library calls are leaves
+        assert!(graph.is_reachable(&MethodId::new("java/lang/Runnable", "run", "()V")));
+        assert!(graph.is_reachable(&MethodId::new("java/lang/Object", "<init>", "()V")));
+
+        assert_eq!(
+            names(graph.unreachable()),
+            [
+                "app/Cube.<clinit>()V",
+                "app/Main.unused()V",
+                "app/Square.<init>()V"
+            ]
+        );
+    }
+
+    #[test]
+    fn rapid_type_analysis() {
+        let graph = build(Analysis::RapidType);
+        let draw = MethodId::new("app/Main", "draw", "(Lapp/Shape;)V");
+        assert_eq!(
+            names(graph.callees(&draw).iter().map(|edge| edge.callee())),
+            ["app/Circle.area()D"]
+        );
+        assert_eq!(
+            graph.instantiated().iter().collect::<Vec<_>>(),
+            ["app/Circle"]
+        );
+        assert!(graph
+            .unreachable()
+            .contains(&&MethodId::new("app/Square", "area", "()D")));
+
+        let dot = graph.to_dot("calls");
+        assert!(dot.contains("    \"app/Main.draw(Lapp/Shape;)V\" -> \"app/Circle.area()D\";\n"));
+        assert!(dot.contains(
+            "    \"app/Main.main([Ljava/lang/String;)V\" -> \"app/Main.lambda$main$0()V\" [style=dashed];\n"
+        ));
+    }
+
+    #[test]
+    fn default_methods_through_library_superclasses() {
+        let classes: Vec<ClassFile> = [
+            "
+.class public app/Main
+.super java/lang/Object
+.method public static main([Ljava/lang/String;)V
+    .limit stack 2
+    new app/Polite
+    dup
+    invokespecial app/Polite/<init>()V
+    invokevirtual app/Polite/greet()V
+    return
+.end method
+",
+            "
+.interface public abstract app/Greeter
+.super java/lang/Object
+.method public greet()V
+    return
+.end method
+",
+            "
+.class public app/Polite
+.super java/lang/Object
+.implements app/Greeter
+.method public <init>()V
+    .limit stack 1
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+",
+        ]
+        .iter()
+        .map(|s| assemble(s).unwrap())
+        .collect();
+        let mut hierarchy = ClassFileHierarchy::new();
+        for class in classes.iter() {
+            hierarchy.add_class_file(class).unwrap();
+        }
+        let roots = [MethodId::parse("app.Main.main([Ljava/lang/String;)V").unwrap()];
+        let main = &roots[0];
+        for analysis in [Analysis::ClassHierarchy, Analysis::RapidType] {
+            let graph = CallGraph::build(&classes, &hierarchy, &roots, analysis).unwrap();
+            // java/lang/Object isn't in the hierarchy, but the default method still wins
+            assert_eq!(
+                names(graph.callees(main).iter().map(|edge| edge.callee())),
+                ["app/Polite.<init>()V", "app/Greeter.greet()V"]
+            );
+        }
+    }
+
+    #[test]
+    fn parses_method_ids() {
+        let id = MethodId::parse("p/Main.main([Ljava/lang/String;)V").unwrap();
+        assert_eq!(id.class_name(), "p/Main");
+        assert_eq!(id.name(), "main");
+        assert_eq!(id.descriptor(), "([Ljava/lang/String;)V");
+        assert!(MethodId::parse("main()V").is_err());
+        assert!(MethodId::parse("p.Main.main").is_err());
+    }
+}
@@ -0,0 +1,755 @@
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::attribute::code::Code;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::io;
+
+// The control-flow graph of a method body. Blocks are numbered in pc order and are followed
+// by two virtual nodes: an entry node with a single edge to the block at pc 0, and an exit
+// node that every return and athrow leads to. Any block inside an exception handler's range
+// is assumed to be able to throw, as the verifier does (section 4.10.1.6).
+
+/// A block, or the entry or exit node
+pub type NodeId = usize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EdgeKind {
+    /// Into the next block without a jump, and from the entry node
+    FallThrough,
+    /// A goto, conditional branch, switch case, jsr, or a ret back to a jsr's return site
+    Branch,
+    /// To an exception handler; the catch type is 0 for handlers that catch everything
+    Exception(u16),
+    /// A return or athrow, to the exit node
+    Exit,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    from: NodeId,
+    to: NodeId,
+    kind: EdgeKind,
+}
+
+impl Edge {
+    pub fn from(&self) -> NodeId {
+        self.from
+    }
+
+    pub fn to(&self) -> NodeId {
+        self.to
+    }
+
+    pub fn kind(&self) -> EdgeKind {
+        self.kind
+    }
+}
+
+/// A straight-line run of instructions that is only entered at the top
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+    start: u32,
+    end: u32,
+    instructions: Vec<(u32, Instruction)>,
+}
+
+impl BasicBlock {
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// The pc just after the last instruction
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    pub fn instructions(&self) -> &[(u32, Instruction)] {
+        &self.instructions
+    }
+
+    pub fn last(&self) -> &(u32, Instruction) {
+        // blocks are never empty
+        &self.instructions[self.instructions.len() - 1]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph {
+    blocks: Vec<BasicBlock>,
+    successors: Vec<Vec<Edge>>,
+    predecessors: Vec<Vec<Edge>>,
+}
+
+impl ControlFlowGraph {
+    pub fn from_code(code: &Code) -> Result<ControlFlowGraph, io::Error> {
+        let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+        let length = code.code().len() as u32;
+        if instructions.is_empty() {
+            return Err(cfg_error("code is empty"));
+        }
+        let check = |pc: u32, end_allowed: bool, what: &str| {
+            if (end_allowed && pc == length)
+                || instructions.binary_search_by_key(&pc, |i| i.0).is_ok()
+            {
+                Ok(pc)
+            } else {
+                Err(cfg_error(&format!(
+                    "{what} {pc} is not the start of an instruction"
+                )))
+            }
+        };
+
+        // a block starts at pc 0, at every jump target and handler, after every instruction
+        // that doesn't simply fall through, and wherever a handler's range starts or ends
+        let mut leaders = BTreeSet::from([0]);
+        for (i, (_, instruction)) in instructions.iter().enumerate() {
+            for target in instruction.branch_targets() {
+                leaders.insert(check(target, false, "branch target")?);
+            }
+            if ends_block(instruction) {
+                if let Some((next, _)) = instructions.get(i + 1) {
+                    leaders.insert(*next);
+                }
+            }
+        }
+        for entry in code.exception_table() {
+            leaders.insert(check(
+                entry.start_pc() as u32,
+                false,
+                "handler range start",
+            )?);
+            leaders.insert(check(entry.end_pc() as u32, true, "handler range end")?);
+            leaders.insert(check(entry.handler_pc() as u32, false, "handler")?);
+        }
+        leaders.remove(&length);
+
+        let mut blocks: Vec<BasicBlock> = Vec::with_capacity(leaders.len());
+        for (pc, instruction) in instructions {
+            if leaders.contains(&pc) {
+                blocks.push(BasicBlock {
+                    start: pc,
+                    end: pc,
+                    instructions: Vec::new(),
+                });
+            }
+            // pc 0 is always a leader, so there is a block to add to
+            let block = blocks.last_mut().unwrap();
+            block.instructions.push((pc, instruction));
+        }
+        let ends: Vec<u32> = blocks.iter().skip(1).map(|b| b.start).collect();
+        for (block, end) in blocks.iter_mut().zip(ends.into_iter().chain([length])) {
+            block.end = end;
+        }
+
+        let node_count = blocks.len() + 2;
+        let mut cfg = ControlFlowGraph {
+            blocks,
+            successors: vec![Vec::new(); node_count],
+            predecessors: vec![Vec::new(); node_count],
+        };
+        let block_at = |cfg: &ControlFlowGraph, pc: u32| cfg.block_at(pc).unwrap();
+        let return_sites: Vec<NodeId> = (1..cfg.blocks.len())
+            .filter(|b| {
+                matches!(
+                    cfg.blocks[b - 1].last().1,
+                    Instruction::Jsr(_) | Instruction::JsrW(_)
+                )
+            })
+            .collect();
+
+        cfg.add_edge(cfg.entry(), 0, EdgeKind::FallThrough);
+        for b in 0..cfg.blocks.len() {
+            let (_, last) = cfg.blocks[b].last().clone();
+            let next = Some(b + 1).filter(|next| *next < cfg.blocks.len());
+            match last {
+                Instruction::Ireturn
+                | Instruction::Lreturn
+                | Instruction::Freturn
+                | Instruction::Dreturn
+                | Instruction::Areturn
+                | Instruction::Return
+                | Instruction::Athrow => cfg.add_edge(b, cfg.exit(), EdgeKind::Exit),
+                Instruction::Ret(_) => {
+                    for site in return_sites.iter() {
+                        cfg.add_edge(b, *site, EdgeKind::Branch);
+                    }
+                }
+                _ => {
+                    for target in last.branch_targets() {
+                        let to = block_at(&cfg, target);
+                        cfg.add_edge(b, to, EdgeKind::Branch);
+                    }
+                    if !ends_block(&last) || is_conditional(&last) {
+                        if let Some(next) = next {
+                            cfg.add_edge(b, next, EdgeKind::FallThrough);
+                        }
+                    }
+                }
+            }
+            let start = cfg.blocks[b].start;
+            for entry in code.exception_table() {
+                if (entry.start_pc() as u32..entry.end_pc() as u32).contains(&start) {
+                    let handler = block_at(&cfg, entry.handler_pc() as u32);
+                    cfg.add_edge(b, handler, EdgeKind::Exception(entry.catch_type()));
+                }
+            }
+        }
+        Ok(cfg)
+    }
+
+    fn add_edge(&mut self, from: NodeId, to: NodeId, kind: EdgeKind) {
+        let edge = Edge { from, to, kind };
+        // switches can list the same target more than once
+        if !self.successors[from].contains(&edge) {
+            self.successors[from].push(edge);
+            self.predecessors[to].push(edge);
+        }
+    }
+
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    pub fn block(&self, node: NodeId) -> Option<&BasicBlock> {
+        self.blocks.get(node)
+    }
+
+    pub fn entry(&self) -> NodeId {
+        self.blocks.len()
+    }
+
+    pub fn exit(&self) -> NodeId {
+        self.blocks.len() + 1
+    }
+
+    /// The blocks plus the entry and exit nodes
+    pub fn node_count(&self) -> usize {
+        self.blocks.len() + 2
+    }
+
+    pub fn successors(&self, node: NodeId) -> &[Edge] {
+        &self.successors[node]
+    }
+
+    pub fn predecessors(&self, node: NodeId) -> &[Edge] {
+        &self.predecessors[node]
+    }
+
+    /// The block containing the instruction at `pc`
+    pub fn block_at(&self, pc: u32) -> Option<NodeId> {
+        let index = self.blocks.partition_point(|block| block.start <= pc);
+        index
+            .checked_sub(1)
+            .filter(|index| pc < self.blocks[*index].end)
+    }
+
+    /// Blocks that can't be reached from the entry, even through exception handlers
+    pub fn unreachable_blocks(&self) -> Vec<NodeId> {
+        let reachable = depth_first(self.entry(), self.node_count(), |node| {
+            self.successors[node].iter().map(|edge| edge.to).collect()
+        });
+        (0..self.blocks.len())
+            .filter(|block| !reachable.contains(block))
+            .collect()
+    }
+
+    pub fn dominators(&self) -> DominatorTree {
+        DominatorTree::new(
+            self.entry(),
+            self.node_count(),
+            |node| self.successors[node].iter().map(|edge| edge.to).collect(),
+            |node| {
+                self.predecessors[node]
+                    .iter()
+                    .map(|edge| edge.from)
+                    .collect()
+            },
+        )
+    }
+
+    /// Dominators of the reversed graph, rooted at the exit. Blocks that never reach the exit
+    /// (such as infinite loops) have no post-dominator.
+    pub fn post_dominators(&self) -> DominatorTree {
+        DominatorTree::new(
+            self.exit(),
+            self.node_count(),
+            |node| {
+                self.predecessors[node]
+                    .iter()
+                    .map(|edge| edge.from)
+                    .collect()
+            },
+            |node| self.successors[node].iter().map(|edge| edge.to).collect(),
+        )
+    }
+
+    /// Natural loops, one per header, ordered by header. A back edge is an edge whose target
+    /// dominates its source; irreducible cycles have none and aren't reported.
+    pub fn loops(&self) -> Vec<Loop> {
+        let dominators = self.dominators();
+        let mut loops: Vec<Loop> = Vec::new();
+        for block in 0..self.blocks.len() {
+            let latches: Vec<NodeId> = self.predecessors[block]
+                .iter()
+                .map(|edge| edge.from)
+                .filter(|from| dominators.dominates(block, *from))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+
+            // everything that reaches a latch without going through the header
+            let mut body = BTreeSet::from([block]);
+            let mut work = latches.clone();
+            while let Some(node) = work.pop() {
+                if body.insert(node) {
+                    work.extend(self.predecessors[node].iter().map(|edge| edge.from));
+                }
+            }
+            loops.push(Loop {
+                header: block,
+                latches,
+                blocks: body.into_iter().collect(),
+                parent: None,
+            });
+        }
+
+        // the innermost enclosing loop is the smallest other loop containing the header
+        for i in 0..loops.len() {
+            loops[i].parent = (0..loops.len())
+                .filter(|j| *j != i && loops[*j].contains(loops[i].header))
+                .min_by_key(|j| loops[*j].blocks.len());
+        }
+        loops
+    }
+
+    /// Renders the graph in Graphviz DOT format
+    pub fn to_dot(&self, name: &str) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph \"{}\" {{", name.replace('"', "\\\"")).unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        writeln!(dot, "    entry [shape=oval];").unwrap();
+        writeln!(dot, "    exit [shape=oval];").unwrap();
+        for (b, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("B{b}\\l");
+            for (pc, instruction) in block.instructions.iter() {
+                write!(label, "{pc}: {}\\l", describe(instruction)).unwrap();
+            }
+            writeln!(dot, "    b{b} [label=\"{label}\"];").unwrap();
+        }
+        let node_name = |node: NodeId| match node {
+            node if node == self.entry() => "entry".to_string(),
+            node if node == self.exit() => "exit".to_string(),
+            node => format!("b{node}"),
+        };
+        for node in [self.entry()].into_iter().chain(0..self.blocks.len()) {
+            for edge in self.successors[node].iter() {
+                let style = match edge.kind {
+                    EdgeKind::Exception(0) => " [style=dashed, label=\"any\"]".to_string(),
+                    EdgeKind::Exception(catch_type) => {
+                        format!(" [style=dashed, label=\"#{catch_type}\"]")
+                    }
+                    _ => String::new(),
+                };
+                writeln!(
+                    dot,
+                    "    {} -> {}{style};",
+                    node_name(edge.from),
+                    node_name(edge.to)
+                )
+                .unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn cfg_error(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Cannot build control-flow graph: {message}"),
+    )
+}
+
+/// Whether control can leave the instruction other than by falling through to the next one
+pub(crate) fn ends_block(instruction: &Instruction) -> bool {
+    !instruction.branch_targets().is_empty()
+        || matches!(
+            instruction,
+            Instruction::Ireturn
+                | Instruction::Lreturn
+                | Instruction::Freturn
+                | Instruction::Dreturn
+                | Instruction::Areturn
+                | Instruction::Return
+                | Instruction::Athrow
+                | Instruction::Ret(_)
+        )
+}
+
+/// Branches that fall through when not taken
+pub(crate) fn is_conditional(instruction: &Instruction) -> bool {
+    instruction.branch_targets().len() == 1
+        && !matches!(
+            instruction,
+            Instruction::Goto(_)
+                | Instruction::GotoW(_)
+                | Instruction::Jsr(_)
+                | Instruction::JsrW(_)
+        )
+}
+
+/// A short, constant-pool-free rendering for DOT labels
+fn describe(instruction: &Instruction) -> String {
+    let mut text = instruction.mnemonic().to_string();
+    if let Some(index) = instruction.constant_index() {
+        write!(text, " #{index}").unwrap();
+    } else if let Some(index) = instruction.local_index() {
+        write!(text, " {index}").unwrap();
+    }
+    let targets = instruction.branch_targets();
+    if !targets.is_empty() {
+        let targets: Vec<String> = targets.iter().map(u32::to_string).collect();
+        write!(text, " {}", targets.join(", ")).unwrap();
+    }
+    text
+}
+
+/// Nodes reachable from `root`, in postorder
+fn depth_first(
+    root: NodeId,
+    node_count: usize,
+    successors: impl Fn(NodeId) -> Vec<NodeId>,
+) -> Vec<NodeId> {
+    let mut visited = vec![false; node_count];
+    let mut order = Vec::with_capacity(node_count);
+    // each frame is a node and the successors still to visit
+    let mut stack = vec![(root, successors(root))];
+    visited[root] = true;
+    while let Some((node, pending)) = stack.last_mut() {
+        match pending.pop() {
+            Some(next) if !visited[next] => {
+                visited[next] = true;
+                stack.push((next, successors(next)));
+            }
+            Some(_) => {}
+            None => {
+                order.push(*node);
+                stack.pop();
+            }
+        }
+    }
+    order
+}
+
+/// Immediate dominators, computed with the iterative algorithm from Cooper, Harvey and
+/// Kennedy's "A Simple, Fast Dominance Algorithm"
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DominatorTree {
+    root: NodeId,
+    /// The root is its own immediate dominator here; unreachable nodes have none
+    idom: Vec<Option<NodeId>>,
+}
+
+impl DominatorTree {
+    pub(crate) fn new(
+        root: NodeId,
+        node_count: usize,
+        successors: impl Fn(NodeId) -> Vec<NodeId>,
+        predecessors: impl Fn(NodeId) -> Vec<NodeId>,
+    ) -> DominatorTree {
+        let postorder = depth_first(root, node_count, successors);
+        let mut rank = vec![usize::MAX; node_count];
+        for (i, node) in postorder.iter().enumerate() {
+            rank[*node] = i;
+        }
+
+        let mut idom = vec![None; node_count];
+        idom[root] = Some(root);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in postorder.iter().rev().filter(|node| **node != root) {
+                let mut new_idom = None;
+                for predecessor in predecessors(*node) {
+                    if idom[predecessor].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(other) => {
+                            // walk both up the tree until they meet; the root has the highest rank
+                            let (mut a, mut b) = (predecessor, other);
+                            while a != b {
+                                while rank[a] < rank[b] {
+                                    a = idom[a].unwrap();
+                                }
+                                while rank[b] < rank[a] {
+                                    b = idom[b].unwrap();
+                                }
+                            }
+                            a
+                        }
+                    });
+                }
+                if idom[*node] != new_idom {
+                    idom[*node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        DominatorTree { root, idom }
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// None for the root and for nodes the root can't reach
+    pub fn immediate_dominator(&self, node: NodeId) -> Option<NodeId> {
+        self.idom[node].filter(|_| node != self.root)
+    }
+
+    pub fn is_reachable(&self, node: NodeId) -> bool {
+        self.idom[node].is_some()
+    }
+
+    /// Whether every path from the root to `node` goes through `dominator`. Every node
+    /// dominates itself.
+    pub fn dominates(&self, dominator: NodeId, node: NodeId) -> bool {
+        if !self.is_reachable(node) {
+            return false;
+        }
+        let mut current = node;
+        loop {
+            if current == dominator {
+                return true;
+            }
+            match self.immediate_dominator(current) {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// The nodes immediately dominated by `node`
+    pub fn children(&self, node: NodeId) -> Vec<NodeId> {
+        (0..self.idom.len())
+            .filter(|child| self.immediate_dominator(*child) == Some(node))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loop {
+    header: NodeId,
+    latches: Vec<NodeId>,
+    blocks: Vec<NodeId>,
+    parent: Option<usize>,
+}
+
+impl Loop {
+    pub fn header(&self) -> NodeId {
+        self.header
+    }
+
+    /// The blocks with a back edge to the header
+    pub fn latches(&self) -> &[NodeId] {
+        &self.latches
+    }
+
+    /// Every block in the loop, including the header, in order
+    pub fn blocks(&self) -> &[NodeId] {
+        &self.blocks
+    }
+
+    pub fn contains(&self, node: NodeId) -> bool {
+        self.blocks.binary_search(&node).is_ok()
+    }
+
+    /// The index of the innermost enclosing loop in the list returned by
+    /// `ControlFlowGraph::loops`
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    fn cfg(body: &str) -> ControlFlowGraph {
+        let source = format!(".class T\n.method static f(I)I\n{body}\n.end method\n");
+        let class_file = assemble(&source).unwrap();
+        let code = class_file
+            .find_method("f", "(I)I")
+            .unwrap()
+            .code(class_file.constant_pool())
+            .unwrap()
+            .unwrap();
+        ControlFlowGraph::from_code(&code).unwrap()
+    }
+
+    fn targets(cfg: &ControlFlowGraph, node: NodeId) -> Vec<(NodeId, EdgeKind)> {
+        cfg.successors(node)
+            .iter()
+            .map(|edge| (edge.to(), edge.kind()))
+            .collect()
+    }
+
+    const DIAMOND: &str = "
+    iload_0
+    ifeq Else
+    iconst_1
+    istore_0
+    goto End
+Else:
+    iconst_2
+    istore_0
+End:
+    iload_0
+    ireturn";
+
+    #[test]
+    fn straight_line_code_is_one_block() {
+        let cfg = cfg("iload_0\n iconst_1\n iadd\n ireturn");
+        assert_eq!(cfg.blocks().len(), 1);
+        assert_eq!(cfg.blocks()[0].start(), 0);
+        assert_eq!(cfg.blocks()[0].end(), 4);
+        assert_eq!(targets(&cfg, cfg.entry()), [(0, EdgeKind::FallThrough)]);
+        assert_eq!(targets(&cfg, 0), [(cfg.exit(), EdgeKind::Exit)]);
+    }
+
+    #[test]
+    fn branches_split_blocks() {
+        let cfg = cfg(DIAMOND);
+        let starts: Vec<u32> = cfg.blocks().iter().map(BasicBlock::start).collect();
+        assert_eq!(starts, [0, 4, 9, 11]);
+        assert_eq!(
+            targets(&cfg, 0),
+            [(2, EdgeKind::Branch), (1, EdgeKind::FallThrough)]
+        );
+        assert_eq!(targets(&cfg, 1), [(3, EdgeKind::Branch)]);
+        assert_eq!(targets(&cfg, 2), [(3, EdgeKind::FallThrough)]);
+        assert_eq!(cfg.predecessors(3).len(), 2);
+        assert_eq!(cfg.block_at(6), Some(1));
+        assert_eq!(cfg.block_at(13), None);
+    }
+
+    #[test]
+    fn dominators_and_post_dominators() {
+        let cfg = cfg(DIAMOND);
+        let dominators = cfg.dominators();
+        assert_eq!(dominators.root(), cfg.entry());
+        assert_eq!(dominators.immediate_dominator(0), Some(cfg.entry()));
+        assert_eq!(dominators.immediate_dominator(3), Some(0));
+        assert!(dominators.dominates(0, 2));
+        assert!(!dominators.dominates(1, 3));
+        assert_eq!(dominators.children(0), [1, 2, 3]);
+
+        let post_dominators = cfg.post_dominators();
+        assert_eq!(post_dominators.immediate_dominator(0), Some(3));
+        assert_eq!(post_dominators.immediate_dominator(1), Some(3));
+        assert!(post_dominators.dominates(cfg.exit(), cfg.entry()));
+    }
+
+    #[test]
+    fn nested_loops() {
+        let cfg = cfg("
+Outer:
+    iload_0
+    ifeq Done
+Inner:
+    iinc 0 -1
+    iload_0
+    iconst_5
+    if_icmpgt Inner
+    goto Outer
+Done:
+    iconst_0
+    ireturn");
+        let loops = cfg.loops();
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].header(), 0);
+        assert_eq!(loops[0].latches(), [2]);
+        assert_eq!(loops[0].blocks(), [0, 1, 2]);
+        assert_eq!(loops[0].parent(), None);
+        assert_eq!(loops[1].header(), 1);
+        assert_eq!(loops[1].latches(), [1]);
+        assert_eq!(loops[1].blocks(), [1]);
+        assert_eq!(loops[1].parent(), Some(0));
+    }
+
+    #[test]
+    fn exception_edges_and_unreachable_code() {
+        let cfg = cfg("
+    .catch java/lang/Exception from Start to End using Handler
+Start:
+    iload_0
+    iconst_1
+    idiv
+    ireturn
+End:
+    iconst_2
+    ireturn
+Handler:
+    pop
+    iconst_0
+    ireturn");
+        assert_eq!(cfg.blocks().len(), 3);
+        let handler = cfg.block_at(6).unwrap();
+        assert!(matches!(
+            targets(&cfg, 0)[..],
+            [(_, EdgeKind::Exit), (2, EdgeKind::Exception(catch_type))] if catch_type != 0
+        ));
+        assert_eq!(handler, 2);
+        assert_eq!(cfg.unreachable_blocks(), [1]);
+        assert!(cfg.dominators().dominates(0, handler));
+    }
+
+    #[test]
+    fn ret_returns_after_every_jsr() {
+        let cfg = cfg("
+    jsr Sub
+    jsr Sub
+    iload_0
+    ireturn
+Sub:
+    astore_1
+    ret 1");
+        assert_eq!(
+            targets(&cfg, 3),
+            [(1, EdgeKind::Branch), (2, EdgeKind::Branch)]
+        );
+        assert_eq!(targets(&cfg, 0), [(3, EdgeKind::Branch)]);
+    }
+
+    #[test]
+    fn dot_output() {
+        let dot = cfg(DIAMOND).to_dot("T.f(I)I");
+        assert!(dot.starts_with("digraph \"T.f(I)I\" {\n"));
+        assert!(dot.contains("    b0 [label=\"B0\\l0: iload_0\\l1: ifeq 9\\l\"];\n"));
+        assert!(dot.contains("    entry -> b0;\n"));
+        assert!(dot.contains("    b3 -> exit;\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn rejects_bad_handlers() {
+        let code = Code::new(
+            0,
+            0,
+            vec![0x10, 0x01, 0xb1],
+            vec![crate::class_file::attribute::code::ExceptionTableEntry::new(1, 3, 2, 0)],
+            vec![],
+        );
+        let error = ControlFlowGraph::from_code(&code).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("not the start of an instruction"));
+    }
+}
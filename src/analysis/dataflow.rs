@@ -0,0 +1,1467 @@
+use crate::analysis::cfg::{ControlFlowGraph, Edge, EdgeKind, NodeId};
+use crate::analysis::frames::local_access;
+use crate::analysis::verifier::is_store;
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::ACC_STATIC;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::descriptor::{FieldType, MethodDescriptor};
+use crate::class_file::member_info::MethodInfo;
+use std::collections::{BTreeSet, HashMap};
+use std::{fmt, io};
+
+// Monotone dataflow over the control-flow graph, solved with a worklist. A forward analysis
+// only reaches blocks that control can reach; a backward one visits every block, starting
+// from the boundary fact where no successor has been solved yet. Exception handlers see the
+// facts before every instruction in their range, since any of them may be the one to throw,
+// which is as coarse as the graph's exception edges (section 4.10.1.6).
+
+/// The facts an analysis computes, ordered so that joining only ever moves up
+pub trait Lattice: Clone {
+    /// Merges `other` into this fact, returning whether this fact changed
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+impl<T: Ord + Clone> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        let length = self.len();
+        self.extend(other.iter().cloned());
+        self.len() != length
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+pub trait Analysis {
+    type Fact: Lattice;
+
+    fn direction(&self) -> Direction;
+
+    /// The fact on entry to the method for a forward analysis, or on exit for a backward one
+    fn boundary(&self) -> Self::Fact;
+
+    /// Moves `fact` across one instruction: from before it to after it in a forward analysis,
+    /// and the other way in a backward one
+    fn transfer(
+        &self,
+        pc: u32,
+        instruction: &Instruction,
+        fact: &mut Self::Fact,
+    ) -> Result<(), io::Error>;
+
+    /// In a forward analysis, the fact along a branch or fall-through edge out of a block that
+    /// ends with `instruction`, given the facts before and after it, or `None` if control can
+    /// never take the edge
+    fn transfer_edge(
+        &self,
+        _edge: &Edge,
+        _instruction: &Instruction,
+        _before: &Self::Fact,
+        after: &Self::Fact,
+    ) -> Option<Self::Fact> {
+        Some(after.clone())
+    }
+
+    /// In a forward analysis, the fact on entry to a handler, given the facts before every
+    /// instruction that can throw to it joined together
+    fn exception(&self, _catch_type: u16, fact: &Self::Fact) -> Self::Fact {
+        fact.clone()
+    }
+}
+
+/// The fixed point of an analysis, at the ends of every node and around every instruction.
+/// Facts are always in program order: `before` an instruction is the fact a backward analysis
+/// computes from the ones after it.
+#[derive(Clone, Debug)]
+pub struct Solution<F> {
+    entry: Vec<Option<F>>,
+    exit: Vec<Option<F>>,
+    before: HashMap<u32, F>,
+    after: HashMap<u32, F>,
+}
+
+impl<F> Solution<F> {
+    /// The fact at the start of a block, or `None` where a forward analysis never got to
+    pub fn entry(&self, node: NodeId) -> Option<&F> {
+        self.entry.get(node).and_then(Option::as_ref)
+    }
+
+    /// The fact at the end of a block
+    pub fn exit(&self, node: NodeId) -> Option<&F> {
+        self.exit.get(node).and_then(Option::as_ref)
+    }
+
+    pub fn before(&self, pc: u32) -> Option<&F> {
+        self.before.get(&pc)
+    }
+
+    pub fn after(&self, pc: u32) -> Option<&F> {
+        self.after.get(&pc)
+    }
+}
+
+pub fn solve<A: Analysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+) -> Result<Solution<A::Fact>, io::Error> {
+    let mut solution = Solution {
+        entry: vec![None; cfg.node_count()],
+        exit: vec![None; cfg.node_count()],
+        before: HashMap::new(),
+        after: HashMap::new(),
+    };
+    match analysis.direction() {
+        Direction::Forward => solve_forward(cfg, analysis, &mut solution)?,
+        Direction::Backward => solve_backward(cfg, analysis, &mut solution)?,
+    }
+    Ok(solution)
+}
+
+fn join_into<F: Lattice>(slot: &mut Option<F>, fact: F) -> bool {
+    match slot {
+        Some(existing) => existing.join(&fact),
+        None => {
+            *slot = Some(fact);
+            true
+        }
+    }
+}
+
+fn solve_forward<A: Analysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+    solution: &mut Solution<A::Fact>,
+) -> Result<(), io::Error> {
+    let boundary = analysis.boundary();
+    solution.entry[cfg.entry()] = Some(boundary.clone());
+    solution.exit[cfg.entry()] = Some(boundary.clone());
+    // blocks are numbered in pc order, so taking the lowest first mostly sees predecessors first
+    let mut worklist = BTreeSet::new();
+    for edge in cfg.successors(cfg.entry()) {
+        join_into(&mut solution.entry[edge.to()], boundary.clone());
+        worklist.insert(edge.to());
+    }
+    while let Some(node) = worklist.pop_first() {
+        let (Some(block), Some(mut fact)) = (cfg.block(node), solution.entry[node].clone()) else {
+            continue;
+        };
+        let throws = cfg
+            .successors(node)
+            .iter()
+            .any(|edge| matches!(edge.kind(), EdgeKind::Exception(_)));
+        let mut thrown = None;
+        let mut before_last = None;
+        for (pc, instruction) in block.instructions() {
+            if throws {
+                join_into(&mut thrown, fact.clone());
+            }
+            solution.before.insert(*pc, fact.clone());
+            before_last = Some(fact.clone());
+            analysis.transfer(*pc, instruction, &mut fact)?;
+            solution.after.insert(*pc, fact.clone());
+        }
+        // blocks are never empty
+        let before_last = before_last.unwrap();
+        let (_, last) = block.last();
+        for edge in cfg.successors(node) {
+            let out = match edge.kind() {
+                EdgeKind::Exception(catch_type) => thrown
+                    .as_ref()
+                    .map(|thrown| analysis.exception(catch_type, thrown)),
+                EdgeKind::Exit => Some(fact.clone()),
+                _ => analysis.transfer_edge(edge, last, &before_last, &fact),
+            };
+            if let Some(out) = out {
+                if join_into(&mut solution.entry[edge.to()], out) {
+                    worklist.insert(edge.to());
+                }
+            }
+        }
+        solution.exit[node] = Some(fact);
+    }
+    solution.exit[cfg.exit()] = solution.entry[cfg.exit()].clone();
+    Ok(())
+}
+
+fn solve_backward<A: Analysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+    solution: &mut Solution<A::Fact>,
+) -> Result<(), io::Error> {
+    let boundary = analysis.boundary();
+    solution.entry[cfg.exit()] = Some(boundary.clone());
+    solution.exit[cfg.exit()] = Some(boundary.clone());
+    let mut worklist: BTreeSet<NodeId> = (0..cfg.blocks().len()).collect();
+    while let Some(node) = worklist.pop_last() {
+        let Some(block) = cfg.block(node) else {
+            continue;
+        };
+        let mut normal = None;
+        let mut exceptional = None;
+        for edge in cfg.successors(node) {
+            if let Some(successor) = solution.entry[edge.to()].clone() {
+                match edge.kind() {
+                    EdgeKind::Exception(_) => join_into(&mut exceptional, successor),
+                    _ => join_into(&mut normal, successor),
+                };
+            }
+        }
+        let mut fact = normal.unwrap_or_else(|| boundary.clone());
+        solution.exit[node] = Some(fact.clone());
+        for (pc, instruction) in block.instructions().iter().rev() {
+            solution.after.insert(*pc, fact.clone());
+            analysis.transfer(*pc, instruction, &mut fact)?;
+            if let Some(exceptional) = &exceptional {
+                fact.join(exceptional);
+            }
+            solution.before.insert(*pc, fact.clone());
+        }
+        // the transfer functions are monotone, so the new fact is never below the old one
+        if join_into(&mut solution.entry[node], fact) {
+            worklist.extend(cfg.predecessors(node).iter().map(Edge::from));
+        }
+    }
+    let first = cfg.successors(cfg.entry()).first().map(Edge::to);
+    let fact = first.and_then(|first| solution.entry[first].clone());
+    solution.entry[cfg.entry()] = fact.clone();
+    solution.exit[cfg.entry()] = fact;
+    Ok(())
+}
+
+/// The locals whose current values may still be read. Facts are sets of local indexes, with
+/// both halves of a long or double.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Liveness;
+
+impl Analysis for Liveness {
+    type Fact = BTreeSet<u16>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self) -> BTreeSet<u16> {
+        BTreeSet::new()
+    }
+
+    fn transfer(
+        &self,
+        _pc: u32,
+        instruction: &Instruction,
+        fact: &mut BTreeSet<u16>,
+    ) -> Result<(), io::Error> {
+        let Some((index, wide)) = local_access(instruction) else {
+            return Ok(());
+        };
+        let slots = index..index + 1 + wide as u16;
+        // iinc reads the local as well as writing it
+        if is_store(instruction) && !matches!(instruction, Instruction::Iinc { .. }) {
+            slots.for_each(|slot| {
+                fact.remove(&slot);
+            });
+        } else {
+            fact.extend(slots);
+        }
+        Ok(())
+    }
+}
+
+/// A store that gave a local its value, or a parameter's value on entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Definition {
+    local: u16,
+    pc: Option<u32>,
+}
+
+impl Definition {
+    pub fn new(local: u16, pc: Option<u32>) -> Definition {
+        Definition { local, pc }
+    }
+
+    pub fn local(&self) -> u16 {
+        self.local
+    }
+
+    /// The pc of the store, or `None` for `this` and the parameters
+    pub fn pc(&self) -> Option<u32> {
+        self.pc
+    }
+}
+
+/// The stores whose values may still be in each local
+#[derive(Clone, Copy, Debug)]
+pub struct ReachingDefinitions {
+    parameter_slots: u16,
+}
+
+impl ReachingDefinitions {
+    /// For a method whose `this` and parameters take the first `parameter_slots` locals
+    pub fn new(parameter_slots: u16) -> ReachingDefinitions {
+        ReachingDefinitions { parameter_slots }
+    }
+}
+
+impl Analysis for ReachingDefinitions {
+    type Fact = BTreeSet<Definition>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> BTreeSet<Definition> {
+        (0..self.parameter_slots)
+            .map(|local| Definition::new(local, None))
+            .collect()
+    }
+
+    fn transfer(
+        &self,
+        pc: u32,
+        instruction: &Instruction,
+        fact: &mut BTreeSet<Definition>,
+    ) -> Result<(), io::Error> {
+        if !is_store(instruction) {
+            return Ok(());
+        }
+        let Some((index, wide)) = local_access(instruction) else {
+            return Ok(());
+        };
+        for local in index..index + 1 + wide as u16 {
+            fact.retain(|definition| definition.local != local);
+            fact.insert(Definition::new(local, Some(pc)));
+        }
+        Ok(())
+    }
+}
+
+/// What constant propagation and nullness know about a local or stack slot. Longs and doubles
+/// take two slots, the second one unknown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    /// Not a constant, or nothing is known
+    Unknown,
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Null,
+    /// A reference that can't be null
+    NonNull,
+}
+
+impl Value {
+    /// Equality with floats and doubles compared by their bits, so NaN is a constant too
+    fn same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Double(a), Value::Double(b)) => a.to_bits() == b.to_bits(),
+            _ => self == other,
+        }
+    }
+}
+
+impl Lattice for Value {
+    fn join(&mut self, other: &Value) -> bool {
+        if self.same(other) || *self == Value::Unknown {
+            return false;
+        }
+        *self = Value::Unknown;
+        true
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Unknown => write!(f, "?"),
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Long(value) => write!(f, "{value}L"),
+            Value::Float(value) => write!(f, "{value}F"),
+            Value::Double(value) => write!(f, "{value}D"),
+            Value::Null => write!(f, "null"),
+            Value::NonNull => write!(f, "non-null"),
+        }
+    }
+}
+
+/// The values of the locals and operand stack at some pc
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueFrame {
+    locals: Vec<Value>,
+    stack: Vec<Value>,
+    /// the local each stack slot was loaded from, while that local still holds it
+    sources: Vec<Option<u16>>,
+}
+
+impl ValueFrame {
+    pub fn locals(&self) -> &[Value] {
+        &self.locals
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+        self.sources.push(None);
+    }
+
+    fn push_wide(&mut self, value: Value) {
+        self.push(value);
+        self.push(Value::Unknown);
+    }
+
+    /// Pushes an unknown value taking `slots` slots
+    fn push_unknown(&mut self, slots: u16) {
+        for _ in 0..slots {
+            self.push(Value::Unknown);
+        }
+    }
+
+    fn pop(&mut self) -> Result<Value, io::Error> {
+        self.sources.pop();
+        self.stack
+            .pop()
+            .ok_or_else(|| value_error("the operand stack is empty"))
+    }
+
+    fn pop_wide(&mut self) -> Result<Value, io::Error> {
+        self.pop()?;
+        self.pop()
+    }
+
+    fn pop_slots(&mut self, slots: u16) -> Result<(), io::Error> {
+        for _ in 0..slots {
+            self.pop()?;
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, index: u16, wide: bool) -> Result<(), io::Error> {
+        let value = *self
+            .locals
+            .get(index as usize)
+            .ok_or_else(|| value_error(&format!("local {index} is out of range")))?;
+        self.stack.push(value);
+        self.sources.push(Some(index));
+        if wide {
+            self.push(Value::Unknown);
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, index: u16, value: Value, wide: bool) -> Result<(), io::Error> {
+        let slots = index as usize..index as usize + 1 + wide as usize;
+        if slots.end > self.locals.len() {
+            return Err(value_error(&format!("local {index} is out of range")));
+        }
+        self.locals[slots.start] = value;
+        if wide {
+            self.locals[slots.start + 1] = Value::Unknown;
+        }
+        for source in self.sources.iter_mut() {
+            if source.is_some_and(|local| slots.contains(&(local as usize))) {
+                *source = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that a local, and every copy of it on the stack, holds `value`
+    fn refine(&mut self, local: u16, value: Value) {
+        if let Some(slot) = self.locals.get_mut(local as usize) {
+            *slot = value;
+        }
+        for (slot, source) in self.stack.iter_mut().zip(self.sources.iter()) {
+            if *source == Some(local) {
+                *slot = value;
+            }
+        }
+    }
+
+    /// The value `depth` slots below the top of the stack
+    fn peek(&self, depth: usize) -> Option<(Value, Option<u16>)> {
+        let index = self.stack.len().checked_sub(depth + 1)?;
+        Some((self.stack[index], self.sources[index]))
+    }
+}
+
+impl Lattice for ValueFrame {
+    fn join(&mut self, other: &ValueFrame) -> bool {
+        let mut changed = false;
+        for (value, other) in self.locals.iter_mut().zip(other.locals.iter()) {
+            changed |= value.join(other);
+        }
+        // verified code always has the same stack height where paths meet
+        for (value, other) in self.stack.iter_mut().zip(other.stack.iter()) {
+            changed |= value.join(other);
+        }
+        for (source, other) in self.sources.iter_mut().zip(other.sources.iter()) {
+            if source.is_some() && source != other {
+                *source = None;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn value_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Evaluates instructions over `ValueFrame`s, folding arithmetic on constants and not taking
+/// branches whose conditions are constant
+#[derive(Clone, Copy)]
+struct ValueInterpreter<'a> {
+    constant_pool: &'a ConstantPool,
+    initial: usize,
+    is_static: bool,
+    /// whether null checks and dereferences tell what a local holds afterwards
+    refine_nulls: bool,
+}
+
+impl ValueInterpreter<'_> {
+    fn boundary(&self) -> ValueFrame {
+        let mut locals = vec![Value::Unknown; self.initial];
+        if !self.is_static && !locals.is_empty() {
+            locals[0] = Value::NonNull;
+        }
+        ValueFrame {
+            locals,
+            stack: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    fn constant(&self, index: u16) -> Result<(Value, bool), io::Error> {
+        Ok(
+            match self.constant_pool.get(index).map(|item| item.data()) {
+                Some(ConstantPoolData::Integer(value)) => (Value::Int(value.value()), false),
+                Some(ConstantPoolData::Float(value)) => (Value::Float(value.value()), false),
+                Some(ConstantPoolData::Long(value)) => (Value::Long(value.value()), true),
+                Some(ConstantPoolData::Double(value)) => (Value::Double(value.value()), true),
+                Some(
+                    ConstantPoolData::String(_)
+                    | ConstantPoolData::ClassInfo(_)
+                    | ConstantPoolData::MethodType(_)
+                    | ConstantPoolData::MethodHandle(_),
+                ) => (Value::NonNull, false),
+                _ => {
+                    return Err(value_error(&format!(
+                        "constant pool entry {index} can't be loaded"
+                    )))
+                }
+            },
+        )
+    }
+
+    fn field_slots(&self, index: u16) -> Result<u16, io::Error> {
+        let (_, _, descriptor) = self.constant_pool.member_ref(index)?;
+        Ok(FieldType::parse(&descriptor)?.slots())
+    }
+
+    fn invoke(&self, frame: &mut ValueFrame, index: u16, receiver: bool) -> Result<(), io::Error> {
+        let descriptor = self.invoked_descriptor(index)?;
+        frame.pop_slots(descriptor.parameter_slots())?;
+        if receiver {
+            frame.pop()?;
+        }
+        frame.push_unknown(descriptor.return_type().map_or(0, FieldType::slots));
+        Ok(())
+    }
+
+    fn invoked_descriptor(&self, index: u16) -> Result<MethodDescriptor, io::Error> {
+        let descriptor = match self.constant_pool.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::InvokeDynamic(indy)) => {
+                self.constant_pool
+                    .name_and_type(indy.name_and_type_index())?
+                    .1
+            }
+            _ => self.constant_pool.member_ref(index)?.2,
+        };
+        MethodDescriptor::parse(&descriptor)
+    }
+
+    /// How far below the top of the stack the reference an instruction dereferences is
+    fn dereference_depth(&self, instruction: &Instruction) -> Result<Option<u16>, io::Error> {
+        use Instruction::*;
+        Ok(Some(match instruction {
+            Getfield(_) | Arraylength | Athrow | Monitorenter | Monitorexit => 0,
+            Iaload | Laload | Faload | Daload | Aaload | Baload | Caload | Saload => 1,
+            Iastore | Fastore | Aastore | Bastore | Castore | Sastore => 2,
+            Lastore | Dastore => 3,
+            Putfield(index) => self.field_slots(*index)?,
+            Invokevirtual(index) | Invokespecial(index) | Invokeinterface { index, .. } => {
+                self.invoked_descriptor(*index)?.parameter_slots()
+            }
+            _ => return Ok(None),
+        }))
+    }
+
+    fn execute(&self, instruction: &Instruction, frame: &mut ValueFrame) -> Result<(), io::Error> {
+        let dereferenced = match self.refine_nulls {
+            true => self
+                .dereference_depth(instruction)?
+                .and_then(|depth| frame.peek(depth as usize)),
+            false => None,
+        };
+        self.evaluate(instruction, frame)?;
+        // had the reference been null, the instruction would have thrown
+        if let Some((value, Some(local))) = dereferenced {
+            if value != Value::Null {
+                frame.refine(local, Value::NonNull);
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self, instruction: &Instruction, frame: &mut ValueFrame) -> Result<(), io::Error> {
+        use Instruction::*;
+        use Value::{Double as D, Float as F, Int as I, Long as L, Unknown};
+        match instruction {
+            Nop | Goto(_) | GotoW(_) | Return | Ret(_) => {}
+            AconstNull => frame.push(Value::Null),
+            IconstM1 => frame.push(I(-1)),
+            Iconst0 => frame.push(I(0)),
+            Iconst1 => frame.push(I(1)),
+            Iconst2 => frame.push(I(2)),
+            Iconst3 => frame.push(I(3)),
+            Iconst4 => frame.push(I(4)),
+            Iconst5 => frame.push(I(5)),
+            Bipush(value) => frame.push(I(*value as i32)),
+            Sipush(value) => frame.push(I(*value as i32)),
+            Lconst0 => frame.push_wide(L(0)),
+            Lconst1 => frame.push_wide(L(1)),
+            Fconst0 => frame.push(F(0.0)),
+            Fconst1 => frame.push(F(1.0)),
+            Fconst2 => frame.push(F(2.0)),
+            Dconst0 => frame.push_wide(D(0.0)),
+            Dconst1 => frame.push_wide(D(1.0)),
+            Ldc(index) | LdcW(index) | Ldc2W(index) => match self.constant(*index)? {
+                (value, true) => frame.push_wide(value),
+                (value, false) => frame.push(value),
+            },
+
+            Iload0 | Iload1 | Iload2 | Iload3 | Lload0 | Lload1 | Lload2 | Lload3 | Fload0
+            | Fload1 | Fload2 | Fload3 | Dload0 | Dload1 | Dload2 | Dload3 | Aload0 | Aload1
+            | Aload2 | Aload3 | Iload(_) | Lload(_) | Fload(_) | Dload(_) | Aload(_) => {
+                // every load has a local
+                let (index, wide) = local_access(instruction).unwrap();
+                frame.load(index, wide)?;
+            }
+            Istore0 | Istore1 | Istore2 | Istore3 | Lstore0 | Lstore1 | Lstore2 | Lstore3
+            | Fstore0 | Fstore1 | Fstore2 | Fstore3 | Dstore0 | Dstore1 | Dstore2 | Dstore3
+            | Astore0 | Astore1 | Astore2 | Astore3 | Istore(_) | Lstore(_) | Fstore(_)
+            | Dstore(_) | Astore(_) => {
+                let (index, wide) = local_access(instruction).unwrap();
+                let value = match wide {
+                    true => frame.pop_wide()?,
+                    false => frame.pop()?,
+                };
+                frame.store(index, value, wide)?;
+            }
+            Iinc { index, increment } => {
+                let value = match frame.locals.get(*index as usize) {
+                    Some(I(value)) => I(value.wrapping_add(*increment as i32)),
+                    _ => Unknown,
+                };
+                frame.store(*index, value, false)?;
+            }
+
+            Iaload | Baload | Caload | Saload | Faload | Aaload | Laload | Daload => {
+                frame.pop_slots(2)?;
+                match instruction {
+                    Laload | Daload => frame.push_unknown(2),
+                    _ => frame.push(Unknown),
+                }
+            }
+            Iastore | Bastore | Castore | Sastore | Fastore | Aastore => frame.pop_slots(3)?,
+            Lastore | Dastore => frame.pop_slots(4)?,
+
+            Pop => frame.pop_slots(1)?,
+            Pop2 => frame.pop_slots(2)?,
+            Dup | DupX1 | DupX2 | Dup2 | Dup2X1 | Dup2X2 | Swap => {
+                let (count, depth) = match instruction {
+                    Dup => (1, 0),
+                    DupX1 => (1, 1),
+                    DupX2 => (1, 2),
+                    Dup2 => (2, 0),
+                    Dup2X1 => (2, 1),
+                    Dup2X2 => (2, 2),
+                    _ => (0, 0),
+                };
+                let length = frame.stack.len();
+                let needed = if count == 0 { 2 } else { count + depth };
+                if length < needed {
+                    return Err(value_error("the operand stack is too shallow"));
+                }
+                if count == 0 {
+                    frame.stack.swap(length - 1, length - 2);
+                    frame.sources.swap(length - 1, length - 2);
+                } else {
+                    let at = length - count - depth;
+                    let top = frame.stack[length - count..].to_vec();
+                    frame.stack.splice(at..at, top);
+                    let top = frame.sources[length - count..].to_vec();
+                    frame.sources.splice(at..at, top);
+                }
+            }
+
+            Iadd | Isub | Imul | Idiv | Irem | Ishl | Ishr | Iushr | Iand | Ior | Ixor => {
+                let b = frame.pop()?;
+                let a = frame.pop()?;
+                frame.push(match (a, b) {
+                    (I(a), I(b)) => int_operation(instruction, a, b).map_or(Unknown, I),
+                    _ => Unknown,
+                });
+            }
+            Ladd | Lsub | Lmul | Ldiv | Lrem | Land | Lor | Lxor | Lshl | Lshr | Lushr => {
+                let b = match instruction {
+                    Lshl | Lshr | Lushr => frame.pop()?,
+                    _ => frame.pop_wide()?,
+                };
+                let a = frame.pop_wide()?;
+                frame.push_wide(match (a, b) {
+                    (L(a), L(b)) => long_operation(instruction, a, b).map_or(Unknown, L),
+                    (L(a), I(b)) => long_operation(instruction, a, b as i64).map_or(Unknown, L),
+                    _ => Unknown,
+                });
+            }
+            Fadd | Fsub | Fmul | Fdiv | Frem => {
+                let b = frame.pop()?;
+                let a = frame.pop()?;
+                frame.push(match (a, b) {
+                    (F(a), F(b)) => F(float_operation(instruction, a as f64, b as f64) as f32),
+                    _ => Unknown,
+                });
+            }
+            Dadd | Dsub | Dmul | Ddiv | Drem => {
+                let b = frame.pop_wide()?;
+                let a = frame.pop_wide()?;
+                frame.push_wide(match (a, b) {
+                    (D(a), D(b)) => D(float_operation(instruction, a, b)),
+                    _ => Unknown,
+                });
+            }
+            Ineg | Lneg | Fneg | Dneg | I2l | I2f | I2d | L2i | L2f | L2d | F2i | F2l | F2d
+            | D2i | D2l | D2f | I2b | I2c | I2s => {
+                let value = match instruction {
+                    Lneg | Dneg | L2i | L2f | L2d | D2i | D2l | D2f => frame.pop_wide()?,
+                    _ => frame.pop()?,
+                };
+                let value = convert(instruction, value);
+                match instruction {
+                    Lneg | Dneg | I2l | I2d | L2d | F2l | F2d | D2l => frame.push_wide(value),
+                    _ => frame.push(value),
+                }
+            }
+            Lcmp | Fcmpl | Fcmpg | Dcmpl | Dcmpg => {
+                let (b, a) = match instruction {
+                    Fcmpl | Fcmpg => (frame.pop()?, frame.pop()?),
+                    _ => (frame.pop_wide()?, frame.pop_wide()?),
+                };
+                let ordering = match (a, b) {
+                    (L(a), L(b)) => Some(a.partial_cmp(&b)),
+                    (F(a), F(b)) => Some(a.partial_cmp(&b)),
+                    (D(a), D(b)) => Some(a.partial_cmp(&b)),
+                    _ => None,
+                };
+                frame.push(match ordering {
+                    Some(Some(ordering)) => I(ordering as i32),
+                    // comparisons with NaN
+                    Some(None) => I(if matches!(instruction, Fcmpl | Dcmpl) {
+                        -1
+                    } else {
+                        1
+                    }),
+                    None => Unknown,
+                });
+            }
+
+            Ifeq(_)
+            | Ifne(_)
+            | Iflt(_)
+            | Ifge(_)
+            | Ifgt(_)
+            | Ifle(_)
+            | Ifnull(_)
+            | Ifnonnull(_)
+            | Tableswitch { .. }
+            | Lookupswitch { .. }
+            | Ireturn
+            | Freturn
+            | Areturn
+            | Athrow
+            | Monitorenter
+            | Monitorexit => frame.pop_slots(1)?,
+            IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_)
+            | IfAcmpeq(_) | IfAcmpne(_) | Lreturn | Dreturn => frame.pop_slots(2)?,
+            Jsr(_) | JsrW(_) => frame.push(Unknown),
+
+            Getstatic(index) => frame.push_unknown(self.field_slots(*index)?),
+            Getfield(index) => {
+                frame.pop()?;
+                frame.push_unknown(self.field_slots(*index)?);
+            }
+            Putstatic(index) => frame.pop_slots(self.field_slots(*index)?)?,
+            Putfield(index) => frame.pop_slots(self.field_slots(*index)? + 1)?,
+            Invokevirtual(index) | Invokespecial(index) | Invokeinterface { index, .. } => {
+                self.invoke(frame, *index, true)?
+            }
+            Invokestatic(index) | Invokedynamic(index) => self.invoke(frame, *index, false)?,
+
+            New(_) => frame.push(Value::NonNull),
+            Newarray(_) | Anewarray(_) => {
+                frame.pop()?;
+                frame.push(Value::NonNull);
+            }
+            Multianewarray { dimensions, .. } => {
+                frame.pop_slots(*dimensions as u16)?;
+                frame.push(Value::NonNull);
+            }
+            Arraylength => {
+                frame.pop()?;
+                frame.push(Unknown);
+            }
+            Checkcast(_) => {
+                frame
+                    .peek(0)
+                    .ok_or_else(|| value_error("the operand stack is empty"))?;
+            }
+            Instanceof(_) => {
+                let value = frame.pop()?;
+                frame.push(if value == Value::Null { I(0) } else { Unknown });
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_edge(
+        &self,
+        edge: &Edge,
+        instruction: &Instruction,
+        before: &ValueFrame,
+        after: &ValueFrame,
+    ) -> Option<ValueFrame> {
+        let taken = match edge.kind() {
+            EdgeKind::Branch => true,
+            EdgeKind::FallThrough => false,
+            _ => return Some(after.clone()),
+        };
+        if condition(instruction, before).is_some_and(|condition| condition != taken) {
+            return None;
+        }
+        let mut after = after.clone();
+        if let (true, Instruction::Ifnull(_) | Instruction::Ifnonnull(_)) =
+            (self.refine_nulls, instruction)
+        {
+            if let Some((_, Some(local))) = before.peek(0) {
+                let null = taken == matches!(instruction, Instruction::Ifnull(_));
+                after.refine(local, if null { Value::Null } else { Value::NonNull });
+            }
+        }
+        Some(after)
+    }
+
+    fn exception(&self, fact: &ValueFrame) -> ValueFrame {
+        ValueFrame {
+            locals: fact.locals.clone(),
+            stack: vec![Value::NonNull],
+            sources: vec![None],
+        }
+    }
+}
+
+/// Runs straight-line instructions from an empty stack with no locals, or `None` if one of
+/// them needs more than that or would throw
+fn evaluate_alone(
+    constant_pool: &ConstantPool,
+    instructions: &[Instruction],
+) -> Option<ValueFrame> {
+    let interpreter = ValueInterpreter {
+        constant_pool,
+        initial: 0,
+        is_static: true,
+        refine_nulls: false,
+    };
+    let mut frame = interpreter.boundary();
+    for instruction in instructions {
+        interpreter.evaluate(instruction, &mut frame).ok()?;
+    }
+    Some(frame)
+}
+
+/// The constant `instructions` leave as the only value on the stack, e.g. 6 for `iconst_2
+/// iconst_3 imul`, if they compute one
+pub(crate) fn constant_result(
+    constant_pool: &ConstantPool,
+    instructions: &[Instruction],
+) -> Option<Value> {
+    let frame = evaluate_alone(constant_pool, instructions)?;
+    match frame.stack.as_slice() {
+        [value @ (Value::Int(_) | Value::Float(_) | Value::Null)] => Some(*value),
+        [value @ (Value::Long(_) | Value::Double(_)), Value::Unknown] => Some(*value),
+        _ => None,
+    }
+}
+
+/// Whether `branch` is taken after `operands` push its operands, if they're constants
+pub(crate) fn constant_condition(
+    constant_pool: &ConstantPool,
+    operands: &[Instruction],
+    branch: &Instruction,
+) -> Option<bool> {
+    condition(branch, &evaluate_alone(constant_pool, operands)?)
+}
+
+fn int_operation(instruction: &Instruction, a: i32, b: i32) -> Option<i32> {
+    use Instruction::*;
+    Some(match instruction {
+        Iadd => a.wrapping_add(b),
+        Isub => a.wrapping_sub(b),
+        Imul => a.wrapping_mul(b),
+        // division by zero throws instead
+        Idiv => a.checked_div(b).or_else(|| (b == -1).then_some(a))?,
+        Irem => a.checked_rem(b).or_else(|| (b == -1).then_some(0))?,
+        Ishl => a.wrapping_shl(b as u32),
+        Ishr => a.wrapping_shr(b as u32),
+        Iushr => (a as u32).wrapping_shr(b as u32) as i32,
+        Iand => a & b,
+        Ior => a | b,
+        _ => a ^ b,
+    })
+}
+
+fn long_operation(instruction: &Instruction, a: i64, b: i64) -> Option<i64> {
+    use Instruction::*;
+    Some(match instruction {
+        Ladd => a.wrapping_add(b),
+        Lsub => a.wrapping_sub(b),
+        Lmul => a.wrapping_mul(b),
+        Ldiv => a.checked_div(b).or_else(|| (b == -1).then_some(a))?,
+        Lrem => a.checked_rem(b).or_else(|| (b == -1).then_some(0))?,
+        Lshl => a.wrapping_shl(b as u32),
+        Lshr => a.wrapping_shr(b as u32),
+        Lushr => (a as u64).wrapping_shr(b as u32) as i64,
+        Land => a & b,
+        Lor => a | b,
+        _ => a ^ b,
+    })
+}
+
+fn float_operation(instruction: &Instruction, a: f64, b: f64) -> f64 {
+    use Instruction::*;
+    match instruction {
+        Fadd | Dadd => a + b,
+        Fsub | Dsub => a - b,
+        Fmul | Dmul => a * b,
+        Fdiv | Ddiv => a / b,
+        _ => a % b,
+    }
+}
+
+/// Negations and conversions (section 2.11.4), which saturate like Rust's `as`
+fn convert(instruction: &Instruction, value: Value) -> Value {
+    use Instruction::*;
+    use Value::{Double as D, Float as F, Int as I, Long as L};
+    match (instruction, value) {
+        (Ineg, I(a)) => I(a.wrapping_neg()),
+        (Lneg, L(a)) => L(a.wrapping_neg()),
+        (Fneg, F(a)) => F(-a),
+        (Dneg, D(a)) => D(-a),
+        (I2l, I(a)) => L(a as i64),
+        (I2f, I(a)) => F(a as f32),
+        (I2d, I(a)) => D(a as f64),
+        (L2i, L(a)) => I(a as i32),
+        (L2f, L(a)) => F(a as f32),
+        (L2d, L(a)) => D(a as f64),
+        (F2i, F(a)) => I(a as i32),
+        (F2l, F(a)) => L(a as i64),
+        (F2d, F(a)) => D(a as f64),
+        (D2i, D(a)) => I(a as i32),
+        (D2l, D(a)) => L(a as i64),
+        (D2f, D(a)) => F(a as f32),
+        (I2b, I(a)) => I(a as i8 as i32),
+        (I2c, I(a)) => I(a as u16 as i32),
+        (I2s, I(a)) => I(a as i16 as i32),
+        _ => Value::Unknown,
+    }
+}
+
+/// Whether a conditional branch is taken, if its operands are known
+fn condition(instruction: &Instruction, frame: &ValueFrame) -> Option<bool> {
+    use Instruction::*;
+    let top = |depth| frame.peek(depth).map(|(value, _)| value);
+    match instruction {
+        Ifeq(_) | Ifne(_) | Iflt(_) | Ifge(_) | Ifgt(_) | Ifle(_) => match top(0)? {
+            Value::Int(value) => Some(compare(instruction, value, 0)),
+            _ => None,
+        },
+        IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_) => {
+            match (top(1)?, top(0)?) {
+                (Value::Int(a), Value::Int(b)) => Some(compare(instruction, a, b)),
+                _ => None,
+            }
+        }
+        Ifnull(_) | Ifnonnull(_) => {
+            let null = match top(0)? {
+                Value::Null => true,
+                Value::NonNull => false,
+                _ => return None,
+            };
+            Some(null == matches!(instruction, Ifnull(_)))
+        }
+        IfAcmpeq(_) | IfAcmpne(_) => {
+            let equal = match (top(1)?, top(0)?) {
+                (Value::Null, Value::Null) => true,
+                (Value::Null, Value::NonNull) | (Value::NonNull, Value::Null) => false,
+                _ => return None,
+            };
+            Some(equal == matches!(instruction, IfAcmpeq(_)))
+        }
+        _ => None,
+    }
+}
+
+fn compare(instruction: &Instruction, a: i32, b: i32) -> bool {
+    use Instruction::*;
+    match instruction {
+        Ifeq(_) | IfIcmpeq(_) => a == b,
+        Ifne(_) | IfIcmpne(_) => a != b,
+        Iflt(_) | IfIcmplt(_) => a < b,
+        Ifge(_) | IfIcmpge(_) => a >= b,
+        Ifgt(_) | IfIcmpgt(_) => a > b,
+        _ => a <= b,
+    }
+}
+
+/// Int, long, float, double and null constants, folded through arithmetic and conversions.
+/// Branches on constant conditions only go one way, so code behind them stays unreached.
+#[derive(Clone, Copy)]
+pub struct ConstantPropagation<'a> {
+    interpreter: ValueInterpreter<'a>,
+}
+
+impl<'a> ConstantPropagation<'a> {
+    pub fn new(
+        constant_pool: &'a ConstantPool,
+        method: &MethodInfo,
+        code: &Code,
+    ) -> ConstantPropagation<'a> {
+        ConstantPropagation {
+            interpreter: value_interpreter(constant_pool, method, code, false),
+        }
+    }
+}
+
+/// Which references are always null and which can't be: constant propagation, plus what a
+/// null check or a dereference that didn't throw tells about the local checked
+#[derive(Clone, Copy)]
+pub struct Nullness<'a> {
+    interpreter: ValueInterpreter<'a>,
+}
+
+impl<'a> Nullness<'a> {
+    pub fn new(constant_pool: &'a ConstantPool, method: &MethodInfo, code: &Code) -> Nullness<'a> {
+        Nullness {
+            interpreter: value_interpreter(constant_pool, method, code, true),
+        }
+    }
+
+    /// The reference `instruction` dereferences in `frame`, the frame before it
+    pub fn dereferenced(
+        &self,
+        instruction: &Instruction,
+        frame: &ValueFrame,
+    ) -> Result<Option<Value>, io::Error> {
+        let depth = self.interpreter.dereference_depth(instruction)?;
+        Ok(depth.and_then(|depth| frame.peek(depth as usize).map(|(value, _)| value)))
+    }
+}
+
+fn value_interpreter<'a>(
+    constant_pool: &'a ConstantPool,
+    method: &MethodInfo,
+    code: &Code,
+    refine_nulls: bool,
+) -> ValueInterpreter<'a> {
+    ValueInterpreter {
+        constant_pool,
+        initial: code.max_locals() as usize,
+        is_static: method.access_flags() & ACC_STATIC != 0,
+        refine_nulls,
+    }
+}
+
+macro_rules! value_analysis {
+    ($analysis:ident) => {
+        impl Analysis for $analysis<'_> {
+            type Fact = ValueFrame;
+
+            fn direction(&self) -> Direction {
+                Direction::Forward
+            }
+
+            fn boundary(&self) -> ValueFrame {
+                self.interpreter.boundary()
+            }
+
+            fn transfer(
+                &self,
+                _pc: u32,
+                instruction: &Instruction,
+                fact: &mut ValueFrame,
+            ) -> Result<(), io::Error> {
+                self.interpreter.execute(instruction, fact)
+            }
+
+            fn transfer_edge(
+                &self,
+                edge: &Edge,
+                instruction: &Instruction,
+                before: &ValueFrame,
+                after: &ValueFrame,
+            ) -> Option<ValueFrame> {
+                self.interpreter
+                    .transfer_edge(edge, instruction, before, after)
+            }
+
+            fn exception(&self, _catch_type: u16, fact: &ValueFrame) -> ValueFrame {
+                self.interpreter.exception(fact)
+            }
+        }
+    };
+}
+
+value_analysis!(ConstantPropagation);
+value_analysis!(Nullness);
+
+/// Something the dataflow checks found in a method
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    class_name: String,
+    method: String,
+    pc: u32,
+    message: String,
+}
+
+impl Finding {
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The method's name and descriptor
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{} at pc {}: {}",
+            self.class_name, self.method, self.pc, self.message
+        )
+    }
+}
+
+/// Finds dereferences of values that are always null, and stores to locals that are never
+/// read afterwards, in reachable code. Handlers storing an exception nothing reads are left
+/// out, since that's every `catch` that ignores its exception.
+pub fn check_class(class_file: &ClassFile) -> Result<Vec<Finding>, io::Error> {
+    let class_name = class_file.class_name()?;
+    let constant_pool = class_file.constant_pool();
+    let mut findings = Vec::new();
+    for method in class_file.methods() {
+        let Some(code) = method.code(constant_pool)? else {
+            continue;
+        };
+        let name = format!(
+            "{}{}",
+            method.name(constant_pool)?,
+            method.descriptor(constant_pool)?
+        );
+        let cfg = ControlFlowGraph::from_code(&code)?;
+        let nullness = Nullness::new(constant_pool, method, &code);
+        let values = solve(&cfg, &nullness)?;
+        let liveness = solve(&cfg, &Liveness)?;
+        let handlers: BTreeSet<u32> = code
+            .exception_table()
+            .iter()
+            .map(|entry| entry.handler_pc() as u32)
+            .collect();
+        let mut finding = |pc: u32, message: String| {
+            findings.push(Finding {
+                class_name: class_name.clone(),
+                method: name.clone(),
+                pc,
+                message,
+            })
+        };
+        for block in cfg.blocks() {
+            for (pc, instruction) in block.instructions() {
+                let Some(frame) = values.before(*pc) else {
+                    continue;
+                };
+                if nullness.dereferenced(instruction, frame)? == Some(Value::Null) {
+                    finding(
+                        *pc,
+                        format!("{} on a value that is always null", instruction.mnemonic()),
+                    );
+                }
+                if !is_store(instruction) || handlers.contains(pc) {
+                    continue;
+                }
+                let Some((index, _)) = local_access(instruction) else {
+                    continue;
+                };
+                if liveness
+                    .after(*pc)
+                    .is_some_and(|live| !live.contains(&index))
+                {
+                    finding(*pc, format!("stores to local {index}, which is never read"));
+                }
+            }
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    fn method(descriptor: &str, body: &str) -> ClassFile {
+        let source = format!(
+            ".class T\n.method static f{descriptor}\n.limit locals 2\n{body}\n.end method\n"
+        );
+        assemble(&source).unwrap()
+    }
+
+    fn code(class_file: &ClassFile) -> (Code, ControlFlowGraph) {
+        let code = class_file.methods()[0]
+            .code(class_file.constant_pool())
+            .unwrap()
+            .unwrap();
+        let cfg = ControlFlowGraph::from_code(&code).unwrap();
+        (code, cfg)
+    }
+
+    fn constants(class_file: &ClassFile) -> (ControlFlowGraph, Solution<ValueFrame>) {
+        let (code, cfg) = code(class_file);
+        let analysis =
+            ConstantPropagation::new(class_file.constant_pool(), &class_file.methods()[0], &code);
+        let solution = solve(&cfg, &analysis).unwrap();
+        (cfg, solution)
+    }
+
+    const DIAMOND: &str = "
+    iload_0
+    ifeq Else
+    iconst_1
+    istore_1
+    goto End
+Else:
+    iconst_2
+    istore_1
+End:
+    iload_1
+    ireturn";
+
+    #[test]
+    fn liveness() {
+        let class_file = method("(I)I", DIAMOND);
+        let (_, cfg) = code(&class_file);
+        let solution = solve(&cfg, &Liveness).unwrap();
+        assert_eq!(solution.before(0), Some(&BTreeSet::from([0])));
+        assert_eq!(solution.after(0), Some(&BTreeSet::new()));
+        assert_eq!(solution.after(5), Some(&BTreeSet::from([1])));
+        assert_eq!(solution.exit(0), Some(&BTreeSet::new()));
+        assert_eq!(solution.entry(cfg.entry()), Some(&BTreeSet::from([0])));
+    }
+
+    #[test]
+    fn reaching_definitions() {
+        let class_file = method("(I)I", DIAMOND);
+        let (_, cfg) = code(&class_file);
+        let solution = solve(&cfg, &ReachingDefinitions::new(1)).unwrap();
+        assert_eq!(
+            solution.before(11),
+            Some(&BTreeSet::from([
+                Definition::new(0, None),
+                Definition::new(1, Some(5)),
+                Definition::new(1, Some(10)),
+            ]))
+        );
+        assert_eq!(
+            solution.after(5),
+            Some(&BTreeSet::from([
+                Definition::new(0, None),
+                Definition::new(1, Some(5))
+            ]))
+        );
+    }
+
+    #[test]
+    fn constant_propagation_folds_and_prunes() {
+        let class_file = method(
+            "(I)I",
+            "
+    iconst_2
+    iconst_3
+    imul
+    istore_1
+    iload_1
+    bipush 6
+    if_icmpeq Six
+    iconst_0
+    ireturn
+Six:
+    iload_1
+    i2l
+    ldc2_w 10
+    lmul
+    l2i
+    ireturn",
+        );
+        let (cfg, solution) = constants(&class_file);
+        let six = cfg.block_at(12).unwrap();
+        assert_eq!(solution.entry(cfg.block_at(10).unwrap()), None);
+        assert_eq!(solution.entry(six).unwrap().locals()[1], Value::Int(6));
+        assert_eq!(solution.before(19).unwrap().stack(), [Value::Int(60)]);
+    }
+
+    #[test]
+    fn joins_lose_constants() {
+        let class_file = method("(I)I", DIAMOND);
+        let (cfg, solution) = constants(&class_file);
+        let end = cfg.block_at(11).unwrap();
+        assert_eq!(solution.entry(end).unwrap().locals(), [Value::Unknown; 2]);
+        assert_eq!(solution.before(10).unwrap().stack(), [Value::Int(2)]);
+    }
+
+    #[test]
+    fn handlers_see_every_state_in_their_range() {
+        let class_file = method(
+            "(I)I",
+            "
+    .catch java/lang/ArithmeticException from Start to End using Handler
+    iconst_1
+    istore_1
+Start:
+    iconst_2
+    istore_1
+    iload_0
+    iload_1
+    idiv
+    ireturn
+End:
+Handler:
+    astore_0
+    iload_1
+    ireturn",
+        );
+        let (cfg, solution) = constants(&class_file);
+        let handler = solution.entry(cfg.block_at(10).unwrap()).unwrap();
+        assert_eq!(handler.locals()[1], Value::Unknown);
+        assert_eq!(handler.stack(), [Value::NonNull]);
+
+        let (_, cfg) = code(&class_file);
+        let liveness = solve(&cfg, &Liveness).unwrap();
+        assert_eq!(liveness.before(2), Some(&BTreeSet::from([0, 1])));
+    }
+
+    #[test]
+    fn nullness_and_dead_stores() {
+        let class_file = assemble(
+            "
+.class T
+.method f(Ljava/lang/String;)I
+    .limit locals 3
+    aload_1
+    ifnonnull Checked
+    aload_1
+    invokevirtual java/lang/String/length()I
+    ireturn
+Checked:
+    aload_1
+    invokevirtual java/lang/String/length()I
+    istore_2
+    aload_1
+    invokevirtual java/lang/String/length()I
+    ireturn
+.end method
+.method g()I
+    .limit locals 2
+    aconst_null
+    astore_1
+    aload_0
+    pop
+    aload_1
+    arraylength
+    ireturn
+.end method
+",
+        )
+        .unwrap();
+        let findings: Vec<String> = check_class(&class_file)
+            .unwrap()
+            .iter()
+            .map(Finding::to_string)
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                "T.f(Ljava/lang/String;)I at pc 5: invokevirtual on a value that is always null",
+                "T.f(Ljava/lang/String;)I at pc 13: stores to local 2, which is never read",
+                "T.g()I at pc 5: arraylength on a value that is always null",
+            ]
+        );
+    }
+
+    #[test]
+    fn dereferences_refine_locals() {
+        let class_file = assemble(
+            "
+.class T
+.method static f(Ljava/lang/String;)I
+    aload_0
+    invokevirtual java/lang/String/length()I
+    pop
+    aload_0
+    ifnull Never
+    iconst_1
+    ireturn
+Never:
+    iconst_0
+    ireturn
+.end method
+",
+        )
+        .unwrap();
+        let (code, cfg) = code(&class_file);
+        let nullness = Nullness::new(class_file.constant_pool(), &class_file.methods()[0], &code);
+        let solution = solve(&cfg, &nullness).unwrap();
+        assert_eq!(solution.before(0).unwrap().locals()[0], Value::Unknown);
+        assert_eq!(solution.after(4).unwrap().locals()[0], Value::NonNull);
+        assert_eq!(solution.entry(cfg.block_at(12).unwrap()), None);
+    }
+}
@@ -0,0 +1,539 @@
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::limits::skip_type_annotation_target;
+use crate::class_file::signature::Signature;
+use crate::util::file::{read_u16, read_u8};
+use crate::util::json::JsonValue;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::io;
+use std::io::{BufReader, Read};
+
+/// The classes a class refers to: Class entries, the descriptors of member references,
+/// NameAndType and MethodType entries, its own fields and methods, generic signatures
+/// (section 4.7.9.1) and annotations (section 4.7.16). The class itself isn't included.
+pub fn class_dependencies(class_file: &ClassFile) -> Result<BTreeSet<String>, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let mut classes = BTreeSet::new();
+    for item in constant_pool.items() {
+        match item.data() {
+            ConstantPoolData::ClassInfo(class_info) => {
+                let name = constant_pool.utf8(class_info.name_index())?;
+                if name.starts_with('[') {
+                    signature_classes(&name, &mut classes)?;
+                } else {
+                    classes.insert(name);
+                }
+            }
+            ConstantPoolData::NameAndType(nat) => {
+                signature_classes(&constant_pool.utf8(nat.descriptor_index())?, &mut classes)?
+            }
+            ConstantPoolData::MethodType(method_type) => signature_classes(
+                &constant_pool.utf8(method_type.descriptor_index())?,
+                &mut classes,
+            )?,
+            _ => {}
+        }
+    }
+
+    attribute_classes(class_file.attributes(), constant_pool, &mut classes)?;
+    let members = class_file.fields().iter().chain(class_file.methods());
+    for member in members {
+        signature_classes(&member.descriptor(constant_pool)?, &mut classes)?;
+        attribute_classes(member.attributes(), constant_pool, &mut classes)?;
+    }
+    classes.remove(&class_file.class_name()?);
+    Ok(classes)
+}
+
+/// The package of an internal class name, e.g. `java/util` for `java/util/Map$Entry`, or an
+/// empty string for the default package
+pub fn package_name(class_name: &str) -> &str {
+    class_name
+        .rsplit_once('/')
+        .map_or("", |(package, _)| package)
+}
+
+/// A directed graph of dependencies between classes, or between whatever classes were grouped
+/// into, such as packages or jars. Nodes with no dependencies of their own only show up as
+/// targets unless they were added explicitly.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DependencyGraph {
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> DependencyGraph {
+        DependencyGraph::default()
+    }
+
+    /// Adds the class and everything it depends on
+    pub fn add_class_file(&mut self, class_file: &ClassFile) -> Result<(), io::Error> {
+        let class_name = class_file.class_name()?;
+        let dependencies = class_dependencies(class_file)?;
+        self.add_node(&class_name);
+        for dependency in dependencies {
+            self.add_dependency(&class_name, &dependency);
+        }
+        Ok(())
+    }
+
+    pub fn add_node(&mut self, node: &str) {
+        self.dependencies.entry(node.to_string()).or_default();
+    }
+
+    /// Records that `from` depends on `to`. Nodes never depend on themselves.
+    pub fn add_dependency(&mut self, from: &str, to: &str) {
+        let dependencies = self.dependencies.entry(from.to_string()).or_default();
+        if from != to {
+            dependencies.insert(to.to_string());
+        }
+    }
+
+    /// The nodes that were added, in order
+    pub fn nodes(&self) -> impl Iterator<Item = &str> {
+        self.dependencies.keys().map(String::as_str)
+    }
+
+    pub fn dependencies(&self, node: &str) -> Option<&BTreeSet<String>> {
+        self.dependencies.get(node)
+    }
+
+    /// Merges nodes into groups, keeping an edge between two groups if any of their members
+    /// had one. Nodes `group` returns None for are dropped along with their edges.
+    pub fn aggregate(&self, group: impl Fn(&str) -> Option<String>) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for (node, dependencies) in self.dependencies.iter() {
+            let Some(from) = group(node) else {
+                continue;
+            };
+            graph.add_node(&from);
+            for to in dependencies
+                .iter()
+                .filter_map(|dependency| group(dependency))
+            {
+                graph.add_dependency(&from, &to);
+            }
+        }
+        graph
+    }
+
+    /// The graph between packages
+    pub fn packages(&self) -> DependencyGraph {
+        self.aggregate(|class_name| Some(package_name(class_name).to_string()))
+    }
+
+    /// Only the edges between nodes that were added, leaving out libraries and the JDK
+    pub fn internal(&self) -> DependencyGraph {
+        self.aggregate(|node| {
+            self.dependencies
+                .contains_key(node)
+                .then(|| node.to_string())
+        })
+    }
+
+    /// Every set of nodes that depend on each other, directly or not, in name order. These
+    /// are the strongly connected components with more than one node, found with Tarjan's
+    /// algorithm.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut names: BTreeSet<&str> = self.nodes().collect();
+        for dependencies in self.dependencies.values() {
+            names.extend(dependencies.iter().map(String::as_str));
+        }
+        let names: Vec<&str> = names.into_iter().collect();
+        let id = |name: &str| names.binary_search(&name).unwrap();
+        let successors: Vec<Vec<usize>> = names
+            .iter()
+            .map(|name| match self.dependencies.get(*name) {
+                Some(dependencies) => dependencies.iter().map(|d| id(d)).collect(),
+                None => Vec::new(),
+            })
+            .collect();
+
+        let mut index: Vec<Option<usize>> = vec![None; names.len()];
+        let mut low_link = vec![0; names.len()];
+        let mut on_stack = vec![false; names.len()];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut cycles = Vec::new();
+        for root in 0..names.len() {
+            if index[root].is_some() {
+                continue;
+            }
+            // (node, next successor to look at), so deep graphs don't overflow the call stack
+            let mut work = vec![(root, 0)];
+            while let Some((node, mut next)) = work.pop() {
+                if next == 0 {
+                    index[node] = Some(next_index);
+                    low_link[node] = next_index;
+                    next_index += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                } else {
+                    // back from the successor before `next`
+                    let child = successors[node][next - 1];
+                    low_link[node] = low_link[node].min(low_link[child]);
+                }
+
+                let mut descended = false;
+                while next < successors[node].len() {
+                    let successor = successors[node][next];
+                    next += 1;
+                    match index[successor] {
+                        None => {
+                            work.push((node, next));
+                            work.push((successor, 0));
+                            descended = true;
+                            break;
+                        }
+                        Some(i) if on_stack[successor] => low_link[node] = low_link[node].min(i),
+                        Some(_) => {}
+                    }
+                }
+                if descended || Some(low_link[node]) != index[node] {
+                    continue;
+                }
+
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(names[member].to_string());
+                    if member == node {
+                        break;
+                    }
+                }
+                if component.len() > 1 {
+                    component.sort();
+                    cycles.push(component);
+                }
+            }
+        }
+        cycles.sort();
+        cycles
+    }
+
+    /// Renders the graph in Graphviz DOT format, with the edges of cycles in red
+    pub fn to_dot(&self, name: &str) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "\\\""));
+        let cycle_of: BTreeMap<String, usize> = self
+            .cycles()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, cycle)| cycle.into_iter().map(move |node| (node, i)))
+            .collect();
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph {} {{", quote(name)).unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for (node, dependencies) in self.dependencies.iter() {
+            writeln!(dot, "    {};", quote(node)).unwrap();
+            for dependency in dependencies.iter() {
+                let in_cycle = matches!(
+                    (cycle_of.get(node), cycle_of.get(dependency)),
+                    (Some(a), Some(b)) if a == b
+                );
+                let style = if in_cycle { " [color=red]" } else { "" };
+                writeln!(dot, "    {} -> {}{style};", quote(node), quote(dependency)).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// `{"nodes": [{"name": ..., "dependencies": [...]}], "cycles": [[...]]}`
+    pub fn to_json(&self) -> JsonValue {
+        let strings = |values: &mut dyn Iterator<Item = &String>| {
+            JsonValue::Array(values.map(|value| value.as_str().into()).collect())
+        };
+        let nodes = self
+            .dependencies
+            .iter()
+            .map(|(node, dependencies)| {
+                JsonValue::object()
+                    .with("name", node.as_str().into())
+                    .with("dependencies", strings(&mut dependencies.iter()))
+            })
+            .collect();
+        let cycles = self
+            .cycles()
+            .iter()
+            .map(|cycle| strings(&mut cycle.iter()))
+            .collect();
+        JsonValue::object()
+            .with("nodes", JsonValue::Array(nodes))
+            .with("cycles", JsonValue::Array(cycles))
+    }
+}
+
+fn dependency_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Adds the classes named in a field or method descriptor or a generic signature. Type
+/// variables aren't classes and inner classes of generic types become their binary names
+/// (`Outer$Inner`).
+fn signature_classes(signature: &str, classes: &mut BTreeSet<String>) -> Result<(), io::Error> {
+    Signature::parse(signature)?.for_each_class_type(&mut |class_type| {
+        classes.insert(class_type.binary_name());
+    });
+    Ok(())
+}
+
+/// Signatures and annotations of a class, field, method or Code attribute
+fn attribute_classes(
+    attributes: &[AttributeInfo],
+    constant_pool: &ConstantPool,
+    classes: &mut BTreeSet<String>,
+) -> Result<(), io::Error> {
+    for attribute in attributes {
+        let Ok(name) = attribute.name(constant_pool) else {
+            continue;
+        };
+        let reader = &mut attribute.reader();
+        match name.as_str() {
+            "Signature" => signature_classes(&constant_pool.utf8(read_u16(reader)?)?, classes)?,
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                for _ in 0..read_u16(reader)? {
+                    annotation_classes(reader, constant_pool, classes)?;
+                }
+            }
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                for _ in 0..read_u8(reader)? {
+                    for _ in 0..read_u16(reader)? {
+                        annotation_classes(reader, constant_pool, classes)?;
+                    }
+                }
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                for _ in 0..read_u16(reader)? {
+                    skip_type_annotation_target(reader)?;
+                    annotation_classes(reader, constant_pool, classes)?;
+                }
+            }
+            "AnnotationDefault" => element_value_classes(reader, constant_pool, classes)?,
+            "Code" => {
+                let code = Code::from(reader)?;
+                attribute_classes(code.attributes(), constant_pool, classes)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// An `annotation` structure (section 4.7.16)
+fn annotation_classes(
+    reader: &mut BufReader<impl Read>,
+    constant_pool: &ConstantPool,
+    classes: &mut BTreeSet<String>,
+) -> Result<(), io::Error> {
+    signature_classes(&constant_pool.utf8(read_u16(reader)?)?, classes)?;
+    for _ in 0..read_u16(reader)? {
+        read_u16(reader)?; // element_name_index
+        element_value_classes(reader, constant_pool, classes)?;
+    }
+    Ok(())
+}
+
+/// An `element_value` structure (section 4.7.16.1)
+fn element_value_classes(
+    reader: &mut BufReader<impl Read>,
+    constant_pool: &ConstantPool,
+    classes: &mut BTreeSet<String>,
+) -> Result<(), io::Error> {
+    match read_u8(reader)? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+            read_u16(reader)?;
+        }
+        b'e' => {
+            signature_classes(&constant_pool.utf8(read_u16(reader)?)?, classes)?;
+            read_u16(reader)?; // const_name_index
+        }
+        b'c' => signature_classes(&constant_pool.utf8(read_u16(reader)?)?, classes)?,
+        b'@' => annotation_classes(reader, constant_pool, classes)?,
+        b'[' => {
+            for _ in 0..read_u16(reader)? {
+                element_value_classes(reader, constant_pool, classes)?;
+            }
+        }
+        tag => {
+            return Err(dependency_error(&format!(
+                "Unknown annotation element value tag {tag}"
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+
+    fn classes(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_descriptors_and_signatures() {
+        let parse = |signature: &str| {
+            let mut found = BTreeSet::new();
+            signature_classes(signature, &mut found).map(|_| found)
+        };
+        assert_eq!(
+            parse("(I[[Ljava/lang/String;J)V").unwrap(),
+            classes(&["java/lang/String"])
+        );
+        assert_eq!(
+            parse("<K:Ljava/lang/Object;V::Ljava/lang/Comparable<-TV;>;>Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;")
+                .unwrap(),
+            classes(&[
+                "java/io/Serializable",
+                "java/lang/Comparable",
+                "java/lang/Object",
+                "java/util/AbstractMap"
+            ])
+        );
+        assert_eq!(
+            parse(
+                "<T:Ljava/lang/Throwable;>(Lp/Outer<TT;>.Inner<*>;)TT;^TT;^Ljava/io/IOException;"
+            )
+            .unwrap(),
+            classes(&[
+                "java/io/IOException",
+                "java/lang/Throwable",
+                "p/Outer$Inner"
+            ])
+        );
+        assert!(parse("(Ljava/lang/String").is_err());
+        assert!(parse("<T>V").is_err());
+    }
+
+    #[test]
+    fn collects_dependencies_from_the_constant_pool_and_members() {
+        let mut class_file = assemble(
+            "
+.class public p/A
+.super java/lang/Object
+.field private list Ljava/util/List;
+.method public f([Ljava/io/File;)V
+    .limit stack 2
+    .limit locals 2
+    new q/B
+    invokestatic q/C/make(Lq/D;)[Lq/E;
+    checkcast [Lq/F;
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    return
+.end method
+",
+        )
+        .unwrap();
+        assert_eq!(
+            class_dependencies(&class_file).unwrap(),
+            classes(&[
+                "java/io/File",
+                "java/io/PrintStream",
+                "java/lang/Object",
+                "java/lang/System",
+                "java/util/List",
+                "q/B",
+                "q/C",
+                "q/D",
+                "q/E",
+                "q/F"
+            ])
+        );
+
+        // @q/Marker(kind=q/Kind.X, type=q/Type.class, nested=@q/Nested) on the class
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        let name = builder.add_utf8("RuntimeVisibleAnnotations").unwrap();
+        let mut info = vec![0, 1];
+        let mut add = |info: &mut Vec<u8>, value: &str| {
+            info.extend(builder.add_utf8(value).unwrap().to_be_bytes())
+        };
+        add(&mut info, "Lq/Marker;");
+        info.extend([0, 3]);
+        add(&mut info, "kind");
+        info.push(b'e');
+        add(&mut info, "Lq/Kind;");
+        add(&mut info, "X");
+        add(&mut info, "type");
+        info.push(b'c');
+        add(&mut info, "Lq/Type;");
+        add(&mut info, "nested");
+        info.push(b'@');
+        add(&mut info, "Lq/Nested;");
+        info.extend([0, 0]);
+        let signature = builder.add_utf8("Signature").unwrap();
+        let generic = builder.add_utf8("Lq/Base<Lq/Param;>;").unwrap();
+        class_file.set_constant_pool(builder.build());
+        class_file.attributes_mut().extend([
+            AttributeInfo::new(name, info),
+            AttributeInfo::new(signature, generic.to_be_bytes().to_vec()),
+        ]);
+
+        let found = class_dependencies(&class_file).unwrap();
+        for class in [
+            "q/Marker", "q/Kind", "q/Type", "q/Nested", "q/Base", "q/Param",
+        ] {
+            assert!(found.contains(class), "{class} is missing from {found:?}");
+        }
+        assert!(!found.contains("p/A"));
+    }
+
+    #[test]
+    fn aggregates_and_finds_cycles() {
+        let mut graph = DependencyGraph::new();
+        for (from, to) in [
+            ("a/A", "b/B"),
+            ("b/B", "a/A2"),
+            ("a/A2", "java/lang/Object"),
+            ("c/C", "b/B"),
+            ("c/C", "c/D"),
+            ("c/D", "c/C"),
+        ] {
+            graph.add_dependency(from, to);
+        }
+
+        assert_eq!(
+            graph.cycles(),
+            vec![vec!["c/C".to_string(), "c/D".to_string()]]
+        );
+        let packages = graph.packages();
+        assert_eq!(packages.nodes().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+        assert_eq!(
+            packages.dependencies("a"),
+            Some(&classes(&["b", "java/lang"]))
+        );
+        assert_eq!(packages.dependencies("c"), Some(&classes(&["b"])));
+        assert_eq!(
+            packages.cycles(),
+            vec![vec!["a".to_string(), "b".to_string()]]
+        );
+        assert_eq!(
+            packages.internal().dependencies("a"),
+            Some(&classes(&["b"]))
+        );
+
+        let dot = packages.to_dot("packages");
+        assert!(dot.starts_with("digraph \"packages\" {\n"));
+        assert!(dot.contains("    \"a\" -> \"b\" [color=red];\n"));
+        assert!(dot.contains("    \"a\" -> \"java/lang\";\n"));
+        assert!(dot.contains("    \"c\" -> \"b\";\n"));
+
+        let json = packages.internal().to_json();
+        let cycles = json.get("cycles").and_then(JsonValue::as_array).unwrap();
+        assert_eq!(cycles, &[JsonValue::Array(vec!["a".into(), "b".into()])]);
+        let nodes = json.get("nodes").and_then(JsonValue::as_array).unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[2].get("name"), Some(&"c".into()));
+        assert_eq!(
+            nodes[2].get("dependencies"),
+            Some(&JsonValue::Array(vec!["b".into()]))
+        );
+    }
+}
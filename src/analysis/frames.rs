@@ -0,0 +1,1555 @@
+use crate::analysis::cfg::{ControlFlowGraph, EdgeKind};
+use crate::analysis::hierarchy::{ClassHierarchy, OBJECT, THROWABLE};
+use crate::bytecode::instruction::Instruction;
+use crate::bytecode::opcode::{ALOAD_3, ASTORE_3, ILOAD_0, ISTORE_0};
+use crate::class_file::access_flags::ACC_STATIC;
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::attribute::local_variable_table::LocalVariableTable;
+use crate::class_file::attribute::stack_map_table::{
+    StackMapFrame, StackMapTable, VerificationType,
+};
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::descriptor::{FieldType, MethodDescriptor};
+use crate::class_file::member_info::MethodInfo;
+use std::fmt;
+use std::io;
+
+// Computes StackMapTable frames, max_stack and max_locals by abstract interpretation over
+// the control-flow graph (section 4.10.1). Where paths merge, locals of different types
+// become top and references become their common superclass, which is what a
+// `ClassHierarchy` is for. Long and double values take two slots, the second one top, both
+// in locals and on the stack, so the stack instructions can treat them as raw slots.
+
+/// The type of a local variable or stack slot
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// Created by the `new` instruction at this pc, before its constructor has run
+    Uninitialized(u32),
+    /// A class name, or an array descriptor such as `[I`, as in CONSTANT_Class entries
+    Reference(String),
+    /// Pushed by `jsr` to the subroutine at this pc, which only class files before version 50
+    /// can use
+    ReturnAddress(u32),
+}
+
+impl ValueType {
+    pub fn from_field_type(field_type: &FieldType) -> ValueType {
+        match field_type {
+            FieldType::Byte
+            | FieldType::Char
+            | FieldType::Int
+            | FieldType::Short
+            | FieldType::Boolean => ValueType::Integer,
+            FieldType::Float => ValueType::Float,
+            FieldType::Long => ValueType::Long,
+            FieldType::Double => ValueType::Double,
+            FieldType::Object(name) => ValueType::Reference(name.clone()),
+            FieldType::Array(_) => ValueType::Reference(field_type.descriptor()),
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            ValueType::Null
+                | ValueType::UninitializedThis
+                | ValueType::Uninitialized(_)
+                | ValueType::Reference(_)
+        )
+    }
+
+    /// Whether a value of this type can be used where `to` is expected (section 4.10.1.2)
+    pub fn is_assignable_to(&self, to: &ValueType, hierarchy: &dyn ClassHierarchy) -> bool {
+        match (self, to) {
+            _ if self == to => true,
+            (_, ValueType::Top) => true,
+            (ValueType::Null, ValueType::Reference(_)) => true,
+            (ValueType::Reference(from), ValueType::Reference(to)) => {
+                hierarchy.is_assignable(from, to)
+            }
+            _ => false,
+        }
+    }
+
+    /// Long and double, which take two slots
+    pub fn is_wide(&self) -> bool {
+        matches!(self, ValueType::Long | ValueType::Double)
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::Top => write!(f, "top"),
+            ValueType::Integer => write!(f, "int"),
+            ValueType::Float => write!(f, "float"),
+            ValueType::Long => write!(f, "long"),
+            ValueType::Double => write!(f, "double"),
+            ValueType::Null => write!(f, "null"),
+            ValueType::UninitializedThis => write!(f, "uninitialized this"),
+            ValueType::Uninitialized(pc) => write!(f, "uninitialized {pc}"),
+            ValueType::Reference(name) => write!(f, "{name}"),
+            ValueType::ReturnAddress(pc) => write!(f, "return address {pc}"),
+        }
+    }
+}
+
+/// The locals and operand stack at some pc, one entry per slot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    locals: Vec<ValueType>,
+    stack: Vec<ValueType>,
+}
+
+impl Frame {
+    pub fn new(locals: Vec<ValueType>, stack: Vec<ValueType>) -> Frame {
+        Frame { locals, stack }
+    }
+
+    /// The frame on entry to a method: `this` (uninitialized in a constructor) followed by
+    /// the parameters
+    pub fn initial(
+        class_name: &str,
+        access_flags: u16,
+        method_name: &str,
+        descriptor: &MethodDescriptor,
+    ) -> Frame {
+        let mut locals = Vec::new();
+        if access_flags & ACC_STATIC == 0 {
+            locals.push(if method_name == "<init>" && class_name != OBJECT {
+                ValueType::UninitializedThis
+            } else {
+                ValueType::Reference(class_name.to_string())
+            });
+        }
+        for parameter in descriptor.parameters() {
+            let value = ValueType::from_field_type(parameter);
+            let wide = value.is_wide();
+            locals.push(value);
+            if wide {
+                locals.push(ValueType::Top);
+            }
+        }
+        Frame::new(locals, Vec::new())
+    }
+
+    pub fn locals(&self) -> &[ValueType] {
+        &self.locals
+    }
+
+    pub fn stack(&self) -> &[ValueType] {
+        &self.stack
+    }
+
+    fn push(&mut self, value: ValueType) {
+        let wide = value.is_wide();
+        self.stack.push(value);
+        if wide {
+            self.stack.push(ValueType::Top);
+        }
+    }
+
+    fn pop_slot(&mut self) -> Result<ValueType, io::Error> {
+        self.stack
+            .pop()
+            .ok_or_else(|| type_error("the operand stack is empty"))
+    }
+
+    /// Pops a value of the given kind, both slots for a long or double
+    fn pop(&mut self, expected: &ValueType) -> Result<ValueType, io::Error> {
+        if expected.is_wide() {
+            self.pop_slot()?;
+        }
+        let value = self.pop_slot()?;
+        let matches = match expected {
+            ValueType::Reference(_) => value.is_reference(),
+            _ => value == *expected,
+        };
+        if !matches {
+            let expected = match expected {
+                ValueType::Reference(_) => "a reference".to_string(),
+                _ => expected.to_string(),
+            };
+            return Err(type_error(&format!(
+                "expected {expected} on the stack but found {value}"
+            )));
+        }
+        Ok(value)
+    }
+
+    fn pop_reference(&mut self) -> Result<ValueType, io::Error> {
+        self.pop(&ValueType::Reference(String::new()))
+    }
+
+    fn load(&mut self, index: u16, expected: ValueType) -> Result<(), io::Error> {
+        let value = self
+            .locals
+            .get(index as usize)
+            .cloned()
+            .unwrap_or(ValueType::Top);
+        let matches = match expected {
+            ValueType::Reference(_) => value.is_reference(),
+            _ => value == expected,
+        };
+        if !matches {
+            return Err(type_error(&format!(
+                "local {index} holds {value}, not {expected}"
+            )));
+        }
+        self.push(value);
+        Ok(())
+    }
+
+    fn store(&mut self, index: u16, value: ValueType) {
+        let index = index as usize;
+        let wide = value.is_wide();
+        let end = index + if wide { 2 } else { 1 };
+        if self.locals.len() < end {
+            self.locals.resize(end, ValueType::Top);
+        }
+        // overwriting either half of a long or double destroys it
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = ValueType::Top;
+        }
+        if self.locals[end - 1].is_wide() && end < self.locals.len() {
+            self.locals[end] = ValueType::Top;
+        }
+        self.locals[index] = value;
+        if wide {
+            self.locals[index + 1] = ValueType::Top;
+        }
+    }
+
+    fn pop_and_store(&mut self, index: u16, expected: ValueType) -> Result<(), io::Error> {
+        // astore also stores the return addresses jsr pushes
+        let value = match self.stack.last() {
+            Some(ValueType::ReturnAddress(_)) if matches!(expected, ValueType::Reference(_)) => {
+                self.pop_slot()?
+            }
+            _ => self.pop(&expected)?,
+        };
+        self.store(index, value);
+        Ok(())
+    }
+
+    /// Replaces an uninitialized object with the initialized one everywhere, after its
+    /// constructor is called
+    fn initialize(&mut self, uninitialized: &ValueType, initialized: ValueType) {
+        for slot in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if slot == uninitialized {
+                *slot = initialized.clone();
+            }
+        }
+    }
+}
+
+/// The local an instruction loads, stores, increments or returns through, and whether it's a
+/// long or double that takes the next one too
+pub(crate) fn local_access(instruction: &Instruction) -> Option<(u16, bool)> {
+    let index = instruction
+        .local_index()
+        .or_else(|| implicit_local_index(instruction))?;
+    let wide = matches!(
+        instruction,
+        Instruction::Lload(_)
+            | Instruction::Dload(_)
+            | Instruction::Lstore(_)
+            | Instruction::Dstore(_)
+            | Instruction::Lload0
+            | Instruction::Lload1
+            | Instruction::Lload2
+            | Instruction::Lload3
+            | Instruction::Dload0
+            | Instruction::Dload1
+            | Instruction::Dload2
+            | Instruction::Dload3
+            | Instruction::Lstore0
+            | Instruction::Lstore1
+            | Instruction::Lstore2
+            | Instruction::Lstore3
+            | Instruction::Dstore0
+            | Instruction::Dstore1
+            | Instruction::Dstore2
+            | Instruction::Dstore3
+    );
+    Some((index, wide))
+}
+
+/// The local of the `iload_0`-`aload_3` and `istore_0`-`astore_3` forms
+fn implicit_local_index(instruction: &Instruction) -> Option<u16> {
+    match instruction.opcode() {
+        opcode @ ILOAD_0..=ALOAD_3 => Some(((opcode - ILOAD_0) % 4) as u16),
+        opcode @ ISTORE_0..=ASTORE_3 => Some(((opcode - ISTORE_0) % 4) as u16),
+        _ => None,
+    }
+}
+
+/// An instruction that doesn't fit the types in its frame
+fn type_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn frame_error(pc: u32, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Cannot compute frames: {message} at pc {pc}"),
+    )
+}
+
+/// Applies instructions to frames, resolving types from the constant pool
+pub(crate) struct Interpreter<'a> {
+    constant_pool: &'a ConstantPool,
+    class_name: &'a str,
+    instructions: &'a [(u32, Instruction)],
+    /// Set when checking that operands are assignable to the types instructions expect,
+    /// rather than only inferring the types they produce
+    hierarchy: Option<&'a dyn ClassHierarchy>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub(crate) fn new(
+        constant_pool: &'a ConstantPool,
+        class_name: &'a str,
+        instructions: &'a [(u32, Instruction)],
+    ) -> Interpreter<'a> {
+        Interpreter {
+            constant_pool,
+            class_name,
+            instructions,
+            hierarchy: None,
+        }
+    }
+
+    /// Checks operand types as the verifier does (section 4.10.1.9)
+    pub(crate) fn checking(mut self, hierarchy: &'a dyn ClassHierarchy) -> Interpreter<'a> {
+        self.hierarchy = Some(hierarchy);
+        self
+    }
+
+    /// Pops a value of the kind of `expected`, which when checking must be assignable to it
+    fn pop_expected(
+        &self,
+        frame: &mut Frame,
+        expected: &ValueType,
+    ) -> Result<ValueType, io::Error> {
+        let value = frame.pop(expected)?;
+        match self.hierarchy {
+            Some(hierarchy) if !value.is_assignable_to(expected, hierarchy) => Err(type_error(
+                &format!("expected {expected} on the stack but found {value}"),
+            )),
+            _ => Ok(value),
+        }
+    }
+
+    /// When checking, `array` has to be null or an array whose element descriptor passes
+    /// `element`
+    fn check_array(
+        &self,
+        instruction: &Instruction,
+        array: &ValueType,
+        element: impl Fn(&str) -> bool,
+    ) -> Result<(), io::Error> {
+        match array {
+            _ if self.hierarchy.is_none() => Ok(()),
+            ValueType::Null => Ok(()),
+            ValueType::Reference(name) if name.strip_prefix('[').is_some_and(element) => Ok(()),
+            _ => Err(type_error(&format!(
+                "{} on {array}, which is not a suitable array",
+                instruction.mnemonic()
+            ))),
+        }
+    }
+
+    fn member_descriptor(&self, index: u16) -> Result<(String, String, String), io::Error> {
+        self.constant_pool.member_ref(index)
+    }
+
+    fn class_type(&self, index: u16) -> Result<ValueType, io::Error> {
+        Ok(ValueType::Reference(self.constant_pool.class_name(index)?))
+    }
+
+    fn constant_type(&self, index: u16) -> Result<ValueType, io::Error> {
+        Ok(
+            match self.constant_pool.get(index).map(|item| item.data()) {
+                Some(ConstantPoolData::Integer(_)) => ValueType::Integer,
+                Some(ConstantPoolData::Float(_)) => ValueType::Float,
+                Some(ConstantPoolData::Long(_)) => ValueType::Long,
+                Some(ConstantPoolData::Double(_)) => ValueType::Double,
+                Some(ConstantPoolData::String(_)) => {
+                    ValueType::Reference("java/lang/String".into())
+                }
+                Some(ConstantPoolData::ClassInfo(_)) => {
+                    ValueType::Reference("java/lang/Class".into())
+                }
+                Some(ConstantPoolData::MethodType(_)) => {
+                    ValueType::Reference("java/lang/invoke/MethodType".into())
+                }
+                Some(ConstantPoolData::MethodHandle(_)) => {
+                    ValueType::Reference("java/lang/invoke/MethodHandle".into())
+                }
+                _ => {
+                    return Err(type_error(&format!(
+                        "constant pool entry {index} can't be loaded"
+                    )))
+                }
+            },
+        )
+    }
+
+    fn invoke(
+        &self,
+        frame: &mut Frame,
+        descriptor: &str,
+        receiver: Option<&ValueType>,
+    ) -> Result<Option<ValueType>, io::Error> {
+        let descriptor = MethodDescriptor::parse(descriptor)?;
+        for parameter in descriptor.parameters().iter().rev() {
+            self.pop_expected(frame, &ValueType::from_field_type(parameter))?;
+        }
+        let receiver = match receiver {
+            Some(expected) => Some(self.pop_expected(frame, expected)?),
+            None => None,
+        };
+        if let Some(return_type) = descriptor.return_type() {
+            frame.push(ValueType::from_field_type(return_type));
+        }
+        Ok(receiver)
+    }
+
+    /// Updates `frame` to the state after the instruction at `pc`
+    pub(crate) fn execute(
+        &self,
+        pc: u32,
+        instruction: &Instruction,
+        frame: &mut Frame,
+    ) -> Result<(), io::Error> {
+        use Instruction::*;
+        use ValueType::{Double as D, Float as F, Integer as I, Long as L};
+        let object = || ValueType::Reference(OBJECT.to_string());
+        match instruction {
+            Nop | Goto(_) | GotoW(_) | Return => {}
+            AconstNull => frame.push(ValueType::Null),
+            IconstM1 | Iconst0 | Iconst1 | Iconst2 | Iconst3 | Iconst4 | Iconst5 | Bipush(_)
+            | Sipush(_) => frame.push(I),
+            Lconst0 | Lconst1 => frame.push(L),
+            Fconst0 | Fconst1 | Fconst2 => frame.push(F),
+            Dconst0 | Dconst1 => frame.push(D),
+            Ldc(index) | LdcW(index) | Ldc2W(index) => frame.push(self.constant_type(*index)?),
+
+            Iload(n) => frame.load(*n, I)?,
+            Lload(n) => frame.load(*n, L)?,
+            Fload(n) => frame.load(*n, F)?,
+            Dload(n) => frame.load(*n, D)?,
+            Aload(n) => frame.load(*n, object())?,
+            Iload0 | Iload1 | Iload2 | Iload3 | Lload0 | Lload1 | Lload2 | Lload3 | Fload0
+            | Fload1 | Fload2 | Fload3 | Dload0 | Dload1 | Dload2 | Dload3 | Aload0 | Aload1
+            | Aload2 | Aload3 => {
+                let n = implicit_local_index(instruction).unwrap_or_default();
+                let expected = match instruction {
+                    Iload0 | Iload1 | Iload2 | Iload3 => I,
+                    Lload0 | Lload1 | Lload2 | Lload3 => L,
+                    Fload0 | Fload1 | Fload2 | Fload3 => F,
+                    Dload0 | Dload1 | Dload2 | Dload3 => D,
+                    _ => object(),
+                };
+                frame.load(n, expected)?;
+            }
+
+            Iaload | Baload | Caload | Saload | Laload | Faload | Daload | Aaload => {
+                frame.pop(&I)?;
+                let array = frame.pop_reference()?;
+                self.check_array(instruction, &array, |element| match instruction {
+                    Iaload => element == "I",
+                    Baload => element == "B" || element == "Z",
+                    Caload => element == "C",
+                    Saload => element == "S",
+                    Laload => element == "J",
+                    Faload => element == "F",
+                    Daload => element == "D",
+                    _ => element.starts_with(['L', '[']),
+                })?;
+                frame.push(match instruction {
+                    Laload => L,
+                    Faload => F,
+                    Daload => D,
+                    Aaload => match &array {
+                        ValueType::Null => ValueType::Null,
+                        ValueType::Reference(name) => component_type(name)
+                            .filter(ValueType::is_reference)
+                            .ok_or_else(|| {
+                                type_error(&format!("aaload from {name}, not an array"))
+                            })?,
+                        _ => return Err(type_error(&format!("aaload from {array}"))),
+                    },
+                    _ => I,
+                });
+            }
+
+            Istore(n) => frame.pop_and_store(*n, I)?,
+            Lstore(n) => frame.pop_and_store(*n, L)?,
+            Fstore(n) => frame.pop_and_store(*n, F)?,
+            Dstore(n) => frame.pop_and_store(*n, D)?,
+            Astore(n) => frame.pop_and_store(*n, object())?,
+            Istore0 | Istore1 | Istore2 | Istore3 | Lstore0 | Lstore1 | Lstore2 | Lstore3
+            | Fstore0 | Fstore1 | Fstore2 | Fstore3 | Dstore0 | Dstore1 | Dstore2 | Dstore3
+            | Astore0 | Astore1 | Astore2 | Astore3 => {
+                let n = implicit_local_index(instruction).unwrap_or_default();
+                let expected = match instruction {
+                    Istore0 | Istore1 | Istore2 | Istore3 => I,
+                    Lstore0 | Lstore1 | Lstore2 | Lstore3 => L,
+                    Fstore0 | Fstore1 | Fstore2 | Fstore3 => F,
+                    Dstore0 | Dstore1 | Dstore2 | Dstore3 => D,
+                    _ => object(),
+                };
+                frame.pop_and_store(n, expected)?;
+            }
+
+            Iastore | Lastore | Fastore | Dastore | Aastore | Bastore | Castore | Sastore => {
+                let value = match instruction {
+                    Lastore => L,
+                    Fastore => F,
+                    Dastore => D,
+                    Aastore => object(),
+                    _ => I,
+                };
+                self.pop_expected(frame, &value)?;
+                frame.pop(&I)?;
+                let array = frame.pop_reference()?;
+                self.check_array(instruction, &array, |element| match instruction {
+                    Iastore => element == "I",
+                    Bastore => element == "B" || element == "Z",
+                    Castore => element == "C",
+                    Sastore => element == "S",
+                    Lastore => element == "J",
+                    Fastore => element == "F",
+                    Dastore => element == "D",
+                    _ => element.starts_with(['L', '[']),
+                })?;
+            }
+
+            // the stack instructions work on slots, which is why wide values take two
+            Pop => {
+                frame.pop_slot()?;
+            }
+            Pop2 => {
+                frame.pop_slot()?;
+                frame.pop_slot()?;
+            }
+            Dup | DupX1 | DupX2 | Dup2 | Dup2X1 | Dup2X2 | Swap => {
+                let (count, depth) = match instruction {
+                    Dup => (1, 0),
+                    DupX1 => (1, 1),
+                    DupX2 => (1, 2),
+                    Dup2 => (2, 0),
+                    Dup2X1 => (2, 1),
+                    Dup2X2 => (2, 2),
+                    _ => (0, 0),
+                };
+                let length = frame.stack.len();
+                let needed = if count == 0 { 2 } else { count + depth };
+                if length < needed {
+                    return Err(type_error("the operand stack is too shallow"));
+                }
+                if count == 0 {
+                    frame.stack.swap(length - 1, length - 2);
+                } else {
+                    let top: Vec<ValueType> = frame.stack[length - count..].to_vec();
+                    let at = length - count - depth;
+                    frame.stack.splice(at..at, top);
+                }
+            }
+
+            Iadd | Isub | Imul | Idiv | Irem | Ishl | Ishr | Iushr | Iand | Ior | Ixor => {
+                frame.pop(&I)?;
+                frame.pop(&I)?;
+                frame.push(I);
+            }
+            Ladd | Lsub | Lmul | Ldiv | Lrem | Land | Lor | Lxor => {
+                frame.pop(&L)?;
+                frame.pop(&L)?;
+                frame.push(L);
+            }
+            Lshl | Lshr | Lushr => {
+                frame.pop(&I)?;
+                frame.pop(&L)?;
+                frame.push(L);
+            }
+            Fadd | Fsub | Fmul | Fdiv | Frem => {
+                frame.pop(&F)?;
+                frame.pop(&F)?;
+                frame.push(F);
+            }
+            Dadd | Dsub | Dmul | Ddiv | Drem => {
+                frame.pop(&D)?;
+                frame.pop(&D)?;
+                frame.push(D);
+            }
+            Ineg | Lneg | Fneg | Dneg | I2l | I2f | I2d | L2i | L2f | L2d | F2i | F2l | F2d
+            | D2i | D2l | D2f | I2b | I2c | I2s => {
+                let (from, to) = match instruction {
+                    Ineg | I2b | I2c | I2s => (I, I),
+                    Lneg => (L, L),
+                    Fneg => (F, F),
+                    Dneg => (D, D),
+                    I2l => (I, L),
+                    I2f => (I, F),
+                    I2d => (I, D),
+                    L2i => (L, I),
+                    L2f => (L, F),
+                    L2d => (L, D),
+                    F2i => (F, I),
+                    F2l => (F, L),
+                    F2d => (F, D),
+                    D2i => (D, I),
+                    D2l => (D, L),
+                    _ => (D, F),
+                };
+                frame.pop(&from)?;
+                frame.push(to);
+            }
+            Iinc { index, .. } => {
+                frame.load(*index, I)?;
+                frame.pop_slot()?;
+            }
+            Lcmp | Fcmpl | Fcmpg | Dcmpl | Dcmpg => {
+                let operand = match instruction {
+                    Lcmp => L,
+                    Fcmpl | Fcmpg => F,
+                    _ => D,
+                };
+                frame.pop(&operand)?;
+                frame.pop(&operand)?;
+                frame.push(I);
+            }
+
+            Ifeq(_) | Ifne(_) | Iflt(_) | Ifge(_) | Ifgt(_) | Ifle(_) => {
+                frame.pop(&I)?;
+            }
+            IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_) => {
+                frame.pop(&I)?;
+                frame.pop(&I)?;
+            }
+            IfAcmpeq(_) | IfAcmpne(_) => {
+                frame.pop_reference()?;
+                frame.pop_reference()?;
+            }
+            Ifnull(_) | Ifnonnull(_) | Monitorenter | Monitorexit | Areturn => {
+                frame.pop_reference()?;
+            }
+            Athrow => {
+                self.pop_expected(frame, &ValueType::Reference(THROWABLE.to_string()))?;
+            }
+            Tableswitch { .. } | Lookupswitch { .. } | Ireturn => {
+                frame.pop(&I)?;
+            }
+            Lreturn => {
+                frame.pop(&L)?;
+            }
+            Freturn => {
+                frame.pop(&F)?;
+            }
+            Dreturn => {
+                frame.pop(&D)?;
+            }
+            Jsr(target) | JsrW(target) => frame.push(ValueType::ReturnAddress(*target)),
+            Ret(index) => match frame.locals.get(*index as usize) {
+                Some(ValueType::ReturnAddress(_)) => {}
+                value => {
+                    return Err(type_error(&format!(
+                        "ret with local {index}, which holds {}, not a return address",
+                        value.unwrap_or(&ValueType::Top)
+                    )))
+                }
+            },
+
+            Getstatic(index) | Getfield(index) => {
+                let (class, _, descriptor) = self.member_descriptor(*index)?;
+                if matches!(instruction, Getfield(_)) {
+                    self.pop_expected(frame, &ValueType::Reference(class))?;
+                }
+                frame.push(ValueType::from_field_type(&FieldType::parse(&descriptor)?));
+            }
+            Putstatic(index) | Putfield(index) => {
+                let (class, _, descriptor) = self.member_descriptor(*index)?;
+                let value = ValueType::from_field_type(&FieldType::parse(&descriptor)?);
+                self.pop_expected(frame, &value)?;
+                if matches!(instruction, Putfield(_)) {
+                    // constructors may set their own fields before calling super()
+                    if frame.stack.last() == Some(&ValueType::UninitializedThis)
+                        && class == self.class_name
+                    {
+                        frame.pop_slot()?;
+                    } else {
+                        self.pop_expected(frame, &ValueType::Reference(class))?;
+                    }
+                }
+            }
+            Invokevirtual(index) | Invokeinterface { index, .. } => {
+                let (class, _, descriptor) = self.member_descriptor(*index)?;
+                self.invoke(frame, &descriptor, Some(&ValueType::Reference(class)))?;
+            }
+            Invokestatic(index) => {
+                let (_, _, descriptor) = self.member_descriptor(*index)?;
+                self.invoke(frame, &descriptor, None)?;
+            }
+            Invokespecial(index) => {
+                let (class, name, descriptor) = self.member_descriptor(*index)?;
+                if name != "<init>" {
+                    // private and super calls are made on the current class
+                    let receiver = ValueType::Reference(self.class_name.to_string());
+                    self.invoke(frame, &descriptor, Some(&receiver))?;
+                    return Ok(());
+                }
+                self.invoke(frame, &descriptor, None)?;
+                let receiver = frame.pop_reference()?;
+                let initialized = match &receiver {
+                    ValueType::UninitializedThis => {
+                        let super_class =
+                            self.hierarchy.and_then(|h| h.super_class(self.class_name));
+                        if class != self.class_name
+                            && super_class.is_some_and(|super_class| class != super_class)
+                        {
+                            return Err(type_error(&format!(
+                                "{class}.<init> called on this, which is a {}",
+                                self.class_name
+                            )));
+                        }
+                        ValueType::Reference(self.class_name.to_string())
+                    }
+                    ValueType::Uninitialized(new_pc) => {
+                        let created = self.created_type(*new_pc)?;
+                        if self.hierarchy.is_some()
+                            && created != ValueType::Reference(class.clone())
+                        {
+                            return Err(type_error(&format!(
+                                "{class}.<init> called on a new {created}"
+                            )));
+                        }
+                        created
+                    }
+                    _ => {
+                        return Err(type_error(&format!(
+                            "{class}.<init> called on {receiver}, which is not uninitialized"
+                        )))
+                    }
+                };
+                // every copy of the uninitialized object is now initialized
+                frame.initialize(&receiver, initialized);
+            }
+            Invokedynamic(index) => {
+                let descriptor = match self.constant_pool.get(*index).map(|item| item.data()) {
+                    Some(ConstantPoolData::InvokeDynamic(indy)) => {
+                        self.constant_pool
+                            .name_and_type(indy.name_and_type_index())?
+                            .1
+                    }
+                    _ => return Err(type_error("invokedynamic without a call site")),
+                };
+                self.invoke(frame, &descriptor, None)?;
+            }
+
+            New(_) => frame.push(ValueType::Uninitialized(pc)),
+            Newarray(array_type) => {
+                frame.pop(&I)?;
+                frame.push(ValueType::Reference(array_type.descriptor().to_string()));
+            }
+            Anewarray(index) => {
+                frame.pop(&I)?;
+                let component = self.constant_pool.class_name(*index)?;
+                frame.push(ValueType::Reference(array_of(&component)));
+            }
+            Multianewarray { index, dimensions } => {
+                for _ in 0..*dimensions {
+                    frame.pop(&I)?;
+                }
+                frame.push(self.class_type(*index)?);
+            }
+            Arraylength => {
+                let array = frame.pop_reference()?;
+                self.check_array(instruction, &array, |_| true)?;
+                frame.push(I);
+            }
+            Checkcast(index) => {
+                frame.pop_reference()?;
+                frame.push(self.class_type(*index)?);
+            }
+            Instanceof(_) => {
+                frame.pop_reference()?;
+                frame.push(I);
+            }
+        }
+        Ok(())
+    }
+
+    /// The class of the object created by the `new` at `new_pc`
+    fn created_type(&self, new_pc: u32) -> Result<ValueType, io::Error> {
+        match self
+            .instructions
+            .binary_search_by_key(&new_pc, |(pc, _)| *pc)
+            .map(|i| &self.instructions[i].1)
+        {
+            Ok(Instruction::New(index)) => self.class_type(*index),
+            _ => Err(type_error(&format!(
+                "uninitialized object from pc {new_pc}, which is not a new"
+            ))),
+        }
+    }
+}
+
+/// The class name of an array of `component`, which is itself a class name or descriptor
+fn array_of(component: &str) -> String {
+    if component.starts_with('[') {
+        format!("[{component}")
+    } else {
+        format!("[L{component};")
+    }
+}
+
+/// The element type of an array type such as `[I` or `[Ljava/lang/String;`
+fn component_type(array: &str) -> Option<ValueType> {
+    let component = array.strip_prefix('[')?;
+    FieldType::parse(component)
+        .ok()
+        .map(|field_type| ValueType::from_field_type(&field_type))
+}
+
+/// The most specific type both can be assigned to, or top if there is none
+fn merge_values(a: &ValueType, b: &ValueType, hierarchy: &dyn ClassHierarchy) -> ValueType {
+    match (a, b) {
+        _ if a == b => a.clone(),
+        (ValueType::Null, ValueType::Reference(_)) => b.clone(),
+        (ValueType::Reference(_), ValueType::Null) => a.clone(),
+        (ValueType::Reference(a), ValueType::Reference(b)) => {
+            ValueType::Reference(merge_references(a, b, hierarchy))
+        }
+        _ => ValueType::Top,
+    }
+}
+
+fn merge_references(a: &str, b: &str, hierarchy: &dyn ClassHierarchy) -> String {
+    match (a.strip_prefix('['), b.strip_prefix('[')) {
+        (None, None) => hierarchy.common_super_class(a, b),
+        // arrays of references merge element-wise; int[] and long[] only share Object
+        (Some(a), Some(b)) => match (
+            component_type(&format!("[{a}")),
+            component_type(&format!("[{b}")),
+        ) {
+            (Some(ValueType::Reference(a)), Some(ValueType::Reference(b))) => {
+                array_of(&merge_references(&a, &b, hierarchy))
+            }
+            _ => OBJECT.to_string(),
+        },
+        _ => OBJECT.to_string(),
+    }
+}
+
+/// Merges `incoming` into a frame, returning whether it changed
+pub(crate) fn merge_into(
+    target: &mut Option<Frame>,
+    incoming: &Frame,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<bool, io::Error> {
+    let Some(frame) = target else {
+        *target = Some(incoming.clone());
+        return Ok(true);
+    };
+    if frame.stack.len() != incoming.stack.len() {
+        return Err(type_error(&format!(
+            "paths meet with stack heights {} and {}",
+            frame.stack.len(),
+            incoming.stack.len()
+        )));
+    }
+    let mut changed = false;
+    for (slot, value) in frame.stack.iter_mut().zip(incoming.stack.iter()) {
+        let merged = merge_values(slot, value, hierarchy);
+        if merged == ValueType::Top && *slot != ValueType::Top {
+            return Err(type_error(&format!(
+                "paths meet with {slot} and {value} on the stack"
+            )));
+        }
+        changed |= merged != *slot;
+        *slot = merged;
+    }
+    // locals only one path has are top afterwards
+    if frame.locals.len() > incoming.locals.len() {
+        frame.locals.truncate(incoming.locals.len());
+        changed = true;
+    }
+    for (slot, value) in frame.locals.iter_mut().zip(incoming.locals.iter()) {
+        let merged = merge_values(slot, value, hierarchy);
+        changed |= merged != *slot;
+        *slot = merged;
+    }
+    Ok(changed)
+}
+
+/// The result of `compute_frames`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodFrames {
+    max_stack: u16,
+    max_locals: u16,
+    initial: Frame,
+    frames: Vec<(u32, Frame)>,
+    unreachable: Vec<u32>,
+}
+
+impl MethodFrames {
+    pub fn max_stack(&self) -> u16 {
+        self.max_stack
+    }
+
+    pub fn max_locals(&self) -> u16 {
+        self.max_locals
+    }
+
+    /// The implicit frame at pc 0
+    pub fn initial(&self) -> &Frame {
+        &self.initial
+    }
+
+    /// The frames a StackMapTable needs: at every branch target and handler, and after every
+    /// unconditional jump, return or throw
+    pub fn frames(&self) -> &[(u32, Frame)] {
+        &self.frames
+    }
+
+    /// Starts of blocks that can't be reached. Stack map frames can't be computed for them,
+    /// so they have to be removed before writing a class file version 50 or later.
+    pub fn unreachable(&self) -> &[u32] {
+        &self.unreachable
+    }
+
+    /// Encodes the frames, adding Class entries for reference types to the constant pool
+    pub fn stack_map_table(
+        &self,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<StackMapTable, io::Error> {
+        if let Some(pc) = self.unreachable.first() {
+            return Err(frame_error(*pc, "unreachable code needs a frame"));
+        }
+        let mut previous_locals = compact(&self.initial.locals, true);
+        let mut previous_pc: Option<u32> = None;
+        let mut entries = Vec::with_capacity(self.frames.len());
+        for (pc, frame) in self.frames.iter() {
+            let offset_delta = match previous_pc {
+                None => *pc,
+                Some(previous) => pc - previous - 1,
+            } as u16;
+            let locals = compact(&frame.locals, true);
+            let stack = compact(&frame.stack, false);
+            let same_locals = locals == previous_locals;
+            entries.push(match stack.len() {
+                0 if same_locals => StackMapFrame::same(offset_delta),
+                1 if same_locals => StackMapFrame::same_locals_1_stack_item(
+                    offset_delta,
+                    verification_type(stack[0], builder)?,
+                ),
+                0 if locals.len() < previous_locals.len()
+                    && previous_locals.len() - locals.len() <= 3
+                    && previous_locals.starts_with(&locals) =>
+                {
+                    StackMapFrame::Chop {
+                        offset_delta,
+                        absent: (previous_locals.len() - locals.len()) as u8,
+                    }
+                }
+                0 if locals.len() > previous_locals.len()
+                    && locals.len() - previous_locals.len() <= 3
+                    && locals.starts_with(&previous_locals) =>
+                {
+                    StackMapFrame::Append {
+                        offset_delta,
+                        locals: verification_types(&locals[previous_locals.len()..], builder)?,
+                    }
+                }
+                _ => StackMapFrame::Full {
+                    offset_delta,
+                    locals: verification_types(&locals, builder)?,
+                    stack: verification_types(&stack, builder)?,
+                },
+            });
+            previous_locals = locals;
+            previous_pc = Some(*pc);
+        }
+        Ok(StackMapTable::new(entries))
+    }
+}
+
+/// One entry per value rather than per slot, as verification types are listed, without
+/// trailing top locals if `trim` is set
+fn compact(slots: &[ValueType], trim: bool) -> Vec<&ValueType> {
+    let mut values = Vec::with_capacity(slots.len());
+    let mut i = 0;
+    while i < slots.len() {
+        values.push(&slots[i]);
+        i += if slots[i].is_wide() { 2 } else { 1 };
+    }
+    if trim {
+        while values.last() == Some(&&ValueType::Top) {
+            values.pop();
+        }
+    }
+    values
+}
+
+/// Adds a Class entry to the constant pool for reference types
+fn verification_type(
+    value: &ValueType,
+    builder: &mut ConstantPoolBuilder,
+) -> Result<VerificationType, io::Error> {
+    Ok(match value {
+        ValueType::Top => VerificationType::Top,
+        ValueType::Integer => VerificationType::Integer,
+        ValueType::Float => VerificationType::Float,
+        ValueType::Long => VerificationType::Long,
+        ValueType::Double => VerificationType::Double,
+        ValueType::Null => VerificationType::Null,
+        ValueType::UninitializedThis => VerificationType::UninitializedThis,
+        ValueType::Uninitialized(pc) => VerificationType::Uninitialized(*pc as u16),
+        ValueType::Reference(name) => VerificationType::Object(builder.add_class(name)?),
+        // compute_frames rejects jsr, so there are none of these
+        ValueType::ReturnAddress(_) => VerificationType::Top,
+    })
+}
+
+fn verification_types(
+    values: &[&ValueType],
+    builder: &mut ConstantPoolBuilder,
+) -> Result<Vec<VerificationType>, io::Error> {
+    values
+        .iter()
+        .map(|value| verification_type(value, builder))
+        .collect()
+}
+
+/// Computes the frames of one method of `class_file`
+pub fn compute_frames(
+    class_file: &ClassFile,
+    method: &MethodInfo,
+    code: &Code,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<MethodFrames, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let class_name = class_file.class_name()?;
+    let descriptor = MethodDescriptor::parse(&method.descriptor(constant_pool)?)?;
+    let initial = Frame::initial(
+        &class_name,
+        method.access_flags(),
+        &method.name(constant_pool)?,
+        &descriptor,
+    );
+
+    let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+    let cfg = ControlFlowGraph::from_code(code)?;
+    let interpreter = Interpreter::new(constant_pool, &class_name, &instructions);
+    let blocks = cfg.blocks();
+
+    let mut handlers = Vec::with_capacity(code.exception_table().len());
+    for entry in code.exception_table() {
+        let exception = match entry.catch_type() {
+            0 => THROWABLE.to_string(),
+            index => constant_pool.class_name(index)?,
+        };
+        // the cfg checked that every handler starts a block
+        let block = cfg.block_at(entry.handler_pc() as u32).unwrap_or_default();
+        handlers.push((entry, block, ValueType::Reference(exception)));
+    }
+
+    let mut states: Vec<Option<Frame>> = vec![None; blocks.len()];
+    states[0] = Some(initial.clone());
+    let mut work = vec![0];
+    let mut max_stack = 0;
+    while let Some(b) = work.pop() {
+        let Some(mut frame) = states[b].clone() else {
+            continue;
+        };
+        for (pc, instruction) in blocks[b].instructions() {
+            let before = frame.clone();
+            if matches!(
+                instruction,
+                Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_)
+            ) {
+                return Err(frame_error(
+                    *pc,
+                    "jsr and ret can't be described by stack map frames",
+                ));
+            }
+            interpreter
+                .execute(*pc, instruction, &mut frame)
+                .map_err(|e| frame_error(*pc, &e.to_string()))?;
+            max_stack = max_stack.max(before.stack.len()).max(frame.stack.len());
+
+            // a handler sees the locals from before or after any instruction it covers
+            for (entry, handler, exception) in handlers.iter() {
+                if !(entry.start_pc() as u32..entry.end_pc() as u32).contains(pc) {
+                    continue;
+                }
+                for locals in [&before.locals, &frame.locals] {
+                    let thrown = Frame::new(locals.clone(), vec![exception.clone()]);
+                    max_stack = max_stack.max(1);
+                    let handler_pc = entry.handler_pc() as u32;
+                    if merge_into(&mut states[*handler], &thrown, hierarchy)
+                        .map_err(|e| frame_error(handler_pc, &e.to_string()))?
+                    {
+                        work.push(*handler);
+                    }
+                }
+            }
+        }
+        for edge in cfg.successors(b) {
+            if matches!(edge.kind(), EdgeKind::FallThrough | EdgeKind::Branch) {
+                let to = edge.to();
+                if merge_into(&mut states[to], &frame, hierarchy)
+                    .map_err(|e| frame_error(blocks[to].start(), &e.to_string()))?
+                {
+                    work.push(to);
+                }
+            }
+        }
+    }
+
+    let mut frames = Vec::new();
+    let mut unreachable = Vec::new();
+    for (b, block) in blocks.iter().enumerate() {
+        // blocks only entered by falling through from the one before need no frame
+        let needs_frame = cfg.predecessors(b).iter().any(|edge| {
+            edge.from() != cfg.entry()
+                && !(edge.kind() == EdgeKind::FallThrough && edge.from() + 1 == b)
+        });
+        match &states[b] {
+            None => unreachable.push(block.start()),
+            Some(frame) if needs_frame => frames.push((block.start(), frame.clone())),
+            Some(_) => {}
+        }
+    }
+
+    let max_locals = instructions
+        .iter()
+        .filter_map(|(_, instruction)| {
+            let (index, wide) = local_access(instruction)?;
+            Some(index as usize + if wide { 2 } else { 1 })
+        })
+        .chain([initial.locals.len(), debug_locals(code, constant_pool)?])
+        .max()
+        .unwrap_or_default();
+
+    Ok(MethodFrames {
+        max_stack: u16::try_from(max_stack)
+            .map_err(|_| frame_error(0, "the operand stack is deeper than 65535"))?,
+        max_locals: u16::try_from(max_locals)
+            .map_err(|_| frame_error(0, "more than 65535 locals"))?,
+        initial,
+        frames,
+        unreachable,
+    })
+}
+
+/// The locals the LocalVariableTable and LocalVariableTypeTable describe, which must stay
+/// below max_locals even for variables the code no longer touches (section 4.7.13)
+fn debug_locals(code: &Code, constant_pool: &ConstantPool) -> Result<usize, io::Error> {
+    let mut max_locals = 0;
+    for attribute in code.attributes() {
+        let name = attribute.name(constant_pool)?;
+        if name != LocalVariableTable::NAME && name != LocalVariableTable::TYPE_TABLE_NAME {
+            continue;
+        }
+        let table = LocalVariableTable::from(&mut attribute.reader())?;
+        for local in table.local_variable_table() {
+            // type table entries are always references, never longs or doubles
+            let wide = name == LocalVariableTable::NAME
+                && matches!(
+                    constant_pool.utf8(local.descriptor_index())?.as_str(),
+                    "J" | "D"
+                );
+            max_locals = max_locals.max(local.index() as usize + if wide { 2 } else { 1 });
+        }
+    }
+    Ok(max_locals)
+}
+
+/// Recomputes max_stack, max_locals and the StackMapTable of every method with code. Frames
+/// are only written for class files version 50 and later, which are the ones that use them.
+pub fn recompute_frames(
+    class_file: &mut ClassFile,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let mut computed = Vec::new();
+    for (i, method) in class_file.methods().iter().enumerate() {
+        let code_attribute = method.attributes().iter().position(
+            |attribute| matches!(attribute.name(constant_pool), Ok(name) if name == Code::NAME),
+        );
+        if let (Some(code), Some(j)) = (method.code(constant_pool)?, code_attribute) {
+            let frames = compute_frames(class_file, method, &code, hierarchy).map_err(|e| {
+                let name = method.name(constant_pool).unwrap_or_default();
+                io::Error::new(e.kind(), format!("{name}: {e}"))
+            })?;
+            computed.push((i, j, code, frames));
+        }
+    }
+
+    let uses_frames = class_file.major_version() >= 50;
+    let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+    let updated = rebuild_code(computed, &mut builder, uses_frames);
+    // put the constant pool back even if a frame couldn't be encoded
+    class_file.set_constant_pool(builder.build());
+
+    for (i, j, code) in updated? {
+        let mut info = Vec::new();
+        code.write(&mut info);
+        let attribute = &mut class_file.methods_mut()[i].attributes_mut()[j];
+        *attribute = AttributeInfo::new(attribute.attribute_name_index(), info);
+    }
+    Ok(())
+}
+
+/// Code attributes with the computed maximums and frames in place of the old ones
+fn rebuild_code(
+    computed: Vec<(usize, usize, Code, MethodFrames)>,
+    builder: &mut ConstantPoolBuilder,
+    uses_frames: bool,
+) -> Result<Vec<(usize, usize, Code)>, io::Error> {
+    let mut updated = Vec::with_capacity(computed.len());
+    for (i, j, code, frames) in computed {
+        let mut attributes = Vec::with_capacity(code.attributes().len() + 1);
+        for attribute in code.attributes() {
+            if attribute.name(builder.constant_pool())? != StackMapTable::NAME {
+                attributes.push(attribute.clone());
+            }
+        }
+        if uses_frames {
+            let table = frames.stack_map_table(builder)?;
+            if !table.entries().is_empty() {
+                let mut info = Vec::new();
+                table.write(&mut info);
+                let name_index = builder.add_utf8(StackMapTable::NAME)?;
+                attributes.push(AttributeInfo::new(name_index, info));
+            }
+        }
+        let code = Code::new(
+            frames.max_stack(),
+            frames.max_locals(),
+            code.code().to_vec(),
+            code.exception_table().to_vec(),
+            attributes,
+        );
+        updated.push((i, j, code));
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::hierarchy::ClassFileHierarchy;
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::attribute::attribute_info::AttributeInfo;
+    use std::io::BufReader;
+
+    fn hierarchy() -> ClassFileHierarchy {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add("java/lang/Number", Some(OBJECT), false);
+        hierarchy.add("java/lang/Integer", Some("java/lang/Number"), false);
+        hierarchy.add("java/lang/Long", Some("java/lang/Number"), false);
+        hierarchy
+    }
+
+    fn frames(method: &str, body: &str) -> Result<MethodFrames, io::Error> {
+        let source =
+            format!(".class T\n.super java/lang/Object\n.method {method}\n{body}\n.end method\n");
+        let class_file = assemble(&source).unwrap();
+        let method = &class_file.methods()[0];
+        let code = method.code(class_file.constant_pool()).unwrap().unwrap();
+        compute_frames(&class_file, method, &code, &hierarchy())
+    }
+
+    fn reference(name: &str) -> ValueType {
+        ValueType::Reference(name.to_string())
+    }
+
+    #[test]
+    fn merges_take_the_common_superclass() {
+        let frames = frames(
+            "static f(I)Ljava/lang/Object;",
+            "
+    iload_0
+    ifeq Else
+    iconst_1
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    goto End
+Else:
+    lconst_1
+    invokestatic java/lang/Long/valueOf(J)Ljava/lang/Long;
+    fconst_0
+    fstore_0
+End:
+    areturn",
+        )
+        .unwrap();
+        assert_eq!(frames.max_stack(), 2);
+        assert_eq!(frames.max_locals(), 1);
+        assert_eq!(frames.initial().locals(), [ValueType::Integer]);
+        assert_eq!(
+            frames.frames(),
+            [
+                (11, Frame::new(vec![ValueType::Integer], vec![])),
+                (
+                    17,
+                    Frame::new(vec![ValueType::Top], vec![reference("java/lang/Number")])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn loops_and_wide_locals() {
+        let frames = frames(
+            "static f(JI)D",
+            "
+    dconst_0
+    dstore 3
+Loop:
+    iload_2
+    ifle Done
+    dload 3
+    lload_0
+    l2d
+    dadd
+    dstore 3
+    iinc 2 -1
+    goto Loop
+Done:
+    dload 3
+    dreturn",
+        )
+        .unwrap();
+        assert_eq!(frames.max_stack(), 4);
+        assert_eq!(frames.max_locals(), 5);
+        let locals = vec![
+            ValueType::Long,
+            ValueType::Top,
+            ValueType::Integer,
+            ValueType::Double,
+            ValueType::Top,
+        ];
+        assert_eq!(
+            frames.frames(),
+            [
+                (3, Frame::new(locals.clone(), vec![])),
+                (20, Frame::new(locals, vec![])),
+            ]
+        );
+
+        let mut builder = ConstantPoolBuilder::new();
+        let table = frames.stack_map_table(&mut builder).unwrap();
+        assert_eq!(
+            table.entries(),
+            [
+                StackMapFrame::Append {
+                    offset_delta: 3,
+                    locals: vec![VerificationType::Double]
+                },
+                StackMapFrame::Same { offset_delta: 16 },
+            ]
+        );
+    }
+
+    #[test]
+    fn max_locals_covers_debug_tables() {
+        // the long in slots 1 and 2 is never stored, but the LocalVariableTable still lists it
+        let frames = frames(
+            "static f()V",
+            "
+    .var 1 is unused J from Start to End
+Start:
+    return
+End:",
+        )
+        .unwrap();
+        assert_eq!(frames.max_locals(), 3);
+    }
+
+    #[test]
+    fn handlers_see_the_locals_of_the_protected_code() {
+        let frames = frames(
+            "static f()I",
+            "
+    iconst_0
+    istore_0
+Start:
+    ldc \"x\"
+    astore_0
+    invokestatic T/g()V
+End:
+    iconst_1
+    ireturn
+Handler:
+    pop
+    iconst_2
+    ireturn
+    .catch java/lang/RuntimeException from Start to End using Handler",
+        )
+        .unwrap();
+        assert_eq!(
+            frames.frames(),
+            [(
+                10,
+                Frame::new(
+                    vec![ValueType::Top],
+                    vec![reference("java/lang/RuntimeException")]
+                )
+            )]
+        );
+    }
+
+    #[test]
+    fn constructors_initialize_objects() {
+        let frames = frames(
+            "<init>(Z)V",
+            "
+    aload_0
+    new java/lang/Object
+    dup
+    iload_1
+    ifeq Skip
+    nop
+Skip:
+    invokespecial java/lang/Object/<init>()V
+    pop
+    invokespecial java/lang/Object/<init>()V
+    return",
+        )
+        .unwrap();
+        assert_eq!(frames.initial().locals()[0], ValueType::UninitializedThis);
+        assert_eq!(
+            frames.frames(),
+            [(
+                10,
+                Frame::new(
+                    vec![ValueType::UninitializedThis, ValueType::Integer],
+                    vec![
+                        ValueType::UninitializedThis,
+                        ValueType::Uninitialized(1),
+                        ValueType::Uninitialized(1)
+                    ]
+                )
+            )]
+        );
+
+        let mut builder = ConstantPoolBuilder::new();
+        let table = frames.stack_map_table(&mut builder).unwrap();
+        assert_eq!(
+            table.entries(),
+            [StackMapFrame::Full {
+                offset_delta: 10,
+                locals: vec![
+                    VerificationType::UninitializedThis,
+                    VerificationType::Integer
+                ],
+                stack: vec![
+                    VerificationType::UninitializedThis,
+                    VerificationType::Uninitialized(1),
+                    VerificationType::Uninitialized(1)
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_code_without_valid_frames() {
+        let unreachable = frames("static f()V", "return\n iconst_0\n pop\n return").unwrap();
+        assert_eq!(unreachable.unreachable(), [1]);
+        let error = unreachable
+            .stack_map_table(&mut ConstantPoolBuilder::new())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot compute frames: unreachable code needs a frame at pc 1"
+        );
+
+        let heights = frames(
+            "static f(I)V",
+            "
+    iload_0
+    ifeq Skip
+    iconst_1
+Skip:
+    return",
+        )
+        .unwrap_err();
+        assert_eq!(
+            heights.to_string(),
+            "Cannot compute frames: paths meet with stack heights 0 and 1 at pc 5"
+        );
+
+        let subroutine = frames("static f()V", "jsr Sub\n return\nSub:\n astore_0\n ret 0");
+        assert!(subroutine.is_err());
+        let types = frames("static f(F)I", "fload_0\n ireturn");
+        assert!(types.is_err());
+    }
+
+    #[test]
+    fn recompute_frames_rewrites_code_attributes() {
+        let mut class_file = assemble(
+            "
+.class T
+.super java/lang/Object
+.method static f(I)I
+    .limit stack 9
+    .limit locals 9
+    iload_0
+    ifeq Zero
+    iconst_1
+    ireturn
+Zero:
+    iconst_0
+    ireturn
+    .attribute StackMapTable 0001ff
+.end method
+.method abstract g()V
+.end method
+",
+        )
+        .unwrap();
+        recompute_frames(&mut class_file, &hierarchy()).unwrap();
+
+        let constant_pool = class_file.constant_pool();
+        let code = class_file
+            .find_method("f", "(I)I")
+            .unwrap()
+            .code(constant_pool)
+            .unwrap()
+            .unwrap();
+        assert_eq!(code.max_stack(), 1);
+        assert_eq!(code.max_locals(), 1);
+        let attributes: &[AttributeInfo] = code.attributes();
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(
+            attributes[0].name(constant_pool).unwrap(),
+            StackMapTable::NAME
+        );
+        let table = StackMapTable::from(&mut BufReader::new(attributes[0].info())).unwrap();
+        assert_eq!(table.entries(), [StackMapFrame::Same { offset_delta: 6 }]);
+
+        // version 49 classes have no frames
+        class_file.set_version(49, 0);
+        recompute_frames(&mut class_file, &hierarchy()).unwrap();
+        let code = class_file.methods()[0]
+            .code(class_file.constant_pool())
+            .unwrap()
+            .unwrap();
+        assert!(code.attributes().is_empty());
+    }
+}
@@ -0,0 +1,517 @@
+use crate::class_file::access_flags::ACC_INTERFACE;
+use crate::class_file::class_file::ClassFile;
+use crate::util::class_path::read_class_path;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::io;
+
+pub const OBJECT: &str = "java/lang/Object";
+pub const THROWABLE: &str = "java/lang/Throwable";
+
+/// Answers questions about classes that aren't necessarily loaded, such as the common
+/// superclass of two types where control flow merges. Class names are internal binary names
+/// (e.g. `java/lang/String`).
+pub trait ClassHierarchy {
+    /// The direct superclass, or None for `java/lang/Object` and for classes it doesn't know
+    fn super_class(&self, class_name: &str) -> Option<String>;
+
+    fn is_interface(&self, class_name: &str) -> bool;
+
+    /// The direct superinterfaces, in declaration order
+    fn interfaces(&self, _class_name: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether the class is known at all, as opposed to assumed
+    fn contains(&self, class_name: &str) -> bool {
+        class_name == OBJECT || self.super_class(class_name).is_some()
+    }
+
+    /// The access flags of a field or method declared by the class itself, if known
+    fn member_access_flags(
+        &self,
+        _class_name: &str,
+        _name: &str,
+        _descriptor: &str,
+    ) -> Option<u16> {
+        None
+    }
+
+    /// The most specific class both are subclasses of. Interfaces are treated as
+    /// `java/lang/Object`, as the verifier does (section 4.10.1.2), and so are classes the
+    /// hierarchy doesn't know.
+    fn common_super_class(&self, a: &str, b: &str) -> String {
+        if a == b {
+            return a.to_string();
+        }
+        if self.is_interface(a) || self.is_interface(b) {
+            return OBJECT.to_string();
+        }
+        let ancestors = self.super_classes(a);
+        self.super_classes(b)
+            .into_iter()
+            .find(|class| ancestors.contains(class))
+            .unwrap_or_else(|| OBJECT.to_string())
+    }
+
+    /// Whether a value of class or array type `from` can be used where `to` is expected
+    /// (section 4.10.1.2). Any class can be assigned to an interface, as in the verifier. When
+    /// the hierarchy doesn't know enough to tell, the answer is yes.
+    fn is_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(_), None) => to == "java/lang/Cloneable" || to == "java/io/Serializable",
+            (None, Some(_)) => false,
+            // arrays of references are assignable like their elements, primitive ones never
+            (Some(from), Some(to)) => match (element_class(from), element_class(to)) {
+                (Some(from), Some(to)) => self.is_assignable(from, to),
+                _ => false,
+            },
+            (None, None) => {
+                let known = |class: &str| class == OBJECT || self.super_class(class).is_some();
+                let super_classes = self.super_classes(from);
+                self.is_interface(to)
+                    || !known(to)
+                    || super_classes.iter().any(|class| class == to)
+                    || super_classes.last().is_some_and(|class| !known(class))
+            }
+        }
+    }
+
+    /// The class itself followed by its superclasses, stopping early on a cycle
+    fn super_classes(&self, class_name: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut classes = Vec::new();
+        let mut current = Some(class_name.to_string());
+        while let Some(class) = current {
+            if !seen.insert(class.clone()) {
+                break;
+            }
+            current = self.super_class(&class);
+            classes.push(class);
+        }
+        classes
+    }
+
+    /// Every superclass and superinterface, direct or not, without the class itself: the
+    /// superclasses nearest first, then the interfaces breadth first
+    fn all_supertypes(&self, class_name: &str) -> Vec<String> {
+        let mut supertypes = self.super_classes(class_name);
+        let mut seen: HashSet<String> = supertypes.iter().cloned().collect();
+        let mut pending: VecDeque<String> = supertypes
+            .iter()
+            .flat_map(|class| self.interfaces(class))
+            .collect();
+        supertypes.remove(0);
+        while let Some(interface) = pending.pop_front() {
+            if seen.insert(interface.clone()) {
+                pending.extend(self.interfaces(&interface));
+                supertypes.push(interface);
+            }
+        }
+        supertypes
+    }
+
+    /// Whether `from` is `to` or one of its subtypes (section 4.10.1.2 without the verifier's
+    /// leniency): classes are only subtypes of the interfaces they implement, and unknown
+    /// classes are only subtypes of `java/lang/Object`.
+    fn is_subtype(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(_), None) => to == "java/lang/Cloneable" || to == "java/io/Serializable",
+            (None, Some(_)) => false,
+            (Some(from), Some(to)) => match (element_class(from), element_class(to)) {
+                (Some(from), Some(to)) => self.is_subtype(from, to),
+                _ => false,
+            },
+            (None, None) => self.all_supertypes(from).iter().any(|class| class == to),
+        }
+    }
+
+    /// The most specific types both are subtypes of. There can be several, e.g. `Number` and
+    /// `Comparable` for `Integer` and `Long`; the superclass comes first, then interfaces.
+    fn least_common_supertypes(&self, a: &str, b: &str) -> Vec<String> {
+        if a == b {
+            return vec![a.to_string()];
+        }
+        if let (Some(a), Some(b)) = (a.strip_prefix('['), b.strip_prefix('[')) {
+            if let (Some(a), Some(b)) = (element_class(a), element_class(b)) {
+                return self
+                    .least_common_supertypes(a, b)
+                    .into_iter()
+                    .map(|class| match class.starts_with('[') {
+                        true => format!("[{class}"),
+                        false => format!("[L{class};"),
+                    })
+                    .collect();
+            }
+        }
+
+        let with_supertypes = |class: &str| {
+            let mut types = vec![class.to_string()];
+            if class.starts_with('[') {
+                types.extend(["java/lang/Cloneable", "java/io/Serializable"].map(String::from));
+            } else {
+                types.extend(self.all_supertypes(class));
+            }
+            types.push(OBJECT.to_string());
+            types
+        };
+        let of_b: HashSet<String> = with_supertypes(b).into_iter().collect();
+        let mut common: Vec<String> = Vec::new();
+        for class in with_supertypes(a) {
+            if of_b.contains(&class) && !common.contains(&class) {
+                common.push(class);
+            }
+        }
+        common
+            .iter()
+            .filter(|class| {
+                !common
+                    .iter()
+                    .any(|other| other != *class && self.is_subtype(other, class))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// The class name of an array component descriptor such as `Ljava/lang/String;` or `[I`, or None
+/// for primitive components
+fn element_class(component: &str) -> Option<&str> {
+    if component.starts_with('[') {
+        Some(component)
+    } else {
+        component.strip_prefix('L')?.strip_suffix(';')
+    }
+}
+
+/// A hierarchy of the classes added to it, indexed both ways so subtypes can be found too.
+/// Anything else is assumed to extend `java/lang/Object` directly, so add library classes too
+/// when code merges their subtypes.
+#[derive(Clone, Debug, Default)]
+pub struct ClassFileHierarchy {
+    classes: HashMap<String, ClassEntry>,
+    /// class to the classes and interfaces that directly extend or implement it
+    subtypes: HashMap<String, BTreeSet<String>>,
+    /// class, member name and descriptor to access flags
+    members: HashMap<(String, String, String), u16>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ClassEntry {
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    is_interface: bool,
+}
+
+impl ClassFileHierarchy {
+    pub fn new() -> ClassFileHierarchy {
+        ClassFileHierarchy::default()
+    }
+
+    /// Adds a class, replacing whatever was known about it before
+    pub fn add(&mut self, class_name: &str, super_class: Option<&str>, is_interface: bool) {
+        let entry = ClassEntry {
+            super_class: super_class.map(str::to_string),
+            interfaces: Vec::new(),
+            is_interface,
+        };
+        if let Some(old) = self.classes.insert(class_name.to_string(), entry) {
+            for supertype in old.super_class.iter().chain(old.interfaces.iter()) {
+                if let Some(subtypes) = self.subtypes.get_mut(supertype) {
+                    subtypes.remove(class_name);
+                }
+            }
+        }
+        if let Some(super_class) = super_class {
+            self.add_subtype(super_class, class_name);
+        }
+    }
+
+    /// Adds a direct superinterface to a class that was already added
+    pub fn add_interface(&mut self, class_name: &str, interface: &str) {
+        if let Some(entry) = self.classes.get_mut(class_name) {
+            entry.interfaces.push(interface.to_string());
+            self.add_subtype(interface, class_name);
+        }
+    }
+
+    fn add_subtype(&mut self, supertype: &str, subtype: &str) {
+        self.subtypes
+            .entry(supertype.to_string())
+            .or_default()
+            .insert(subtype.to_string());
+    }
+
+    /// Adds every class in a jar, a directory or a class file from its header alone, which is
+    /// much faster than parsing whole classes but leaves out their fields and methods
+    pub fn add_class_path(&mut self, path: &str) -> Result<(), io::Error> {
+        for entry in read_class_path(path)? {
+            let mut class_file = ClassFile::new();
+            class_file
+                .read_header(entry.data())
+                .and_then(|_| self.add_class_file(&class_file))
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", entry.source())))?;
+        }
+        Ok(())
+    }
+
+    /// Every class added, in name order
+    pub fn class_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.classes.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// The classes and interfaces that directly extend or implement the class, in name order
+    pub fn direct_subtypes(&self, class_name: &str) -> Vec<String> {
+        self.subtypes
+            .get(class_name)
+            .map(|subtypes| subtypes.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The classes that directly extend the class
+    pub fn subclasses(&self, class_name: &str) -> Vec<String> {
+        self.direct_subtypes(class_name)
+            .into_iter()
+            .filter(|subtype| self.super_class(subtype).as_deref() == Some(class_name))
+            .collect()
+    }
+
+    /// The classes and interfaces that list the interface as a direct superinterface
+    pub fn implementors(&self, interface: &str) -> Vec<String> {
+        self.direct_subtypes(interface)
+            .into_iter()
+            .filter(|subtype| self.interfaces(subtype).iter().any(|i| i == interface))
+            .collect()
+    }
+
+    /// Every class and interface that extends or implements the class, directly or not
+    pub fn all_subtypes(&self, class_name: &str) -> BTreeSet<String> {
+        let mut subtypes = BTreeSet::new();
+        let mut pending = vec![class_name.to_string()];
+        while let Some(class) = pending.pop() {
+            for subtype in self.subtypes.get(&class).into_iter().flatten() {
+                if subtypes.insert(subtype.clone()) {
+                    pending.push(subtype.clone());
+                }
+            }
+        }
+        subtypes
+    }
+
+    pub fn add_member(
+        &mut self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+        access_flags: u16,
+    ) {
+        self.members.insert(
+            (
+                class_name.to_string(),
+                name.to_string(),
+                descriptor.to_string(),
+            ),
+            access_flags,
+        );
+    }
+
+    /// Adds the class with its fields and methods
+    pub fn add_class_file(&mut self, class_file: &ClassFile) -> Result<(), io::Error> {
+        let class_name = class_file.class_name()?;
+        let super_class = class_file.super_class_name()?;
+        self.add(
+            &class_name,
+            super_class.as_deref(),
+            class_file.access_flags() & ACC_INTERFACE != 0,
+        );
+        for interface in class_file.interface_names()? {
+            self.add_interface(&class_name, &interface);
+        }
+        let constant_pool = class_file.constant_pool();
+        for member in class_file.fields().iter().chain(class_file.methods()) {
+            self.add_member(
+                &class_name,
+                &member.name(constant_pool)?,
+                &member.descriptor(constant_pool)?,
+                member.access_flags(),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl ClassHierarchy for ClassFileHierarchy {
+    fn super_class(&self, class_name: &str) -> Option<String> {
+        self.classes
+            .get(class_name)
+            .and_then(|entry| entry.super_class.clone())
+    }
+
+    fn is_interface(&self, class_name: &str) -> bool {
+        self.classes
+            .get(class_name)
+            .is_some_and(|entry| entry.is_interface)
+    }
+
+    fn interfaces(&self, class_name: &str) -> Vec<String> {
+        self.classes
+            .get(class_name)
+            .map(|entry| entry.interfaces.clone())
+            .unwrap_or_default()
+    }
+
+    fn contains(&self, class_name: &str) -> bool {
+        self.classes.contains_key(class_name)
+    }
+
+    fn member_access_flags(&self, class_name: &str, name: &str, descriptor: &str) -> Option<u16> {
+        let key = (
+            class_name.to_string(),
+            name.to_string(),
+            descriptor.to_string(),
+        );
+        self.members.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn common_super_classes() {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add("java/lang/Number", Some(OBJECT), false);
+        hierarchy.add("java/lang/Integer", Some("java/lang/Number"), false);
+        hierarchy.add("java/lang/Long", Some("java/lang/Number"), false);
+        hierarchy.add("java/lang/Comparable", Some(OBJECT), true);
+        hierarchy.add("Loop", Some("Loop"), false);
+
+        let common = |a, b| hierarchy.common_super_class(a, b);
+        assert_eq!(
+            common("java/lang/Integer", "java/lang/Long"),
+            "java/lang/Number"
+        );
+        assert_eq!(
+            common("java/lang/Integer", "java/lang/Number"),
+            "java/lang/Number"
+        );
+        assert_eq!(common("java/lang/Integer", "java/lang/String"), OBJECT);
+        assert_eq!(common("java/lang/Integer", "java/lang/Comparable"), OBJECT);
+        assert_eq!(common("Loop", "java/lang/Long"), OBJECT);
+        assert_eq!(hierarchy.super_classes("Loop"), ["Loop"]);
+    }
+
+    #[test]
+    fn assignability() {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add("java/lang/Number", Some(OBJECT), false);
+        hierarchy.add("java/lang/Integer", Some("java/lang/Number"), false);
+        hierarchy.add("java/lang/String", Some(OBJECT), false);
+        hierarchy.add("java/lang/Comparable", Some(OBJECT), true);
+
+        let assignable = |from, to| hierarchy.is_assignable(from, to);
+        assert!(assignable("java/lang/Integer", "java/lang/Number"));
+        assert!(!assignable("java/lang/Number", "java/lang/Integer"));
+        assert!(!assignable("java/lang/String", "java/lang/Number"));
+        assert!(assignable("java/lang/String", "java/lang/Comparable"));
+        assert!(assignable("[Ljava/lang/Integer;", "[Ljava/lang/Number;"));
+        assert!(assignable("[[I", "[Ljava/lang/Object;"));
+        assert!(assignable("[I", "java/io/Serializable"));
+        assert!(!assignable("[I", "[J"));
+        assert!(!assignable("[I", "[Ljava/lang/Object;"));
+        assert!(!assignable("java/lang/String", "[I"));
+        // unknown classes can't be ruled out
+        assert!(assignable("java/lang/String", "java/util/List"));
+        assert!(assignable("Unknown", "java/lang/Number"));
+    }
+
+    #[test]
+    fn indexes_subtypes_and_supertypes() {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add("java/lang/Number", Some(OBJECT), false);
+        hierarchy.add("java/lang/Integer", Some("java/lang/Number"), false);
+        hierarchy.add_interface("java/lang/Integer", "java/lang/Comparable");
+        hierarchy.add("java/lang/Long", Some("java/lang/Number"), false);
+        hierarchy.add_interface("java/lang/Long", "java/lang/Comparable");
+        hierarchy.add("java/lang/Comparable", Some(OBJECT), true);
+        hierarchy.add("java/util/Collection", Some(OBJECT), true);
+        hierarchy.add("java/util/List", Some(OBJECT), true);
+        hierarchy.add_interface("java/util/List", "java/util/Collection");
+        hierarchy.add("java/util/ArrayList", Some(OBJECT), false);
+        hierarchy.add_interface("java/util/ArrayList", "java/util/List");
+
+        assert_eq!(
+            hierarchy.subclasses("java/lang/Number"),
+            ["java/lang/Integer", "java/lang/Long"]
+        );
+        assert_eq!(
+            hierarchy.implementors("java/lang/Comparable"),
+            ["java/lang/Integer", "java/lang/Long"]
+        );
+        assert_eq!(
+            hierarchy.all_subtypes("java/util/Collection"),
+            BTreeSet::from([
+                "java/util/ArrayList".to_string(),
+                "java/util/List".to_string()
+            ])
+        );
+        assert_eq!(
+            hierarchy.all_supertypes("java/util/ArrayList"),
+            [OBJECT, "java/util/List", "java/util/Collection"]
+        );
+        assert!(hierarchy.is_subtype("java/util/ArrayList", "java/util/Collection"));
+        assert!(!hierarchy.is_subtype("java/lang/Integer", "java/util/Collection"));
+        assert!(hierarchy.is_subtype("[Ljava/util/ArrayList;", "[Ljava/util/List;"));
+        assert_eq!(
+            hierarchy.least_common_supertypes("java/lang/Integer", "java/lang/Long"),
+            ["java/lang/Number", "java/lang/Comparable"]
+        );
+        assert_eq!(
+            hierarchy.least_common_supertypes("java/util/ArrayList", "java/util/List"),
+            ["java/util/List"]
+        );
+        assert_eq!(
+            hierarchy.least_common_supertypes("[Ljava/lang/Integer;", "[Ljava/lang/Long;"),
+            ["[Ljava/lang/Number;", "[Ljava/lang/Comparable;"]
+        );
+        assert_eq!(
+            hierarchy.least_common_supertypes("[I", "java/lang/Integer"),
+            [OBJECT]
+        );
+
+        // replacing a class drops its old edges
+        hierarchy.add("java/lang/Long", Some(OBJECT), false);
+        assert_eq!(
+            hierarchy.subclasses("java/lang/Number"),
+            ["java/lang/Integer"]
+        );
+        assert_eq!(
+            hierarchy.implementors("java/lang/Comparable"),
+            ["java/lang/Integer"]
+        );
+    }
+
+    #[test]
+    fn builds_from_a_class_path() {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add_class_path("tests/java/compat/new").unwrap();
+        assert_eq!(hierarchy.class_names().len(), 5);
+        assert_eq!(hierarchy.subclasses("compat/Base"), ["compat/Api"]);
+        assert_eq!(hierarchy.implementors("java/lang/Runnable"), ["compat/Api"]);
+        assert!(hierarchy.is_interface("compat/Service"));
+        assert!(hierarchy.is_subtype("compat/Api", "java/lang/Runnable"));
+        // headers only
+        assert_eq!(
+            hierarchy.member_access_flags("compat/Base", "pulledUp", "()V"),
+            None
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use crate::analysis::hierarchy::{ClassHierarchy, OBJECT};
+use crate::class_file::access_flags::{
+    ACC_FINAL, ACC_NATIVE, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC, ACC_STATIC, ACC_VARARGS,
+};
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use std::collections::{BTreeSet, HashSet};
+use std::{fmt, io};
+
+// Resolution of symbolic references (section 5.4.3) without loading anything: every Class,
+// FieldRef, MethodRef and InterfaceMethodRef entry of a class is looked up in a hierarchy the
+// way the JVM would the first time it's used, so references that would fail at runtime show
+// up before deployment instead.
+
+/// Classes with signature polymorphic methods, which match any descriptor (section 2.9.3 of
+/// the Java SE 9 specification; Java 8 only has `MethodHandle`'s)
+const POLYMORPHIC_CLASSES: [&str; 2] = [
+    "java/lang/invoke/MethodHandle",
+    "java/lang/invoke/VarHandle",
+];
+
+/// The methods of `java/lang/Object` that can be referred to, for when it isn't in the
+/// hierarchy. Every class ends up here, so it can't just be ignored like the rest of the JDK.
+const OBJECT_METHODS: [(&str, &str, u16); 12] = [
+    ("<init>", "()V", ACC_PUBLIC),
+    ("clone", "()Ljava/lang/Object;", ACC_PROTECTED | ACC_NATIVE),
+    ("equals", "(Ljava/lang/Object;)Z", ACC_PUBLIC),
+    ("finalize", "()V", ACC_PROTECTED),
+    (
+        "getClass",
+        "()Ljava/lang/Class;",
+        ACC_PUBLIC | ACC_FINAL | ACC_NATIVE,
+    ),
+    ("hashCode", "()I", ACC_PUBLIC | ACC_NATIVE),
+    ("notify", "()V", ACC_PUBLIC | ACC_FINAL | ACC_NATIVE),
+    ("notifyAll", "()V", ACC_PUBLIC | ACC_FINAL | ACC_NATIVE),
+    ("toString", "()Ljava/lang/String;", ACC_PUBLIC),
+    ("wait", "()V", ACC_PUBLIC | ACC_FINAL),
+    ("wait", "(J)V", ACC_PUBLIC | ACC_FINAL | ACC_NATIVE),
+    ("wait", "(JI)V", ACC_PUBLIC | ACC_FINAL),
+];
+
+/// The `java.lang.LinkageError` a failed resolution throws
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LinkErrorKind {
+    NoClassDefFound,
+    NoSuchField,
+    NoSuchMethod,
+    IncompatibleClassChange,
+}
+
+impl fmt::Display for LinkErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LinkErrorKind::NoClassDefFound => "java.lang.NoClassDefFoundError",
+            LinkErrorKind::NoSuchField => "java.lang.NoSuchFieldError",
+            LinkErrorKind::NoSuchMethod => "java.lang.NoSuchMethodError",
+            LinkErrorKind::IncompatibleClassChange => "java.lang.IncompatibleClassChangeError",
+        })
+    }
+}
+
+/// A reference from a class that wouldn't resolve
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LinkError {
+    class_name: String,
+    kind: LinkErrorKind,
+    message: String,
+}
+
+impl LinkError {
+    /// The class the reference is in
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    pub fn kind(&self) -> LinkErrorKind {
+        self.kind
+    }
+
+    /// What couldn't be found, e.g. `q/B.f(I)V`, or why the reference is wrong
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.class_name, self.kind, self.message)
+    }
+}
+
+/// What looking a member up found
+enum Lookup {
+    Found,
+    NotFound,
+    /// a class on the way is missing, so the class referred to can't even be loaded
+    MissingClass(String),
+}
+
+/// Resolves references against a hierarchy holding the class path. Classes in ignored
+/// packages are assumed to exist and to have whatever members are looked up in them, for
+/// when the JDK or other provided libraries aren't in the hierarchy.
+pub struct LinkChecker<'a, H: ClassHierarchy> {
+    hierarchy: &'a H,
+    ignored: Vec<String>,
+}
+
+impl<'a, H: ClassHierarchy> LinkChecker<'a, H> {
+    pub fn new(hierarchy: &'a H) -> LinkChecker<'a, H> {
+        LinkChecker {
+            hierarchy,
+            ignored: Vec::new(),
+        }
+    }
+
+    /// Ignores classes whose names start with the prefix, e.g. `java/`
+    pub fn ignore(&mut self, prefix: &str) {
+        self.ignored.push(prefix.to_string());
+    }
+
+    /// Every reference in the class's constant pool that would fail to resolve, in order
+    pub fn check(&self, class_file: &ClassFile) -> Result<Vec<LinkError>, io::Error> {
+        let class_name = class_file.class_name()?;
+        let constant_pool = class_file.constant_pool();
+        let mut errors = BTreeSet::new();
+        let mut error = |kind, message: String| {
+            errors.insert(LinkError {
+                class_name: class_name.clone(),
+                kind,
+                message,
+            });
+        };
+
+        for (index, item) in constant_pool.items().iter().enumerate() {
+            let index = index as u16 + 1;
+            let (kind, interface_expected) = match item.data() {
+                ConstantPoolData::ClassInfo(_) => {
+                    if let Some(missing) = self.load(&constant_pool.class_name(index)?) {
+                        error(LinkErrorKind::NoClassDefFound, missing);
+                    }
+                    continue;
+                }
+                ConstantPoolData::FieldRef(_) => (LinkErrorKind::NoSuchField, None),
+                ConstantPoolData::MethodRef(_) => (LinkErrorKind::NoSuchMethod, Some(false)),
+                ConstantPoolData::InterfaceMethodRef(_) => {
+                    (LinkErrorKind::NoSuchMethod, Some(true))
+                }
+                _ => continue,
+            };
+
+            let (class, name, descriptor) = constant_pool.member_ref(index)?;
+            if let Some(missing) = self.load(&class) {
+                error(LinkErrorKind::NoClassDefFound, missing);
+                continue;
+            }
+            let lookup = match interface_expected {
+                None => self.resolve_field(&class, &name, &descriptor, &mut HashSet::new()),
+                Some(interface_expected) => {
+                    if let Some(message) = self.check_class_kind(&class, interface_expected) {
+                        error(LinkErrorKind::IncompatibleClassChange, message);
+                        continue;
+                    }
+                    if interface_expected {
+                        self.resolve_interface_method(&class, &name, &descriptor)
+                    } else {
+                        self.resolve_method(&class, &name, &descriptor)
+                    }
+                }
+            };
+            match lookup {
+                Lookup::Found => {}
+                Lookup::MissingClass(missing) => error(LinkErrorKind::NoClassDefFound, missing),
+                Lookup::NotFound if kind == LinkErrorKind::NoSuchField => {
+                    error(kind, format!("{class}.{name}:{descriptor}"))
+                }
+                Lookup::NotFound => error(kind, format!("{class}.{name}{descriptor}")),
+            }
+        }
+        Ok(errors.into_iter().collect())
+    }
+
+    fn is_ignored(&self, class_name: &str) -> bool {
+        class_name != OBJECT
+            && self
+                .ignored
+                .iter()
+                .any(|prefix| class_name.starts_with(prefix.as_str()))
+    }
+
+    fn contains(&self, class_name: &str) -> bool {
+        class_name == OBJECT || self.hierarchy.contains(class_name)
+    }
+
+    /// Loading a class loads its superclasses and superinterfaces too (section 5.3.5), so
+    /// returns the first of them that's missing. Arrays need their element class.
+    fn load(&self, class_name: &str) -> Option<String> {
+        let class_name = class_name.trim_start_matches('[');
+        let class_name = match class_name.strip_prefix('L') {
+            Some(element) if class_name.ends_with(';') => &element[..element.len() - 1],
+            // primitive arrays
+            _ if class_name.len() == 1 => return None,
+            _ => class_name,
+        };
+        let mut seen = HashSet::new();
+        let mut pending = vec![class_name.to_string()];
+        while let Some(class) = pending.pop() {
+            if !seen.insert(class.clone()) || self.is_ignored(&class) {
+                continue;
+            }
+            if !self.contains(&class) {
+                return Some(class);
+            }
+            pending.extend(self.hierarchy.super_class(&class));
+            pending.extend(self.hierarchy.interfaces(&class));
+        }
+        None
+    }
+
+    /// Method references must name a class and interface method references an interface
+    fn check_class_kind(&self, class_name: &str, interface_expected: bool) -> Option<String> {
+        if self.is_ignored(class_name) || class_name.starts_with('[') {
+            return None;
+        }
+        match (self.hierarchy.is_interface(class_name), interface_expected) {
+            (true, false) => Some(format!(
+                "Found interface {class_name}, but class was expected"
+            )),
+            (false, true) => Some(format!(
+                "Found class {class_name}, but interface was expected"
+            )),
+            _ => None,
+        }
+    }
+
+    fn declares(&self, class_name: &str, name: &str, descriptor: &str) -> Option<u16> {
+        if class_name == OBJECT && !self.hierarchy.contains(OBJECT) {
+            return OBJECT_METHODS
+                .iter()
+                .find(|(n, d, _)| *n == name && *d == descriptor)
+                .map(|(_, _, flags)| *flags);
+        }
+        self.hierarchy
+            .member_access_flags(class_name, name, descriptor)
+    }
+
+    /// Field resolution (section 5.4.3.2): the class, then its superinterfaces, then its
+    /// superclass, recursively
+    fn resolve_field(
+        &self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+        seen: &mut HashSet<String>,
+    ) -> Lookup {
+        if !seen.insert(class_name.to_string()) {
+            return Lookup::NotFound;
+        }
+        if self.is_ignored(class_name) {
+            return Lookup::Found;
+        }
+        if !self.contains(class_name) {
+            return Lookup::MissingClass(class_name.to_string());
+        }
+        if self.declares(class_name, name, descriptor).is_some() {
+            return Lookup::Found;
+        }
+        let supertypes = self
+            .hierarchy
+            .interfaces(class_name)
+            .into_iter()
+            .chain(self.hierarchy.super_class(class_name));
+        for supertype in supertypes {
+            match self.resolve_field(&supertype, name, descriptor, seen) {
+                Lookup::NotFound => {}
+                lookup => return lookup,
+            }
+        }
+        Lookup::NotFound
+    }
+
+    /// Method resolution (section 5.4.3.3): the class and its superclasses, where signature
+    /// polymorphic methods match any descriptor, then the non-private instance methods of all
+    /// their superinterfaces
+    fn resolve_method(&self, class_name: &str, name: &str, descriptor: &str) -> Lookup {
+        // arrays have the methods of Object, with a public clone
+        if class_name.starts_with('[') {
+            if name == "clone" && descriptor == "()Ljava/lang/Object;" {
+                return Lookup::Found;
+            }
+            return self.resolve_method(OBJECT, name, descriptor);
+        }
+
+        let mut classes = Vec::new();
+        let mut current = Some(class_name.to_string());
+        while let Some(class) = current {
+            if classes.contains(&class) {
+                break;
+            }
+            if self.is_ignored(&class) {
+                return Lookup::Found;
+            }
+            if !self.contains(&class) {
+                return Lookup::MissingClass(class);
+            }
+            if self.declares(&class, name, descriptor).is_some() {
+                return Lookup::Found;
+            }
+            if POLYMORPHIC_CLASSES.contains(&class.as_str())
+                && self.is_signature_polymorphic(&class, name)
+            {
+                return Lookup::Found;
+            }
+            current = self.hierarchy.super_class(&class);
+            classes.push(class);
+        }
+
+        let interfaces = classes
+            .iter()
+            .flat_map(|class| self.hierarchy.interfaces(class))
+            .collect();
+        self.resolve_in_superinterfaces(interfaces, name, descriptor)
+    }
+
+    /// Native varargs methods taking an `Object[]`, whatever they return
+    fn is_signature_polymorphic(&self, class_name: &str, name: &str) -> bool {
+        let polymorphic = ACC_VARARGS | ACC_NATIVE;
+        ["Ljava/lang/Object;", "Z", "V"].iter().any(|return_type| {
+            let descriptor = format!("([Ljava/lang/Object;){return_type}");
+            self.declares(class_name, name, &descriptor)
+                .is_some_and(|flags| flags & polymorphic == polymorphic)
+        })
+    }
+
+    /// Interface method resolution (section 5.4.3.4): the interface, then the public methods
+    /// of Object, then the non-private instance methods of its superinterfaces
+    fn resolve_interface_method(&self, class_name: &str, name: &str, descriptor: &str) -> Lookup {
+        if self.is_ignored(class_name) || self.declares(class_name, name, descriptor).is_some() {
+            return Lookup::Found;
+        }
+        let public_in_object = self
+            .declares(OBJECT, name, descriptor)
+            .is_some_and(|flags| flags & (ACC_PUBLIC | ACC_STATIC) == ACC_PUBLIC);
+        if public_in_object {
+            return Lookup::Found;
+        }
+        let interfaces = self.hierarchy.interfaces(class_name);
+        self.resolve_in_superinterfaces(interfaces, name, descriptor)
+    }
+
+    /// Searches the interfaces and all of their superinterfaces for a method that is neither
+    /// private nor static
+    fn resolve_in_superinterfaces(
+        &self,
+        mut pending: Vec<String>,
+        name: &str,
+        descriptor: &str,
+    ) -> Lookup {
+        let mut seen = HashSet::new();
+        let mut missing = None;
+        while let Some(interface) = pending.pop() {
+            if !seen.insert(interface.clone()) {
+                continue;
+            }
+            if self.is_ignored(&interface) {
+                return Lookup::Found;
+            }
+            if !self.contains(&interface) {
+                missing.get_or_insert(interface);
+                continue;
+            }
+            let flags = self.declares(&interface, name, descriptor);
+            if flags.is_some_and(|flags| flags & (ACC_PRIVATE | ACC_STATIC) == 0) {
+                return Lookup::Found;
+            }
+            pending.extend(self.hierarchy.interfaces(&interface));
+        }
+        missing.map_or(Lookup::NotFound, Lookup::MissingClass)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::hierarchy::ClassFileHierarchy;
+    use crate::assembler::assembler::assemble;
+
+    fn hierarchy(sources: &[&str]) -> ClassFileHierarchy {
+        let mut hierarchy = ClassFileHierarchy::new();
+        for source in sources {
+            hierarchy
+                .add_class_file(&assemble(source).unwrap())
+                .unwrap();
+        }
+        hierarchy
+    }
+
+    fn check(hierarchy: &ClassFileHierarchy, source: &str) -> Vec<String> {
+        let class_file = assemble(source).unwrap();
+        let mut hierarchy = hierarchy.clone();
+        hierarchy.add_class_file(&class_file).unwrap();
+        let mut checker = LinkChecker::new(&hierarchy);
+        checker.ignore("java/");
+        let errors = checker.check(&class_file).unwrap();
+        errors.iter().map(LinkError::to_string).collect()
+    }
+
+    const LIBRARY: [&str; 4] = [
+        "
+.class public lib/Base
+.super java/lang/Object
+.field protected count I
+.method public base()V
+    return
+.end method
+",
+        "
+.class public lib/Impl
+.super lib/Base
+.implements lib/Api
+.method public impl()V
+    return
+.end method
+",
+        "
+.interface public abstract lib/Api
+.super java/lang/Object
+.implements lib/Parent
+.field public static final LIMIT I = 10
+.method public abstract api()V
+.end method
+.method private static helper()V
+    return
+.end method
+",
+        "
+.interface public abstract lib/Parent
+.super java/lang/Object
+.method public inherited()V
+    return
+.end method
+",
+    ];
+
+    #[test]
+    fn resolves_through_superclasses_and_superinterfaces() {
+        let hierarchy = hierarchy(&LIBRARY);
+        let errors = check(
+            &hierarchy,
+            "
+.class public app/Main
+.super java/lang/Object
+.method public static f(Llib/Impl;Llib/Api;)V
+    .limit stack 2
+    aload_0
+    invokevirtual lib/Impl/impl()V
+    aload_0
+    invokevirtual lib/Impl/base()V
+    aload_0
+    invokevirtual lib/Impl/api()V
+    aload_0
+    invokevirtual lib/Impl/inherited()V
+    aload_0
+    invokevirtual lib/Impl/hashCode()I
+    pop
+    aload_0
+    getfield lib/Impl/count I
+    pop
+    getstatic lib/Impl/LIMIT I
+    pop
+    aload_1
+    invokeinterface lib/Api/inherited()V 1
+    aload_1
+    invokeinterface lib/Api/toString()Ljava/lang/String; 1
+    pop
+    return
+.end method
+",
+        );
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_missing_classes_and_members() {
+        let hierarchy = hierarchy(&LIBRARY);
+        let errors = check(
+            &hierarchy,
+            "
+.class public app/Main
+.super lib/Base
+.method public static f(Llib/Impl;Llib/Api;)V
+    .limit stack 2
+    aload_0
+    invokevirtual lib/Impl/removed()V
+    aload_0
+    invokevirtual lib/Impl/base(I)V
+    aload_0
+    invokevirtual lib/Impl/helper()V
+    aload_0
+    getfield lib/Impl/total I
+    pop
+    aload_1
+    invokeinterface lib/Api/impl()V 1
+    aload_1
+    invokevirtual lib/Api/api()V
+    aload_0
+    invokeinterface lib/Impl/impl()V 1
+    new lib/Gone
+    invokestatic lib/Gone/make()V
+    return
+.end method
+",
+        );
+        assert_eq!(
+            errors,
+            [
+                "app/Main: java.lang.NoClassDefFoundError: lib/Gone",
+                "app/Main: java.lang.NoSuchFieldError: lib/Impl.total:I",
+                "app/Main: java.lang.NoSuchMethodError: lib/Api.impl()V",
+                "app/Main: java.lang.NoSuchMethodError: lib/Impl.base(I)V",
+                "app/Main: java.lang.NoSuchMethodError: lib/Impl.helper()V",
+                "app/Main: java.lang.NoSuchMethodError: lib/Impl.removed()V",
+                "app/Main: java.lang.IncompatibleClassChangeError: Found class lib/Impl, but interface was expected",
+                "app/Main: java.lang.IncompatibleClassChangeError: Found interface lib/Api, but class was expected",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_supertypes_fail_to_load() {
+        let hierarchy = hierarchy(&LIBRARY[1..]);
+        let errors = check(
+            &hierarchy,
+            "
+.class public app/Main
+.super java/lang/Object
+.method public static f([Llib/Impl;)V
+    .limit stack 2
+    aload_0
+    invokevirtual [Llib/Impl;/clone()Ljava/lang/Object;
+    pop
+    return
+.end method
+",
+        );
+        assert_eq!(
+            errors,
+            ["app/Main: java.lang.NoClassDefFoundError: lib/Base"]
+        );
+    }
+}
@@ -0,0 +1,8 @@
+pub mod call_graph;
+pub mod cfg;
+pub mod dataflow;
+pub mod dependencies;
+pub mod frames;
+pub mod hierarchy;
+pub mod linkage;
+pub mod verifier;
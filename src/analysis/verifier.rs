@@ -0,0 +1,1280 @@
+use crate::analysis::cfg::{ends_block, is_conditional};
+use crate::analysis::frames::{local_access, merge_into, Frame, Interpreter, ValueType};
+use crate::analysis::hierarchy::{ClassHierarchy, THROWABLE};
+use crate::bytecode::instruction::Instruction;
+use crate::bytecode::opcode::{ASTORE, ASTORE_3, IINC, ISTORE, ISTORE_0};
+use crate::class_file::access_flags::{ACC_ABSTRACT, ACC_NATIVE, ACC_PROTECTED};
+use crate::class_file::attribute::code::Code;
+use crate::class_file::attribute::stack_map_table::{
+    StackMapFrame, StackMapTable, VerificationType,
+};
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::descriptor::{FieldType, MethodDescriptor};
+use crate::class_file::member_info::MethodInfo;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::io::BufReader;
+use std::ops::Range;
+use std::{error, fmt};
+
+// Verification by type checking (section 4.10.1) and by type inference (section 4.10.2). With
+// a StackMapTable every branch target and exception handler has a recorded frame, so a method
+// is checked in one linear pass: the frame flowing out of every instruction has to be
+// assignable to the recorded frame wherever control can go next. Without one, the frames are
+// inferred by dataflow instead. Both use the same interpreter and report the same errors.
+
+/// Why a class or method failed verification, as a `java.lang.VerifyError` would report it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyError {
+    class_name: String,
+    method: Option<String>,
+    pc: Option<u32>,
+    message: String,
+}
+
+impl VerifyError {
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The method's name and descriptor, e.g. `f(I)I`, or None for problems with the class
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+
+    pub fn pc(&self) -> Option<u32> {
+        self.pc
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VerifyError: {}", self.class_name)?;
+        if let Some(method) = &self.method {
+            write!(f, ".{method}")?;
+        }
+        if let Some(pc) = self.pc {
+            write!(f, " at pc {pc}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl error::Error for VerifyError {}
+
+/// A failure inside a method, before the class and method names are attached
+struct Failure {
+    pc: Option<u32>,
+    message: String,
+}
+
+fn fail<T>(pc: u32, message: impl Into<String>) -> Result<T, Failure> {
+    Err(Failure {
+        pc: Some(pc),
+        message: message.into(),
+    })
+}
+
+fn fail_method<T>(message: impl Into<String>) -> Result<T, Failure> {
+    Err(Failure {
+        pc: None,
+        message: message.into(),
+    })
+}
+
+/// Verifies every method, returning the first problem found in each. Class files version 50
+/// and later are type checked against their stack map frames; older ones, which have none,
+/// are verified by type inference.
+pub fn verify_class(class_file: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Vec<VerifyError> {
+    let class_name = match class_file.class_name() {
+        Ok(class_name) => class_name,
+        Err(e) => {
+            return vec![VerifyError {
+                class_name: String::new(),
+                method: None,
+                pc: None,
+                message: e.to_string(),
+            }]
+        }
+    };
+    let constant_pool = class_file.constant_pool();
+    let mut errors = Vec::new();
+    for method in class_file.methods() {
+        let name = method.name(constant_pool).unwrap_or_default();
+        let descriptor = method.descriptor(constant_pool).unwrap_or_default();
+        if let Err(failure) = verify_method(class_file, &class_name, method, hierarchy) {
+            errors.push(VerifyError {
+                class_name: class_name.clone(),
+                method: Some(format!("{name}{descriptor}")),
+                pc: failure.pc,
+                message: failure.message,
+            });
+        }
+    }
+    errors
+}
+
+/// What both kinds of verification need to know about a method
+struct Method<'a> {
+    class_name: &'a str,
+    name: String,
+    descriptor: MethodDescriptor,
+    code: Code,
+    instructions: Vec<(u32, Instruction)>,
+    initial: Frame,
+    handlers: Vec<Handler>,
+    constant_pool: &'a ConstantPool,
+    hierarchy: &'a dyn ClassHierarchy,
+}
+
+struct Handler {
+    range: Range<u32>,
+    handler_pc: u32,
+    exception: ValueType,
+}
+
+fn verify_method(
+    class_file: &ClassFile,
+    class_name: &str,
+    method: &MethodInfo,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), Failure> {
+    let constant_pool = class_file.constant_pool();
+    let malformed = |e: io::Error| Failure {
+        pc: None,
+        message: e.to_string(),
+    };
+    let name = method.name(constant_pool).map_err(malformed)?;
+    let descriptor = method.descriptor(constant_pool).map_err(malformed)?;
+    let descriptor = MethodDescriptor::parse(&descriptor).map_err(malformed)?;
+    let code = method.code(constant_pool).map_err(malformed)?;
+    let code = match code {
+        _ if method.access_flags() & (ACC_ABSTRACT | ACC_NATIVE) != 0 => {
+            return match code {
+                Some(_) => fail_method("abstract and native methods can't have code"),
+                None => Ok(()),
+            }
+        }
+        Some(code) => code,
+        None => return fail_method("the method has no Code attribute"),
+    };
+    let instructions = code
+        .instructions()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(malformed)?;
+    if instructions.is_empty() {
+        return fail_method("the code is empty");
+    }
+
+    let initial = Frame::initial(class_name, method.access_flags(), &name, &descriptor);
+    if initial.locals().len() > code.max_locals() as usize {
+        return fail_method(format!(
+            "the parameters need {} locals but max_locals is {}",
+            initial.locals().len(),
+            code.max_locals()
+        ));
+    }
+
+    let mut handlers = Vec::with_capacity(code.exception_table().len());
+    for entry in code.exception_table() {
+        let (start, end, handler_pc) = (
+            entry.start_pc() as u32,
+            entry.end_pc() as u32,
+            entry.handler_pc() as u32,
+        );
+        if start >= end || end as usize > code.code().len() {
+            return fail(
+                handler_pc,
+                format!("bad exception handler range {start}-{end}"),
+            );
+        }
+        let exception = match entry.catch_type() {
+            0 => THROWABLE.to_string(),
+            index => constant_pool.class_name(index).map_err(malformed)?,
+        };
+        if !hierarchy.is_assignable(&exception, THROWABLE) {
+            return fail(
+                handler_pc,
+                format!("{exception} is caught but is not a Throwable"),
+            );
+        }
+        handlers.push(Handler {
+            range: start..end,
+            handler_pc,
+            exception: ValueType::Reference(exception),
+        });
+    }
+
+    let method = Method {
+        class_name,
+        name,
+        descriptor,
+        code,
+        instructions,
+        initial,
+        handlers,
+        constant_pool,
+        hierarchy,
+    };
+    match class_file.major_version() {
+        0..50 => method.infer_types(),
+        // version 50 may fall back to type inference when type checking fails (section 4.10)
+        50 => method
+            .check_types()
+            .or_else(|failure| method.infer_types().map_err(|_| failure)),
+        _ => method.check_types(),
+    }
+}
+
+impl Method<'_> {
+    fn interpreter(&self) -> Interpreter<'_> {
+        Interpreter::new(self.constant_pool, self.class_name, &self.instructions)
+            .checking(self.hierarchy)
+    }
+
+    /// Checks and executes one instruction, leaving the frame after it in `frame`
+    fn step(
+        &self,
+        interpreter: &Interpreter,
+        pc: u32,
+        instruction: &Instruction,
+        frame: &mut Frame,
+    ) -> Result<(), Failure> {
+        check_return(
+            frame,
+            instruction,
+            &self.name,
+            &self.descriptor,
+            self.hierarchy,
+        )
+        .or_else(|message| fail(pc, message))?;
+        check_protected(
+            frame,
+            instruction,
+            self.class_name,
+            self.constant_pool,
+            self.hierarchy,
+        )
+        .or_else(|message| fail(pc, message))?;
+        interpreter
+            .execute(pc, instruction, frame)
+            .or_else(|e| fail(pc, e.to_string()))?;
+
+        let max_stack = self.code.max_stack();
+        if frame.stack().len() > max_stack as usize {
+            return fail(
+                pc,
+                format!("the operand stack exceeds max_stack {max_stack}"),
+            );
+        }
+        let max_locals = self.code.max_locals();
+        if frame.locals().len() > max_locals as usize {
+            return fail(pc, format!("a local is beyond max_locals {max_locals}"));
+        }
+        Ok(())
+    }
+
+    /// The handlers covering `pc`
+    fn handlers_at(&self, pc: u32) -> impl Iterator<Item = &Handler> {
+        self.handlers
+            .iter()
+            .filter(move |handler| handler.range.contains(&pc))
+    }
+
+    /// Verification by type checking (section 4.10.1), in one pass against the stack map
+    fn check_types(&self) -> Result<(), Failure> {
+        let hierarchy = self.hierarchy;
+        let stack_map = stack_map_frames(
+            &self.initial,
+            &self.code,
+            self.constant_pool,
+            &self.instructions,
+        )?;
+        let handler_frame = |handler: &Handler| match stack_map.get(&handler.handler_pc) {
+            Some(frame) => Ok(frame),
+            None => fail(
+                handler.handler_pc,
+                "an exception handler has no stack map frame",
+            ),
+        };
+        for handler in self.handlers.iter() {
+            handler_frame(handler)?;
+        }
+
+        let interpreter = self.interpreter();
+        // None after an instruction control can't fall through
+        let mut current = Some(self.initial.clone());
+        for (pc, instruction) in self.instructions.iter() {
+            let pc = *pc;
+            if matches!(
+                instruction,
+                Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_)
+            ) {
+                return fail(
+                    pc,
+                    "jsr and ret can't be used in class files with stack map frames",
+                );
+            }
+            let mut frame = match (current.take(), stack_map.get(&pc)) {
+                (Some(frame), Some(recorded)) => {
+                    check_assignable(&frame, recorded, hierarchy).or_else(|message| {
+                        fail(pc, format!("doesn't match the stack map frame: {message}"))
+                    })?;
+                    recorded.clone()
+                }
+                (None, Some(recorded)) => recorded.clone(),
+                (Some(frame), None) => frame,
+                (None, None) => {
+                    return fail(pc, "no stack map frame after an unconditional branch");
+                }
+            };
+
+            for handler in self.handlers_at(pc) {
+                check_handler(
+                    &frame,
+                    &handler.exception,
+                    handler_frame(handler)?,
+                    hierarchy,
+                )
+                .or_else(|message| fail(pc, message))?;
+            }
+            self.step(&interpreter, pc, instruction, &mut frame)?;
+            if is_store(instruction) {
+                for handler in self.handlers_at(pc) {
+                    let handler_frame = handler_frame(handler)?;
+                    check_handler(&frame, &handler.exception, handler_frame, hierarchy)
+                        .or_else(|message| fail(pc, message))?;
+                }
+            }
+
+            for target in instruction.branch_targets() {
+                let Some(recorded) = stack_map.get(&target) else {
+                    return fail(
+                        pc,
+                        format!("the branch target {target} has no stack map frame"),
+                    );
+                };
+                check_assignable(&frame, recorded, hierarchy).or_else(|message| {
+                    fail(
+                        pc,
+                        format!(
+                            "doesn't match the stack map frame at branch target {target}: {message}"
+                        ),
+                    )
+                })?;
+            }
+            if falls_through(instruction) {
+                current = Some(frame);
+            }
+        }
+        match (current, self.instructions.last()) {
+            (Some(_), Some((pc, _))) => fail(*pc, "execution falls off the end of the code"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Verification by type inference (section 4.10.2): dataflow to a fixpoint, merging the
+    /// frames where paths meet. Subroutines are followed with the locals they use, so that
+    /// `ret` returns to each caller with the caller's other locals (section 4.10.2.4).
+    fn infer_types(&self) -> Result<(), Failure> {
+        let instructions = &self.instructions;
+        let index_of = |pc: u32| instructions.binary_search_by_key(&pc, |(pc, _)| *pc).ok();
+        let mut frames: Vec<Option<Frame>> = vec![None; instructions.len()];
+        let mut subroutines: Vec<Option<Subroutine>> = vec![None; instructions.len()];
+        frames[0] = Some(self.initial.clone());
+        let mut work = vec![0];
+
+        let interpreter = self.interpreter();
+        while let Some(i) = work.pop() {
+            let (pc, instruction) = &instructions[i];
+            let pc = *pc;
+            let Some(before) = frames[i].clone() else {
+                continue;
+            };
+            // note the locals the enclosing subroutine uses before passing it on
+            if let (Some(subroutine), Some((index, wide))) =
+                (&mut subroutines[i], local_access(instruction))
+            {
+                subroutine.used.insert(index);
+                if wide {
+                    subroutine.used.insert(index + 1);
+                }
+            }
+            let subroutine = subroutines[i].clone();
+            let mut frame = before.clone();
+            self.step(&interpreter, pc, instruction, &mut frame)?;
+
+            // (instruction index, frame, subroutine) for every successor
+            let mut successors = Vec::new();
+            for handler in self.handlers_at(pc) {
+                let Some(target) = index_of(handler.handler_pc) else {
+                    return fail(pc, "an exception handler is not at an instruction");
+                };
+                let exception = vec![handler.exception.clone()];
+                successors.push((
+                    target,
+                    Frame::new(before.locals().to_vec(), exception.clone()),
+                    subroutine.clone(),
+                ));
+                if is_store(instruction) {
+                    successors.push((
+                        target,
+                        Frame::new(frame.locals().to_vec(), exception),
+                        subroutine.clone(),
+                    ));
+                }
+            }
+            match instruction {
+                Instruction::Jsr(target) | Instruction::JsrW(target) => {
+                    if subroutine.as_ref().is_some_and(|s| s.start == *target) {
+                        return fail(pc, format!("the subroutine at {target} calls itself"));
+                    }
+                    let Some(target) = index_of(*target) else {
+                        return fail(pc, format!("the jsr target {target} is not an instruction"));
+                    };
+                    let called = Subroutine::new(instructions[target].0, i);
+                    successors.push((target, frame.clone(), Some(called)));
+                }
+                Instruction::Ret(_) => {
+                    let Some(subroutine) = &subroutine else {
+                        return fail(pc, "ret outside of a subroutine");
+                    };
+                    // locals the subroutine didn't touch come back from before the jsr
+                    for &caller in subroutine.callers.iter() {
+                        let Some(at_jsr) = &frames[caller] else {
+                            continue;
+                        };
+                        if caller + 1 == instructions.len() {
+                            return fail(pc, "a jsr is the last instruction");
+                        }
+                        let length = frame.locals().len().max(at_jsr.locals().len());
+                        let locals = (0..length)
+                            .map(|local| {
+                                let from = match subroutine.used.contains(&(local as u16)) {
+                                    true => &frame,
+                                    false => at_jsr,
+                                };
+                                from.locals().get(local).cloned().unwrap_or(ValueType::Top)
+                            })
+                            .collect();
+                        let returned = Frame::new(locals, frame.stack().to_vec());
+                        successors.push((caller + 1, returned, subroutines[caller].clone()));
+                    }
+                }
+                _ => {
+                    for target in instruction.branch_targets() {
+                        let Some(target) = index_of(target) else {
+                            return fail(
+                                pc,
+                                format!("the branch target {target} is not an instruction"),
+                            );
+                        };
+                        successors.push((target, frame.clone(), subroutine.clone()));
+                    }
+                    if falls_through(instruction) {
+                        if i + 1 == instructions.len() {
+                            return fail(pc, "execution falls off the end of the code");
+                        }
+                        successors.push((i + 1, frame, subroutine.clone()));
+                    }
+                }
+            }
+
+            for (target, incoming, called) in successors {
+                let target_pc = instructions[target].0;
+                let mut changed = merge_into(&mut frames[target], &incoming, self.hierarchy)
+                    .or_else(|e| fail(target_pc, e.to_string()))?;
+                if let Some(called) = called {
+                    changed |= match &mut subroutines[target] {
+                        Some(subroutine) => subroutine.merge(&called),
+                        empty => {
+                            *empty = Some(called);
+                            true
+                        }
+                    };
+                }
+                if changed && !work.contains(&target) {
+                    work.push(target);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The subroutine an instruction runs in during type inference
+#[derive(Clone, Debug)]
+struct Subroutine {
+    start: u32,
+    /// Indices of the jsr instructions that call it
+    callers: BTreeSet<usize>,
+    /// Locals it loads or stores, which `ret` passes back to the callers
+    used: BTreeSet<u16>,
+}
+
+impl Subroutine {
+    fn new(start: u32, caller: usize) -> Subroutine {
+        Subroutine {
+            start,
+            callers: BTreeSet::from([caller]),
+            used: BTreeSet::new(),
+        }
+    }
+
+    /// Adds the callers and locals of `other`, returning whether anything was new
+    fn merge(&mut self, other: &Subroutine) -> bool {
+        let (callers, used) = (self.callers.len(), self.used.len());
+        self.callers.extend(other.callers.iter().copied());
+        self.used.extend(other.used.iter().copied());
+        callers != self.callers.len() || used != self.used.len()
+    }
+}
+
+/// Whether control can continue to the next instruction
+pub(crate) fn falls_through(instruction: &Instruction) -> bool {
+    !ends_block(instruction) || is_conditional(instruction)
+}
+
+/// The frames recorded in the StackMapTable, by pc, with one entry per slot like `Frame`
+fn stack_map_frames(
+    initial: &Frame,
+    code: &Code,
+    constant_pool: &ConstantPool,
+    instructions: &[(u32, Instruction)],
+) -> Result<BTreeMap<u32, Frame>, Failure> {
+    let mut frames = BTreeMap::new();
+    let mut table = None;
+    for attribute in code.attributes() {
+        if matches!(attribute.name(constant_pool), Ok(name) if name == StackMapTable::NAME) {
+            if table.is_some() {
+                return fail_method("there is more than one StackMapTable");
+            }
+            let mut reader = BufReader::new(attribute.info());
+            table = Some(StackMapTable::from(&mut reader).or_else(|e| fail_method(e.to_string()))?);
+        }
+    }
+    let Some(table) = table else {
+        return Ok(frames);
+    };
+
+    let is_new = |pc: u32| {
+        instructions
+            .binary_search_by_key(&pc, |(pc, _)| *pc)
+            .is_ok_and(|i| matches!(instructions[i].1, Instruction::New(_)))
+    };
+    let value = |pc: u32, verification_type: &VerificationType| -> Result<ValueType, Failure> {
+        Ok(match verification_type {
+            VerificationType::Top => ValueType::Top,
+            VerificationType::Integer => ValueType::Integer,
+            VerificationType::Float => ValueType::Float,
+            VerificationType::Long => ValueType::Long,
+            VerificationType::Double => ValueType::Double,
+            VerificationType::Null => ValueType::Null,
+            VerificationType::UninitializedThis => ValueType::UninitializedThis,
+            VerificationType::Object(index) => ValueType::Reference(
+                constant_pool
+                    .class_name(*index)
+                    .or_else(|e| fail(pc, e.to_string()))?,
+            ),
+            VerificationType::Uninitialized(offset) if is_new(*offset as u32) => {
+                ValueType::Uninitialized(*offset as u32)
+            }
+            VerificationType::Uninitialized(offset) => {
+                return fail(pc, format!("uninitialized {offset} doesn't refer to a new"))
+            }
+        })
+    };
+    let values = |pc: u32, types: &[VerificationType]| -> Result<Vec<ValueType>, Failure> {
+        types.iter().map(|t| value(pc, t)).collect()
+    };
+
+    // the recorded locals, one entry per value as in the table
+    let mut locals = Vec::new();
+    let mut i = 0;
+    while i < initial.locals().len() {
+        locals.push(initial.locals()[i].clone());
+        i += if initial.locals()[i].is_wide() { 2 } else { 1 };
+    }
+    let mut previous: Option<u32> = None;
+    for entry in table.entries() {
+        let pc = match previous {
+            None => entry.offset_delta() as u32,
+            Some(previous) => previous + entry.offset_delta() as u32 + 1,
+        };
+        let stack = match entry {
+            StackMapFrame::Same { .. } | StackMapFrame::SameExtended { .. } => Vec::new(),
+            StackMapFrame::SameLocals1StackItem { stack, .. }
+            | StackMapFrame::SameLocals1StackItemExtended { stack, .. } => {
+                vec![value(pc, stack)?]
+            }
+            StackMapFrame::Chop { absent, .. } => {
+                let Some(kept) = locals.len().checked_sub(*absent as usize) else {
+                    return fail(pc, format!("chops {absent} locals but there are fewer"));
+                };
+                locals.truncate(kept);
+                Vec::new()
+            }
+            StackMapFrame::Append { locals: added, .. } => {
+                locals.extend(values(pc, added)?);
+                Vec::new()
+            }
+            StackMapFrame::Full {
+                locals: all, stack, ..
+            } => {
+                locals = values(pc, all)?;
+                values(pc, stack)?
+            }
+        };
+        if instructions
+            .binary_search_by_key(&pc, |(pc, _)| *pc)
+            .is_err()
+        {
+            return fail(pc, "a stack map frame is not at an instruction");
+        }
+        let frame = Frame::new(slots(&locals), slots(&stack));
+        if frame.locals().len() > code.max_locals() as usize {
+            return fail(pc, "the stack map frame has more locals than max_locals");
+        }
+        if frame.stack().len() > code.max_stack() as usize {
+            return fail(pc, "the stack map frame's stack is deeper than max_stack");
+        }
+        frames.insert(pc, frame);
+        previous = Some(pc);
+    }
+    Ok(frames)
+}
+
+/// Long and double values followed by their top second slot
+fn slots(values: &[ValueType]) -> Vec<ValueType> {
+    let mut slots = Vec::with_capacity(values.len());
+    for value in values {
+        slots.push(value.clone());
+        if value.is_wide() {
+            slots.push(ValueType::Top);
+        }
+    }
+    slots
+}
+
+/// Whether `frame` can flow into `target` (section 4.10.1.4), describing the first
+/// difference otherwise
+fn check_assignable(
+    frame: &Frame,
+    target: &Frame,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), String> {
+    if frame.stack().len() != target.stack().len() {
+        return Err(format!(
+            "the operand stack has {} slots where the frame has {}",
+            frame.stack().len(),
+            target.stack().len()
+        ));
+    }
+    for (i, (from, to)) in frame.stack().iter().zip(target.stack()).enumerate() {
+        if !from.is_assignable_to(to, hierarchy) {
+            return Err(format!("stack slot {i} is {from} where the frame has {to}"));
+        }
+    }
+    let length = frame.locals().len().max(target.locals().len());
+    for i in 0..length {
+        let from = frame.locals().get(i).unwrap_or(&ValueType::Top);
+        let to = target.locals().get(i).unwrap_or(&ValueType::Top);
+        if !from.is_assignable_to(to, hierarchy) {
+            return Err(format!("local {i} is {from} where the frame has {to}"));
+        }
+    }
+    Ok(())
+}
+
+/// The locals at a protected instruction, with just the exception on the stack, have to be
+/// assignable to the handler's frame (section 4.10.1.6)
+fn check_handler(
+    frame: &Frame,
+    exception: &ValueType,
+    handler: &Frame,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), String> {
+    let thrown = Frame::new(frame.locals().to_vec(), vec![exception.clone()]);
+    check_assignable(&thrown, handler, hierarchy)
+        .map_err(|message| format!("doesn't match the exception handler's frame: {message}"))
+}
+
+/// Return instructions have to match the method's return type, and constructors have to
+/// call another constructor on `this` before returning
+fn check_return(
+    frame: &Frame,
+    instruction: &Instruction,
+    method_name: &str,
+    descriptor: &MethodDescriptor,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), String> {
+    let return_type = descriptor.return_type();
+    let returned = match (instruction, return_type) {
+        (Instruction::Return, None) => {
+            if method_name == "<init>" && frame.locals().contains(&ValueType::UninitializedThis) {
+                return Err("the constructor returns before calling super() or this()".into());
+            }
+            return Ok(());
+        }
+        (
+            Instruction::Ireturn,
+            Some(
+                FieldType::Boolean
+                | FieldType::Byte
+                | FieldType::Char
+                | FieldType::Short
+                | FieldType::Int,
+            ),
+        )
+        | (Instruction::Lreturn, Some(FieldType::Long))
+        | (Instruction::Freturn, Some(FieldType::Float))
+        | (Instruction::Dreturn, Some(FieldType::Double)) => return Ok(()),
+        (Instruction::Areturn, Some(FieldType::Object(_) | FieldType::Array(_))) => {
+            ValueType::from_field_type(return_type.unwrap())
+        }
+        (
+            Instruction::Return
+            | Instruction::Ireturn
+            | Instruction::Lreturn
+            | Instruction::Freturn
+            | Instruction::Dreturn
+            | Instruction::Areturn,
+            _,
+        ) => {
+            let expected = return_type.map_or("void".to_string(), FieldType::descriptor);
+            return Err(format!(
+                "{} in a method returning {expected}",
+                instruction.mnemonic()
+            ));
+        }
+        _ => return Ok(()),
+    };
+    match frame.stack().last() {
+        Some(value) if !value.is_assignable_to(&returned, hierarchy) => {
+            Err(format!("returns {value}, not {returned}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A protected member of a superclass in another package can only be used through a
+/// reference to the current class or one of its subclasses (section 4.10.1.8)
+fn check_protected(
+    frame: &Frame,
+    instruction: &Instruction,
+    class_name: &str,
+    constant_pool: &ConstantPool,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), String> {
+    let (Instruction::Getfield(index)
+    | Instruction::Putfield(index)
+    | Instruction::Invokevirtual(index)) = instruction
+    else {
+        return Ok(());
+    };
+    let Ok((member_class, name, descriptor)) = constant_pool.member_ref(*index) else {
+        return Ok(());
+    };
+    let super_classes = hierarchy.super_classes(class_name);
+    if member_class == class_name || !super_classes.contains(&member_class) {
+        return Ok(());
+    }
+    let declared = hierarchy
+        .super_classes(&member_class)
+        .into_iter()
+        .find_map(|class| {
+            let flags = hierarchy.member_access_flags(&class, &name, &descriptor)?;
+            Some((class, flags))
+        });
+    let Some((declaring_class, flags)) = declared else {
+        return Ok(());
+    };
+    if flags & ACC_PROTECTED == 0 || package(&declaring_class) == package(class_name) {
+        return Ok(());
+    }
+
+    // the receiver sits below the value or arguments
+    let depth = match instruction {
+        Instruction::Getfield(_) => 0,
+        Instruction::Putfield(_) => field_slots(&descriptor),
+        _ => MethodDescriptor::parse(&descriptor)
+            .map(|descriptor| descriptor.parameters().iter().map(slots_of).sum())
+            .unwrap_or_default(),
+    };
+    let stack = frame.stack();
+    let Some(receiver) = stack.len().checked_sub(depth + 1).map(|i| &stack[i]) else {
+        return Ok(());
+    };
+    let current = ValueType::Reference(class_name.to_string());
+    if receiver.is_assignable_to(&current, hierarchy) {
+        Ok(())
+    } else {
+        Err(format!(
+            "protected {declaring_class}.{name} used through {receiver}, not a {class_name}"
+        ))
+    }
+}
+
+fn package(class_name: &str) -> &str {
+    class_name
+        .rsplit_once('/')
+        .map_or("", |(package, _)| package)
+}
+
+fn slots_of(field_type: &FieldType) -> usize {
+    match field_type {
+        FieldType::Long | FieldType::Double => 2,
+        _ => 1,
+    }
+}
+
+fn field_slots(descriptor: &str) -> usize {
+    FieldType::parse(descriptor).map_or(1, |field_type| slots_of(&field_type))
+}
+
+/// Instructions that change locals, whose handlers also see the locals afterwards
+pub(crate) fn is_store(instruction: &Instruction) -> bool {
+    matches!(
+        instruction.opcode(),
+        ISTORE..=ASTORE | ISTORE_0..=ASTORE_3 | IINC
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::frames::recompute_frames;
+    use crate::analysis::hierarchy::{ClassFileHierarchy, OBJECT};
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::attribute::attribute_info::AttributeInfo;
+
+    fn hierarchy() -> ClassFileHierarchy {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add("java/lang/Number", Some(OBJECT), false);
+        hierarchy.add("java/lang/Integer", Some("java/lang/Number"), false);
+        hierarchy.add("java/lang/String", Some(OBJECT), false);
+        hierarchy.add(THROWABLE, Some(OBJECT), false);
+        hierarchy.add("java/lang/Exception", Some(THROWABLE), false);
+        hierarchy
+    }
+
+    /// Assembles a class with the given methods, computes its frames and verifies it
+    fn verify(methods: &str) -> Vec<String> {
+        let source = format!(".class T\n.super java/lang/Object\n{methods}");
+        let mut class_file = assemble(&source).unwrap();
+        let mut hierarchy = hierarchy();
+        hierarchy.add_class_file(&class_file).unwrap();
+        recompute_frames(&mut class_file, &hierarchy).unwrap();
+        verify_class(&class_file, &hierarchy)
+            .iter()
+            .map(VerifyError::to_string)
+            .collect()
+    }
+
+    /// Swaps the StackMapTable of the first method for `info`
+    fn replace_stack_map(class_file: &mut ClassFile, info: Vec<u8>) {
+        let constant_pool = class_file.constant_pool();
+        let code = class_file.methods()[0]
+            .code(constant_pool)
+            .unwrap()
+            .unwrap();
+        let attributes = code
+            .attributes()
+            .iter()
+            .map(
+                |attribute| match attribute.name(constant_pool).unwrap().as_str() {
+                    StackMapTable::NAME => {
+                        AttributeInfo::new(attribute.attribute_name_index(), info.clone())
+                    }
+                    _ => attribute.clone(),
+                },
+            )
+            .collect();
+        let code = Code::new(
+            code.max_stack(),
+            code.max_locals(),
+            code.code().to_vec(),
+            code.exception_table().to_vec(),
+            attributes,
+        );
+        let mut bytes = Vec::new();
+        code.write(&mut bytes);
+        let method = &mut class_file.methods_mut()[0];
+        let name_index = method.attributes()[0].attribute_name_index();
+        method.attributes_mut()[0] = AttributeInfo::new(name_index, bytes);
+    }
+
+    #[test]
+    fn accepts_well_typed_code() {
+        let errors = verify(
+            "
+.method <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method static f(Ljava/lang/Integer;I)Ljava/lang/Number;
+    .catch java/lang/Exception from Start to End using Handler
+Start:
+    iload_1
+    ifeq Zero
+    aload_0
+    areturn
+Zero:
+    new java/lang/Integer
+    dup
+    iconst_0
+    invokespecial java/lang/Integer/<init>(I)V
+End:
+    areturn
+Handler:
+    athrow
+.end method
+.method abstract g()V
+.end method
+",
+        );
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_operand_type_errors() {
+        let errors = verify(
+            "
+.method static f(Ljava/lang/String;)V
+    aload_0
+    invokestatic T/g(Ljava/lang/Integer;)V
+    return
+.end method
+.method static h(Ljava/lang/String;)Ljava/lang/Number;
+    aload_0
+    areturn
+.end method
+.method static i([I)I
+    aload_0
+    iconst_0
+    baload
+    ireturn
+.end method
+",
+        );
+        assert_eq!(
+            errors,
+            [
+                "VerifyError: T.f(Ljava/lang/String;)V at pc 1: expected java/lang/Integer on the \
+                 stack but found java/lang/String",
+                "VerifyError: T.h(Ljava/lang/String;)Ljava/lang/Number; at pc 1: returns \
+                 java/lang/String, not java/lang/Number",
+                "VerifyError: T.i([I)I at pc 2: baload on [I, which is not a suitable array",
+            ]
+        );
+    }
+
+    #[test]
+    fn tracks_uninitialized_objects() {
+        let errors = verify(
+            "
+.method <init>()V
+    return
+.end method
+.method static f()V
+    new java/lang/Integer
+    dup
+    invokestatic T/g(Ljava/lang/Object;)V
+    return
+.end method
+.method static g()V
+    new java/lang/Integer
+    invokespecial java/lang/Number/<init>()V
+    return
+.end method
+",
+        );
+        assert_eq!(
+            errors,
+            [
+                "VerifyError: T.<init>()V at pc 0: the constructor returns before calling \
+                 super() or this()",
+                "VerifyError: T.f()V at pc 4: expected java/lang/Object on the stack but found \
+                 uninitialized 0",
+                "VerifyError: T.g()V at pc 3: java/lang/Number.<init> called on a new \
+                 java/lang/Integer",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_code_against_the_stack_map() {
+        let source = "
+.class T
+.super java/lang/Object
+.method static f(I)I
+    .limit stack 1
+    iload_0
+    ifeq Zero
+    iconst_1
+    ireturn
+Zero:
+    iconst_0
+    ireturn
+.end method
+.method static g()V
+    .limit stack 1
+    iconst_0
+    ireturn
+.end method
+";
+        let mut class_file = assemble(source).unwrap();
+        let errors = verify_class(&class_file, &hierarchy());
+        assert_eq!(
+            errors
+                .iter()
+                .map(VerifyError::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "VerifyError: T.f(I)I at pc 1: the branch target 6 has no stack map frame",
+                "VerifyError: T.g()V at pc 1: ireturn in a method returning void",
+            ]
+        );
+        assert_eq!(errors[0].method(), Some("f(I)I"));
+        assert_eq!(errors[0].pc(), Some(1));
+
+        // a frame that claims local 0 is a float at the branch target
+        recompute_frames(&mut class_file, &hierarchy()).unwrap();
+        #[rustfmt::skip]
+        let float_frame = vec![
+            0x00, 0x01,                         // 1 entry
+            0xff, 0x00, 0x06, 0x00, 0x01, 0x02, // full_frame at 6, locals = [float]
+            0x00, 0x00,                         // stack = []
+        ];
+        replace_stack_map(&mut class_file, float_frame);
+        assert_eq!(
+            verify_class(&class_file, &hierarchy())[0].to_string(),
+            "VerifyError: T.f(I)I at pc 1: doesn't match the stack map frame at branch target \
+             6: local 0 is int where the frame has float"
+        );
+
+        // without frames, version 49 infers them and only g is wrong
+        class_file.set_version(49, 0);
+        assert_eq!(
+            verify_class(&class_file, &hierarchy())
+                .iter()
+                .map(VerifyError::to_string)
+                .collect::<Vec<_>>(),
+            ["VerifyError: T.g()V at pc 1: ireturn in a method returning void"]
+        );
+    }
+
+    #[test]
+    fn protected_members_need_a_subclass_receiver() {
+        let mut hierarchy = hierarchy();
+        hierarchy.add("q/A", Some(OBJECT), false);
+        hierarchy.add_member("q/A", "f", "I", ACC_PROTECTED);
+        let source = "
+.class p/B
+.super q/A
+.method static f(Lq/A;Lp/B;)I
+    .limit stack 2
+    aload_1
+    getfield q/A/f I
+    aload_0
+    getfield q/A/f I
+    iadd
+    ireturn
+.end method
+";
+        let class_file = assemble(source).unwrap();
+        hierarchy.add_class_file(&class_file).unwrap();
+        assert_eq!(
+            verify_class(&class_file, &hierarchy)
+                .iter()
+                .map(VerifyError::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "VerifyError: p/B.f(Lq/A;Lp/B;)I at pc 5: protected q/A.f used through q/A, not \
+              a p/B"
+            ]
+        );
+    }
+
+    /// Assembles a version 49 class, which is verified by type inference
+    fn verify_old(methods: &str) -> Vec<String> {
+        let source = format!(".version 49 0\n.class T\n.super java/lang/Object\n{methods}");
+        let class_file = assemble(&source).unwrap();
+        verify_class(&class_file, &hierarchy())
+            .iter()
+            .map(VerifyError::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn infers_types_through_loops_and_handlers() {
+        let errors = verify_old(
+            "
+.method static f(I)Ljava/lang/Number;
+    .limit stack 3
+    .limit locals 3
+    .catch java/lang/Exception from Start to End using Handler
+    aconst_null
+    astore_1
+Loop:
+    iload_0
+    ifle Done
+    new java/lang/Integer
+    dup
+    iload_0
+    invokespecial java/lang/Integer/<init>(I)V
+    astore_1
+    iinc 0 -1
+    goto Loop
+Done:
+Start:
+    aload_1
+    areturn
+End:
+Handler:
+    astore_2
+    aconst_null
+    areturn
+.end method
+.method static g(Z)I
+    .limit stack 1
+    .limit locals 2
+    iload_0
+    ifeq Float
+    iconst_0
+    istore_1
+    goto Join
+Float:
+    fconst_0
+    fstore_1
+Join:
+    iload_1
+    ireturn
+.end method
+.method static h(Z)I
+    .limit stack 1
+    iload_0
+    ifeq Join
+    iconst_1
+Join:
+    ireturn
+.end method
+",
+        );
+        assert_eq!(
+            errors,
+            [
+                "VerifyError: T.g(Z)I at pc 11: local 1 holds top, not int",
+                "VerifyError: T.h(Z)I at pc 5: paths meet with stack heights 0 and 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn subroutines_keep_their_callers_locals() {
+        // a finally block called with an int in local 0 from one place and a String from
+        // another, which it doesn't touch
+        let errors = verify_old(
+            "
+.method static f(Z)Ljava/lang/String;
+    .limit stack 1
+    .limit locals 2
+    iload_0
+    ifeq Text
+    iconst_1
+    istore_0
+    jsr Finally
+    iload_0
+    pop
+    aconst_null
+    areturn
+Text:
+    ldc \"x\"
+    astore_0
+    jsr Finally
+    aload_0
+    areturn
+Finally:
+    astore_1
+    ret 1
+.end method
+",
+        );
+        assert_eq!(errors, Vec::<String>::new());
+
+        let errors = verify_old(
+            "
+.method static f()V
+    .limit stack 1
+    .limit locals 1
+    iconst_0
+    istore_0
+    ret 0
+.end method
+.method static g()V
+    .limit stack 1
+    .limit locals 1
+    jsr Sub
+    return
+Sub:
+    astore_0
+    jsr Sub
+    return
+.end method
+.method static h()V
+    .limit stack 1
+    .limit locals 1
+    jsr Sub
+    return
+Sub:
+    pop
+    iconst_0
+    istore_0
+    ret 0
+.end method
+",
+        );
+        assert_eq!(
+            errors,
+            [
+                "VerifyError: T.f()V at pc 2: ret with local 0, which holds int, not a return \
+                 address",
+                "VerifyError: T.g()V at pc 5: the subroutine at 4 calls itself",
+                "VerifyError: T.h()V at pc 7: ret with local 0, which holds int, not a return \
+                 address",
+            ]
+        );
+    }
+
+    #[test]
+    fn version_50_falls_back_to_type_inference() {
+        let source = "
+.version 50 0
+.class T
+.super java/lang/Object
+.method static f()V
+    .limit stack 1
+    .limit locals 1
+    jsr Sub
+    return
+Sub:
+    astore_0
+    ret 0
+.end method
+";
+        let mut class_file = assemble(source).unwrap();
+        assert_eq!(verify_class(&class_file, &hierarchy()), []);
+        class_file.set_version(51, 0);
+        assert_eq!(
+            verify_class(&class_file, &hierarchy())[0].to_string(),
+            "VerifyError: T.f()V at pc 0: jsr and ret can't be used in class files with stack \
+             map frames"
+        );
+    }
+}
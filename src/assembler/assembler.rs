@@ -0,0 +1,1722 @@
+use crate::assembler::lexer::{syntax_error, tokenize, Line, Token};
+use crate::bytecode::opcode::{self, Opcode, OperandKind, ARRAY_TYPES};
+use crate::class_file::access_flags::{
+    flag_from_keyword, FlagTarget, ACC_ABSTRACT, ACC_INTERFACE, ACC_NATIVE, ACC_STATIC,
+};
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::bootstrap_methods::{BootstrapMethod, BootstrapMethods};
+use crate::class_file::attribute::code::{Code, ExceptionTableEntry};
+use crate::class_file::attribute::constant_value::ConstantValue;
+use crate::class_file::attribute::exceptions::Exceptions;
+use crate::class_file::attribute::line_number_table::{LineNumber, LineNumberTable};
+use crate::class_file::attribute::local_variable_table::{LocalVariable, LocalVariableTable};
+use crate::class_file::attribute::source_file::SourceFile;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
+use crate::class_file::constant_pool::types::class_info::ClassInfo;
+use crate::class_file::constant_pool::types::double::Double;
+use crate::class_file::constant_pool::types::field_ref::FieldRef;
+use crate::class_file::constant_pool::types::float::Float;
+use crate::class_file::constant_pool::types::integer::Integer;
+use crate::class_file::constant_pool::types::interface_method_ref::InterfaceMethodRef;
+use crate::class_file::constant_pool::types::invoke_dynamic::InvokeDynamic;
+use crate::class_file::constant_pool::types::jvm_string::JvmString;
+use crate::class_file::constant_pool::types::long::Long;
+use crate::class_file::constant_pool::types::method_handle::{MethodHandle, ReferenceKind};
+use crate::class_file::constant_pool::types::method_ref::MethodRef;
+use crate::class_file::constant_pool::types::method_type::MethodType;
+use crate::class_file::constant_pool::types::name_and_type::NameAndType;
+use crate::class_file::constant_pool::types::utf8::Utf8;
+use crate::class_file::descriptor::{FieldType, MethodDescriptor};
+use crate::class_file::member_info::MemberInfo;
+use std::collections::HashMap;
+use std::io;
+
+/// Assembles Jasmin-style source into a class file.
+///
+/// ```text
+/// .class public HelloWorld
+/// .super java/lang/Object
+///
+/// .method public static main([Ljava/lang/String;)V
+///     .limit stack 2
+///     getstatic java/lang/System/out Ljava/io/PrintStream;
+///     ldc "Hello, World!"
+///     invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+///     return
+/// .end method
+/// ```
+///
+/// Besides the usual directives (`.version`, `.source`, `.class`/`.interface`, `.super`,
+/// `.implements`, `.field`, `.method`, `.limit`, `.throws`, `.catch`, `.line`, `.var`) there are
+/// a few for writing class files javac would never produce:
+/// - `.constant <name> <Tag> <values...>` appends a raw, non-interned constant pool entry.
+///   These are added before anything else so the first one is always `#1`. Other operands can
+///   refer to them (or any index) as `#name` or `#<index>`.
+/// - `.bytes <hex...>` emits raw bytes into a method's code array.
+/// - `.attribute <name> <hex...>` adds a raw attribute to the current method, or the class.
+///
+/// Branch offsets must fit in 16 bits unless `goto_w`/`jsr_w` are used. No StackMapTable is
+/// generated, and `max_stack` is 0 unless given with `.limit stack`.
+pub fn assemble(source: &str) -> Result<ClassFile, io::Error> {
+    let lines = tokenize(source)?;
+    let mut assembler = Assembler::new();
+
+    // raw constants go first so their indices are predictable
+    for line in lines.iter() {
+        if line.tokens()[0].text() == ".constant" {
+            assembler
+                .raw_constant(line)
+                .map_err(|e| at_line(line.number(), e))?;
+        }
+    }
+
+    let mut i = 0;
+    while i < lines.len() {
+        i = assembler
+            .line(&lines, i)
+            .map_err(|e| at_line(lines[i].number(), e))?;
+    }
+
+    assembler.finish()
+}
+
+/// Adds the line number to errors from elsewhere, such as a constant pool that's full or a
+/// string too long for a Utf8 constant
+fn at_line(line: usize, error: io::Error) -> io::Error {
+    let message = error.to_string();
+    if message.starts_with("line ") {
+        error
+    } else {
+        syntax_error(line, &message)
+    }
+}
+
+/// One element of a method body before offsets are known
+enum BodyItem {
+    Label(String),
+    /// Fully encoded instruction (or raw `.bytes`)
+    Bytes(Vec<u8>),
+    Branch {
+        line: usize,
+        opcode: u8,
+        target: String,
+    },
+    TableSwitch {
+        line: usize,
+        low: i32,
+        targets: Vec<String>,
+        default: String,
+    },
+    LookupSwitch {
+        line: usize,
+        pairs: Vec<(i32, String)>,
+        default: String,
+    },
+    LineNumber(u16),
+}
+
+struct Catch {
+    line: usize,
+    catch_type: u16,
+    from: String,
+    to: String,
+    using: String,
+}
+
+struct Var {
+    line: usize,
+    index: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    from: String,
+    to: String,
+}
+
+struct MethodBody {
+    access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    max_stack: Option<u16>,
+    max_locals: Option<u16>,
+    /// Highest local variable slot touched by a load/store, plus one
+    locals_used: u16,
+    items: Vec<BodyItem>,
+    catches: Vec<Catch>,
+    vars: Vec<Var>,
+    throws: Vec<u16>,
+    attributes: Vec<AttributeInfo>,
+}
+
+struct Assembler {
+    builder: ConstantPoolBuilder,
+    named_constants: HashMap<String, u16>,
+    version: (u16, u16),
+    access_flags: u16,
+    this_class: Option<u16>,
+    super_class: Option<u16>,
+    interfaces: Vec<u16>,
+    fields: Vec<MemberInfo>,
+    methods: Vec<MemberInfo>,
+    attributes: Vec<AttributeInfo>,
+    bootstrap_methods: Vec<BootstrapMethod>,
+    method: Option<MethodBody>,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            builder: ConstantPoolBuilder::new(),
+            named_constants: HashMap::new(),
+            version: (52, 0),
+            access_flags: 0,
+            this_class: None,
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            bootstrap_methods: Vec::new(),
+            method: None,
+        }
+    }
+
+    /// Handles the line at `i` and returns the index of the next unprocessed line
+    fn line(&mut self, lines: &[Line], i: usize) -> Result<usize, io::Error> {
+        let line = &lines[i];
+        let number = line.number();
+        let mut tokens = line.tokens();
+
+        // labels may share a line with an instruction
+        let first = tokens[0].text();
+        if matches!(tokens[0], Token::Word(_)) && first.len() > 1 && first.ends_with(':') {
+            let method = self.method_body(number)?;
+            method
+                .items
+                .push(BodyItem::Label(first[..first.len() - 1].to_string()));
+            tokens = &tokens[1..];
+            if tokens.is_empty() {
+                return Ok(i + 1);
+            }
+        }
+
+        let keyword = tokens[0].text();
+        let args = &tokens[1..];
+        match keyword {
+            ".constant" => {}
+            ".version" => {
+                expect_args(number, args, 1, 2)?;
+                let major = parse_number::<u16>(number, args[0].text())?;
+                let minor = match args.get(1) {
+                    Some(token) => parse_number::<u16>(number, token.text())?,
+                    None => 0,
+                };
+                self.version = (major, minor);
+            }
+            ".source" => {
+                expect_args(number, args, 1, 1)?;
+                let sourcefile_index = self.builder.add_utf8(args[0].text())?;
+                let mut info = Vec::new();
+                SourceFile::new(sourcefile_index).write(&mut info);
+                let name_index = self.builder.add_utf8(SourceFile::NAME)?;
+                self.attributes.push(AttributeInfo::new(name_index, info));
+            }
+            ".class" | ".interface" => {
+                let (flags, name) = self.flags_and_name(number, args, FlagTarget::Class)?;
+                self.access_flags = flags;
+                if keyword == ".interface" {
+                    self.access_flags |= ACC_INTERFACE | ACC_ABSTRACT;
+                }
+                self.this_class = Some(self.class_operand(number, &name)?);
+            }
+            ".super" => {
+                expect_args(number, args, 1, 1)?;
+                self.super_class = Some(self.class_operand(number, args[0].text())?);
+            }
+            ".implements" => {
+                expect_args(number, args, 1, 1)?;
+                let interface = self.class_operand(number, args[0].text())?;
+                self.interfaces.push(interface);
+            }
+            ".field" => self.field(number, args)?,
+            ".method" => self.start_method(number, args)?,
+            ".end" => {
+                expect_args(number, args, 1, 1)?;
+                if args[0].text() != "method" {
+                    return Err(syntax_error(number, "expected '.end method'"));
+                }
+                self.end_method(number)?;
+            }
+            ".limit" => {
+                expect_args(number, args, 2, 2)?;
+                let value = parse_number::<u16>(number, args[1].text())?;
+                let method = self.method_body(number)?;
+                match args[0].text() {
+                    "stack" => method.max_stack = Some(value),
+                    "locals" => method.max_locals = Some(value),
+                    other => {
+                        return Err(syntax_error(
+                            number,
+                            &format!("unknown limit '{other}', expected stack or locals"),
+                        ))
+                    }
+                }
+            }
+            ".throws" => {
+                expect_args(number, args, 1, 1)?;
+                let class = self.class_operand(number, args[0].text())?;
+                self.method_body(number)?.throws.push(class);
+            }
+            ".catch" => self.catch(number, args)?,
+            ".line" => {
+                expect_args(number, args, 1, 1)?;
+                let line_number = parse_number::<u16>(number, args[0].text())?;
+                self.method_body(number)?
+                    .items
+                    .push(BodyItem::LineNumber(line_number));
+            }
+            ".var" => self.var(number, args)?,
+            ".bytes" => {
+                let bytes = parse_hex(number, args)?;
+                self.method_body(number)?.items.push(BodyItem::Bytes(bytes));
+            }
+            ".attribute" => {
+                if args.is_empty() {
+                    return Err(syntax_error(number, "expected an attribute name"));
+                }
+                let name_index = self.builder.add_utf8(args[0].text())?;
+                let attribute = AttributeInfo::new(name_index, parse_hex(number, &args[1..])?);
+                match self.method.as_mut() {
+                    Some(method) => method.attributes.push(attribute),
+                    None => self.attributes.push(attribute),
+                }
+            }
+            ".bootstrap" => self.bootstrap(number, args)?,
+            "tableswitch" => return self.table_switch(lines, i, args),
+            "lookupswitch" => return self.lookup_switch(lines, i, args),
+            _ if keyword.starts_with('.') => {
+                return Err(syntax_error(
+                    number,
+                    &format!("unknown directive '{keyword}'"),
+                ))
+            }
+            _ => self.instruction(number, tokens)?,
+        }
+
+        Ok(i + 1)
+    }
+
+    fn finish(mut self) -> Result<ClassFile, io::Error> {
+        if let Some(method) = self.method.as_ref() {
+            let name = self.builder.constant_pool().utf8(method.name_index)?;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("method {name} is missing '.end method'"),
+            ));
+        }
+        let this_class = self.this_class.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "missing .class directive")
+        })?;
+        let super_class = match self.super_class {
+            Some(super_class) => super_class,
+            None => self.builder.add_class("java/lang/Object")?,
+        };
+
+        if !self.bootstrap_methods.is_empty() {
+            let mut info = Vec::new();
+            BootstrapMethods::new(std::mem::take(&mut self.bootstrap_methods)).write(&mut info);
+            let name_index = self.builder.add_utf8(BootstrapMethods::NAME)?;
+            self.attributes.push(AttributeInfo::new(name_index, info));
+        }
+
+        let mut class_file = ClassFile::new();
+        class_file.set_version(self.version.0, self.version.1);
+        class_file.set_constant_pool(self.builder.build());
+        class_file.set_access_flags(self.access_flags);
+        class_file.set_this_class(this_class);
+        class_file.set_super_class(super_class);
+        *class_file.interfaces_mut() = self.interfaces;
+        *class_file.fields_mut() = self.fields;
+        *class_file.methods_mut() = self.methods;
+        *class_file.attributes_mut() = self.attributes;
+        Ok(class_file)
+    }
+
+    fn method_body(&mut self, line: usize) -> Result<&mut MethodBody, io::Error> {
+        self.method
+            .as_mut()
+            .ok_or_else(|| syntax_error(line, "only allowed inside a method"))
+    }
+
+    /// Splits `<flags...> <name>` into the combined flags and the name
+    fn flags_and_name(
+        &self,
+        line: usize,
+        args: &[Token],
+        target: FlagTarget,
+    ) -> Result<(u16, String), io::Error> {
+        let (name, flags) = args
+            .split_last()
+            .ok_or_else(|| syntax_error(line, "expected a name"))?;
+        Ok((parse_flags(line, flags, target)?, name.text().to_string()))
+    }
+
+    fn raw_constant(&mut self, line: &Line) -> Result<(), io::Error> {
+        let number = line.number();
+        let args = &line.tokens()[1..];
+        if args.len() < 3 {
+            return Err(syntax_error(
+                number,
+                "expected '.constant <name> <Tag> <values...>'",
+            ));
+        }
+        let name = args[0].text().to_string();
+        let values = &args[2..];
+        let index = |i: usize| -> Result<u16, io::Error> {
+            let token = values
+                .get(i)
+                .ok_or_else(|| syntax_error(number, "missing constant pool index"))?;
+            self.raw_index(number, token.text())
+        };
+
+        let data = match args[1].text() {
+            "Utf8" => ConstantPoolData::Utf8(Utf8::from_string(values[0].text())?),
+            "Integer" => {
+                ConstantPoolData::Integer(Integer::from_value(parse_int(number, values[0].text())?))
+            }
+            "Float" => {
+                ConstantPoolData::Float(Float::from_value(parse_float(number, values[0].text())?))
+            }
+            "Long" => {
+                ConstantPoolData::Long(Long::from_value(parse_long(number, values[0].text())?))
+            }
+            "Double" => ConstantPoolData::Double(Double::from_value(parse_double(
+                number,
+                values[0].text(),
+            )?)),
+            "Class" => ConstantPoolData::ClassInfo(ClassInfo::from_name_index(index(0)?)),
+            "String" => ConstantPoolData::String(JvmString::from_string_index(index(0)?)),
+            "Fieldref" => ConstantPoolData::FieldRef(FieldRef::from_indices(index(0)?, index(1)?)),
+            "Methodref" => {
+                ConstantPoolData::MethodRef(MethodRef::from_indices(index(0)?, index(1)?))
+            }
+            "InterfaceMethodref" => ConstantPoolData::InterfaceMethodRef(
+                InterfaceMethodRef::from_indices(index(0)?, index(1)?),
+            ),
+            "NameAndType" => {
+                ConstantPoolData::NameAndType(NameAndType::from_indices(index(0)?, index(1)?))
+            }
+            "MethodHandle" => {
+                let kind = parse_number::<u8>(number, values[0].text())?;
+                ConstantPoolData::MethodHandle(MethodHandle::from_reference(
+                    ReferenceKind::from_u8(kind),
+                    index(1)?,
+                ))
+            }
+            "MethodType" => {
+                ConstantPoolData::MethodType(MethodType::from_descriptor_index(index(0)?))
+            }
+            "InvokeDynamic" => ConstantPoolData::InvokeDynamic(InvokeDynamic::from_indices(
+                parse_number::<u16>(number, values[0].text())?,
+                index(1)?,
+            )),
+            other => {
+                return Err(syntax_error(
+                    number,
+                    &format!("unknown constant pool tag '{other}'"),
+                ))
+            }
+        };
+
+        let index = self
+            .builder
+            .push_item(ConstantPoolItem::from_data(data))
+            .map_err(|e| syntax_error(number, &e.to_string()))?;
+        if self.named_constants.insert(name.clone(), index).is_some() {
+            return Err(syntax_error(
+                number,
+                &format!("constant '{name}' is already defined"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resolves `#12` or `#name` (a `.constant`) to a constant pool index
+    fn raw_index(&self, line: usize, text: &str) -> Result<u16, io::Error> {
+        let reference = text
+            .strip_prefix('#')
+            .ok_or_else(|| syntax_error(line, &format!("expected '#index' but found '{text}'")))?;
+        if let Some(index) = self.named_constants.get(reference) {
+            return Ok(*index);
+        }
+        reference
+            .parse::<u16>()
+            .map_err(|_| syntax_error(line, &format!("unknown constant '{text}'")))
+    }
+
+    fn class_operand(&mut self, line: usize, text: &str) -> Result<u16, io::Error> {
+        if text.starts_with('#') {
+            return self.raw_index(line, text);
+        }
+        self.builder.add_class(text)
+    }
+
+    fn field(&mut self, line: usize, args: &[Token]) -> Result<(), io::Error> {
+        // .field <flags...> <name> <descriptor> [= <value>]
+        let (declaration, value) = match args.iter().position(|t| t.text() == "=") {
+            Some(position) => (&args[..position], args.get(position + 1)),
+            None => (args, None),
+        };
+        if declaration.len() < 2 {
+            return Err(syntax_error(
+                line,
+                "expected '.field <flags> <name> <descriptor>'",
+            ));
+        }
+        let (descriptor, rest) = declaration.split_last().unwrap();
+        let (name, flags) = rest.split_last().unwrap();
+        let access_flags = parse_flags(line, flags, FlagTarget::Field)?;
+        let field_type =
+            FieldType::parse(descriptor.text()).map_err(|e| syntax_error(line, &e.to_string()))?;
+
+        let mut attributes = Vec::new();
+        if let Some(value) = value {
+            let constantvalue_index = match (&field_type, value) {
+                (FieldType::Object(class), Token::Str(s)) if class == "java/lang/String" => {
+                    self.builder.add_string(s)?
+                }
+                (_, token) if token.text().starts_with('#') => {
+                    self.raw_index(line, token.text())?
+                }
+                (FieldType::Long, token) => {
+                    self.builder.add_long(parse_long(line, token.text())?)?
+                }
+                (FieldType::Float, token) => {
+                    self.builder.add_float(parse_float(line, token.text())?)?
+                }
+                (FieldType::Double, token) => {
+                    self.builder.add_double(parse_double(line, token.text())?)?
+                }
+                (
+                    FieldType::Int
+                    | FieldType::Short
+                    | FieldType::Char
+                    | FieldType::Byte
+                    | FieldType::Boolean,
+                    token,
+                ) => self.builder.add_integer(parse_int(line, token.text())?)?,
+                _ => {
+                    return Err(syntax_error(
+                        line,
+                        &format!(
+                            "a field of type {} can't have a constant value",
+                            descriptor.text()
+                        ),
+                    ))
+                }
+            };
+            let mut info = Vec::new();
+            ConstantValue::new(constantvalue_index).write(&mut info);
+            attributes.push(AttributeInfo::new(
+                self.builder.add_utf8(ConstantValue::NAME)?,
+                info,
+            ));
+        }
+
+        let name_index = self.builder.add_utf8(name.text())?;
+        let descriptor_index = self.builder.add_utf8(descriptor.text())?;
+        self.fields.push(MemberInfo::new(
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        ));
+        Ok(())
+    }
+
+    fn start_method(&mut self, line: usize, args: &[Token]) -> Result<(), io::Error> {
+        if self.method.is_some() {
+            return Err(syntax_error(
+                line,
+                "nested .method, expected '.end method' first",
+            ));
+        }
+        let (access_flags, signature) = self.flags_and_name(line, args, FlagTarget::Method)?;
+        let open = signature
+            .find('(')
+            .ok_or_else(|| syntax_error(line, "expected '<name>(<descriptor>'"))?;
+        let (name, descriptor) = signature.split_at(open);
+        let parsed =
+            MethodDescriptor::parse(descriptor).map_err(|e| syntax_error(line, &e.to_string()))?;
+
+        let this_slot = if access_flags & ACC_STATIC != 0 { 0 } else { 1 };
+        self.method = Some(MethodBody {
+            access_flags,
+            name_index: self.builder.add_utf8(name)?,
+            descriptor_index: self.builder.add_utf8(descriptor)?,
+            locals_used: parsed.parameter_slots() + this_slot,
+            max_stack: None,
+            max_locals: None,
+            items: Vec::new(),
+            catches: Vec::new(),
+            vars: Vec::new(),
+            throws: Vec::new(),
+            attributes: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn end_method(&mut self, line: usize) -> Result<(), io::Error> {
+        let mut method = self
+            .method
+            .take()
+            .ok_or_else(|| syntax_error(line, "'.end method' without '.method'"))?;
+
+        if !method.throws.is_empty() {
+            let mut info = Vec::new();
+            Exceptions::new(std::mem::take(&mut method.throws)).write(&mut info);
+            let name_index = self.builder.add_utf8(Exceptions::NAME)?;
+            method.attributes.push(AttributeInfo::new(name_index, info));
+        }
+
+        let has_body = !method.items.is_empty();
+        if method.access_flags & (ACC_ABSTRACT | ACC_NATIVE) != 0 {
+            if has_body {
+                return Err(syntax_error(
+                    line,
+                    "abstract and native methods can't have code",
+                ));
+            }
+        } else {
+            let code = self.code(&method)?;
+            let mut info = Vec::new();
+            code.write(&mut info);
+            let name_index = self.builder.add_utf8(Code::NAME)?;
+            method
+                .attributes
+                .insert(0, AttributeInfo::new(name_index, info));
+        }
+
+        self.methods.push(MemberInfo::new(
+            method.access_flags,
+            method.name_index,
+            method.descriptor_index,
+            method.attributes,
+        ));
+        Ok(())
+    }
+
+    /// Lays out the method body, resolves labels and builds the Code attribute
+    fn code(&mut self, method: &MethodBody) -> Result<Code, io::Error> {
+        // first pass: offsets of every label
+        let mut labels: HashMap<&str, u32> = HashMap::new();
+        let mut pc: u32 = 0;
+        for item in method.items.iter() {
+            match item {
+                BodyItem::Label(name) => {
+                    if labels.insert(name, pc).is_some() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("label '{name}' is defined more than once"),
+                        ));
+                    }
+                }
+                _ => pc += item_size(item, pc),
+            }
+        }
+        if pc == 0 || pc > u16::MAX as u32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "method {} must have between 1 and 65535 bytes of code but has {pc}",
+                    self.builder.constant_pool().utf8(method.name_index)?
+                ),
+            ));
+        }
+
+        let resolve = |line: usize, label: &str| -> Result<u32, io::Error> {
+            labels
+                .get(label)
+                .copied()
+                .ok_or_else(|| syntax_error(line, &format!("undefined label '{label}'")))
+        };
+
+        // second pass: emit bytes
+        let mut code: Vec<u8> = Vec::with_capacity(pc as usize);
+        let mut line_numbers = Vec::new();
+        for item in method.items.iter() {
+            let pc = code.len() as u32;
+            match item {
+                BodyItem::Label(_) => {}
+                BodyItem::LineNumber(line_number) => {
+                    line_numbers.push(LineNumber::new(pc as u16, *line_number))
+                }
+                BodyItem::Bytes(bytes) => code.extend_from_slice(bytes),
+                BodyItem::Branch {
+                    line,
+                    opcode,
+                    target,
+                } => {
+                    let offset = resolve(*line, target)? as i64 - pc as i64;
+                    code.push(*opcode);
+                    if *opcode == opcode::GOTO_W || *opcode == opcode::JSR_W {
+                        code.extend_from_slice(&(offset as i32).to_be_bytes());
+                    } else {
+                        let offset = i16::try_from(offset).map_err(|_| {
+                            syntax_error(
+                                *line,
+                                &format!("branch to '{target}' is too far, use goto_w"),
+                            )
+                        })?;
+                        code.extend_from_slice(&offset.to_be_bytes());
+                    }
+                }
+                BodyItem::TableSwitch {
+                    line,
+                    low,
+                    targets,
+                    default,
+                } => {
+                    code.push(opcode::TABLESWITCH);
+                    pad_switch(&mut code);
+                    let offset = |label: &str| -> Result<i32, io::Error> {
+                        Ok((resolve(*line, label)? as i64 - pc as i64) as i32)
+                    };
+                    code.extend_from_slice(&offset(default)?.to_be_bytes());
+                    code.extend_from_slice(&low.to_be_bytes());
+                    let high = *low + targets.len() as i32 - 1;
+                    code.extend_from_slice(&high.to_be_bytes());
+                    for target in targets.iter() {
+                        code.extend_from_slice(&offset(target)?.to_be_bytes());
+                    }
+                }
+                BodyItem::LookupSwitch {
+                    line,
+                    pairs,
+                    default,
+                } => {
+                    code.push(opcode::LOOKUPSWITCH);
+                    pad_switch(&mut code);
+                    let offset = |label: &str| -> Result<i32, io::Error> {
+                        Ok((resolve(*line, label)? as i64 - pc as i64) as i32)
+                    };
+                    code.extend_from_slice(&offset(default)?.to_be_bytes());
+                    code.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+                    for (key, target) in pairs.iter() {
+                        code.extend_from_slice(&key.to_be_bytes());
+                        code.extend_from_slice(&offset(target)?.to_be_bytes());
+                    }
+                }
+            }
+        }
+
+        let mut exception_table = Vec::new();
+        for catch in method.catches.iter() {
+            exception_table.push(ExceptionTableEntry::new(
+                resolve(catch.line, &catch.from)? as u16,
+                resolve(catch.line, &catch.to)? as u16,
+                resolve(catch.line, &catch.using)? as u16,
+                catch.catch_type,
+            ));
+        }
+
+        let mut attributes = Vec::new();
+        if !line_numbers.is_empty() {
+            let mut info = Vec::new();
+            LineNumberTable::new(line_numbers).write(&mut info);
+            attributes.push(AttributeInfo::new(
+                self.builder.add_utf8(LineNumberTable::NAME)?,
+                info,
+            ));
+        }
+        if !method.vars.is_empty() {
+            let mut local_variables = Vec::new();
+            for var in method.vars.iter() {
+                let start = resolve(var.line, &var.from)?;
+                let end = resolve(var.line, &var.to)?;
+                if end < start {
+                    return Err(syntax_error(
+                        var.line,
+                        "variable range ends before it starts",
+                    ));
+                }
+                local_variables.push(LocalVariable::new(
+                    start as u16,
+                    (end - start) as u16,
+                    var.name_index,
+                    var.descriptor_index,
+                    var.index,
+                ));
+            }
+            let mut info = Vec::new();
+            LocalVariableTable::new(local_variables).write(&mut info);
+            attributes.push(AttributeInfo::new(
+                self.builder.add_utf8(LocalVariableTable::NAME)?,
+                info,
+            ));
+        }
+
+        Ok(Code::new(
+            method.max_stack.unwrap_or(0),
+            method.max_locals.unwrap_or(method.locals_used),
+            code,
+            exception_table,
+            attributes,
+        ))
+    }
+
+    fn catch(&mut self, line: usize, args: &[Token]) -> Result<(), io::Error> {
+        // .catch <class|all> from <label> to <label> using <label>
+        if args.len() != 7
+            || args[1].text() != "from"
+            || args[3].text() != "to"
+            || args[5].text() != "using"
+        {
+            return Err(syntax_error(
+                line,
+                "expected '.catch <class|all> from <label> to <label> using <label>'",
+            ));
+        }
+        let catch_type = match args[0].text() {
+            "all" => 0,
+            class => self.class_operand(line, class)?,
+        };
+        self.method_body(line)?.catches.push(Catch {
+            line,
+            catch_type,
+            from: args[2].text().to_string(),
+            to: args[4].text().to_string(),
+            using: args[6].text().to_string(),
+        });
+        Ok(())
+    }
+
+    fn var(&mut self, line: usize, args: &[Token]) -> Result<(), io::Error> {
+        // .var <index> is <name> <descriptor> from <label> to <label>
+        if args.len() != 8
+            || args[1].text() != "is"
+            || args[4].text() != "from"
+            || args[6].text() != "to"
+        {
+            return Err(syntax_error(
+                line,
+                "expected '.var <index> is <name> <descriptor> from <label> to <label>'",
+            ));
+        }
+        let index = parse_number::<u16>(line, args[0].text())?;
+        let name_index = self.builder.add_utf8(args[2].text())?;
+        let descriptor_index = self.builder.add_utf8(args[3].text())?;
+        self.method_body(line)?.vars.push(Var {
+            line,
+            index,
+            name_index,
+            descriptor_index,
+            from: args[5].text().to_string(),
+            to: args[7].text().to_string(),
+        });
+        Ok(())
+    }
+
+    fn bootstrap(&mut self, line: usize, args: &[Token]) -> Result<(), io::Error> {
+        // .bootstrap <handle kind> <reference> [arguments...]
+        let (bootstrap_method_ref, consumed) = self.method_handle(line, args)?;
+        let mut bootstrap_arguments = Vec::new();
+        let mut rest = &args[consumed..];
+        while !rest.is_empty() {
+            let (index, consumed) = self.loadable_constant(line, rest, false)?;
+            bootstrap_arguments.push(index);
+            rest = &rest[consumed..];
+        }
+        self.bootstrap_methods.push(BootstrapMethod::new(
+            bootstrap_method_ref,
+            bootstrap_arguments,
+        ));
+        Ok(())
+    }
+
+    /// Parses `<kind> [interface] <owner/name(descriptor)>` or `<kind> <owner/name> <descriptor>`
+    /// into a MethodHandle constant, returning its index and the number of tokens used
+    fn method_handle(&mut self, line: usize, args: &[Token]) -> Result<(u16, usize), io::Error> {
+        let kind_name = args
+            .first()
+            .ok_or_else(|| syntax_error(line, "expected a method handle kind"))?
+            .text();
+        let kind = match kind_name {
+            "getfield" => ReferenceKind::GetField,
+            "getstatic" => ReferenceKind::GetStatic,
+            "putfield" => ReferenceKind::PutField,
+            "putstatic" => ReferenceKind::PutStatic,
+            "invokevirtual" => ReferenceKind::InvokeVirtual,
+            "invokestatic" => ReferenceKind::InvokeStatic,
+            "invokespecial" => ReferenceKind::InvokeSpecial,
+            "newinvokespecial" => ReferenceKind::NewInvokeSpecial,
+            "invokeinterface" => ReferenceKind::InvokeInterface,
+            other => {
+                return Err(syntax_error(
+                    line,
+                    &format!("unknown method handle kind '{other}'"),
+                ))
+            }
+        };
+        let (reference_index, consumed) = match kind {
+            ReferenceKind::GetField
+            | ReferenceKind::GetStatic
+            | ReferenceKind::PutField
+            | ReferenceKind::PutStatic => (self.field_ref(line, &args[1..])?, 2),
+            ReferenceKind::InvokeInterface => (self.method_ref(line, &args[1..], true)?, 1),
+            _ => match args.get(1).map(|t| t.text()) {
+                Some("interface") => (self.method_ref(line, &args[2..], true)?, 2),
+                _ => (self.method_ref(line, &args[1..], false)?, 1),
+            },
+        };
+        Ok((
+            self.builder.add_method_handle(kind, reference_index)?,
+            consumed + 1,
+        ))
+    }
+
+    /// `<owner>/<name> <descriptor>` or `#index`
+    fn field_ref(&mut self, line: usize, args: &[Token]) -> Result<u16, io::Error> {
+        let reference = args
+            .first()
+            .ok_or_else(|| syntax_error(line, "expected a field reference"))?
+            .text();
+        if reference.starts_with('#') {
+            return self.raw_index(line, reference);
+        }
+        let descriptor = args
+            .get(1)
+            .ok_or_else(|| syntax_error(line, "expected a field descriptor"))?
+            .text();
+        let (owner, name) = split_member(line, reference)?;
+        self.builder.add_field_ref(owner, name, descriptor)
+    }
+
+    /// `<owner>/<name>(<descriptor>` or `#index`
+    fn method_ref(
+        &mut self,
+        line: usize,
+        args: &[Token],
+        interface: bool,
+    ) -> Result<u16, io::Error> {
+        let reference = args
+            .first()
+            .ok_or_else(|| syntax_error(line, "expected a method reference"))?
+            .text();
+        if reference.starts_with('#') {
+            return self.raw_index(line, reference);
+        }
+        let open = reference
+            .find('(')
+            .ok_or_else(|| syntax_error(line, "expected '<owner>/<name>(<descriptor>'"))?;
+        let (owner, name) = split_member(line, &reference[..open])?;
+        let descriptor = &reference[open..];
+        if interface {
+            self.builder
+                .add_interface_method_ref(owner, name, descriptor)
+        } else {
+            self.builder.add_method_ref(owner, name, descriptor)
+        }
+    }
+
+    /// Parses a loadable constant (ldc operand or bootstrap argument), returning its index and
+    /// the number of tokens used. `wide` selects long/double for untyped number literals (ldc2_w).
+    fn loadable_constant(
+        &mut self,
+        line: usize,
+        args: &[Token],
+        wide: bool,
+    ) -> Result<(u16, usize), io::Error> {
+        let token = args
+            .first()
+            .ok_or_else(|| syntax_error(line, "expected a constant"))?;
+        let text = match token {
+            Token::Str(s) => return Ok((self.builder.add_string(s)?, 1)),
+            Token::Word(text) => text.as_str(),
+        };
+        let argument = |i: usize| -> Result<&str, io::Error> {
+            args.get(i)
+                .map(|t| t.text())
+                .ok_or_else(|| syntax_error(line, &format!("expected a value after '{text}'")))
+        };
+
+        let index = match text {
+            _ if text.starts_with('#') => self.raw_index(line, text)?,
+            "class" => return Ok((self.class_operand(line, argument(1)?)?, 2)),
+            "methodtype" => return Ok((self.builder.add_method_type(argument(1)?)?, 2)),
+            "methodhandle" => {
+                let (index, consumed) = self.method_handle(line, &args[1..])?;
+                return Ok((index, consumed + 1));
+            }
+            _ if text.ends_with(['L', 'l']) => self.builder.add_long(parse_long(line, text)?)?,
+            _ if text.ends_with(['F', 'f']) && !is_hex(text) => {
+                self.builder.add_float(parse_float(line, text)?)?
+            }
+            _ if text.ends_with(['D', 'd']) && !is_hex(text) => {
+                self.builder.add_double(parse_double(line, text)?)?
+            }
+            _ if is_decimal(text) => match wide {
+                true => self.builder.add_double(parse_double(line, text)?)?,
+                false => self.builder.add_float(parse_float(line, text)?)?,
+            },
+            _ => match wide {
+                true => self.builder.add_long(parse_long(line, text)?)?,
+                false => self.builder.add_integer(parse_int(line, text)?)?,
+            },
+        };
+        Ok((index, 1))
+    }
+
+    fn instruction(&mut self, line: usize, tokens: &[Token]) -> Result<(), io::Error> {
+        let mnemonic = tokens[0].text();
+        let (force_wide, mnemonic, args) = match mnemonic {
+            "wide" => {
+                let next = tokens
+                    .get(1)
+                    .ok_or_else(|| syntax_error(line, "expected an instruction after wide"))?;
+                (true, next.text(), &tokens[2..])
+            }
+            _ => (false, mnemonic, &tokens[1..]),
+        };
+        let op = Opcode::from_mnemonic(mnemonic)
+            .ok_or_else(|| syntax_error(line, &format!("unknown instruction '{mnemonic}'")))?;
+        if force_wide && !matches!(op.operands(), OperandKind::LocalIndex | OperandKind::Iinc) {
+            return Err(syntax_error(line, &format!("{mnemonic} can't be widened")));
+        }
+
+        let arg = |i: usize| -> Result<&str, io::Error> {
+            args.get(i)
+                .map(|t| t.text())
+                .ok_or_else(|| syntax_error(line, &format!("missing operand for {mnemonic}")))
+        };
+        let expect = |count: usize| -> Result<(), io::Error> {
+            match args.len() == count {
+                true => Ok(()),
+                false => Err(syntax_error(
+                    line,
+                    &format!("{mnemonic} takes {count} operand(s) but got {}", args.len()),
+                )),
+            }
+        };
+
+        let mut bytes = vec![op.code()];
+        match op.operands() {
+            OperandKind::None => expect(0)?,
+            OperandKind::Byte => {
+                expect(1)?;
+                bytes.push(parse_number::<i8>(line, arg(0)?)? as u8);
+            }
+            OperandKind::Short => {
+                expect(1)?;
+                bytes.extend_from_slice(&parse_number::<i16>(line, arg(0)?)?.to_be_bytes());
+            }
+            OperandKind::LocalIndex => {
+                expect(1)?;
+                let index = parse_number::<u16>(line, arg(0)?)?;
+                let slots = match op.code() {
+                    opcode::LLOAD | opcode::DLOAD | opcode::LSTORE | opcode::DSTORE => 2,
+                    _ => 1,
+                };
+                self.touch_local(line, index, slots)?;
+                if force_wide || index > u8::MAX as u16 {
+                    bytes.insert(0, opcode::WIDE);
+                    bytes.extend_from_slice(&index.to_be_bytes());
+                } else {
+                    bytes.push(index as u8);
+                }
+            }
+            OperandKind::Iinc => {
+                expect(2)?;
+                let index = parse_number::<u16>(line, arg(0)?)?;
+                let increment = parse_number::<i16>(line, arg(1)?)?;
+                self.touch_local(line, index, 1)?;
+                if force_wide || index > u8::MAX as u16 || i8::try_from(increment).is_err() {
+                    bytes.insert(0, opcode::WIDE);
+                    bytes.extend_from_slice(&index.to_be_bytes());
+                    bytes.extend_from_slice(&increment.to_be_bytes());
+                } else {
+                    bytes.push(index as u8);
+                    bytes.push(increment as i8 as u8);
+                }
+            }
+            OperandKind::ConstantIndex8 => {
+                let (index, consumed) = self.loadable_constant(line, args, false)?;
+                expect(consumed)?;
+                if index > u8::MAX as u16 {
+                    bytes[0] = opcode::LDC_W;
+                    bytes.extend_from_slice(&index.to_be_bytes());
+                } else {
+                    bytes.push(index as u8);
+                }
+            }
+            OperandKind::ConstantIndex16 => {
+                let index = match op.code() {
+                    opcode::LDC_W | opcode::LDC2_W => {
+                        let (index, consumed) =
+                            self.loadable_constant(line, args, op.code() == opcode::LDC2_W)?;
+                        expect(consumed)?;
+                        index
+                    }
+                    opcode::GETSTATIC | opcode::PUTSTATIC | opcode::GETFIELD | opcode::PUTFIELD => {
+                        if !arg(0)?.starts_with('#') {
+                            expect(2)?;
+                        }
+                        self.field_ref(line, args)?
+                    }
+                    opcode::INVOKEVIRTUAL | opcode::INVOKESPECIAL | opcode::INVOKESTATIC => {
+                        match arg(0)? {
+                            "interface" => {
+                                expect(2)?;
+                                self.method_ref(line, &args[1..], true)?
+                            }
+                            _ => {
+                                expect(1)?;
+                                self.method_ref(line, args, false)?
+                            }
+                        }
+                    }
+                    _ => {
+                        // new, anewarray, checkcast, instanceof
+                        expect(1)?;
+                        self.class_operand(line, arg(0)?)?
+                    }
+                };
+                bytes.extend_from_slice(&index.to_be_bytes());
+            }
+            OperandKind::InvokeInterface => {
+                let reference = arg(0)?;
+                let index = self.method_ref(line, args, true)?;
+                // the count operand is redundant with the descriptor, so it's optional
+                let count = match args.get(1) {
+                    Some(count) => {
+                        expect(2)?;
+                        parse_number::<u8>(line, count.text())?
+                    }
+                    None => {
+                        expect(1)?;
+                        let open = reference.find('(').ok_or_else(|| {
+                            syntax_error(line, "invokeinterface #index needs an explicit count")
+                        })?;
+                        let descriptor = MethodDescriptor::parse(&reference[open..])
+                            .map_err(|e| syntax_error(line, &e.to_string()))?;
+                        (descriptor.parameter_slots() + 1) as u8
+                    }
+                };
+                bytes.extend_from_slice(&index.to_be_bytes());
+                bytes.push(count);
+                bytes.push(0);
+            }
+            OperandKind::InvokeDynamic => {
+                // invokedynamic <bootstrap index> <name(descriptor)>  or  invokedynamic #index
+                let index = match arg(0)? {
+                    reference if reference.starts_with('#') => {
+                        expect(1)?;
+                        self.raw_index(line, reference)?
+                    }
+                    bootstrap => {
+                        expect(2)?;
+                        let bootstrap = parse_number::<u16>(line, bootstrap)?;
+                        let signature = arg(1)?;
+                        let open = signature
+                            .find('(')
+                            .ok_or_else(|| syntax_error(line, "expected '<name>(<descriptor>'"))?;
+                        self.builder.add_invoke_dynamic(
+                            bootstrap,
+                            &signature[..open],
+                            &signature[open..],
+                        )?
+                    }
+                };
+                bytes.extend_from_slice(&index.to_be_bytes());
+                bytes.extend_from_slice(&[0, 0]);
+            }
+            OperandKind::MultiANewArray => {
+                expect(2)?;
+                let index = self.class_operand(line, arg(0)?)?;
+                bytes.extend_from_slice(&index.to_be_bytes());
+                bytes.push(parse_number::<u8>(line, arg(1)?)?);
+            }
+            OperandKind::NewArray => {
+                expect(1)?;
+                let type_name = arg(0)?;
+                let code = match ARRAY_TYPES.iter().find(|(_, name)| *name == type_name) {
+                    Some((code, _)) => *code,
+                    None => parse_number::<u8>(line, type_name)?,
+                };
+                bytes.push(code);
+            }
+            OperandKind::Branch16 | OperandKind::Branch32 => {
+                expect(1)?;
+                self.method_body(line)?.items.push(BodyItem::Branch {
+                    line,
+                    opcode: op.code(),
+                    target: arg(0)?.to_string(),
+                });
+                return Ok(());
+            }
+            OperandKind::TableSwitch | OperandKind::LookupSwitch | OperandKind::Wide => {
+                return Err(syntax_error(line, &format!("unexpected {mnemonic}")))
+            }
+        }
+
+        self.method_body(line)?.items.push(BodyItem::Bytes(bytes));
+        Ok(())
+    }
+
+    fn touch_local(&mut self, line: usize, index: u16, slots: u16) -> Result<(), io::Error> {
+        let method = self.method_body(line)?;
+        method.locals_used = method.locals_used.max(index.saturating_add(slots));
+        Ok(())
+    }
+
+    /// ```text
+    /// tableswitch <low> [<high>]
+    ///     <label>
+    ///     default : <label>
+    /// ```
+    fn table_switch(
+        &mut self,
+        lines: &[Line],
+        start: usize,
+        args: &[Token],
+    ) -> Result<usize, io::Error> {
+        let number = lines[start].number();
+        expect_args(number, args, 1, 2)?;
+        let low = parse_int(number, args[0].text())?;
+        let (cases, default, next) = switch_cases(lines, start)?;
+
+        let mut targets = Vec::new();
+        for (line, tokens) in cases {
+            if tokens.len() != 1 {
+                return Err(syntax_error(
+                    line,
+                    "expected one label per tableswitch case",
+                ));
+            }
+            targets.push(tokens[0].text().to_string());
+        }
+        if targets.is_empty() {
+            return Err(syntax_error(number, "tableswitch needs at least one case"));
+        }
+        if let Some(high) = args.get(1) {
+            let high = parse_int(number, high.text())?;
+            if high as i64 - low as i64 + 1 != targets.len() as i64 {
+                return Err(syntax_error(
+                    number,
+                    &format!("tableswitch {low} to {high} has {} cases", targets.len()),
+                ));
+            }
+        }
+
+        self.method_body(number)?.items.push(BodyItem::TableSwitch {
+            line: number,
+            low,
+            targets,
+            default,
+        });
+        Ok(next)
+    }
+
+    /// ```text
+    /// lookupswitch
+    ///     <key> : <label>
+    ///     default : <label>
+    /// ```
+    fn lookup_switch(
+        &mut self,
+        lines: &[Line],
+        start: usize,
+        args: &[Token],
+    ) -> Result<usize, io::Error> {
+        let number = lines[start].number();
+        expect_args(number, args, 0, 0)?;
+        let (cases, default, next) = switch_cases(lines, start)?;
+
+        let mut pairs: Vec<(i32, String)> = Vec::new();
+        for (line, tokens) in cases {
+            let (key, label) = split_case(line, &tokens)?;
+            pairs.push((parse_int(line, &key)?, label));
+        }
+        // 4.10.1.9: the match keys must be sorted in increasing order
+        pairs.sort_by_key(|(key, _)| *key);
+        if pairs.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(syntax_error(number, "lookupswitch has duplicate keys"));
+        }
+
+        self.method_body(number)?
+            .items
+            .push(BodyItem::LookupSwitch {
+                line: number,
+                pairs,
+                default,
+            });
+        Ok(next)
+    }
+}
+
+/// Collects the case lines of a switch up to and including its `default` line.
+/// Returns the cases, the default label and the index of the line after the switch.
+#[allow(clippy::type_complexity)]
+fn switch_cases(
+    lines: &[Line],
+    start: usize,
+) -> Result<(Vec<(usize, Vec<Token>)>, String, usize), io::Error> {
+    let mut cases = Vec::new();
+    let mut i = start + 1;
+    while i < lines.len() {
+        let line = &lines[i];
+        let tokens = line.tokens().to_vec();
+        if tokens[0].text().starts_with("default") {
+            let (_, label) = split_case(line.number(), &tokens)?;
+            return Ok((cases, label, i + 1));
+        }
+        cases.push((line.number(), tokens));
+        i += 1;
+    }
+    Err(syntax_error(
+        lines[start].number(),
+        "switch is missing a default case",
+    ))
+}
+
+/// Splits `key : label`, `key: label` or `key :label` into its two parts
+fn split_case(line: usize, tokens: &[Token]) -> Result<(String, String), io::Error> {
+    let joined: Vec<&str> = tokens.iter().map(|t| t.text()).collect();
+    let joined = joined.join(" ");
+    let (key, label) = joined
+        .split_once(':')
+        .ok_or_else(|| syntax_error(line, "expected '<key> : <label>'"))?;
+    let (key, label) = (key.trim(), label.trim());
+    if key.is_empty() || label.is_empty() || label.contains(' ') {
+        return Err(syntax_error(line, "expected '<key> : <label>'"));
+    }
+    Ok((key.to_string(), label.to_string()))
+}
+
+fn item_size(item: &BodyItem, pc: u32) -> u32 {
+    let padding = (4 - (pc + 1) % 4) % 4;
+    match item {
+        BodyItem::Label(_) | BodyItem::LineNumber(_) => 0,
+        BodyItem::Bytes(bytes) => bytes.len() as u32,
+        BodyItem::Branch { opcode, .. } => match *opcode {
+            opcode::GOTO_W | opcode::JSR_W => 5,
+            _ => 3,
+        },
+        BodyItem::TableSwitch { targets, .. } => 1 + padding + 12 + 4 * targets.len() as u32,
+        BodyItem::LookupSwitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len() as u32,
+    }
+}
+
+/// Switch operands start at the next multiple of four from the start of the code (section 6.5)
+fn pad_switch(code: &mut Vec<u8>) {
+    while !code.len().is_multiple_of(4) {
+        code.push(0);
+    }
+}
+
+fn split_member(line: usize, reference: &str) -> Result<(&str, &str), io::Error> {
+    reference
+        .rsplit_once('/')
+        .filter(|(owner, name)| !owner.is_empty() && !name.is_empty())
+        .ok_or_else(|| {
+            syntax_error(
+                line,
+                &format!("expected '<owner>/<name>' but found '{reference}'"),
+            )
+        })
+}
+
+fn parse_flags(line: usize, tokens: &[Token], target: FlagTarget) -> Result<u16, io::Error> {
+    let mut flags = 0;
+    for token in tokens.iter() {
+        flags |= flag_from_keyword(target, token.text()).ok_or_else(|| {
+            syntax_error(line, &format!("unknown access flag '{}'", token.text()))
+        })?;
+    }
+    Ok(flags)
+}
+
+fn parse_hex(line: usize, tokens: &[Token]) -> Result<Vec<u8>, io::Error> {
+    let digits: String = tokens.iter().map(|t| t.text()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(syntax_error(line, "expected an even number of hex digits"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| {
+                syntax_error(line, &format!("invalid hex byte '{}'", &digits[i..i + 2]))
+            })
+        })
+        .collect()
+}
+
+fn expect_args(line: usize, args: &[Token], min: usize, max: usize) -> Result<(), io::Error> {
+    if args.len() < min || args.len() > max {
+        return Err(syntax_error(
+            line,
+            &format!("unexpected number of arguments ({})", args.len()),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(line: usize, text: &str) -> Result<T, io::Error> {
+    text.parse::<T>()
+        .map_err(|_| syntax_error(line, &format!("invalid or out of range number '{text}'")))
+}
+
+fn is_hex(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    digits.starts_with("0x") || digits.starts_with("0X")
+}
+
+fn is_decimal(text: &str) -> bool {
+    !is_hex(text)
+        && (text.contains(['.', 'e', 'E']) || text.ends_with("NaN") || text.ends_with("Infinity"))
+}
+
+/// Parses a decimal or `0x` hexadecimal integer literal
+fn parse_integer_literal(line: usize, text: &str) -> Result<i128, io::Error> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse::<i128>(),
+    }
+    .map_err(|_| syntax_error(line, &format!("invalid number '{text}'")))?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Hex literals may use the full unsigned range, e.g. `0xFFFFFFFF` for -1
+fn parse_int(line: usize, text: &str) -> Result<i32, io::Error> {
+    let value = parse_integer_literal(line, text)?;
+    match (i32::try_from(value), is_hex(text)) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), true) if (0..=u32::MAX as i128).contains(&value) => Ok(value as u32 as i32),
+        _ => Err(syntax_error(
+            line,
+            &format!("number '{text}' doesn't fit in an int"),
+        )),
+    }
+}
+
+fn parse_long(line: usize, text: &str) -> Result<i64, io::Error> {
+    let digits = text.trim_end_matches(['L', 'l']);
+    let value = parse_integer_literal(line, digits)?;
+    match (i64::try_from(value), is_hex(digits)) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), true) if (0..=u64::MAX as i128).contains(&value) => Ok(value as u64 as i64),
+        _ => Err(syntax_error(
+            line,
+            &format!("number '{text}' doesn't fit in a long"),
+        )),
+    }
+}
+
+fn parse_float(line: usize, text: &str) -> Result<f32, io::Error> {
+    let digits = text.trim_end_matches(['F', 'f']);
+    let value = digits
+        .parse::<f32>()
+        .map_err(|_| syntax_error(line, &format!("invalid float '{text}'")))?;
+    match value.is_infinite() && !digits.ends_with("Infinity") {
+        true => Err(syntax_error(
+            line,
+            &format!("number '{text}' doesn't fit in a float"),
+        )),
+        false => Ok(value),
+    }
+}
+
+fn parse_double(line: usize, text: &str) -> Result<f64, io::Error> {
+    let digits = text.trim_end_matches(['D', 'd']);
+    let value = digits
+        .parse::<f64>()
+        .map_err(|_| syntax_error(line, &format!("invalid double '{text}'")))?;
+    // only an explicit `Infinity` is infinite; a literal too big for its type is an error
+    match value.is_infinite() && !digits.ends_with("Infinity") {
+        true => Err(syntax_error(
+            line,
+            &format!("number '{text}' doesn't fit in a double"),
+        )),
+        false => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HELLO_WORLD: &str = r#"
+.source HelloWorld.java
+.class public super HelloWorld
+.super java/lang/Object
+
+.method public <init>()V
+    .limit stack 1
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+
+.method public static main([Ljava/lang/String;)V
+    .limit stack 2
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    ldc "Hello, World!"
+    invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+    return
+.end method
+"#;
+
+    fn method_code(class_file: &ClassFile, name: &str, descriptor: &str) -> Code {
+        class_file
+            .find_method(name, descriptor)
+            .unwrap()
+            .code(class_file.constant_pool())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn assemble_hello_world() {
+        let class_file = assemble(HELLO_WORLD).unwrap();
+        assert_eq!(class_file.magic_number(), 0xCAFEBABE);
+        assert_eq!(class_file.major_version(), 52);
+        assert_eq!(class_file.access_flags(), 0x0021);
+        assert_eq!(class_file.class_name().unwrap(), "HelloWorld");
+        assert!(class_file.find_attribute("SourceFile").is_some());
+
+        let code = method_code(&class_file, "main", "([Ljava/lang/String;)V");
+        assert_eq!(code.max_stack(), 2);
+        assert_eq!(code.max_locals(), 1);
+        let constant_pool = class_file.constant_pool();
+        let code_bytes = code.code();
+        assert_eq!(code_bytes[0], opcode::GETSTATIC);
+        let field = u16::from_be_bytes([code_bytes[1], code_bytes[2]]);
+        assert_eq!(
+            constant_pool.member_ref(field).unwrap(),
+            (
+                "java/lang/System".to_string(),
+                "out".to_string(),
+                "Ljava/io/PrintStream;".to_string()
+            )
+        );
+        assert_eq!(code_bytes[3], opcode::LDC);
+        assert_eq!(code_bytes[8], opcode::RETURN);
+
+        // the written bytes parse back to the same class
+        let bytes = class_file.to_bytes();
+        let mut parsed = ClassFile::new();
+        parsed.read_slice(&bytes).unwrap();
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn branches_and_switches() {
+        let source = r#"
+.class Branches
+.method static f(I)I
+    iload_0
+    ifeq Zero
+    iload_0
+    tableswitch 1 2
+        One
+        Two
+        default : Other
+Zero:
+    iconst_0
+    ireturn
+One: iconst_1
+    ireturn
+Two:
+    lookupswitch
+        5 : Other
+        -1: One
+        default : Other
+Other:
+    iconst_m1
+    ireturn
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let code = method_code(&class_file, "f", "(I)I");
+        assert_eq!(code.max_locals(), 1);
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            0x1a,                   // 0: iload_0
+            0x99, 0x00, 0x1b,       // 1: ifeq 28
+            0x1a,                   // 4: iload_0
+            0xaa, 0x00, 0x00,       // 5: tableswitch + padding to 8
+            0x00, 0x00, 0x00, 0x37, // default 60
+            0x00, 0x00, 0x00, 0x01, // low 1
+            0x00, 0x00, 0x00, 0x02, // high 2
+            0x00, 0x00, 0x00, 0x19, // 1 -> 30
+            0x00, 0x00, 0x00, 0x1b, // 2 -> 32
+            0x03,                   // 28: iconst_0
+            0xac,                   // 29: ireturn
+            0x04,                   // 30: iconst_1
+            0xac,                   // 31: ireturn
+            0xab, 0x00, 0x00, 0x00, // 32: lookupswitch + padding to 36
+            0x00, 0x00, 0x00, 0x1c, // default 60
+            0x00, 0x00, 0x00, 0x02, // 2 pairs
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, // -1 -> 30
+            0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x1c, // 5 -> 60
+            0x02,                   // 60: iconst_m1
+            0xac,                   // 61: ireturn
+        ];
+        assert_eq!(code.code(), expected.as_slice());
+    }
+
+    #[test]
+    fn wide_locals_and_constants() {
+        let source = r#"
+.class Wide
+.method static f()V
+    .limit stack 2
+    ldc2_w 5
+    lstore 300
+    iinc 1 1000
+    ldc 1.5
+    ldc2_w 1.5
+    return
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let code = method_code(&class_file, "f", "()V");
+        assert_eq!(code.max_locals(), 302);
+        let bytes = code.code();
+        assert_eq!(bytes[0], opcode::LDC2_W);
+        assert_eq!(&bytes[3..7], &[opcode::WIDE, opcode::LSTORE, 0x01, 0x2c]);
+        assert_eq!(
+            &bytes[7..13],
+            &[opcode::WIDE, opcode::IINC, 0x00, 0x01, 0x03, 0xe8]
+        );
+
+        let constant_pool = class_file.constant_pool();
+        let long_index = u16::from_be_bytes([bytes[1], bytes[2]]);
+        assert!(matches!(
+            constant_pool.get(long_index).unwrap().data(),
+            ConstantPoolData::Long(l) if l.value() == 5
+        ));
+        let float_index = bytes[14] as u16;
+        assert!(matches!(
+            constant_pool.get(float_index).unwrap().data(),
+            ConstantPoolData::Float(f) if f.value() == 1.5
+        ));
+    }
+
+    #[test]
+    fn raw_constants_and_bytes() {
+        // a Class entry pointing at an Integer is invalid but can still be written
+        let source = r#"
+.class Raw
+.constant number Integer 7
+.constant bad Class #number
+.method static f()V
+    ldc #bad
+    .bytes ff 00
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let constant_pool = class_file.constant_pool();
+        assert!(matches!(
+            constant_pool.get(2).unwrap().data(),
+            ConstantPoolData::ClassInfo(c) if c.name_index() == 1
+        ));
+        let code = method_code(&class_file, "f", "()V");
+        assert_eq!(code.code(), &[opcode::LDC, 0x02, 0xff, 0x00]);
+    }
+
+    #[test]
+    fn fields_exceptions_and_debug_info() {
+        let source = r#"
+.class public Fields
+.field public static final MAX I = 0x7fffffff
+.field private static final NAME Ljava/lang/String; = "n"
+.method public run()V
+    .throws java/io/IOException
+    .limit stack 1
+Start:
+    .line 10
+    aload_0
+    pop
+End:
+    return
+Handler:
+    athrow
+    .catch java/lang/RuntimeException from Start to End using Handler
+    .catch all from Start to End using Handler
+    .var 0 is this LFields; from Start to End
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let constant_pool = class_file.constant_pool();
+        let field = class_file.find_field("MAX", "I").unwrap();
+        assert_eq!(field.access_flags(), 0x0019);
+        assert_eq!(field.attributes().len(), 1);
+
+        let method = class_file.find_method("run", "()V").unwrap();
+        let code = method.code(constant_pool).unwrap().unwrap();
+        let runtime_exception = constant_pool
+            .items()
+            .iter()
+            .position(|item| match item.data() {
+                ConstantPoolData::ClassInfo(c) => {
+                    constant_pool.utf8(c.name_index()).unwrap() == "java/lang/RuntimeException"
+                }
+                _ => false,
+            })
+            .unwrap() as u16
+            + 1;
+        assert_eq!(
+            code.exception_table(),
+            &[
+                ExceptionTableEntry::new(0, 2, 3, runtime_exception),
+                ExceptionTableEntry::new(0, 2, 3, 0),
+            ]
+        );
+        assert_eq!(code.attributes().len(), 2);
+        assert!(method
+            .attributes()
+            .iter()
+            .any(|a| a.name(constant_pool).unwrap() == "Exceptions"));
+    }
+
+    #[test]
+    fn errors_report_line_numbers() {
+        let error = assemble(".class A\n.method static f()V\n    foo\n.end method\n").unwrap_err();
+        assert!(error.to_string().starts_with("line 3:"));
+
+        let error =
+            assemble(".class A\n.method static f()V\n    goto Nowhere\n.end method\n").unwrap_err();
+        assert!(error.to_string().contains("undefined label"));
+
+        assert!(assemble(".method static f()V\n    return\n.end method\n").is_err());
+        assert!(assemble(".class A\n.method static f()V\n    return\n").is_err());
+        assert!(assemble(".class A\n.method static f()V\n    bipush 200\n.end method\n").is_err());
+
+        // literals too big for their type aren't rounded to infinity
+        for literal in ["1e400", "1e39f", "-1e400d"] {
+            let source = format!(".class A\n.method static f()V\n    ldc {literal}\n.end method\n");
+            let error = assemble(&source).unwrap_err();
+            assert!(error.to_string().starts_with("line 3:"));
+            assert!(error.to_string().contains("doesn't fit"));
+        }
+        assert!(
+            assemble(".class A\n.method static f()V\n    ldc -Infinity\n.end method\n").is_ok()
+        );
+
+        // strings too long for a Utf8 constant
+        let long = "x".repeat(70000);
+        let source = format!(".class A\n.method static f()V\n    ldc \"{long}\"\n.end method\n");
+        let error = assemble(&source).unwrap_err();
+        assert!(error.to_string().starts_with("line 3:"), "{error}");
+        assert!(error.to_string().contains("longer than the maximum"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::io;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    /// Any whitespace separated word: directives, mnemonics, names, descriptors, numbers
+    Word(String),
+    /// A double quoted string with escapes already processed
+    Str(String),
+}
+
+impl Token {
+    pub fn text(&self) -> &str {
+        match self {
+            Token::Word(text) | Token::Str(text) => text,
+        }
+    }
+}
+
+/// The tokens of one non-empty source line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    number: usize,
+    tokens: Vec<Token>,
+}
+
+impl Line {
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+}
+
+/// Splits assembler source into lines of tokens, dropping blank lines and comments.
+///
+/// A `;` starts a comment only at the beginning of a token, since descriptors such as
+/// `Ljava/lang/String;` contain semicolons.
+pub fn tokenize(source: &str) -> Result<Vec<Line>, io::Error> {
+    let mut lines = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        let number = i + 1;
+        let tokens = tokenize_line(text).map_err(|message| syntax_error(number, &message))?;
+        if !tokens.is_empty() {
+            lines.push(Line { number, tokens });
+        }
+    }
+    Ok(lines)
+}
+
+fn tokenize_line(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.peek().copied() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            chars.next();
+            tokens.push(Token::Str(read_string(&mut chars)?));
+        } else {
+            let mut word = String::new();
+            while let Some(c) = chars.peek().copied() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_string(chars: &mut impl Iterator<Item = char>) -> Result<String, String> {
+    // collect UTF-16 units so that \uXXXX surrogate pairs combine into one character
+    let mut units: Vec<u16> = Vec::new();
+    loop {
+        let c = chars.next().ok_or("unterminated string")?;
+        match c {
+            '"' => break,
+            '\\' => {
+                let escaped = chars.next().ok_or("unterminated string")?;
+                let unit = match escaped {
+                    'n' => '\n' as u16,
+                    't' => '\t' as u16,
+                    'r' => '\r' as u16,
+                    'b' => 0x08,
+                    'f' => 0x0C,
+                    '0' => 0,
+                    '"' => '"' as u16,
+                    '\'' => '\'' as u16,
+                    '\\' => '\\' as u16,
+                    'u' => {
+                        let hex: String = chars.take(4).collect();
+                        u16::from_str_radix(&hex, 16)
+                            .map_err(|_| format!("invalid unicode escape \\u{hex}"))?
+                    }
+                    other => return Err(format!("unknown escape sequence \\{other}")),
+                };
+                units.push(unit);
+            }
+            _ => {
+                let mut buffer = [0; 2];
+                units.extend_from_slice(c.encode_utf16(&mut buffer));
+            }
+        }
+    }
+    String::from_utf16(&units).map_err(|_| "string contains an unpaired surrogate".to_string())
+}
+
+pub fn syntax_error(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {line}: {message}"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn descriptors_keep_semicolons() {
+        let lines =
+            tokenize("  getstatic java/lang/System/out Ljava/io/PrintStream; ; comment").unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            lines[0].tokens(),
+            &[
+                Token::Word("getstatic".to_string()),
+                Token::Word("java/lang/System/out".to_string()),
+                Token::Word("Ljava/io/PrintStream;".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_and_comment_lines_are_skipped() {
+        let lines = tokenize("\n; just a comment\n\nreturn\n").unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].number(), 4);
+    }
+
+    #[test]
+    fn string_escapes() {
+        let lines = tokenize(r#"ldc "a \"b\"\né ; not a comment""#).unwrap();
+        assert_eq!(
+            lines[0].tokens()[1],
+            Token::Str("a \"b\"\n\u{e9} ; not a comment".to_string())
+        );
+        assert!(tokenize(r#"ldc "unterminated"#).is_err());
+        assert!(tokenize(r#"ldc "\q""#).is_err());
+    }
+}
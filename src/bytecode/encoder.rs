@@ -0,0 +1,758 @@
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::{Code, ExceptionTableEntry};
+use crate::class_file::attribute::line_number_table::{LineNumber, LineNumberTable};
+use crate::class_file::attribute::local_variable_table::{LocalVariable, LocalVariableTable};
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use std::collections::BTreeMap;
+use std::io;
+
+// Method bodies with symbolic labels instead of pcs. Instructions can be inserted, removed
+// or replaced freely; `LabeledCode::to_code` lays the code out again, choosing between short
+// and wide branches and moving the exception table and debug ranges along with it.
+
+/// A position in a `LabeledCode`, placed with `LabeledCode::push_label`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Label(u32);
+
+impl Label {
+    /// A label with a chosen id, for code that isn't built through a single `LabeledCode`.
+    /// `LabeledCode::new_label` counts up from 0, so pick ids that can't meet those, e.g. by
+    /// counting down from `u32::MAX`.
+    pub fn new(id: u32) -> Label {
+        Label(id)
+    }
+
+    /// The value to use as a branch target in instructions pushed into a `LabeledCode`
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+/// An instruction or label. Inside a `LabeledCode` the branch targets of an instruction are
+/// label ids, not pcs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodeElement {
+    Label(Label),
+    Instruction(Instruction),
+}
+
+/// An exception table entry (section 4.7.3) over labels; `end` is exclusive
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LabeledHandler {
+    start: Label,
+    end: Label,
+    handler: Label,
+    catch_type: u16,
+}
+
+impl LabeledHandler {
+    pub fn new(start: Label, end: Label, handler: Label, catch_type: u16) -> LabeledHandler {
+        LabeledHandler {
+            start,
+            end,
+            handler,
+            catch_type,
+        }
+    }
+
+    pub fn start(&self) -> Label {
+        self.start
+    }
+
+    pub fn end(&self) -> Label {
+        self.end
+    }
+
+    pub fn handler(&self) -> Label {
+        self.handler
+    }
+
+    pub fn catch_type(&self) -> u16 {
+        self.catch_type
+    }
+}
+
+/// A LocalVariableTable or LocalVariableTypeTable entry (sections 4.7.13, 4.7.14) over labels
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LabeledLocal {
+    start: Label,
+    end: Label,
+    name_index: u16,
+    descriptor_index: u16,
+    index: u16,
+}
+
+impl LabeledLocal {
+    pub fn new(
+        start: Label,
+        end: Label,
+        name_index: u16,
+        descriptor_index: u16,
+        index: u16,
+    ) -> LabeledLocal {
+        LabeledLocal {
+            start,
+            end,
+            name_index,
+            descriptor_index,
+            index,
+        }
+    }
+
+    pub fn start(&self) -> Label {
+        self.start
+    }
+
+    pub fn end(&self) -> Label {
+        self.end
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    /// The descriptor, or the signature for a LocalVariableTypeTable
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+}
+
+/// An attribute of a Code attribute. The ones that refer to pcs are kept over labels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LabeledAttribute {
+    LineNumberTable {
+        name_index: u16,
+        lines: Vec<(Label, u16)>,
+    },
+    /// Also used for LocalVariableTypeTable, which has the same layout
+    LocalVariableTable {
+        name_index: u16,
+        variables: Vec<LabeledLocal>,
+    },
+    /// Kept as is, but only written back if the code comes out byte for byte the same; any
+    /// other change invalidates its frames
+    StackMapTable(AttributeInfo),
+    Other(AttributeInfo),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LabeledCode {
+    elements: Vec<CodeElement>,
+    exception_table: Vec<LabeledHandler>,
+    attributes: Vec<LabeledAttribute>,
+    next_label: u32,
+    /// The code array this was read from, to tell if a StackMapTable is still valid
+    original_code: Option<Vec<u8>>,
+}
+
+impl LabeledCode {
+    pub fn new() -> LabeledCode {
+        LabeledCode::default()
+    }
+
+    /// Decodes a Code attribute, with a label at every pc something refers to
+    pub fn from_code(code: &Code, constant_pool: &ConstantPool) -> Result<LabeledCode, io::Error> {
+        let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+        let length = code.code().len() as u32;
+        let is_boundary =
+            |pc: u32| pc == length || instructions.binary_search_by_key(&pc, |i| i.0).is_ok();
+
+        // collect every referenced pc first so labels are numbered in code order
+        let mut pcs = Vec::new();
+        for (_, instruction) in instructions.iter() {
+            pcs.extend(instruction.branch_targets());
+        }
+        for entry in code.exception_table() {
+            pcs.extend([entry.start_pc(), entry.end_pc(), entry.handler_pc()].map(u32::from));
+        }
+        let mut attributes = Vec::with_capacity(code.attributes().len());
+        for attribute in code.attributes() {
+            let name = attribute.name(constant_pool)?;
+            if name == LineNumberTable::NAME {
+                let table = LineNumberTable::from(&mut attribute.reader())?;
+                pcs.extend(
+                    table
+                        .line_number_table()
+                        .iter()
+                        .map(|l| l.start_pc() as u32),
+                );
+                attributes.push((attribute, Some(table), None));
+            } else if name == LocalVariableTable::NAME
+                || name == LocalVariableTable::TYPE_TABLE_NAME
+            {
+                let table = LocalVariableTable::from(&mut attribute.reader())?;
+                for local in table.local_variable_table() {
+                    let start = local.start_pc() as u32;
+                    pcs.extend([start, start + local.length() as u32]);
+                }
+                attributes.push((attribute, None, Some(table)));
+            } else {
+                attributes.push((attribute, None, None));
+            }
+        }
+
+        let mut labeled = LabeledCode::new();
+        let mut labels = BTreeMap::new();
+        pcs.sort_unstable();
+        pcs.dedup();
+        for pc in pcs {
+            if !is_boundary(pc) {
+                return Err(encoder_error(&format!(
+                    "pc {pc} is not the start of an instruction"
+                )));
+            }
+            labels.insert(pc, labeled.new_label());
+        }
+        let label = |pc: u16| labels[&(pc as u32)];
+
+        for (pc, instruction) in instructions.iter() {
+            if let Some(label) = labels.get(pc) {
+                labeled.push_label(*label);
+            }
+            labeled.push(instruction.map_branch_targets(|target| labels[&target].0));
+        }
+        if let Some(label) = labels.get(&length) {
+            labeled.push_label(*label);
+        }
+
+        for entry in code.exception_table() {
+            labeled.exception_table.push(LabeledHandler::new(
+                label(entry.start_pc()),
+                label(entry.end_pc()),
+                label(entry.handler_pc()),
+                entry.catch_type(),
+            ));
+        }
+        for (attribute, lines, locals) in attributes {
+            let name_index = attribute.attribute_name_index();
+            labeled.attributes.push(match (lines, locals) {
+                (Some(lines), _) => LabeledAttribute::LineNumberTable {
+                    name_index,
+                    lines: lines
+                        .line_number_table()
+                        .iter()
+                        .map(|line| (label(line.start_pc()), line.line_number()))
+                        .collect(),
+                },
+                (_, Some(locals)) => LabeledAttribute::LocalVariableTable {
+                    name_index,
+                    variables: locals
+                        .local_variable_table()
+                        .iter()
+                        .map(|local| {
+                            let end = labels[&(local.start_pc() as u32 + local.length() as u32)];
+                            LabeledLocal::new(
+                                label(local.start_pc()),
+                                end,
+                                local.name_index(),
+                                local.descriptor_index(),
+                                local.index(),
+                            )
+                        })
+                        .collect(),
+                },
+                _ if attribute.name(constant_pool)? == "StackMapTable" => {
+                    LabeledAttribute::StackMapTable(attribute.clone())
+                }
+                _ => LabeledAttribute::Other(attribute.clone()),
+            });
+        }
+        labeled.original_code = Some(code.code().to_vec());
+        Ok(labeled)
+    }
+
+    /// Creates a label; it still has to be placed with `push_label`
+    pub fn new_label(&mut self) -> Label {
+        let label = Label(self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    pub fn push_label(&mut self, label: Label) {
+        self.elements.push(CodeElement::Label(label));
+    }
+
+    /// Appends an instruction whose branch targets are label ids
+    pub fn push(&mut self, instruction: Instruction) {
+        self.elements.push(CodeElement::Instruction(instruction));
+    }
+
+    pub fn elements(&self) -> &[CodeElement] {
+        &self.elements
+    }
+
+    pub fn elements_mut(&mut self) -> &mut Vec<CodeElement> {
+        &mut self.elements
+    }
+
+    pub fn exception_table(&self) -> &[LabeledHandler] {
+        &self.exception_table
+    }
+
+    pub fn exception_table_mut(&mut self) -> &mut Vec<LabeledHandler> {
+        &mut self.exception_table
+    }
+
+    pub fn attributes(&self) -> &[LabeledAttribute] {
+        &self.attributes
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut Vec<LabeledAttribute> {
+        &mut self.attributes
+    }
+
+    /// The code array a StackMapTable was computed for, as `from_code` records it
+    pub fn original_code(&self) -> Option<&[u8]> {
+        self.original_code.as_deref()
+    }
+
+    pub fn set_original_code(&mut self, original_code: Option<Vec<u8>>) {
+        self.original_code = original_code;
+    }
+
+    /// Lays the code out and encodes it. Branches start out short; any whose offset doesn't
+    /// fit in 16 bits is widened (`goto_w`, `jsr_w`, or an inverted conditional over a
+    /// `goto_w`) and the layout is redone until nothing else needs widening. Since branches
+    /// only ever grow, this always finishes.
+    pub fn to_code(&self, max_stack: u16, max_locals: u16) -> Result<Code, io::Error> {
+        let mut long = vec![false; self.elements.len()];
+        let positions = loop {
+            let positions = self.layout(&long)?;
+            let mut changed = false;
+            let mut pc = 0;
+            for (i, element) in self.elements.iter().enumerate() {
+                let CodeElement::Instruction(instruction) = element else {
+                    continue;
+                };
+                if !long[i] && is_short_branch(instruction) {
+                    let target = positions[&instruction.branch_targets()[0]];
+                    if i16::try_from(target as i64 - pc as i64).is_err() {
+                        long[i] = true;
+                        changed = true;
+                    }
+                }
+                pc += instruction_size(instruction, pc, long[i])?;
+            }
+            if !changed {
+                break positions;
+            }
+        };
+
+        let mut code = Vec::new();
+        for (i, element) in self.elements.iter().enumerate() {
+            let CodeElement::Instruction(instruction) = element else {
+                continue;
+            };
+            let pc = code.len() as u32;
+            let instruction = instruction.map_branch_targets(|label| positions[&label]);
+            if long[i] {
+                encode_long(&instruction, pc, &mut code)?;
+            } else {
+                instruction.encode(pc, &mut code)?;
+            }
+        }
+        if code.is_empty() {
+            return Err(encoder_error("code is empty"));
+        }
+
+        let pc = |label: Label| positions[&label.0] as u16;
+        let exception_table = self
+            .exception_table
+            .iter()
+            .map(|entry| {
+                ExceptionTableEntry::new(
+                    pc(entry.start),
+                    pc(entry.end),
+                    pc(entry.handler),
+                    entry.catch_type,
+                )
+            })
+            .collect();
+
+        let mut attributes = Vec::with_capacity(self.attributes.len());
+        for attribute in self.attributes.iter() {
+            let mut info = Vec::new();
+            match attribute {
+                LabeledAttribute::LineNumberTable { name_index, lines } => {
+                    let lines = lines
+                        .iter()
+                        .map(|(label, line)| LineNumber::new(pc(*label), *line))
+                        .collect();
+                    LineNumberTable::new(lines).write(&mut info);
+                    attributes.push(AttributeInfo::new(*name_index, info));
+                }
+                LabeledAttribute::LocalVariableTable {
+                    name_index,
+                    variables,
+                } => {
+                    let mut locals = Vec::with_capacity(variables.len());
+                    for local in variables {
+                        let (start, end) = (pc(local.start), pc(local.end));
+                        let length = end.checked_sub(start).ok_or_else(|| {
+                            encoder_error(&format!(
+                                "local variable {} ends before it starts",
+                                local.index
+                            ))
+                        })?;
+                        locals.push(LocalVariable::new(
+                            start,
+                            length,
+                            local.name_index,
+                            local.descriptor_index,
+                            local.index,
+                        ));
+                    }
+                    LocalVariableTable::new(locals).write(&mut info);
+                    attributes.push(AttributeInfo::new(*name_index, info));
+                }
+                LabeledAttribute::StackMapTable(attribute) => {
+                    if self.original_code.as_ref() == Some(&code) {
+                        attributes.push(attribute.clone());
+                    }
+                }
+                LabeledAttribute::Other(attribute) => attributes.push(attribute.clone()),
+            }
+        }
+
+        Ok(Code::new(
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        ))
+    }
+
+    /// Works out the pc of every label for the given choice of long branches
+    fn layout(&self, long: &[bool]) -> Result<BTreeMap<u32, u32>, io::Error> {
+        let mut positions = BTreeMap::new();
+        let mut pc = 0;
+        for (i, element) in self.elements.iter().enumerate() {
+            match element {
+                CodeElement::Label(label) => {
+                    if positions.insert(label.0, pc).is_some() {
+                        return Err(encoder_error(&format!(
+                            "label {} is placed more than once",
+                            label.0
+                        )));
+                    }
+                }
+                CodeElement::Instruction(instruction) => {
+                    pc += instruction_size(instruction, pc, long[i])?;
+                    if pc > u16::MAX as u32 {
+                        return Err(encoder_error("code is longer than 65535 bytes"));
+                    }
+                }
+            }
+        }
+
+        let unplaced = |label: u32| !positions.contains_key(&label);
+        let mut referenced = Vec::new();
+        for element in self.elements.iter() {
+            if let CodeElement::Instruction(instruction) = element {
+                referenced.extend(instruction.branch_targets());
+            }
+        }
+        for entry in self.exception_table.iter() {
+            referenced.extend([entry.start.0, entry.end.0, entry.handler.0]);
+        }
+        for attribute in self.attributes.iter() {
+            match attribute {
+                LabeledAttribute::LineNumberTable { lines, .. } => {
+                    referenced.extend(lines.iter().map(|(label, _)| label.0))
+                }
+                LabeledAttribute::LocalVariableTable { variables, .. } => {
+                    referenced.extend(variables.iter().flat_map(|l| [l.start.0, l.end.0]))
+                }
+                _ => {}
+            }
+        }
+        match referenced.into_iter().find(|label| unplaced(*label)) {
+            Some(label) => Err(encoder_error(&format!("label {label} is never placed"))),
+            None => Ok(positions),
+        }
+    }
+}
+
+fn encoder_error(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Cannot encode code: {message}"),
+    )
+}
+
+/// Branches with a 16 bit offset (`goto`, `jsr` and the conditionals)
+fn is_short_branch(instruction: &Instruction) -> bool {
+    matches!(instruction, Instruction::Goto(_) | Instruction::Jsr(_))
+        || negate(instruction, 0).is_some()
+}
+
+/// The conditional branch with the opposite condition, or None if this isn't one
+fn negate(instruction: &Instruction, target: u32) -> Option<Instruction> {
+    Some(match instruction {
+        Instruction::Ifeq(_) => Instruction::Ifne(target),
+        Instruction::Ifne(_) => Instruction::Ifeq(target),
+        Instruction::Iflt(_) => Instruction::Ifge(target),
+        Instruction::Ifge(_) => Instruction::Iflt(target),
+        Instruction::Ifgt(_) => Instruction::Ifle(target),
+        Instruction::Ifle(_) => Instruction::Ifgt(target),
+        Instruction::IfIcmpeq(_) => Instruction::IfIcmpne(target),
+        Instruction::IfIcmpne(_) => Instruction::IfIcmpeq(target),
+        Instruction::IfIcmplt(_) => Instruction::IfIcmpge(target),
+        Instruction::IfIcmpge(_) => Instruction::IfIcmplt(target),
+        Instruction::IfIcmpgt(_) => Instruction::IfIcmple(target),
+        Instruction::IfIcmple(_) => Instruction::IfIcmpgt(target),
+        Instruction::IfAcmpeq(_) => Instruction::IfAcmpne(target),
+        Instruction::IfAcmpne(_) => Instruction::IfAcmpeq(target),
+        Instruction::Ifnull(_) => Instruction::Ifnonnull(target),
+        Instruction::Ifnonnull(_) => Instruction::Ifnull(target),
+        _ => return None,
+    })
+}
+
+/// Encodes a widened branch whose target has already been resolved to a pc
+fn encode_long(instruction: &Instruction, pc: u32, out: &mut Vec<u8>) -> Result<(), io::Error> {
+    let target = instruction.branch_targets()[0];
+    match instruction {
+        Instruction::Goto(_) => Instruction::GotoW(target).encode(pc, out),
+        Instruction::Jsr(_) => Instruction::JsrW(target).encode(pc, out),
+        _ => {
+            // if<cond> L  =>  if<!cond> +8; goto_w L
+            let skip = negate(instruction, pc + 8)
+                .ok_or_else(|| encoder_error("only branches can be widened"))?;
+            skip.encode(pc, out)?;
+            Instruction::GotoW(target).encode(pc + 3, out)
+        }
+    }
+}
+
+/// The encoded size, which only depends on the pc (for switch padding) and on whether a
+/// branch has been widened
+fn instruction_size(instruction: &Instruction, pc: u32, long: bool) -> Result<u32, io::Error> {
+    if long {
+        return Ok(match instruction {
+            Instruction::Goto(_) | Instruction::Jsr(_) => 5,
+            _ => 8,
+        });
+    }
+    let mut scratch = Vec::new();
+    instruction
+        .map_branch_targets(|_| pc)
+        .encode(pc, &mut scratch)?;
+    Ok(scratch.len() as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::class_file::ClassFile;
+
+    fn method_code(class_file: &ClassFile, name: &str, descriptor: &str) -> Code {
+        class_file
+            .find_method(name, descriptor)
+            .unwrap()
+            .code(class_file.constant_pool())
+            .unwrap()
+            .unwrap()
+    }
+
+    fn instructions(code: &Code) -> Vec<(u32, Instruction)> {
+        code.instructions().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn unchanged_code_round_trips() {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/helloworld/HelloWorld.class")
+            .unwrap();
+        for method in class_file.methods() {
+            let code = method.code(class_file.constant_pool()).unwrap().unwrap();
+            let labeled = LabeledCode::from_code(&code, class_file.constant_pool()).unwrap();
+            let encoded = labeled
+                .to_code(code.max_stack(), code.max_locals())
+                .unwrap();
+            assert_eq!(encoded, code);
+        }
+    }
+
+    #[test]
+    fn inserting_code_moves_branches_handlers_and_debug_ranges() {
+        let source = r#"
+.class Moves
+.method static f(I)I
+    .catch all from Start to End using Handler
+    .var 0 is x I from Start to End
+Start:
+    .line 3
+    iload_0
+    ifeq Zero
+    iconst_1
+    ireturn
+Zero:
+    .line 5
+    iconst_0
+End:
+    ireturn
+Handler:
+    athrow
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let code = method_code(&class_file, "f", "(I)I");
+        let mut labeled = LabeledCode::from_code(&code, class_file.constant_pool()).unwrap();
+        labeled
+            .elements_mut()
+            .insert(0, CodeElement::Instruction(Instruction::Nop));
+        let moved = labeled.to_code(1, 1).unwrap();
+
+        assert_eq!(
+            instructions(&moved)[..3],
+            [
+                (0, Instruction::Nop),
+                (1, Instruction::Iload0),
+                (2, Instruction::Ifeq(7)),
+            ]
+        );
+        // the labels sit after the nop, so everything they mark moves by one
+        assert_eq!(
+            moved.exception_table(),
+            &[ExceptionTableEntry::new(1, 8, 9, 0)]
+        );
+        let constant_pool = class_file.constant_pool();
+        let lines = find(&moved, constant_pool, LineNumberTable::NAME);
+        let lines = LineNumberTable::from(&mut lines.reader()).unwrap();
+        assert_eq!(
+            lines.line_number_table(),
+            &[LineNumber::new(1, 3), LineNumber::new(7, 5)]
+        );
+        let locals = find(&moved, constant_pool, LocalVariableTable::NAME);
+        let locals = LocalVariableTable::from(&mut locals.reader()).unwrap();
+        assert_eq!(locals.local_variable_table()[0].start_pc(), 1);
+        assert_eq!(locals.local_variable_table()[0].length(), 7);
+    }
+
+    fn find<'a>(code: &'a Code, constant_pool: &ConstantPool, name: &str) -> &'a AttributeInfo {
+        crate::class_file::attribute::attribute_info::find_attribute(
+            code.attributes(),
+            constant_pool,
+            name,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn long_branches_are_widened() {
+        let mut labeled = LabeledCode::new();
+        let (top, end) = (labeled.new_label(), labeled.new_label());
+        labeled.push_label(top);
+        labeled.push(Instruction::Iload0);
+        labeled.push(Instruction::Ifeq(end.id()));
+        for _ in 0..33000 {
+            labeled.push(Instruction::Nop);
+        }
+        labeled.push(Instruction::Goto(top.id()));
+        labeled.push_label(end);
+        labeled.push(Instruction::Return);
+
+        let code = labeled.to_code(1, 1).unwrap();
+        let decoded = instructions(&code);
+        // ifeq End  =>  ifne +8; goto_w End
+        assert_eq!(decoded[1], (1, Instruction::Ifne(9)));
+        assert_eq!(decoded[2], (4, Instruction::GotoW(33014)));
+        // the backward goto is too far for 16 bits as well
+        assert_eq!(decoded[33003], (33009, Instruction::GotoW(0)));
+        assert_eq!(decoded[33004], (33014, Instruction::Return));
+    }
+
+    #[test]
+    fn switch_padding_is_recomputed() {
+        let source = r#"
+.class Switch
+.method static f(I)I
+    iload_0
+    lookupswitch
+        1 : One
+        default : Other
+One:
+    iconst_1
+    ireturn
+Other:
+    iconst_0
+    ireturn
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let code = method_code(&class_file, "f", "(I)I");
+        assert_eq!(code.code().len(), 24);
+        let mut labeled = LabeledCode::from_code(&code, class_file.constant_pool()).unwrap();
+        labeled
+            .elements_mut()
+            .insert(0, CodeElement::Instruction(Instruction::Nop));
+        let moved = labeled.to_code(1, 1).unwrap();
+
+        // the switch moves from pc 1 to 2, losing a byte of padding
+        assert_eq!(moved.code().len(), 24);
+        assert_eq!(
+            instructions(&moved)[2],
+            (
+                2,
+                Instruction::Lookupswitch {
+                    default: 22,
+                    pairs: vec![(1, 20)],
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn stack_map_table_is_dropped_when_code_changes() {
+        // the assembler puts `.attribute` on the method; move it into the Code attribute
+        let source = r#"
+.class Frames
+.method static f()V
+    .attribute StackMapTable 0000
+    return
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let constant_pool = class_file.constant_pool();
+        let method = class_file.find_method("f", "()V").unwrap();
+        let frames = method.attributes()[1].clone();
+        let code = Code::new(0, 0, vec![0xb1], vec![], vec![frames.clone()]);
+
+        let mut labeled = LabeledCode::from_code(&code, constant_pool).unwrap();
+        assert_eq!(
+            labeled.attributes(),
+            &[LabeledAttribute::StackMapTable(frames)]
+        );
+        assert_eq!(labeled.to_code(0, 0).unwrap(), code);
+
+        labeled
+            .elements_mut()
+            .insert(0, CodeElement::Instruction(Instruction::Nop));
+        assert!(labeled.to_code(0, 0).unwrap().attributes().is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_labels() {
+        let mut labeled = LabeledCode::new();
+        let label = labeled.new_label();
+        labeled.push(Instruction::Goto(label.id()));
+        let error = labeled.to_code(0, 0).unwrap_err();
+        assert!(error.to_string().contains("never placed"));
+
+        labeled.push_label(label);
+        labeled.push_label(label);
+        let error = labeled.to_code(0, 0).unwrap_err();
+        assert!(error.to_string().contains("more than once"));
+
+        assert!(LabeledCode::new().to_code(0, 0).is_err());
+    }
+}
@@ -0,0 +1,1382 @@
+use crate::bytecode::opcode::{self, Opcode, OperandKind, ARRAY_TYPES};
+use std::io;
+
+/// A decoded instruction (chapter 6), one variant per Java 8 opcode.
+///
+/// Constant pool and local variable indexes are widened to `u16`; loads, stores, `ret` and
+/// `iinc` use the same variant whether or not they were prefixed by `wide`. Branch targets
+/// are absolute offsets into the code array rather than the relative offsets stored in it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Instruction {
+    Nop,
+    AconstNull,
+    IconstM1,
+    Iconst0,
+    Iconst1,
+    Iconst2,
+    Iconst3,
+    Iconst4,
+    Iconst5,
+    Lconst0,
+    Lconst1,
+    Fconst0,
+    Fconst1,
+    Fconst2,
+    Dconst0,
+    Dconst1,
+    Bipush(i8),
+    Sipush(i16),
+    /// Constant pool index, one byte in the code array
+    Ldc(u16),
+    LdcW(u16),
+    Ldc2W(u16),
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Iload0,
+    Iload1,
+    Iload2,
+    Iload3,
+    Lload0,
+    Lload1,
+    Lload2,
+    Lload3,
+    Fload0,
+    Fload1,
+    Fload2,
+    Fload3,
+    Dload0,
+    Dload1,
+    Dload2,
+    Dload3,
+    Aload0,
+    Aload1,
+    Aload2,
+    Aload3,
+    Iaload,
+    Laload,
+    Faload,
+    Daload,
+    Aaload,
+    Baload,
+    Caload,
+    Saload,
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Istore0,
+    Istore1,
+    Istore2,
+    Istore3,
+    Lstore0,
+    Lstore1,
+    Lstore2,
+    Lstore3,
+    Fstore0,
+    Fstore1,
+    Fstore2,
+    Fstore3,
+    Dstore0,
+    Dstore1,
+    Dstore2,
+    Dstore3,
+    Astore0,
+    Astore1,
+    Astore2,
+    Astore3,
+    Iastore,
+    Lastore,
+    Fastore,
+    Dastore,
+    Aastore,
+    Bastore,
+    Castore,
+    Sastore,
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+    Iadd,
+    Ladd,
+    Fadd,
+    Dadd,
+    Isub,
+    Lsub,
+    Fsub,
+    Dsub,
+    Imul,
+    Lmul,
+    Fmul,
+    Dmul,
+    Idiv,
+    Ldiv,
+    Fdiv,
+    Ddiv,
+    Irem,
+    Lrem,
+    Frem,
+    Drem,
+    Ineg,
+    Lneg,
+    Fneg,
+    Dneg,
+    Ishl,
+    Lshl,
+    Ishr,
+    Lshr,
+    Iushr,
+    Lushr,
+    Iand,
+    Land,
+    Ior,
+    Lor,
+    Ixor,
+    Lxor,
+    Iinc {
+        index: u16,
+        increment: i16,
+    },
+    I2l,
+    I2f,
+    I2d,
+    L2i,
+    L2f,
+    L2d,
+    F2i,
+    F2l,
+    F2d,
+    D2i,
+    D2l,
+    D2f,
+    I2b,
+    I2c,
+    I2s,
+    Lcmp,
+    Fcmpl,
+    Fcmpg,
+    Dcmpl,
+    Dcmpg,
+    Ifeq(u32),
+    Ifne(u32),
+    Iflt(u32),
+    Ifge(u32),
+    Ifgt(u32),
+    Ifle(u32),
+    IfIcmpeq(u32),
+    IfIcmpne(u32),
+    IfIcmplt(u32),
+    IfIcmpge(u32),
+    IfIcmpgt(u32),
+    IfIcmple(u32),
+    IfAcmpeq(u32),
+    IfAcmpne(u32),
+    Goto(u32),
+    Jsr(u32),
+    Ret(u16),
+    /// Jumps to `targets[key - low]`, or `default` when the key is outside `low..=high`
+    Tableswitch {
+        default: u32,
+        low: i32,
+        high: i32,
+        targets: Vec<u32>,
+    },
+    /// (match, target) pairs, which the specification requires to be sorted by match
+    Lookupswitch {
+        default: u32,
+        pairs: Vec<(i32, u32)>,
+    },
+    Ireturn,
+    Lreturn,
+    Freturn,
+    Dreturn,
+    Areturn,
+    Return,
+    Getstatic(u16),
+    Putstatic(u16),
+    Getfield(u16),
+    Putfield(u16),
+    Invokevirtual(u16),
+    Invokespecial(u16),
+    Invokestatic(u16),
+    Invokeinterface {
+        index: u16,
+        count: u8,
+    },
+    Invokedynamic(u16),
+    New(u16),
+    Newarray(ArrayType),
+    Anewarray(u16),
+    Arraylength,
+    Athrow,
+    Checkcast(u16),
+    Instanceof(u16),
+    Monitorenter,
+    Monitorexit,
+    Multianewarray {
+        index: u16,
+        dimensions: u8,
+    },
+    Ifnull(u32),
+    Ifnonnull(u32),
+    GotoW(u32),
+    JsrW(u32),
+}
+
+/// Element type of a `newarray` (section 6.5 newarray)
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ArrayType {
+    Boolean = 4,
+    Char = 5,
+    Float = 6,
+    Double = 7,
+    Byte = 8,
+    Short = 9,
+    Int = 10,
+    Long = 11,
+}
+
+impl ArrayType {
+    pub fn from_code(code: u8) -> Option<ArrayType> {
+        Some(match code {
+            4 => ArrayType::Boolean,
+            5 => ArrayType::Char,
+            6 => ArrayType::Float,
+            7 => ArrayType::Double,
+            8 => ArrayType::Byte,
+            9 => ArrayType::Short,
+            10 => ArrayType::Int,
+            11 => ArrayType::Long,
+            _ => return None,
+        })
+    }
+
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The Java keyword, e.g. `int`
+    pub fn name(&self) -> &'static str {
+        // ARRAY_TYPES is indexed by code starting at 4
+        ARRAY_TYPES[(self.code() - 4) as usize].1
+    }
+
+    /// The descriptor of an array of this type, e.g. `[I`
+    pub fn descriptor(&self) -> &'static str {
+        match self {
+            ArrayType::Boolean => "[Z",
+            ArrayType::Char => "[C",
+            ArrayType::Float => "[F",
+            ArrayType::Double => "[D",
+            ArrayType::Byte => "[B",
+            ArrayType::Short => "[S",
+            ArrayType::Int => "[I",
+            ArrayType::Long => "[J",
+        }
+    }
+}
+
+impl Instruction {
+    /// Decodes the instruction at `pc`, returning it along with its length in bytes
+    pub fn decode(code: &[u8], pc: usize) -> Result<(Instruction, usize), io::Error> {
+        let opcode = *code
+            .get(pc)
+            .ok_or_else(|| decode_error(pc, "no instruction"))?;
+        let op = Opcode::from_code(opcode)
+            .ok_or_else(|| decode_error(pc, &format!("unknown opcode 0x{opcode:02x}")))?;
+        let decoder = Decoder {
+            code,
+            pc,
+            mnemonic: op.mnemonic(),
+        };
+        decoder.decode(opcode)
+    }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => opcode::NOP,
+            Instruction::AconstNull => opcode::ACONST_NULL,
+            Instruction::IconstM1 => opcode::ICONST_M1,
+            Instruction::Iconst0 => opcode::ICONST_0,
+            Instruction::Iconst1 => opcode::ICONST_1,
+            Instruction::Iconst2 => opcode::ICONST_2,
+            Instruction::Iconst3 => opcode::ICONST_3,
+            Instruction::Iconst4 => opcode::ICONST_4,
+            Instruction::Iconst5 => opcode::ICONST_5,
+            Instruction::Lconst0 => opcode::LCONST_0,
+            Instruction::Lconst1 => opcode::LCONST_1,
+            Instruction::Fconst0 => opcode::FCONST_0,
+            Instruction::Fconst1 => opcode::FCONST_1,
+            Instruction::Fconst2 => opcode::FCONST_2,
+            Instruction::Dconst0 => opcode::DCONST_0,
+            Instruction::Dconst1 => opcode::DCONST_1,
+            Instruction::Bipush(_) => opcode::BIPUSH,
+            Instruction::Sipush(_) => opcode::SIPUSH,
+            Instruction::Ldc(_) => opcode::LDC,
+            Instruction::LdcW(_) => opcode::LDC_W,
+            Instruction::Ldc2W(_) => opcode::LDC2_W,
+            Instruction::Iload(_) => opcode::ILOAD,
+            Instruction::Lload(_) => opcode::LLOAD,
+            Instruction::Fload(_) => opcode::FLOAD,
+            Instruction::Dload(_) => opcode::DLOAD,
+            Instruction::Aload(_) => opcode::ALOAD,
+            Instruction::Iload0 => opcode::ILOAD_0,
+            Instruction::Iload1 => opcode::ILOAD_1,
+            Instruction::Iload2 => opcode::ILOAD_2,
+            Instruction::Iload3 => opcode::ILOAD_3,
+            Instruction::Lload0 => opcode::LLOAD_0,
+            Instruction::Lload1 => opcode::LLOAD_1,
+            Instruction::Lload2 => opcode::LLOAD_2,
+            Instruction::Lload3 => opcode::LLOAD_3,
+            Instruction::Fload0 => opcode::FLOAD_0,
+            Instruction::Fload1 => opcode::FLOAD_1,
+            Instruction::Fload2 => opcode::FLOAD_2,
+            Instruction::Fload3 => opcode::FLOAD_3,
+            Instruction::Dload0 => opcode::DLOAD_0,
+            Instruction::Dload1 => opcode::DLOAD_1,
+            Instruction::Dload2 => opcode::DLOAD_2,
+            Instruction::Dload3 => opcode::DLOAD_3,
+            Instruction::Aload0 => opcode::ALOAD_0,
+            Instruction::Aload1 => opcode::ALOAD_1,
+            Instruction::Aload2 => opcode::ALOAD_2,
+            Instruction::Aload3 => opcode::ALOAD_3,
+            Instruction::Iaload => opcode::IALOAD,
+            Instruction::Laload => opcode::LALOAD,
+            Instruction::Faload => opcode::FALOAD,
+            Instruction::Daload => opcode::DALOAD,
+            Instruction::Aaload => opcode::AALOAD,
+            Instruction::Baload => opcode::BALOAD,
+            Instruction::Caload => opcode::CALOAD,
+            Instruction::Saload => opcode::SALOAD,
+            Instruction::Istore(_) => opcode::ISTORE,
+            Instruction::Lstore(_) => opcode::LSTORE,
+            Instruction::Fstore(_) => opcode::FSTORE,
+            Instruction::Dstore(_) => opcode::DSTORE,
+            Instruction::Astore(_) => opcode::ASTORE,
+            Instruction::Istore0 => opcode::ISTORE_0,
+            Instruction::Istore1 => opcode::ISTORE_1,
+            Instruction::Istore2 => opcode::ISTORE_2,
+            Instruction::Istore3 => opcode::ISTORE_3,
+            Instruction::Lstore0 => opcode::LSTORE_0,
+            Instruction::Lstore1 => opcode::LSTORE_1,
+            Instruction::Lstore2 => opcode::LSTORE_2,
+            Instruction::Lstore3 => opcode::LSTORE_3,
+            Instruction::Fstore0 => opcode::FSTORE_0,
+            Instruction::Fstore1 => opcode::FSTORE_1,
+            Instruction::Fstore2 => opcode::FSTORE_2,
+            Instruction::Fstore3 => opcode::FSTORE_3,
+            Instruction::Dstore0 => opcode::DSTORE_0,
+            Instruction::Dstore1 => opcode::DSTORE_1,
+            Instruction::Dstore2 => opcode::DSTORE_2,
+            Instruction::Dstore3 => opcode::DSTORE_3,
+            Instruction::Astore0 => opcode::ASTORE_0,
+            Instruction::Astore1 => opcode::ASTORE_1,
+            Instruction::Astore2 => opcode::ASTORE_2,
+            Instruction::Astore3 => opcode::ASTORE_3,
+            Instruction::Iastore => opcode::IASTORE,
+            Instruction::Lastore => opcode::LASTORE,
+            Instruction::Fastore => opcode::FASTORE,
+            Instruction::Dastore => opcode::DASTORE,
+            Instruction::Aastore => opcode::AASTORE,
+            Instruction::Bastore => opcode::BASTORE,
+            Instruction::Castore => opcode::CASTORE,
+            Instruction::Sastore => opcode::SASTORE,
+            Instruction::Pop => opcode::POP,
+            Instruction::Pop2 => opcode::POP2,
+            Instruction::Dup => opcode::DUP,
+            Instruction::DupX1 => opcode::DUP_X1,
+            Instruction::DupX2 => opcode::DUP_X2,
+            Instruction::Dup2 => opcode::DUP2,
+            Instruction::Dup2X1 => opcode::DUP2_X1,
+            Instruction::Dup2X2 => opcode::DUP2_X2,
+            Instruction::Swap => opcode::SWAP,
+            Instruction::Iadd => opcode::IADD,
+            Instruction::Ladd => opcode::LADD,
+            Instruction::Fadd => opcode::FADD,
+            Instruction::Dadd => opcode::DADD,
+            Instruction::Isub => opcode::ISUB,
+            Instruction::Lsub => opcode::LSUB,
+            Instruction::Fsub => opcode::FSUB,
+            Instruction::Dsub => opcode::DSUB,
+            Instruction::Imul => opcode::IMUL,
+            Instruction::Lmul => opcode::LMUL,
+            Instruction::Fmul => opcode::FMUL,
+            Instruction::Dmul => opcode::DMUL,
+            Instruction::Idiv => opcode::IDIV,
+            Instruction::Ldiv => opcode::LDIV,
+            Instruction::Fdiv => opcode::FDIV,
+            Instruction::Ddiv => opcode::DDIV,
+            Instruction::Irem => opcode::IREM,
+            Instruction::Lrem => opcode::LREM,
+            Instruction::Frem => opcode::FREM,
+            Instruction::Drem => opcode::DREM,
+            Instruction::Ineg => opcode::INEG,
+            Instruction::Lneg => opcode::LNEG,
+            Instruction::Fneg => opcode::FNEG,
+            Instruction::Dneg => opcode::DNEG,
+            Instruction::Ishl => opcode::ISHL,
+            Instruction::Lshl => opcode::LSHL,
+            Instruction::Ishr => opcode::ISHR,
+            Instruction::Lshr => opcode::LSHR,
+            Instruction::Iushr => opcode::IUSHR,
+            Instruction::Lushr => opcode::LUSHR,
+            Instruction::Iand => opcode::IAND,
+            Instruction::Land => opcode::LAND,
+            Instruction::Ior => opcode::IOR,
+            Instruction::Lor => opcode::LOR,
+            Instruction::Ixor => opcode::IXOR,
+            Instruction::Lxor => opcode::LXOR,
+            Instruction::Iinc { .. } => opcode::IINC,
+            Instruction::I2l => opcode::I2L,
+            Instruction::I2f => opcode::I2F,
+            Instruction::I2d => opcode::I2D,
+            Instruction::L2i => opcode::L2I,
+            Instruction::L2f => opcode::L2F,
+            Instruction::L2d => opcode::L2D,
+            Instruction::F2i => opcode::F2I,
+            Instruction::F2l => opcode::F2L,
+            Instruction::F2d => opcode::F2D,
+            Instruction::D2i => opcode::D2I,
+            Instruction::D2l => opcode::D2L,
+            Instruction::D2f => opcode::D2F,
+            Instruction::I2b => opcode::I2B,
+            Instruction::I2c => opcode::I2C,
+            Instruction::I2s => opcode::I2S,
+            Instruction::Lcmp => opcode::LCMP,
+            Instruction::Fcmpl => opcode::FCMPL,
+            Instruction::Fcmpg => opcode::FCMPG,
+            Instruction::Dcmpl => opcode::DCMPL,
+            Instruction::Dcmpg => opcode::DCMPG,
+            Instruction::Ifeq(_) => opcode::IFEQ,
+            Instruction::Ifne(_) => opcode::IFNE,
+            Instruction::Iflt(_) => opcode::IFLT,
+            Instruction::Ifge(_) => opcode::IFGE,
+            Instruction::Ifgt(_) => opcode::IFGT,
+            Instruction::Ifle(_) => opcode::IFLE,
+            Instruction::IfIcmpeq(_) => opcode::IF_ICMPEQ,
+            Instruction::IfIcmpne(_) => opcode::IF_ICMPNE,
+            Instruction::IfIcmplt(_) => opcode::IF_ICMPLT,
+            Instruction::IfIcmpge(_) => opcode::IF_ICMPGE,
+            Instruction::IfIcmpgt(_) => opcode::IF_ICMPGT,
+            Instruction::IfIcmple(_) => opcode::IF_ICMPLE,
+            Instruction::IfAcmpeq(_) => opcode::IF_ACMPEQ,
+            Instruction::IfAcmpne(_) => opcode::IF_ACMPNE,
+            Instruction::Goto(_) => opcode::GOTO,
+            Instruction::Jsr(_) => opcode::JSR,
+            Instruction::Ret(_) => opcode::RET,
+            Instruction::Tableswitch { .. } => opcode::TABLESWITCH,
+            Instruction::Lookupswitch { .. } => opcode::LOOKUPSWITCH,
+            Instruction::Ireturn => opcode::IRETURN,
+            Instruction::Lreturn => opcode::LRETURN,
+            Instruction::Freturn => opcode::FRETURN,
+            Instruction::Dreturn => opcode::DRETURN,
+            Instruction::Areturn => opcode::ARETURN,
+            Instruction::Return => opcode::RETURN,
+            Instruction::Getstatic(_) => opcode::GETSTATIC,
+            Instruction::Putstatic(_) => opcode::PUTSTATIC,
+            Instruction::Getfield(_) => opcode::GETFIELD,
+            Instruction::Putfield(_) => opcode::PUTFIELD,
+            Instruction::Invokevirtual(_) => opcode::INVOKEVIRTUAL,
+            Instruction::Invokespecial(_) => opcode::INVOKESPECIAL,
+            Instruction::Invokestatic(_) => opcode::INVOKESTATIC,
+            Instruction::Invokeinterface { .. } => opcode::INVOKEINTERFACE,
+            Instruction::Invokedynamic(_) => opcode::INVOKEDYNAMIC,
+            Instruction::New(_) => opcode::NEW,
+            Instruction::Newarray(_) => opcode::NEWARRAY,
+            Instruction::Anewarray(_) => opcode::ANEWARRAY,
+            Instruction::Arraylength => opcode::ARRAYLENGTH,
+            Instruction::Athrow => opcode::ATHROW,
+            Instruction::Checkcast(_) => opcode::CHECKCAST,
+            Instruction::Instanceof(_) => opcode::INSTANCEOF,
+            Instruction::Monitorenter => opcode::MONITORENTER,
+            Instruction::Monitorexit => opcode::MONITOREXIT,
+            Instruction::Multianewarray { .. } => opcode::MULTIANEWARRAY,
+            Instruction::Ifnull(_) => opcode::IFNULL,
+            Instruction::Ifnonnull(_) => opcode::IFNONNULL,
+            Instruction::GotoW(_) => opcode::GOTO_W,
+            Instruction::JsrW(_) => opcode::JSR_W,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        // every variant maps to a defined opcode
+        Opcode::from_code(self.opcode()).unwrap().mnemonic()
+    }
+
+    /// The constant pool entry an instruction refers to, if any
+    pub fn constant_index(&self) -> Option<u16> {
+        match self {
+            Instruction::Ldc(index)
+            | Instruction::LdcW(index)
+            | Instruction::Ldc2W(index)
+            | Instruction::Getstatic(index)
+            | Instruction::Putstatic(index)
+            | Instruction::Getfield(index)
+            | Instruction::Putfield(index)
+            | Instruction::Invokevirtual(index)
+            | Instruction::Invokespecial(index)
+            | Instruction::Invokestatic(index)
+            | Instruction::Invokeinterface { index, .. }
+            | Instruction::Invokedynamic(index)
+            | Instruction::New(index)
+            | Instruction::Anewarray(index)
+            | Instruction::Checkcast(index)
+            | Instruction::Instanceof(index)
+            | Instruction::Multianewarray { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// The local variable an instruction with an explicit index operand uses, if any
+    pub fn local_index(&self) -> Option<u16> {
+        match self {
+            Instruction::Iload(index)
+            | Instruction::Lload(index)
+            | Instruction::Fload(index)
+            | Instruction::Dload(index)
+            | Instruction::Aload(index)
+            | Instruction::Istore(index)
+            | Instruction::Lstore(index)
+            | Instruction::Fstore(index)
+            | Instruction::Dstore(index)
+            | Instruction::Astore(index)
+            | Instruction::Ret(index)
+            | Instruction::Iinc { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+    /// Every pc control can transfer to other than the next instruction: the target of a
+    /// branch, `jsr` or `goto`, or all targets of a switch (default first)
+    pub fn branch_targets(&self) -> Vec<u32> {
+        match self {
+            Instruction::Ifeq(target)
+            | Instruction::Ifne(target)
+            | Instruction::Iflt(target)
+            | Instruction::Ifge(target)
+            | Instruction::Ifgt(target)
+            | Instruction::Ifle(target)
+            | Instruction::IfIcmpeq(target)
+            | Instruction::IfIcmpne(target)
+            | Instruction::IfIcmplt(target)
+            | Instruction::IfIcmpge(target)
+            | Instruction::IfIcmpgt(target)
+            | Instruction::IfIcmple(target)
+            | Instruction::IfAcmpeq(target)
+            | Instruction::IfAcmpne(target)
+            | Instruction::Goto(target)
+            | Instruction::Jsr(target)
+            | Instruction::Ifnull(target)
+            | Instruction::Ifnonnull(target)
+            | Instruction::GotoW(target)
+            | Instruction::JsrW(target) => vec![*target],
+            Instruction::Tableswitch {
+                default, targets, ..
+            } => std::iter::once(*default)
+                .chain(targets.iter().copied())
+                .collect(),
+            Instruction::Lookupswitch { default, pairs } => std::iter::once(*default)
+                .chain(pairs.iter().map(|(_, target)| *target))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Copies the instruction with every branch target passed through `f`
+    pub fn map_branch_targets(&self, mut f: impl FnMut(u32) -> u32) -> Instruction {
+        match self {
+            Instruction::Ifeq(target) => Instruction::Ifeq(f(*target)),
+            Instruction::Ifne(target) => Instruction::Ifne(f(*target)),
+            Instruction::Iflt(target) => Instruction::Iflt(f(*target)),
+            Instruction::Ifge(target) => Instruction::Ifge(f(*target)),
+            Instruction::Ifgt(target) => Instruction::Ifgt(f(*target)),
+            Instruction::Ifle(target) => Instruction::Ifle(f(*target)),
+            Instruction::IfIcmpeq(target) => Instruction::IfIcmpeq(f(*target)),
+            Instruction::IfIcmpne(target) => Instruction::IfIcmpne(f(*target)),
+            Instruction::IfIcmplt(target) => Instruction::IfIcmplt(f(*target)),
+            Instruction::IfIcmpge(target) => Instruction::IfIcmpge(f(*target)),
+            Instruction::IfIcmpgt(target) => Instruction::IfIcmpgt(f(*target)),
+            Instruction::IfIcmple(target) => Instruction::IfIcmple(f(*target)),
+            Instruction::IfAcmpeq(target) => Instruction::IfAcmpeq(f(*target)),
+            Instruction::IfAcmpne(target) => Instruction::IfAcmpne(f(*target)),
+            Instruction::Goto(target) => Instruction::Goto(f(*target)),
+            Instruction::Jsr(target) => Instruction::Jsr(f(*target)),
+            Instruction::Ifnull(target) => Instruction::Ifnull(f(*target)),
+            Instruction::Ifnonnull(target) => Instruction::Ifnonnull(f(*target)),
+            Instruction::GotoW(target) => Instruction::GotoW(f(*target)),
+            Instruction::JsrW(target) => Instruction::JsrW(f(*target)),
+            Instruction::Tableswitch {
+                default,
+                low,
+                high,
+                targets,
+            } => Instruction::Tableswitch {
+                default: f(*default),
+                low: *low,
+                high: *high,
+                targets: targets.iter().map(|target| f(*target)).collect(),
+            },
+            Instruction::Lookupswitch { default, pairs } => Instruction::Lookupswitch {
+                default: f(*default),
+                pairs: pairs
+                    .iter()
+                    .map(|(key, target)| (*key, f(*target)))
+                    .collect(),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Encodes the instruction at `pc`, the reverse of `decode`. Loads, stores, `ret` and
+    /// `iinc` get a `wide` prefix only when their operands don't fit in a byte, and `ldc`
+    /// becomes `ldc_w` for constant pool indexes above 255.
+    pub fn encode(&self, pc: u32, out: &mut Vec<u8>) -> Result<(), io::Error> {
+        let opcode = self.opcode();
+        let offset = |target: u32| target as i64 - pc as i64;
+        let offset32 = |target: u32| -> Result<[u8; 4], io::Error> {
+            i32::try_from(offset(target))
+                .map(i32::to_be_bytes)
+                .map_err(|_| decode_error(pc as usize, "branch offset does not fit in 32 bits"))
+        };
+        match self {
+            Instruction::Bipush(value) => out.extend_from_slice(&[opcode, *value as u8]),
+            Instruction::Sipush(value) => {
+                out.push(opcode);
+                out.extend_from_slice(&value.to_be_bytes());
+            }
+            Instruction::Ldc(index) => match u8::try_from(*index) {
+                Ok(index) => out.extend_from_slice(&[opcode, index]),
+                Err(_) => {
+                    out.push(opcode::LDC_W);
+                    out.extend_from_slice(&index.to_be_bytes());
+                }
+            },
+            Instruction::Iinc { index, increment } => {
+                match (u8::try_from(*index), i8::try_from(*increment)) {
+                    (Ok(index), Ok(increment)) => {
+                        out.extend_from_slice(&[opcode, index, increment as u8])
+                    }
+                    _ => {
+                        out.extend_from_slice(&[opcode::WIDE, opcode]);
+                        out.extend_from_slice(&index.to_be_bytes());
+                        out.extend_from_slice(&increment.to_be_bytes());
+                    }
+                }
+            }
+            Instruction::Invokeinterface { index, count } => {
+                out.push(opcode);
+                out.extend_from_slice(&index.to_be_bytes());
+                out.extend_from_slice(&[*count, 0]);
+            }
+            Instruction::Invokedynamic(index) => {
+                out.push(opcode);
+                out.extend_from_slice(&index.to_be_bytes());
+                out.extend_from_slice(&[0, 0]);
+            }
+            Instruction::Newarray(array_type) => {
+                out.extend_from_slice(&[opcode, array_type.code()])
+            }
+            Instruction::Multianewarray { index, dimensions } => {
+                out.push(opcode);
+                out.extend_from_slice(&index.to_be_bytes());
+                out.push(*dimensions);
+            }
+            Instruction::Tableswitch {
+                default,
+                low,
+                high,
+                targets,
+            } => {
+                if *high < *low || targets.len() as i64 != *high as i64 - *low as i64 + 1 {
+                    return Err(decode_error(
+                        pc as usize,
+                        &format!(
+                            "tableswitch from {low} to {high} has {} targets",
+                            targets.len()
+                        ),
+                    ));
+                }
+                out.push(opcode);
+                out.resize(out.len() + 3 - pc as usize % 4, 0);
+                out.extend_from_slice(&offset32(*default)?);
+                out.extend_from_slice(&low.to_be_bytes());
+                out.extend_from_slice(&high.to_be_bytes());
+                for target in targets {
+                    out.extend_from_slice(&offset32(*target)?);
+                }
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                out.push(opcode);
+                out.resize(out.len() + 3 - pc as usize % 4, 0);
+                out.extend_from_slice(&offset32(*default)?);
+                out.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+                for (key, target) in pairs {
+                    out.extend_from_slice(&key.to_be_bytes());
+                    out.extend_from_slice(&offset32(*target)?);
+                }
+            }
+            _ => {
+                if let Some(index) = self.constant_index() {
+                    out.push(opcode);
+                    out.extend_from_slice(&index.to_be_bytes());
+                } else if let Some(index) = self.local_index() {
+                    match u8::try_from(index) {
+                        Ok(index) => out.extend_from_slice(&[opcode, index]),
+                        Err(_) => {
+                            out.extend_from_slice(&[opcode::WIDE, opcode]);
+                            out.extend_from_slice(&index.to_be_bytes());
+                        }
+                    }
+                } else if let [target] = self.branch_targets()[..] {
+                    out.push(opcode);
+                    if opcode == opcode::GOTO_W || opcode == opcode::JSR_W {
+                        out.extend_from_slice(&offset32(target)?);
+                    } else {
+                        let offset = i16::try_from(offset(target)).map_err(|_| {
+                            decode_error(pc as usize, "branch offset does not fit in 16 bits")
+                        })?;
+                        out.extend_from_slice(&offset.to_be_bytes());
+                    }
+                } else {
+                    out.push(opcode);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode_error(pc: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{message} at pc {pc}"))
+}
+
+/// Reads the operands of the instruction at `pc`; offsets are relative to the opcode
+struct Decoder<'a> {
+    code: &'a [u8],
+    pc: usize,
+    mnemonic: &'static str,
+}
+
+impl Decoder<'_> {
+    fn error(&self, message: &str) -> io::Error {
+        decode_error(self.pc, message)
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N], io::Error> {
+        let start = self.pc + offset;
+        self.code
+            .get(start..start + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| self.error(&format!("truncated {}", self.mnemonic)))
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, io::Error> {
+        Ok(self.bytes::<1>(offset)?[0])
+    }
+
+    fn i8(&self, offset: usize) -> Result<i8, io::Error> {
+        Ok(self.u8(offset)? as i8)
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, io::Error> {
+        Ok(u16::from_be_bytes(self.bytes(offset)?))
+    }
+
+    fn i16(&self, offset: usize) -> Result<i16, io::Error> {
+        Ok(i16::from_be_bytes(self.bytes(offset)?))
+    }
+
+    fn i32(&self, offset: usize) -> Result<i32, io::Error> {
+        Ok(i32::from_be_bytes(self.bytes(offset)?))
+    }
+
+    /// Turns a branch offset relative to this instruction into an absolute one
+    fn target(&self, offset: i32) -> Result<u32, io::Error> {
+        u32::try_from(self.pc as i64 + offset as i64).map_err(|_| {
+            self.error(&format!(
+                "{} branches before the start of the code",
+                self.mnemonic
+            ))
+        })
+    }
+
+    fn decode(&self, opcode: u8) -> Result<(Instruction, usize), io::Error> {
+        if opcode == opcode::WIDE {
+            return self.wide();
+        }
+        let instruction = match opcode {
+            opcode::NOP => Instruction::Nop,
+            opcode::ACONST_NULL => Instruction::AconstNull,
+            opcode::ICONST_M1 => Instruction::IconstM1,
+            opcode::ICONST_0 => Instruction::Iconst0,
+            opcode::ICONST_1 => Instruction::Iconst1,
+            opcode::ICONST_2 => Instruction::Iconst2,
+            opcode::ICONST_3 => Instruction::Iconst3,
+            opcode::ICONST_4 => Instruction::Iconst4,
+            opcode::ICONST_5 => Instruction::Iconst5,
+            opcode::LCONST_0 => Instruction::Lconst0,
+            opcode::LCONST_1 => Instruction::Lconst1,
+            opcode::FCONST_0 => Instruction::Fconst0,
+            opcode::FCONST_1 => Instruction::Fconst1,
+            opcode::FCONST_2 => Instruction::Fconst2,
+            opcode::DCONST_0 => Instruction::Dconst0,
+            opcode::DCONST_1 => Instruction::Dconst1,
+            opcode::BIPUSH => Instruction::Bipush(self.i8(1)?),
+            opcode::SIPUSH => Instruction::Sipush(self.i16(1)?),
+            opcode::LDC => Instruction::Ldc(self.u8(1)? as u16),
+            opcode::LDC_W => Instruction::LdcW(self.u16(1)?),
+            opcode::LDC2_W => Instruction::Ldc2W(self.u16(1)?),
+            opcode::ILOAD => Instruction::Iload(self.u8(1)? as u16),
+            opcode::LLOAD => Instruction::Lload(self.u8(1)? as u16),
+            opcode::FLOAD => Instruction::Fload(self.u8(1)? as u16),
+            opcode::DLOAD => Instruction::Dload(self.u8(1)? as u16),
+            opcode::ALOAD => Instruction::Aload(self.u8(1)? as u16),
+            opcode::ILOAD_0 => Instruction::Iload0,
+            opcode::ILOAD_1 => Instruction::Iload1,
+            opcode::ILOAD_2 => Instruction::Iload2,
+            opcode::ILOAD_3 => Instruction::Iload3,
+            opcode::LLOAD_0 => Instruction::Lload0,
+            opcode::LLOAD_1 => Instruction::Lload1,
+            opcode::LLOAD_2 => Instruction::Lload2,
+            opcode::LLOAD_3 => Instruction::Lload3,
+            opcode::FLOAD_0 => Instruction::Fload0,
+            opcode::FLOAD_1 => Instruction::Fload1,
+            opcode::FLOAD_2 => Instruction::Fload2,
+            opcode::FLOAD_3 => Instruction::Fload3,
+            opcode::DLOAD_0 => Instruction::Dload0,
+            opcode::DLOAD_1 => Instruction::Dload1,
+            opcode::DLOAD_2 => Instruction::Dload2,
+            opcode::DLOAD_3 => Instruction::Dload3,
+            opcode::ALOAD_0 => Instruction::Aload0,
+            opcode::ALOAD_1 => Instruction::Aload1,
+            opcode::ALOAD_2 => Instruction::Aload2,
+            opcode::ALOAD_3 => Instruction::Aload3,
+            opcode::IALOAD => Instruction::Iaload,
+            opcode::LALOAD => Instruction::Laload,
+            opcode::FALOAD => Instruction::Faload,
+            opcode::DALOAD => Instruction::Daload,
+            opcode::AALOAD => Instruction::Aaload,
+            opcode::BALOAD => Instruction::Baload,
+            opcode::CALOAD => Instruction::Caload,
+            opcode::SALOAD => Instruction::Saload,
+            opcode::ISTORE => Instruction::Istore(self.u8(1)? as u16),
+            opcode::LSTORE => Instruction::Lstore(self.u8(1)? as u16),
+            opcode::FSTORE => Instruction::Fstore(self.u8(1)? as u16),
+            opcode::DSTORE => Instruction::Dstore(self.u8(1)? as u16),
+            opcode::ASTORE => Instruction::Astore(self.u8(1)? as u16),
+            opcode::ISTORE_0 => Instruction::Istore0,
+            opcode::ISTORE_1 => Instruction::Istore1,
+            opcode::ISTORE_2 => Instruction::Istore2,
+            opcode::ISTORE_3 => Instruction::Istore3,
+            opcode::LSTORE_0 => Instruction::Lstore0,
+            opcode::LSTORE_1 => Instruction::Lstore1,
+            opcode::LSTORE_2 => Instruction::Lstore2,
+            opcode::LSTORE_3 => Instruction::Lstore3,
+            opcode::FSTORE_0 => Instruction::Fstore0,
+            opcode::FSTORE_1 => Instruction::Fstore1,
+            opcode::FSTORE_2 => Instruction::Fstore2,
+            opcode::FSTORE_3 => Instruction::Fstore3,
+            opcode::DSTORE_0 => Instruction::Dstore0,
+            opcode::DSTORE_1 => Instruction::Dstore1,
+            opcode::DSTORE_2 => Instruction::Dstore2,
+            opcode::DSTORE_3 => Instruction::Dstore3,
+            opcode::ASTORE_0 => Instruction::Astore0,
+            opcode::ASTORE_1 => Instruction::Astore1,
+            opcode::ASTORE_2 => Instruction::Astore2,
+            opcode::ASTORE_3 => Instruction::Astore3,
+            opcode::IASTORE => Instruction::Iastore,
+            opcode::LASTORE => Instruction::Lastore,
+            opcode::FASTORE => Instruction::Fastore,
+            opcode::DASTORE => Instruction::Dastore,
+            opcode::AASTORE => Instruction::Aastore,
+            opcode::BASTORE => Instruction::Bastore,
+            opcode::CASTORE => Instruction::Castore,
+            opcode::SASTORE => Instruction::Sastore,
+            opcode::POP => Instruction::Pop,
+            opcode::POP2 => Instruction::Pop2,
+            opcode::DUP => Instruction::Dup,
+            opcode::DUP_X1 => Instruction::DupX1,
+            opcode::DUP_X2 => Instruction::DupX2,
+            opcode::DUP2 => Instruction::Dup2,
+            opcode::DUP2_X1 => Instruction::Dup2X1,
+            opcode::DUP2_X2 => Instruction::Dup2X2,
+            opcode::SWAP => Instruction::Swap,
+            opcode::IADD => Instruction::Iadd,
+            opcode::LADD => Instruction::Ladd,
+            opcode::FADD => Instruction::Fadd,
+            opcode::DADD => Instruction::Dadd,
+            opcode::ISUB => Instruction::Isub,
+            opcode::LSUB => Instruction::Lsub,
+            opcode::FSUB => Instruction::Fsub,
+            opcode::DSUB => Instruction::Dsub,
+            opcode::IMUL => Instruction::Imul,
+            opcode::LMUL => Instruction::Lmul,
+            opcode::FMUL => Instruction::Fmul,
+            opcode::DMUL => Instruction::Dmul,
+            opcode::IDIV => Instruction::Idiv,
+            opcode::LDIV => Instruction::Ldiv,
+            opcode::FDIV => Instruction::Fdiv,
+            opcode::DDIV => Instruction::Ddiv,
+            opcode::IREM => Instruction::Irem,
+            opcode::LREM => Instruction::Lrem,
+            opcode::FREM => Instruction::Frem,
+            opcode::DREM => Instruction::Drem,
+            opcode::INEG => Instruction::Ineg,
+            opcode::LNEG => Instruction::Lneg,
+            opcode::FNEG => Instruction::Fneg,
+            opcode::DNEG => Instruction::Dneg,
+            opcode::ISHL => Instruction::Ishl,
+            opcode::LSHL => Instruction::Lshl,
+            opcode::ISHR => Instruction::Ishr,
+            opcode::LSHR => Instruction::Lshr,
+            opcode::IUSHR => Instruction::Iushr,
+            opcode::LUSHR => Instruction::Lushr,
+            opcode::IAND => Instruction::Iand,
+            opcode::LAND => Instruction::Land,
+            opcode::IOR => Instruction::Ior,
+            opcode::LOR => Instruction::Lor,
+            opcode::IXOR => Instruction::Ixor,
+            opcode::LXOR => Instruction::Lxor,
+            opcode::IINC => Instruction::Iinc {
+                index: self.u8(1)? as u16,
+                increment: self.i8(2)? as i16,
+            },
+            opcode::I2L => Instruction::I2l,
+            opcode::I2F => Instruction::I2f,
+            opcode::I2D => Instruction::I2d,
+            opcode::L2I => Instruction::L2i,
+            opcode::L2F => Instruction::L2f,
+            opcode::L2D => Instruction::L2d,
+            opcode::F2I => Instruction::F2i,
+            opcode::F2L => Instruction::F2l,
+            opcode::F2D => Instruction::F2d,
+            opcode::D2I => Instruction::D2i,
+            opcode::D2L => Instruction::D2l,
+            opcode::D2F => Instruction::D2f,
+            opcode::I2B => Instruction::I2b,
+            opcode::I2C => Instruction::I2c,
+            opcode::I2S => Instruction::I2s,
+            opcode::LCMP => Instruction::Lcmp,
+            opcode::FCMPL => Instruction::Fcmpl,
+            opcode::FCMPG => Instruction::Fcmpg,
+            opcode::DCMPL => Instruction::Dcmpl,
+            opcode::DCMPG => Instruction::Dcmpg,
+            opcode::IFEQ => Instruction::Ifeq(self.target(self.i16(1)? as i32)?),
+            opcode::IFNE => Instruction::Ifne(self.target(self.i16(1)? as i32)?),
+            opcode::IFLT => Instruction::Iflt(self.target(self.i16(1)? as i32)?),
+            opcode::IFGE => Instruction::Ifge(self.target(self.i16(1)? as i32)?),
+            opcode::IFGT => Instruction::Ifgt(self.target(self.i16(1)? as i32)?),
+            opcode::IFLE => Instruction::Ifle(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ICMPEQ => Instruction::IfIcmpeq(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ICMPNE => Instruction::IfIcmpne(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ICMPLT => Instruction::IfIcmplt(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ICMPGE => Instruction::IfIcmpge(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ICMPGT => Instruction::IfIcmpgt(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ICMPLE => Instruction::IfIcmple(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ACMPEQ => Instruction::IfAcmpeq(self.target(self.i16(1)? as i32)?),
+            opcode::IF_ACMPNE => Instruction::IfAcmpne(self.target(self.i16(1)? as i32)?),
+            opcode::GOTO => Instruction::Goto(self.target(self.i16(1)? as i32)?),
+            opcode::JSR => Instruction::Jsr(self.target(self.i16(1)? as i32)?),
+            opcode::RET => Instruction::Ret(self.u8(1)? as u16),
+            opcode::TABLESWITCH => self.tableswitch()?,
+            opcode::LOOKUPSWITCH => self.lookupswitch()?,
+            opcode::IRETURN => Instruction::Ireturn,
+            opcode::LRETURN => Instruction::Lreturn,
+            opcode::FRETURN => Instruction::Freturn,
+            opcode::DRETURN => Instruction::Dreturn,
+            opcode::ARETURN => Instruction::Areturn,
+            opcode::RETURN => Instruction::Return,
+            opcode::GETSTATIC => Instruction::Getstatic(self.u16(1)?),
+            opcode::PUTSTATIC => Instruction::Putstatic(self.u16(1)?),
+            opcode::GETFIELD => Instruction::Getfield(self.u16(1)?),
+            opcode::PUTFIELD => Instruction::Putfield(self.u16(1)?),
+            opcode::INVOKEVIRTUAL => Instruction::Invokevirtual(self.u16(1)?),
+            opcode::INVOKESPECIAL => Instruction::Invokespecial(self.u16(1)?),
+            opcode::INVOKESTATIC => Instruction::Invokestatic(self.u16(1)?),
+            opcode::INVOKEINTERFACE => Instruction::Invokeinterface {
+                index: self.u16(1)?,
+                count: self.u8(3)?,
+            },
+            opcode::INVOKEDYNAMIC => Instruction::Invokedynamic(self.u16(1)?),
+            opcode::NEW => Instruction::New(self.u16(1)?),
+            opcode::NEWARRAY => {
+                let code = self.u8(1)?;
+                let array_type = ArrayType::from_code(code)
+                    .ok_or_else(|| self.error(&format!("invalid newarray type {code}")))?;
+                Instruction::Newarray(array_type)
+            }
+            opcode::ANEWARRAY => Instruction::Anewarray(self.u16(1)?),
+            opcode::ARRAYLENGTH => Instruction::Arraylength,
+            opcode::ATHROW => Instruction::Athrow,
+            opcode::CHECKCAST => Instruction::Checkcast(self.u16(1)?),
+            opcode::INSTANCEOF => Instruction::Instanceof(self.u16(1)?),
+            opcode::MONITORENTER => Instruction::Monitorenter,
+            opcode::MONITOREXIT => Instruction::Monitorexit,
+            opcode::MULTIANEWARRAY => Instruction::Multianewarray {
+                index: self.u16(1)?,
+                dimensions: self.u8(3)?,
+            },
+            opcode::IFNULL => Instruction::Ifnull(self.target(self.i16(1)? as i32)?),
+            opcode::IFNONNULL => Instruction::Ifnonnull(self.target(self.i16(1)? as i32)?),
+            opcode::GOTO_W => Instruction::GotoW(self.target(self.i32(1)?)?),
+            opcode::JSR_W => Instruction::JsrW(self.target(self.i32(1)?)?),
+            _ => unreachable!("every defined opcode is decoded"),
+        };
+        let length = match &instruction {
+            Instruction::Tableswitch { targets, .. } => 1 + self.padding() + 12 + 4 * targets.len(),
+            Instruction::Lookupswitch { pairs, .. } => 1 + self.padding() + 8 + 8 * pairs.len(),
+            _ => match Opcode::from_code(opcode).unwrap().operands() {
+                OperandKind::None => 1,
+                OperandKind::Byte
+                | OperandKind::LocalIndex
+                | OperandKind::ConstantIndex8
+                | OperandKind::NewArray => 2,
+                OperandKind::Short
+                | OperandKind::ConstantIndex16
+                | OperandKind::Branch16
+                | OperandKind::Iinc => 3,
+                OperandKind::MultiANewArray => 4,
+                OperandKind::Branch32
+                | OperandKind::InvokeInterface
+                | OperandKind::InvokeDynamic => 5,
+                OperandKind::TableSwitch | OperandKind::LookupSwitch | OperandKind::Wide => {
+                    unreachable!("handled above")
+                }
+            },
+        };
+        Ok((instruction, length))
+    }
+
+    /// `wide` followed by a load, store or ret with a 16 bit index, or an iinc with 16 bit
+    /// index and increment (section 6.5 wide)
+    fn wide(&self) -> Result<(Instruction, usize), io::Error> {
+        let index = || self.u16(2);
+        let instruction = match self.u8(1)? {
+            opcode::ILOAD => Instruction::Iload(index()?),
+            opcode::LLOAD => Instruction::Lload(index()?),
+            opcode::FLOAD => Instruction::Fload(index()?),
+            opcode::DLOAD => Instruction::Dload(index()?),
+            opcode::ALOAD => Instruction::Aload(index()?),
+            opcode::ISTORE => Instruction::Istore(index()?),
+            opcode::LSTORE => Instruction::Lstore(index()?),
+            opcode::FSTORE => Instruction::Fstore(index()?),
+            opcode::DSTORE => Instruction::Dstore(index()?),
+            opcode::ASTORE => Instruction::Astore(index()?),
+            opcode::RET => Instruction::Ret(index()?),
+            opcode::IINC => {
+                let instruction = Instruction::Iinc {
+                    index: index()?,
+                    increment: self.i16(4)?,
+                };
+                return Ok((instruction, 6));
+            }
+            other => return Err(self.error(&format!("wide cannot modify opcode 0x{other:02x}"))),
+        };
+        Ok((instruction, 4))
+    }
+
+    /// Bytes between the opcode and the next multiple of four from the start of the code
+    fn padding(&self) -> usize {
+        3 - self.pc % 4
+    }
+
+    fn tableswitch(&self) -> Result<Instruction, io::Error> {
+        let start = 1 + self.padding();
+        let default = self.target(self.i32(start)?)?;
+        let (low, high) = (self.i32(start + 4)?, self.i32(start + 8)?);
+        if high < low {
+            return Err(self.error(&format!(
+                "tableswitch low {low} is greater than high {high}"
+            )));
+        }
+        // check the size against the code array before allocating anything
+        let count = (high as i64 - low as i64 + 1) as usize;
+        if count > (self.code.len() - self.pc) / 4 {
+            return Err(self.error("truncated tableswitch"));
+        }
+        let targets = (0..count)
+            .map(|i| self.target(self.i32(start + 12 + 4 * i)?))
+            .collect::<Result<Vec<u32>, io::Error>>()?;
+        Ok(Instruction::Tableswitch {
+            default,
+            low,
+            high,
+            targets,
+        })
+    }
+
+    fn lookupswitch(&self) -> Result<Instruction, io::Error> {
+        let start = 1 + self.padding();
+        let default = self.target(self.i32(start)?)?;
+        let count = self.i32(start + 4)?;
+        if count < 0 {
+            return Err(self.error(&format!("negative lookupswitch pair count {count}")));
+        }
+        if count as usize > (self.code.len() - self.pc) / 8 {
+            return Err(self.error("truncated lookupswitch"));
+        }
+        let pairs = (0..count as usize)
+            .map(|i| {
+                let offset = start + 8 + 8 * i;
+                Ok((self.i32(offset)?, self.target(self.i32(offset + 4)?)?))
+            })
+            .collect::<Result<Vec<(i32, u32)>, io::Error>>()?;
+        Ok(Instruction::Lookupswitch { default, pairs })
+    }
+}
+
+/// Iterates over the instructions of a code array as `(pc, Instruction)` pairs, stopping
+/// after the first instruction that can't be decoded
+pub struct Instructions<'a> {
+    code: &'a [u8],
+    pc: usize,
+    failed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(code: &'a [u8]) -> Instructions<'a> {
+        Instructions {
+            code,
+            pc: 0,
+            failed: false,
+        }
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<(u32, Instruction), io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pc >= self.code.len() {
+            return None;
+        }
+        match Instruction::decode(self.code, self.pc) {
+            Ok((instruction, length)) => {
+                let pc = self.pc as u32;
+                self.pc += length;
+                Some(Ok((pc, instruction)))
+            }
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode_all(code: &[u8]) -> Result<Vec<(u32, Instruction)>, io::Error> {
+        Instructions::new(code).collect()
+    }
+
+    #[test]
+    fn simple_and_operand_instructions() {
+        // iconst_1, bipush -2, sipush 300, ldc #4, getstatic #2, iinc 1 -1, newarray int, return
+        let code = [
+            0x04, 0x10, 0xfe, 0x11, 0x01, 0x2c, 0x12, 0x04, 0xb2, 0x00, 0x02, 0x84, 0x01, 0xff,
+            0xbc, 0x0a, 0xb1,
+        ];
+        let instructions = decode_all(&code).unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                (0, Instruction::Iconst1),
+                (1, Instruction::Bipush(-2)),
+                (3, Instruction::Sipush(300)),
+                (6, Instruction::Ldc(4)),
+                (8, Instruction::Getstatic(2)),
+                (
+                    11,
+                    Instruction::Iinc {
+                        index: 1,
+                        increment: -1
+                    }
+                ),
+                (14, Instruction::Newarray(ArrayType::Int)),
+                (16, Instruction::Return),
+            ]
+        );
+        assert_eq!(instructions[4].1.mnemonic(), "getstatic");
+        assert_eq!(instructions[4].1.constant_index(), Some(2));
+        assert_eq!(ArrayType::Int.name(), "int");
+    }
+
+    #[test]
+    fn every_opcode_round_trips_through_its_variant() {
+        for opcode in opcode::OPCODES.iter() {
+            if opcode.code() == opcode::WIDE {
+                continue;
+            }
+            // long enough for any operands (a one entry tableswitch is the longest), with a
+            // valid newarray type
+            let mut code = vec![0; 24];
+            code[0] = opcode.code();
+            if opcode.code() == opcode::NEWARRAY {
+                code[1] = 4;
+            }
+            let (instruction, _) = Instruction::decode(&code, 0).unwrap();
+            assert_eq!(instruction.opcode(), opcode.code());
+            assert_eq!(instruction.mnemonic(), opcode.mnemonic());
+        }
+    }
+
+    #[test]
+    fn wide_forms_and_branches() {
+        // 0: wide iload 256, 4: wide iinc 2 1000, 10: goto 4 (-6), 13: goto_w 0 (-13)
+        let code = [
+            0xc4, 0x15, 0x01, 0x00, 0xc4, 0x84, 0x00, 0x02, 0x03, 0xe8, 0xa7, 0xff, 0xfa, 0xc8,
+            0xff, 0xff, 0xff, 0xf3,
+        ];
+        let instructions = decode_all(&code).unwrap();
+        assert_eq!(instructions[0], (0, Instruction::Iload(256)));
+        assert_eq!(
+            instructions[1],
+            (
+                4,
+                Instruction::Iinc {
+                    index: 2,
+                    increment: 1000
+                }
+            )
+        );
+        assert_eq!(instructions[2], (10, Instruction::Goto(4)));
+        assert_eq!(instructions[3], (13, Instruction::GotoW(0)));
+        assert_eq!(instructions[0].1.local_index(), Some(256));
+        assert_eq!(instructions[2].1.branch_targets(), vec![4]);
+    }
+
+    #[test]
+    fn switches_are_aligned() {
+        // 0: iload_0, 1: tableswitch (2 bytes padding) default 24, low 0, high 1 -> 24, 1
+        // 24: lookupswitch (3 bytes padding) default 0, pairs 5 -> 1, 9 -> 24
+        let mut code = vec![0x1a, 0xaa, 0x00, 0x00];
+        for value in [23i32, 0, 1, 23, 0] {
+            code.extend_from_slice(&value.to_be_bytes());
+        }
+        code.extend_from_slice(&[0xab, 0x00, 0x00, 0x00]);
+        for value in [-24i32, 2, 5, -23, 9, 0] {
+            code.extend_from_slice(&value.to_be_bytes());
+        }
+        let instructions = decode_all(&code).unwrap();
+        assert_eq!(
+            instructions[1],
+            (
+                1,
+                Instruction::Tableswitch {
+                    default: 24,
+                    low: 0,
+                    high: 1,
+                    targets: vec![24, 1]
+                }
+            )
+        );
+        assert_eq!(
+            instructions[2],
+            (
+                24,
+                Instruction::Lookupswitch {
+                    default: 0,
+                    pairs: vec![(5, 1), (9, 24)]
+                }
+            )
+        );
+        assert_eq!(instructions[2].1.branch_targets(), vec![0, 1, 24]);
+        assert_eq!(instructions.len(), 3);
+    }
+
+    #[test]
+    fn encode_reverses_decode() {
+        let code = [
+            0x04, 0x10, 0xfe, 0x11, 0x01, 0x2c, 0x12, 0x04, 0xb2, 0x00, 0x02, 0x84, 0x01, 0xff,
+            0xbc, 0x0a, 0xc4, 0x15, 0x01, 0x00, 0xa7, 0xff, 0xec, 0xb9, 0x00, 0x07, 0x02, 0x00,
+            0xaa, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xe4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xb1,
+        ];
+        let mut out = Vec::new();
+        for result in Instructions::new(&code) {
+            let (pc, instruction) = result.unwrap();
+            instruction.encode(pc, &mut out).unwrap();
+        }
+        assert_eq!(out, code);
+    }
+
+    #[test]
+    fn encode_picks_short_or_wide_forms() {
+        let encode = |instruction: Instruction| {
+            let mut out = Vec::new();
+            instruction.encode(0, &mut out).map(|_| out)
+        };
+        assert_eq!(encode(Instruction::Iload(3)).unwrap(), [0x15, 3]);
+        assert_eq!(
+            encode(Instruction::Iload(300)).unwrap(),
+            [0xc4, 0x15, 0x01, 0x2c]
+        );
+        assert_eq!(encode(Instruction::Ldc(300)).unwrap(), [0x13, 0x01, 0x2c]);
+        assert_eq!(
+            encode(Instruction::Iinc {
+                index: 1,
+                increment: 200
+            })
+            .unwrap(),
+            [0xc4, 0x84, 0x00, 0x01, 0x00, 0xc8]
+        );
+        assert!(encode(Instruction::Goto(40000)).is_err());
+        assert_eq!(
+            encode(Instruction::GotoW(40000)).unwrap(),
+            [0xc8, 0x00, 0x00, 0x9c, 0x40]
+        );
+        let switch = Instruction::Tableswitch {
+            default: 0,
+            low: 0,
+            high: 1,
+            targets: vec![0],
+        };
+        assert!(encode(switch).is_err());
+        assert_eq!(
+            Instruction::Goto(3).map_branch_targets(|target| target * 2),
+            Instruction::Goto(6)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_code() {
+        let error = |code: &[u8]| decode_all(code).unwrap_err().to_string();
+        assert_eq!(error(&[0x00, 0xcb]), "unknown opcode 0xcb at pc 1");
+        assert_eq!(error(&[0xb2, 0x00]), "truncated getstatic at pc 0");
+        assert_eq!(
+            error(&[0xc4, 0xb1, 0x00, 0x00]),
+            "wide cannot modify opcode 0xb1 at pc 0"
+        );
+        assert_eq!(
+            error(&[0xa7, 0xff, 0xff]),
+            "goto branches before the start of the code at pc 0"
+        );
+        assert_eq!(error(&[0xbc, 0x03]), "invalid newarray type 3 at pc 0");
+        // tableswitch with high < low, and one claiming far more targets than there are bytes
+        let mut code = vec![0xaa, 0, 0, 0];
+        for value in [0i32, 5, 1] {
+            code.extend_from_slice(&value.to_be_bytes());
+        }
+        assert!(error(&code).contains("greater than high"));
+        let mut code = vec![0xaa, 0, 0, 0];
+        for value in [0, 0, i32::MAX] {
+            code.extend_from_slice(&value.to_be_bytes());
+        }
+        assert_eq!(error(&code), "truncated tableswitch at pc 0");
+
+        // iteration stops after an error
+        let mut iter = Instructions::new(&[0xcb, 0x00]);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod encoder;
+pub mod instruction;
+pub mod opcode;
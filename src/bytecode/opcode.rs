@@ -0,0 +1,524 @@
+/// Operand layout following an opcode in the code array (chapter 6)
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum OperandKind {
+    None,
+    /// Signed byte immediate (bipush)
+    Byte,
+    /// Signed short immediate (sipush)
+    Short,
+    /// Unsigned byte local variable index, 16 bits after a `wide` prefix
+    LocalIndex,
+    /// Unsigned byte constant pool index (ldc)
+    ConstantIndex8,
+    /// Unsigned short constant pool index
+    ConstantIndex16,
+    /// Signed 16 bit branch offset relative to the opcode
+    Branch16,
+    /// Signed 32 bit branch offset relative to the opcode
+    Branch32,
+    /// Local variable index and signed increment, both 16 bits after a `wide` prefix
+    Iinc,
+    /// Primitive array type code (newarray)
+    NewArray,
+    /// Constant pool index, argument count and a zero byte
+    InvokeInterface,
+    /// Constant pool index followed by two zero bytes
+    InvokeDynamic,
+    /// Constant pool index and dimension count
+    MultiANewArray,
+    /// Padding, default, low, high and jump offsets
+    TableSwitch,
+    /// Padding, default, pair count and match/offset pairs
+    LookupSwitch,
+    /// Prefix that widens the following load, store, ret or iinc
+    Wide,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Opcode {
+    code: u8,
+    mnemonic: &'static str,
+    operands: OperandKind,
+}
+
+impl Opcode {
+    const fn new(code: u8, mnemonic: &'static str, operands: OperandKind) -> Opcode {
+        Opcode {
+            code,
+            mnemonic,
+            operands,
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        self.code
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        self.mnemonic
+    }
+
+    pub fn operands(&self) -> OperandKind {
+        self.operands
+    }
+
+    /// Looks up a defined opcode (0x00 - 0xc9); reserved and unassigned values return `None`
+    pub fn from_code(code: u8) -> Option<&'static Opcode> {
+        OPCODES.get(code as usize)
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<&'static Opcode> {
+        OPCODES.iter().find(|opcode| opcode.mnemonic == mnemonic)
+    }
+}
+
+pub const NOP: u8 = 0x00;
+pub const ACONST_NULL: u8 = 0x01;
+pub const ICONST_M1: u8 = 0x02;
+pub const ICONST_0: u8 = 0x03;
+pub const ICONST_1: u8 = 0x04;
+pub const ICONST_2: u8 = 0x05;
+pub const ICONST_3: u8 = 0x06;
+pub const ICONST_4: u8 = 0x07;
+pub const ICONST_5: u8 = 0x08;
+pub const LCONST_0: u8 = 0x09;
+pub const LCONST_1: u8 = 0x0a;
+pub const FCONST_0: u8 = 0x0b;
+pub const FCONST_1: u8 = 0x0c;
+pub const FCONST_2: u8 = 0x0d;
+pub const DCONST_0: u8 = 0x0e;
+pub const DCONST_1: u8 = 0x0f;
+pub const BIPUSH: u8 = 0x10;
+pub const SIPUSH: u8 = 0x11;
+pub const LDC: u8 = 0x12;
+pub const LDC_W: u8 = 0x13;
+pub const LDC2_W: u8 = 0x14;
+pub const ILOAD: u8 = 0x15;
+pub const LLOAD: u8 = 0x16;
+pub const FLOAD: u8 = 0x17;
+pub const DLOAD: u8 = 0x18;
+pub const ALOAD: u8 = 0x19;
+pub const ILOAD_0: u8 = 0x1a;
+pub const ILOAD_1: u8 = 0x1b;
+pub const ILOAD_2: u8 = 0x1c;
+pub const ILOAD_3: u8 = 0x1d;
+pub const LLOAD_0: u8 = 0x1e;
+pub const LLOAD_1: u8 = 0x1f;
+pub const LLOAD_2: u8 = 0x20;
+pub const LLOAD_3: u8 = 0x21;
+pub const FLOAD_0: u8 = 0x22;
+pub const FLOAD_1: u8 = 0x23;
+pub const FLOAD_2: u8 = 0x24;
+pub const FLOAD_3: u8 = 0x25;
+pub const DLOAD_0: u8 = 0x26;
+pub const DLOAD_1: u8 = 0x27;
+pub const DLOAD_2: u8 = 0x28;
+pub const DLOAD_3: u8 = 0x29;
+pub const ALOAD_0: u8 = 0x2a;
+pub const ALOAD_1: u8 = 0x2b;
+pub const ALOAD_2: u8 = 0x2c;
+pub const ALOAD_3: u8 = 0x2d;
+pub const IALOAD: u8 = 0x2e;
+pub const LALOAD: u8 = 0x2f;
+pub const FALOAD: u8 = 0x30;
+pub const DALOAD: u8 = 0x31;
+pub const AALOAD: u8 = 0x32;
+pub const BALOAD: u8 = 0x33;
+pub const CALOAD: u8 = 0x34;
+pub const SALOAD: u8 = 0x35;
+pub const ISTORE: u8 = 0x36;
+pub const LSTORE: u8 = 0x37;
+pub const FSTORE: u8 = 0x38;
+pub const DSTORE: u8 = 0x39;
+pub const ASTORE: u8 = 0x3a;
+pub const ISTORE_0: u8 = 0x3b;
+pub const ISTORE_1: u8 = 0x3c;
+pub const ISTORE_2: u8 = 0x3d;
+pub const ISTORE_3: u8 = 0x3e;
+pub const LSTORE_0: u8 = 0x3f;
+pub const LSTORE_1: u8 = 0x40;
+pub const LSTORE_2: u8 = 0x41;
+pub const LSTORE_3: u8 = 0x42;
+pub const FSTORE_0: u8 = 0x43;
+pub const FSTORE_1: u8 = 0x44;
+pub const FSTORE_2: u8 = 0x45;
+pub const FSTORE_3: u8 = 0x46;
+pub const DSTORE_0: u8 = 0x47;
+pub const DSTORE_1: u8 = 0x48;
+pub const DSTORE_2: u8 = 0x49;
+pub const DSTORE_3: u8 = 0x4a;
+pub const ASTORE_0: u8 = 0x4b;
+pub const ASTORE_1: u8 = 0x4c;
+pub const ASTORE_2: u8 = 0x4d;
+pub const ASTORE_3: u8 = 0x4e;
+pub const IASTORE: u8 = 0x4f;
+pub const LASTORE: u8 = 0x50;
+pub const FASTORE: u8 = 0x51;
+pub const DASTORE: u8 = 0x52;
+pub const AASTORE: u8 = 0x53;
+pub const BASTORE: u8 = 0x54;
+pub const CASTORE: u8 = 0x55;
+pub const SASTORE: u8 = 0x56;
+pub const POP: u8 = 0x57;
+pub const POP2: u8 = 0x58;
+pub const DUP: u8 = 0x59;
+pub const DUP_X1: u8 = 0x5a;
+pub const DUP_X2: u8 = 0x5b;
+pub const DUP2: u8 = 0x5c;
+pub const DUP2_X1: u8 = 0x5d;
+pub const DUP2_X2: u8 = 0x5e;
+pub const SWAP: u8 = 0x5f;
+pub const IADD: u8 = 0x60;
+pub const LADD: u8 = 0x61;
+pub const FADD: u8 = 0x62;
+pub const DADD: u8 = 0x63;
+pub const ISUB: u8 = 0x64;
+pub const LSUB: u8 = 0x65;
+pub const FSUB: u8 = 0x66;
+pub const DSUB: u8 = 0x67;
+pub const IMUL: u8 = 0x68;
+pub const LMUL: u8 = 0x69;
+pub const FMUL: u8 = 0x6a;
+pub const DMUL: u8 = 0x6b;
+pub const IDIV: u8 = 0x6c;
+pub const LDIV: u8 = 0x6d;
+pub const FDIV: u8 = 0x6e;
+pub const DDIV: u8 = 0x6f;
+pub const IREM: u8 = 0x70;
+pub const LREM: u8 = 0x71;
+pub const FREM: u8 = 0x72;
+pub const DREM: u8 = 0x73;
+pub const INEG: u8 = 0x74;
+pub const LNEG: u8 = 0x75;
+pub const FNEG: u8 = 0x76;
+pub const DNEG: u8 = 0x77;
+pub const ISHL: u8 = 0x78;
+pub const LSHL: u8 = 0x79;
+pub const ISHR: u8 = 0x7a;
+pub const LSHR: u8 = 0x7b;
+pub const IUSHR: u8 = 0x7c;
+pub const LUSHR: u8 = 0x7d;
+pub const IAND: u8 = 0x7e;
+pub const LAND: u8 = 0x7f;
+pub const IOR: u8 = 0x80;
+pub const LOR: u8 = 0x81;
+pub const IXOR: u8 = 0x82;
+pub const LXOR: u8 = 0x83;
+pub const IINC: u8 = 0x84;
+pub const I2L: u8 = 0x85;
+pub const I2F: u8 = 0x86;
+pub const I2D: u8 = 0x87;
+pub const L2I: u8 = 0x88;
+pub const L2F: u8 = 0x89;
+pub const L2D: u8 = 0x8a;
+pub const F2I: u8 = 0x8b;
+pub const F2L: u8 = 0x8c;
+pub const F2D: u8 = 0x8d;
+pub const D2I: u8 = 0x8e;
+pub const D2L: u8 = 0x8f;
+pub const D2F: u8 = 0x90;
+pub const I2B: u8 = 0x91;
+pub const I2C: u8 = 0x92;
+pub const I2S: u8 = 0x93;
+pub const LCMP: u8 = 0x94;
+pub const FCMPL: u8 = 0x95;
+pub const FCMPG: u8 = 0x96;
+pub const DCMPL: u8 = 0x97;
+pub const DCMPG: u8 = 0x98;
+pub const IFEQ: u8 = 0x99;
+pub const IFNE: u8 = 0x9a;
+pub const IFLT: u8 = 0x9b;
+pub const IFGE: u8 = 0x9c;
+pub const IFGT: u8 = 0x9d;
+pub const IFLE: u8 = 0x9e;
+pub const IF_ICMPEQ: u8 = 0x9f;
+pub const IF_ICMPNE: u8 = 0xa0;
+pub const IF_ICMPLT: u8 = 0xa1;
+pub const IF_ICMPGE: u8 = 0xa2;
+pub const IF_ICMPGT: u8 = 0xa3;
+pub const IF_ICMPLE: u8 = 0xa4;
+pub const IF_ACMPEQ: u8 = 0xa5;
+pub const IF_ACMPNE: u8 = 0xa6;
+pub const GOTO: u8 = 0xa7;
+pub const JSR: u8 = 0xa8;
+pub const RET: u8 = 0xa9;
+pub const TABLESWITCH: u8 = 0xaa;
+pub const LOOKUPSWITCH: u8 = 0xab;
+pub const IRETURN: u8 = 0xac;
+pub const LRETURN: u8 = 0xad;
+pub const FRETURN: u8 = 0xae;
+pub const DRETURN: u8 = 0xaf;
+pub const ARETURN: u8 = 0xb0;
+pub const RETURN: u8 = 0xb1;
+pub const GETSTATIC: u8 = 0xb2;
+pub const PUTSTATIC: u8 = 0xb3;
+pub const GETFIELD: u8 = 0xb4;
+pub const PUTFIELD: u8 = 0xb5;
+pub const INVOKEVIRTUAL: u8 = 0xb6;
+pub const INVOKESPECIAL: u8 = 0xb7;
+pub const INVOKESTATIC: u8 = 0xb8;
+pub const INVOKEINTERFACE: u8 = 0xb9;
+pub const INVOKEDYNAMIC: u8 = 0xba;
+pub const NEW: u8 = 0xbb;
+pub const NEWARRAY: u8 = 0xbc;
+pub const ANEWARRAY: u8 = 0xbd;
+pub const ARRAYLENGTH: u8 = 0xbe;
+pub const ATHROW: u8 = 0xbf;
+pub const CHECKCAST: u8 = 0xc0;
+pub const INSTANCEOF: u8 = 0xc1;
+pub const MONITORENTER: u8 = 0xc2;
+pub const MONITOREXIT: u8 = 0xc3;
+pub const WIDE: u8 = 0xc4;
+pub const MULTIANEWARRAY: u8 = 0xc5;
+pub const IFNULL: u8 = 0xc6;
+pub const IFNONNULL: u8 = 0xc7;
+pub const GOTO_W: u8 = 0xc8;
+pub const JSR_W: u8 = 0xc9;
+
+/// Every Java 8 opcode, indexed by its value
+pub const OPCODES: [Opcode; 202] = [
+    Opcode::new(NOP, "nop", OperandKind::None),
+    Opcode::new(ACONST_NULL, "aconst_null", OperandKind::None),
+    Opcode::new(ICONST_M1, "iconst_m1", OperandKind::None),
+    Opcode::new(ICONST_0, "iconst_0", OperandKind::None),
+    Opcode::new(ICONST_1, "iconst_1", OperandKind::None),
+    Opcode::new(ICONST_2, "iconst_2", OperandKind::None),
+    Opcode::new(ICONST_3, "iconst_3", OperandKind::None),
+    Opcode::new(ICONST_4, "iconst_4", OperandKind::None),
+    Opcode::new(ICONST_5, "iconst_5", OperandKind::None),
+    Opcode::new(LCONST_0, "lconst_0", OperandKind::None),
+    Opcode::new(LCONST_1, "lconst_1", OperandKind::None),
+    Opcode::new(FCONST_0, "fconst_0", OperandKind::None),
+    Opcode::new(FCONST_1, "fconst_1", OperandKind::None),
+    Opcode::new(FCONST_2, "fconst_2", OperandKind::None),
+    Opcode::new(DCONST_0, "dconst_0", OperandKind::None),
+    Opcode::new(DCONST_1, "dconst_1", OperandKind::None),
+    Opcode::new(BIPUSH, "bipush", OperandKind::Byte),
+    Opcode::new(SIPUSH, "sipush", OperandKind::Short),
+    Opcode::new(LDC, "ldc", OperandKind::ConstantIndex8),
+    Opcode::new(LDC_W, "ldc_w", OperandKind::ConstantIndex16),
+    Opcode::new(LDC2_W, "ldc2_w", OperandKind::ConstantIndex16),
+    Opcode::new(ILOAD, "iload", OperandKind::LocalIndex),
+    Opcode::new(LLOAD, "lload", OperandKind::LocalIndex),
+    Opcode::new(FLOAD, "fload", OperandKind::LocalIndex),
+    Opcode::new(DLOAD, "dload", OperandKind::LocalIndex),
+    Opcode::new(ALOAD, "aload", OperandKind::LocalIndex),
+    Opcode::new(ILOAD_0, "iload_0", OperandKind::None),
+    Opcode::new(ILOAD_1, "iload_1", OperandKind::None),
+    Opcode::new(ILOAD_2, "iload_2", OperandKind::None),
+    Opcode::new(ILOAD_3, "iload_3", OperandKind::None),
+    Opcode::new(LLOAD_0, "lload_0", OperandKind::None),
+    Opcode::new(LLOAD_1, "lload_1", OperandKind::None),
+    Opcode::new(LLOAD_2, "lload_2", OperandKind::None),
+    Opcode::new(LLOAD_3, "lload_3", OperandKind::None),
+    Opcode::new(FLOAD_0, "fload_0", OperandKind::None),
+    Opcode::new(FLOAD_1, "fload_1", OperandKind::None),
+    Opcode::new(FLOAD_2, "fload_2", OperandKind::None),
+    Opcode::new(FLOAD_3, "fload_3", OperandKind::None),
+    Opcode::new(DLOAD_0, "dload_0", OperandKind::None),
+    Opcode::new(DLOAD_1, "dload_1", OperandKind::None),
+    Opcode::new(DLOAD_2, "dload_2", OperandKind::None),
+    Opcode::new(DLOAD_3, "dload_3", OperandKind::None),
+    Opcode::new(ALOAD_0, "aload_0", OperandKind::None),
+    Opcode::new(ALOAD_1, "aload_1", OperandKind::None),
+    Opcode::new(ALOAD_2, "aload_2", OperandKind::None),
+    Opcode::new(ALOAD_3, "aload_3", OperandKind::None),
+    Opcode::new(IALOAD, "iaload", OperandKind::None),
+    Opcode::new(LALOAD, "laload", OperandKind::None),
+    Opcode::new(FALOAD, "faload", OperandKind::None),
+    Opcode::new(DALOAD, "daload", OperandKind::None),
+    Opcode::new(AALOAD, "aaload", OperandKind::None),
+    Opcode::new(BALOAD, "baload", OperandKind::None),
+    Opcode::new(CALOAD, "caload", OperandKind::None),
+    Opcode::new(SALOAD, "saload", OperandKind::None),
+    Opcode::new(ISTORE, "istore", OperandKind::LocalIndex),
+    Opcode::new(LSTORE, "lstore", OperandKind::LocalIndex),
+    Opcode::new(FSTORE, "fstore", OperandKind::LocalIndex),
+    Opcode::new(DSTORE, "dstore", OperandKind::LocalIndex),
+    Opcode::new(ASTORE, "astore", OperandKind::LocalIndex),
+    Opcode::new(ISTORE_0, "istore_0", OperandKind::None),
+    Opcode::new(ISTORE_1, "istore_1", OperandKind::None),
+    Opcode::new(ISTORE_2, "istore_2", OperandKind::None),
+    Opcode::new(ISTORE_3, "istore_3", OperandKind::None),
+    Opcode::new(LSTORE_0, "lstore_0", OperandKind::None),
+    Opcode::new(LSTORE_1, "lstore_1", OperandKind::None),
+    Opcode::new(LSTORE_2, "lstore_2", OperandKind::None),
+    Opcode::new(LSTORE_3, "lstore_3", OperandKind::None),
+    Opcode::new(FSTORE_0, "fstore_0", OperandKind::None),
+    Opcode::new(FSTORE_1, "fstore_1", OperandKind::None),
+    Opcode::new(FSTORE_2, "fstore_2", OperandKind::None),
+    Opcode::new(FSTORE_3, "fstore_3", OperandKind::None),
+    Opcode::new(DSTORE_0, "dstore_0", OperandKind::None),
+    Opcode::new(DSTORE_1, "dstore_1", OperandKind::None),
+    Opcode::new(DSTORE_2, "dstore_2", OperandKind::None),
+    Opcode::new(DSTORE_3, "dstore_3", OperandKind::None),
+    Opcode::new(ASTORE_0, "astore_0", OperandKind::None),
+    Opcode::new(ASTORE_1, "astore_1", OperandKind::None),
+    Opcode::new(ASTORE_2, "astore_2", OperandKind::None),
+    Opcode::new(ASTORE_3, "astore_3", OperandKind::None),
+    Opcode::new(IASTORE, "iastore", OperandKind::None),
+    Opcode::new(LASTORE, "lastore", OperandKind::None),
+    Opcode::new(FASTORE, "fastore", OperandKind::None),
+    Opcode::new(DASTORE, "dastore", OperandKind::None),
+    Opcode::new(AASTORE, "aastore", OperandKind::None),
+    Opcode::new(BASTORE, "bastore", OperandKind::None),
+    Opcode::new(CASTORE, "castore", OperandKind::None),
+    Opcode::new(SASTORE, "sastore", OperandKind::None),
+    Opcode::new(POP, "pop", OperandKind::None),
+    Opcode::new(POP2, "pop2", OperandKind::None),
+    Opcode::new(DUP, "dup", OperandKind::None),
+    Opcode::new(DUP_X1, "dup_x1", OperandKind::None),
+    Opcode::new(DUP_X2, "dup_x2", OperandKind::None),
+    Opcode::new(DUP2, "dup2", OperandKind::None),
+    Opcode::new(DUP2_X1, "dup2_x1", OperandKind::None),
+    Opcode::new(DUP2_X2, "dup2_x2", OperandKind::None),
+    Opcode::new(SWAP, "swap", OperandKind::None),
+    Opcode::new(IADD, "iadd", OperandKind::None),
+    Opcode::new(LADD, "ladd", OperandKind::None),
+    Opcode::new(FADD, "fadd", OperandKind::None),
+    Opcode::new(DADD, "dadd", OperandKind::None),
+    Opcode::new(ISUB, "isub", OperandKind::None),
+    Opcode::new(LSUB, "lsub", OperandKind::None),
+    Opcode::new(FSUB, "fsub", OperandKind::None),
+    Opcode::new(DSUB, "dsub", OperandKind::None),
+    Opcode::new(IMUL, "imul", OperandKind::None),
+    Opcode::new(LMUL, "lmul", OperandKind::None),
+    Opcode::new(FMUL, "fmul", OperandKind::None),
+    Opcode::new(DMUL, "dmul", OperandKind::None),
+    Opcode::new(IDIV, "idiv", OperandKind::None),
+    Opcode::new(LDIV, "ldiv", OperandKind::None),
+    Opcode::new(FDIV, "fdiv", OperandKind::None),
+    Opcode::new(DDIV, "ddiv", OperandKind::None),
+    Opcode::new(IREM, "irem", OperandKind::None),
+    Opcode::new(LREM, "lrem", OperandKind::None),
+    Opcode::new(FREM, "frem", OperandKind::None),
+    Opcode::new(DREM, "drem", OperandKind::None),
+    Opcode::new(INEG, "ineg", OperandKind::None),
+    Opcode::new(LNEG, "lneg", OperandKind::None),
+    Opcode::new(FNEG, "fneg", OperandKind::None),
+    Opcode::new(DNEG, "dneg", OperandKind::None),
+    Opcode::new(ISHL, "ishl", OperandKind::None),
+    Opcode::new(LSHL, "lshl", OperandKind::None),
+    Opcode::new(ISHR, "ishr", OperandKind::None),
+    Opcode::new(LSHR, "lshr", OperandKind::None),
+    Opcode::new(IUSHR, "iushr", OperandKind::None),
+    Opcode::new(LUSHR, "lushr", OperandKind::None),
+    Opcode::new(IAND, "iand", OperandKind::None),
+    Opcode::new(LAND, "land", OperandKind::None),
+    Opcode::new(IOR, "ior", OperandKind::None),
+    Opcode::new(LOR, "lor", OperandKind::None),
+    Opcode::new(IXOR, "ixor", OperandKind::None),
+    Opcode::new(LXOR, "lxor", OperandKind::None),
+    Opcode::new(IINC, "iinc", OperandKind::Iinc),
+    Opcode::new(I2L, "i2l", OperandKind::None),
+    Opcode::new(I2F, "i2f", OperandKind::None),
+    Opcode::new(I2D, "i2d", OperandKind::None),
+    Opcode::new(L2I, "l2i", OperandKind::None),
+    Opcode::new(L2F, "l2f", OperandKind::None),
+    Opcode::new(L2D, "l2d", OperandKind::None),
+    Opcode::new(F2I, "f2i", OperandKind::None),
+    Opcode::new(F2L, "f2l", OperandKind::None),
+    Opcode::new(F2D, "f2d", OperandKind::None),
+    Opcode::new(D2I, "d2i", OperandKind::None),
+    Opcode::new(D2L, "d2l", OperandKind::None),
+    Opcode::new(D2F, "d2f", OperandKind::None),
+    Opcode::new(I2B, "i2b", OperandKind::None),
+    Opcode::new(I2C, "i2c", OperandKind::None),
+    Opcode::new(I2S, "i2s", OperandKind::None),
+    Opcode::new(LCMP, "lcmp", OperandKind::None),
+    Opcode::new(FCMPL, "fcmpl", OperandKind::None),
+    Opcode::new(FCMPG, "fcmpg", OperandKind::None),
+    Opcode::new(DCMPL, "dcmpl", OperandKind::None),
+    Opcode::new(DCMPG, "dcmpg", OperandKind::None),
+    Opcode::new(IFEQ, "ifeq", OperandKind::Branch16),
+    Opcode::new(IFNE, "ifne", OperandKind::Branch16),
+    Opcode::new(IFLT, "iflt", OperandKind::Branch16),
+    Opcode::new(IFGE, "ifge", OperandKind::Branch16),
+    Opcode::new(IFGT, "ifgt", OperandKind::Branch16),
+    Opcode::new(IFLE, "ifle", OperandKind::Branch16),
+    Opcode::new(IF_ICMPEQ, "if_icmpeq", OperandKind::Branch16),
+    Opcode::new(IF_ICMPNE, "if_icmpne", OperandKind::Branch16),
+    Opcode::new(IF_ICMPLT, "if_icmplt", OperandKind::Branch16),
+    Opcode::new(IF_ICMPGE, "if_icmpge", OperandKind::Branch16),
+    Opcode::new(IF_ICMPGT, "if_icmpgt", OperandKind::Branch16),
+    Opcode::new(IF_ICMPLE, "if_icmple", OperandKind::Branch16),
+    Opcode::new(IF_ACMPEQ, "if_acmpeq", OperandKind::Branch16),
+    Opcode::new(IF_ACMPNE, "if_acmpne", OperandKind::Branch16),
+    Opcode::new(GOTO, "goto", OperandKind::Branch16),
+    Opcode::new(JSR, "jsr", OperandKind::Branch16),
+    Opcode::new(RET, "ret", OperandKind::LocalIndex),
+    Opcode::new(TABLESWITCH, "tableswitch", OperandKind::TableSwitch),
+    Opcode::new(LOOKUPSWITCH, "lookupswitch", OperandKind::LookupSwitch),
+    Opcode::new(IRETURN, "ireturn", OperandKind::None),
+    Opcode::new(LRETURN, "lreturn", OperandKind::None),
+    Opcode::new(FRETURN, "freturn", OperandKind::None),
+    Opcode::new(DRETURN, "dreturn", OperandKind::None),
+    Opcode::new(ARETURN, "areturn", OperandKind::None),
+    Opcode::new(RETURN, "return", OperandKind::None),
+    Opcode::new(GETSTATIC, "getstatic", OperandKind::ConstantIndex16),
+    Opcode::new(PUTSTATIC, "putstatic", OperandKind::ConstantIndex16),
+    Opcode::new(GETFIELD, "getfield", OperandKind::ConstantIndex16),
+    Opcode::new(PUTFIELD, "putfield", OperandKind::ConstantIndex16),
+    Opcode::new(INVOKEVIRTUAL, "invokevirtual", OperandKind::ConstantIndex16),
+    Opcode::new(INVOKESPECIAL, "invokespecial", OperandKind::ConstantIndex16),
+    Opcode::new(INVOKESTATIC, "invokestatic", OperandKind::ConstantIndex16),
+    Opcode::new(
+        INVOKEINTERFACE,
+        "invokeinterface",
+        OperandKind::InvokeInterface,
+    ),
+    Opcode::new(INVOKEDYNAMIC, "invokedynamic", OperandKind::InvokeDynamic),
+    Opcode::new(NEW, "new", OperandKind::ConstantIndex16),
+    Opcode::new(NEWARRAY, "newarray", OperandKind::NewArray),
+    Opcode::new(ANEWARRAY, "anewarray", OperandKind::ConstantIndex16),
+    Opcode::new(ARRAYLENGTH, "arraylength", OperandKind::None),
+    Opcode::new(ATHROW, "athrow", OperandKind::None),
+    Opcode::new(CHECKCAST, "checkcast", OperandKind::ConstantIndex16),
+    Opcode::new(INSTANCEOF, "instanceof", OperandKind::ConstantIndex16),
+    Opcode::new(MONITORENTER, "monitorenter", OperandKind::None),
+    Opcode::new(MONITOREXIT, "monitorexit", OperandKind::None),
+    Opcode::new(WIDE, "wide", OperandKind::Wide),
+    Opcode::new(
+        MULTIANEWARRAY,
+        "multianewarray",
+        OperandKind::MultiANewArray,
+    ),
+    Opcode::new(IFNULL, "ifnull", OperandKind::Branch16),
+    Opcode::new(IFNONNULL, "ifnonnull", OperandKind::Branch16),
+    Opcode::new(GOTO_W, "goto_w", OperandKind::Branch32),
+    Opcode::new(JSR_W, "jsr_w", OperandKind::Branch32),
+];
+
+/// Type codes used by `newarray` (section 6.5 newarray)
+pub const ARRAY_TYPES: [(u8, &str); 8] = [
+    (4, "boolean"),
+    (5, "char"),
+    (6, "float"),
+    (7, "double"),
+    (8, "byte"),
+    (9, "short"),
+    (10, "int"),
+    (11, "long"),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn table_is_indexed_by_code() {
+        for (i, opcode) in OPCODES.iter().enumerate() {
+            assert_eq!(opcode.code() as usize, i);
+        }
+        assert_eq!(Opcode::from_code(GOTO_W).unwrap().mnemonic(), "goto_w");
+        assert!(Opcode::from_code(0xca).is_none());
+    }
+
+    #[test]
+    fn lookup_by_mnemonic() {
+        let opcode = Opcode::from_mnemonic("invokeinterface").unwrap();
+        assert_eq!(opcode.code(), INVOKEINTERFACE);
+        assert_eq!(opcode.operands(), OperandKind::InvokeInterface);
+        assert!(Opcode::from_mnemonic("breakpoint").is_none());
+    }
+}
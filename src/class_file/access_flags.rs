@@ -0,0 +1,146 @@
+/// Access and property flags (sections 4.1, 4.5, 4.6 and 4.7.6).
+/// Some bits mean different things depending on whether they belong to a class,
+/// field or method (e.g. 0x0040 is ACC_VOLATILE on a field but ACC_BRIDGE on a method).
+pub const ACC_PUBLIC: u16 = 0x0001;
+pub const ACC_PRIVATE: u16 = 0x0002;
+pub const ACC_PROTECTED: u16 = 0x0004;
+pub const ACC_STATIC: u16 = 0x0008;
+pub const ACC_FINAL: u16 = 0x0010;
+pub const ACC_SUPER: u16 = 0x0020;
+pub const ACC_SYNCHRONIZED: u16 = 0x0020;
+pub const ACC_VOLATILE: u16 = 0x0040;
+pub const ACC_BRIDGE: u16 = 0x0040;
+pub const ACC_TRANSIENT: u16 = 0x0080;
+pub const ACC_VARARGS: u16 = 0x0080;
+pub const ACC_NATIVE: u16 = 0x0100;
+pub const ACC_INTERFACE: u16 = 0x0200;
+pub const ACC_ABSTRACT: u16 = 0x0400;
+pub const ACC_STRICT: u16 = 0x0800;
+pub const ACC_SYNTHETIC: u16 = 0x1000;
+pub const ACC_ANNOTATION: u16 = 0x2000;
+pub const ACC_ENUM: u16 = 0x4000;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FlagTarget {
+    Class,
+    Field,
+    Method,
+    /// `inner_class_access_flags` of an InnerClasses entry
+    InnerClass,
+}
+
+/// (mask, keyword as written in source, name used by the specification)
+type FlagName = (u16, &'static str, &'static str);
+
+const CLASS_FLAGS: [FlagName; 8] = [
+    (ACC_PUBLIC, "public", "ACC_PUBLIC"),
+    (ACC_FINAL, "final", "ACC_FINAL"),
+    (ACC_SUPER, "super", "ACC_SUPER"),
+    (ACC_INTERFACE, "interface", "ACC_INTERFACE"),
+    (ACC_ABSTRACT, "abstract", "ACC_ABSTRACT"),
+    (ACC_SYNTHETIC, "synthetic", "ACC_SYNTHETIC"),
+    (ACC_ANNOTATION, "annotation", "ACC_ANNOTATION"),
+    (ACC_ENUM, "enum", "ACC_ENUM"),
+];
+
+const FIELD_FLAGS: [FlagName; 9] = [
+    (ACC_PUBLIC, "public", "ACC_PUBLIC"),
+    (ACC_PRIVATE, "private", "ACC_PRIVATE"),
+    (ACC_PROTECTED, "protected", "ACC_PROTECTED"),
+    (ACC_STATIC, "static", "ACC_STATIC"),
+    (ACC_FINAL, "final", "ACC_FINAL"),
+    (ACC_VOLATILE, "volatile", "ACC_VOLATILE"),
+    (ACC_TRANSIENT, "transient", "ACC_TRANSIENT"),
+    (ACC_SYNTHETIC, "synthetic", "ACC_SYNTHETIC"),
+    (ACC_ENUM, "enum", "ACC_ENUM"),
+];
+
+const METHOD_FLAGS: [FlagName; 12] = [
+    (ACC_PUBLIC, "public", "ACC_PUBLIC"),
+    (ACC_PRIVATE, "private", "ACC_PRIVATE"),
+    (ACC_PROTECTED, "protected", "ACC_PROTECTED"),
+    (ACC_STATIC, "static", "ACC_STATIC"),
+    (ACC_FINAL, "final", "ACC_FINAL"),
+    (ACC_SYNCHRONIZED, "synchronized", "ACC_SYNCHRONIZED"),
+    (ACC_BRIDGE, "bridge", "ACC_BRIDGE"),
+    (ACC_VARARGS, "varargs", "ACC_VARARGS"),
+    (ACC_NATIVE, "native", "ACC_NATIVE"),
+    (ACC_ABSTRACT, "abstract", "ACC_ABSTRACT"),
+    (ACC_STRICT, "strict", "ACC_STRICT"),
+    (ACC_SYNTHETIC, "synthetic", "ACC_SYNTHETIC"),
+];
+
+const INNER_CLASS_FLAGS: [FlagName; 10] = [
+    (ACC_PUBLIC, "public", "ACC_PUBLIC"),
+    (ACC_PRIVATE, "private", "ACC_PRIVATE"),
+    (ACC_PROTECTED, "protected", "ACC_PROTECTED"),
+    (ACC_STATIC, "static", "ACC_STATIC"),
+    (ACC_FINAL, "final", "ACC_FINAL"),
+    (ACC_INTERFACE, "interface", "ACC_INTERFACE"),
+    (ACC_ABSTRACT, "abstract", "ACC_ABSTRACT"),
+    (ACC_SYNTHETIC, "synthetic", "ACC_SYNTHETIC"),
+    (ACC_ANNOTATION, "annotation", "ACC_ANNOTATION"),
+    (ACC_ENUM, "enum", "ACC_ENUM"),
+];
+
+fn flag_names(target: FlagTarget) -> &'static [FlagName] {
+    match target {
+        FlagTarget::Class => &CLASS_FLAGS,
+        FlagTarget::Field => &FIELD_FLAGS,
+        FlagTarget::Method => &METHOD_FLAGS,
+        FlagTarget::InnerClass => &INNER_CLASS_FLAGS,
+    }
+}
+
+/// Looks up the flag for a keyword such as `public` or `synchronized`
+pub fn flag_from_keyword(target: FlagTarget, keyword: &str) -> Option<u16> {
+    flag_names(target)
+        .iter()
+        .find(|(_, name, _)| *name == keyword)
+        .map(|(mask, _, _)| *mask)
+}
+
+/// Keywords for every set flag, in declaration order (e.g. `["public", "static"]`)
+pub fn keywords(target: FlagTarget, flags: u16) -> Vec<&'static str> {
+    flag_names(target)
+        .iter()
+        .filter(|(mask, _, _)| flags & mask != 0)
+        .map(|(_, keyword, _)| *keyword)
+        .collect()
+}
+
+/// Specification names for every set flag (e.g. `["ACC_PUBLIC", "ACC_STATIC"]`)
+pub fn spec_names(target: FlagTarget, flags: u16) -> Vec<&'static str> {
+    flag_names(target)
+        .iter()
+        .filter(|(mask, _, _)| flags & mask != 0)
+        .map(|(_, _, name)| *name)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shared_bits_depend_on_target() {
+        assert_eq!(keywords(FlagTarget::Class, 0x0021), vec!["public", "super"]);
+        assert_eq!(
+            keywords(FlagTarget::Method, 0x0021),
+            vec!["public", "synchronized"]
+        );
+        assert_eq!(
+            spec_names(FlagTarget::Field, 0x0048),
+            vec!["ACC_STATIC", "ACC_VOLATILE"]
+        );
+    }
+
+    #[test]
+    fn keyword_lookup() {
+        assert_eq!(
+            flag_from_keyword(FlagTarget::Method, "varargs"),
+            Some(ACC_VARARGS)
+        );
+        assert_eq!(flag_from_keyword(FlagTarget::Field, "varargs"), None);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::util::file::{read_u16, read_u32};
+use std::io;
+use std::io::{BufReader, Read};
+
+/// An attribute as stored in the class file: its name and the raw `info` bytes (section 4.7).
+/// Typed views such as `Code` are parsed from `info` on demand so that attributes we don't
+/// understand are kept byte-for-byte when a class is written back out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeInfo {
+    attribute_name_index: u16,
+    info: Vec<u8>,
+}
+
+impl AttributeInfo {
+    pub fn new(attribute_name_index: u16, info: Vec<u8>) -> AttributeInfo {
+        AttributeInfo {
+            attribute_name_index,
+            info,
+        }
+    }
+
+    pub fn attribute_name_index(&self) -> u16 {
+        self.attribute_name_index
+    }
+
+    pub fn info(&self) -> &[u8] {
+        &self.info
+    }
+
+    pub fn name(&self, constant_pool: &ConstantPool) -> Result<String, io::Error> {
+        constant_pool.utf8(self.attribute_name_index)
+    }
+
+    /// A reader over the `info` bytes for parsing typed attributes
+    pub fn reader(&self) -> BufReader<&[u8]> {
+        BufReader::new(self.info.as_slice())
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<AttributeInfo, io::Error> {
+        let attribute_name_index = read_u16(reader)?;
+        let attribute_length = read_u32(reader)?;
+
+        // attribute_length is untrusted, so let the buffer grow with the bytes that are actually
+        // there rather than allocating up to 4 GiB before finding out the input is shorter
+        let mut info = Vec::new();
+        reader
+            .take(attribute_length as u64)
+            .read_to_end(&mut info)?;
+        if info.len() != attribute_length as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "attribute_length {attribute_length} is longer than the {} bytes left",
+                    info.len()
+                ),
+            ));
+        }
+
+        Ok(AttributeInfo {
+            attribute_name_index,
+            info,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.attribute_name_index.to_be_bytes());
+        out.extend_from_slice(&(self.info.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.info);
+    }
+}
+
+/// Reads a `u2 attributes_count` followed by that many attributes
+pub fn read_attributes(reader: &mut BufReader<impl Read>) -> Result<Vec<AttributeInfo>, io::Error> {
+    let attributes_count = read_u16(reader)?;
+    let mut attributes = Vec::with_capacity(attributes_count as usize);
+    for _ in 0..attributes_count {
+        attributes.push(AttributeInfo::from(reader)?);
+    }
+    Ok(attributes)
+}
+
+pub fn write_attributes(attributes: &[AttributeInfo], out: &mut Vec<u8>) {
+    out.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+    for attribute in attributes.iter() {
+        attribute.write(out);
+    }
+}
+
+/// Finds the first attribute with the given name; attributes whose name can't be resolved are skipped
+pub fn find_attribute<'a>(
+    attributes: &'a [AttributeInfo],
+    constant_pool: &ConstantPool,
+    name: &str,
+) -> Option<&'a AttributeInfo> {
+    attributes
+        .iter()
+        .find(|attribute| matches!(attribute.name(constant_pool), Ok(n) if n == name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_and_write_round_trip() {
+        // name_index = 3, length = 2, info = [0xAB, 0xCD]
+        let bytes = [0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0xAB, 0xCD];
+        let mut reader = BufReader::new(bytes.as_ref());
+        let attribute = AttributeInfo::from(&mut reader).unwrap();
+        assert_eq!(attribute.attribute_name_index(), 3);
+        assert_eq!(attribute.info(), &[0xAB, 0xCD]);
+
+        let mut out = Vec::new();
+        attribute.write(&mut out);
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn truncated_info_fails() {
+        let bytes = [0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0xAB, 0xCD];
+        let mut reader = BufReader::new(bytes.as_ref());
+        assert!(AttributeInfo::from(&mut reader).is_err());
+    }
+
+    #[test]
+    fn length_past_the_input_fails_without_allocating_it() {
+        let bytes = [0x00, 0x03, 0xFF, 0xFF, 0xFF, 0xFF, 0xAB];
+        let mut reader = BufReader::new(bytes.as_ref());
+        assert!(AttributeInfo::from(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::util::file::{read_u16, read_u16_list};
+use std::io;
+use std::io::{BufReader, Read};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BootstrapMethod {
+    bootstrap_method_ref: u16,
+    bootstrap_arguments: Vec<u16>,
+}
+
+impl BootstrapMethod {
+    pub fn new(bootstrap_method_ref: u16, bootstrap_arguments: Vec<u16>) -> BootstrapMethod {
+        BootstrapMethod {
+            bootstrap_method_ref,
+            bootstrap_arguments,
+        }
+    }
+
+    /// Index of the MethodHandle constant for the bootstrap method
+    pub fn bootstrap_method_ref(&self) -> u16 {
+        self.bootstrap_method_ref
+    }
+
+    pub fn bootstrap_arguments(&self) -> &[u16] {
+        &self.bootstrap_arguments
+    }
+}
+
+/// The BootstrapMethods attribute of a class, referenced by InvokeDynamic constants (section 4.7.23)
+#[derive(Clone, Debug, PartialEq)]
+pub struct BootstrapMethods {
+    bootstrap_methods: Vec<BootstrapMethod>,
+}
+
+impl BootstrapMethods {
+    pub const NAME: &'static str = "BootstrapMethods";
+
+    pub fn new(bootstrap_methods: Vec<BootstrapMethod>) -> BootstrapMethods {
+        BootstrapMethods { bootstrap_methods }
+    }
+
+    pub fn bootstrap_methods(&self) -> &[BootstrapMethod] {
+        &self.bootstrap_methods
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<BootstrapMethods, io::Error> {
+        let num_bootstrap_methods = read_u16(reader)?;
+        let mut bootstrap_methods = Vec::with_capacity(num_bootstrap_methods as usize);
+        for _ in 0..num_bootstrap_methods {
+            bootstrap_methods.push(BootstrapMethod {
+                bootstrap_method_ref: read_u16(reader)?,
+                bootstrap_arguments: read_u16_list(reader)?,
+            });
+        }
+        Ok(BootstrapMethods { bootstrap_methods })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.bootstrap_methods.len() as u16).to_be_bytes());
+        for method in self.bootstrap_methods.iter() {
+            out.extend_from_slice(&method.bootstrap_method_ref.to_be_bytes());
+            out.extend_from_slice(&(method.bootstrap_arguments.len() as u16).to_be_bytes());
+            for argument in method.bootstrap_arguments.iter() {
+                out.extend_from_slice(&argument.to_be_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_and_write_round_trip() {
+        // one bootstrap method #20 with arguments #21, #22
+        let bytes = [0x00, 0x01, 0x00, 0x14, 0x00, 0x02, 0x00, 0x15, 0x00, 0x16];
+        let mut reader = BufReader::new(bytes.as_ref());
+        let attribute = BootstrapMethods::from(&mut reader).unwrap();
+        assert_eq!(attribute.bootstrap_methods().len(), 1);
+        assert_eq!(attribute.bootstrap_methods()[0].bootstrap_method_ref(), 20);
+        assert_eq!(
+            attribute.bootstrap_methods()[0].bootstrap_arguments(),
+            &[21, 22]
+        );
+
+        let mut out = Vec::new();
+        attribute.write(&mut out);
+        assert_eq!(out, bytes);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::bytecode::instruction::Instructions;
+use crate::class_file::attribute::attribute_info::{
+    read_attributes, write_attributes, AttributeInfo,
+};
+use crate::util::file::{read_bytes, read_u16, read_u32};
+use std::io;
+use std::io::{BufReader, Read};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExceptionTableEntry {
+    start_pc: u16,
+    end_pc: u16,
+    handler_pc: u16,
+    catch_type: u16,
+}
+
+impl ExceptionTableEntry {
+    /// `catch_type` 0 catches everything (used for `finally`)
+    pub fn new(
+        start_pc: u16,
+        end_pc: u16,
+        handler_pc: u16,
+        catch_type: u16,
+    ) -> ExceptionTableEntry {
+        ExceptionTableEntry {
+            start_pc,
+            end_pc,
+            handler_pc,
+            catch_type,
+        }
+    }
+
+    pub fn start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn end_pc(&self) -> u16 {
+        self.end_pc
+    }
+
+    pub fn handler_pc(&self) -> u16 {
+        self.handler_pc
+    }
+
+    pub fn catch_type(&self) -> u16 {
+        self.catch_type
+    }
+}
+
+/// The Code attribute of a method (section 4.7.3)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Code {
+    max_stack: u16,
+    max_locals: u16,
+    code: Vec<u8>,
+    exception_table: Vec<ExceptionTableEntry>,
+    attributes: Vec<AttributeInfo>,
+}
+
+impl Code {
+    pub const NAME: &'static str = "Code";
+
+    pub fn new(
+        max_stack: u16,
+        max_locals: u16,
+        code: Vec<u8>,
+        exception_table: Vec<ExceptionTableEntry>,
+        attributes: Vec<AttributeInfo>,
+    ) -> Code {
+        Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        }
+    }
+
+    pub fn max_stack(&self) -> u16 {
+        self.max_stack
+    }
+
+    pub fn max_locals(&self) -> u16 {
+        self.max_locals
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    /// Decodes the code array as `(pc, Instruction)` pairs
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(&self.code)
+    }
+
+    pub fn exception_table(&self) -> &[ExceptionTableEntry] {
+        &self.exception_table
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<Code, io::Error> {
+        let max_stack = read_u16(reader)?;
+        let max_locals = read_u16(reader)?;
+
+        // 4.7.3: code_length must be greater than zero and less than 65536
+        let code_length = read_u32(reader)? as usize;
+        if code_length == 0 || code_length > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("code_length must be between 1 and 65535 but was {code_length}"),
+            ));
+        }
+        let mut code = vec![0; code_length];
+        read_bytes(reader, &mut code, code_length)?;
+
+        let exception_table_length = read_u16(reader)?;
+        let mut exception_table = Vec::with_capacity(exception_table_length as usize);
+        for _ in 0..exception_table_length {
+            exception_table.push(ExceptionTableEntry {
+                start_pc: read_u16(reader)?,
+                end_pc: read_u16(reader)?,
+                handler_pc: read_u16(reader)?,
+                catch_type: read_u16(reader)?,
+            });
+        }
+
+        let attributes = read_attributes(reader)?;
+
+        Ok(Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.max_stack.to_be_bytes());
+        out.extend_from_slice(&self.max_locals.to_be_bytes());
+        out.extend_from_slice(&(self.code.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.code);
+        out.extend_from_slice(&(self.exception_table.len() as u16).to_be_bytes());
+        for entry in self.exception_table.iter() {
+            out.extend_from_slice(&entry.start_pc.to_be_bytes());
+            out.extend_from_slice(&entry.end_pc.to_be_bytes());
+            out.extend_from_slice(&entry.handler_pc.to_be_bytes());
+            out.extend_from_slice(&entry.catch_type.to_be_bytes());
+        }
+        write_attributes(&self.attributes, out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_and_write_round_trip() {
+        let bytes = [
+            0x00, 0x02, // max_stack
+            0x00, 0x01, // max_locals
+            0x00, 0x00, 0x00, 0x01, 0xB1, // code_length = 1, return
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, // one handler
+            0x00, 0x00, // no attributes
+        ];
+        let mut reader = BufReader::new(bytes.as_ref());
+        let code = Code::from(&mut reader).unwrap();
+        assert_eq!(code.max_stack(), 2);
+        assert_eq!(code.max_locals(), 1);
+        assert_eq!(code.code(), &[0xB1]);
+        assert_eq!(
+            code.exception_table(),
+            &[ExceptionTableEntry::new(0, 1, 1, 0)]
+        );
+
+        let mut out = Vec::new();
+        code.write(&mut out);
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn empty_code_fails() {
+        let bytes = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut reader = BufReader::new(bytes.as_ref());
+        assert!(Code::from(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use crate::util::file::read_u16;
+use std::io;
+use std::io::{BufReader, Read};
+
+/// The ConstantValue attribute of a field (section 4.7.2)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConstantValue {
+    constantvalue_index: u16,
+}
+
+impl ConstantValue {
+    pub const NAME: &'static str = "ConstantValue";
+
+    pub fn new(constantvalue_index: u16) -> ConstantValue {
+        ConstantValue {
+            constantvalue_index,
+        }
+    }
+
+    pub fn constantvalue_index(&self) -> u16 {
+        self.constantvalue_index
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<ConstantValue, io::Error> {
+        Ok(ConstantValue {
+            constantvalue_index: read_u16(reader)?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.constantvalue_index.to_be_bytes());
+    }
+}
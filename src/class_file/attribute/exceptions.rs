@@ -0,0 +1,36 @@
+use crate::util::file::read_u16_list;
+use std::io;
+use std::io::{BufReader, Read};
+
+/// The Exceptions attribute of a method, listing its checked exceptions (section 4.7.5)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exceptions {
+    exception_index_table: Vec<u16>,
+}
+
+impl Exceptions {
+    pub const NAME: &'static str = "Exceptions";
+
+    pub fn new(exception_index_table: Vec<u16>) -> Exceptions {
+        Exceptions {
+            exception_index_table,
+        }
+    }
+
+    pub fn exception_index_table(&self) -> &[u16] {
+        &self.exception_index_table
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<Exceptions, io::Error> {
+        Ok(Exceptions {
+            exception_index_table: read_u16_list(reader)?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.exception_index_table.len() as u16).to_be_bytes());
+        for index in self.exception_index_table.iter() {
+            out.extend_from_slice(&index.to_be_bytes());
+        }
+    }
+}
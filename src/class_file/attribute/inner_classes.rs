@@ -0,0 +1,87 @@
+use crate::util::file::read_u16;
+use std::io;
+use std::io::{BufReader, Read};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InnerClass {
+    inner_class_info_index: u16,
+    /// 0 for top-level, local and anonymous classes
+    outer_class_info_index: u16,
+    /// 0 for anonymous classes
+    inner_name_index: u16,
+    inner_class_access_flags: u16,
+}
+
+impl InnerClass {
+    pub fn new(
+        inner_class_info_index: u16,
+        outer_class_info_index: u16,
+        inner_name_index: u16,
+        inner_class_access_flags: u16,
+    ) -> InnerClass {
+        InnerClass {
+            inner_class_info_index,
+            outer_class_info_index,
+            inner_name_index,
+            inner_class_access_flags,
+        }
+    }
+
+    pub fn inner_class_info_index(&self) -> u16 {
+        self.inner_class_info_index
+    }
+
+    pub fn outer_class_info_index(&self) -> u16 {
+        self.outer_class_info_index
+    }
+
+    pub fn inner_name_index(&self) -> u16 {
+        self.inner_name_index
+    }
+
+    pub fn inner_class_access_flags(&self) -> u16 {
+        self.inner_class_access_flags
+    }
+}
+
+/// The InnerClasses attribute of a ClassFile (section 4.7.6)
+#[derive(Clone, Debug, PartialEq)]
+pub struct InnerClasses {
+    classes: Vec<InnerClass>,
+}
+
+impl InnerClasses {
+    pub const NAME: &'static str = "InnerClasses";
+
+    pub fn new(classes: Vec<InnerClass>) -> InnerClasses {
+        InnerClasses { classes }
+    }
+
+    pub fn classes(&self) -> &[InnerClass] {
+        &self.classes
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<InnerClasses, io::Error> {
+        let number_of_classes = read_u16(reader)?;
+        let mut classes = Vec::with_capacity(number_of_classes as usize);
+        for _ in 0..number_of_classes {
+            classes.push(InnerClass {
+                inner_class_info_index: read_u16(reader)?,
+                outer_class_info_index: read_u16(reader)?,
+                inner_name_index: read_u16(reader)?,
+                inner_class_access_flags: read_u16(reader)?,
+            });
+        }
+        Ok(InnerClasses { classes })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.classes.len() as u16).to_be_bytes());
+        for class in self.classes.iter() {
+            out.extend_from_slice(&class.inner_class_info_index.to_be_bytes());
+            out.extend_from_slice(&class.outer_class_info_index.to_be_bytes());
+            out.extend_from_slice(&class.inner_name_index.to_be_bytes());
+            out.extend_from_slice(&class.inner_class_access_flags.to_be_bytes());
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::util::file::read_u16;
+use std::io;
+use std::io::{BufReader, Read};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LineNumber {
+    start_pc: u16,
+    line_number: u16,
+}
+
+impl LineNumber {
+    pub fn new(start_pc: u16, line_number: u16) -> LineNumber {
+        LineNumber {
+            start_pc,
+            line_number,
+        }
+    }
+
+    pub fn start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn line_number(&self) -> u16 {
+        self.line_number
+    }
+}
+
+/// The LineNumberTable attribute of a Code attribute (section 4.7.12)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineNumberTable {
+    line_number_table: Vec<LineNumber>,
+}
+
+impl LineNumberTable {
+    pub const NAME: &'static str = "LineNumberTable";
+
+    pub fn new(line_number_table: Vec<LineNumber>) -> LineNumberTable {
+        LineNumberTable { line_number_table }
+    }
+
+    pub fn line_number_table(&self) -> &[LineNumber] {
+        &self.line_number_table
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<LineNumberTable, io::Error> {
+        let length = read_u16(reader)?;
+        let mut line_number_table = Vec::with_capacity(length as usize);
+        for _ in 0..length {
+            line_number_table.push(LineNumber {
+                start_pc: read_u16(reader)?,
+                line_number: read_u16(reader)?,
+            });
+        }
+        Ok(LineNumberTable { line_number_table })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.line_number_table.len() as u16).to_be_bytes());
+        for entry in self.line_number_table.iter() {
+            out.extend_from_slice(&entry.start_pc.to_be_bytes());
+            out.extend_from_slice(&entry.line_number.to_be_bytes());
+        }
+    }
+}
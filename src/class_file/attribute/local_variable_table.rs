@@ -0,0 +1,101 @@
+use crate::util::file::read_u16;
+use std::io;
+use std::io::{BufReader, Read};
+
+/// One entry of a LocalVariableTable or LocalVariableTypeTable. The two tables share a
+/// layout; in the latter `descriptor_index` points at a generic signature instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LocalVariable {
+    start_pc: u16,
+    length: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    index: u16,
+}
+
+impl LocalVariable {
+    pub fn new(
+        start_pc: u16,
+        length: u16,
+        name_index: u16,
+        descriptor_index: u16,
+        index: u16,
+    ) -> LocalVariable {
+        LocalVariable {
+            start_pc,
+            length,
+            name_index,
+            descriptor_index,
+            index,
+        }
+    }
+
+    pub fn start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+}
+
+/// The LocalVariableTable (section 4.7.13) and LocalVariableTypeTable (section 4.7.14) attributes
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalVariableTable {
+    local_variable_table: Vec<LocalVariable>,
+}
+
+impl LocalVariableTable {
+    pub const NAME: &'static str = "LocalVariableTable";
+    pub const TYPE_TABLE_NAME: &'static str = "LocalVariableTypeTable";
+
+    pub fn new(local_variable_table: Vec<LocalVariable>) -> LocalVariableTable {
+        LocalVariableTable {
+            local_variable_table,
+        }
+    }
+
+    pub fn local_variable_table(&self) -> &[LocalVariable] {
+        &self.local_variable_table
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<LocalVariableTable, io::Error> {
+        let length = read_u16(reader)?;
+        let mut local_variable_table = Vec::with_capacity(length as usize);
+        for _ in 0..length {
+            local_variable_table.push(LocalVariable {
+                start_pc: read_u16(reader)?,
+                length: read_u16(reader)?,
+                name_index: read_u16(reader)?,
+                descriptor_index: read_u16(reader)?,
+                index: read_u16(reader)?,
+            });
+        }
+        Ok(LocalVariableTable {
+            local_variable_table,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.local_variable_table.len() as u16).to_be_bytes());
+        for entry in self.local_variable_table.iter() {
+            out.extend_from_slice(&entry.start_pc.to_be_bytes());
+            out.extend_from_slice(&entry.length.to_be_bytes());
+            out.extend_from_slice(&entry.name_index.to_be_bytes());
+            out.extend_from_slice(&entry.descriptor_index.to_be_bytes());
+            out.extend_from_slice(&entry.index.to_be_bytes());
+        }
+    }
+}
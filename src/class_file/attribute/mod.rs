@@ -0,0 +1,10 @@
+pub mod attribute_info;
+pub mod bootstrap_methods;
+pub mod code;
+pub mod constant_value;
+pub mod exceptions;
+pub mod inner_classes;
+pub mod line_number_table;
+pub mod local_variable_table;
+pub mod source_file;
+pub mod stack_map_table;
@@ -0,0 +1,31 @@
+use crate::util::file::read_u16;
+use std::io;
+use std::io::{BufReader, Read};
+
+/// The SourceFile attribute of a class (section 4.7.10)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SourceFile {
+    sourcefile_index: u16,
+}
+
+impl SourceFile {
+    pub const NAME: &'static str = "SourceFile";
+
+    pub fn new(sourcefile_index: u16) -> SourceFile {
+        SourceFile { sourcefile_index }
+    }
+
+    pub fn sourcefile_index(&self) -> u16 {
+        self.sourcefile_index
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<SourceFile, io::Error> {
+        Ok(SourceFile {
+            sourcefile_index: read_u16(reader)?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.sourcefile_index.to_be_bytes());
+    }
+}
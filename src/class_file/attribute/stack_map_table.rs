@@ -0,0 +1,346 @@
+use crate::util::file::{read_u16, read_u8};
+use std::io;
+use std::io::{BufReader, Read};
+
+/// A verification_type_info (section 4.7.4)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    /// Index of a CONSTANT_Class entry
+    Object(u16),
+    /// Offset of the `new` instruction that created the object
+    Uninitialized(u16),
+}
+
+impl VerificationType {
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<VerificationType, io::Error> {
+        Ok(match read_u8(reader)? {
+            0 => VerificationType::Top,
+            1 => VerificationType::Integer,
+            2 => VerificationType::Float,
+            3 => VerificationType::Double,
+            4 => VerificationType::Long,
+            5 => VerificationType::Null,
+            6 => VerificationType::UninitializedThis,
+            7 => VerificationType::Object(read_u16(reader)?),
+            8 => VerificationType::Uninitialized(read_u16(reader)?),
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown verification type tag {tag}"),
+                ))
+            }
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            VerificationType::Top => out.push(0),
+            VerificationType::Integer => out.push(1),
+            VerificationType::Float => out.push(2),
+            VerificationType::Double => out.push(3),
+            VerificationType::Long => out.push(4),
+            VerificationType::Null => out.push(5),
+            VerificationType::UninitializedThis => out.push(6),
+            VerificationType::Object(index) => {
+                out.push(7);
+                out.extend_from_slice(&index.to_be_bytes());
+            }
+            VerificationType::Uninitialized(offset) => {
+                out.push(8);
+                out.extend_from_slice(&offset.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// A stack_map_frame (section 4.7.4). Each frame applies at the previous frame's offset plus
+/// `offset_delta` plus one, except the first, which applies at `offset_delta`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StackMapFrame {
+    /// frame_type 0-63, which is also the offset delta
+    Same { offset_delta: u8 },
+    /// frame_type 64-127
+    SameLocals1StackItem {
+        offset_delta: u8,
+        stack: VerificationType,
+    },
+    /// frame_type 247
+    SameLocals1StackItemExtended {
+        offset_delta: u16,
+        stack: VerificationType,
+    },
+    /// frame_type 248-250, dropping the last 1-3 locals
+    Chop { offset_delta: u16, absent: u8 },
+    /// frame_type 251
+    SameExtended { offset_delta: u16 },
+    /// frame_type 252-254, adding 1-3 locals
+    Append {
+        offset_delta: u16,
+        locals: Vec<VerificationType>,
+    },
+    /// frame_type 255
+    Full {
+        offset_delta: u16,
+        locals: Vec<VerificationType>,
+        stack: Vec<VerificationType>,
+    },
+}
+
+impl StackMapFrame {
+    /// A frame with the previous frame's locals and an empty stack, in its shortest form
+    pub fn same(offset_delta: u16) -> StackMapFrame {
+        match u8::try_from(offset_delta) {
+            Ok(offset_delta) if offset_delta < 64 => StackMapFrame::Same { offset_delta },
+            _ => StackMapFrame::SameExtended { offset_delta },
+        }
+    }
+
+    /// A frame with the previous frame's locals and one stack item, in its shortest form
+    pub fn same_locals_1_stack_item(offset_delta: u16, stack: VerificationType) -> StackMapFrame {
+        match u8::try_from(offset_delta) {
+            Ok(offset_delta) if offset_delta < 64 => StackMapFrame::SameLocals1StackItem {
+                offset_delta,
+                stack,
+            },
+            _ => StackMapFrame::SameLocals1StackItemExtended {
+                offset_delta,
+                stack,
+            },
+        }
+    }
+
+    pub fn frame_type(&self) -> u8 {
+        match self {
+            StackMapFrame::Same { offset_delta } => *offset_delta,
+            StackMapFrame::SameLocals1StackItem { offset_delta, .. } => 64 + offset_delta,
+            StackMapFrame::SameLocals1StackItemExtended { .. } => 247,
+            StackMapFrame::Chop { absent, .. } => 251 - absent,
+            StackMapFrame::SameExtended { .. } => 251,
+            StackMapFrame::Append { locals, .. } => 251 + locals.len() as u8,
+            StackMapFrame::Full { .. } => 255,
+        }
+    }
+
+    pub fn offset_delta(&self) -> u16 {
+        match self {
+            StackMapFrame::Same { offset_delta }
+            | StackMapFrame::SameLocals1StackItem { offset_delta, .. } => *offset_delta as u16,
+            StackMapFrame::SameLocals1StackItemExtended { offset_delta, .. }
+            | StackMapFrame::Chop { offset_delta, .. }
+            | StackMapFrame::SameExtended { offset_delta }
+            | StackMapFrame::Append { offset_delta, .. }
+            | StackMapFrame::Full { offset_delta, .. } => *offset_delta,
+        }
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<StackMapFrame, io::Error> {
+        let frame_type = read_u8(reader)?;
+        Ok(match frame_type {
+            0..=63 => StackMapFrame::Same {
+                offset_delta: frame_type,
+            },
+            64..=127 => StackMapFrame::SameLocals1StackItem {
+                offset_delta: frame_type - 64,
+                stack: VerificationType::from(reader)?,
+            },
+            247 => StackMapFrame::SameLocals1StackItemExtended {
+                offset_delta: read_u16(reader)?,
+                stack: VerificationType::from(reader)?,
+            },
+            248..=250 => StackMapFrame::Chop {
+                offset_delta: read_u16(reader)?,
+                absent: 251 - frame_type,
+            },
+            251 => StackMapFrame::SameExtended {
+                offset_delta: read_u16(reader)?,
+            },
+            252..=254 => {
+                let offset_delta = read_u16(reader)?;
+                let mut locals = Vec::with_capacity((frame_type - 251) as usize);
+                for _ in 251..frame_type {
+                    locals.push(VerificationType::from(reader)?);
+                }
+                StackMapFrame::Append {
+                    offset_delta,
+                    locals,
+                }
+            }
+            255 => {
+                let offset_delta = read_u16(reader)?;
+                let locals = read_verification_types(reader)?;
+                let stack = read_verification_types(reader)?;
+                StackMapFrame::Full {
+                    offset_delta,
+                    locals,
+                    stack,
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Reserved stack map frame type {frame_type}"),
+                ))
+            }
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.frame_type());
+        match self {
+            StackMapFrame::Same { .. } => {}
+            StackMapFrame::SameLocals1StackItem { stack, .. } => stack.write(out),
+            StackMapFrame::SameLocals1StackItemExtended {
+                offset_delta,
+                stack,
+            } => {
+                out.extend_from_slice(&offset_delta.to_be_bytes());
+                stack.write(out);
+            }
+            StackMapFrame::Chop { offset_delta, .. }
+            | StackMapFrame::SameExtended { offset_delta } => {
+                out.extend_from_slice(&offset_delta.to_be_bytes())
+            }
+            StackMapFrame::Append {
+                offset_delta,
+                locals,
+            } => {
+                out.extend_from_slice(&offset_delta.to_be_bytes());
+                locals.iter().for_each(|local| local.write(out));
+            }
+            StackMapFrame::Full {
+                offset_delta,
+                locals,
+                stack,
+            } => {
+                out.extend_from_slice(&offset_delta.to_be_bytes());
+                write_verification_types(locals, out);
+                write_verification_types(stack, out);
+            }
+        }
+    }
+}
+
+fn read_verification_types(
+    reader: &mut BufReader<impl Read>,
+) -> Result<Vec<VerificationType>, io::Error> {
+    let count = read_u16(reader)?;
+    let mut types = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        types.push(VerificationType::from(reader)?);
+    }
+    Ok(types)
+}
+
+fn write_verification_types(types: &[VerificationType], out: &mut Vec<u8>) {
+    out.extend_from_slice(&(types.len() as u16).to_be_bytes());
+    types.iter().for_each(|t| t.write(out));
+}
+
+/// The StackMapTable attribute of a Code attribute (section 4.7.4)
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackMapTable {
+    entries: Vec<StackMapFrame>,
+}
+
+impl StackMapTable {
+    pub const NAME: &'static str = "StackMapTable";
+
+    pub fn new(entries: Vec<StackMapFrame>) -> StackMapTable {
+        StackMapTable { entries }
+    }
+
+    pub fn entries(&self) -> &[StackMapFrame] {
+        &self.entries
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<StackMapTable, io::Error> {
+        let count = read_u16(reader)?;
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            entries.push(StackMapFrame::from(reader)?);
+        }
+        Ok(StackMapTable { entries })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.entries.len() as u16).to_be_bytes());
+        for entry in self.entries.iter() {
+            entry.write(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_and_write_round_trip() {
+        #[rustfmt::skip]
+        let bytes = [
+            0x00, 0x07,                         // 7 entries
+            0x03,                               // same, offset 3
+            0x41, 0x01,                         // same_locals_1_stack_item, int
+            0xf7, 0x01, 0x00, 0x08, 0x00, 0x05, // ..._extended, offset 256, uninitialized 5
+            0xf9, 0x00, 0x02,                   // chop 2
+            0xfb, 0x00, 0x40,                   // same_frame_extended, offset 64
+            0xfd, 0x00, 0x04, 0x04, 0x07, 0x00, 0x09, // append long, class #9
+            0xff, 0x00, 0x01, 0x00, 0x01, 0x06, 0x00, 0x02, 0x05, 0x00, // full
+        ];
+        let table = StackMapTable::from(&mut BufReader::new(bytes.as_ref())).unwrap();
+        assert_eq!(
+            table.entries(),
+            &[
+                StackMapFrame::Same { offset_delta: 3 },
+                StackMapFrame::SameLocals1StackItem {
+                    offset_delta: 1,
+                    stack: VerificationType::Integer
+                },
+                StackMapFrame::SameLocals1StackItemExtended {
+                    offset_delta: 256,
+                    stack: VerificationType::Uninitialized(5)
+                },
+                StackMapFrame::Chop {
+                    offset_delta: 2,
+                    absent: 2
+                },
+                StackMapFrame::SameExtended { offset_delta: 64 },
+                StackMapFrame::Append {
+                    offset_delta: 4,
+                    locals: vec![VerificationType::Long, VerificationType::Object(9)]
+                },
+                StackMapFrame::Full {
+                    offset_delta: 1,
+                    locals: vec![VerificationType::UninitializedThis],
+                    stack: vec![VerificationType::Null, VerificationType::Top]
+                },
+            ]
+        );
+        assert_eq!(table.entries()[3].frame_type(), 249);
+        assert_eq!(table.entries()[2].offset_delta(), 256);
+
+        let mut out = Vec::new();
+        table.write(&mut out);
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn shortest_forms_and_bad_tags() {
+        assert_eq!(StackMapFrame::same(63).frame_type(), 63);
+        assert_eq!(StackMapFrame::same(64).frame_type(), 251);
+        let item = StackMapFrame::same_locals_1_stack_item(70, VerificationType::Float);
+        assert_eq!(item.frame_type(), 247);
+
+        let bytes = [0x00, 0x01, 0x40, 0x09];
+        assert!(StackMapTable::from(&mut BufReader::new(bytes.as_ref())).is_err());
+        let bytes = [0x00, 0x01, 0x80];
+        assert!(StackMapTable::from(&mut BufReader::new(bytes.as_ref())).is_err());
+    }
+}
@@ -1,15 +1,30 @@
+use crate::class_file::attribute::attribute_info::{
+    find_attribute, read_attributes, write_attributes, AttributeInfo,
+};
 use crate::class_file::constant_pool::constant_pool::ConstantPool;
-use crate::util::file::read_bytes;
+use crate::class_file::limits::ParseLimits;
+use crate::class_file::member_info::{FieldInfo, MemberInfo, MethodInfo};
+use crate::util::file::{read_bytes, read_u16, read_u16_list};
 use log::info;
 use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+pub const MAGIC_NUMBER: u32 = 0xCAFEBABE;
+
+#[derive(Debug)]
 pub struct ClassFile {
     magic_number: u32,
     minor_version: u16,
     major_version: u16,
     constant_pool: ConstantPool,
+    access_flags: u16,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<AttributeInfo>,
 }
 
 impl ClassFile {
@@ -19,13 +34,20 @@ impl ClassFile {
             minor_version: 0,
             major_version: 0,
             constant_pool: ConstantPool::new(),
+            access_flags: 0,
+            this_class: 0,
+            super_class: 0,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
         }
     }
 
     /// Reads a file and attempts to parse it as a ClassFile
     ///
     /// # Examples
-    /// ```rust
+    /// ```rust,ignore
     /// let class_file = ClassFile::new();
     /// class_file.read_file("MyClass.class");
     /// match class_file {
@@ -34,27 +56,248 @@ impl ClassFile {
     /// }
     /// ```
     pub fn read_file(&mut self, file_path: &str) -> Result<(), io::Error> {
-        info!("Now reading class file {file_path}");
+        self.read_file_with_limits(file_path, &ParseLimits::new())
+    }
 
-        let file = File::open(file_path)?;
-        let mut reader = BufReader::new(file);
+    pub fn read_file_with_limits(
+        &mut self,
+        file_path: &str,
+        limits: &ParseLimits,
+    ) -> Result<(), io::Error> {
+        info!("Now reading class file {file_path}");
 
-        self.parse_magic_number(&mut reader)?;
-        self.parse_minor_version(&mut reader)?;
-        self.parse_major_version(&mut reader)?;
-        self.parse_constant_pool(&mut reader)?;
+        // read one byte past the limit so oversized files are refused without loading them
+        let mut bytes = Vec::new();
+        File::open(file_path)?
+            .take(limits.max_file_size() as u64 + 1)
+            .read_to_end(&mut bytes)?;
+        self.read_slice_with_limits(&bytes, limits)?;
 
         info!("Finished reading class file {file_path}");
 
         Ok(())
     }
 
+    /// Parses a class file that is already in memory (e.g. an entry of a jar)
+    pub fn read_slice(&mut self, bytes: &[u8]) -> Result<(), io::Error> {
+        self.read_slice_with_limits(bytes, &ParseLimits::new())
+    }
+
+    /// Parses untrusted input, returning an error rather than panicking or allocating more
+    /// than the input can account for when it is malformed or exceeds `limits`
+    pub fn read_slice_with_limits(
+        &mut self,
+        bytes: &[u8],
+        limits: &ParseLimits,
+    ) -> Result<(), io::Error> {
+        limits.check_file_size(bytes.len())?;
+        let mut reader = BufReader::new(bytes);
+        self.read(&mut reader, limits)?;
+
+        if !reader.buffer().is_empty() || !reader.get_ref().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unexpected trailing bytes after the end of the class file",
+            ));
+        }
+        limits.check_class(self)
+    }
+
+    /// Parses only as far as the interfaces (section 4.1), for when the class hierarchy is all
+    /// that's needed. Fields, methods and attributes are left empty.
+    pub fn read_header(&mut self, bytes: &[u8]) -> Result<(), io::Error> {
+        let limits = ParseLimits::new();
+        limits.check_file_size(bytes.len())?;
+        self.read_through_interfaces(&mut BufReader::new(bytes), &limits)
+    }
+
+    fn read(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        limits: &ParseLimits,
+    ) -> Result<(), io::Error> {
+        self.read_through_interfaces(reader, limits)?;
+        self.parse_fields(reader)?;
+        self.parse_methods(reader)?;
+        self.parse_attributes(reader)?;
+        Ok(())
+    }
+
+    fn read_through_interfaces(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        limits: &ParseLimits,
+    ) -> Result<(), io::Error> {
+        self.parse_magic_number(reader)?;
+        self.parse_minor_version(reader)?;
+        self.parse_major_version(reader)?;
+        self.parse_constant_pool(reader, limits)?;
+        self.parse_access_flags(reader)?;
+        self.parse_this_class(reader)?;
+        self.parse_super_class(reader)?;
+        self.parse_interfaces(reader)?;
+        Ok(())
+    }
+
+    /// Serializes the class back into the class file format (section 4.1)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.magic_number.to_be_bytes());
+        out.extend_from_slice(&self.minor_version.to_be_bytes());
+        out.extend_from_slice(&self.major_version.to_be_bytes());
+        self.constant_pool.write(&mut out);
+        out.extend_from_slice(&self.access_flags.to_be_bytes());
+        out.extend_from_slice(&self.this_class.to_be_bytes());
+        out.extend_from_slice(&self.super_class.to_be_bytes());
+        out.extend_from_slice(&(self.interfaces.len() as u16).to_be_bytes());
+        for interface in self.interfaces.iter() {
+            out.extend_from_slice(&interface.to_be_bytes());
+        }
+        out.extend_from_slice(&(self.fields.len() as u16).to_be_bytes());
+        for field in self.fields.iter() {
+            field.write(&mut out);
+        }
+        out.extend_from_slice(&(self.methods.len() as u16).to_be_bytes());
+        for method in self.methods.iter() {
+            method.write(&mut out);
+        }
+        write_attributes(&self.attributes, &mut out);
+        out
+    }
+
+    pub fn write_file(&self, file_path: &str) -> Result<(), io::Error> {
+        std::fs::write(file_path, self.to_bytes())
+    }
+
+    pub fn magic_number(&self) -> u32 {
+        self.magic_number
+    }
+
+    pub fn minor_version(&self) -> u16 {
+        self.minor_version
+    }
+
+    pub fn major_version(&self) -> u16 {
+        self.major_version
+    }
+
+    /// Sets the magic number and version, e.g. `set_version(52, 0)` for Java 8
+    pub fn set_version(&mut self, major_version: u16, minor_version: u16) {
+        self.magic_number = MAGIC_NUMBER;
+        self.major_version = major_version;
+        self.minor_version = minor_version;
+    }
+
+    pub fn constant_pool(&self) -> &ConstantPool {
+        &self.constant_pool
+    }
+
+    pub fn set_constant_pool(&mut self, constant_pool: ConstantPool) {
+        self.constant_pool = constant_pool;
+    }
+
+    /// Takes the constant pool out of the class (leaving an empty one) so it can be extended
+    /// with a `ConstantPoolBuilder` and put back with `set_constant_pool`
+    pub fn take_constant_pool(&mut self) -> ConstantPool {
+        std::mem::replace(&mut self.constant_pool, ConstantPool::new())
+    }
+
+    pub fn access_flags(&self) -> u16 {
+        self.access_flags
+    }
+
+    pub fn set_access_flags(&mut self, access_flags: u16) {
+        self.access_flags = access_flags;
+    }
+
+    pub fn this_class(&self) -> u16 {
+        self.this_class
+    }
+
+    pub fn set_this_class(&mut self, this_class: u16) {
+        self.this_class = this_class;
+    }
+
+    /// 0 for `java/lang/Object`, which has no superclass
+    pub fn super_class(&self) -> u16 {
+        self.super_class
+    }
+
+    pub fn set_super_class(&mut self, super_class: u16) {
+        self.super_class = super_class;
+    }
+
+    pub fn interfaces(&self) -> &[u16] {
+        &self.interfaces
+    }
+
+    pub fn interfaces_mut(&mut self) -> &mut Vec<u16> {
+        &mut self.interfaces
+    }
+
+    pub fn fields(&self) -> &[FieldInfo] {
+        &self.fields
+    }
+
+    pub fn fields_mut(&mut self) -> &mut Vec<FieldInfo> {
+        &mut self.fields
+    }
+
+    pub fn methods(&self) -> &[MethodInfo] {
+        &self.methods
+    }
+
+    pub fn methods_mut(&mut self) -> &mut Vec<MethodInfo> {
+        &mut self.methods
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut Vec<AttributeInfo> {
+        &mut self.attributes
+    }
+
+    pub fn find_attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        find_attribute(&self.attributes, &self.constant_pool, name)
+    }
+
+    /// Internal binary name of this class, e.g. `java/lang/String`
+    pub fn class_name(&self) -> Result<String, io::Error> {
+        self.constant_pool.class_name(self.this_class)
+    }
+
+    /// Internal binary name of the superclass, `None` for `java/lang/Object`
+    pub fn super_class_name(&self) -> Result<Option<String>, io::Error> {
+        if self.super_class == 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.constant_pool.class_name(self.super_class)?))
+    }
+
+    pub fn interface_names(&self) -> Result<Vec<String>, io::Error> {
+        self.interfaces
+            .iter()
+            .map(|index| self.constant_pool.class_name(*index))
+            .collect()
+    }
+
+    /// Finds a field or method by name and descriptor
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&MethodInfo> {
+        find_member(&self.methods, &self.constant_pool, name, descriptor)
+    }
+
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&FieldInfo> {
+        find_member(&self.fields, &self.constant_pool, name, descriptor)
+    }
+
     fn parse_magic_number(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
         let mut buffer: [u8; 4] = [0; 4];
         read_bytes(reader, &mut buffer, 4)?;
         self.magic_number = u32::from_be_bytes(buffer);
-        if self.magic_number != 0xCAFEBABE {
-            return Err(io::Error::new(io::ErrorKind::Other, "Invalid magic number"));
+        if self.magic_number != MAGIC_NUMBER {
+            return Err(io::Error::other("Invalid magic number"));
         }
 
         Ok(())
@@ -72,37 +315,101 @@ impl ClassFile {
         read_bytes(reader, &mut buffer, 2)?;
         self.major_version = u16::from_be_bytes(buffer);
         if self.major_version > 52 {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
+            return Err(io::Error::other(
                 "Unsupported major class version - must be <= 52",
             ));
         }
         Ok(())
     }
 
-    fn parse_constant_pool(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+    fn parse_constant_pool(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        limits: &ParseLimits,
+    ) -> Result<(), io::Error> {
         // first get size of constant pool
         let mut buffer: [u8; 2] = [0; 2];
         read_bytes(reader, &mut buffer, 2)?;
         let constant_pool_count = u16::from_be_bytes(buffer);
+        limits.check_constant_pool_count(constant_pool_count)?;
         self.constant_pool.set_count(constant_pool_count);
 
         // parse all constant pool items
         // constant pool starts at index 1 up to count - 1 (described in section 4.1)
-        for _i in 1..constant_pool_count {
+        // Long and Double items take up two indices, so track the next index from the items
+        while self.constant_pool.items().len() + 1 < constant_pool_count as usize {
             self.constant_pool.parse_item_from_class_file(reader)?;
         }
+        // a Long or Double in the last slot would take up an index past the end (section 4.4.5)
+        if self.constant_pool.items().len() + 1 > constant_pool_count.max(1) as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Last constant pool entry runs past constant_pool_count",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn parse_access_flags(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        self.access_flags = read_u16(reader)?;
+        Ok(())
+    }
+
+    fn parse_this_class(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        self.this_class = read_u16(reader)?;
+        Ok(())
+    }
+
+    fn parse_super_class(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        self.super_class = read_u16(reader)?;
+        Ok(())
+    }
+
+    fn parse_interfaces(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        self.interfaces = read_u16_list(reader)?;
+        Ok(())
+    }
+
+    fn parse_fields(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        let fields_count = read_u16(reader)?;
+        for _i in 0..fields_count {
+            self.fields.push(FieldInfo::from(reader)?);
+        }
+        Ok(())
+    }
+
+    fn parse_methods(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        let methods_count = read_u16(reader)?;
+        for _i in 0..methods_count {
+            self.methods.push(MethodInfo::from(reader)?);
+        }
+        Ok(())
+    }
 
+    fn parse_attributes(&mut self, reader: &mut BufReader<impl Read>) -> Result<(), io::Error> {
+        self.attributes = read_attributes(reader)?;
         Ok(())
     }
 }
 
+fn find_member<'a>(
+    members: &'a [MemberInfo],
+    constant_pool: &ConstantPool,
+    name: &str,
+    descriptor: &str,
+) -> Option<&'a MemberInfo> {
+    members.iter().find(|member| {
+        matches!(member.name(constant_pool), Ok(n) if n == name)
+            && matches!(member.descriptor(constant_pool), Ok(d) if d == descriptor)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
     use crate::class_file::constant_pool::tag::ConstantPoolTag;
-    use std::ops::Deref;
 
     #[test]
     fn read_bad_file() {
@@ -131,7 +438,7 @@ mod tests {
         let constant_pool_items = class_file.constant_pool.items();
 
         // #1 MethodRef class_index=6 name_and_type_index=15 ("java/lang/Object")
-        let mut item: &ConstantPoolItem = constant_pool_items.get(0).unwrap();
+        let mut item: &ConstantPoolItem = constant_pool_items.first().unwrap();
         assert_eq!(*item.tag(), ConstantPoolTag::MethodRef);
         let mut data: &ConstantPoolData = item.data();
         match data {
@@ -407,6 +714,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_members_and_attributes() {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/helloworld/HelloWorld.class")
+            .unwrap();
+        assert_eq!(class_file.access_flags(), 0x0021);
+        assert_eq!(class_file.class_name().unwrap(), "HelloWorld");
+        assert_eq!(
+            class_file.super_class_name().unwrap(),
+            Some("java/lang/Object".to_string())
+        );
+        assert!(class_file.interfaces().is_empty());
+        assert!(class_file.fields().is_empty());
+        assert_eq!(class_file.methods().len(), 2);
+
+        let main = class_file
+            .find_method("main", "([Ljava/lang/String;)V")
+            .unwrap();
+        assert_eq!(main.access_flags(), 0x0009);
+        let code = main.code(class_file.constant_pool()).unwrap().unwrap();
+        assert_eq!(code.max_stack(), 2);
+        assert_eq!(code.max_locals(), 1);
+        // getstatic #2, ldc #3, invokevirtual #4, return
+        assert_eq!(
+            code.code(),
+            &[0xB2, 0x00, 0x02, 0x12, 0x03, 0xB6, 0x00, 0x04, 0xB1]
+        );
+
+        assert!(class_file.find_attribute("SourceFile").is_some());
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let bytes = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        let mut class_file = ClassFile::new();
+        class_file.read_slice(&bytes).unwrap();
+        assert_eq!(class_file.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn read_header_stops_after_the_interfaces() {
+        let bytes = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        let mut class_file = ClassFile::new();
+        class_file.read_header(&bytes).unwrap();
+        assert_eq!(class_file.class_name().unwrap(), "HelloWorld");
+        assert_eq!(
+            class_file.super_class_name().unwrap().as_deref(),
+            Some("java/lang/Object")
+        );
+        assert!(class_file.methods().is_empty());
+        assert!(class_file.attributes().is_empty());
+    }
+
+    #[test]
+    fn trailing_bytes_fail() {
+        let mut bytes = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        bytes.push(0);
+        let mut class_file = ClassFile::new();
+        assert!(class_file.read_slice(&bytes).is_err());
+    }
+
+    #[test]
+    fn long_in_the_last_constant_pool_slot_fails() {
+        // constant_pool_count = 2 leaves room for index 1 only, but a Long takes two
+        let mut bytes = vec![
+            0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x34, 0x00, 0x02, 0x05,
+        ];
+        bytes.extend([0; 8]);
+        let mut class_file = ClassFile::new();
+        assert!(class_file.read_header(&bytes).is_err());
+    }
+
     #[test]
     fn fail_to_read_class_thats_too_new() {
         let mut class_file = ClassFile::new();
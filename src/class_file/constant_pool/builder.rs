@@ -0,0 +1,574 @@
+use crate::class_file::constant_pool::constant_pool::{ConstantPool, MAX_CONSTANT_POOL_COUNT};
+use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
+use crate::class_file::constant_pool::types::class_info::ClassInfo;
+use crate::class_file::constant_pool::types::double::Double;
+use crate::class_file::constant_pool::types::field_ref::FieldRef;
+use crate::class_file::constant_pool::types::float::Float;
+use crate::class_file::constant_pool::types::integer::Integer;
+use crate::class_file::constant_pool::types::interface_method_ref::InterfaceMethodRef;
+use crate::class_file::constant_pool::types::invoke_dynamic::InvokeDynamic;
+use crate::class_file::constant_pool::types::jvm_string::JvmString;
+use crate::class_file::constant_pool::types::long::Long;
+use crate::class_file::constant_pool::types::method_handle::{MethodHandle, ReferenceKind};
+use crate::class_file::constant_pool::types::method_ref::MethodRef;
+use crate::class_file::constant_pool::types::method_type::MethodType;
+use crate::class_file::constant_pool::types::name_and_type::NameAndType;
+use crate::class_file::constant_pool::types::utf8::Utf8;
+use std::collections::HashMap;
+use std::io;
+
+/// Identity of a constant pool entry used for interning.
+/// Floating point values are compared by their bits so that NaN and -0.0 keep their own entries.
+#[derive(PartialEq, Eq, Hash)]
+enum EntryKey {
+    Utf8(Vec<u8>),
+    Class(u16),
+    String(u16),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    NameAndType(u16, u16),
+    FieldRef(u16, u16),
+    MethodRef(u16, u16),
+    InterfaceMethodRef(u16, u16),
+    MethodHandle(ReferenceKind, u16),
+    MethodType(u16),
+    InvokeDynamic(u16, u16),
+}
+
+impl EntryKey {
+    fn from_data(data: &ConstantPoolData) -> Option<EntryKey> {
+        let key = match data {
+            ConstantPoolData::Utf8(u) => EntryKey::Utf8(u.bytes().to_vec()),
+            ConstantPoolData::ClassInfo(c) => EntryKey::Class(c.name_index()),
+            ConstantPoolData::String(s) => EntryKey::String(s.string_index()),
+            ConstantPoolData::Integer(i) => EntryKey::Integer(i.value()),
+            ConstantPoolData::Float(f) => EntryKey::Float(f.bytes()),
+            ConstantPoolData::Long(l) => EntryKey::Long(l.value()),
+            ConstantPoolData::Double(d) => EntryKey::Double(d.value().to_bits()),
+            ConstantPoolData::NameAndType(n) => {
+                EntryKey::NameAndType(n.name_index(), n.descriptor_index())
+            }
+            ConstantPoolData::FieldRef(r) => {
+                EntryKey::FieldRef(r.class_index(), r.name_and_type_index())
+            }
+            ConstantPoolData::MethodRef(r) => {
+                EntryKey::MethodRef(r.class_index(), r.name_and_type_index())
+            }
+            ConstantPoolData::InterfaceMethodRef(r) => {
+                EntryKey::InterfaceMethodRef(r.class_index(), r.name_and_type_index())
+            }
+            ConstantPoolData::MethodHandle(h) => {
+                EntryKey::MethodHandle(h.reference_kind(), h.reference_index())
+            }
+            ConstantPoolData::MethodType(t) => EntryKey::MethodType(t.descriptor_index()),
+            ConstantPoolData::InvokeDynamic(i) => {
+                EntryKey::InvokeDynamic(i.bootstrap_method_attr_index(), i.name_and_type_index())
+            }
+            ConstantPoolData::Uninit | ConstantPoolData::Unusable => return None,
+        };
+        Some(key)
+    }
+}
+
+/// Builds a constant pool one entry at a time.
+///
+/// Adding an entry that already exists returns the index of the existing entry, and
+/// entries that refer to other entries (e.g. a MethodRef's Class and NameAndType)
+/// are created on demand.
+///
+/// # Examples
+/// ```rust,ignore
+/// let mut builder = ConstantPoolBuilder::new();
+/// let object = builder.add_class("java/lang/Object")?;
+/// assert_eq!(builder.add_class("java/lang/Object")?, object);
+/// let constant_pool = builder.build();
+/// ```
+pub struct ConstantPoolBuilder {
+    constant_pool: ConstantPool,
+    indices: HashMap<EntryKey, u16>,
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> ConstantPoolBuilder {
+        ConstantPoolBuilder {
+            constant_pool: ConstantPool::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Continues building on top of an existing (e.g. parsed) constant pool.
+    /// Existing indices are kept as-is; if the pool contains duplicates the first one is reused.
+    pub fn from_constant_pool(constant_pool: ConstantPool) -> ConstantPoolBuilder {
+        let mut indices = HashMap::new();
+        for (i, item) in constant_pool.items().iter().enumerate() {
+            if let Some(key) = EntryKey::from_data(item.data()) {
+                indices.entry(key).or_insert((i + 1) as u16);
+            }
+        }
+
+        ConstantPoolBuilder {
+            constant_pool,
+            indices,
+        }
+    }
+
+    pub fn constant_pool(&self) -> &ConstantPool {
+        &self.constant_pool
+    }
+
+    pub fn build(self) -> ConstantPool {
+        self.constant_pool
+    }
+
+    /// Appends an item without interning it, so duplicate or malformed entries can be created on purpose
+    pub fn push_item(&mut self, item: ConstantPoolItem) -> Result<u16, io::Error> {
+        self.constant_pool.push_item(item)
+    }
+
+    /// Changes the entry at `index` in place, so everything referring to it sees the new value.
+    /// Later lookups of the new value find it at `index` unless it was already interned.
+    pub fn replace(&mut self, index: u16, data: ConstantPoolData) -> Result<(), io::Error> {
+        if let Some(old) = self
+            .constant_pool
+            .get(index)
+            .and_then(|item| EntryKey::from_data(item.data()))
+        {
+            if self.indices.get(&old) == Some(&index) {
+                self.indices.remove(&old);
+            }
+        }
+        let key = EntryKey::from_data(&data);
+        self.constant_pool
+            .replace_item(index, ConstantPoolItem::from_data(data))?;
+        if let Some(key) = key {
+            self.indices.entry(key).or_insert(index);
+        }
+        Ok(())
+    }
+
+    fn intern(&mut self, data: ConstantPoolData) -> Result<u16, io::Error> {
+        let key = EntryKey::from_data(&data).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Only initialized entries can be added to the constant pool",
+            )
+        })?;
+        if let Some(index) = self.indices.get(&key) {
+            return Ok(*index);
+        }
+
+        let index = self
+            .constant_pool
+            .push_item(ConstantPoolItem::from_data(data))?;
+        self.indices.insert(key, index);
+        Ok(index)
+    }
+
+    /// The index of an entry that's already been interned
+    fn find(&self, data: ConstantPoolData) -> Option<u16> {
+        EntryKey::from_data(&data).and_then(|key| self.indices.get(&key).copied())
+    }
+
+    fn find_utf8(&self, value: &str) -> Option<u16> {
+        self.find(ConstantPoolData::Utf8(Utf8::from_string(value).ok()?))
+    }
+
+    fn find_class(&self, name: &str) -> Option<u16> {
+        let name_index = self.find_utf8(name)?;
+        self.find(ConstantPoolData::ClassInfo(ClassInfo::from_name_index(
+            name_index,
+        )))
+    }
+
+    fn find_name_and_type(&self, name: &str, descriptor: &str) -> Option<u16> {
+        let name_index = self.find_utf8(name)?;
+        let descriptor_index = self.find_utf8(descriptor)?;
+        self.find(ConstantPoolData::NameAndType(NameAndType::from_indices(
+            name_index,
+            descriptor_index,
+        )))
+    }
+
+    /// Fails unless the Utf8 entries for `strings` that are missing and `entries` other
+    /// single-slot entries all fit, so that an entry that overflows leaves none of its parts
+    /// behind
+    fn reserve(&self, strings: &[&str], entries: usize) -> Result<(), io::Error> {
+        let mut missing: Vec<&str> = strings
+            .iter()
+            .copied()
+            .filter(|value| self.find_utf8(value).is_none())
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        let index = self.constant_pool.items().len() + 1;
+        let needed = missing.len() + entries;
+        if index + needed > MAX_CONSTANT_POOL_COUNT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Constant pool overflow: adding {needed} entries at index {index} exceeds the maximum of {} entries",
+                    MAX_CONSTANT_POOL_COUNT - 1
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reserves room for a field, method or interface method reference and its parts
+    fn reserve_member_ref(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<(), io::Error> {
+        let entries = 1
+            + self.find_class(class).is_none() as usize
+            + self.find_name_and_type(name, descriptor).is_none() as usize;
+        self.reserve(&[class, name, descriptor], entries)
+    }
+
+    /// The index of a reference whose class and name and type are all interned already
+    fn find_member_ref(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+        data: fn(u16, u16) -> ConstantPoolData,
+    ) -> Option<u16> {
+        let class_index = self.find_class(class)?;
+        let name_and_type_index = self.find_name_and_type(name, descriptor)?;
+        self.find(data(class_index, name_and_type_index))
+    }
+
+    pub fn add_utf8(&mut self, value: &str) -> Result<u16, io::Error> {
+        self.intern(ConstantPoolData::Utf8(Utf8::from_string(value)?))
+    }
+
+    pub fn add_class(&mut self, name: &str) -> Result<u16, io::Error> {
+        if let Some(index) = self.find_class(name) {
+            return Ok(index);
+        }
+        self.reserve(&[name], 1)?;
+        let name_index = self.add_utf8(name)?;
+        self.intern(ConstantPoolData::ClassInfo(ClassInfo::from_name_index(
+            name_index,
+        )))
+    }
+
+    pub fn add_string(&mut self, value: &str) -> Result<u16, io::Error> {
+        let existing = self.find_utf8(value).and_then(|string_index| {
+            self.find(ConstantPoolData::String(JvmString::from_string_index(
+                string_index,
+            )))
+        });
+        if let Some(index) = existing {
+            return Ok(index);
+        }
+        self.reserve(&[value], 1)?;
+        let string_index = self.add_utf8(value)?;
+        self.intern(ConstantPoolData::String(JvmString::from_string_index(
+            string_index,
+        )))
+    }
+
+    pub fn add_integer(&mut self, value: i32) -> Result<u16, io::Error> {
+        self.intern(ConstantPoolData::Integer(Integer::from_value(value)))
+    }
+
+    pub fn add_float(&mut self, value: f32) -> Result<u16, io::Error> {
+        self.intern(ConstantPoolData::Float(Float::from_value(value)))
+    }
+
+    pub fn add_long(&mut self, value: i64) -> Result<u16, io::Error> {
+        self.intern(ConstantPoolData::Long(Long::from_value(value)))
+    }
+
+    pub fn add_double(&mut self, value: f64) -> Result<u16, io::Error> {
+        self.intern(ConstantPoolData::Double(Double::from_value(value)))
+    }
+
+    pub fn add_name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, io::Error> {
+        if let Some(index) = self.find_name_and_type(name, descriptor) {
+            return Ok(index);
+        }
+        self.reserve(&[name, descriptor], 1)?;
+        let name_index = self.add_utf8(name)?;
+        let descriptor_index = self.add_utf8(descriptor)?;
+        self.intern(ConstantPoolData::NameAndType(NameAndType::from_indices(
+            name_index,
+            descriptor_index,
+        )))
+    }
+
+    pub fn add_field_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, io::Error> {
+        let data = |c, n| ConstantPoolData::FieldRef(FieldRef::from_indices(c, n));
+        if let Some(index) = self.find_member_ref(class, name, descriptor, data) {
+            return Ok(index);
+        }
+        self.reserve_member_ref(class, name, descriptor)?;
+        let class_index = self.add_class(class)?;
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.intern(ConstantPoolData::FieldRef(FieldRef::from_indices(
+            class_index,
+            name_and_type_index,
+        )))
+    }
+
+    pub fn add_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, io::Error> {
+        let data = |c, n| ConstantPoolData::MethodRef(MethodRef::from_indices(c, n));
+        if let Some(index) = self.find_member_ref(class, name, descriptor, data) {
+            return Ok(index);
+        }
+        self.reserve_member_ref(class, name, descriptor)?;
+        let class_index = self.add_class(class)?;
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.intern(ConstantPoolData::MethodRef(MethodRef::from_indices(
+            class_index,
+            name_and_type_index,
+        )))
+    }
+
+    pub fn add_interface_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, io::Error> {
+        let data =
+            |c, n| ConstantPoolData::InterfaceMethodRef(InterfaceMethodRef::from_indices(c, n));
+        if let Some(index) = self.find_member_ref(class, name, descriptor, data) {
+            return Ok(index);
+        }
+        self.reserve_member_ref(class, name, descriptor)?;
+        let class_index = self.add_class(class)?;
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.intern(ConstantPoolData::InterfaceMethodRef(
+            InterfaceMethodRef::from_indices(class_index, name_and_type_index),
+        ))
+    }
+
+    /// `reference_index` must point at the field or method reference the handle operates on
+    pub fn add_method_handle(
+        &mut self,
+        reference_kind: ReferenceKind,
+        reference_index: u16,
+    ) -> Result<u16, io::Error> {
+        self.intern(ConstantPoolData::MethodHandle(
+            MethodHandle::from_reference(reference_kind, reference_index),
+        ))
+    }
+
+    pub fn add_method_type(&mut self, descriptor: &str) -> Result<u16, io::Error> {
+        let existing = self.find_utf8(descriptor).and_then(|descriptor_index| {
+            self.find(ConstantPoolData::MethodType(
+                MethodType::from_descriptor_index(descriptor_index),
+            ))
+        });
+        if let Some(index) = existing {
+            return Ok(index);
+        }
+        self.reserve(&[descriptor], 1)?;
+        let descriptor_index = self.add_utf8(descriptor)?;
+        self.intern(ConstantPoolData::MethodType(
+            MethodType::from_descriptor_index(descriptor_index),
+        ))
+    }
+
+    /// `bootstrap_method_attr_index` indexes the class's BootstrapMethods attribute, not the constant pool
+    pub fn add_invoke_dynamic(
+        &mut self,
+        bootstrap_method_attr_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, io::Error> {
+        let existing = self
+            .find_name_and_type(name, descriptor)
+            .and_then(|name_and_type_index| {
+                self.find(ConstantPoolData::InvokeDynamic(
+                    InvokeDynamic::from_indices(bootstrap_method_attr_index, name_and_type_index),
+                ))
+            });
+        if let Some(index) = existing {
+            return Ok(index);
+        }
+        let entries = 1 + self.find_name_and_type(name, descriptor).is_none() as usize;
+        self.reserve(&[name, descriptor], entries)?;
+        let name_and_type_index = self.add_name_and_type(name, descriptor)?;
+        self.intern(ConstantPoolData::InvokeDynamic(
+            InvokeDynamic::from_indices(bootstrap_method_attr_index, name_and_type_index),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_file::constant_pool::tag::ConstantPoolTag;
+
+    #[test]
+    fn duplicate_class_returns_same_index() {
+        let mut builder = ConstantPoolBuilder::new();
+        let first = builder.add_class("java/lang/Object").unwrap();
+        let second = builder.add_class("java/lang/Object").unwrap();
+        assert_eq!(first, second);
+
+        // #1 Utf8 "java/lang/Object", #2 Class #1
+        let constant_pool = builder.build();
+        assert_eq!(first, 2);
+        assert_eq!(constant_pool.count(), 3);
+        assert_eq!(constant_pool.class_name(2).unwrap(), "java/lang/Object");
+    }
+
+    #[test]
+    fn method_ref_creates_dependent_entries() {
+        let mut builder = ConstantPoolBuilder::new();
+        let method_ref = builder
+            .add_method_ref("java/lang/Object", "<init>", "()V")
+            .unwrap();
+        let class = builder.add_class("java/lang/Object").unwrap();
+        let name_and_type = builder.add_name_and_type("<init>", "()V").unwrap();
+
+        // Utf8, Class, Utf8, Utf8, NameAndType, MethodRef
+        let constant_pool = builder.build();
+        assert_eq!(constant_pool.items().len(), 6);
+        assert_eq!((class, name_and_type, method_ref), (2, 5, 6));
+        assert_eq!(
+            constant_pool.member_ref(method_ref).unwrap(),
+            (
+                "java/lang/Object".to_string(),
+                "<init>".to_string(),
+                "()V".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn long_and_double_take_two_slots() {
+        let mut builder = ConstantPoolBuilder::new();
+        assert_eq!(builder.add_long(1).unwrap(), 1);
+        assert_eq!(builder.add_double(2.0).unwrap(), 3);
+        assert_eq!(builder.add_integer(3).unwrap(), 5);
+        assert_eq!(builder.add_long(1).unwrap(), 1);
+
+        let constant_pool = builder.build();
+        assert_eq!(constant_pool.count(), 6);
+        assert_eq!(
+            *constant_pool.get(2).unwrap().tag(),
+            ConstantPoolTag::Unusable
+        );
+        assert_eq!(
+            *constant_pool.get(4).unwrap().tag(),
+            ConstantPoolTag::Unusable
+        );
+    }
+
+    #[test]
+    fn float_interning_is_bitwise() {
+        let mut builder = ConstantPoolBuilder::new();
+        let zero = builder.add_float(0.0).unwrap();
+        let negative_zero = builder.add_float(-0.0).unwrap();
+        let nan = builder.add_float(f32::NAN).unwrap();
+        assert_ne!(zero, negative_zero);
+        assert_eq!(builder.add_float(f32::NAN).unwrap(), nan);
+    }
+
+    #[test]
+    fn push_item_does_not_intern() {
+        let mut builder = ConstantPoolBuilder::new();
+        let first = builder.add_utf8("x").unwrap();
+        let raw = builder
+            .push_item(ConstantPoolItem::from_data(ConstantPoolData::Utf8(
+                Utf8::from_string("x").unwrap(),
+            )))
+            .unwrap();
+        assert_ne!(first, raw);
+        assert_eq!(builder.add_utf8("x").unwrap(), first);
+    }
+
+    #[test]
+    fn continues_from_existing_constant_pool() {
+        let mut builder = ConstantPoolBuilder::new();
+        builder.add_string("hello").unwrap();
+        let mut builder = ConstantPoolBuilder::from_constant_pool(builder.build());
+        assert_eq!(builder.add_string("hello").unwrap(), 2);
+        assert_eq!(builder.add_utf8("world").unwrap(), 3);
+    }
+
+    #[test]
+    fn replace_keeps_the_index_and_reinterns() {
+        let mut builder = ConstantPoolBuilder::new();
+        let class = builder.add_class("a/B").unwrap();
+        let renamed = builder.add_utf8("c/D").unwrap();
+        builder
+            .replace(
+                class,
+                ConstantPoolData::ClassInfo(ClassInfo::from_name_index(renamed)),
+            )
+            .unwrap();
+        assert_eq!(builder.constant_pool().class_name(class).unwrap(), "c/D");
+        assert_eq!(builder.add_class("c/D").unwrap(), class);
+        assert_ne!(builder.add_class("a/B").unwrap(), class);
+
+        // a Long can't take the place of an entry with one slot
+        let long = ConstantPoolData::Long(Long::from_value(1));
+        assert!(builder.replace(class, long).is_err());
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        let mut builder = ConstantPoolBuilder::new();
+        for i in 0..65534 {
+            builder.add_integer(i).unwrap();
+        }
+        // index 65535 would need constant_pool_count = 65536
+        assert!(builder.add_integer(-1).is_err());
+        assert_eq!(builder.add_integer(0).unwrap(), 1);
+
+        let mut builder = ConstantPoolBuilder::new();
+        for i in 0..65533 {
+            builder.add_integer(i).unwrap();
+        }
+        // a Long at index 65534 would need index 65535 as well
+        assert!(builder.add_long(0).is_err());
+        assert_eq!(builder.add_integer(-1).unwrap(), 65534);
+    }
+
+    #[test]
+    fn overflow_leaves_no_parts_behind() {
+        let mut builder = ConstantPoolBuilder::new();
+        for i in 0..65532 {
+            builder.add_integer(i).unwrap();
+        }
+        // the Utf8 fits at 65533 but the MethodRef's Class and NameAndType don't
+        assert!(builder.add_method_ref("A", "f", "()V").is_err());
+        assert_eq!(builder.constant_pool().items().len(), 65532);
+        assert_eq!(builder.add_class("A").unwrap(), 65534);
+        assert_eq!(builder.constant_pool().items().len(), 65534);
+        // interned entries are still found when the pool is full
+        assert_eq!(builder.add_class("A").unwrap(), 65534);
+        assert!(builder.add_string("A").is_err());
+        assert_eq!(builder.constant_pool().items().len(), 65534);
+    }
+    #[test]
+    fn rejects_strings_too_long_for_a_utf8() {
+        let mut builder = ConstantPoolBuilder::new();
+        let long = "x".repeat(70000);
+        let error = builder.add_utf8(&long).unwrap_err();
+        assert!(error.to_string().contains("70000 bytes"), "{error}");
+        assert!(builder.add_string(&long).is_err());
+        assert!(builder.add_class(&long).is_err());
+        assert!(builder.constant_pool().items().is_empty());
+    }
+}
@@ -1,11 +1,14 @@
-use crate::class_file::constant_pool::item::ConstantPoolItem;
+use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
 use crate::class_file::constant_pool::tag::ConstantPoolTag;
 use crate::util::file::read_bytes;
 
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+/// Largest value `constant_pool_count` can hold, so the highest usable index is one less
+pub const MAX_CONSTANT_POOL_COUNT: usize = u16::MAX as usize;
+
+#[derive(Debug)]
 pub struct ConstantPool {
     count: u16,
     items: Vec<ConstantPoolItem>,
@@ -38,31 +41,129 @@ impl ConstantPool {
         &self.items
     }
 
+    /// Looks up an item by its constant pool index, which starts at 1 (section 4.1)
+    pub fn get(&self, index: u16) -> Option<&ConstantPoolItem> {
+        if index == 0 {
+            return None;
+        }
+        self.items.get(index as usize - 1)
+    }
+
+    /// Appends an item after the current last entry and returns its index.
+    /// Long and Double items also reserve the following (unusable) index.
+    pub fn push_item(&mut self, item: ConstantPoolItem) -> Result<u16, io::Error> {
+        let index = self.items.len() + 1;
+        let slots = item.tag().slots() as usize;
+        if index + slots > MAX_CONSTANT_POOL_COUNT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Constant pool overflow: adding a {:?} at index {index} exceeds the maximum of {} entries",
+                    item.tag(),
+                    MAX_CONSTANT_POOL_COUNT - 1
+                ),
+            ));
+        }
+
+        self.append(item);
+        self.count = (self.items.len() + 1) as u16;
+        Ok(index as u16)
+    }
+
+    /// Puts an item in place of the one at `index`, which must take as many slots
+    pub(crate) fn replace_item(
+        &mut self,
+        index: u16,
+        item: ConstantPoolItem,
+    ) -> Result<(), io::Error> {
+        let slots = item.tag().slots();
+        match self.get(index) {
+            Some(existing) if existing.tag().slots() == slots => {
+                self.items[index as usize - 1] = item;
+                Ok(())
+            }
+            _ => Err(self.unexpected_entry(index, &format!("an entry taking {slots} slots"))),
+        }
+    }
+
+    fn append(&mut self, item: ConstantPoolItem) {
+        let slots = item.tag().slots();
+        self.items.push(item);
+        if slots == 2 {
+            self.items
+                .push(ConstantPoolItem::from_data(ConstantPoolData::Unusable));
+        }
+    }
+
+    /// Resolves a Utf8 entry to its string value
+    pub fn utf8(&self, index: u16) -> Result<String, io::Error> {
+        match self.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::Utf8(utf8)) => Ok(utf8.value()),
+            _ => Err(self.unexpected_entry(index, "Utf8")),
+        }
+    }
+
+    /// Resolves a Class entry to its internal binary name (e.g. `java/lang/Object`)
+    pub fn class_name(&self, index: u16) -> Result<String, io::Error> {
+        match self.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::ClassInfo(class_info)) => self.utf8(class_info.name_index()),
+            _ => Err(self.unexpected_entry(index, "Class")),
+        }
+    }
+
+    /// Resolves a NameAndType entry to its `(name, descriptor)` pair
+    pub fn name_and_type(&self, index: u16) -> Result<(String, String), io::Error> {
+        match self.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::NameAndType(nat)) => Ok((
+                self.utf8(nat.name_index())?,
+                self.utf8(nat.descriptor_index())?,
+            )),
+            _ => Err(self.unexpected_entry(index, "NameAndType")),
+        }
+    }
+
+    /// Resolves a FieldRef, MethodRef or InterfaceMethodRef entry to its
+    /// `(class, name, descriptor)` triple
+    pub fn member_ref(&self, index: u16) -> Result<(String, String, String), io::Error> {
+        let (class_index, name_and_type_index) = match self.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::FieldRef(r)) => (r.class_index(), r.name_and_type_index()),
+            Some(ConstantPoolData::MethodRef(r)) => (r.class_index(), r.name_and_type_index()),
+            Some(ConstantPoolData::InterfaceMethodRef(r)) => {
+                (r.class_index(), r.name_and_type_index())
+            }
+            _ => return Err(self.unexpected_entry(index, "member reference")),
+        };
+        let (name, descriptor) = self.name_and_type(name_and_type_index)?;
+        Ok((self.class_name(class_index)?, name, descriptor))
+    }
+
+    fn unexpected_entry(&self, index: u16, expected: &str) -> io::Error {
+        let found = match self.get(index) {
+            Some(item) => format!("{:?}", item.tag()),
+            None => "nothing".to_string(),
+        };
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Expected {expected} at constant pool index {index} but found {found}"),
+        )
+    }
+
+    /// Writes `constant_pool_count` followed by every entry (section 4.1)
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.count.to_be_bytes());
+        for item in self.items.iter() {
+            item.write(out);
+        }
+    }
+
     pub fn parse_item_from_class_file(
         &mut self,
         reader: &mut BufReader<impl Read>,
     ) -> Result<(), io::Error> {
         // read the tag
-        // TODO: convert this to stack buffer
-        let mut buffer = vec![0; 1];
+        let mut buffer: [u8; 1] = [0; 1];
         read_bytes(reader, &mut buffer, 1)?;
-        let tag = match buffer[0] {
-            7 => ConstantPoolTag::Class,
-            9 => ConstantPoolTag::FieldRef,
-            10 => ConstantPoolTag::MethodRef,
-            15 => ConstantPoolTag::MethodHandle,
-            16 => ConstantPoolTag::MethodType,
-            18 => ConstantPoolTag::InvokeDynamic,
-            11 => ConstantPoolTag::InterfaceMethodRef,
-            8 => ConstantPoolTag::String,
-            3 => ConstantPoolTag::Integer,
-            4 => ConstantPoolTag::Float,
-            5 => ConstantPoolTag::Long,
-            6 => ConstantPoolTag::Double,
-            12 => ConstantPoolTag::NameAndType,
-            1 => ConstantPoolTag::Utf8,
-            _ => ConstantPoolTag::Unknown,
-        };
+        let tag = ConstantPoolTag::from_u8(buffer[0]);
 
         if tag == ConstantPoolTag::Unknown {
             return Err(io::Error::new(
@@ -75,9 +176,52 @@ impl ConstantPool {
         }
 
         let mut item = ConstantPoolItem::new(tag);
-        item.parse(reader, &self)?;
+        item.parse(reader, self)?;
 
-        self.items.push(item);
+        self.append(item);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn long_takes_two_slots_when_parsed() {
+        let mut constant_pool = ConstantPool::new();
+        constant_pool.set_count(4);
+
+        // #1 Long 1, #2 unusable, #3 Utf8 "a"
+        let bytes = [
+            0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x01, 0x61,
+        ];
+        let mut reader = BufReader::new(bytes.as_ref());
+        constant_pool
+            .parse_item_from_class_file(&mut reader)
+            .unwrap();
+        constant_pool
+            .parse_item_from_class_file(&mut reader)
+            .unwrap();
+
+        assert_eq!(constant_pool.items().len(), 3);
+        assert_eq!(
+            *constant_pool.get(2).unwrap().tag(),
+            ConstantPoolTag::Unusable
+        );
+        assert_eq!(constant_pool.utf8(3).unwrap(), "a");
+    }
+
+    #[test]
+    fn resolve_wrong_entry_type_fails() {
+        let mut constant_pool = ConstantPool::new();
+        constant_pool
+            .push_item(ConstantPoolItem::from_data(ConstantPoolData::Utf8(
+                crate::class_file::constant_pool::types::utf8::Utf8::from_string("a").unwrap(),
+            )))
+            .unwrap();
+        assert!(constant_pool.class_name(1).is_err());
+        assert!(constant_pool.utf8(0).is_err());
+        assert!(constant_pool.utf8(2).is_err());
+    }
+}
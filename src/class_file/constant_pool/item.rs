@@ -14,12 +14,13 @@ use crate::class_file::constant_pool::types::method_ref::MethodRef;
 use crate::class_file::constant_pool::types::method_type::MethodType;
 use crate::class_file::constant_pool::types::name_and_type::NameAndType;
 use crate::class_file::constant_pool::types::utf8::Utf8;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum ConstantPoolData {
-    Uninit, // only for uninitialized data
+    Uninit,   // only for uninitialized data
+    Unusable, // second slot taken by a Long or Double (section 4.4.5)
     ClassInfo(ClassInfo),
     FieldRef(FieldRef),
     MethodRef(MethodRef),
@@ -36,6 +37,7 @@ pub enum ConstantPoolData {
     InvokeDynamic(InvokeDynamic),
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct ConstantPoolItem {
     tag: ConstantPoolTag,
     data: ConstantPoolData,
@@ -49,6 +51,29 @@ impl ConstantPoolItem {
         }
     }
 
+    /// Creates an already parsed item, deriving the tag from the data
+    pub fn from_data(data: ConstantPoolData) -> ConstantPoolItem {
+        let tag = match data {
+            ConstantPoolData::Uninit => ConstantPoolTag::Unknown,
+            ConstantPoolData::Unusable => ConstantPoolTag::Unusable,
+            ConstantPoolData::ClassInfo(_) => ConstantPoolTag::Class,
+            ConstantPoolData::FieldRef(_) => ConstantPoolTag::FieldRef,
+            ConstantPoolData::MethodRef(_) => ConstantPoolTag::MethodRef,
+            ConstantPoolData::InterfaceMethodRef(_) => ConstantPoolTag::InterfaceMethodRef,
+            ConstantPoolData::String(_) => ConstantPoolTag::String,
+            ConstantPoolData::Float(_) => ConstantPoolTag::Float,
+            ConstantPoolData::Integer(_) => ConstantPoolTag::Integer,
+            ConstantPoolData::Long(_) => ConstantPoolTag::Long,
+            ConstantPoolData::Double(_) => ConstantPoolTag::Double,
+            ConstantPoolData::NameAndType(_) => ConstantPoolTag::NameAndType,
+            ConstantPoolData::Utf8(_) => ConstantPoolTag::Utf8,
+            ConstantPoolData::MethodHandle(_) => ConstantPoolTag::MethodHandle,
+            ConstantPoolData::MethodType(_) => ConstantPoolTag::MethodType,
+            ConstantPoolData::InvokeDynamic(_) => ConstantPoolTag::InvokeDynamic,
+        };
+        ConstantPoolItem { tag, data }
+    }
+
     pub fn tag(&self) -> &ConstantPoolTag {
         &self.tag
     }
@@ -57,6 +82,33 @@ impl ConstantPoolItem {
         &self.data
     }
 
+    /// Writes the tag followed by the item's body in class file format.
+    /// The unusable slot following a Long or Double has no representation and writes nothing.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        if let ConstantPoolData::Uninit | ConstantPoolData::Unusable = self.data {
+            return;
+        }
+
+        out.push(self.tag as u8);
+        match &self.data {
+            ConstantPoolData::ClassInfo(data) => data.write(out),
+            ConstantPoolData::FieldRef(data) => data.write(out),
+            ConstantPoolData::MethodRef(data) => data.write(out),
+            ConstantPoolData::InterfaceMethodRef(data) => data.write(out),
+            ConstantPoolData::String(data) => data.write(out),
+            ConstantPoolData::Float(data) => data.write(out),
+            ConstantPoolData::Integer(data) => data.write(out),
+            ConstantPoolData::Long(data) => data.write(out),
+            ConstantPoolData::Double(data) => data.write(out),
+            ConstantPoolData::NameAndType(data) => data.write(out),
+            ConstantPoolData::Utf8(data) => data.write(out),
+            ConstantPoolData::MethodHandle(data) => data.write(out),
+            ConstantPoolData::MethodType(data) => data.write(out),
+            ConstantPoolData::InvokeDynamic(data) => data.write(out),
+            ConstantPoolData::Uninit | ConstantPoolData::Unusable => {}
+        }
+    }
+
     pub fn parse(
         &mut self,
         reader: &mut BufReader<impl Read>,
@@ -106,10 +158,10 @@ impl ConstantPoolItem {
             ConstantPoolTag::InvokeDynamic => {
                 self.data = ConstantPoolData::InvokeDynamic(InvokeDynamic::from(reader)?);
             }
-            _ => Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Unsupported constant pool tag {:?}", self.tag),
-            ))?,
+            _ => Err(io::Error::other(format!(
+                "Unsupported constant pool tag {:?}",
+                self.tag
+            )))?,
         }
         Ok(())
     }
@@ -1,3 +1,4 @@
+pub mod builder;
 pub mod constant_pool;
 pub mod item;
 pub mod tag;
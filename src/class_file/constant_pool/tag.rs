@@ -1,6 +1,7 @@
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum ConstantPoolTag {
-    Unknown = -1, // only for default initialization
+    Unknown = -1,  // only for default initialization
+    Unusable = -2, // second slot taken by a Long or Double (section 4.4.5)
     Class = 7,
     FieldRef = 9,
     MethodRef = 10,
@@ -16,3 +17,55 @@ pub enum ConstantPoolTag {
     MethodType = 16,
     InvokeDynamic = 18,
 }
+
+impl ConstantPoolTag {
+    pub fn from_u8(value: u8) -> ConstantPoolTag {
+        match value {
+            7 => ConstantPoolTag::Class,
+            9 => ConstantPoolTag::FieldRef,
+            10 => ConstantPoolTag::MethodRef,
+            15 => ConstantPoolTag::MethodHandle,
+            16 => ConstantPoolTag::MethodType,
+            18 => ConstantPoolTag::InvokeDynamic,
+            11 => ConstantPoolTag::InterfaceMethodRef,
+            8 => ConstantPoolTag::String,
+            3 => ConstantPoolTag::Integer,
+            4 => ConstantPoolTag::Float,
+            5 => ConstantPoolTag::Long,
+            6 => ConstantPoolTag::Double,
+            12 => ConstantPoolTag::NameAndType,
+            1 => ConstantPoolTag::Utf8,
+            _ => ConstantPoolTag::Unknown,
+        }
+    }
+
+    /// The tag's name as written in the specification (table 4.4-A), e.g. `Methodref`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConstantPoolTag::Unknown => "Unknown",
+            ConstantPoolTag::Unusable => "Unusable",
+            ConstantPoolTag::Class => "Class",
+            ConstantPoolTag::FieldRef => "Fieldref",
+            ConstantPoolTag::MethodRef => "Methodref",
+            ConstantPoolTag::InterfaceMethodRef => "InterfaceMethodref",
+            ConstantPoolTag::String => "String",
+            ConstantPoolTag::Integer => "Integer",
+            ConstantPoolTag::Float => "Float",
+            ConstantPoolTag::Long => "Long",
+            ConstantPoolTag::Double => "Double",
+            ConstantPoolTag::NameAndType => "NameAndType",
+            ConstantPoolTag::Utf8 => "Utf8",
+            ConstantPoolTag::MethodHandle => "MethodHandle",
+            ConstantPoolTag::MethodType => "MethodType",
+            ConstantPoolTag::InvokeDynamic => "InvokeDynamic",
+        }
+    }
+
+    /// Long and Double entries take up two indices in the constant pool (section 4.4.5)
+    pub fn slots(&self) -> u16 {
+        match self {
+            ConstantPoolTag::Long | ConstantPoolTag::Double => 2,
+            _ => 1,
+        }
+    }
+}
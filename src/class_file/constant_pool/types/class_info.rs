@@ -1,11 +1,10 @@
 use crate::class_file::constant_pool::constant_pool::ConstantPool;
 use crate::util::file::read_bytes;
 use log::{debug, info};
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ClassInfo {
     name_index: u16,
 }
@@ -15,6 +14,10 @@ impl ClassInfo {
         ClassInfo { name_index: 0 }
     }
 
+    pub fn from_name_index(name_index: u16) -> ClassInfo {
+        ClassInfo { name_index }
+    }
+
     pub fn name_index(&self) -> u16 {
         self.name_index
     }
@@ -45,6 +48,10 @@ impl ClassInfo {
 
         Ok(class_info)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.name_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
@@ -84,6 +91,6 @@ mod test {
         let mut reader = BufReader::new(bytes.as_ref());
 
         let class_info = ClassInfo::from(&mut reader, &constant_pool);
-        assert_eq!(class_info.is_err(), true);
+        assert!(class_info.is_err());
     }
 }
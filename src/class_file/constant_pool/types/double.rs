@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Double {
     high_bytes: u32,
     low_bytes: u32,
@@ -16,6 +16,26 @@ impl Double {
         }
     }
 
+    pub fn from_value(value: f64) -> Double {
+        let bits = value.to_bits();
+        Double {
+            high_bytes: (bits >> 32) as u32,
+            low_bytes: bits as u32,
+        }
+    }
+
+    pub fn high_bytes(&self) -> u32 {
+        self.high_bytes
+    }
+
+    pub fn low_bytes(&self) -> u32 {
+        self.low_bytes
+    }
+
+    pub fn value(&self) -> f64 {
+        f64::from_bits(((self.high_bytes as u64) << 32) | self.low_bytes as u64)
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<Double, io::Error> {
         let mut double: Double = Double::new();
 
@@ -29,4 +49,9 @@ impl Double {
 
         Ok(double)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.high_bytes.to_be_bytes());
+        out.extend_from_slice(&self.low_bytes.to_be_bytes());
+    }
 }
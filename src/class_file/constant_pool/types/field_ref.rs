@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct FieldRef {
     class_index: u16,
     name_and_type_index: u16,
@@ -24,6 +24,13 @@ impl FieldRef {
         self.name_and_type_index
     }
 
+    pub fn from_indices(class_index: u16, name_and_type_index: u16) -> FieldRef {
+        FieldRef {
+            class_index,
+            name_and_type_index,
+        }
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<FieldRef, io::Error> {
         let mut field_ref: FieldRef = FieldRef::new();
 
@@ -37,4 +44,9 @@ impl FieldRef {
 
         Ok(field_ref)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.class_index.to_be_bytes());
+        out.extend_from_slice(&self.name_and_type_index.to_be_bytes());
+    }
 }
@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Float {
     bytes: u32,
 }
@@ -12,6 +12,20 @@ impl Float {
         Float { bytes: 0 }
     }
 
+    pub fn from_value(value: f32) -> Float {
+        Float {
+            bytes: value.to_bits(),
+        }
+    }
+
+    pub fn bytes(&self) -> u32 {
+        self.bytes
+    }
+
+    pub fn value(&self) -> f32 {
+        f32::from_bits(self.bytes)
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<Float, io::Error> {
         let mut float = Float::new();
 
@@ -22,4 +36,8 @@ impl Float {
 
         Ok(float)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.bytes.to_be_bytes());
+    }
 }
@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Integer {
     bytes: u32,
 }
@@ -12,6 +12,20 @@ impl Integer {
         Integer { bytes: 0 }
     }
 
+    pub fn from_value(value: i32) -> Integer {
+        Integer {
+            bytes: value as u32,
+        }
+    }
+
+    pub fn bytes(&self) -> u32 {
+        self.bytes
+    }
+
+    pub fn value(&self) -> i32 {
+        self.bytes as i32
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<Integer, io::Error> {
         let mut integer = Integer::new();
 
@@ -22,4 +36,8 @@ impl Integer {
 
         Ok(integer)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.bytes.to_be_bytes());
+    }
 }
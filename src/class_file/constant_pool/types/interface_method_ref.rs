@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct InterfaceMethodRef {
     class_index: u16,
     name_and_type_index: u16,
@@ -16,6 +16,21 @@ impl InterfaceMethodRef {
         }
     }
 
+    pub fn class_index(&self) -> u16 {
+        self.class_index
+    }
+
+    pub fn name_and_type_index(&self) -> u16 {
+        self.name_and_type_index
+    }
+
+    pub fn from_indices(class_index: u16, name_and_type_index: u16) -> InterfaceMethodRef {
+        InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        }
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<InterfaceMethodRef, io::Error> {
         let mut interface_method_ref: InterfaceMethodRef = InterfaceMethodRef::new();
 
@@ -29,4 +44,9 @@ impl InterfaceMethodRef {
 
         Ok(interface_method_ref)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.class_index.to_be_bytes());
+        out.extend_from_slice(&self.name_and_type_index.to_be_bytes());
+    }
 }
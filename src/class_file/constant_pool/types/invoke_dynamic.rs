@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct InvokeDynamic {
     bootstrap_method_attr_index: u16,
     name_and_type_index: u16,
@@ -16,6 +16,24 @@ impl InvokeDynamic {
         }
     }
 
+    pub fn from_indices(
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    ) -> InvokeDynamic {
+        InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }
+    }
+
+    pub fn bootstrap_method_attr_index(&self) -> u16 {
+        self.bootstrap_method_attr_index
+    }
+
+    pub fn name_and_type_index(&self) -> u16 {
+        self.name_and_type_index
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<InvokeDynamic, io::Error> {
         let mut invoke_dynamic = InvokeDynamic::new();
 
@@ -29,6 +47,11 @@ impl InvokeDynamic {
 
         Ok(invoke_dynamic)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.bootstrap_method_attr_index.to_be_bytes());
+        out.extend_from_slice(&self.name_and_type_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
@@ -1,10 +1,10 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
 /// Named JvmString to avoid ambiguity with String.
 /// Represents constant objects of String type.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct JvmString {
     string_index: u16,
 }
@@ -14,6 +14,10 @@ impl JvmString {
         JvmString { string_index: 0 }
     }
 
+    pub fn from_string_index(string_index: u16) -> JvmString {
+        JvmString { string_index }
+    }
+
     pub fn string_index(&self) -> u16 {
         self.string_index
     }
@@ -27,6 +31,10 @@ impl JvmString {
 
         Ok(string)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.string_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Long {
     high_bytes: u32,
     low_bytes: u32,
@@ -16,6 +16,25 @@ impl Long {
         }
     }
 
+    pub fn from_value(value: i64) -> Long {
+        Long {
+            high_bytes: (value >> 32) as u32,
+            low_bytes: value as u32,
+        }
+    }
+
+    pub fn high_bytes(&self) -> u32 {
+        self.high_bytes
+    }
+
+    pub fn low_bytes(&self) -> u32 {
+        self.low_bytes
+    }
+
+    pub fn value(&self) -> i64 {
+        (((self.high_bytes as u64) << 32) | self.low_bytes as u64) as i64
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<Long, io::Error> {
         let mut long: Long = Long::new();
 
@@ -29,6 +48,11 @@ impl Long {
 
         Ok(long)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.high_bytes.to_be_bytes());
+        out.extend_from_slice(&self.low_bytes.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
@@ -1,10 +1,9 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
-#[derive(Eq, PartialEq)]
-enum ReferenceKind {
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+pub enum ReferenceKind {
     Uninit = -1, // only for uninitialized method handles
     GetField = 1,
     GetStatic = 2,
@@ -16,6 +15,24 @@ enum ReferenceKind {
     NewInvokeSpecial = 8,
     InvokeInterface = 9,
 }
+impl ReferenceKind {
+    pub fn from_u8(value: u8) -> ReferenceKind {
+        match value {
+            1 => ReferenceKind::GetField,
+            2 => ReferenceKind::GetStatic,
+            3 => ReferenceKind::PutField,
+            4 => ReferenceKind::PutStatic,
+            5 => ReferenceKind::InvokeVirtual,
+            6 => ReferenceKind::InvokeStatic,
+            7 => ReferenceKind::InvokeSpecial,
+            8 => ReferenceKind::NewInvokeSpecial,
+            9 => ReferenceKind::InvokeInterface,
+            _ => ReferenceKind::Uninit,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct MethodHandle {
     reference_kind: ReferenceKind,
     reference_index: u16,
@@ -29,24 +46,28 @@ impl MethodHandle {
         }
     }
 
+    pub fn from_reference(reference_kind: ReferenceKind, reference_index: u16) -> MethodHandle {
+        MethodHandle {
+            reference_kind,
+            reference_index,
+        }
+    }
+
+    pub fn reference_kind(&self) -> ReferenceKind {
+        self.reference_kind
+    }
+
+    pub fn reference_index(&self) -> u16 {
+        self.reference_index
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<MethodHandle, io::Error> {
         let mut method_handle = MethodHandle::new();
 
         // read 1 byte for reference_kind
         let mut kind_buf: [u8; 1] = [0; 1];
         read_bytes(reader, &mut kind_buf, 1)?;
-        method_handle.reference_kind = match kind_buf[0] {
-            1 => ReferenceKind::GetField,
-            2 => ReferenceKind::GetStatic,
-            3 => ReferenceKind::PutField,
-            4 => ReferenceKind::PutStatic,
-            5 => ReferenceKind::InvokeVirtual,
-            6 => ReferenceKind::InvokeStatic,
-            7 => ReferenceKind::InvokeSpecial,
-            8 => ReferenceKind::NewInvokeSpecial,
-            9 => ReferenceKind::InvokeInterface,
-            _ => ReferenceKind::Uninit,
-        };
+        method_handle.reference_kind = ReferenceKind::from_u8(kind_buf[0]);
 
         // read 2 bytes for reference_index
         let mut idx_buf: [u8; 2] = [0; 2];
@@ -55,6 +76,11 @@ impl MethodHandle {
 
         Ok(method_handle)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.reference_kind as u8);
+        out.extend_from_slice(&self.reference_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
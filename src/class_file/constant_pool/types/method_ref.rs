@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct MethodRef {
     class_index: u16,
     name_and_type_index: u16,
@@ -24,6 +24,13 @@ impl MethodRef {
         self.name_and_type_index
     }
 
+    pub fn from_indices(class_index: u16, name_and_type_index: u16) -> MethodRef {
+        MethodRef {
+            class_index,
+            name_and_type_index,
+        }
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<MethodRef, io::Error> {
         let mut method_ref: MethodRef = MethodRef::new();
 
@@ -37,6 +44,11 @@ impl MethodRef {
 
         Ok(method_ref)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.class_index.to_be_bytes());
+        out.extend_from_slice(&self.name_and_type_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
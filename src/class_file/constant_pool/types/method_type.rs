@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct MethodType {
     descriptor_index: u16,
 }
@@ -14,6 +14,14 @@ impl MethodType {
         }
     }
 
+    pub fn from_descriptor_index(descriptor_index: u16) -> MethodType {
+        MethodType { descriptor_index }
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<MethodType, io::Error> {
         let mut method_type: MethodType = MethodType::new();
 
@@ -24,6 +32,10 @@ impl MethodType {
 
         Ok(method_type)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.descriptor_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
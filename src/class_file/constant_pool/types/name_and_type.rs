@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct NameAndType {
     name_index: u16,
     descriptor_index: u16,
@@ -16,6 +16,13 @@ impl NameAndType {
         }
     }
 
+    pub fn from_indices(name_index: u16, descriptor_index: u16) -> NameAndType {
+        NameAndType {
+            name_index,
+            descriptor_index,
+        }
+    }
+
     pub fn name_index(&self) -> u16 {
         self.name_index
     }
@@ -36,6 +43,11 @@ impl NameAndType {
 
         Ok(name_and_type)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.name_index.to_be_bytes());
+        out.extend_from_slice(&self.descriptor_index.to_be_bytes());
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,8 @@
 use crate::util::file::read_bytes;
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Utf8 {
     length: u16,
     bytes: Vec<u8>,
@@ -24,10 +24,70 @@ impl Utf8 {
         &self.bytes
     }
 
-    /// Returns the UTF-8 decoded string value. For the current use-cases,
-    /// class file Modified UTF-8 specifics don't affect these literals.
+    /// Wraps already encoded bytes as they'd appear in a class file, valid or not. Fails if
+    /// there are more than the 65535 bytes the length can count.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Utf8, io::Error> {
+        let length = u16::try_from(bytes.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Utf8 constant of {} bytes is longer than the maximum of {}",
+                    bytes.len(),
+                    u16::MAX
+                ),
+            )
+        })?;
+        Ok(Utf8 { length, bytes })
+    }
+
+    /// Encodes a string using the class file's modified UTF-8 (section 4.4.7):
+    /// the null character takes two bytes and supplementary characters are
+    /// written as a surrogate pair of three byte sequences. Fails if the encoding is longer
+    /// than 65535 bytes.
+    pub fn from_string(value: &str) -> Result<Utf8, io::Error> {
+        let mut bytes = Vec::with_capacity(value.len());
+        for unit in value.encode_utf16() {
+            match unit {
+                0x0001..=0x007F => bytes.push(unit as u8),
+                0x0000 | 0x0080..=0x07FF => {
+                    bytes.push(0xC0 | (unit >> 6) as u8);
+                    bytes.push(0x80 | (unit & 0x3F) as u8);
+                }
+                _ => {
+                    bytes.push(0xE0 | (unit >> 12) as u8);
+                    bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                    bytes.push(0x80 | (unit & 0x3F) as u8);
+                }
+            }
+        }
+        Utf8::from_bytes(bytes)
+    }
+
+    /// Returns the decoded string value, undoing the modified UTF-8 encoding.
+    /// Malformed sequences and unpaired surrogates are replaced with U+FFFD.
     pub fn value(&self) -> String {
-        String::from_utf8_lossy(&self.bytes).to_string()
+        let mut units: Vec<u16> = Vec::with_capacity(self.bytes.len());
+        let mut i = 0;
+        while i < self.bytes.len() {
+            let x = self.bytes[i] as u16;
+            let y = self.bytes.get(i + 1).map(|b| *b as u16).unwrap_or(0);
+            let z = self.bytes.get(i + 2).map(|b| *b as u16).unwrap_or(0);
+            if x & 0x80 == 0 {
+                units.push(x);
+                i += 1;
+            } else if x & 0xE0 == 0xC0 && y & 0xC0 == 0x80 {
+                units.push(((x & 0x1F) << 6) | (y & 0x3F));
+                i += 2;
+            } else if x & 0xF0 == 0xE0 && y & 0xC0 == 0x80 && z & 0xC0 == 0x80 {
+                units.push(((x & 0x0F) << 12) | ((y & 0x3F) << 6) | (z & 0x3F));
+                i += 3;
+            } else {
+                units.push(0xFFFD);
+                i += 1;
+            }
+        }
+
+        String::from_utf16_lossy(&units)
     }
 
     pub fn from(reader: &mut BufReader<impl Read>) -> Result<Utf8, io::Error> {
@@ -54,6 +114,11 @@ impl Utf8 {
 
         Ok(utf8)
     }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.length.to_be_bytes());
+        out.extend_from_slice(&self.bytes);
+    }
 }
 
 #[cfg(test)]
@@ -91,6 +156,30 @@ mod test {
         assert_eq!(utf8.bytes(), &[0xEE, 0xEF, 0x7F]);
     }
 
+    #[test]
+    fn modified_utf8_round_trip() {
+        // null is written as two bytes and supplementary characters as surrogate pairs
+        let utf8 = Utf8::from_string("a\u{0}\u{e9}\u{1F600}").unwrap();
+        assert_eq!(
+            utf8.bytes(),
+            &[0x61, 0xC0, 0x80, 0xC3, 0xA9, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]
+        );
+        assert_eq!(utf8.length(), 11);
+        assert_eq!(utf8.value(), "a\u{0}\u{e9}\u{1F600}");
+    }
+
+    #[test]
+    fn rejects_strings_over_the_length_limit() {
+        assert_eq!(
+            Utf8::from_string(&"x".repeat(65535)).unwrap().length(),
+            65535
+        );
+        // the encoded length counts, not the number of characters
+        let error = Utf8::from_string(&"\u{e9}".repeat(32768)).unwrap_err();
+        assert!(error.to_string().contains("65536 bytes"), "{error}");
+        assert!(Utf8::from_bytes(vec![b'x'; 70000]).is_err());
+    }
+
     #[test]
     fn invalid_zero_byte_fails() {
         // length = 1, byte = 0x00 is invalid per check
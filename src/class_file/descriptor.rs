@@ -0,0 +1,241 @@
+use std::io;
+
+/// A field type as written in a field or method descriptor (section 4.3.2)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// Internal binary class name, e.g. `java/lang/String`
+    Object(String),
+    Array(Box<FieldType>),
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> Result<FieldType, io::Error> {
+        let (field_type, rest) = FieldType::parse_prefix(descriptor)?;
+        if !rest.is_empty() {
+            return Err(invalid_descriptor(descriptor));
+        }
+        Ok(field_type)
+    }
+
+    /// Parses one field type from the start of `descriptor` and returns the remainder
+    fn parse_prefix(descriptor: &str) -> Result<(FieldType, &str), io::Error> {
+        // 4.3.2: an array type may have at most 255 dimensions
+        let dimensions = descriptor.bytes().take_while(|b| *b == b'[').count();
+        if dimensions > 255 {
+            return Err(invalid_descriptor(descriptor));
+        }
+        if dimensions > 0 {
+            let (mut field_type, rest) = FieldType::parse_prefix(&descriptor[dimensions..])?;
+            for _ in 0..dimensions {
+                field_type = FieldType::Array(Box::new(field_type));
+            }
+            return Ok((field_type, rest));
+        }
+
+        let mut chars = descriptor.chars();
+        let field_type = match chars.next() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            Some('L') => {
+                let end = descriptor
+                    .find(';')
+                    .ok_or_else(|| invalid_descriptor(descriptor))?;
+                let name = &descriptor[1..end];
+                if name.is_empty() {
+                    return Err(invalid_descriptor(descriptor));
+                }
+                return Ok((FieldType::Object(name.to_string()), &descriptor[end + 1..]));
+            }
+            _ => return Err(invalid_descriptor(descriptor)),
+        };
+        Ok((field_type, &descriptor[1..]))
+    }
+
+    /// Number of local variable / operand stack slots a value of this type takes
+    pub fn slots(&self) -> u16 {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    pub fn descriptor(&self) -> String {
+        match self {
+            FieldType::Byte => "B".to_string(),
+            FieldType::Char => "C".to_string(),
+            FieldType::Double => "D".to_string(),
+            FieldType::Float => "F".to_string(),
+            FieldType::Int => "I".to_string(),
+            FieldType::Long => "J".to_string(),
+            FieldType::Short => "S".to_string(),
+            FieldType::Boolean => "Z".to_string(),
+            FieldType::Object(name) => format!("L{name};"),
+            FieldType::Array(component) => format!("[{}", component.descriptor()),
+        }
+    }
+
+    /// The type as it would be written in Java source, e.g. `java.lang.String[]`
+    pub fn java_name(&self) -> String {
+        match self {
+            FieldType::Byte => "byte".to_string(),
+            FieldType::Char => "char".to_string(),
+            FieldType::Double => "double".to_string(),
+            FieldType::Float => "float".to_string(),
+            FieldType::Int => "int".to_string(),
+            FieldType::Long => "long".to_string(),
+            FieldType::Short => "short".to_string(),
+            FieldType::Boolean => "boolean".to_string(),
+            FieldType::Object(name) => name.replace('/', "."),
+            FieldType::Array(component) => format!("{}[]", component.java_name()),
+        }
+    }
+}
+
+/// A method descriptor (section 4.3.3); a `None` return type means `void`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    parameters: Vec<FieldType>,
+    return_type: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+    pub fn new(parameters: Vec<FieldType>, return_type: Option<FieldType>) -> MethodDescriptor {
+        MethodDescriptor {
+            parameters,
+            return_type,
+        }
+    }
+
+    pub fn parse(descriptor: &str) -> Result<MethodDescriptor, io::Error> {
+        let mut rest = descriptor
+            .strip_prefix('(')
+            .ok_or_else(|| invalid_descriptor(descriptor))?;
+
+        let mut parameters = Vec::new();
+        while !rest.starts_with(')') {
+            if rest.is_empty() {
+                return Err(invalid_descriptor(descriptor));
+            }
+            let (parameter, remainder) = FieldType::parse_prefix(rest)?;
+            parameters.push(parameter);
+            rest = remainder;
+        }
+
+        let return_type = match &rest[1..] {
+            "V" => None,
+            other => Some(FieldType::parse(other).map_err(|_| invalid_descriptor(descriptor))?),
+        };
+
+        Ok(MethodDescriptor {
+            parameters,
+            return_type,
+        })
+    }
+
+    pub fn parameters(&self) -> &[FieldType] {
+        &self.parameters
+    }
+
+    pub fn return_type(&self) -> Option<&FieldType> {
+        self.return_type.as_ref()
+    }
+
+    /// Number of local variable slots taken by the parameters, not counting `this`
+    pub fn parameter_slots(&self) -> u16 {
+        self.parameters.iter().map(|p| p.slots()).sum()
+    }
+
+    pub fn descriptor(&self) -> String {
+        let mut descriptor = String::from("(");
+        for parameter in self.parameters.iter() {
+            descriptor.push_str(&parameter.descriptor());
+        }
+        descriptor.push(')');
+        match &self.return_type {
+            Some(return_type) => descriptor.push_str(&return_type.descriptor()),
+            None => descriptor.push('V'),
+        }
+        descriptor
+    }
+}
+
+fn invalid_descriptor(descriptor: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid descriptor '{descriptor}'"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_field_types() {
+        assert_eq!(FieldType::parse("I").unwrap(), FieldType::Int);
+        assert_eq!(
+            FieldType::parse("[[Ljava/lang/String;").unwrap(),
+            FieldType::Array(Box::new(FieldType::Array(Box::new(FieldType::Object(
+                "java/lang/String".to_string()
+            )))))
+        );
+        assert_eq!(
+            FieldType::parse("[J").unwrap().java_name(),
+            "long[]".to_string()
+        );
+    }
+
+    #[test]
+    fn parse_invalid_field_types() {
+        assert!(FieldType::parse("").is_err());
+        assert!(FieldType::parse("V").is_err());
+        assert!(FieldType::parse("Ljava/lang/String").is_err());
+        assert!(FieldType::parse("L;").is_err());
+        assert!(FieldType::parse("II").is_err());
+        assert!(FieldType::parse(&format!("{}I", "[".repeat(255))).is_ok());
+        assert!(FieldType::parse(&format!("{}I", "[".repeat(256))).is_err());
+    }
+
+    #[test]
+    fn parse_method_descriptor() {
+        let descriptor =
+            MethodDescriptor::parse("(IDLjava/lang/Thread;[J)Ljava/lang/Object;").unwrap();
+        assert_eq!(descriptor.parameters().len(), 4);
+        assert_eq!(descriptor.parameter_slots(), 5);
+        assert_eq!(
+            descriptor.return_type(),
+            Some(&FieldType::Object("java/lang/Object".to_string()))
+        );
+        assert_eq!(
+            descriptor.descriptor(),
+            "(IDLjava/lang/Thread;[J)Ljava/lang/Object;"
+        );
+        assert_eq!(MethodDescriptor::parse("()V").unwrap().return_type(), None);
+    }
+
+    #[test]
+    fn parse_invalid_method_descriptors() {
+        assert!(MethodDescriptor::parse("V").is_err());
+        assert!(MethodDescriptor::parse("(I").is_err());
+        assert!(MethodDescriptor::parse("(I)").is_err());
+        assert!(MethodDescriptor::parse("(V)V").is_err());
+    }
+}
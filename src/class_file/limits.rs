@@ -0,0 +1,526 @@
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::util::file::{read_u16, read_u8};
+use std::io;
+use std::io::{BufReader, Read};
+
+/// Bounds on the memory and work that parsing a class file may take, so that hostile input is
+/// rejected with an error instead of exhausting memory or the stack. The defaults accept
+/// anything javac produces; the format itself already caps the constant pool at 65535 slots.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    max_file_size: usize,
+    max_constant_pool_count: u16,
+    max_attribute_length: u32,
+    max_nesting: usize,
+}
+
+impl ParseLimits {
+    pub const DEFAULT_MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+    /// How deeply attributes (a Code attribute's own attributes), annotation element values and
+    /// generic signatures may nest
+    pub const DEFAULT_MAX_NESTING: usize = 64;
+
+    pub fn new() -> ParseLimits {
+        ParseLimits {
+            max_file_size: ParseLimits::DEFAULT_MAX_FILE_SIZE,
+            max_constant_pool_count: u16::MAX,
+            max_attribute_length: ParseLimits::DEFAULT_MAX_FILE_SIZE as u32,
+            max_nesting: ParseLimits::DEFAULT_MAX_NESTING,
+        }
+    }
+
+    pub fn max_file_size(&self) -> usize {
+        self.max_file_size
+    }
+
+    pub fn set_max_file_size(&mut self, max_file_size: usize) {
+        self.max_file_size = max_file_size;
+    }
+
+    pub fn max_constant_pool_count(&self) -> u16 {
+        self.max_constant_pool_count
+    }
+
+    pub fn set_max_constant_pool_count(&mut self, max_constant_pool_count: u16) {
+        self.max_constant_pool_count = max_constant_pool_count;
+    }
+
+    /// An attribute may also never be longer than the input left after its header
+    pub fn max_attribute_length(&self) -> u32 {
+        self.max_attribute_length
+    }
+
+    pub fn set_max_attribute_length(&mut self, max_attribute_length: u32) {
+        self.max_attribute_length = max_attribute_length;
+    }
+
+    pub fn max_nesting(&self) -> usize {
+        self.max_nesting
+    }
+
+    pub fn set_max_nesting(&mut self, max_nesting: usize) {
+        self.max_nesting = max_nesting;
+    }
+
+    pub(crate) fn check_file_size(&self, size: usize) -> Result<(), io::Error> {
+        if size > self.max_file_size {
+            return Err(limit_error(format!(
+                "Class file is larger than the limit of {} bytes",
+                self.max_file_size
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_constant_pool_count(&self, count: u16) -> Result<(), io::Error> {
+        if count > self.max_constant_pool_count {
+            return Err(limit_error(format!(
+                "constant_pool_count {count} is over the limit of {}",
+                self.max_constant_pool_count
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks the attribute lengths and nesting of a parsed class. Attributes are only parsed
+    /// when something asks for them, so structures that are malformed rather than too large or
+    /// too deep are left for that reader to report.
+    pub(crate) fn check_class(&self, class_file: &ClassFile) -> Result<(), io::Error> {
+        let constant_pool = class_file.constant_pool();
+        for member in class_file.fields().iter().chain(class_file.methods()) {
+            self.check_attributes(member.attributes(), constant_pool, 1)?;
+        }
+        self.check_attributes(class_file.attributes(), constant_pool, 1)
+    }
+
+    pub(crate) fn check_attributes(
+        &self,
+        attributes: &[AttributeInfo],
+        constant_pool: &ConstantPool,
+        depth: usize,
+    ) -> Result<(), io::Error> {
+        if depth > self.max_nesting {
+            return Err(self.nesting_error());
+        }
+        for attribute in attributes {
+            if attribute.info().len() > self.max_attribute_length as usize {
+                return Err(limit_error(format!(
+                    "Attribute of {} bytes is over the limit of {}",
+                    attribute.info().len(),
+                    self.max_attribute_length
+                )));
+            }
+            let Ok(name) = attribute.name(constant_pool) else {
+                continue;
+            };
+            let reader = &mut attribute.reader();
+            let deepest = match name.as_str() {
+                Code::NAME => {
+                    if let Ok(code) = Code::from(reader) {
+                        self.check_attributes(code.attributes(), constant_pool, depth + 1)?;
+                    }
+                    continue;
+                }
+                "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                    self.annotations_depth(reader, depth, false)
+                }
+                "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                    read_u8(reader).and_then(|parameters| {
+                        let mut deepest = depth;
+                        for _ in 0..parameters {
+                            deepest = deepest.max(self.annotations_depth(reader, depth, false)?);
+                        }
+                        Ok(deepest)
+                    })
+                }
+                "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                    self.annotations_depth(reader, depth, true)
+                }
+                "AnnotationDefault" => self.element_value_depth(reader, depth + 1),
+                _ => continue,
+            };
+            if matches!(deepest, Ok(deepest) if deepest > self.max_nesting) {
+                return Err(self.nesting_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// The deepest element value in a `u2` count of annotations (section 4.7.16) or type
+    /// annotations (section 4.7.20)
+    fn annotations_depth(
+        &self,
+        reader: &mut BufReader<impl Read>,
+        depth: usize,
+        type_annotations: bool,
+    ) -> Result<usize, io::Error> {
+        let mut deepest = depth;
+        for _ in 0..read_u16(reader)? {
+            if type_annotations {
+                skip_type_annotation_target(reader)?;
+            }
+            deepest = deepest.max(self.annotation_depth(reader, depth)?);
+            if deepest > self.max_nesting {
+                break;
+            }
+        }
+        Ok(deepest)
+    }
+
+    fn annotation_depth(
+        &self,
+        reader: &mut BufReader<impl Read>,
+        depth: usize,
+    ) -> Result<usize, io::Error> {
+        read_u16(reader)?; // type_index
+        let mut deepest = depth;
+        for _ in 0..read_u16(reader)? {
+            read_u16(reader)?; // element_name_index
+            deepest = deepest.max(self.element_value_depth(reader, depth + 1)?);
+            if deepest > self.max_nesting {
+                break;
+            }
+        }
+        Ok(deepest)
+    }
+
+    /// Stops descending as soon as the limit is passed so hostile input can't exhaust the stack
+    fn element_value_depth(
+        &self,
+        reader: &mut BufReader<impl Read>,
+        depth: usize,
+    ) -> Result<usize, io::Error> {
+        if depth > self.max_nesting {
+            return Ok(depth);
+        }
+        match read_u8(reader)? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' | b'c' => {
+                read_u16(reader)?;
+                Ok(depth)
+            }
+            b'e' => {
+                read_u16(reader)?; // type_name_index
+                read_u16(reader)?; // const_name_index
+                Ok(depth)
+            }
+            b'@' => self.annotation_depth(reader, depth),
+            b'[' => {
+                let mut deepest = depth;
+                for _ in 0..read_u16(reader)? {
+                    deepest = deepest.max(self.element_value_depth(reader, depth + 1)?);
+                    if deepest > self.max_nesting {
+                        break;
+                    }
+                }
+                Ok(deepest)
+            }
+            tag => Err(limit_error(format!(
+                "Unknown annotation element value tag {tag}"
+            ))),
+        }
+    }
+
+    fn nesting_error(&self) -> io::Error {
+        limit_error(format!(
+            "Attributes are nested more than {} deep",
+            self.max_nesting
+        ))
+    }
+}
+
+/// Skips the `target_info` and `type_path` of a `type_annotation` (section 4.7.20)
+pub(crate) fn skip_type_annotation_target(
+    reader: &mut BufReader<impl Read>,
+) -> Result<(), io::Error> {
+    read_type_annotation_target(reader).map(|_| ())
+}
+
+/// Reads the `target_type`, `target_info` and `type_path` of a `type_annotation` (section
+/// 4.7.20) as raw bytes. They only hold offsets and indices into tables, never constant pool
+/// indices, so callers can skip or copy them unchanged.
+pub(crate) fn read_type_annotation_target(
+    reader: &mut BufReader<impl Read>,
+) -> Result<Vec<u8>, io::Error> {
+    let target_type = read_u8(reader)?;
+    let mut bytes = vec![target_type];
+    let length = match target_type {
+        0x00 | 0x01 | 0x16 => 1,
+        0x10..=0x12 | 0x17 | 0x42..=0x46 => 2,
+        0x13..=0x15 => 0,
+        0x40 | 0x41 => {
+            let table_length = read_u16(reader)?;
+            bytes.extend_from_slice(&table_length.to_be_bytes());
+            6 * table_length as usize
+        }
+        0x47..=0x4B => 3,
+        _ => {
+            return Err(limit_error(format!(
+                "Unknown type annotation target type {target_type:#04x}"
+            )))
+        }
+    };
+    read_into(reader, &mut bytes, length)?;
+    let path_length = read_u8(reader)?;
+    bytes.push(path_length);
+    read_into(reader, &mut bytes, 2 * path_length as usize)?;
+    Ok(bytes)
+}
+
+fn read_into(
+    reader: &mut BufReader<impl Read>,
+    bytes: &mut Vec<u8>,
+    length: usize,
+) -> Result<(), io::Error> {
+    let start = bytes.len();
+    bytes.resize(start + length, 0);
+    reader.read_exact(&mut bytes[start..])
+}
+
+fn limit_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::dependencies::class_dependencies;
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+    use crate::tools::javap::{javap, JavapOptions};
+    use crate::tools::json::to_json;
+    use std::panic;
+    use std::path::Path;
+
+    const FUZZ_ITERATIONS: usize = 20_000;
+
+    /// xorshift64, so every run mutates the same way and a failure names its iteration
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn seeds() -> Vec<Vec<u8>> {
+        fn visit(dir: &Path, seeds: &mut Vec<Vec<u8>>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    visit(&path, seeds);
+                } else if path
+                    .extension()
+                    .is_some_and(|extension| extension == "class")
+                {
+                    seeds.push(std::fs::read(path).unwrap());
+                }
+            }
+        }
+        let mut seeds = Vec::new();
+        visit(Path::new("tests/java"), &mut seeds);
+        let source = "
+.class public Seed
+.super java/lang/Object
+.method public static pick(I)J
+  .limit stack 4
+  .limit locals 2
+  .catch java/lang/RuntimeException from Start to End using Handler
+Start:
+  iload_0
+  tableswitch 0
+    One
+    default : Other
+One:
+  ldc2_w 1234567890123
+  lreturn
+Other:
+  iload_0
+  lookupswitch
+    5 : One
+    default : End
+End:
+  lconst_0
+  lreturn
+Handler:
+  pop
+  ldc2_w 2.5
+  d2l
+  lreturn
+.end method
+";
+        seeds.push(assemble(source).unwrap().to_bytes());
+        seeds
+    }
+
+    fn mutate(rng: &mut Rng, bytes: &mut Vec<u8>) {
+        const INTERESTING: [u32; 7] = [0, 1, 0x7F, 0x80, 0xFFFF, 0x7FFF_FFFF, 0xFFFF_FFFF];
+        for _ in 0..1 + rng.below(4) {
+            if bytes.is_empty() {
+                return;
+            }
+            let at = rng.below(bytes.len());
+            match rng.below(6) {
+                0 => bytes[at] ^= 1 << rng.below(8),
+                1 => bytes[at] = INTERESTING[rng.below(INTERESTING.len())] as u8,
+                2 => {
+                    let value = INTERESTING[rng.below(INTERESTING.len())].to_be_bytes();
+                    let width = [2, 4][rng.below(2)];
+                    for (offset, byte) in value[4 - width..].iter().enumerate() {
+                        if let Some(slot) = bytes.get_mut(at + offset) {
+                            *slot = *byte;
+                        }
+                    }
+                }
+                3 => bytes.truncate(at),
+                4 => {
+                    let end = at + rng.below(bytes.len() - at + 1);
+                    let chunk = bytes[at..end].to_vec();
+                    let to = rng.below(bytes.len());
+                    bytes.splice(to..to, chunk);
+                }
+                _ => {
+                    let end = at + rng.below(bytes.len() - at + 1);
+                    bytes.drain(at..end);
+                }
+            }
+        }
+    }
+
+    /// Parses the class and everything that is read from it on demand
+    fn exercise(bytes: &[u8]) {
+        let mut class_file = ClassFile::new();
+        if class_file.read_slice(bytes).is_err() {
+            return;
+        }
+        let constant_pool = class_file.constant_pool();
+        for method in class_file.methods() {
+            if let Ok(Some(code)) = method.code(constant_pool) {
+                for instruction in code.instructions() {
+                    if instruction.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = javap(&class_file, JavapOptions::new(true, true));
+        let _ = class_dependencies(&class_file);
+        let _ = to_json(&class_file);
+        let _ = class_file.to_bytes();
+    }
+
+    fn read(bytes: &[u8], limits: &ParseLimits) -> Result<(), io::Error> {
+        ClassFile::new().read_slice_with_limits(bytes, limits)
+    }
+
+    #[test]
+    fn rejects_input_over_the_limits() {
+        let bytes = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        assert!(read(&bytes, &ParseLimits::new()).is_ok());
+
+        let mut limits = ParseLimits::new();
+        limits.set_max_file_size(bytes.len() - 1);
+        assert!(read(&bytes, &limits).is_err());
+
+        // HelloWorld's constant_pool_count is 29
+        let mut limits = ParseLimits::new();
+        limits.set_max_constant_pool_count(28);
+        assert!(read(&bytes, &limits).is_err());
+
+        let mut limits = ParseLimits::new();
+        limits.set_max_attribute_length(16);
+        assert!(read(&bytes, &limits).is_err());
+    }
+
+    #[test]
+    fn rejects_deeply_nested_annotations() {
+        // @A(value = {{{...{"value"}...}}}) nested 100 arrays deep
+        let mut class_file = assemble(".class public A\n.super java/lang/Object\n").unwrap();
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        let name = builder.add_utf8("RuntimeVisibleAnnotations").unwrap();
+        let annotation_type = builder.add_utf8("LA;").unwrap();
+        let value = builder.add_utf8("value").unwrap();
+        class_file.set_constant_pool(builder.build());
+        let mut info = vec![0, 1];
+        info.extend(annotation_type.to_be_bytes());
+        info.extend([0, 1]);
+        info.extend(value.to_be_bytes());
+        for _ in 0..100 {
+            info.extend([b'[', 0, 1]);
+        }
+        info.push(b's');
+        info.extend(value.to_be_bytes());
+        class_file
+            .attributes_mut()
+            .push(AttributeInfo::new(name, info));
+        let bytes = class_file.to_bytes();
+
+        assert!(read(&bytes, &ParseLimits::new()).is_err());
+        let mut limits = ParseLimits::new();
+        limits.set_max_nesting(200);
+        assert!(read(&bytes, &limits).is_ok());
+    }
+
+    #[test]
+    fn rejects_code_nested_in_code() {
+        let mut class_file = assemble(
+            ".class public A
+.super java/lang/Object
+.method public static run()V
+  return
+.end method
+",
+        )
+        .unwrap();
+        let code_name = class_file.methods()[0].attributes()[0].attribute_name_index();
+        // max_stack, max_locals, code_length and `return`, then an empty exception table
+        let header = [0, 1, 0, 1, 0, 0, 0, 1, 0xB1, 0, 0];
+        let mut info = [header.as_slice(), &[0, 0]].concat();
+        for _ in 0..ParseLimits::DEFAULT_MAX_NESTING {
+            let mut outer = [header.as_slice(), &[0, 1]].concat();
+            AttributeInfo::new(code_name, info).write(&mut outer);
+            info = outer;
+        }
+        class_file.methods_mut()[0].attributes_mut()[0] = AttributeInfo::new(code_name, info);
+
+        // the method's attributes plus those of each of the 65 Code attributes
+        let bytes = class_file.to_bytes();
+        assert!(read(&bytes, &ParseLimits::new()).is_err());
+        let mut limits = ParseLimits::new();
+        limits.set_max_nesting(ParseLimits::DEFAULT_MAX_NESTING + 2);
+        assert!(read(&bytes, &limits).is_ok());
+    }
+
+    #[test]
+    fn reads_type_annotation_targets() {
+        // a localvar_target with one entry, a one-step type_path, then the annotation's type index
+        let bytes = [0x40, 0, 1, 0, 2, 0, 3, 0, 4, 1, 3, 0, 0, 9];
+        let mut reader = BufReader::new(&bytes[..]);
+        assert_eq!(
+            read_type_annotation_target(&mut reader).unwrap(),
+            &bytes[..bytes.len() - 2]
+        );
+        assert_eq!(read_u16(&mut reader).unwrap(), 9);
+
+        let error = read_type_annotation_target(&mut BufReader::new(&[0x30, 0][..])).unwrap_err();
+        assert!(error.to_string().contains("target type 0x30"));
+    }
+
+    #[test]
+    fn fuzz_mutated_classes_never_panic() {
+        let seeds = seeds();
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for iteration in 0..FUZZ_ITERATIONS {
+            let mut bytes = seeds[rng.below(seeds.len())].clone();
+            mutate(&mut rng, &mut bytes);
+            let result = panic::catch_unwind(|| exercise(&bytes));
+            assert!(result.is_ok(), "panicked on fuzz iteration {iteration}");
+        }
+    }
+}
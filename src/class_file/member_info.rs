@@ -0,0 +1,116 @@
+use crate::class_file::attribute::attribute_info::{
+    find_attribute, read_attributes, write_attributes, AttributeInfo,
+};
+use crate::class_file::attribute::code::Code;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::util::file::read_u16;
+use std::io;
+use std::io::{BufReader, Read};
+
+/// A field_info (section 4.5) or method_info (section 4.6) structure; both share the same layout
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberInfo {
+    access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<AttributeInfo>,
+}
+
+pub type FieldInfo = MemberInfo;
+pub type MethodInfo = MemberInfo;
+
+impl MemberInfo {
+    pub fn new(
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+        attributes: Vec<AttributeInfo>,
+    ) -> MemberInfo {
+        MemberInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        }
+    }
+
+    pub fn access_flags(&self) -> u16 {
+        self.access_flags
+    }
+
+    pub fn set_access_flags(&mut self, access_flags: u16) {
+        self.access_flags = access_flags;
+    }
+
+    pub fn name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut Vec<AttributeInfo> {
+        &mut self.attributes
+    }
+
+    pub fn name(&self, constant_pool: &ConstantPool) -> Result<String, io::Error> {
+        constant_pool.utf8(self.name_index)
+    }
+
+    pub fn descriptor(&self, constant_pool: &ConstantPool) -> Result<String, io::Error> {
+        constant_pool.utf8(self.descriptor_index)
+    }
+
+    /// Parses the method's Code attribute, if it has one (abstract and native methods don't)
+    pub fn code(&self, constant_pool: &ConstantPool) -> Result<Option<Code>, io::Error> {
+        match find_attribute(&self.attributes, constant_pool, Code::NAME) {
+            Some(attribute) => Ok(Some(Code::from(&mut attribute.reader())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn from(reader: &mut BufReader<impl Read>) -> Result<MemberInfo, io::Error> {
+        Ok(MemberInfo {
+            access_flags: read_u16(reader)?,
+            name_index: read_u16(reader)?,
+            descriptor_index: read_u16(reader)?,
+            attributes: read_attributes(reader)?,
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.access_flags.to_be_bytes());
+        out.extend_from_slice(&self.name_index.to_be_bytes());
+        out.extend_from_slice(&self.descriptor_index.to_be_bytes());
+        write_attributes(&self.attributes, out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_and_write_round_trip() {
+        // public static, name #5, descriptor #6, one attribute #7 with 2 bytes
+        let bytes = [
+            0x00, 0x09, 0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x07, 0x00, 0x00, 0x00, 0x02,
+            0x00, 0x08,
+        ];
+        let mut reader = BufReader::new(bytes.as_ref());
+        let member = MemberInfo::from(&mut reader).unwrap();
+        assert_eq!(member.access_flags(), 0x0009);
+        assert_eq!(member.name_index(), 5);
+        assert_eq!(member.descriptor_index(), 6);
+        assert_eq!(member.attributes().len(), 1);
+
+        let mut out = Vec::new();
+        member.write(&mut out);
+        assert_eq!(out, bytes);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod access_flags;
+pub mod attribute;
 pub mod class_file;
 pub mod constant_pool;
+pub mod descriptor;
+pub mod limits;
+pub mod member_info;
+pub mod signature;
+pub mod visitor;
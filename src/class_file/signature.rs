@@ -0,0 +1,538 @@
+use crate::class_file::limits::ParseLimits;
+use std::fmt;
+use std::io;
+
+// Generic signatures (section 4.7.9.1) as a tree. Field and method descriptors are a subset of
+// the grammar, so they parse too, which lets tools that rename or list classes treat both the
+// same way. Writing a parsed signature back gives the text it was parsed from.
+
+/// A class, method or field signature, or a descriptor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    type_parameters: Vec<TypeParameter>,
+    parameters: Option<Vec<TypeSignature>>,
+    types: Vec<TypeSignature>,
+    thrown: Vec<TypeSignature>,
+}
+
+impl Signature {
+    pub fn parse(signature: &str) -> Result<Signature, io::Error> {
+        let mut parser = Parser { signature, i: 0 };
+        parser
+            .signature()
+            .filter(|_| parser.i == signature.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Malformed signature {signature}"),
+                )
+            })
+    }
+
+    pub fn type_parameters(&self) -> &[TypeParameter] {
+        &self.type_parameters
+    }
+
+    /// The parameter types of a method, or `None` for a class or field
+    pub fn parameters(&self) -> Option<&[TypeSignature]> {
+        self.parameters.as_deref()
+    }
+
+    /// The return type of a method, the type of a field, or a class's superclass followed
+    /// by its interfaces
+    pub fn types(&self) -> &[TypeSignature] {
+        &self.types
+    }
+
+    /// The exceptions a method throws
+    pub fn thrown(&self) -> &[TypeSignature] {
+        &self.thrown
+    }
+
+    /// Calls `f` for every class type, including those in type arguments and bounds
+    pub fn for_each_class_type(&self, f: &mut dyn FnMut(&ClassTypeSignature)) {
+        let bounds = self.type_parameters.iter().flat_map(|parameter| {
+            parameter
+                .class_bound
+                .iter()
+                .chain(parameter.interface_bounds.iter())
+        });
+        for type_signature in bounds
+            .chain(self.parameters.iter().flatten())
+            .chain(self.types.iter())
+            .chain(self.thrown.iter())
+        {
+            type_signature.for_each_class_type(f);
+        }
+    }
+
+    /// Calls `f` for every class type, which it may change
+    pub fn for_each_class_type_mut(&mut self, f: &mut dyn FnMut(&mut ClassTypeSignature)) {
+        let bounds = self.type_parameters.iter_mut().flat_map(|parameter| {
+            parameter
+                .class_bound
+                .iter_mut()
+                .chain(parameter.interface_bounds.iter_mut())
+        });
+        for type_signature in bounds
+            .chain(self.parameters.iter_mut().flatten())
+            .chain(self.types.iter_mut())
+            .chain(self.thrown.iter_mut())
+        {
+            type_signature.for_each_class_type_mut(f);
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.type_parameters.is_empty() {
+            f.write_str("<")?;
+            for parameter in self.type_parameters.iter() {
+                write!(f, "{}:", parameter.name)?;
+                if let Some(bound) = parameter.class_bound.as_ref() {
+                    write!(f, "{bound}")?;
+                }
+                for bound in parameter.interface_bounds.iter() {
+                    write!(f, ":{bound}")?;
+                }
+            }
+            f.write_str(">")?;
+        }
+        if let Some(parameters) = self.parameters.as_ref() {
+            f.write_str("(")?;
+            for parameter in parameters {
+                write!(f, "{parameter}")?;
+            }
+            f.write_str(")")?;
+        }
+        for type_signature in self.types.iter() {
+            write!(f, "{type_signature}")?;
+        }
+        for thrown in self.thrown.iter() {
+            write!(f, "^{thrown}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `T:Ljava/lang/Object;:Ljava/lang/Comparable<TT;>;`. The class bound is missing when only
+/// interface bounds are given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeParameter {
+    name: String,
+    class_bound: Option<TypeSignature>,
+    interface_bounds: Vec<TypeSignature>,
+}
+
+impl TypeParameter {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn class_bound(&self) -> Option<&TypeSignature> {
+        self.class_bound.as_ref()
+    }
+
+    pub fn interface_bounds(&self) -> &[TypeSignature] {
+        &self.interface_bounds
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeSignature {
+    /// One of `BCDFIJSZ`, or `V` for a void return type
+    Base(char),
+    /// The name of a type variable, without the `T` and `;`
+    TypeVariable(String),
+    Class(ClassTypeSignature),
+    /// An array of this many dimensions, whose element type is never an array itself
+    Array(usize, Box<TypeSignature>),
+}
+
+impl TypeSignature {
+    pub fn for_each_class_type(&self, f: &mut dyn FnMut(&ClassTypeSignature)) {
+        match self {
+            TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => {}
+            TypeSignature::Array(_, element) => element.for_each_class_type(f),
+            TypeSignature::Class(class_type) => {
+                f(class_type);
+                for argument in class_type.arguments() {
+                    if let Some(bound) = argument.bound() {
+                        bound.for_each_class_type(f);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn for_each_class_type_mut(&mut self, f: &mut dyn FnMut(&mut ClassTypeSignature)) {
+        match self {
+            TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => {}
+            TypeSignature::Array(_, element) => element.for_each_class_type_mut(f),
+            TypeSignature::Class(class_type) => {
+                f(class_type);
+                for segment in class_type.segments.iter_mut() {
+                    for argument in segment.arguments.iter_mut() {
+                        if let Some(bound) = argument.bound_mut() {
+                            bound.for_each_class_type_mut(f);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for TypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeSignature::Base(base) => write!(f, "{base}"),
+            TypeSignature::TypeVariable(name) => write!(f, "T{name};"),
+            TypeSignature::Class(class_type) => write!(f, "{class_type}"),
+            TypeSignature::Array(dimensions, element) => {
+                write!(f, "{}{element}", "[".repeat(*dimensions))
+            }
+        }
+    }
+}
+
+/// `Ljava/util/Map<TK;TV;>.Entry;`: the outermost class with its package, then the inner
+/// classes of a generic class, each with its own type arguments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassTypeSignature {
+    segments: Vec<ClassTypeSegment>,
+}
+
+impl ClassTypeSignature {
+    pub fn segments(&self) -> &[ClassTypeSegment] {
+        &self.segments
+    }
+
+    pub fn segments_mut(&mut self) -> &mut [ClassTypeSegment] {
+        &mut self.segments
+    }
+
+    /// The internal name of the class, e.g. `java/util/Map$Entry`
+    pub fn binary_name(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| segment.name.as_str())
+            .collect::<Vec<_>>()
+            .join("$")
+    }
+
+    /// The type arguments of every segment
+    pub fn arguments(&self) -> impl Iterator<Item = &TypeArgument> {
+        self.segments
+            .iter()
+            .flat_map(|segment| segment.arguments.iter())
+    }
+}
+
+impl fmt::Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("L")?;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            f.write_str(&segment.name)?;
+            if !segment.arguments.is_empty() {
+                f.write_str("<")?;
+                for argument in segment.arguments.iter() {
+                    match argument {
+                        TypeArgument::Any => f.write_str("*")?,
+                        TypeArgument::Extends(bound) => write!(f, "+{bound}")?,
+                        TypeArgument::Super(bound) => write!(f, "-{bound}")?,
+                        TypeArgument::Exact(bound) => write!(f, "{bound}")?,
+                    }
+                }
+                f.write_str(">")?;
+            }
+        }
+        f.write_str(";")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassTypeSegment {
+    name: String,
+    arguments: Vec<TypeArgument>,
+}
+
+impl ClassTypeSegment {
+    /// The class name with its package in the first segment, the simple name after that
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn arguments(&self) -> &[TypeArgument] {
+        &self.arguments
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeArgument {
+    /// `*`
+    Any,
+    /// `+`, `? extends`
+    Extends(TypeSignature),
+    /// `-`, `? super`
+    Super(TypeSignature),
+    Exact(TypeSignature),
+}
+
+impl TypeArgument {
+    pub fn bound(&self) -> Option<&TypeSignature> {
+        match self {
+            TypeArgument::Any => None,
+            TypeArgument::Extends(bound)
+            | TypeArgument::Super(bound)
+            | TypeArgument::Exact(bound) => Some(bound),
+        }
+    }
+
+    fn bound_mut(&mut self) -> Option<&mut TypeSignature> {
+        match self {
+            TypeArgument::Any => None,
+            TypeArgument::Extends(bound)
+            | TypeArgument::Super(bound)
+            | TypeArgument::Exact(bound) => Some(bound),
+        }
+    }
+}
+
+struct Parser<'s> {
+    signature: &'s str,
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.signature.as_bytes().get(self.i).copied()
+    }
+
+    /// A non-empty identifier up to the first of `end`
+    fn identifier(&mut self, end: &[char]) -> Option<String> {
+        let start = self.i;
+        self.i += self.signature[start..].find(end)?;
+        (self.i > start).then(|| self.signature[start..self.i].to_string())
+    }
+
+    fn signature(&mut self) -> Option<Signature> {
+        let type_parameters = self.type_parameters()?;
+        let mut parameters = None;
+        let mut types = Vec::new();
+        let mut thrown = Vec::new();
+        if self.peek() == Some(b'(') {
+            self.i += 1;
+            let mut list = Vec::new();
+            while self.peek()? != b')' {
+                list.push(self.type_signature(0)?);
+            }
+            self.i += 1;
+            parameters = Some(list);
+            types.push(self.type_signature(0)?);
+            while self.peek() == Some(b'^') {
+                self.i += 1;
+                thrown.push(self.type_signature(0)?);
+            }
+        } else {
+            // a field type, or a superclass and interfaces
+            types.push(self.type_signature(0)?);
+            while self.peek().is_some() {
+                types.push(self.type_signature(0)?);
+            }
+        }
+        Some(Signature {
+            type_parameters,
+            parameters,
+            types,
+            thrown,
+        })
+    }
+
+    fn type_parameters(&mut self) -> Option<Vec<TypeParameter>> {
+        let mut parameters = Vec::new();
+        if self.peek() != Some(b'<') {
+            return Some(parameters);
+        }
+        self.i += 1;
+        while self.peek()? != b'>' {
+            let name = self.identifier(&[':'])?;
+            self.i += 1;
+            let class_bound = match self.peek()? {
+                b':' | b'>' => None,
+                _ => Some(self.type_signature(0)?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.peek() == Some(b':') {
+                self.i += 1;
+                interface_bounds.push(self.type_signature(0)?);
+            }
+            parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+        }
+        self.i += 1;
+        (!parameters.is_empty()).then_some(parameters)
+    }
+
+    /// `depth` counts the enclosing type arguments, which are given up on past the class
+    /// parser's nesting limit
+    fn type_signature(&mut self, depth: usize) -> Option<TypeSignature> {
+        if depth > ParseLimits::DEFAULT_MAX_NESTING {
+            return None;
+        }
+        let type_signature = match self.peek()? {
+            base @ (b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b'V') => {
+                self.i += 1;
+                TypeSignature::Base(base as char)
+            }
+            b'[' => {
+                // 4.3.2: an array type may have at most 255 dimensions
+                let dimensions = self.signature.as_bytes()[self.i..]
+                    .iter()
+                    .take_while(|b| **b == b'[')
+                    .count();
+                if dimensions > 255 {
+                    return None;
+                }
+                self.i += dimensions;
+                let element = self.type_signature(depth)?;
+                TypeSignature::Array(dimensions, Box::new(element))
+            }
+            b'T' => {
+                self.i += 1;
+                let name = self.identifier(&[';'])?;
+                self.i += 1;
+                TypeSignature::TypeVariable(name)
+            }
+            b'L' => {
+                self.i += 1;
+                TypeSignature::Class(self.class_type(depth)?)
+            }
+            _ => return None,
+        };
+        Some(type_signature)
+    }
+
+    fn class_type(&mut self, depth: usize) -> Option<ClassTypeSignature> {
+        let mut segments = Vec::new();
+        loop {
+            let name = self.identifier(&['<', '.', ';'])?;
+            let mut arguments = Vec::new();
+            if self.peek()? == b'<' {
+                self.i += 1;
+                while self.peek()? != b'>' {
+                    arguments.push(match self.peek()? {
+                        b'*' => {
+                            self.i += 1;
+                            TypeArgument::Any
+                        }
+                        b'+' => {
+                            self.i += 1;
+                            TypeArgument::Extends(self.type_signature(depth + 1)?)
+                        }
+                        b'-' => {
+                            self.i += 1;
+                            TypeArgument::Super(self.type_signature(depth + 1)?)
+                        }
+                        _ => TypeArgument::Exact(self.type_signature(depth + 1)?),
+                    });
+                }
+                self.i += 1;
+                if arguments.is_empty() {
+                    return None;
+                }
+            }
+            segments.push(ClassTypeSegment { name, arguments });
+            let end = self.peek()?;
+            self.i += 1;
+            match end {
+                b';' => return Some(ClassTypeSignature { segments }),
+                b'.' => {}
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_signatures_and_descriptors() {
+        for text in [
+            "(I[[Ljava/lang/String;J)V",
+            "[[Ljava/lang/Object;",
+            "<K:Ljava/lang/Object;V::Ljava/lang/Comparable<-TV;>;>Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;",
+            "<T:Ljava/lang/Throwable;>(Lp/Outer<TT;>.Inner<*>;)TT;^TT;^Ljava/io/IOException;",
+        ] {
+            assert_eq!(Signature::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn walks_class_types() {
+        let signature = Signature::parse(
+            "<T::Ljava/lang/Comparable<TT;>;>(Lp/Outer<[Lp/A;>.Inner<+Lp/B;>;TT;)V^Lp/E;",
+        )
+        .unwrap();
+        assert_eq!(signature.type_parameters()[0].name(), "T");
+        assert!(signature.type_parameters()[0].class_bound().is_none());
+        assert_eq!(signature.parameters().unwrap().len(), 2);
+        assert_eq!(signature.types(), [TypeSignature::Base('V')]);
+        let mut names = Vec::new();
+        signature.for_each_class_type(&mut |class_type| names.push(class_type.binary_name()));
+        assert_eq!(
+            names,
+            ["java/lang/Comparable", "p/Outer$Inner", "p/A", "p/B", "p/E"]
+        );
+
+        let mut signature = signature;
+        signature.for_each_class_type_mut(&mut |class_type| {
+            let segment = &mut class_type.segments_mut()[0];
+            let name = segment.name().replace("p/", "q/");
+            segment.set_name(name);
+        });
+        assert_eq!(
+            signature.to_string(),
+            "<T::Ljava/lang/Comparable<TT;>;>(Lq/Outer<[Lq/A;>.Inner<+Lq/B;>;TT;)V^Lq/E;"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        for text in [
+            "",
+            "(Ljava/lang/String",
+            "<T>V",
+            "<>V",
+            "L;",
+            "Lp/A<>;",
+            "Lp/A.;",
+            "(I)",
+            "I)",
+        ] {
+            assert!(Signature::parse(text).is_err(), "{text}");
+        }
+        let deep = format!(
+            "{}I{}",
+            "Lp/A<".repeat(ParseLimits::DEFAULT_MAX_NESTING + 2),
+            ">;".repeat(ParseLimits::DEFAULT_MAX_NESTING + 2)
+        );
+        assert!(Signature::parse(&deep).is_err());
+        assert!(Signature::parse(&format!("{}I", "[".repeat(256))).is_err());
+        // a run of dimensions is one array, however long
+        assert!(Signature::parse(&format!("{}I", "[".repeat(255))).is_ok());
+    }
+}
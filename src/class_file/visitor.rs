@@ -0,0 +1,758 @@
+use crate::analysis::frames::recompute_frames;
+use crate::analysis::hierarchy::ClassHierarchy;
+use crate::bytecode::encoder::{CodeElement, Label, LabeledAttribute, LabeledCode, LabeledHandler};
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::attribute::attribute_info::{read_attributes, AttributeInfo};
+use crate::class_file::attribute::code::Code;
+use crate::class_file::class_file::{ClassFile, MAGIC_NUMBER};
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
+use crate::class_file::constant_pool::types::utf8::Utf8;
+use crate::class_file::limits::ParseLimits;
+use crate::class_file::member_info::MemberInfo;
+use crate::util::file::{read_u16, read_u16_list, read_u32};
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+
+// An event-based view of class files in the style of ASM: `accept` parses a class and calls a
+// `ClassVisitor` for each part as it is read instead of building a `ClassFile`, and visitors can
+// be chained to filter or rewrite the events on their way to a `ClassWriter`.
+//
+// Events come in class file order:
+//
+//   visit_header
+//   visit_constant*
+//   visit_class
+//   (visit_field visit_attribute* visit_member_end)*
+//   (visit_method (visit_code (visit_label | visit_instruction)* visit_exception_handler*
+//       visit_code_attribute* visit_code_end | visit_attribute)* visit_member_end)*
+//   visit_attribute*
+//   visit_end
+//
+// Like the rest of the crate everything refers to the constant pool by index. A visitor that
+// adds constants numbers them after the last one it has seen, and code uses the labels of
+// `LabeledCode`, numbered from 0 for each method.
+
+/// Receives the parts of a class file. Every method passes the event on to `delegate`, so a
+/// visitor in the middle of a chain only implements the events it changes; one without a
+/// delegate ignores the rest.
+pub trait ClassVisitor {
+    /// The next visitor in the chain
+    fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+        None
+    }
+
+    fn visit_header(&mut self, minor_version: u16, major_version: u16) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_header(minor_version, major_version),
+            None => Ok(()),
+        }
+    }
+
+    /// A constant pool entry; a Long or Double also takes up the index after it
+    fn visit_constant(&mut self, index: u16, item: &ConstantPoolItem) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_constant(index, item),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_class(
+        &mut self,
+        access_flags: u16,
+        this_class: u16,
+        super_class: u16,
+        interfaces: &[u16],
+    ) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_class(access_flags, this_class, super_class, interfaces),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns false to drop the field, which skips its attributes and `visit_member_end`
+    fn visit_field(
+        &mut self,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) -> Result<bool, io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_field(access_flags, name_index, descriptor_index),
+            None => Ok(true),
+        }
+    }
+
+    /// Returns false to drop the method, which skips its code, attributes and
+    /// `visit_member_end`
+    fn visit_method(
+        &mut self,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) -> Result<bool, io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_method(access_flags, name_index, descriptor_index),
+            None => Ok(true),
+        }
+    }
+
+    /// The start of a method's Code attribute (section 4.7.3). `code` is the code array as
+    /// read; a StackMapTable is only written back if the instructions encode to it again.
+    fn visit_code(
+        &mut self,
+        max_stack: u16,
+        max_locals: u16,
+        code: &[u8],
+    ) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_code(max_stack, max_locals, code),
+            None => Ok(()),
+        }
+    }
+
+    /// The position of the next instruction, for branches, handlers and debug ranges to refer to
+    fn visit_label(&mut self, label: Label) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_label(label),
+            None => Ok(()),
+        }
+    }
+
+    /// An instruction whose branch targets are label ids
+    fn visit_instruction(&mut self, instruction: &Instruction) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_instruction(instruction),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_exception_handler(&mut self, handler: &LabeledHandler) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_exception_handler(handler),
+            None => Ok(()),
+        }
+    }
+
+    /// An attribute of the Code attribute, such as its LineNumberTable
+    fn visit_code_attribute(&mut self, attribute: &LabeledAttribute) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_code_attribute(attribute),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_code_end(&mut self) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_code_end(),
+            None => Ok(()),
+        }
+    }
+
+    /// An attribute of the current field or method, or of the class once all methods are done
+    fn visit_attribute(&mut self, attribute: &AttributeInfo) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_attribute(attribute),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_member_end(&mut self) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_member_end(),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_end(&mut self) -> Result<(), io::Error> {
+        match self.delegate() {
+            Some(next) => next.visit_end(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a class file from `reader`, calling `visitor` for each part as it is read. Only the
+/// constant pool and one member at a time are kept in memory.
+pub fn accept(reader: impl Read, visitor: &mut dyn ClassVisitor) -> Result<(), io::Error> {
+    accept_with_limits(reader, &ParseLimits::new(), visitor)
+}
+
+pub fn accept_with_limits(
+    reader: impl Read,
+    limits: &ParseLimits,
+    visitor: &mut dyn ClassVisitor,
+) -> Result<(), io::Error> {
+    // read one byte past the limit so an oversized class is told apart from a truncated one
+    let mut input = reader.take(limits.max_file_size() as u64 + 1);
+    let reader = &mut BufReader::new(&mut input);
+
+    if read_u32(reader)? != MAGIC_NUMBER {
+        return Err(io::Error::other("Invalid magic number"));
+    }
+    let minor_version = read_u16(reader)?;
+    let major_version = read_u16(reader)?;
+    if major_version > 52 {
+        return Err(io::Error::other(
+            "Unsupported major class version - must be <= 52",
+        ));
+    }
+    visitor.visit_header(minor_version, major_version)?;
+
+    let constant_pool_count = read_u16(reader)?;
+    limits.check_constant_pool_count(constant_pool_count)?;
+    let mut constant_pool = ConstantPool::new();
+    constant_pool.set_count(constant_pool_count);
+    while constant_pool.items().len() + 1 < constant_pool_count as usize {
+        let index = constant_pool.items().len() as u16 + 1;
+        constant_pool.parse_item_from_class_file(reader)?;
+        if constant_pool.items().len() + 1 > constant_pool_count as usize {
+            return Err(visitor_error(
+                "Last constant pool entry runs past constant_pool_count",
+            ));
+        }
+        if let Some(item) = constant_pool.get(index) {
+            visitor.visit_constant(index, item)?;
+        }
+    }
+
+    let access_flags = read_u16(reader)?;
+    let this_class = read_u16(reader)?;
+    let super_class = read_u16(reader)?;
+    let interfaces = read_u16_list(reader)?;
+    visitor.visit_class(access_flags, this_class, super_class, &interfaces)?;
+
+    for is_method in [false, true] {
+        for _ in 0..read_u16(reader)? {
+            let member = MemberInfo::from(reader)?;
+            limits.check_attributes(member.attributes(), &constant_pool, 1)?;
+            let (flags, name, descriptor) = (
+                member.access_flags(),
+                member.name_index(),
+                member.descriptor_index(),
+            );
+            let visited = match is_method {
+                false => visitor.visit_field(flags, name, descriptor)?,
+                true => visitor.visit_method(flags, name, descriptor)?,
+            };
+            if !visited {
+                continue;
+            }
+            for attribute in member.attributes() {
+                let is_code = is_method
+                    && matches!(attribute.name(&constant_pool), Ok(name) if name == Code::NAME);
+                match is_code {
+                    true => accept_code(attribute, &constant_pool, visitor)?,
+                    false => visitor.visit_attribute(attribute)?,
+                }
+            }
+            visitor.visit_member_end()?;
+        }
+    }
+
+    let attributes = read_attributes(reader)?;
+    limits.check_attributes(&attributes, &constant_pool, 1)?;
+    for attribute in attributes.iter() {
+        visitor.visit_attribute(attribute)?;
+    }
+
+    let trailing = !reader.fill_buf()?.is_empty();
+    limits.check_file_size(limits.max_file_size() + 1 - input.limit() as usize)?;
+    if trailing {
+        return Err(visitor_error(
+            "Unexpected trailing bytes after the end of the class file",
+        ));
+    }
+    visitor.visit_end()
+}
+
+/// Replays a Code attribute as labels and instructions
+fn accept_code(
+    attribute: &AttributeInfo,
+    constant_pool: &ConstantPool,
+    visitor: &mut dyn ClassVisitor,
+) -> Result<(), io::Error> {
+    let code = Code::from(&mut attribute.reader())?;
+    let labeled = LabeledCode::from_code(&code, constant_pool)?;
+    visitor.visit_code(code.max_stack(), code.max_locals(), code.code())?;
+    for element in labeled.elements() {
+        match element {
+            CodeElement::Label(label) => visitor.visit_label(*label)?,
+            CodeElement::Instruction(instruction) => visitor.visit_instruction(instruction)?,
+        }
+    }
+    for handler in labeled.exception_table() {
+        visitor.visit_exception_handler(handler)?;
+    }
+    for attribute in labeled.attributes() {
+        visitor.visit_code_attribute(attribute)?;
+    }
+    visitor.visit_code_end()
+}
+
+/// The end of a chain: collects the events back into a class file. Code is laid out again
+/// from its labels, so instructions can be added or removed on the way.
+///
+/// A StackMapTable only survives if the method's code comes out exactly as it went in. When
+/// code that needs frames changes, a writer made with `computing_frames` recomputes them for
+/// the whole class at `visit_end`, like ASM's COMPUTE_FRAMES; one made with `new` fails
+/// rather than write a class that won't verify.
+pub struct ClassWriter<'a> {
+    class_file: ClassFile,
+    constant_pool: ConstantPool,
+    member: Option<(bool, MemberInfo)>,
+    code: Option<(u16, u16, LabeledCode)>,
+    hierarchy: Option<&'a dyn ClassHierarchy>,
+    frames_changed: bool,
+}
+
+impl<'a> ClassWriter<'a> {
+    pub fn new() -> ClassWriter<'a> {
+        ClassWriter {
+            class_file: ClassFile::new(),
+            constant_pool: ConstantPool::new(),
+            member: None,
+            code: None,
+            hierarchy: None,
+            frames_changed: false,
+        }
+    }
+
+    /// A writer that recomputes stack map frames when code changes. The hierarchy is needed
+    /// to merge reference types.
+    pub fn computing_frames(hierarchy: &'a dyn ClassHierarchy) -> ClassWriter<'a> {
+        ClassWriter {
+            hierarchy: Some(hierarchy),
+            ..ClassWriter::new()
+        }
+    }
+
+    /// The class as written so far; complete once `visit_end` has been called
+    pub fn class_file(&self) -> &ClassFile {
+        &self.class_file
+    }
+
+    pub fn into_class_file(self) -> ClassFile {
+        self.class_file
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.class_file.to_bytes()
+    }
+
+    fn code(&mut self) -> Result<&mut LabeledCode, io::Error> {
+        match self.code.as_mut() {
+            Some((_, _, code)) => Ok(code),
+            None => Err(visitor_error("Code event outside of visit_code")),
+        }
+    }
+
+    /// The index of the Utf8 `Code`, which is added if no constant so far has it
+    fn code_name_index(&mut self) -> Result<u16, io::Error> {
+        let existing = self.constant_pool.items().iter().position(|item| {
+            matches!(item.data(), ConstantPoolData::Utf8(utf8) if utf8.value() == Code::NAME)
+        });
+        match existing {
+            Some(position) => Ok(position as u16 + 1),
+            None => {
+                self.constant_pool
+                    .push_item(ConstantPoolItem::from_data(ConstantPoolData::Utf8(
+                        Utf8::from_string(Code::NAME)?,
+                    )))
+            }
+        }
+    }
+
+    /// Checks whether changed code loses frames it needs: a StackMapTable it had, or any for
+    /// new branches and handlers in a class file version that uses them
+    fn check_frames(&mut self, labeled: &LabeledCode, code: &Code) -> Result<(), io::Error> {
+        if labeled.original_code() == Some(code.code()) || self.class_file.major_version() < 50 {
+            return Ok(());
+        }
+        let had_frames = labeled
+            .attributes()
+            .iter()
+            .any(|attribute| matches!(attribute, LabeledAttribute::StackMapTable(_)));
+        let branches = !labeled.exception_table().is_empty()
+            || labeled.elements().iter().any(|element| {
+                matches!(element, CodeElement::Instruction(instruction)
+                    if !instruction.branch_targets().is_empty())
+            });
+        if !had_frames && !branches {
+            return Ok(());
+        }
+        if self.hierarchy.is_none() {
+            let name = match self.member.as_ref() {
+                Some((_, method)) => self.constant_pool.utf8(method.name_index())?,
+                None => String::new(),
+            };
+            return Err(visitor_error(&format!(
+                "{name}: the code changed and its stack map frames can't be kept; use \
+                 ClassWriter::computing_frames to recompute them"
+            )));
+        }
+        self.frames_changed = true;
+        Ok(())
+    }
+
+    fn start_member(
+        &mut self,
+        is_method: bool,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) -> Result<bool, io::Error> {
+        if self.member.is_some() {
+            return Err(visitor_error("Member started before the last one ended"));
+        }
+        let member = MemberInfo::new(access_flags, name_index, descriptor_index, Vec::new());
+        self.member = Some((is_method, member));
+        Ok(true)
+    }
+}
+
+impl ClassVisitor for ClassWriter<'_> {
+    fn visit_header(&mut self, minor_version: u16, major_version: u16) -> Result<(), io::Error> {
+        self.class_file.set_version(major_version, minor_version);
+        Ok(())
+    }
+
+    fn visit_constant(&mut self, index: u16, item: &ConstantPoolItem) -> Result<(), io::Error> {
+        let expected = self.constant_pool.items().len() + 1;
+        if index as usize != expected {
+            return Err(visitor_error(&format!(
+                "Constant #{index} is out of order, the next index is #{expected}"
+            )));
+        }
+        self.constant_pool.push_item(item.clone())?;
+        Ok(())
+    }
+
+    fn visit_class(
+        &mut self,
+        access_flags: u16,
+        this_class: u16,
+        super_class: u16,
+        interfaces: &[u16],
+    ) -> Result<(), io::Error> {
+        self.class_file.set_access_flags(access_flags);
+        self.class_file.set_this_class(this_class);
+        self.class_file.set_super_class(super_class);
+        *self.class_file.interfaces_mut() = interfaces.to_vec();
+        Ok(())
+    }
+
+    fn visit_field(
+        &mut self,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) -> Result<bool, io::Error> {
+        self.start_member(false, access_flags, name_index, descriptor_index)
+    }
+
+    fn visit_method(
+        &mut self,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) -> Result<bool, io::Error> {
+        self.start_member(true, access_flags, name_index, descriptor_index)
+    }
+
+    fn visit_code(
+        &mut self,
+        max_stack: u16,
+        max_locals: u16,
+        code: &[u8],
+    ) -> Result<(), io::Error> {
+        if !matches!(self.member, Some((true, _))) || self.code.is_some() {
+            return Err(visitor_error("visit_code outside of a method"));
+        }
+        let mut labeled = LabeledCode::new();
+        labeled.set_original_code(Some(code.to_vec()));
+        self.code = Some((max_stack, max_locals, labeled));
+        Ok(())
+    }
+
+    fn visit_label(&mut self, label: Label) -> Result<(), io::Error> {
+        self.code()?.push_label(label);
+        Ok(())
+    }
+
+    fn visit_instruction(&mut self, instruction: &Instruction) -> Result<(), io::Error> {
+        self.code()?.push(instruction.clone());
+        Ok(())
+    }
+
+    fn visit_exception_handler(&mut self, handler: &LabeledHandler) -> Result<(), io::Error> {
+        self.code()?.exception_table_mut().push(*handler);
+        Ok(())
+    }
+
+    fn visit_code_attribute(&mut self, attribute: &LabeledAttribute) -> Result<(), io::Error> {
+        self.code()?.attributes_mut().push(attribute.clone());
+        Ok(())
+    }
+
+    fn visit_code_end(&mut self) -> Result<(), io::Error> {
+        let Some((max_stack, max_locals, labeled)) = self.code.take() else {
+            return Err(visitor_error("visit_code_end without visit_code"));
+        };
+        let code = labeled.to_code(max_stack, max_locals)?;
+        self.check_frames(&labeled, &code)?;
+        let mut info = Vec::new();
+        code.write(&mut info);
+        let name_index = self.code_name_index()?;
+        self.visit_attribute(&AttributeInfo::new(name_index, info))
+    }
+
+    fn visit_attribute(&mut self, attribute: &AttributeInfo) -> Result<(), io::Error> {
+        match self.member.as_mut() {
+            Some((_, member)) => member.attributes_mut().push(attribute.clone()),
+            None => self.class_file.attributes_mut().push(attribute.clone()),
+        }
+        Ok(())
+    }
+
+    fn visit_member_end(&mut self) -> Result<(), io::Error> {
+        if self.code.is_some() {
+            return Err(visitor_error("Member ended inside its code"));
+        }
+        match self.member.take() {
+            Some((false, field)) => self.class_file.fields_mut().push(field),
+            Some((true, method)) => self.class_file.methods_mut().push(method),
+            None => return Err(visitor_error("visit_member_end without a member")),
+        }
+        Ok(())
+    }
+
+    fn visit_end(&mut self) -> Result<(), io::Error> {
+        if self.member.is_some() {
+            return Err(visitor_error("Class ended inside a member"));
+        }
+        self.class_file.set_constant_pool(std::mem::replace(
+            &mut self.constant_pool,
+            ConstantPool::new(),
+        ));
+        match self.hierarchy {
+            Some(hierarchy) if self.frames_changed => {
+                recompute_frames(&mut self.class_file, hierarchy)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn visitor_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::hierarchy::ClassFileHierarchy;
+    use crate::analysis::verifier::verify_class;
+    use crate::assembler::assembler::assemble;
+
+    const SWITCH: &str = "
+.class t/Switch
+.super java/lang/Object
+.field private static count J
+.method static pick(I)I
+    .limit stack 2
+    .limit locals 1
+    .catch java/lang/RuntimeException from Start to End using Handler
+Start:
+    iload_0
+    tableswitch 0
+        One
+        Two
+        default : Other
+One:
+    iconst_1
+    ireturn
+Two:
+    ldc2_w 5000000000
+    l2i
+    ireturn
+Other:
+    iconst_m1
+End:
+    ireturn
+Handler:
+    pop
+    iconst_0
+    ireturn
+.end method
+.method static unused()V
+    .limit stack 0
+    .limit locals 0
+    return
+.end method
+";
+
+    fn hierarchy() -> ClassFileHierarchy {
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add("java/lang/Throwable", Some("java/lang/Object"), false);
+        hierarchy.add("java/lang/Exception", Some("java/lang/Throwable"), false);
+        hierarchy.add(
+            "java/lang/RuntimeException",
+            Some("java/lang/Exception"),
+            false,
+        );
+        hierarchy
+    }
+
+    /// The switch class with the stack map frames it needs, as a compiler would write it
+    fn switch_with_frames() -> Vec<u8> {
+        let mut class_file = assemble(SWITCH).unwrap();
+        recompute_frames(&mut class_file, &hierarchy()).unwrap();
+        class_file.to_bytes()
+    }
+
+    fn rewrite<'a>(
+        bytes: &[u8],
+        mut writer: ClassWriter<'a>,
+        visitor: impl for<'w> FnOnce(&'w mut ClassWriter<'a>) -> Box<dyn ClassVisitor + 'w>,
+    ) -> Result<ClassFile, io::Error> {
+        accept(bytes, visitor(&mut writer).as_mut())?;
+        let mut class_file = ClassFile::new();
+        class_file.read_slice(&writer.to_bytes())?;
+        Ok(class_file)
+    }
+
+    /// Drops methods by name, keeping its own copy of the constant pool to look them up
+    struct DropMethod<'a> {
+        name: &'a str,
+        constant_pool: ConstantPool,
+        next: &'a mut dyn ClassVisitor,
+    }
+
+    impl ClassVisitor for DropMethod<'_> {
+        fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.next)
+        }
+
+        fn visit_constant(&mut self, index: u16, item: &ConstantPoolItem) -> Result<(), io::Error> {
+            self.constant_pool.push_item(item.clone())?;
+            self.next.visit_constant(index, item)
+        }
+
+        fn visit_method(
+            &mut self,
+            access_flags: u16,
+            name_index: u16,
+            descriptor_index: u16,
+        ) -> Result<bool, io::Error> {
+            if self.constant_pool.utf8(name_index)? == self.name {
+                return Ok(false);
+            }
+            self.next
+                .visit_method(access_flags, name_index, descriptor_index)
+        }
+    }
+
+    /// Puts a nop at the start of every method
+    struct PrependNop<'a> {
+        next: &'a mut dyn ClassVisitor,
+    }
+
+    impl ClassVisitor for PrependNop<'_> {
+        fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.next)
+        }
+
+        fn visit_code(
+            &mut self,
+            max_stack: u16,
+            max_locals: u16,
+            code: &[u8],
+        ) -> Result<(), io::Error> {
+            self.next.visit_code(max_stack, max_locals, code)?;
+            self.next.visit_instruction(&Instruction::Nop)
+        }
+    }
+
+    #[test]
+    fn writer_reproduces_the_input() {
+        let hello_world = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        let switch = assemble(SWITCH).unwrap().to_bytes();
+        for bytes in [hello_world, switch] {
+            let mut writer = ClassWriter::new();
+            accept(bytes.as_slice(), &mut writer).unwrap();
+            assert_eq!(writer.to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn chained_visitors_filter_and_transform() {
+        let bytes = switch_with_frames();
+        let hierarchy = hierarchy();
+        // unchanged code keeps its frames
+        let class_file = rewrite(&bytes, ClassWriter::new(), |writer| {
+            Box::new(DropMethod {
+                name: "unused",
+                constant_pool: ConstantPool::new(),
+                next: writer,
+            })
+        })
+        .unwrap();
+        assert!(class_file.find_method("unused", "()V").is_none());
+        assert!(class_file.find_method("pick", "(I)I").is_some());
+        assert_eq!(verify_class(&class_file, &hierarchy), []);
+
+        let writer = ClassWriter::computing_frames(&hierarchy);
+        let class_file = rewrite(&bytes, writer, |writer| {
+            Box::new(PrependNop { next: writer })
+        })
+        .unwrap();
+        assert_eq!(verify_class(&class_file, &hierarchy), []);
+        let constant_pool = class_file.constant_pool();
+        let code = class_file
+            .find_method("pick", "(I)I")
+            .unwrap()
+            .code(constant_pool)
+            .unwrap()
+            .unwrap();
+        let instructions = code.instructions().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(instructions[0], (0, Instruction::Nop));
+        assert_eq!(instructions[1], (1, Instruction::Iload0));
+        // the switch and the handler range move with the code
+        assert_eq!(code.exception_table()[0].start_pc(), 1);
+        let Instruction::Tableswitch { default, .. } = instructions[2].1 else {
+            panic!("{:?}", instructions[2]);
+        };
+        assert!(instructions.contains(&(default, Instruction::IconstM1)));
+
+        // without a hierarchy the frames can't be recomputed, so the writer refuses
+        let error = rewrite(&bytes, ClassWriter::new(), |writer| {
+            Box::new(PrependNop { next: writer })
+        })
+        .unwrap_err();
+        assert!(
+            error.to_string().starts_with("pick: the code changed"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn malformed_input_fails() {
+        let bytes = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        assert!(accept(&bytes[..bytes.len() - 1], &mut ClassWriter::new()).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let error = accept(trailing.as_slice(), &mut ClassWriter::new()).unwrap_err();
+        assert!(error.to_string().contains("trailing bytes"), "{error}");
+
+        let mut limits = ParseLimits::new();
+        limits.set_max_file_size(bytes.len() - 1);
+        let error =
+            accept_with_limits(bytes.as_slice(), &limits, &mut ClassWriter::new()).unwrap_err();
+        assert!(
+            error.to_string().contains("larger than the limit"),
+            "{error}"
+        );
+    }
+}
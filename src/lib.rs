@@ -0,0 +1,9 @@
+#![allow(clippy::module_inception)]
+#![allow(clippy::new_without_default)]
+
+pub mod analysis;
+pub mod assembler;
+pub mod bytecode;
+pub mod class_file;
+pub mod tools;
+pub mod util;
@@ -1,6 +1,1043 @@
-mod class_file;
-mod util;
+use rusty_jvm8::analysis::call_graph::{Analysis, CallGraph, MethodId};
+use rusty_jvm8::analysis::cfg::ControlFlowGraph;
+use rusty_jvm8::analysis::dataflow::check_class;
+use rusty_jvm8::analysis::dependencies::DependencyGraph;
+use rusty_jvm8::analysis::frames::recompute_frames;
+use rusty_jvm8::analysis::hierarchy::{ClassFileHierarchy, ClassHierarchy};
+use rusty_jvm8::analysis::linkage::LinkChecker;
+use rusty_jvm8::analysis::verifier::verify_class;
+use rusty_jvm8::assembler::assembler::assemble;
+use rusty_jvm8::class_file::class_file::ClassFile;
+use rusty_jvm8::tools::compat::{compare_class_sets, has_breaking_changes, report};
+use rusty_jvm8::tools::decompile::decompiler::decompile;
+use rusty_jvm8::tools::explain::explain;
+use rusty_jvm8::tools::instrument::Instrumenter;
+use rusty_jvm8::tools::javap::{javap, JavapOptions};
+use rusty_jvm8::tools::json::{from_json, to_json};
+use rusty_jvm8::tools::metrics::{self, class_metrics, SortKey};
+use rusty_jvm8::tools::optimize::{Optimizer, Pass};
+use rusty_jvm8::tools::remap::{Mapping, Remapper};
+use rusty_jvm8::tools::shrink::Shrinker;
+use rusty_jvm8::util::class_path::read_class_path;
+use rusty_jvm8::util::jar::{JarEntry, JarFile};
+use rusty_jvm8::util::json::JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
 
-fn main() {
-    println!("Hello, world!");
+const USAGE: &str = "\
+usage: rusty-jvm8 <command> [<args>]
+
+commands:
+    javap [-v] [-c] <class file>...        print the contents of class files
+    asm <source> [-o <class file>]         assemble a Jasmin-style source file
+    json <class file>                      print a class file as JSON
+    explain <class file>                   print every byte of a class file with what it means
+    from-json <json file> -o <class file>  write a class file back from its JSON form
+    compat <old> <new>                     report API changes between two class files or jars
+    cfg <class file> <method>              print a method's control-flow graph in DOT format
+    frames <class file> [-cp <class or jar>...] [-o <class file>]
+                                           recompute max_stack, max_locals and stack map frames
+    verify [-cp <class or jar>...] <class file or jar>...
+                                           verify methods against their stack map frames
+    deps [-level class|package|jar] [-internal] [-json] <class file or jar>...
+                                           print the dependency graph in DOT or JSON format
+    link [-cp <class or jar>...] [-ignore <package prefix>...] <class file or jar>...
+                                           find references that would fail to resolve
+    hierarchy -cp <class, jar or directory>... <class name>...
+                                           print the supertypes and subtypes of classes
+    callgraph [-cp <class or jar>...] [-rta] [-entry <class.method(descriptor)>...] [-dot]
+              <class file or jar>...       list reachable and unreachable methods
+    lint <class file or jar>...            find null dereferences and stores that are never read
+    metrics [-sort <column>] [-top <n>] [-json] <class file or jar>...
+                                           report code size and complexity for every method
+    remap -mapping <file> -o <jar or directory> <class file, jar or directory>...
+                                           rename classes, packages and members, e.g. to shade
+    instrument -hook <class.method> [-cp <class or jar>...] [-include <class prefix>...]
+               -o <jar or directory> <class file, jar or directory>...
+                                           call a static hook on method entry, return and throw
+    shrink [-strip lines|vars|source|debug]... -o <jar or directory>
+           <class file, jar or directory>...
+                                           strip debug info and drop unused constants
+    optimize [-cp <class or jar>...] [-pass <name>]... -o <jar or directory>
+             <class file, jar or directory>...
+                                           fold constants and remove dead code, jumps and stores
+    decompile <class file, jar or directory>...
+                                           print classes as Java-like source (experimental)
+
+class paths can be class files, jars or directories";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("javap") => javap_command(&args[1..]),
+        Some("asm") => asm_command(&args[1..]),
+        Some("json") => json_command(&args[1..]),
+        Some("from-json") => from_json_command(&args[1..]),
+        Some("explain") => explain_command(&args[1..]),
+        Some("compat") => match compat_command(&args[1..]) {
+            // breaking changes fail the command so it can gate releases
+            Ok(true) => return ExitCode::FAILURE,
+            result => result.map(|_| ()),
+        },
+        Some("cfg") => cfg_command(&args[1..]),
+        Some("frames") => frames_command(&args[1..]),
+        Some("verify") => match verify_command(&args[1..]) {
+            // like compat, failures are reported and fail the command
+            Ok(true) => return ExitCode::FAILURE,
+            result => result.map(|_| ()),
+        },
+        Some("deps") => deps_command(&args[1..]),
+        Some("link") => match link_command(&args[1..]) {
+            Ok(true) => return ExitCode::FAILURE,
+            result => result.map(|_| ()),
+        },
+        Some("hierarchy") => hierarchy_command(&args[1..]),
+        Some("callgraph") => callgraph_command(&args[1..]),
+        Some("metrics") => metrics_command(&args[1..]),
+        Some("remap") => remap_command(&args[1..]),
+        Some("instrument") => instrument_command(&args[1..]),
+        Some("shrink") => shrink_command(&args[1..]),
+        Some("optimize") => optimize_command(&args[1..]),
+        Some("decompile") => decompile_command(&args[1..]),
+        Some("lint") => match lint_command(&args[1..]) {
+            Ok(true) => return ExitCode::FAILURE,
+            result => result.map(|_| ()),
+        },
+        Some("-h" | "--help" | "help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{message}\n{USAGE}"))
+}
+
+fn javap_command(args: &[String]) -> Result<(), io::Error> {
+    let mut verbose = false;
+    let mut code = false;
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-v" | "-verbose" => verbose = true,
+            "-c" => code = true,
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown javap option '{flag}'")))
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(usage_error("javap needs at least one class file"));
+    }
+
+    let options = JavapOptions::new(verbose, code);
+    for file in files {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file(file)
+            .map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))?;
+        if verbose {
+            let path = std::fs::canonicalize(file).unwrap_or_else(|_| file.into());
+            println!("Classfile {}", path.display());
+        }
+        print!("{}", javap(&class_file, options)?);
+    }
+    Ok(())
+}
+
+fn asm_command(args: &[String]) -> Result<(), io::Error> {
+    let (source, output) = match args {
+        [source] => (source, Path::new(source).with_extension("class")),
+        [source, flag, output] if flag == "-o" => (source, output.into()),
+        _ => {
+            return Err(usage_error(
+                "asm needs a source file and an optional -o <class file>",
+            ))
+        }
+    };
+    let text = std::fs::read_to_string(source)?;
+    let class_file =
+        assemble(&text).map_err(|e| io::Error::new(e.kind(), format!("{source}: {e}")))?;
+    std::fs::write(&output, class_file.to_bytes())?;
+    Ok(())
+}
+
+fn json_command(args: &[String]) -> Result<(), io::Error> {
+    let [file] = args else {
+        return Err(usage_error("json needs exactly one class file"));
+    };
+    let mut class_file = ClassFile::new();
+    class_file
+        .read_file(file)
+        .map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))?;
+    println!("{}", to_json(&class_file).to_pretty_string());
+    Ok(())
+}
+
+fn explain_command(args: &[String]) -> Result<(), io::Error> {
+    let [file] = args else {
+        return Err(usage_error("explain needs exactly one class file"));
+    };
+    let bytes = std::fs::read(file)?;
+    // the dump up to a malformed structure is the useful part, so print it before the error
+    let mut out = String::new();
+    let result = explain(&bytes, &mut out);
+    print!("{out}");
+    result.map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))
+}
+
+fn from_json_command(args: &[String]) -> Result<(), io::Error> {
+    let [source, flag, output] = args else {
+        return Err(usage_error(
+            "from-json needs a JSON file and -o <class file>",
+        ));
+    };
+    if flag != "-o" {
+        return Err(usage_error(&format!("unknown from-json option '{flag}'")));
+    }
+    let text = std::fs::read_to_string(source)?;
+    let class_file = JsonValue::parse(&text)
+        .and_then(|json| from_json(&json))
+        .map_err(|e| io::Error::new(e.kind(), format!("{source}: {e}")))?;
+    std::fs::write(output, class_file.to_bytes())?;
+    Ok(())
+}
+
+/// Prints the changes and returns whether any of them are breaking
+fn compat_command(args: &[String]) -> Result<bool, io::Error> {
+    let [old, new] = args else {
+        return Err(usage_error(
+            "compat needs an old and a new class file or jar",
+        ));
+    };
+    let changes = compare_class_sets(&read_classes(old)?, &read_classes(new)?)?;
+    print!("{}", report(&changes));
+    Ok(has_breaking_changes(&changes))
+}
+
+/// `method` is a name, or a name and descriptor to pick one overload
+fn cfg_command(args: &[String]) -> Result<(), io::Error> {
+    let [file, method] = args else {
+        return Err(usage_error("cfg needs a class file and a method name"));
+    };
+    let mut class_file = ClassFile::new();
+    class_file
+        .read_file(file)
+        .map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))?;
+    let constant_pool = class_file.constant_pool();
+    let class_name = class_file.class_name()?;
+    let mut found = false;
+    for candidate in class_file.methods() {
+        let name = candidate.name(constant_pool)?;
+        let signature = format!("{name}{}", candidate.descriptor(constant_pool)?);
+        if name != *method && signature != *method {
+            continue;
+        }
+        found = true;
+        if let Some(code) = candidate.code(constant_pool)? {
+            let cfg = ControlFlowGraph::from_code(&code)?;
+            print!("{}", cfg.to_dot(&format!("{class_name}.{signature}")));
+        }
+    }
+    if !found {
+        return Err(usage_error(&format!("{file} has no method {method}")));
+    }
+    Ok(())
+}
+
+fn frames_command(args: &[String]) -> Result<(), io::Error> {
+    let mut file = None;
+    let mut output = None;
+    let mut class_path = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = args.next(),
+            "-cp" => class_path.extend(args.next()),
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown frames option {flag}")))
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(usage_error("frames takes a single class file")),
+        }
+    }
+    let Some(file) = file else {
+        return Err(usage_error("frames needs a class file"));
+    };
+
+    let mut class_file = ClassFile::new();
+    class_file
+        .read_file(file)
+        .map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))?;
+    // common superclasses at merge points come from the class path and the class itself
+    let mut hierarchy = ClassFileHierarchy::new();
+    for entry in class_path {
+        for class in read_classes(entry)? {
+            hierarchy.add_class_file(&class)?;
+        }
+    }
+    hierarchy.add_class_file(&class_file)?;
+    recompute_frames(&mut class_file, &hierarchy)
+        .map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))?;
+    class_file.write_file(output.unwrap_or(file))
+}
+
+/// Prints every verification failure, returning whether there were any
+fn verify_command(args: &[String]) -> Result<bool, io::Error> {
+    let mut files = Vec::new();
+    let mut class_path = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" => class_path.extend(args.next()),
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown verify option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(usage_error("verify needs at least one class file or jar"));
+    }
+
+    let mut classes = Vec::new();
+    for file in files {
+        classes.extend(read_classes(file)?);
+    }
+    let mut hierarchy = ClassFileHierarchy::new();
+    for entry in class_path {
+        for class in read_classes(entry)? {
+            hierarchy.add_class_file(&class)?;
+        }
+    }
+    for class in classes.iter() {
+        hierarchy.add_class_file(class)?;
+    }
+
+    let mut failed = false;
+    for class in classes.iter() {
+        for error in verify_class(class, &hierarchy) {
+            println!("{error}");
+            failed = true;
+        }
+    }
+    Ok(failed)
+}
+
+/// `-internal` leaves out dependencies on classes that weren't given, such as the JDK's
+fn deps_command(args: &[String]) -> Result<(), io::Error> {
+    let mut files = Vec::new();
+    let mut level = "class";
+    let mut internal = false;
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-level" => match args.next().map(String::as_str) {
+                Some(value @ ("class" | "package" | "jar")) => level = value,
+                _ => return Err(usage_error("-level must be class, package or jar")),
+            },
+            "-internal" => internal = true,
+            "-json" => json = true,
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown deps option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(usage_error("deps needs at least one class file or jar"));
+    }
+
+    let mut graph = DependencyGraph::new();
+    let mut sources = HashMap::new();
+    for file in files.iter() {
+        for class in read_classes(file)? {
+            graph
+                .add_class_file(&class)
+                .map_err(|e| io::Error::new(e.kind(), format!("{file}: {e}")))?;
+            sources.insert(class.class_name()?, file.to_string());
+        }
+    }
+    if internal {
+        graph = graph.internal();
+    }
+    graph = match level {
+        "package" => graph.packages(),
+        // classes outside the given files have no jar and drop out
+        "jar" => graph.aggregate(|class_name| sources.get(class_name).cloned()),
+        _ => graph,
+    };
+
+    if json {
+        println!("{}", graph.to_json().to_pretty_string());
+    } else {
+        print!("{}", graph.to_dot("dependencies"));
+    }
+    Ok(())
+}
+
+/// Prints every reference that wouldn't resolve, returning whether there were any
+fn link_command(args: &[String]) -> Result<bool, io::Error> {
+    let mut files = Vec::new();
+    let mut class_path = Vec::new();
+    let mut ignored = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" => class_path.extend(args.next()),
+            "-ignore" => ignored.extend(args.next()),
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown link option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(usage_error("link needs at least one class file or jar"));
+    }
+
+    let mut classes = Vec::new();
+    for file in files {
+        classes.extend(read_classes(file)?);
+    }
+    let mut hierarchy = ClassFileHierarchy::new();
+    for entry in class_path {
+        for class in read_classes(entry)? {
+            hierarchy.add_class_file(&class)?;
+        }
+    }
+    for class in classes.iter() {
+        hierarchy.add_class_file(class)?;
+    }
+
+    let mut checker = LinkChecker::new(&hierarchy);
+    for prefix in ignored {
+        checker.ignore(prefix);
+    }
+    let mut failed = false;
+    for class in classes.iter() {
+        for error in checker.check(class)? {
+            println!("{error}");
+            failed = true;
+        }
+    }
+    Ok(failed)
+}
+
+fn hierarchy_command(args: &[String]) -> Result<(), io::Error> {
+    let mut class_names = Vec::new();
+    let mut hierarchy = ClassFileHierarchy::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" => match args.next() {
+                Some(entry) => hierarchy.add_class_path(entry)?,
+                None => return Err(usage_error("-cp needs a class path entry")),
+            },
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown hierarchy option {flag}")))
+            }
+            class_name => class_names.push(class_name.replace('.', "/")),
+        }
+    }
+    if class_names.is_empty() {
+        return Err(usage_error("hierarchy needs at least one class name"));
+    }
+
+    for class_name in class_names {
+        if !hierarchy.contains(&class_name) {
+            return Err(usage_error(&format!(
+                "{class_name} isn't on the class path"
+            )));
+        }
+        let kind = if hierarchy.is_interface(&class_name) {
+            "interface"
+        } else {
+            "class"
+        };
+        println!("{kind} {class_name}");
+        let supertypes = hierarchy.all_supertypes(&class_name);
+        let (classes, interfaces): (Vec<_>, Vec<_>) = supertypes
+            .iter()
+            .partition(|supertype| !hierarchy.is_interface(supertype));
+        let list = |types: &[&String]| {
+            types
+                .iter()
+                .map(|t| t.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if !classes.is_empty() {
+            println!("  superclasses: {}", list(&classes));
+        }
+        if !interfaces.is_empty() {
+            println!("  interfaces: {}", list(&interfaces));
+        }
+        let subtypes = hierarchy.all_subtypes(&class_name);
+        if !subtypes.is_empty() {
+            println!("  subtypes: {}", list(&subtypes.iter().collect::<Vec<_>>()));
+        }
+    }
+    Ok(())
+}
+
+/// Entry points default to every `main` method in the given classes
+fn callgraph_command(args: &[String]) -> Result<(), io::Error> {
+    let mut files = Vec::new();
+    let mut class_path = Vec::new();
+    let mut entry_points = Vec::new();
+    let mut analysis = Analysis::ClassHierarchy;
+    let mut dot = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" => class_path.extend(args.next()),
+            "-rta" => analysis = Analysis::RapidType,
+            "-entry" => match args.next() {
+                Some(entry) => entry_points.push(MethodId::parse(entry)?),
+                None => return Err(usage_error("-entry needs a method")),
+            },
+            "-dot" => dot = true,
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown callgraph option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(usage_error(
+            "callgraph needs at least one class file or jar",
+        ));
+    }
+
+    let mut classes = Vec::new();
+    for file in files {
+        classes.extend(read_classes(file)?);
+    }
+    let mut hierarchy = ClassFileHierarchy::new();
+    for entry in class_path {
+        for class in read_classes(entry)? {
+            hierarchy.add_class_file(&class)?;
+        }
+    }
+    for class in classes.iter() {
+        hierarchy.add_class_file(class)?;
+    }
+    if entry_points.is_empty() {
+        for class in classes.iter() {
+            if class
+                .find_method("main", "([Ljava/lang/String;)V")
+                .is_some()
+            {
+                entry_points.push(MethodId::new(
+                    &class.class_name()?,
+                    "main",
+                    "([Ljava/lang/String;)V",
+                ));
+            }
+        }
+    }
+    if entry_points.is_empty() {
+        return Err(usage_error(
+            "no main methods found; give entry points with -entry",
+        ));
+    }
+
+    let graph = CallGraph::build(&classes, &hierarchy, &entry_points, analysis)?;
+    if dot {
+        print!("{}", graph.to_dot("calls"));
+        return Ok(());
+    }
+    let reachable: Vec<&MethodId> = graph
+        .reachable()
+        .iter()
+        .filter(|method| graph.methods().contains(*method))
+        .collect();
+    println!("reachable methods ({}):", reachable.len());
+    for method in reachable {
+        println!("    {method}");
+    }
+    let unreachable = graph.unreachable();
+    println!("unreachable methods ({}):", unreachable.len());
+    for method in unreachable {
+        println!("    {method}");
+    }
+    Ok(())
+}
+
+fn lint_command(args: &[String]) -> Result<bool, io::Error> {
+    if args.is_empty() {
+        return Err(usage_error("lint needs at least one class file or jar"));
+    }
+    let mut found = false;
+    for file in args {
+        for class in read_classes(file)? {
+            for finding in check_class(&class)? {
+                println!("{finding}");
+                found = true;
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Sorted by code size unless `-sort` names another column
+fn metrics_command(args: &[String]) -> Result<(), io::Error> {
+    let mut files = Vec::new();
+    let mut key = SortKey::Size;
+    let mut top = None;
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-sort" => {
+                let column = args
+                    .next()
+                    .ok_or_else(|| usage_error("-sort needs a column"))?;
+                key = SortKey::parse(column)?;
+            }
+            "-top" => match args.next().map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => top = Some(n),
+                _ => return Err(usage_error("-top needs a number of methods")),
+            },
+            "-json" => json = true,
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown metrics option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return Err(usage_error("metrics needs at least one class file or jar"));
+    }
+
+    let mut all = Vec::new();
+    for file in files {
+        for class in read_classes(file)? {
+            all.extend(class_metrics(&class)?);
+        }
+    }
+    metrics::sort(&mut all, key);
+    all.truncate(top.unwrap_or(all.len()));
+    if json {
+        println!("{}", metrics::to_json(&all).to_pretty_string());
+    } else {
+        print!("{}", metrics::report(&all));
+    }
+    Ok(())
+}
+
+/// Remaps every class and writes them out together with the resources from the input jars,
+/// which move with their packages. The first of several resources with the same name wins,
+/// but two classes with the same name are an error.
+fn remap_command(args: &[String]) -> Result<(), io::Error> {
+    let mut mapping_file = None;
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-mapping" => {
+                mapping_file = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("-mapping needs a file"))?,
+                )
+            }
+            "-o" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("-o needs a jar or directory"))?,
+                )
+            }
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown remap option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    let (Some(mapping_file), Some(output)) = (mapping_file, output) else {
+        return Err(usage_error("remap needs -mapping and -o"));
+    };
+    if files.is_empty() {
+        return Err(usage_error(
+            "remap needs at least one class file, jar or directory",
+        ));
+    }
+
+    let mapping = Mapping::parse(&std::fs::read_to_string(mapping_file)?)
+        .map_err(|e| io::Error::new(e.kind(), format!("{mapping_file}: {e}")))?;
+    let mut classes = Vec::new();
+    let mut resources = Vec::new();
+    for file in files {
+        classes.extend(read_classes(file)?);
+        for entry in read_resources(file)? {
+            resources.push(mapping.remap_resource(entry.name(), entry.data().to_vec()));
+        }
+    }
+    let mut hierarchy = ClassFileHierarchy::new();
+    for class in classes.iter() {
+        hierarchy.add_class_file(class)?;
+    }
+
+    let remapper = Remapper::new(&mapping, &hierarchy);
+    let mut names = HashSet::new();
+    let mut entries = Vec::new();
+    for (name, data) in resources {
+        if names.insert(name.clone()) {
+            entries.push(JarEntry::new(name, data));
+        }
+    }
+    for mut class in classes {
+        let class_name = class.class_name()?;
+        remapper
+            .remap(&mut class)
+            .map_err(|e| io::Error::new(e.kind(), format!("{class_name}: {e}")))?;
+        let name = format!("{}.class", class.class_name()?);
+        if !names.insert(name.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{class_name} is remapped to {name}, which is already taken"),
+            ));
+        }
+        entries.push(JarEntry::new(name, class.to_bytes()));
+    }
+    write_entries(output, entries)
+}
+
+/// Instruments the classes, or those in the packages given with `-include`, and writes every
+/// class out together with the resources from the input jars
+fn instrument_command(args: &[String]) -> Result<(), io::Error> {
+    let mut hook = None;
+    let mut output = None;
+    let mut class_path = Vec::new();
+    let mut includes = Vec::new();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-hook" => {
+                hook = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("-hook needs a class and method"))?,
+                )
+            }
+            "-o" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("-o needs a jar or directory"))?,
+                )
+            }
+            "-cp" => class_path.push(
+                args.next()
+                    .ok_or_else(|| usage_error("-cp needs a class file or jar"))?,
+            ),
+            "-include" => includes.push(
+                args.next()
+                    .ok_or_else(|| usage_error("-include needs a class name prefix"))?
+                    .replace('.', "/"),
+            ),
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown instrument option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    let (Some(hook), Some(output)) = (hook, output) else {
+        return Err(usage_error("instrument needs -hook and -o"));
+    };
+    let Some((hook_class, hook_method)) = hook.rsplit_once('.') else {
+        return Err(usage_error(&format!(
+            "expected -hook <class>.<method> but found {hook}"
+        )));
+    };
+    if files.is_empty() {
+        return Err(usage_error(
+            "instrument needs at least one class file, jar or directory",
+        ));
+    }
+
+    let mut classes = Vec::new();
+    let mut entries = Vec::new();
+    for file in files {
+        classes.extend(read_classes(file)?);
+        entries.extend(read_resources(file)?);
+    }
+    let mut hierarchy = ClassFileHierarchy::new();
+    for entry in class_path {
+        for class in read_classes(entry)? {
+            hierarchy.add_class_file(&class)?;
+        }
+    }
+    for class in classes.iter() {
+        hierarchy.add_class_file(class)?;
+    }
+
+    let instrumenter = Instrumenter::new(&hook_class.replace('.', "/"), hook_method, &hierarchy)?;
+    let mut methods = 0;
+    let mut instrumented = 0;
+    for mut class in classes {
+        let class_name = class.class_name()?;
+        if includes.is_empty() || includes.iter().any(|prefix| class_name.starts_with(prefix)) {
+            let count = instrumenter
+                .instrument(&mut class)
+                .map_err(|e| io::Error::new(e.kind(), format!("{class_name}.{e}")))?;
+            methods += count;
+            instrumented += (count > 0) as usize;
+        }
+        entries.push(JarEntry::new(
+            format!("{class_name}.class"),
+            class.to_bytes(),
+        ));
+    }
+    println!("instrumented {methods} methods in {instrumented} classes");
+    write_entries(output, entries)
+}
+
+/// Strips the debug attributes given with `-strip` and unused constants from every class,
+/// and writes them out together with the resources from the input jars
+fn shrink_command(args: &[String]) -> Result<(), io::Error> {
+    let mut shrinker = Shrinker::new();
+    let mut output = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-strip" => match args.next().map(String::as_str) {
+                Some("lines") => shrinker.set_strip_line_numbers(true),
+                Some("vars") => shrinker.set_strip_local_variables(true),
+                Some("source") => shrinker.set_strip_source(true),
+                Some("debug") => {
+                    shrinker.set_strip_line_numbers(true);
+                    shrinker.set_strip_local_variables(true);
+                    shrinker.set_strip_source(true);
+                }
+                Some(other) => {
+                    return Err(usage_error(&format!(
+                        "expected -strip lines, vars, source or debug but found {other}"
+                    )))
+                }
+                None => return Err(usage_error("-strip needs lines, vars, source or debug")),
+            },
+            "-o" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("-o needs a jar or directory"))?,
+                )
+            }
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown shrink option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    let Some(output) = output else {
+        return Err(usage_error("shrink needs -o"));
+    };
+    if files.is_empty() {
+        return Err(usage_error(
+            "shrink needs at least one class file, jar or directory",
+        ));
+    }
+
+    let mut entries = Vec::new();
+    let mut before = 0;
+    let mut after = 0;
+    let mut count = 0;
+    for file in files {
+        for mut class in read_classes(file)? {
+            let class_name = class.class_name()?;
+            before += class.to_bytes().len();
+            shrinker
+                .shrink(&mut class)
+                .map_err(|e| io::Error::new(e.kind(), format!("{class_name}: {e}")))?;
+            let bytes = class.to_bytes();
+            after += bytes.len();
+            count += 1;
+            entries.push(JarEntry::new(format!("{class_name}.class"), bytes));
+        }
+        entries.extend(read_resources(file)?);
+    }
+    println!("shrank {count} classes from {before} to {after} bytes");
+    write_entries(output, entries)
+}
+
+/// Runs the peephole passes given with `-pass`, or all of them, over every class, and writes
+/// them out together with the resources from the input jars
+fn optimize_command(args: &[String]) -> Result<(), io::Error> {
+    let mut passes = Vec::new();
+    let mut output = None;
+    let mut class_path = Vec::new();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-pass" => {
+                let names = Pass::ALL.map(|pass| pass.name()).join(", ");
+                let name = args
+                    .next()
+                    .ok_or_else(|| usage_error(&format!("-pass needs one of {names}")))?;
+                passes.push(Pass::from_name(name).ok_or_else(|| {
+                    usage_error(&format!("expected -pass {names} but found {name}"))
+                })?);
+            }
+            "-o" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| usage_error("-o needs a jar or directory"))?,
+                )
+            }
+            "-cp" => class_path.push(
+                args.next()
+                    .ok_or_else(|| usage_error("-cp needs a class file or jar"))?,
+            ),
+            flag if flag.starts_with('-') => {
+                return Err(usage_error(&format!("unknown optimize option {flag}")))
+            }
+            file => files.push(file),
+        }
+    }
+    let Some(output) = output else {
+        return Err(usage_error("optimize needs -o"));
+    };
+    if files.is_empty() {
+        return Err(usage_error(
+            "optimize needs at least one class file, jar or directory",
+        ));
+    }
+
+    let mut classes = Vec::new();
+    let mut entries = Vec::new();
+    for file in files {
+        classes.extend(read_classes(file)?);
+        entries.extend(read_resources(file)?);
+    }
+    let mut hierarchy = ClassFileHierarchy::new();
+    for entry in class_path {
+        for class in read_classes(entry)? {
+            hierarchy.add_class_file(&class)?;
+        }
+    }
+    for class in classes.iter() {
+        hierarchy.add_class_file(class)?;
+    }
+
+    let mut optimizer = Optimizer::new(&hierarchy);
+    if !passes.is_empty() {
+        optimizer.set_passes(passes);
+    }
+    let mut methods = 0;
+    let mut before = (0, 0);
+    let mut after = (0, 0);
+    for mut class in classes {
+        let class_name = class.class_name()?;
+        let size = code_size(&class)?;
+        before = (before.0 + size.0, before.1 + size.1);
+        methods += optimizer
+            .optimize(&mut class)
+            .map_err(|e| io::Error::new(e.kind(), format!("{class_name}.{e}")))?;
+        let size = code_size(&class)?;
+        after = (after.0 + size.0, after.1 + size.1);
+        entries.push(JarEntry::new(
+            format!("{class_name}.class"),
+            class.to_bytes(),
+        ));
+    }
+    println!(
+        "optimized {methods} methods from {} to {} instructions and {} to {} code bytes",
+        before.0, after.0, before.1, after.1
+    );
+    write_entries(output, entries)
+}
+
+fn decompile_command(args: &[String]) -> Result<(), io::Error> {
+    if let Some(flag) = args.iter().find(|arg| arg.starts_with('-')) {
+        return Err(usage_error(&format!("unknown decompile option {flag}")));
+    }
+    if args.is_empty() {
+        return Err(usage_error(
+            "decompile needs at least one class file, jar or directory",
+        ));
+    }
+    let mut first = true;
+    for file in args {
+        for class in read_classes(file)? {
+            if !first {
+                println!();
+            }
+            first = false;
+            print!("{}", decompile(&class)?);
+        }
+    }
+    Ok(())
+}
+
+/// The number of instructions and bytes of code in all of a class's methods
+fn code_size(class: &ClassFile) -> Result<(usize, usize), io::Error> {
+    let mut size = (0, 0);
+    for method in class.methods() {
+        if let Some(code) = method.code(class.constant_pool())? {
+            size.0 += code.instructions().count();
+            size.1 += code.code().len();
+        }
+    }
+    Ok(size)
+}
+
+/// The files in a jar other than class files, or nothing for anything else
+fn read_resources(file: &str) -> Result<Vec<JarEntry>, io::Error> {
+    if !file.ends_with(".jar") {
+        return Ok(Vec::new());
+    }
+    let jar = JarFile::read_file(file)?;
+    Ok(jar
+        .entries()
+        .iter()
+        .filter(|entry| !entry.is_class_file())
+        .map(|entry| JarEntry::new(entry.name().to_string(), entry.data().to_vec()))
+        .collect())
+}
+
+/// Writes a jar if the output ends in `.jar`, or else files under the output directory
+fn write_entries(output: &str, entries: Vec<JarEntry>) -> Result<(), io::Error> {
+    if output.ends_with(".jar") {
+        return JarFile::new(entries).write_file(output);
+    }
+    for entry in entries {
+        let path = Path::new(output).join(entry.name());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, entry.data())?;
+    }
+    Ok(())
+}
+
+/// Every class in a jar or directory, or a single class file
+fn read_classes(file: &str) -> Result<Vec<ClassFile>, io::Error> {
+    let mut classes = Vec::new();
+    for entry in read_class_path(file)? {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_slice(entry.data())
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", entry.source())))?;
+        classes.push(class_file);
+    }
+    Ok(classes)
 }
@@ -0,0 +1,1034 @@
+use crate::class_file::access_flags::{
+    ACC_ABSTRACT, ACC_FINAL, ACC_INTERFACE, ACC_PRIVATE, ACC_PROTECTED, ACC_PUBLIC, ACC_STATIC,
+    ACC_SYNTHETIC,
+};
+use crate::class_file::attribute::attribute_info::find_attribute;
+use crate::class_file::attribute::constant_value::ConstantValue;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::descriptor::MethodDescriptor;
+use crate::class_file::member_info::MemberInfo;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io;
+
+// Compares two versions of a library's API and classifies every difference using the rules
+// for binary compatibility in chapter 13 of the Java Language Specification (JLS).
+// Only what other packages can link against is considered: public classes, and their
+// public and protected members that aren't synthetic.
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Severity {
+    /// Pre-existing binaries keep linking and behave the same
+    Compatible,
+    /// Binary compatible, but pre-existing binaries may fail at run time (e.g. an
+    /// `AbstractMethodError` after adding an interface method)
+    Warning,
+    /// Pre-existing binaries may fail to link, or keep using stale inlined constants
+    Breaking,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Compatible => "compatible",
+            Severity::Warning => "warning",
+            Severity::Breaking => "BREAKING",
+        })
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    ClassAdded,
+    ClassRemoved,
+    ClassKindChanged,
+    ClassAccessChanged,
+    ClassMadeAbstract,
+    ClassMadeFinal,
+    SuperclassChanged,
+    InterfacesChanged,
+    MemberAdded,
+    MemberRemoved,
+    DescriptorChanged,
+    AccessChanged,
+    StaticChanged,
+    MadeFinal,
+    MadeAbstract,
+    ConstantChanged,
+}
+
+/// One difference between the old and new version of a class
+#[derive(Clone, PartialEq, Debug)]
+pub struct Change {
+    kind: ChangeKind,
+    severity: Severity,
+    class_name: String,
+    member: Option<String>,
+    description: String,
+    /// The JLS section the classification comes from, e.g. `13.4.7`
+    section: &'static str,
+}
+
+impl Change {
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// `name(descriptor)` for methods and `name:descriptor` for fields
+    pub fn member(&self) -> Option<&str> {
+        self.member.as_deref()
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn section(&self) -> &'static str {
+        self.section
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.class_name)?;
+        if let Some(member) = &self.member {
+            write!(f, ".{member}")?;
+        }
+        write!(f, ": {} (JLS {})", self.description, self.section)
+    }
+}
+
+/// Compares two versions of one class
+pub fn compare_classes(old: &ClassFile, new: &ClassFile) -> Result<Vec<Change>, io::Error> {
+    let old = ClassSet::new(std::slice::from_ref(old))?;
+    let new = ClassSet::new(std::slice::from_ref(new))?;
+    let (old_class, new_class) = match (old.classes.values().next(), new.classes.values().next()) {
+        (Some(old_class), Some(new_class)) => (old_class, new_class),
+        _ => return Ok(Vec::new()),
+    };
+    let mut changes = Vec::new();
+    Comparison {
+        old: &old,
+        new: &new,
+        changes: &mut changes,
+    }
+    .compare_class(old_class, new_class);
+    Ok(changes)
+}
+
+/// Compares two versions of a whole library (e.g. every class in two jars), matching
+/// classes by name
+pub fn compare_class_sets(old: &[ClassFile], new: &[ClassFile]) -> Result<Vec<Change>, io::Error> {
+    let old = ClassSet::new(old)?;
+    let new = ClassSet::new(new)?;
+    let mut changes = Vec::new();
+    let mut comparison = Comparison {
+        old: &old,
+        new: &new,
+        changes: &mut changes,
+    };
+    for (name, old_class) in &old.classes {
+        match new.classes.get(name) {
+            Some(new_class) => comparison.compare_class(old_class, new_class),
+            None if old_class.is_public() => comparison.class_change(
+                old_class,
+                ChangeKind::ClassRemoved,
+                Severity::Breaking,
+                "13.3",
+                "class removed".to_string(),
+            ),
+            None => {}
+        }
+    }
+    for (name, new_class) in &new.classes {
+        if !old.classes.contains_key(name) && new_class.is_public() {
+            comparison.class_change(
+                new_class,
+                ChangeKind::ClassAdded,
+                Severity::Compatible,
+                "13.3",
+                "class added".to_string(),
+            );
+        }
+    }
+    Ok(changes)
+}
+
+/// Whether any change would break pre-existing binaries
+pub fn has_breaking_changes(changes: &[Change]) -> bool {
+    changes
+        .iter()
+        .any(|change| change.severity == Severity::Breaking)
+}
+
+/// What comparing needs to know about a class file
+struct Class {
+    name: String,
+    access_flags: u16,
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    fields: Vec<Member>,
+    methods: Vec<Member>,
+}
+
+impl Class {
+    fn from(class_file: &ClassFile) -> Result<Class, io::Error> {
+        let constant_pool = class_file.constant_pool();
+        let members = |members: &[MemberInfo]| -> Result<Vec<Member>, io::Error> {
+            members
+                .iter()
+                .map(|member| Member::from(member, constant_pool))
+                .collect()
+        };
+        Ok(Class {
+            name: class_file.class_name()?,
+            access_flags: class_file.access_flags(),
+            super_class: class_file.super_class_name()?,
+            interfaces: class_file.interface_names()?,
+            fields: members(class_file.fields())?,
+            methods: members(class_file.methods())?,
+        })
+    }
+
+    fn is_public(&self) -> bool {
+        self.access_flags & ACC_PUBLIC != 0
+    }
+
+    fn is_interface(&self) -> bool {
+        self.access_flags & ACC_INTERFACE != 0
+    }
+
+    fn kind(&self) -> &'static str {
+        if self.is_interface() {
+            "interface"
+        } else {
+            "class"
+        }
+    }
+
+    /// Methods and fields other packages can link against, keyed by their display name
+    fn api(&self, members: &[Member]) -> BTreeMap<String, Member> {
+        members
+            .iter()
+            .filter(|member| member.is_api() && member.name != "<clinit>")
+            .map(|member| (member.key.clone(), member.clone()))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+struct Member {
+    /// `name(descriptor)` or `name:descriptor`
+    key: String,
+    name: String,
+    descriptor: String,
+    access_flags: u16,
+    constant: Option<Constant>,
+}
+
+impl Member {
+    fn from(member: &MemberInfo, constant_pool: &ConstantPool) -> Result<Member, io::Error> {
+        let name = member.name(constant_pool)?;
+        let descriptor = member.descriptor(constant_pool)?;
+        let key = if descriptor.starts_with('(') {
+            format!("{name}{descriptor}")
+        } else {
+            format!("{name}:{descriptor}")
+        };
+        let constant = match find_attribute(member.attributes(), constant_pool, ConstantValue::NAME)
+        {
+            Some(attribute)
+                if member.access_flags() & (ACC_STATIC | ACC_FINAL) == ACC_STATIC | ACC_FINAL =>
+            {
+                let value = ConstantValue::from(&mut attribute.reader())?;
+                Some(Constant::from(constant_pool, value.constantvalue_index())?)
+            }
+            _ => None,
+        };
+        Ok(Member {
+            key,
+            name,
+            descriptor,
+            access_flags: member.access_flags(),
+            constant,
+        })
+    }
+
+    fn is_method(&self) -> bool {
+        self.descriptor.starts_with('(')
+    }
+
+    fn is_api(&self) -> bool {
+        self.access_flags & (ACC_PUBLIC | ACC_PROTECTED) != 0
+            && self.access_flags & ACC_SYNTHETIC == 0
+    }
+
+    fn is_static(&self) -> bool {
+        self.access_flags & ACC_STATIC != 0
+    }
+}
+
+/// 0 for private up to 3 for public, so that reductions can be spotted (JLS 6.6)
+fn access_level(access_flags: u16) -> u8 {
+    if access_flags & ACC_PUBLIC != 0 {
+        3
+    } else if access_flags & ACC_PROTECTED != 0 {
+        2
+    } else if access_flags & ACC_PRIVATE != 0 {
+        0
+    } else {
+        1
+    }
+}
+
+fn access_name(access_flags: u16) -> &'static str {
+    match access_level(access_flags) {
+        3 => "public",
+        2 => "protected",
+        1 => "package-private",
+        _ => "private",
+    }
+}
+
+/// The value of a constant variable, which compilers inline into other classes (JLS 13.1)
+#[derive(Clone, PartialEq)]
+enum Constant {
+    Integer(i32),
+    Long(i64),
+    /// Compared by bits so that NaN is equal to itself
+    Float(u32),
+    Double(u64),
+    String(String),
+}
+
+impl Constant {
+    fn from(constant_pool: &ConstantPool, index: u16) -> Result<Constant, io::Error> {
+        let data = constant_pool.get(index).map(|item| item.data());
+        Ok(match data {
+            Some(ConstantPoolData::Integer(value)) => Constant::Integer(value.value()),
+            Some(ConstantPoolData::Long(value)) => Constant::Long(value.value()),
+            Some(ConstantPoolData::Float(value)) => Constant::Float(value.bytes()),
+            Some(ConstantPoolData::Double(value)) => {
+                Constant::Double(((value.high_bytes() as u64) << 32) | value.low_bytes() as u64)
+            }
+            Some(ConstantPoolData::String(value)) => {
+                Constant::String(constant_pool.utf8(value.string_index())?)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("ConstantValue index {index} is not a constant"),
+                ))
+            }
+        })
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Integer(value) => write!(f, "{value}"),
+            Constant::Long(value) => write!(f, "{value}L"),
+            Constant::Float(bits) => write!(f, "{:?}f", f32::from_bits(*bits)),
+            Constant::Double(bits) => write!(f, "{:?}", f64::from_bits(*bits)),
+            Constant::String(value) => write!(f, "{value:?}"),
+        }
+    }
+}
+
+/// One version of the library, used to follow superclasses and superinterfaces
+struct ClassSet {
+    classes: BTreeMap<String, Class>,
+}
+
+impl ClassSet {
+    fn new(class_files: &[ClassFile]) -> Result<ClassSet, io::Error> {
+        let mut classes = BTreeMap::new();
+        for class_file in class_files {
+            let class = Class::from(class_file)?;
+            classes.insert(class.name.clone(), class);
+        }
+        Ok(ClassSet { classes })
+    }
+
+    /// Superclasses in order, as far as they are known; the chain ends at the first class
+    /// that isn't part of the set
+    fn superclasses(&self, class: &Class) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut next = class.super_class.clone();
+        while let Some(name) = next {
+            if chain.contains(&name) {
+                break;
+            }
+            next = self
+                .classes
+                .get(&name)
+                .and_then(|class| class.super_class.clone());
+            chain.push(name);
+        }
+        chain
+    }
+
+    /// Every interface implemented directly or through superclasses and superinterfaces
+    fn all_interfaces(&self, class: &Class) -> BTreeSet<String> {
+        let mut interfaces = BTreeSet::new();
+        let mut pending: Vec<String> = class.interfaces.clone();
+        for superclass in self.superclasses(class) {
+            if let Some(superclass) = self.classes.get(&superclass) {
+                pending.extend(superclass.interfaces.iter().cloned());
+            }
+        }
+        while let Some(name) = pending.pop() {
+            if interfaces.insert(name.clone()) {
+                if let Some(interface) = self.classes.get(&name) {
+                    pending.extend(interface.interfaces.iter().cloned());
+                }
+            }
+        }
+        interfaces
+    }
+
+    /// Finds an accessible method with the same signature declared by a superclass
+    fn inherited_method(&self, class: &Class, key: &str) -> Option<String> {
+        self.superclasses(class).into_iter().find(|name| {
+            self.classes.get(name).is_some_and(|superclass| {
+                superclass
+                    .methods
+                    .iter()
+                    .any(|method| method.key == key && method.is_api())
+            })
+        })
+    }
+}
+
+struct Comparison<'a> {
+    old: &'a ClassSet,
+    new: &'a ClassSet,
+    changes: &'a mut Vec<Change>,
+}
+
+impl Comparison<'_> {
+    fn class_change(
+        &mut self,
+        class: &Class,
+        kind: ChangeKind,
+        severity: Severity,
+        section: &'static str,
+        description: String,
+    ) {
+        self.changes.push(Change {
+            kind,
+            severity,
+            class_name: class.name.clone(),
+            member: None,
+            description,
+            section,
+        });
+    }
+
+    fn member_change(
+        &mut self,
+        class: &Class,
+        member: &Member,
+        kind: ChangeKind,
+        severity: Severity,
+        section: &'static str,
+        description: String,
+    ) {
+        self.changes.push(Change {
+            kind,
+            severity,
+            class_name: class.name.clone(),
+            member: Some(member.key.clone()),
+            description,
+            section,
+        });
+    }
+
+    fn compare_class(&mut self, old: &Class, new: &Class) {
+        // non-public classes can only be used from their own package, which ships with them
+        if !old.is_public() {
+            return;
+        }
+        if !new.is_public() {
+            let description = format!("{} is no longer public", old.kind());
+            self.class_change(
+                old,
+                ChangeKind::ClassAccessChanged,
+                Severity::Breaking,
+                "13.4.3",
+                description,
+            );
+            return;
+        }
+        if old.is_interface() != new.is_interface() {
+            let description = format!("changed from {} to {}", old.kind(), new.kind());
+            self.class_change(
+                old,
+                ChangeKind::ClassKindChanged,
+                Severity::Breaking,
+                "13.4.1",
+                description,
+            );
+            return;
+        }
+
+        if !old.is_interface() {
+            if old.access_flags & ACC_ABSTRACT == 0 && new.access_flags & ACC_ABSTRACT != 0 {
+                let description = "class made abstract".to_string();
+                self.class_change(
+                    old,
+                    ChangeKind::ClassMadeAbstract,
+                    Severity::Breaking,
+                    "13.4.1",
+                    description,
+                );
+            }
+            if old.access_flags & ACC_FINAL == 0 && new.access_flags & ACC_FINAL != 0 {
+                let description = "class made final".to_string();
+                self.class_change(
+                    old,
+                    ChangeKind::ClassMadeFinal,
+                    Severity::Breaking,
+                    "13.4.2",
+                    description,
+                );
+            }
+            self.compare_superclasses(old, new);
+        }
+        self.compare_interfaces(old, new);
+
+        let section = if old.is_interface() {
+            "13.5.3"
+        } else {
+            "13.4.6"
+        };
+        self.compare_members(
+            old,
+            new,
+            &old.api(&old.fields),
+            &new.api(&new.fields),
+            section,
+        );
+        self.compare_members(
+            old,
+            new,
+            &old.api(&old.methods),
+            &new.api(&new.methods),
+            section,
+        );
+    }
+
+    /// Inserting a class into the chain is fine, losing one from it is not (JLS 13.4.4)
+    fn compare_superclasses(&mut self, old: &Class, new: &Class) {
+        if old.super_class == new.super_class {
+            return;
+        }
+        let new_chain = self.new.superclasses(new);
+        let lost: Vec<String> = self
+            .old
+            .superclasses(old)
+            .into_iter()
+            .filter(|name| !new_chain.contains(name))
+            .collect();
+        let from = old.super_class.as_deref().unwrap_or("none");
+        let to = new.super_class.as_deref().unwrap_or("none");
+        if lost.is_empty() {
+            let description = format!("superclass changed from {from} to {to}");
+            self.class_change(
+                old,
+                ChangeKind::SuperclassChanged,
+                Severity::Compatible,
+                "13.4.4",
+                description,
+            );
+        } else {
+            let description = format!(
+                "superclass changed from {from} to {to}, no longer a subclass of {}",
+                lost.join(", ")
+            );
+            self.class_change(
+                old,
+                ChangeKind::SuperclassChanged,
+                Severity::Breaking,
+                "13.4.4",
+                description,
+            );
+        }
+    }
+
+    fn compare_interfaces(&mut self, old: &Class, new: &Class) {
+        let section = if old.is_interface() {
+            "13.5.2"
+        } else {
+            "13.4.4"
+        };
+        let old_interfaces = self.old.all_interfaces(old);
+        let new_interfaces = self.new.all_interfaces(new);
+        let removed: Vec<&str> = old_interfaces
+            .difference(&new_interfaces)
+            .map(String::as_str)
+            .collect();
+        let added: Vec<&str> = new_interfaces
+            .difference(&old_interfaces)
+            .map(String::as_str)
+            .collect();
+        if !removed.is_empty() {
+            let description = format!("no longer implements {}", removed.join(", "));
+            self.class_change(
+                old,
+                ChangeKind::InterfacesChanged,
+                Severity::Breaking,
+                section,
+                description,
+            );
+        }
+        if !added.is_empty() {
+            let description = format!("now implements {}", added.join(", "));
+            self.class_change(
+                old,
+                ChangeKind::InterfacesChanged,
+                Severity::Compatible,
+                section,
+                description,
+            );
+        }
+    }
+
+    fn compare_members(
+        &mut self,
+        old_class: &Class,
+        new_class: &Class,
+        old: &BTreeMap<String, Member>,
+        new: &BTreeMap<String, Member>,
+        section: &'static str,
+    ) {
+        let removed: Vec<&Member> = old.values().filter(|m| !new.contains_key(&m.key)).collect();
+        let added: Vec<&Member> = new.values().filter(|m| !old.contains_key(&m.key)).collect();
+
+        // a member that still exists with the same name but a different type was changed
+        // rather than removed, as long as the match is unambiguous
+        let same_name = |members: &[&Member], member: &Member| -> usize {
+            members.iter().filter(|m| m.name == member.name).count()
+        };
+        let mut changed = BTreeSet::new();
+        for old_member in &removed {
+            let candidates: Vec<&&Member> =
+                added.iter().filter(|m| m.name == old_member.name).collect();
+            if let [new_member] = candidates[..] {
+                if same_name(&removed, old_member) == 1 && old_member.name != "<init>" {
+                    self.descriptor_changed(old_class, old_member, new_member);
+                    changed.insert(old_member.key.clone());
+                    changed.insert(new_member.key.clone());
+                }
+            }
+        }
+
+        for old_member in removed.iter().filter(|m| !changed.contains(&m.key)) {
+            self.member_removed(old_class, new_class, old_member, section);
+        }
+        for new_member in added.iter().filter(|m| !changed.contains(&m.key)) {
+            self.member_added(new_class, new_member, section);
+        }
+        for (key, old_member) in old {
+            if let Some(new_member) = new.get(key) {
+                self.compare_member(old_class, new_class, old_member, new_member);
+            }
+        }
+        // members that became private or package-private drop out of the API entirely
+        for new_member in new_class.fields.iter().chain(&new_class.methods) {
+            if let Some(old_member) = old.get(&new_member.key) {
+                if !new_member.is_api() {
+                    self.access_reduced(old_class, old_member, new_member);
+                }
+            }
+        }
+    }
+
+    fn member_removed(
+        &mut self,
+        old_class: &Class,
+        new_class: &Class,
+        member: &Member,
+        section: &'static str,
+    ) {
+        // still declared but no longer accessible, which compare_members reports as an
+        // access change instead
+        let redeclared = new_class
+            .fields
+            .iter()
+            .chain(&new_class.methods)
+            .any(|m| m.key == member.key);
+        if redeclared {
+            return;
+        }
+        if member.is_method() && !member.is_static() {
+            if let Some(superclass) = self.new.inherited_method(new_class, &member.key) {
+                let description = format!("method moved to superclass {superclass}");
+                self.member_change(
+                    old_class,
+                    member,
+                    ChangeKind::MemberRemoved,
+                    Severity::Compatible,
+                    "13.4.6",
+                    description,
+                );
+                return;
+            }
+        }
+        let description = format!("{} removed", member_kind(member));
+        self.member_change(
+            old_class,
+            member,
+            ChangeKind::MemberRemoved,
+            Severity::Breaking,
+            section,
+            description,
+        );
+    }
+
+    fn member_added(&mut self, new_class: &Class, member: &Member, section: &'static str) {
+        let abstract_method = member.access_flags & ACC_ABSTRACT != 0;
+        let (severity, description) = if abstract_method {
+            // existing implementations won't have it and fail with AbstractMethodError
+            (Severity::Warning, "abstract method added".to_string())
+        } else {
+            (
+                Severity::Compatible,
+                format!("{} added", member_kind(member)),
+            )
+        };
+        let section = if abstract_method && !new_class.is_interface() {
+            "13.4.16"
+        } else {
+            section
+        };
+        self.member_change(
+            new_class,
+            member,
+            ChangeKind::MemberAdded,
+            severity,
+            section,
+            description,
+        );
+    }
+
+    fn descriptor_changed(&mut self, class: &Class, old: &Member, new: &Member) {
+        let (section, description) = if !old.is_method() || !new.is_method() {
+            (
+                "13.4.8",
+                format!("type changed from {} to {}", old.descriptor, new.descriptor),
+            )
+        } else {
+            let parameters = |descriptor: &str| {
+                MethodDescriptor::parse(descriptor)
+                    .map(|d| d.parameters().to_vec())
+                    .ok()
+            };
+            if parameters(&old.descriptor) == parameters(&new.descriptor) {
+                (
+                    "13.4.15",
+                    format!("return type changed, now {}", new.descriptor),
+                )
+            } else {
+                (
+                    "13.4.14",
+                    format!("parameters changed, now {}", new.descriptor),
+                )
+            }
+        };
+        self.member_change(
+            class,
+            old,
+            ChangeKind::DescriptorChanged,
+            Severity::Breaking,
+            section,
+            description,
+        );
+    }
+
+    fn access_reduced(&mut self, class: &Class, old: &Member, new: &Member) {
+        let description = format!(
+            "access reduced from {} to {}",
+            access_name(old.access_flags),
+            access_name(new.access_flags)
+        );
+        self.member_change(
+            class,
+            old,
+            ChangeKind::AccessChanged,
+            Severity::Breaking,
+            "13.4.7",
+            description,
+        );
+    }
+
+    fn compare_member(&mut self, old_class: &Class, new_class: &Class, old: &Member, new: &Member) {
+        let (old_flags, new_flags) = (old.access_flags, new.access_flags);
+        if access_level(new_flags) < access_level(old_flags) {
+            self.access_reduced(old_class, old, new);
+        }
+
+        if old.is_static() != new.is_static() {
+            let (section, now) = match (old.is_method(), new.is_static()) {
+                (true, true) => ("13.4.19", "now static"),
+                (true, false) => ("13.4.19", "no longer static"),
+                (false, true) => ("13.4.10", "now static"),
+                (false, false) => ("13.4.10", "no longer static"),
+            };
+            let description = format!("{} {now}", member_kind(old));
+            self.member_change(
+                old_class,
+                old,
+                ChangeKind::StaticChanged,
+                Severity::Breaking,
+                section,
+                description,
+            );
+        }
+
+        if old_flags & ACC_FINAL == 0 && new_flags & ACC_FINAL != 0 {
+            if !old.is_method() {
+                // existing binaries that assign the field fail with IllegalAccessError
+                let description = "field made final".to_string();
+                self.member_change(
+                    old_class,
+                    old,
+                    ChangeKind::MadeFinal,
+                    Severity::Breaking,
+                    "13.4.9",
+                    description,
+                );
+            } else if !new.is_static() && new_class.access_flags & ACC_FINAL == 0 {
+                // only matters if subclasses could override it
+                let description = "method made final".to_string();
+                self.member_change(
+                    old_class,
+                    old,
+                    ChangeKind::MadeFinal,
+                    Severity::Breaking,
+                    "13.4.17",
+                    description,
+                );
+            }
+        }
+
+        if old.is_method() && old_flags & ACC_ABSTRACT == 0 && new_flags & ACC_ABSTRACT != 0 {
+            let section = if new_class.is_interface() {
+                "13.5.6"
+            } else {
+                "13.4.16"
+            };
+            let description = "method made abstract".to_string();
+            self.member_change(
+                old_class,
+                old,
+                ChangeKind::MadeAbstract,
+                Severity::Breaking,
+                section,
+                description,
+            );
+        }
+
+        // other classes inlined the old value and won't see the new one until recompiled
+        let description = match (&old.constant, &new.constant) {
+            (Some(before), Some(after)) if before != after => {
+                Some(format!("constant value changed from {before} to {after}"))
+            }
+            (Some(before), None) => {
+                Some(format!("no longer a constant, inlined value was {before}"))
+            }
+            _ => None,
+        };
+        if let Some(description) = description {
+            self.member_change(
+                old_class,
+                old,
+                ChangeKind::ConstantChanged,
+                Severity::Breaking,
+                "13.4.9",
+                description,
+            );
+        }
+    }
+}
+
+fn member_kind(member: &Member) -> &'static str {
+    if member.name == "<init>" {
+        "constructor"
+    } else if member.is_method() {
+        "method"
+    } else {
+        "field"
+    }
+}
+
+/// One line per change, most severe first, followed by a summary
+pub fn report(changes: &[Change]) -> String {
+    let mut sorted: Vec<&Change> = changes.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.class_name.cmp(&b.class_name))
+            .then_with(|| a.member.cmp(&b.member))
+    });
+    let mut out = String::new();
+    for change in &sorted {
+        out.push_str(&format!("{change}\n"));
+    }
+    let count = |severity: Severity| changes.iter().filter(|c| c.severity == severity).count();
+    out.push_str(&format!(
+        "breaking: {}, warning: {}, compatible: {}\n",
+        count(Severity::Breaking),
+        count(Severity::Warning),
+        count(Severity::Compatible)
+    ));
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::jar::JarFile;
+
+    fn read_jar(path: &str) -> Vec<ClassFile> {
+        let jar = JarFile::read_file(path).unwrap();
+        jar.entries()
+            .iter()
+            .filter(|entry| entry.is_class_file())
+            .map(|entry| {
+                let mut class_file = ClassFile::new();
+                class_file.read_slice(entry.data()).unwrap();
+                class_file
+            })
+            .collect()
+    }
+
+    fn find<'a>(changes: &'a [Change], class_name: &str, member: Option<&str>) -> Vec<&'a Change> {
+        changes
+            .iter()
+            .filter(|c| c.class_name() == class_name && c.member() == member)
+            .collect()
+    }
+
+    fn assert_change(
+        changes: &[Change],
+        member: &str,
+        kind: ChangeKind,
+        severity: Severity,
+        section: &str,
+    ) {
+        let found = find(changes, "compat/Api", Some(member));
+        assert!(
+            found
+                .iter()
+                .any(|c| c.kind() == kind && c.severity() == severity && c.section() == section),
+            "no {kind:?} for {member}: {found:?}"
+        );
+    }
+
+    #[test]
+    fn jar_versions() {
+        let old = read_jar("tests/java/compat/old.jar");
+        let new = read_jar("tests/java/compat/new.jar");
+        let changes = compare_class_sets(&old, &new).unwrap();
+        assert!(has_breaking_changes(&changes));
+
+        use ChangeKind::*;
+        use Severity::*;
+        assert_change(&changes, "removed()V", MemberRemoved, Breaking, "13.4.6");
+        assert_change(&changes, "pulledUp()V", MemberRemoved, Compatible, "13.4.6");
+        assert_change(
+            &changes,
+            "changed(I)I",
+            DescriptorChanged,
+            Breaking,
+            "13.4.15",
+        );
+        assert_change(&changes, "count:I", DescriptorChanged, Breaking, "13.4.8");
+        assert_change(
+            &changes,
+            "label:Ljava/lang/String;",
+            MadeFinal,
+            Breaking,
+            "13.4.9",
+        );
+        assert_change(&changes, "total:J", AccessChanged, Breaking, "13.4.7");
+        assert_change(&changes, "hidden()V", AccessChanged, Breaking, "13.4.7");
+        assert_change(&changes, "overridable()V", MadeFinal, Breaking, "13.4.17");
+        assert_change(&changes, "helper()V", StaticChanged, Breaking, "13.4.19");
+        assert_change(&changes, "LIMIT:I", ConstantChanged, Breaking, "13.4.9");
+        assert_change(&changes, "added()V", MemberAdded, Compatible, "13.4.6");
+        assert!(find(&changes, "compat/Api", Some("NAME:Ljava/lang/String;")).is_empty());
+        assert!(find(&changes, "compat/Api", Some("secret2()V")).is_empty());
+
+        let interfaces = find(&changes, "compat/Api", None);
+        assert_eq!(interfaces.len(), 1);
+        assert_eq!(
+            interfaces[0].description(),
+            "no longer implements java/io/Serializable"
+        );
+
+        let service = find(&changes, "compat/Service", Some("extra()V"));
+        assert_eq!(service[0].severity(), Warning);
+        let fallback = find(&changes, "compat/Service", Some("fallback()V"));
+        assert_eq!(fallback[0].severity(), Compatible);
+
+        assert_eq!(find(&changes, "compat/Gone", None)[0].kind(), ClassRemoved);
+        assert_eq!(find(&changes, "compat/Fresh", None)[0].kind(), ClassAdded);
+        assert_eq!(
+            find(&changes, "compat/Stays", None)[0].kind(),
+            ClassMadeFinal
+        );
+
+        let text = report(&changes);
+        assert!(text.contains(
+            "BREAKING: compat/Api.LIMIT:I: constant value changed from 10 to 20 (JLS 13.4.9)\n"
+        ));
+        assert!(text.starts_with("BREAKING"));
+    }
+
+    #[test]
+    fn identical_classes() {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/helloworld/HelloWorld.class")
+            .unwrap();
+        let changes = compare_classes(&class_file, &class_file).unwrap();
+        assert!(changes.is_empty());
+        assert_eq!(report(&changes), "breaking: 0, warning: 0, compatible: 0\n");
+    }
+
+    #[test]
+    fn superclass_insertion_is_compatible() {
+        let classes = |source: &str| -> Vec<ClassFile> {
+            source
+                .split("---")
+                .map(|part| crate::assembler::assembler::assemble(part).unwrap())
+                .collect()
+        };
+        let old = classes(".class public A\n.super java/lang/Object\n");
+        let new =
+            classes(".class public A\n.super B\n---\n.class public B\n.super java/lang/Object\n");
+        let changes = compare_class_sets(&old, &new).unwrap();
+        let superclass = find(&changes, "A", None);
+        assert_eq!(superclass[0].severity(), Severity::Compatible);
+
+        let changes = compare_class_sets(&new, &old).unwrap();
+        let superclass = find(&changes, "A", None);
+        assert_eq!(superclass[0].severity(), Severity::Breaking);
+        assert!(superclass[0]
+            .description()
+            .ends_with("no longer a subclass of B"));
+    }
+}
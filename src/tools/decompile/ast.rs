@@ -0,0 +1,925 @@
+use std::collections::HashSet;
+
+// The Java-like syntax tree the decompiler builds and prints. Types and class names are kept
+// as the source text they print as, already shortened to what the class's imports allow.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Str(String),
+    Null,
+    /// Printed as is, e.g. `true`, `'a'` or `String.class`
+    Text(String),
+    This,
+    /// `descriptor` is the local's type from the LocalVariableTable, or a guess from the
+    /// instruction that used it
+    Local {
+        slot: u16,
+        name: String,
+        descriptor: String,
+        type_name: String,
+    },
+    /// The value at a position of the operand stack where paths merge
+    StackVar(usize),
+    /// A value saved before something could change it
+    Temp(usize),
+    /// The exception a handler caught, until it's stored
+    Caught,
+    /// A static field when `object` is `None`, qualified with `owner`
+    Field {
+        object: Option<Box<Expr>>,
+        owner: String,
+        name: String,
+        descriptor: String,
+    },
+    ArrayElement {
+        array: Box<Expr>,
+        index: Box<Expr>,
+    },
+    ArrayLength(Box<Expr>),
+    /// `descriptor` is the return type's
+    Call {
+        target: Target,
+        name: String,
+        args: Vec<Expr>,
+        descriptor: String,
+    },
+    New {
+        class: String,
+        args: Vec<Expr>,
+    },
+    /// An object from `new` whose constructor hasn't been called yet
+    Uninitialized {
+        pc: u32,
+        class: String,
+    },
+    /// `element` is the type without any brackets, `dimensions` the sizes given and `extra`
+    /// the number of dimensions left unsized
+    NewArray {
+        element: String,
+        dimensions: Vec<Expr>,
+        extra: usize,
+    },
+    /// An array created with its elements, `dimensions` being how many it has
+    ArrayInit {
+        element: String,
+        dimensions: usize,
+        values: Vec<Expr>,
+    },
+    Binary {
+        op: &'static str,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// `-` or `!`
+    Unary {
+        op: &'static str,
+        operand: Box<Expr>,
+    },
+    Cast {
+        to: String,
+        value: Box<Expr>,
+    },
+    InstanceOf {
+        value: Box<Expr>,
+        class: String,
+    },
+    /// lcmp, fcmpl, fcmpg, dcmpl or dcmpg whose result is used as a number
+    Compare {
+        class: &'static str,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Ternary {
+        condition: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// A lambda or method reference from invokedynamic, shown as a reference to the method
+    /// that implements it along with any values it captures
+    MethodRef {
+        target: Target,
+        name: String,
+        captured: Vec<Expr>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Static(String),
+    Instance(Box<Expr>),
+    /// A superclass method called on `this`
+    Super,
+    /// `super(...)` or `this(...)` at the start of a constructor
+    Constructor(&'static str),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    /// `declare` is the type to declare a local with on its first assignment
+    Assign {
+        target: Expr,
+        value: Expr,
+        declare: Option<String>,
+    },
+    Increment {
+        target: Expr,
+        amount: i32,
+    },
+    Return(Option<Expr>),
+    Throw(Expr),
+    Monitor {
+        enter: bool,
+        lock: Expr,
+    },
+    If {
+        condition: Expr,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    /// Loops and switches have an id that breaks and continues from inside nested ones use
+    /// as a label
+    Loop {
+        id: usize,
+        kind: LoopKind,
+        body: Vec<Stmt>,
+    },
+    Switch {
+        id: usize,
+        key: Expr,
+        cases: Vec<Case>,
+    },
+    Try {
+        body: Vec<Stmt>,
+        catches: Vec<Catch>,
+    },
+    Break(Option<usize>),
+    Continue(Option<usize>),
+    /// Control flow that couldn't be structured, to the block at a pc
+    Goto(u32),
+    Label(u32),
+    Comment(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoopKind {
+    Infinite,
+    While(Expr),
+    DoWhile(Expr),
+}
+
+/// `None` is the default case
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub keys: Vec<Option<i32>>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Catch {
+    pub class: String,
+    pub name: String,
+    pub body: Vec<Stmt>,
+}
+
+const PRIMARY: u8 = 14;
+const UNARY: u8 = 12;
+
+impl Expr {
+    pub fn binary(op: &'static str, left: Expr, right: Expr) -> Expr {
+        Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// The condition that holds when this one doesn't
+    pub fn negate(self) -> Expr {
+        match self {
+            Expr::Binary { op, left, right } => {
+                let negated = match op {
+                    "==" => "!=",
+                    "!=" => "==",
+                    "<" => ">=",
+                    ">=" => "<",
+                    ">" => "<=",
+                    "<=" => ">",
+                    "&&" => return Expr::binary("||", left.negate(), right.negate()),
+                    "||" => return Expr::binary("&&", left.negate(), right.negate()),
+                    _ => {
+                        return Expr::Unary {
+                            op: "!",
+                            operand: Box::new(Expr::Binary { op, left, right }),
+                        }
+                    }
+                };
+                Expr::Binary {
+                    op: negated,
+                    left,
+                    right,
+                }
+            }
+            Expr::Unary { op: "!", operand } => *operand,
+            Expr::Text(text) if text == "true" => Expr::Text("false".to_string()),
+            Expr::Text(text) if text == "false" => Expr::Text("true".to_string()),
+            expr => Expr::Unary {
+                op: "!",
+                operand: Box::new(expr),
+            },
+        }
+    }
+
+    /// Whether the expression is known to be a boolean rather than an int
+    pub fn is_boolean(&self) -> bool {
+        match self {
+            Expr::Binary { op, left, right } => match *op {
+                "==" | "!=" | "<" | ">=" | ">" | "<=" | "&&" | "||" => true,
+                "&" | "|" | "^" => left.is_boolean() && right.is_boolean(),
+                _ => false,
+            },
+            Expr::Unary { op, .. } => *op == "!",
+            Expr::InstanceOf { .. } => true,
+            Expr::Text(text) => text == "true" || text == "false",
+            Expr::Local { descriptor, .. }
+            | Expr::Field { descriptor, .. }
+            | Expr::Call { descriptor, .. } => descriptor == "Z",
+            Expr::Ternary {
+                then, otherwise, ..
+            } => then.is_boolean() && otherwise.is_boolean(),
+            _ => false,
+        }
+    }
+
+    /// Whether the expression only reads values nothing between here and its use can change:
+    /// constants, `this`, arithmetic, and locals other than `written`
+    pub fn is_stable(&self, written: Option<u16>) -> bool {
+        match self {
+            Expr::Int(_)
+            | Expr::Long(_)
+            | Expr::Float(_)
+            | Expr::Double(_)
+            | Expr::Str(_)
+            | Expr::Null
+            | Expr::Text(_)
+            | Expr::This
+            | Expr::StackVar(_)
+            | Expr::Temp(_)
+            | Expr::Caught
+            | Expr::Uninitialized { .. } => true,
+            Expr::Local { slot, .. } => Some(*slot) != written,
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } => {
+                left.is_stable(written) && right.is_stable(written)
+            }
+            Expr::Unary { operand: value, .. }
+            | Expr::Cast { value, .. }
+            | Expr::InstanceOf { value, .. } => value.is_stable(written),
+            Expr::Ternary {
+                condition,
+                then,
+                otherwise,
+            } => {
+                condition.is_stable(written)
+                    && then.is_stable(written)
+                    && otherwise.is_stable(written)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether copying the expression's text is the same as evaluating it once
+    pub fn is_simple(&self) -> bool {
+        matches!(
+            self,
+            Expr::Int(_)
+                | Expr::Long(_)
+                | Expr::Float(_)
+                | Expr::Double(_)
+                | Expr::Str(_)
+                | Expr::Null
+                | Expr::Text(_)
+                | Expr::This
+                | Expr::Local { .. }
+                | Expr::StackVar(_)
+                | Expr::Temp(_)
+                | Expr::Caught
+                | Expr::Uninitialized { .. }
+        )
+    }
+
+    /// Calls `f` on this expression and everything inside it, outermost first
+    pub fn visit(&self, f: &mut impl FnMut(&Expr)) {
+        f(self);
+        match self {
+            Expr::Field {
+                object: Some(value),
+                ..
+            }
+            | Expr::ArrayLength(value)
+            | Expr::Unary { operand: value, .. }
+            | Expr::Cast { value, .. }
+            | Expr::InstanceOf { value, .. } => value.visit(f),
+            Expr::ArrayElement { array, index } => {
+                array.visit(f);
+                index.visit(f);
+            }
+            Expr::Call { target, args, .. } => {
+                if let Target::Instance(object) = target {
+                    object.visit(f);
+                }
+                args.iter().for_each(|arg| arg.visit(f));
+            }
+            Expr::MethodRef {
+                target: Target::Instance(object),
+                ..
+            } => object.visit(f),
+            Expr::New { args, .. }
+            | Expr::ArrayInit { values: args, .. }
+            | Expr::MethodRef { captured: args, .. } => args.iter().for_each(|arg| arg.visit(f)),
+            Expr::NewArray { dimensions, .. } => dimensions.iter().for_each(|d| d.visit(f)),
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } => {
+                left.visit(f);
+                right.visit(f);
+            }
+            Expr::Ternary {
+                condition,
+                then,
+                otherwise,
+            } => {
+                condition.visit(f);
+                then.visit(f);
+                otherwise.visit(f);
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces every expression `f` returns a replacement for, outermost first
+    pub fn replace(&mut self, f: &mut impl FnMut(&Expr) -> Option<Expr>) {
+        if let Some(replacement) = f(self) {
+            *self = replacement;
+            return;
+        }
+        match self {
+            Expr::Field {
+                object: Some(value),
+                ..
+            }
+            | Expr::ArrayLength(value)
+            | Expr::Unary { operand: value, .. }
+            | Expr::Cast { value, .. }
+            | Expr::InstanceOf { value, .. } => value.replace(f),
+            Expr::ArrayElement { array, index } => {
+                array.replace(f);
+                index.replace(f);
+            }
+            Expr::Call { target, args, .. } => {
+                if let Target::Instance(object) = target {
+                    object.replace(f);
+                }
+                args.iter_mut().for_each(|arg| arg.replace(f));
+            }
+            Expr::New { args, .. } => args.iter_mut().for_each(|arg| arg.replace(f)),
+            Expr::MethodRef {
+                target, captured, ..
+            } => {
+                if let Target::Instance(object) = target {
+                    object.replace(f);
+                }
+                captured.iter_mut().for_each(|value| value.replace(f));
+            }
+            Expr::NewArray { dimensions, .. } => dimensions.iter_mut().for_each(|d| d.replace(f)),
+            Expr::ArrayInit { values, .. } => values.iter_mut().for_each(|v| v.replace(f)),
+            Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } => {
+                left.replace(f);
+                right.replace(f);
+            }
+            Expr::Ternary {
+                condition,
+                then,
+                otherwise,
+            } => {
+                condition.replace(f);
+                then.replace(f);
+                otherwise.replace(f);
+            }
+            _ => {}
+        }
+    }
+
+    /// How many times `expr` appears in this expression
+    pub fn count(&self, expr: &Expr) -> usize {
+        let mut count = 0;
+        self.visit(&mut |e| count += (e == expr) as usize);
+        count
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Ternary { .. } => 1,
+            Expr::Binary { op, .. } => binary_precedence(op),
+            Expr::InstanceOf { .. } => 8,
+            Expr::Unary { .. } | Expr::Cast { .. } => UNARY,
+            Expr::Int(value) if *value < 0 => UNARY,
+            Expr::Long(value) if *value < 0 => UNARY,
+            Expr::Float(value) if value.is_sign_negative() => UNARY,
+            Expr::Double(value) if value.is_sign_negative() => UNARY,
+            _ => PRIMARY,
+        }
+    }
+
+    /// The expression's text, in parentheses if it binds less tightly than `precedence`
+    fn wrapped(&self, precedence: u8) -> String {
+        let text = self.to_source();
+        if self.precedence() < precedence {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    pub fn to_source(&self) -> String {
+        match self {
+            Expr::Int(value) => value.to_string(),
+            Expr::Long(value) => format!("{value}L"),
+            Expr::Float(value) => float_literal(*value as f64, "Float", "F"),
+            Expr::Double(value) => float_literal(*value, "Double", ""),
+            Expr::Str(value) => format!("\"{}\"", escape(value, '"')),
+            Expr::Null => "null".to_string(),
+            Expr::Text(text) => text.clone(),
+            Expr::This => "this".to_string(),
+            Expr::Local { name, .. } => name.clone(),
+            Expr::StackVar(position) => format!("stack{position}"),
+            Expr::Temp(number) => format!("temp{number}"),
+            Expr::Caught => "e".to_string(),
+            Expr::Field {
+                object: Some(object),
+                name,
+                ..
+            } => format!("{}.{name}", object.wrapped(PRIMARY)),
+            Expr::Field {
+                object: None,
+                owner,
+                name,
+                ..
+            } => format!("{owner}.{name}"),
+            Expr::ArrayElement { array, index } => {
+                format!("{}[{}]", array.wrapped(PRIMARY), index.to_source())
+            }
+            Expr::ArrayLength(array) => format!("{}.length", array.wrapped(PRIMARY)),
+            Expr::Call {
+                target, name, args, ..
+            } => {
+                let args = arguments(args);
+                match target {
+                    Target::Static(owner) => format!("{owner}.{name}({args})"),
+                    Target::Instance(object) => {
+                        format!("{}.{name}({args})", object.wrapped(PRIMARY))
+                    }
+                    Target::Super => format!("super.{name}({args})"),
+                    Target::Constructor(keyword) => format!("{keyword}({args})"),
+                }
+            }
+            Expr::New { class, args } => format!("new {class}({})", arguments(args)),
+            Expr::Uninitialized { class, .. } => format!("new {class}"),
+            Expr::NewArray {
+                element,
+                dimensions,
+                extra,
+            } => {
+                let mut text = format!("new {element}");
+                for dimension in dimensions {
+                    text.push_str(&format!("[{}]", dimension.to_source()));
+                }
+                text.push_str(&"[]".repeat(*extra));
+                text
+            }
+            Expr::ArrayInit {
+                element,
+                dimensions,
+                values,
+            } => format!(
+                "new {element}{}{{{}}}",
+                "[]".repeat(*dimensions),
+                arguments(values)
+            ),
+            Expr::Binary { op, left, right } => {
+                let precedence = binary_precedence(op);
+                format!(
+                    "{} {op} {}",
+                    left.wrapped(precedence),
+                    right.wrapped(precedence + 1)
+                )
+            }
+            Expr::Unary { op, operand } => {
+                let text = operand.wrapped(UNARY);
+                // keeps `- -x` from reading as a decrement
+                if *op == "-" && text.starts_with('-') {
+                    format!("-({text})")
+                } else {
+                    format!("{op}{text}")
+                }
+            }
+            Expr::Cast { to, value } => format!("({to}) {}", value.wrapped(UNARY)),
+            Expr::InstanceOf { value, class } => {
+                format!("{} instanceof {class}", value.wrapped(8))
+            }
+            Expr::Compare { class, left, right } => format!(
+                "{class}.compare({}, {})",
+                left.to_source(),
+                right.to_source()
+            ),
+            Expr::Ternary {
+                condition,
+                then,
+                otherwise,
+            } => format!(
+                "{} ? {} : {}",
+                condition.wrapped(2),
+                then.wrapped(2),
+                otherwise.wrapped(1)
+            ),
+            Expr::MethodRef {
+                target,
+                name,
+                captured,
+            } => {
+                let text = match target {
+                    Target::Static(owner) => format!("{owner}::{name}"),
+                    Target::Instance(object) => format!("{}::{name}", object.wrapped(PRIMARY)),
+                    Target::Super => format!("super::{name}"),
+                    Target::Constructor(_) => format!("{name}::new"),
+                };
+                match captured.is_empty() {
+                    true => text,
+                    false => format!("{text} /* capturing {} */", arguments(captured)),
+                }
+            }
+        }
+    }
+}
+
+fn binary_precedence(op: &str) -> u8 {
+    match op {
+        "||" => 2,
+        "&&" => 3,
+        "|" => 4,
+        "^" => 5,
+        "&" => 6,
+        "==" | "!=" => 7,
+        "<" | ">" | "<=" | ">=" => 8,
+        "<<" | ">>" | ">>>" => 9,
+        "+" | "-" => 10,
+        _ => 11,
+    }
+}
+
+fn arguments(args: &[Expr]) -> String {
+    args.iter()
+        .map(Expr::to_source)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn float_literal(value: f64, class: &str, suffix: &str) -> String {
+    if value.is_nan() {
+        format!("{class}.NaN")
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "NEGATIVE" } else { "POSITIVE" };
+        format!("{class}.{sign}_INFINITY")
+    } else if suffix == "F" {
+        format!("{:?}F", value as f32)
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// Escapes text for a Java string or char literal quoted with `quote`
+pub fn escape(text: &str, quote: char) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\\' => escaped.push_str("\\\\"),
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Stmt {
+    /// Whether control never continues after this statement
+    pub fn is_jump(&self) -> bool {
+        match self {
+            Stmt::Return(_)
+            | Stmt::Throw(_)
+            | Stmt::Break(_)
+            | Stmt::Continue(_)
+            | Stmt::Goto(_) => true,
+            Stmt::If {
+                then, otherwise, ..
+            } => ends_with_jump(then) && ends_with_jump(otherwise),
+            _ => false,
+        }
+    }
+
+    /// The expressions evaluated by the statement itself, not by those nested in it
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Stmt::Expr(expr)
+            | Stmt::Throw(expr)
+            | Stmt::Return(Some(expr))
+            | Stmt::Monitor { lock: expr, .. }
+            | Stmt::If {
+                condition: expr, ..
+            }
+            | Stmt::Switch { key: expr, .. }
+            | Stmt::Loop {
+                kind: LoopKind::While(expr),
+                ..
+            } => vec![expr],
+            Stmt::Assign { target, value, .. } => vec![target, value],
+            Stmt::Increment { target, .. } => vec![target],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The statement lists nested in this one
+    pub fn blocks_mut(&mut self) -> Vec<&mut Vec<Stmt>> {
+        match self {
+            Stmt::If {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Stmt::Loop { body, .. } => vec![body],
+            Stmt::Switch { cases, .. } => cases.iter_mut().map(|case| &mut case.body).collect(),
+            Stmt::Try { body, catches } => {
+                let mut blocks = vec![body];
+                blocks.extend(catches.iter_mut().map(|catch| &mut catch.body));
+                blocks
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+pub fn ends_with_jump(stmts: &[Stmt]) -> bool {
+    stmts.last().is_some_and(Stmt::is_jump)
+}
+
+/// Prints statements indented by `indent` levels. Loops and switches get a label only if
+/// something in `labels` breaks out of or continues them.
+pub fn render(stmts: &[Stmt], indent: usize, labels: &HashSet<usize>, out: &mut String) {
+    let pad = "    ".repeat(indent);
+    let line = |text: &str, out: &mut String| {
+        out.push_str(&pad);
+        out.push_str(text);
+        out.push('\n');
+    };
+    for stmt in stmts {
+        match stmt {
+            Stmt::Expr(expr) => line(&format!("{};", expr.to_source()), out),
+            Stmt::Assign {
+                target,
+                value,
+                declare,
+            } => line(&format!("{};", assignment(target, value, declare)), out),
+            Stmt::Increment { target, amount } => {
+                let target = target.to_source();
+                let text = match amount {
+                    1 => format!("{target}++;"),
+                    -1 => format!("{target}--;"),
+                    amount if *amount < 0 => format!("{target} -= {};", -(*amount as i64)),
+                    amount => format!("{target} += {amount};"),
+                };
+                line(&text, out)
+            }
+            Stmt::Return(None) => line("return;", out),
+            Stmt::Return(Some(value)) => line(&format!("return {};", value.to_source()), out),
+            Stmt::Throw(value) => line(&format!("throw {};", value.to_source()), out),
+            Stmt::Monitor { enter, lock } => {
+                let keyword = if *enter {
+                    "monitorenter"
+                } else {
+                    "monitorexit"
+                };
+                line(&format!("{keyword}({});", lock.to_source()), out)
+            }
+            Stmt::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                line(&format!("if ({}) {{", condition.to_source()), out);
+                render(then, indent + 1, labels, out);
+                let mut otherwise = otherwise;
+                // else-if chains stay flat
+                while let [Stmt::If {
+                    condition,
+                    then,
+                    otherwise: rest,
+                }] = otherwise.as_slice()
+                {
+                    line(&format!("}} else if ({}) {{", condition.to_source()), out);
+                    render(then, indent + 1, labels, out);
+                    otherwise = rest;
+                }
+                if !otherwise.is_empty() {
+                    line("} else {", out);
+                    render(otherwise, indent + 1, labels, out);
+                }
+                line("}", out);
+            }
+            Stmt::Loop { id, kind, body } => {
+                let label = match labels.contains(id) {
+                    true => format!("label{id}: "),
+                    false => String::new(),
+                };
+                match kind {
+                    LoopKind::Infinite => line(&format!("{label}while (true) {{"), out),
+                    LoopKind::While(condition) => {
+                        line(&format!("{label}while ({}) {{", condition.to_source()), out)
+                    }
+                    LoopKind::DoWhile(_) => line(&format!("{label}do {{"), out),
+                }
+                render(body, indent + 1, labels, out);
+                match kind {
+                    LoopKind::DoWhile(condition) => {
+                        line(&format!("}} while ({});", condition.to_source()), out)
+                    }
+                    _ => line("}", out),
+                }
+            }
+            Stmt::Switch { id, key, cases } => {
+                let label = match labels.contains(id) {
+                    true => format!("label{id}: "),
+                    false => String::new(),
+                };
+                line(&format!("{label}switch ({}) {{", key.to_source()), out);
+                for case in cases {
+                    for key in case.keys.iter() {
+                        match key {
+                            Some(key) => line(&format!("case {key}:"), out),
+                            None => line("default:", out),
+                        }
+                    }
+                    render(&case.body, indent + 1, labels, out);
+                }
+                line("}", out);
+            }
+            Stmt::Try { body, catches } => {
+                line("try {", out);
+                render(body, indent + 1, labels, out);
+                for catch in catches {
+                    line(
+                        &format!("}} catch ({} {}) {{", catch.class, catch.name),
+                        out,
+                    );
+                    render(&catch.body, indent + 1, labels, out);
+                }
+                line("}", out);
+            }
+            Stmt::Break(None) => line("break;", out),
+            Stmt::Break(Some(id)) => line(&format!("break label{id};"), out),
+            Stmt::Continue(None) => line("continue;", out),
+            Stmt::Continue(Some(id)) => line(&format!("continue label{id};"), out),
+            Stmt::Goto(pc) => line(&format!("goto pc{pc};"), out),
+            // labels go one level out, as in most code styles
+            Stmt::Label(pc) => {
+                out.push_str(&"    ".repeat(indent.saturating_sub(1)));
+                out.push_str(&format!("pc{pc}:\n"));
+            }
+            Stmt::Comment(text) => line(&format!("// {text}"), out),
+        }
+    }
+}
+
+/// `x = y`, `int x = y`, or a compound assignment such as `x += y` or `x++`
+fn assignment(target: &Expr, value: &Expr, declare: &Option<String>) -> String {
+    let target_text = target.to_source();
+    if let Some(type_name) = declare {
+        return format!("{type_name} {target_text} = {}", value.to_source());
+    }
+    if let Expr::Binary { op, left, right } = value {
+        let compound = matches!(
+            *op,
+            "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<" | ">>" | ">>>"
+        );
+        if compound && **left == *target {
+            return match (*op, &**right) {
+                ("+", Expr::Int(1)) => format!("{target_text}++"),
+                ("-", Expr::Int(1)) => format!("{target_text}--"),
+                _ => format!("{target_text} {op}= {}", right.to_source()),
+            };
+        }
+    }
+    format!("{target_text} = {}", value.to_source())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn local(name: &str) -> Expr {
+        Expr::Local {
+            slot: 1,
+            name: name.to_string(),
+            descriptor: "I".to_string(),
+            type_name: "int".to_string(),
+        }
+    }
+
+    #[test]
+    fn parenthesizes_by_precedence() {
+        let sum = Expr::binary("+", local("a"), local("b"));
+        let product = Expr::binary("*", sum.clone(), local("c"));
+        assert_eq!(product.to_source(), "(a + b) * c");
+        let difference = Expr::binary("-", local("a"), sum);
+        assert_eq!(difference.to_source(), "a - (a + b)");
+        let cast = Expr::Cast {
+            to: "long".to_string(),
+            value: Box::new(difference),
+        };
+        assert_eq!(cast.to_source(), "(long) (a - (a + b))");
+        assert_eq!(
+            Expr::Unary {
+                op: "-",
+                operand: Box::new(Expr::Int(-1))
+            }
+            .to_source(),
+            "-(-1)"
+        );
+        assert_eq!(Expr::Float(f32::NAN).to_source(), "Float.NaN");
+        assert_eq!(Expr::Str("a\"\n".to_string()).to_source(), "\"a\\\"\\n\"");
+    }
+
+    #[test]
+    fn negates_conditions() {
+        let condition = Expr::binary(
+            "&&",
+            Expr::binary("<", local("a"), Expr::Int(0)),
+            Expr::binary("!=", local("b"), Expr::Null),
+        );
+        assert_eq!(condition.negate().to_source(), "a >= 0 || b == null");
+        assert_eq!(local("a").negate().negate(), local("a"));
+    }
+
+    #[test]
+    fn renders_statements() {
+        let i = local("i");
+        let stmts = vec![
+            Stmt::Assign {
+                target: i.clone(),
+                value: Expr::Int(0),
+                declare: Some("int".to_string()),
+            },
+            Stmt::Loop {
+                id: 0,
+                kind: LoopKind::While(Expr::binary("<", i.clone(), Expr::Int(10))),
+                body: vec![
+                    Stmt::If {
+                        condition: Expr::binary("==", i.clone(), Expr::Int(5)),
+                        then: vec![Stmt::Break(Some(0))],
+                        otherwise: vec![Stmt::If {
+                            condition: Expr::binary("==", i.clone(), Expr::Int(6)),
+                            then: vec![Stmt::Continue(None)],
+                            otherwise: Vec::new(),
+                        }],
+                    },
+                    Stmt::Assign {
+                        target: i.clone(),
+                        value: Expr::binary("+", i.clone(), Expr::Int(1)),
+                        declare: None,
+                    },
+                ],
+            },
+        ];
+        let mut out = String::new();
+        render(&stmts, 0, &HashSet::from([0]), &mut out);
+        assert_eq!(
+            out,
+            "int i = 0;
+label0: while (i < 10) {
+    if (i == 5) {
+        break label0;
+    } else if (i == 6) {
+        continue;
+    }
+    i++;
+}
+"
+        );
+    }
+}
@@ -0,0 +1,1151 @@
+use crate::analysis::cfg::ControlFlowGraph;
+use crate::analysis::frames::{compute_frames, local_access, Interpreter, ValueType};
+use crate::analysis::hierarchy::{ClassFileHierarchy, ClassHierarchy};
+use crate::analysis::verifier::falls_through;
+use crate::bytecode::opcode::{ASTORE, ASTORE_0, ASTORE_3};
+use crate::class_file::access_flags::{
+    keywords, FlagTarget, ACC_ABSTRACT, ACC_ANNOTATION, ACC_ENUM, ACC_INTERFACE, ACC_NATIVE,
+    ACC_STATIC, ACC_VARARGS,
+};
+use crate::class_file::attribute::attribute_info::{find_attribute, AttributeInfo};
+use crate::class_file::attribute::bootstrap_methods::BootstrapMethods;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::attribute::constant_value::ConstantValue;
+use crate::class_file::attribute::exceptions::Exceptions;
+use crate::class_file::attribute::local_variable_table::LocalVariableTable;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::descriptor::MethodDescriptor;
+use crate::class_file::member_info::MemberInfo;
+use crate::class_file::signature::{Signature, TypeArgument, TypeSignature};
+use crate::tools::decompile::ast::{render, Expr, LoopKind, Stmt};
+use crate::tools::decompile::expressions::{
+    coerce, fallback_name, kind_of, MethodContext, Variable,
+};
+use crate::tools::decompile::structure::structure;
+use crate::util::file::read_u16;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io;
+
+/// Decompiles a class file to Java-like source. Methods that can't be decompiled get a
+/// comment saying why in place of their body.
+pub fn decompile(class_file: &ClassFile) -> Result<String, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let class_name = class_file.class_name()?;
+    let mut names = Names::new(&class_name);
+    let mut hierarchy = ClassFileHierarchy::new();
+    hierarchy.add_class_file(class_file)?;
+    let bootstrap_methods = match class_file.find_attribute(BootstrapMethods::NAME) {
+        Some(attribute) => Some(BootstrapMethods::from(&mut attribute.reader())?),
+        None => None,
+    };
+
+    let mut body = format!("{} {{\n", class_declaration(class_file, &mut names)?);
+    for field in class_file.fields() {
+        body.push_str(&format!(
+            "    {};\n",
+            field_declaration(class_file, field, &mut names)?
+        ));
+    }
+    for method in class_file.methods() {
+        if body.len() > 1 && !body.ends_with("{\n") {
+            body.push('\n');
+        }
+        let parameters = parameters(class_file, method, &mut names)?;
+        let declaration = method_declaration(class_file, method, &parameters, &mut names)?;
+        let Some(code) = method.code(constant_pool)? else {
+            body.push_str(&format!("    {declaration};\n"));
+            continue;
+        };
+        body.push_str(&format!("    {declaration} {{\n"));
+        let context = MethodContext {
+            constant_pool,
+            names: &mut names,
+            class_name: class_name.clone(),
+            is_static: method.access_flags() & ACC_STATIC != 0,
+            return_descriptor: String::new(),
+            variables: Vec::new(),
+            parameters: parameters
+                .into_iter()
+                .map(|(slot, name, descriptor, _)| (slot, name, descriptor))
+                .collect(),
+            // code the frames can't be computed for, such as subroutines, keeps Object locals
+            local_types: stored_types(class_file, method, &code, &hierarchy).unwrap_or_default(),
+            bootstrap_methods: bootstrap_methods.clone(),
+            temps: 0,
+        };
+        match method_body(method, &code, context) {
+            Ok(stmts) => render(&stmts, 2, &loop_labels(&stmts), &mut body),
+            Err(e) => body.push_str(&format!("        // could not decompile: {e}\n")),
+        }
+        body.push_str("    }\n");
+    }
+    body.push_str("}\n");
+
+    let mut out = String::new();
+    if !names.package.is_empty() {
+        out.push_str(&format!("package {};\n\n", names.package.replace('/', ".")));
+    }
+    for import in names.imports.iter() {
+        out.push_str(&format!("import {import};\n"));
+    }
+    if !names.imports.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&body);
+    Ok(out)
+}
+
+/// Shortens class names to what the imports of the class being decompiled allow, and
+/// collects those imports
+pub struct Names {
+    package: String,
+    imports: BTreeSet<String>,
+    /// The class each simple name refers to
+    simple_names: HashMap<String, String>,
+}
+
+impl Names {
+    pub fn new(class_name: &str) -> Names {
+        let mut names = Names {
+            package: package(class_name).to_string(),
+            imports: BTreeSet::new(),
+            simple_names: HashMap::new(),
+        };
+        names.class(class_name);
+        names
+    }
+
+    /// The name to refer to a class by: its simple name, or its qualified name if another
+    /// class already has that simple name. Nested classes are `Outer.Inner`.
+    pub fn class(&mut self, internal_name: &str) -> String {
+        let package = package(internal_name);
+        let java_name = java_name(internal_name);
+        let simple = match package.is_empty() {
+            true => java_name.clone(),
+            false => java_name[package.len() + 1..].to_string(),
+        };
+        // nested classes are imported through their outermost class
+        let outer = simple.split('.').next().unwrap_or_default().to_string();
+        let outer_name = match package.is_empty() {
+            true => outer.clone(),
+            false => format!("{package}/{outer}"),
+        };
+        match self.simple_names.get(&outer) {
+            Some(existing) if *existing != outer_name => java_name,
+            Some(_) => simple,
+            None => {
+                if !package.is_empty() && package != self.package && package != "java/lang" {
+                    self.imports
+                        .insert(java_name[..package.len() + 1 + outer.len()].to_string());
+                }
+                self.simple_names.insert(outer, outer_name);
+                simple
+            }
+        }
+    }
+
+    /// The source form of a field descriptor, such as `String[]`
+    pub fn type_name(&mut self, descriptor: &str) -> String {
+        let dimensions = descriptor.bytes().take_while(|b| *b == b'[').count();
+        let element = &descriptor[dimensions..];
+        let name = match element {
+            "B" => "byte".to_string(),
+            "C" => "char".to_string(),
+            "D" => "double".to_string(),
+            "F" => "float".to_string(),
+            "I" => "int".to_string(),
+            "J" => "long".to_string(),
+            "S" => "short".to_string(),
+            "Z" => "boolean".to_string(),
+            "V" => "void".to_string(),
+            _ => match element.strip_prefix('L').and_then(|e| e.strip_suffix(';')) {
+                Some(class) => self.class(class),
+                None => element.to_string(),
+            },
+        };
+        name + &"[]".repeat(dimensions)
+    }
+}
+
+fn package(internal_name: &str) -> &str {
+    internal_name
+        .rfind('/')
+        .map_or("", |slash| &internal_name[..slash])
+}
+
+/// `java.util.Map.Entry` for `java/util/Map$Entry`. A `$` stays unless a letter follows, as in
+/// the names of anonymous classes.
+fn java_name(internal_name: &str) -> String {
+    let mut name = String::new();
+    let mut chars = internal_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' => name.push('.'),
+            '$' if chars.peek().is_some_and(|next| next.is_alphabetic())
+                && !name.is_empty()
+                && !name.ends_with(['.', '$']) =>
+            {
+                name.push('.')
+            }
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// `<T extends Comparable<T>, U>`, or nothing if there are no type parameters
+fn type_parameter_list(signature: &Signature, names: &mut Names) -> String {
+    if signature.type_parameters().is_empty() {
+        return String::new();
+    }
+    let mut parameters = Vec::new();
+    for parameter in signature.type_parameters() {
+        let mut bounds = Vec::new();
+        for bound in parameter
+            .class_bound()
+            .into_iter()
+            .chain(parameter.interface_bounds())
+        {
+            bounds.push(java_type(bound, names));
+        }
+        bounds.retain(|bound| bound != "Object");
+        parameters.push(match bounds.is_empty() {
+            true => parameter.name().to_string(),
+            false => format!("{} extends {}", parameter.name(), bounds.join(" & ")),
+        });
+    }
+    format!("<{}>", parameters.join(", "))
+}
+
+/// A type from a generic signature (section 4.7.9.1 of the JVM specification) as source
+/// text: `Map.Entry<K, V>` for `Ljava/util/Map$Entry<TK;TV;>;`, and `Outer<T>.Inner` for
+/// inner classes of generic classes
+fn java_type(type_signature: &TypeSignature, names: &mut Names) -> String {
+    match type_signature {
+        TypeSignature::Base(base) => match base {
+            'B' => "byte",
+            'C' => "char",
+            'D' => "double",
+            'F' => "float",
+            'I' => "int",
+            'J' => "long",
+            'S' => "short",
+            'Z' => "boolean",
+            _ => "void",
+        }
+        .to_string(),
+        TypeSignature::TypeVariable(name) => name.clone(),
+        TypeSignature::Array(dimensions, element) => {
+            java_type(element, names) + &"[]".repeat(*dimensions)
+        }
+        TypeSignature::Class(class_type) => {
+            let mut text = String::new();
+            for segment in class_type.segments() {
+                text = match text.is_empty() {
+                    true => names.class(segment.name()),
+                    false => format!("{text}.{}", segment.name()),
+                };
+                if segment.arguments().is_empty() {
+                    continue;
+                }
+                let mut arguments = Vec::new();
+                for argument in segment.arguments() {
+                    arguments.push(match argument {
+                        TypeArgument::Any => "?".to_string(),
+                        TypeArgument::Extends(bound) => {
+                            format!("? extends {}", java_type(bound, names))
+                        }
+                        TypeArgument::Super(bound) => {
+                            format!("? super {}", java_type(bound, names))
+                        }
+                        TypeArgument::Exact(bound) => java_type(bound, names),
+                    });
+                }
+                text = format!("{text}<{}>", arguments.join(", "));
+            }
+            text
+        }
+    }
+}
+
+/// The type parameters, parameter types, return type and thrown types of a method signature
+fn method_signature(
+    signature: &str,
+    names: &mut Names,
+) -> Option<(String, Vec<String>, String, Vec<String>)> {
+    let signature = Signature::parse(signature).ok()?;
+    let parameters = signature.parameters()?;
+    let type_parameters = type_parameter_list(&signature, names);
+    let parameters = parameters
+        .iter()
+        .map(|parameter| java_type(parameter, names))
+        .collect();
+    let return_type = java_type(&signature.types()[0], names);
+    let thrown = signature
+        .thrown()
+        .iter()
+        .map(|thrown| java_type(thrown, names))
+        .collect();
+    Some((type_parameters, parameters, return_type, thrown))
+}
+
+/// The source text of a field or local variable signature
+fn field_signature(signature: &str, names: &mut Names) -> Option<String> {
+    let signature = Signature::parse(signature).ok()?;
+    match (
+        signature.type_parameters(),
+        signature.parameters(),
+        signature.types(),
+    ) {
+        ([], None, [field_type]) => Some(java_type(field_type, names)),
+        _ => None,
+    }
+}
+
+fn signature(
+    attributes: &[AttributeInfo],
+    constant_pool: &ConstantPool,
+) -> Result<Option<String>, io::Error> {
+    match find_attribute(attributes, constant_pool, "Signature") {
+        Some(attribute) => Ok(Some(
+            constant_pool.utf8(read_u16(&mut attribute.reader())?)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// The words of the modifiers that source would show
+fn modifiers(target: FlagTarget, flags: u16, default: bool) -> Vec<String> {
+    let mut words: Vec<String> = keywords(target, flags)
+        .into_iter()
+        .filter(|keyword| !matches!(*keyword, "bridge" | "varargs" | "synthetic" | "enum"))
+        .map(|keyword| match keyword {
+            "strict" => "strictfp".to_string(),
+            keyword => keyword.to_string(),
+        })
+        .collect();
+    if default {
+        words.push("default".to_string());
+    }
+    words
+}
+
+/// e.g. `public final class Box<T> extends Base implements Comparable<Box<T>>`
+fn class_declaration(class_file: &ClassFile, names: &mut Names) -> Result<String, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let flags = class_file.access_flags();
+    let kind = if flags & ACC_ANNOTATION != 0 {
+        "@interface"
+    } else if flags & ACC_INTERFACE != 0 {
+        "interface"
+    } else if flags & ACC_ENUM != 0 {
+        "enum"
+    } else {
+        "class"
+    };
+    let mut words: Vec<String> = keywords(FlagTarget::Class, flags)
+        .into_iter()
+        .filter(|keyword| match *keyword {
+            "public" => true,
+            "final" => kind == "class",
+            "abstract" => kind == "class",
+            _ => false,
+        })
+        .map(str::to_string)
+        .collect();
+    words.push(kind.to_string());
+
+    let class_name = class_file.class_name()?;
+    let simple = names.class(&class_name);
+    let simple = simple.rsplit('.').next().unwrap_or_default().to_string();
+    let mut super_class = class_file
+        .super_class_name()?
+        .map(|name| names.class(&name));
+    let mut interfaces = class_file
+        .interface_names()?
+        .iter()
+        .map(|name| names.class(name))
+        .collect::<Vec<String>>();
+    let mut type_parameters = String::new();
+    if let Some(signature) = signature(class_file.attributes(), constant_pool)? {
+        let parsed = Signature::parse(&signature)
+            .ok()
+            .filter(|signature| signature.parameters().is_none())
+            .map(|signature| {
+                let types = signature
+                    .types()
+                    .iter()
+                    .map(|class_type| java_type(class_type, names))
+                    .collect::<Vec<_>>();
+                (type_parameter_list(&signature, names), types)
+            })
+            .map(|(parameters, mut types)| {
+                let super_class = types.remove(0);
+                (parameters, super_class, types)
+            });
+        // a signature that doesn't parse is ignored, as the JVM does
+        if let Some((parameters, parsed_super, parsed_interfaces)) = parsed {
+            if parsed_interfaces.len() == interfaces.len() {
+                type_parameters = parameters;
+                super_class = Some(parsed_super);
+                interfaces = parsed_interfaces;
+            }
+        }
+    }
+    words.push(format!("{simple}{type_parameters}"));
+
+    if let Some(super_class) = super_class {
+        let implicit =
+            super_class == "Object" || (kind == "enum" && super_class.starts_with("Enum"));
+        if !implicit && kind == "class" {
+            words.push(format!("extends {super_class}"));
+        }
+    }
+    if kind == "@interface" {
+        interfaces.retain(|name| name != "Annotation" && name != "java.lang.annotation.Annotation");
+    }
+    if !interfaces.is_empty() {
+        let keyword = if flags & ACC_INTERFACE != 0 {
+            "extends"
+        } else {
+            "implements"
+        };
+        words.push(format!("{keyword} {}", interfaces.join(", ")));
+    }
+    Ok(words.join(" "))
+}
+
+/// e.g. `private static final int LIMIT = 10`
+fn field_declaration(
+    class_file: &ClassFile,
+    field: &MemberInfo,
+    names: &mut Names,
+) -> Result<String, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let descriptor = field.descriptor(constant_pool)?;
+    let mut words = modifiers(FlagTarget::Field, field.access_flags(), false);
+    let field_type = match signature(field.attributes(), constant_pool)? {
+        Some(signature) => field_signature(&signature, names),
+        None => None,
+    };
+    words.push(field_type.unwrap_or_else(|| names.type_name(&descriptor)));
+    words.push(field.name(constant_pool)?);
+    if let Some(attribute) = find_attribute(field.attributes(), constant_pool, ConstantValue::NAME)
+    {
+        let index = ConstantValue::from(&mut attribute.reader())?.constantvalue_index();
+        let value = match constant_pool.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::Integer(value)) => Expr::Int(value.value()),
+            Some(ConstantPoolData::Long(value)) => Expr::Long(value.value()),
+            Some(ConstantPoolData::Float(value)) => Expr::Float(value.value()),
+            Some(ConstantPoolData::Double(value)) => Expr::Double(value.value()),
+            Some(ConstantPoolData::String(value)) => {
+                Expr::Str(constant_pool.utf8(value.string_index())?)
+            }
+            _ => Expr::Text(format!("/* invalid constant #{index} */")),
+        };
+        words.push(format!("= {}", coerce(value, &descriptor).to_source()));
+    }
+    Ok(words.join(" "))
+}
+
+/// The parameters of a method: their slots, names, descriptors and source types
+fn parameters(
+    class_file: &ClassFile,
+    method: &MemberInfo,
+    names: &mut Names,
+) -> Result<Vec<(u16, String, String, String)>, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let descriptor = MethodDescriptor::parse(&method.descriptor(constant_pool)?)?;
+    let variables = match method.code(constant_pool)? {
+        Some(code) => variables(&code, constant_pool, names)?,
+        None => Vec::new(),
+    };
+    let signature_types = match signature(method.attributes(), constant_pool)? {
+        Some(signature) => method_signature(&signature, names)
+            .map(|(_, parameters, ..)| parameters)
+            .filter(|parameters| parameters.len() == descriptor.parameters().len()),
+        None => None,
+    };
+    let mut slot = (method.access_flags() & ACC_STATIC == 0) as u16;
+    let mut parameters = Vec::new();
+    for (i, parameter) in descriptor.parameters().iter().enumerate() {
+        let parameter_descriptor = parameter.descriptor();
+        let name = variables
+            .iter()
+            .find(|variable| variable.slot == slot && variable.start == 0)
+            .map_or_else(
+                || fallback_name(slot, kind_of(&parameter_descriptor)),
+                |variable| variable.name.clone(),
+            );
+        let type_name = match &signature_types {
+            Some(types) => types[i].clone(),
+            None => names.type_name(&parameter_descriptor),
+        };
+        parameters.push((slot, name, parameter_descriptor, type_name));
+        slot += parameter.slots();
+    }
+    Ok(parameters)
+}
+
+/// e.g. `public <T> List<T> copy(List<? extends T> list) throws IOException`
+fn method_declaration(
+    class_file: &ClassFile,
+    method: &MemberInfo,
+    parameters: &[(u16, String, String, String)],
+    names: &mut Names,
+) -> Result<String, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let name = method.name(constant_pool)?;
+    let flags = method.access_flags();
+    if name == "<clinit>" {
+        return Ok("static".to_string());
+    }
+    let interface = class_file.access_flags() & ACC_INTERFACE != 0;
+    let default = interface && flags & (ACC_ABSTRACT | ACC_STATIC) == 0;
+    let mut words = modifiers(FlagTarget::Method, flags, default);
+    if interface {
+        words.retain(|word| word != "abstract");
+    }
+    let descriptor = MethodDescriptor::parse(&method.descriptor(constant_pool)?)?;
+    let parsed = match signature(method.attributes(), constant_pool)? {
+        Some(signature) => method_signature(&signature, names),
+        None => None,
+    };
+    let (type_parameters, return_type, mut thrown) = match parsed {
+        Some((type_parameters, _, return_type, thrown)) => (type_parameters, return_type, thrown),
+        None => (
+            String::new(),
+            names.type_name(
+                &descriptor
+                    .return_type()
+                    .map_or("V".to_string(), |t| t.descriptor()),
+            ),
+            Vec::new(),
+        ),
+    };
+    if !type_parameters.is_empty() {
+        words.push(type_parameters);
+    }
+    match name.as_str() {
+        "<init>" => {
+            let class = names.class(&class_file.class_name()?);
+            words.push(class.rsplit('.').next().unwrap_or_default().to_string());
+        }
+        name => {
+            words.push(return_type);
+            words.push(name.to_string());
+        }
+    }
+    let mut parameters: Vec<String> = parameters
+        .iter()
+        .map(|(_, name, _, type_name)| format!("{type_name} {name}"))
+        .collect();
+    if flags & ACC_VARARGS != 0 {
+        if let Some(last) = parameters.last_mut() {
+            if let Some(brackets) = last.rfind("[] ") {
+                last.replace_range(brackets..brackets + 2, "...");
+            }
+        }
+    }
+    let mut declaration = words.join(" ");
+    declaration = format!("{declaration}({})", parameters.join(", "));
+
+    if thrown.is_empty() {
+        if let Some(attribute) =
+            find_attribute(method.attributes(), constant_pool, Exceptions::NAME)
+        {
+            for index in Exceptions::from(&mut attribute.reader())?.exception_index_table() {
+                thrown.push(names.class(&constant_pool.class_name(*index)?));
+            }
+        }
+    }
+    if !thrown.is_empty() {
+        declaration.push_str(&format!(" throws {}", thrown.join(", ")));
+    }
+    if flags & (ACC_ABSTRACT | ACC_NATIVE) != 0 && method.code(constant_pool)?.is_some() {
+        declaration.push_str(" /* has code */");
+    }
+    Ok(declaration)
+}
+
+/// The LocalVariableTable entries of a method, with types from the LocalVariableTypeTable
+fn variables(
+    code: &Code,
+    constant_pool: &ConstantPool,
+    names: &mut Names,
+) -> Result<Vec<Variable>, io::Error> {
+    let mut variables = Vec::new();
+    let mut signatures = HashMap::new();
+    for attribute in code.attributes() {
+        let name = attribute.name(constant_pool)?;
+        if name != LocalVariableTable::NAME && name != LocalVariableTable::TYPE_TABLE_NAME {
+            continue;
+        }
+        // the type table has the same layout, with signatures instead of descriptors
+        let table = LocalVariableTable::from(&mut attribute.reader())?;
+        for entry in table.local_variable_table() {
+            let start = entry.start_pc() as u32;
+            let descriptor = constant_pool.utf8(entry.descriptor_index())?;
+            if name == LocalVariableTable::TYPE_TABLE_NAME {
+                signatures.insert((entry.index(), start), descriptor);
+                continue;
+            }
+            variables.push(Variable {
+                start,
+                end: start + entry.length() as u32,
+                slot: entry.index(),
+                name: constant_pool.utf8(entry.name_index())?,
+                type_name: names.type_name(&descriptor),
+                descriptor,
+            });
+        }
+    }
+    for variable in variables.iter_mut() {
+        if let Some(signature) = signatures.get(&(variable.slot, variable.start)) {
+            if let Some(type_name) = field_signature(signature, names) {
+                variable.type_name = type_name;
+            }
+        }
+    }
+    // the method's scopes are flattened, so a name reused with another type gets a number
+    variables.sort_by_key(|v| v.start);
+    let mut renamed: HashMap<(String, String), String> = HashMap::new();
+    let mut taken: HashSet<String> = variables.iter().map(|v| v.name.clone()).collect();
+    let mut typed: HashMap<String, String> = HashMap::new();
+    for variable in variables.iter_mut() {
+        let key = (variable.name.clone(), variable.type_name.clone());
+        if let Some(name) = renamed.get(&key) {
+            variable.name = name.clone();
+            continue;
+        }
+        let first = typed
+            .entry(variable.name.clone())
+            .or_insert_with(|| variable.type_name.clone());
+        if *first != variable.type_name {
+            let name = (2..)
+                .map(|n| format!("{}{n}", variable.name))
+                .find(|name| !taken.contains(name))
+                .unwrap();
+            taken.insert(name.clone());
+            variable.name = name;
+        }
+        renamed.insert(key, variable.name.clone());
+    }
+    Ok(variables)
+}
+
+/// The type of the values stored to each reference local, found by replaying the computed
+/// frames in code order as the verifier does. Slots stored values of different types are left
+/// out, and so are stores of `null`, which fits any of them.
+fn stored_types(
+    class_file: &ClassFile,
+    method: &MemberInfo,
+    code: &Code,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<HashMap<u16, String>, io::Error> {
+    let frames = compute_frames(class_file, method, code, hierarchy)?;
+    let class_name = class_file.class_name()?;
+    let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+    let interpreter = Interpreter::new(class_file.constant_pool(), &class_name, &instructions);
+
+    let mut types: HashMap<u16, Option<String>> = HashMap::new();
+    let mut frame = Some(frames.initial().clone());
+    for (pc, instruction) in &instructions {
+        if let Ok(i) = frames.frames().binary_search_by_key(pc, |(pc, _)| *pc) {
+            frame = Some(frames.frames()[i].1.clone());
+        }
+        let Some(current) = frame.as_mut() else {
+            continue;
+        };
+        interpreter.execute(*pc, instruction, current)?;
+        if let (ASTORE | ASTORE_0..=ASTORE_3, Some((slot, _))) =
+            (instruction.opcode(), local_access(instruction))
+        {
+            let stored = match current.locals().get(slot as usize) {
+                Some(ValueType::Null) => continue,
+                Some(ValueType::Reference(name)) if name.starts_with('[') => Some(name.clone()),
+                Some(ValueType::Reference(name)) => Some(format!("L{name};")),
+                _ => None,
+            };
+            types
+                .entry(slot)
+                .and_modify(|known| {
+                    if *known != stored {
+                        *known = None
+                    }
+                })
+                .or_insert(stored);
+        }
+        if !falls_through(instruction) {
+            frame = None;
+        }
+    }
+    Ok(types
+        .into_iter()
+        .filter_map(|(slot, descriptor)| Some((slot, descriptor?)))
+        .collect())
+}
+
+/// The statements of a method, with the declarations of its locals added
+fn method_body(
+    method: &MemberInfo,
+    code: &Code,
+    mut context: MethodContext,
+) -> Result<Vec<Stmt>, io::Error> {
+    let constant_pool = context.constant_pool;
+    let name = method.name(constant_pool)?;
+    let descriptor = MethodDescriptor::parse(&method.descriptor(constant_pool)?)?;
+    context.return_descriptor = descriptor
+        .return_type()
+        .map_or("V".to_string(), |t| t.descriptor());
+    context.variables = variables(code, constant_pool, context.names)?;
+
+    let cfg = ControlFlowGraph::from_code(code)?;
+    let mut stmts = structure(&cfg, code, &mut context)?;
+    if name == "<init>" {
+        if let Some(Stmt::Expr(Expr::Call { args, name, .. })) = stmts.first() {
+            if args.is_empty() && name.is_empty() {
+                stmts.remove(0);
+            }
+        }
+    }
+    let mut declared: HashSet<String> = context
+        .parameters
+        .iter()
+        .map(|(_, name, _)| name.clone())
+        .collect();
+    infer_types(&mut stmts, context.names);
+    declare(&mut stmts, &mut declared);
+    Ok(stmts)
+}
+
+/// Gives reference locals that have no LocalVariableTable entry, and that the computed frames
+/// couldn't type, the type of the values assigned to them, when those all agree
+fn infer_types(stmts: &mut [Stmt], names: &mut Names) {
+    let mut types = HashMap::new();
+    assigned_types(stmts, names, &mut types);
+    let retype = |expr: &mut Expr| {
+        expr.replace(&mut |e| match e {
+            Expr::Local {
+                slot,
+                name,
+                descriptor,
+                ..
+            } => match types.get(name) {
+                Some(Some(type_name)) => Some(Expr::Local {
+                    slot: *slot,
+                    name: name.clone(),
+                    descriptor: descriptor.clone(),
+                    type_name: type_name.clone(),
+                }),
+                _ => None,
+            },
+            _ => None,
+        })
+    };
+    let mut work: Vec<&mut Stmt> = stmts.iter_mut().collect();
+    while let Some(stmt) = work.pop() {
+        if let Stmt::Loop {
+            kind: LoopKind::DoWhile(condition),
+            ..
+        } = stmt
+        {
+            retype(condition);
+        }
+        if let Stmt::Assign { target, .. } = stmt {
+            retype(target);
+        }
+        for expr in stmt.expressions_mut() {
+            retype(expr);
+        }
+        for block in stmt.blocks_mut() {
+            work.extend(block.iter_mut());
+        }
+    }
+}
+
+/// The type of the values assigned to each reference local with a made up name, or `None`
+/// for those assigned values of different or unknown types
+fn assigned_types(
+    stmts: &mut [Stmt],
+    names: &mut Names,
+    types: &mut HashMap<String, Option<String>>,
+) {
+    for stmt in stmts.iter_mut() {
+        if let Stmt::Assign {
+            target:
+                Expr::Local {
+                    slot,
+                    name,
+                    type_name,
+                    ..
+                },
+            value,
+            ..
+        } = stmt
+        {
+            if type_name == "Object" && *name == fallback_name(*slot, 'a') {
+                let assigned = match value {
+                    Expr::Str(_) => Some("String".to_string()),
+                    Expr::NewArray {
+                        element,
+                        dimensions,
+                        extra,
+                    } => Some(format!(
+                        "{element}{}",
+                        "[]".repeat(dimensions.len() + *extra)
+                    )),
+                    Expr::ArrayInit {
+                        element,
+                        dimensions,
+                        ..
+                    } => Some(format!("{element}{}", "[]".repeat(*dimensions))),
+                    Expr::Binary {
+                        op: "+",
+                        left,
+                        right,
+                    } if matches!(**left, Expr::Str(_)) || matches!(**right, Expr::Str(_)) => {
+                        Some("String".to_string())
+                    }
+                    Expr::New { class, .. } => Some(class.clone()),
+                    Expr::Cast { to, .. } => Some(to.clone()),
+                    Expr::Call { descriptor, .. } | Expr::Field { descriptor, .. } => {
+                        Some(names.type_name(descriptor))
+                    }
+                    _ => None,
+                };
+                types
+                    .entry(name.clone())
+                    .and_modify(|known| {
+                        if *known != assigned {
+                            *known = None
+                        }
+                    })
+                    .or_insert(assigned);
+            }
+        }
+        for block in stmt.blocks_mut() {
+            assigned_types(block, names, types);
+        }
+    }
+}
+
+/// Declares each local where it's first assigned, or just before the statement it's first
+/// assigned in when a later statement at the same level reads it too
+fn declare(stmts: &mut Vec<Stmt>, declared: &mut HashSet<String>) {
+    let mut i = 0;
+    while i < stmts.len() {
+        // locals first assigned inside this statement and used after it
+        let mut inner = Vec::new();
+        for block in stmts[i].blocks_mut() {
+            assigned_locals(block, &mut inner);
+        }
+        let mut later = HashSet::new();
+        for stmt in stmts[i + 1..].iter_mut() {
+            used_locals(stmt, &mut later);
+        }
+        // a do/while's condition is outside its body
+        if let Stmt::Loop {
+            kind: LoopKind::DoWhile(condition),
+            ..
+        } = &stmts[i]
+        {
+            condition.visit(&mut |e| {
+                if let Expr::Local { name, .. } = e {
+                    later.insert(name.clone());
+                }
+            });
+        }
+        for (name, type_name) in inner {
+            if later.contains(&name) && declared.insert(name.clone()) {
+                stmts.insert(i, Stmt::Expr(Expr::Text(format!("{type_name} {name}"))));
+                i += 1;
+            }
+        }
+
+        let stmt = &mut stmts[i];
+        if let Stmt::Assign {
+            target: Expr::Local {
+                name, type_name, ..
+            },
+            declare,
+            ..
+        } = stmt
+        {
+            if declared.insert(name.clone()) {
+                *declare = Some(type_name.clone());
+            }
+        }
+        if let Stmt::Try { catches, .. } = stmt {
+            for catch in catches.iter_mut() {
+                let mut scope = declared.clone();
+                scope.insert(catch.name.clone());
+                declare(&mut catch.body, &mut scope);
+            }
+        }
+        let catches = matches!(stmt, Stmt::Try { .. });
+        for (n, block) in stmt.blocks_mut().into_iter().enumerate() {
+            // catch bodies were done above, with their variable
+            if catches && n > 0 {
+                break;
+            }
+            declare(block, &mut declared.clone());
+        }
+        i += 1;
+    }
+}
+
+/// The names and types of locals assigned anywhere in the statements, in order
+fn assigned_locals(stmts: &mut [Stmt], names: &mut Vec<(String, String)>) {
+    for stmt in stmts.iter_mut() {
+        if let Stmt::Assign {
+            target: Expr::Local {
+                name, type_name, ..
+            },
+            ..
+        } = stmt
+        {
+            if !names.iter().any(|(n, _)| n == name) {
+                names.push((name.clone(), type_name.clone()));
+            }
+        }
+        for block in stmt.blocks_mut() {
+            assigned_locals(block, names);
+        }
+    }
+}
+
+fn used_locals(stmt: &mut Stmt, names: &mut HashSet<String>) {
+    if let Stmt::Loop {
+        kind: LoopKind::DoWhile(condition),
+        ..
+    } = stmt
+    {
+        condition.visit(&mut |e| {
+            if let Expr::Local { name, .. } = e {
+                names.insert(name.clone());
+            }
+        });
+    }
+    for expr in stmt.expressions_mut() {
+        expr.visit(&mut |e| {
+            if let Expr::Local { name, .. } = e {
+                names.insert(name.clone());
+            }
+        });
+    }
+    for block in stmt.blocks_mut() {
+        for stmt in block.iter_mut() {
+            used_locals(stmt, names);
+        }
+    }
+}
+
+/// The loops and switches that a `break` or `continue` names
+fn loop_labels(stmts: &[Stmt]) -> HashSet<usize> {
+    let mut labels = HashSet::new();
+    let mut stmts = stmts.to_vec();
+    collect_labels(&mut stmts, &mut labels);
+    labels
+}
+
+fn collect_labels(stmts: &mut [Stmt], labels: &mut HashSet<usize>) {
+    for stmt in stmts.iter_mut() {
+        if let Stmt::Break(Some(id)) | Stmt::Continue(Some(id)) = stmt {
+            labels.insert(*id);
+        }
+        for block in stmt.blocks_mut() {
+            collect_labels(block, labels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    #[test]
+    fn decompiles_loops_and_conditions() {
+        let class_file = assemble(
+            ".class public Loops
+.super java/lang/Object
+.method public static sum(I)I
+  .limit stack 2
+  .limit locals 3
+  .var 0 is n I from Start to End
+  .var 1 is total I from Total to End
+  .var 2 is i I from Loop to End
+Start:
+  iconst_0
+  istore_1
+Total:
+  iconst_0
+  istore_2
+Loop:
+  iload_2
+  iload_0
+  if_icmpge Done
+  iload_2
+  iconst_2
+  irem
+  ifne Odd
+  iload_1
+  iload_2
+  iadd
+  istore_1
+Odd:
+  iinc 2 1
+  goto Loop
+Done:
+  iload_1
+  ifle Negative
+  iload_1
+  goto Return
+Negative:
+  iconst_0
+Return:
+  ireturn
+End:
+.end method
+",
+        )
+        .unwrap();
+        assert_eq!(
+            decompile(&class_file).unwrap(),
+            "public class Loops {
+    public static int sum(int n) {
+        int total = 0;
+        int i = 0;
+        while (i < n) {
+            if (i % 2 == 0) {
+                total += i;
+            }
+            i++;
+        }
+        return total > 0 ? total : 0;
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn decompiles_try_catch() {
+        let class_file = assemble(
+            ".class public Catch
+.super java/lang/Object
+.method public static parse(Ljava/lang/String;)I
+  .limit stack 2
+  .limit locals 2
+  .catch java/lang/NumberFormatException from Start to End using Handler
+  .var 0 is text Ljava/lang/String; from Start to Last
+  .var 1 is error Ljava/lang/NumberFormatException; from Stored to Last
+Start:
+  aload_0
+  invokestatic java/lang/Integer/parseInt(Ljava/lang/String;)I
+  ireturn
+End:
+Handler:
+  astore_1
+Stored:
+  getstatic java/lang/System/err Ljava/io/PrintStream;
+  aload_1
+  invokevirtual java/io/PrintStream/println(Ljava/lang/Object;)V
+  iconst_m1
+  ireturn
+Last:
+.end method
+",
+        )
+        .unwrap();
+        let source = decompile(&class_file).unwrap();
+        assert!(source.contains(
+            "        try {
+            return Integer.parseInt(text);
+        } catch (NumberFormatException error) {
+            System.err.println(error);
+            return -1;
+        }
+"
+        ));
+    }
+
+    #[test]
+    fn types_locals_from_their_frames() {
+        // javac's enhanced for over an int[], without a LocalVariableTable
+        let class_file = assemble(
+            ".class public Each
+.super java/lang/Object
+.method public static sum([I)I
+  .limit stack 2
+  .limit locals 6
+  iconst_0
+  istore_1
+  aload_0
+  astore_2
+  aload_2
+  arraylength
+  istore_3
+  iconst_0
+  istore 4
+Loop:
+  iload 4
+  iload_3
+  if_icmpge Done
+  aload_2
+  iload 4
+  iaload
+  istore 5
+  iload_1
+  iload 5
+  iadd
+  istore_1
+  iinc 4 1
+  goto Loop
+Done:
+  iload_1
+  ireturn
+.end method
+",
+        )
+        .unwrap();
+        let source = decompile(&class_file).unwrap();
+        assert!(source.contains("        int[] a2 = a0;\n        int i3 = a2.length;\n"));
+    }
+
+    #[test]
+    fn uses_generic_signatures() {
+        // the raw constants are #1 and #2
+        let class_file = assemble(
+            ".class public Box
+.super java/lang/Object
+.constant classSignature Utf8 <T:Ljava/lang/Object;>Ljava/lang/Object;
+.constant methodSignature Utf8 ()Ljava/util/Map<TT;+Ljava/util/List<Ljava/lang/String;>;>;
+.attribute Signature 0001
+.method public items()Ljava/util/Map;
+  .attribute Signature 0002
+  .limit stack 1
+  .limit locals 1
+  aconst_null
+  areturn
+.end method
+",
+        )
+        .unwrap();
+        assert_eq!(
+            decompile(&class_file).unwrap(),
+            "import java.util.List;
+import java.util.Map;
+
+public class Box<T> {
+    public Map<T, ? extends List<String>> items() {
+        return null;
+    }
+}
+"
+        );
+    }
+}
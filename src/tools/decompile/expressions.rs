@@ -0,0 +1,1348 @@
+use crate::analysis::cfg::{ends_block, ControlFlowGraph, NodeId};
+use crate::bytecode::instruction::{ArrayType, Instruction};
+use crate::class_file::attribute::bootstrap_methods::BootstrapMethods;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::constant_pool::types::method_handle::ReferenceKind;
+use crate::class_file::descriptor::MethodDescriptor;
+use crate::tools::decompile::ast::{escape, Expr, Stmt, Target};
+use crate::tools::decompile::decompiler::Names;
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+// Rebuilds the expressions and statements of each basic block by running its instructions
+// over a stack of expressions instead of values. Stores, calls whose result is unused, field
+// and array writes and the like become statements. A value that is still on the stack when
+// something it reads could change is first saved to a temporary, so evaluation order is kept.
+// Values left on the stack at the end of a block are assigned to variables named by their
+// stack position, which the blocks after it start with; most of these later fold into `?:`.
+
+/// A local variable from the LocalVariableTable, with its generic type if the
+/// LocalVariableTypeTable has one
+pub struct Variable {
+    pub start: u32,
+    pub end: u32,
+    pub slot: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub type_name: String,
+}
+
+/// What the expressions of a method need to know about it
+pub struct MethodContext<'a> {
+    pub constant_pool: &'a ConstantPool,
+    pub names: &'a mut Names,
+    pub class_name: String,
+    pub is_static: bool,
+    /// `V` for void methods
+    pub return_descriptor: String,
+    pub variables: Vec<Variable>,
+    /// The slots, names and descriptors of the parameters, used where there's no
+    /// LocalVariableTable
+    pub parameters: Vec<(u16, String, String)>,
+    /// The descriptor of every value stored to each reference local, for the locals where the
+    /// computed frames agree on one
+    pub local_types: HashMap<u16, String>,
+    pub bootstrap_methods: Option<BootstrapMethods>,
+    pub temps: usize,
+}
+
+impl MethodContext<'_> {
+    /// The local in `slot` at `pc`, falling back to a name made of the slot and the kind of
+    /// value the instruction uses (`i2` for an int, `a3` for a reference)
+    fn local(&mut self, slot: u16, pc: u32, kind: char) -> Expr {
+        if slot == 0 && !self.is_static {
+            return Expr::This;
+        }
+        if let Some(variable) = self
+            .variables
+            .iter()
+            .find(|v| v.slot == slot && v.start <= pc && pc < v.end)
+        {
+            return Expr::Local {
+                slot,
+                name: variable.name.clone(),
+                descriptor: variable.descriptor.clone(),
+                type_name: variable.type_name.clone(),
+            };
+        }
+        if let Some((_, name, descriptor)) = self.parameters.iter().find(|(s, _, descriptor)| {
+            *s == slot && self.variables.is_empty() && kind_of(descriptor) == kind
+        }) {
+            let type_name = self.names.type_name(descriptor);
+            return Expr::Local {
+                slot,
+                name: name.clone(),
+                descriptor: descriptor.clone(),
+                type_name,
+            };
+        }
+        let (descriptor, type_name) = match kind {
+            'i' => ("I".to_string(), "int".to_string()),
+            'l' => ("J".to_string(), "long".to_string()),
+            'f' => ("F".to_string(), "float".to_string()),
+            'd' => ("D".to_string(), "double".to_string()),
+            _ => match self.local_types.get(&slot) {
+                Some(descriptor) => (descriptor.clone(), self.names.type_name(descriptor)),
+                None => ("Ljava/lang/Object;".to_string(), "Object".to_string()),
+            },
+        };
+        Expr::Local {
+            slot,
+            name: fallback_name(slot, kind),
+            descriptor,
+            type_name,
+        }
+    }
+}
+
+/// `i` for int-like descriptors, `l`, `f` and `d` for the others, and `a` for references
+pub fn kind_of(descriptor: &str) -> char {
+    match descriptor.as_bytes().first() {
+        Some(b'J') => 'l',
+        Some(b'F') => 'f',
+        Some(b'D') => 'd',
+        Some(b'L' | b'[') => 'a',
+        _ => 'i',
+    }
+}
+
+pub fn fallback_name(slot: u16, kind: char) -> String {
+    format!("{kind}{slot}")
+}
+
+/// How a block ends
+#[derive(Clone, Debug, PartialEq)]
+pub enum End {
+    Jump(NodeId),
+    Branch {
+        condition: Expr,
+        taken: NodeId,
+        fall: NodeId,
+    },
+    Switch {
+        key: Expr,
+        cases: Vec<(i32, NodeId)>,
+        default: NodeId,
+    },
+    /// A return or throw, which is the last statement
+    Exit,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockCode {
+    pub stmts: Vec<Stmt>,
+    pub end: End,
+    /// How many values are on the stack when the block starts
+    pub entry_depth: usize,
+}
+
+impl End {
+    pub fn successors(&self) -> Vec<NodeId> {
+        match self {
+            End::Jump(next) => vec![*next],
+            End::Branch { taken, fall, .. } => vec![*taken, *fall],
+            End::Switch { cases, default, .. } => {
+                let mut targets: Vec<NodeId> = cases.iter().map(|(_, target)| *target).collect();
+                targets.push(*default);
+                targets
+            }
+            End::Exit => Vec::new(),
+        }
+    }
+}
+
+pub struct Rebuilt {
+    /// `None` for blocks that can't be reached
+    pub blocks: Vec<Option<BlockCode>>,
+    /// Stack positions some block leaves holding the value it started with, which can't be
+    /// folded into the statement after their assignment
+    pub passthrough: HashSet<usize>,
+}
+
+struct Slot {
+    expr: Expr,
+    wide: bool,
+}
+
+/// Rebuilds every reachable block, starting from the entry and the exception handlers
+pub fn rebuild(
+    cfg: &ControlFlowGraph,
+    code: &Code,
+    context: &mut MethodContext,
+) -> Result<Rebuilt, io::Error> {
+    let count = cfg.blocks().len();
+    // the width of each value on the stack when a block starts
+    let mut entries: Vec<Option<Vec<bool>>> = vec![None; count];
+    let mut handlers = HashSet::new();
+    let mut work = vec![0];
+    entries[0] = Some(Vec::new());
+    for entry in code.exception_table() {
+        // blocks start at every handler
+        let handler = cfg.block_at(entry.handler_pc() as u32).unwrap();
+        entries[handler] = Some(vec![false]);
+        handlers.insert(handler);
+        work.push(handler);
+    }
+
+    let mut rebuilt = Rebuilt {
+        blocks: vec![None; count],
+        passthrough: HashSet::new(),
+    };
+    while let Some(node) = work.pop() {
+        if rebuilt.blocks[node].is_some() {
+            continue;
+        }
+        let widths = entries[node].clone().unwrap_or_default();
+        let stack = match handlers.contains(&node) {
+            true => vec![Slot {
+                expr: Expr::Caught,
+                wide: false,
+            }],
+            false => widths
+                .iter()
+                .enumerate()
+                .map(|(position, wide)| Slot {
+                    expr: Expr::StackVar(position),
+                    wide: *wide,
+                })
+                .collect(),
+        };
+        let mut builder = Builder {
+            cfg,
+            context,
+            node,
+            stack,
+            stmts: Vec::new(),
+        };
+        let end = builder.run()?;
+        let exit_widths: Vec<bool> = builder.stack.iter().map(|slot| slot.wide).collect();
+        let mut stmts = builder.stmts;
+        if end != End::Exit {
+            for (position, slot) in builder.stack.into_iter().enumerate() {
+                if slot.expr == Expr::StackVar(position) {
+                    rebuilt.passthrough.insert(position);
+                } else {
+                    stmts.push(Stmt::Assign {
+                        target: Expr::StackVar(position),
+                        value: slot.expr,
+                        declare: None,
+                    });
+                }
+            }
+        }
+        for successor in end.successors() {
+            if entries[successor].is_none() {
+                entries[successor] = Some(exit_widths.clone());
+            }
+            work.push(successor);
+        }
+        rebuilt.blocks[node] = Some(BlockCode {
+            stmts,
+            end,
+            entry_depth: widths.len(),
+        });
+    }
+    Ok(rebuilt)
+}
+
+struct Builder<'b, 'a> {
+    cfg: &'b ControlFlowGraph,
+    context: &'b mut MethodContext<'a>,
+    node: NodeId,
+    stack: Vec<Slot>,
+    stmts: Vec<Stmt>,
+}
+
+impl Builder<'_, '_> {
+    fn run(&mut self) -> Result<End, io::Error> {
+        // blocks are never empty and every target starts one
+        let block = self.cfg.block(self.node).unwrap();
+        let instructions = block.instructions();
+        for (i, (pc, instruction)) in instructions.iter().enumerate() {
+            let next_pc = instructions
+                .get(i + 1)
+                .map_or(block.end(), |(next, _)| *next);
+            if let Some(end) = self
+                .instruction(*pc, next_pc, instruction)
+                .map_err(|e| io::Error::new(e.kind(), format!("pc {pc}: {e}")))?
+            {
+                return Ok(end);
+            }
+        }
+        let (_, last) = block.last();
+        if ends_block(last) {
+            return Err(decompile_error("block ends without a jump"));
+        }
+        Ok(End::Jump(self.next_block()?))
+    }
+
+    /// The block control falls into
+    fn next_block(&self) -> Result<NodeId, io::Error> {
+        Some(self.node + 1)
+            .filter(|next| *next < self.cfg.blocks().len())
+            .ok_or_else(|| decompile_error("code falls off its end"))
+    }
+
+    fn push(&mut self, expr: Expr, wide: bool) {
+        self.stack.push(Slot { expr, wide });
+    }
+
+    fn pop(&mut self) -> Result<Expr, io::Error> {
+        self.pop_slot().map(|slot| slot.expr)
+    }
+
+    fn pop_slot(&mut self) -> Result<Slot, io::Error> {
+        self.stack
+            .pop()
+            .ok_or_else(|| decompile_error("operand stack underflow"))
+    }
+
+    /// Pops values making up `words` stack words
+    fn pop_words(&mut self, words: usize) -> Result<Vec<Slot>, io::Error> {
+        let mut popped = Vec::new();
+        let mut count = 0;
+        while count < words {
+            let slot = self.pop_slot()?;
+            count += 1 + slot.wide as usize;
+            popped.push(slot);
+        }
+        if count != words {
+            return Err(decompile_error("instruction splits a long or double"));
+        }
+        popped.reverse();
+        Ok(popped)
+    }
+
+    fn pop_args(&mut self, count: usize) -> Result<Vec<Expr>, io::Error> {
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            args.push(self.pop()?);
+        }
+        args.reverse();
+        Ok(args)
+    }
+
+    /// Saves every value on the stack that `written` or some other side effect could change
+    fn spill(&mut self, written: Option<u16>) {
+        for i in 0..self.stack.len() {
+            if !self.stack[i].expr.is_stable(written) {
+                self.save(i);
+            }
+        }
+    }
+
+    /// Assigns the value at stack position `i` to a new temporary and leaves that there
+    fn save(&mut self, i: usize) {
+        let temp = Expr::Temp(self.context.temps);
+        self.context.temps += 1;
+        let value = std::mem::replace(&mut self.stack[i].expr, temp.clone());
+        self.stmts.push(Stmt::Assign {
+            target: temp,
+            value,
+            declare: None,
+        });
+    }
+
+    /// Adds a statement with side effects, after saving what it could change. Stores to a
+    /// local, an array element or a field only affect reads of that kind, and calls.
+    fn statement(&mut self, stmt: Stmt) {
+        for i in 0..self.stack.len() {
+            let value = &self.stack[i].expr;
+            let mut reads = false;
+            match &stmt {
+                Stmt::Assign {
+                    target: Expr::Local { slot, .. },
+                    ..
+                }
+                | Stmt::Increment {
+                    target: Expr::Local { slot, .. },
+                    ..
+                } => value
+                    .visit(&mut |e| reads |= matches!(e, Expr::Local { slot: s, .. } if s == slot)),
+                Stmt::Assign {
+                    target: Expr::ArrayElement { .. },
+                    ..
+                } => value.visit(&mut |e| reads |= matches!(e, Expr::ArrayElement { .. })),
+                Stmt::Assign {
+                    target: Expr::Field { name, .. },
+                    ..
+                } => value
+                    .visit(&mut |e| reads |= matches!(e, Expr::Field { name: n, .. } if n == name)),
+                _ => reads = !value.is_stable(None),
+            }
+            let store = matches!(stmt, Stmt::Assign { .. } | Stmt::Increment { .. });
+            if reads || (store && calls(value)) {
+                self.save(i);
+            }
+        }
+        self.stmts.push(stmt);
+    }
+
+    fn block_at(&self, pc: u32) -> Result<NodeId, io::Error> {
+        self.cfg
+            .block_at(pc)
+            .ok_or_else(|| decompile_error(&format!("no instruction at {pc}")))
+    }
+
+    fn load(&mut self, slot: u16, pc: u32, kind: char) {
+        let local = self.context.local(slot, pc, kind);
+        self.push(local, matches!(kind, 'l' | 'd'));
+    }
+
+    fn store(&mut self, slot: u16, next_pc: u32, kind: char) -> Result<(), io::Error> {
+        let value = self.pop()?;
+        // a local's range starts after the store that initializes it
+        let target = self.context.local(slot, next_pc, kind);
+        let value = match &target {
+            Expr::Local { descriptor, .. } => coerce(value, descriptor),
+            _ => value,
+        };
+        self.statement(Stmt::Assign {
+            target,
+            value,
+            declare: None,
+        });
+        Ok(())
+    }
+
+    fn binary(&mut self, op: &'static str, wide: bool) -> Result<(), io::Error> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.push(Expr::binary(op, left, right), wide);
+        Ok(())
+    }
+
+    fn cast(&mut self, to: &str, wide: bool) -> Result<(), io::Error> {
+        let value = self.pop()?;
+        self.push(
+            Expr::Cast {
+                to: to.to_string(),
+                value: Box::new(value),
+            },
+            wide,
+        );
+        Ok(())
+    }
+
+    fn array_load(&mut self, wide: bool) -> Result<(), io::Error> {
+        let index = self.pop()?;
+        let array = self.pop()?;
+        self.push(
+            Expr::ArrayElement {
+                array: Box::new(array),
+                index: Box::new(index),
+            },
+            wide,
+        );
+        Ok(())
+    }
+
+    fn array_store(&mut self) -> Result<(), io::Error> {
+        let value = self.pop()?;
+        let index = self.pop()?;
+        let array = self.pop()?;
+        self.statement(Stmt::Assign {
+            target: Expr::ArrayElement {
+                array: Box::new(array),
+                index: Box::new(index),
+            },
+            value,
+            declare: None,
+        });
+        Ok(())
+    }
+
+    /// Copies the top `words` stack words below the `below` words under them
+    fn dup(&mut self, words: usize, below: usize) -> Result<(), io::Error> {
+        let mut copied = self.pop_words(words)?;
+        let mut under = self.pop_words(below)?;
+        // copying the text of a call or a new array would make it twice; reads are saved
+        // before anything could change what they read anyway, and a new array can't
+        let effects = copied.iter().any(|slot| calls(&slot.expr));
+        let arrays = copied.iter().any(|slot| {
+            let mut arrays = false;
+            slot.expr
+                .visit(&mut |e| arrays |= matches!(e, Expr::NewArray { .. }));
+            arrays
+        });
+        if effects || arrays {
+            let top = self.stack.len() + under.len();
+            self.stack.append(&mut under);
+            self.stack.append(&mut copied);
+            match effects {
+                true => self.spill(None),
+                // calls below come first, as they did
+                false => {
+                    for i in 0..self.stack.len() {
+                        if i >= top || calls(&self.stack[i].expr) {
+                            self.save(i);
+                        }
+                    }
+                }
+            }
+            copied = self.pop_words(words)?;
+            under = self.pop_words(below)?;
+        }
+        for slot in copied.iter() {
+            self.push(slot.expr.clone(), slot.wide);
+        }
+        self.stack.append(&mut under);
+        self.stack.append(&mut copied);
+        Ok(())
+    }
+
+    fn constant(&mut self, index: u16) -> Result<(Expr, bool), io::Error> {
+        let constant_pool = self.context.constant_pool;
+        let item = constant_pool
+            .get(index)
+            .ok_or_else(|| decompile_error(&format!("invalid constant pool index {index}")))?;
+        Ok(match item.data() {
+            ConstantPoolData::Integer(value) => (Expr::Int(value.value()), false),
+            ConstantPoolData::Float(value) => (Expr::Float(value.value()), false),
+            ConstantPoolData::Long(value) => (Expr::Long(value.value()), true),
+            ConstantPoolData::Double(value) => (Expr::Double(value.value()), true),
+            ConstantPoolData::String(string) => {
+                (Expr::Str(constant_pool.utf8(string.string_index())?), false)
+            }
+            ConstantPoolData::ClassInfo(_) => {
+                let name = constant_pool.class_name(index)?;
+                (
+                    Expr::Text(format!("{}.class", self.class_type(&name))),
+                    false,
+                )
+            }
+            ConstantPoolData::MethodType(method_type) => {
+                let descriptor = constant_pool.utf8(method_type.descriptor_index())?;
+                (
+                    Expr::Text(format!("/* MethodType */ \"{descriptor}\"")),
+                    false,
+                )
+            }
+            ConstantPoolData::MethodHandle(handle) => {
+                let (owner, name, _) = constant_pool.member_ref(handle.reference_index())?;
+                let owner = self.context.names.class(&owner);
+                (
+                    Expr::Text(format!("/* MethodHandle */ {owner}::{name}")),
+                    false,
+                )
+            }
+            _ => return Err(decompile_error(&format!("ldc of constant {index}"))),
+        })
+    }
+
+    /// A class from a constant pool entry, which may be an array descriptor
+    fn class_type(&mut self, name: &str) -> String {
+        match name.starts_with('[') {
+            true => self.context.names.type_name(name),
+            false => self.context.names.class(name),
+        }
+    }
+
+    fn invoke(&mut self, instruction: &Instruction, index: u16) -> Result<(), io::Error> {
+        let (owner, name, descriptor) = self.context.constant_pool.member_ref(index)?;
+        let method = MethodDescriptor::parse(&descriptor)?;
+        let mut args = self.pop_args(method.parameters().len())?;
+        for (arg, parameter) in args.iter_mut().zip(method.parameters()) {
+            *arg = coerce(std::mem::replace(arg, Expr::Null), &parameter.descriptor());
+        }
+        let returns = method
+            .return_type()
+            .map_or("V".to_string(), |t| t.descriptor());
+        let target = match instruction {
+            Instruction::Invokestatic(_) => Target::Static(self.context.names.class(&owner)),
+            _ => {
+                let receiver = self.pop()?;
+                if name == "<init>" {
+                    return self.construct(receiver, &owner, args);
+                }
+                match (instruction, receiver) {
+                    (Instruction::Invokespecial(_), Expr::This)
+                        if owner != self.context.class_name =>
+                    {
+                        Target::Super
+                    }
+                    (_, receiver) => Target::Instance(Box::new(receiver)),
+                }
+            }
+        };
+        let call = match (&target, name.as_str()) {
+            (Target::Instance(receiver), "toString")
+                if matches!(
+                    owner.as_str(),
+                    "java/lang/StringBuilder" | "java/lang/StringBuffer"
+                ) =>
+            {
+                concat(receiver)
+            }
+            _ => None,
+        }
+        .unwrap_or(Expr::Call {
+            target,
+            name,
+            args,
+            descriptor: returns.clone(),
+        });
+        self.result(call, &returns);
+        Ok(())
+    }
+
+    /// Pushes the value of a call, or adds the call as a statement if it's void
+    fn result(&mut self, call: Expr, returns: &str) {
+        match returns {
+            "V" => self.statement(Stmt::Expr(call)),
+            returns => {
+                let wide = is_wide(returns);
+                self.push(call, wide)
+            }
+        }
+    }
+
+    /// A constructor call: `new` when it's on an object from `new`, otherwise `super(...)`
+    /// or `this(...)` from a constructor
+    fn construct(&mut self, receiver: Expr, owner: &str, args: Vec<Expr>) -> Result<(), io::Error> {
+        match receiver {
+            Expr::Uninitialized { pc, class } => {
+                let new = Expr::New { class, args };
+                let mut replaced = false;
+                for slot in self.stack.iter_mut() {
+                    if matches!(&slot.expr, Expr::Uninitialized { pc: p, .. } if *p == pc) {
+                        slot.expr = new.clone();
+                        replaced = true;
+                    }
+                }
+                if !replaced {
+                    self.statement(Stmt::Expr(new));
+                }
+            }
+            Expr::This => {
+                let keyword = if owner == self.context.class_name {
+                    "this"
+                } else {
+                    "super"
+                };
+                self.statement(Stmt::Expr(Expr::Call {
+                    target: Target::Constructor(keyword),
+                    name: String::new(),
+                    args,
+                    descriptor: "V".to_string(),
+                }));
+            }
+            receiver => self.statement(Stmt::Expr(Expr::Call {
+                target: Target::Instance(Box::new(receiver)),
+                name: "<init>".to_string(),
+                args,
+                descriptor: "V".to_string(),
+            })),
+        }
+        Ok(())
+    }
+
+    fn invokedynamic(&mut self, index: u16) -> Result<(), io::Error> {
+        let constant_pool = self.context.constant_pool;
+        let Some(ConstantPoolData::InvokeDynamic(indy)) =
+            constant_pool.get(index).map(|i| i.data())
+        else {
+            return Err(decompile_error(&format!("invalid invokedynamic {index}")));
+        };
+        let (name, descriptor) = constant_pool.name_and_type(indy.name_and_type_index())?;
+        let method = MethodDescriptor::parse(&descriptor)?;
+        let args = self.pop_args(method.parameters().len())?;
+        let returns = method
+            .return_type()
+            .map_or("V".to_string(), |t| t.descriptor());
+        let bootstrap = self
+            .context
+            .bootstrap_methods
+            .as_ref()
+            .and_then(|methods| {
+                methods
+                    .bootstrap_methods()
+                    .get(indy.bootstrap_method_attr_index() as usize)
+            })
+            .ok_or_else(|| decompile_error("invokedynamic without a bootstrap method"))?;
+        let arguments = bootstrap.bootstrap_arguments().to_vec();
+        let (factory, factory_method) = match constant_pool
+            .get(bootstrap.bootstrap_method_ref())
+            .map(|item| item.data())
+        {
+            Some(ConstantPoolData::MethodHandle(handle)) => {
+                let (owner, name, _) = constant_pool.member_ref(handle.reference_index())?;
+                (owner, name)
+            }
+            _ => return Err(decompile_error("invalid bootstrap method")),
+        };
+
+        let expr = match (factory.as_str(), factory_method.as_str()) {
+            ("java/lang/invoke/LambdaMetafactory", _) if arguments.len() >= 2 => {
+                self.method_ref(arguments[1], args)?
+            }
+            ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants")
+                if !arguments.is_empty() =>
+            {
+                let recipe = match constant_pool.get(arguments[0]).map(|item| item.data()) {
+                    Some(ConstantPoolData::String(string)) => {
+                        constant_pool.utf8(string.string_index())?
+                    }
+                    _ => return Err(decompile_error("invalid string concatenation recipe")),
+                };
+                let mut parts = Vec::new();
+                let mut args = args.into_iter();
+                let mut constants = arguments[1..].iter();
+                let mut text = String::new();
+                for c in recipe.chars() {
+                    let part = match c {
+                        '\u{1}' => args.next(),
+                        '\u{2}' => match constants.next() {
+                            Some(index) => Some(self.constant(*index)?.0),
+                            None => None,
+                        },
+                        c => {
+                            text.push(c);
+                            continue;
+                        }
+                    };
+                    if !text.is_empty() {
+                        parts.push(Expr::Str(std::mem::take(&mut text)));
+                    }
+                    parts.push(part.ok_or_else(|| {
+                        decompile_error("string concatenation recipe doesn't match its arguments")
+                    })?);
+                }
+                if !text.is_empty() {
+                    parts.push(Expr::Str(text));
+                }
+                join_strings(parts)
+            }
+            _ => Expr::Call {
+                target: Target::Static(format!(
+                    "/* invokedynamic {} */ {}",
+                    factory_method,
+                    self.context.names.class(&factory)
+                )),
+                name,
+                args,
+                descriptor: returns.clone(),
+            },
+        };
+        self.result(expr, &returns);
+        Ok(())
+    }
+
+    /// `Owner::method` for the method a lambda or method reference calls, `receiver::method`
+    /// when it's bound to a receiver
+    fn method_ref(&mut self, handle: u16, mut captured: Vec<Expr>) -> Result<Expr, io::Error> {
+        let constant_pool = self.context.constant_pool;
+        let Some(ConstantPoolData::MethodHandle(handle)) =
+            constant_pool.get(handle).map(|item| item.data())
+        else {
+            return Err(decompile_error("lambda without a method handle"));
+        };
+        let (owner, name, _) = constant_pool.member_ref(handle.reference_index())?;
+        let owner = self.context.names.class(&owner);
+        let kind = handle.reference_kind();
+        let target = match kind {
+            ReferenceKind::NewInvokeSpecial => Target::Constructor("new"),
+            ReferenceKind::InvokeVirtual
+            | ReferenceKind::InvokeInterface
+            | ReferenceKind::InvokeSpecial
+                if !captured.is_empty() =>
+            {
+                Target::Instance(Box::new(captured.remove(0)))
+            }
+            _ => Target::Static(owner.clone()),
+        };
+        Ok(Expr::MethodRef {
+            target,
+            name: match kind {
+                ReferenceKind::NewInvokeSpecial => owner,
+                _ => name,
+            },
+            captured,
+        })
+    }
+
+    fn branch(&mut self, condition: Expr, target: u32) -> Result<End, io::Error> {
+        Ok(End::Branch {
+            condition,
+            taken: self.block_at(target)?,
+            fall: self.next_block()?,
+        })
+    }
+
+    /// The condition for `if<op>` on one value, comparing the result of lcmp and the like
+    /// directly
+    fn compare_zero(&mut self, op: &'static str, target: u32) -> Result<End, io::Error> {
+        let value = self.pop()?;
+        let condition = match value {
+            Expr::Compare { left, right, .. } => Expr::Binary { op, left, right },
+            value if value.is_boolean() && op == "!=" => value,
+            value if value.is_boolean() && op == "==" => value.negate(),
+            value => Expr::binary(op, value, Expr::Int(0)),
+        };
+        self.branch(condition, target)
+    }
+
+    fn compare(&mut self, op: &'static str, target: u32) -> Result<End, io::Error> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        self.branch(Expr::binary(op, left, right), target)
+    }
+
+    fn switch(&mut self, cases: Vec<(i32, u32)>, default: u32) -> Result<End, io::Error> {
+        let key = self.pop()?;
+        let cases = cases
+            .into_iter()
+            .map(|(key, target)| Ok((key, self.block_at(target)?)))
+            .collect::<Result<Vec<_>, io::Error>>()?;
+        Ok(End::Switch {
+            key,
+            cases,
+            default: self.block_at(default)?,
+        })
+    }
+
+    fn exit(&mut self, stmt: Stmt) -> Result<Option<End>, io::Error> {
+        self.stmts.push(stmt);
+        Ok(Some(End::Exit))
+    }
+
+    /// Runs one instruction, returning how the block ends if it's the last one
+    fn instruction(
+        &mut self,
+        pc: u32,
+        next_pc: u32,
+        instruction: &Instruction,
+    ) -> Result<Option<End>, io::Error> {
+        use Instruction::*;
+        match instruction {
+            Nop => {}
+            AconstNull => self.push(Expr::Null, false),
+            IconstM1 => self.push(Expr::Int(-1), false),
+            Iconst0 => self.push(Expr::Int(0), false),
+            Iconst1 => self.push(Expr::Int(1), false),
+            Iconst2 => self.push(Expr::Int(2), false),
+            Iconst3 => self.push(Expr::Int(3), false),
+            Iconst4 => self.push(Expr::Int(4), false),
+            Iconst5 => self.push(Expr::Int(5), false),
+            Lconst0 => self.push(Expr::Long(0), true),
+            Lconst1 => self.push(Expr::Long(1), true),
+            Fconst0 => self.push(Expr::Float(0.0), false),
+            Fconst1 => self.push(Expr::Float(1.0), false),
+            Fconst2 => self.push(Expr::Float(2.0), false),
+            Dconst0 => self.push(Expr::Double(0.0), true),
+            Dconst1 => self.push(Expr::Double(1.0), true),
+            Bipush(value) => self.push(Expr::Int(*value as i32), false),
+            Sipush(value) => self.push(Expr::Int(*value as i32), false),
+            Ldc(index) | LdcW(index) | Ldc2W(index) => {
+                let (constant, wide) = self.constant(*index)?;
+                self.push(constant, wide);
+            }
+            Iload(slot) => self.load(*slot, pc, 'i'),
+            Lload(slot) => self.load(*slot, pc, 'l'),
+            Fload(slot) => self.load(*slot, pc, 'f'),
+            Dload(slot) => self.load(*slot, pc, 'd'),
+            Aload(slot) => self.load(*slot, pc, 'a'),
+            Iload0 | Iload1 | Iload2 | Iload3 => {
+                self.load(short_slot(instruction, Iload0), pc, 'i')
+            }
+            Lload0 | Lload1 | Lload2 | Lload3 => {
+                self.load(short_slot(instruction, Lload0), pc, 'l')
+            }
+            Fload0 | Fload1 | Fload2 | Fload3 => {
+                self.load(short_slot(instruction, Fload0), pc, 'f')
+            }
+            Dload0 | Dload1 | Dload2 | Dload3 => {
+                self.load(short_slot(instruction, Dload0), pc, 'd')
+            }
+            Aload0 | Aload1 | Aload2 | Aload3 => {
+                self.load(short_slot(instruction, Aload0), pc, 'a')
+            }
+            Iaload | Faload | Aaload | Baload | Caload | Saload => self.array_load(false)?,
+            Laload | Daload => self.array_load(true)?,
+            Istore(slot) => self.store(*slot, next_pc, 'i')?,
+            Lstore(slot) => self.store(*slot, next_pc, 'l')?,
+            Fstore(slot) => self.store(*slot, next_pc, 'f')?,
+            Dstore(slot) => self.store(*slot, next_pc, 'd')?,
+            Astore(slot) => self.store(*slot, next_pc, 'a')?,
+            Istore0 | Istore1 | Istore2 | Istore3 => {
+                self.store(short_slot(instruction, Istore0), next_pc, 'i')?
+            }
+            Lstore0 | Lstore1 | Lstore2 | Lstore3 => {
+                self.store(short_slot(instruction, Lstore0), next_pc, 'l')?
+            }
+            Fstore0 | Fstore1 | Fstore2 | Fstore3 => {
+                self.store(short_slot(instruction, Fstore0), next_pc, 'f')?
+            }
+            Dstore0 | Dstore1 | Dstore2 | Dstore3 => {
+                self.store(short_slot(instruction, Dstore0), next_pc, 'd')?
+            }
+            Astore0 | Astore1 | Astore2 | Astore3 => {
+                self.store(short_slot(instruction, Astore0), next_pc, 'a')?
+            }
+            Iastore | Lastore | Fastore | Dastore | Aastore | Bastore | Castore | Sastore => {
+                self.array_store()?
+            }
+            Pop | Pop2 => {
+                let words = if *instruction == Pop { 1 } else { 2 };
+                for slot in self.pop_words(words)? {
+                    // values without side effects can just be dropped, as can the null checks
+                    // javac adds before method references
+                    if !slot.expr.is_stable(None) && !is_null_check(&slot.expr) {
+                        self.statement(Stmt::Expr(slot.expr));
+                    }
+                }
+            }
+            Dup => self.dup(1, 0)?,
+            DupX1 => self.dup(1, 1)?,
+            DupX2 => self.dup(1, 2)?,
+            Dup2 => self.dup(2, 0)?,
+            Dup2X1 => self.dup(2, 1)?,
+            Dup2X2 => self.dup(2, 2)?,
+            Swap => {
+                let top = self.pop_slot()?;
+                let under = self.pop_slot()?;
+                self.stack.push(top);
+                self.stack.push(under);
+            }
+            Iadd => self.binary("+", false)?,
+            Ladd => self.binary("+", true)?,
+            Fadd => self.binary("+", false)?,
+            Dadd => self.binary("+", true)?,
+            Isub | Fsub => self.binary("-", false)?,
+            Lsub | Dsub => self.binary("-", true)?,
+            Imul | Fmul => self.binary("*", false)?,
+            Lmul | Dmul => self.binary("*", true)?,
+            Idiv | Fdiv => self.binary("/", false)?,
+            Ldiv | Ddiv => self.binary("/", true)?,
+            Irem | Frem => self.binary("%", false)?,
+            Lrem | Drem => self.binary("%", true)?,
+            Ishl => self.binary("<<", false)?,
+            Lshl => self.binary("<<", true)?,
+            Ishr => self.binary(">>", false)?,
+            Lshr => self.binary(">>", true)?,
+            Iushr => self.binary(">>>", false)?,
+            Lushr => self.binary(">>>", true)?,
+            Iand => self.binary("&", false)?,
+            Land => self.binary("&", true)?,
+            Ior => self.binary("|", false)?,
+            Lor => self.binary("|", true)?,
+            Ixor => self.binary("^", false)?,
+            Lxor => self.binary("^", true)?,
+            Ineg | Fneg | Lneg | Dneg => {
+                let slot = self.pop_slot()?;
+                self.push(
+                    Expr::Unary {
+                        op: "-",
+                        operand: Box::new(slot.expr),
+                    },
+                    slot.wide,
+                );
+            }
+            Iinc { index, increment } => {
+                let target = self.context.local(*index, pc, 'i');
+                self.statement(Stmt::Increment {
+                    target,
+                    amount: *increment as i32,
+                });
+            }
+            I2l | F2l | D2l => self.cast("long", true)?,
+            I2f | L2f | D2f => self.cast("float", false)?,
+            I2d | L2d | F2d => self.cast("double", true)?,
+            L2i | F2i | D2i => self.cast("int", false)?,
+            I2b => self.cast("byte", false)?,
+            I2c => self.cast("char", false)?,
+            I2s => self.cast("short", false)?,
+            Lcmp | Fcmpl | Fcmpg | Dcmpl | Dcmpg => {
+                let right = self.pop()?;
+                let left = self.pop()?;
+                let class = match instruction {
+                    Lcmp => "Long",
+                    Fcmpl | Fcmpg => "Float",
+                    _ => "Double",
+                };
+                self.push(
+                    Expr::Compare {
+                        class,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    },
+                    false,
+                );
+            }
+            Ifeq(target) => return self.compare_zero("==", *target).map(Some),
+            Ifne(target) => return self.compare_zero("!=", *target).map(Some),
+            Iflt(target) => return self.compare_zero("<", *target).map(Some),
+            Ifge(target) => return self.compare_zero(">=", *target).map(Some),
+            Ifgt(target) => return self.compare_zero(">", *target).map(Some),
+            Ifle(target) => return self.compare_zero("<=", *target).map(Some),
+            IfIcmpeq(target) | IfAcmpeq(target) => return self.compare("==", *target).map(Some),
+            IfIcmpne(target) | IfAcmpne(target) => return self.compare("!=", *target).map(Some),
+            IfIcmplt(target) => return self.compare("<", *target).map(Some),
+            IfIcmpge(target) => return self.compare(">=", *target).map(Some),
+            IfIcmpgt(target) => return self.compare(">", *target).map(Some),
+            IfIcmple(target) => return self.compare("<=", *target).map(Some),
+            Ifnull(target) | Ifnonnull(target) => {
+                let value = self.pop()?;
+                let op = if matches!(instruction, Ifnull(_)) {
+                    "=="
+                } else {
+                    "!="
+                };
+                return self
+                    .branch(Expr::binary(op, value, Expr::Null), *target)
+                    .map(Some);
+            }
+            Goto(target) | GotoW(target) => return Ok(Some(End::Jump(self.block_at(*target)?))),
+            Jsr(_) | JsrW(_) | Ret(_) => {
+                return Err(decompile_error("jsr and ret subroutines aren't supported"))
+            }
+            Tableswitch {
+                default,
+                low,
+                targets,
+                ..
+            } => {
+                let cases = targets
+                    .iter()
+                    .enumerate()
+                    .map(|(i, target)| (low.wrapping_add(i as i32), *target))
+                    .collect();
+                return self.switch(cases, *default).map(Some);
+            }
+            Lookupswitch { default, pairs } => {
+                return self.switch(pairs.clone(), *default).map(Some)
+            }
+            Ireturn | Lreturn | Freturn | Dreturn | Areturn => {
+                let value = self.pop()?;
+                let value = coerce(value, &self.context.return_descriptor);
+                return self.exit(Stmt::Return(Some(value)));
+            }
+            Return => return self.exit(Stmt::Return(None)),
+            Getstatic(index) => {
+                let (owner, name, descriptor) = self.context.constant_pool.member_ref(*index)?;
+                let wide = is_wide(&descriptor);
+                let owner = self.context.names.class(&owner);
+                self.push(
+                    Expr::Field {
+                        object: None,
+                        owner,
+                        name,
+                        descriptor,
+                    },
+                    wide,
+                );
+            }
+            Getfield(index) => {
+                let (owner, name, descriptor) = self.context.constant_pool.member_ref(*index)?;
+                let object = self.pop()?;
+                let wide = is_wide(&descriptor);
+                let owner = self.context.names.class(&owner);
+                self.push(
+                    Expr::Field {
+                        object: Some(Box::new(object)),
+                        owner,
+                        name,
+                        descriptor,
+                    },
+                    wide,
+                );
+            }
+            Putstatic(index) | Putfield(index) => {
+                let (owner, name, descriptor) = self.context.constant_pool.member_ref(*index)?;
+                let value = coerce(self.pop()?, &descriptor);
+                let object = match instruction {
+                    Putfield(_) => Some(Box::new(self.pop()?)),
+                    _ => None,
+                };
+                let owner = self.context.names.class(&owner);
+                self.statement(Stmt::Assign {
+                    target: Expr::Field {
+                        object,
+                        owner,
+                        name,
+                        descriptor,
+                    },
+                    value,
+                    declare: None,
+                });
+            }
+            Invokevirtual(index)
+            | Invokespecial(index)
+            | Invokestatic(index)
+            | Invokeinterface { index, .. } => self.invoke(instruction, *index)?,
+            Invokedynamic(index) => self.invokedynamic(*index)?,
+            New(index) => {
+                let name = self.context.constant_pool.class_name(*index)?;
+                let class = self.context.names.class(&name);
+                self.push(Expr::Uninitialized { pc, class }, false);
+            }
+            Newarray(array_type) => {
+                let count = self.pop()?;
+                let element = match array_type {
+                    ArrayType::Boolean => "boolean",
+                    ArrayType::Char => "char",
+                    ArrayType::Float => "float",
+                    ArrayType::Double => "double",
+                    ArrayType::Byte => "byte",
+                    ArrayType::Short => "short",
+                    ArrayType::Int => "int",
+                    ArrayType::Long => "long",
+                };
+                self.push(
+                    Expr::NewArray {
+                        element: element.to_string(),
+                        dimensions: vec![count],
+                        extra: 0,
+                    },
+                    false,
+                );
+            }
+            Anewarray(index) => {
+                let count = self.pop()?;
+                let name = self.context.constant_pool.class_name(*index)?;
+                let element = self.class_type(&name);
+                // an array of arrays puts the new dimension first
+                let (element, extra) = match element.find("[]") {
+                    Some(brackets) => (
+                        element[..brackets].to_string(),
+                        element[brackets..].len() / 2,
+                    ),
+                    None => (element, 0),
+                };
+                self.push(
+                    Expr::NewArray {
+                        element,
+                        dimensions: vec![count],
+                        extra,
+                    },
+                    false,
+                );
+            }
+            Multianewarray { index, dimensions } => {
+                let sizes = self.pop_args(*dimensions as usize)?;
+                let name = self.context.constant_pool.class_name(*index)?;
+                let array = self.class_type(&name);
+                let brackets = array.find("[]").unwrap_or(array.len());
+                let total = array[brackets..].len() / 2;
+                self.push(
+                    Expr::NewArray {
+                        element: array[..brackets].to_string(),
+                        extra: total.saturating_sub(sizes.len()),
+                        dimensions: sizes,
+                    },
+                    false,
+                );
+            }
+            Arraylength => {
+                let array = self.pop()?;
+                self.push(Expr::ArrayLength(Box::new(array)), false);
+            }
+            Athrow => {
+                let value = self.pop()?;
+                return self.exit(Stmt::Throw(value));
+            }
+            Checkcast(index) => {
+                let name = self.context.constant_pool.class_name(*index)?;
+                let to = self.class_type(&name);
+                self.cast(&to, false)?;
+            }
+            Instanceof(index) => {
+                let value = self.pop()?;
+                let name = self.context.constant_pool.class_name(*index)?;
+                let class = self.class_type(&name);
+                self.push(
+                    Expr::InstanceOf {
+                        value: Box::new(value),
+                        class,
+                    },
+                    false,
+                );
+            }
+            Monitorenter | Monitorexit => {
+                let lock = self.pop()?;
+                self.statement(Stmt::Monitor {
+                    enter: *instruction == Monitorenter,
+                    lock,
+                });
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Whether evaluating the expression calls a method or constructor
+fn calls(expr: &Expr) -> bool {
+    let mut calls = false;
+    expr.visit(&mut |e| calls |= matches!(e, Expr::Call { .. } | Expr::New { .. }));
+    calls
+}
+
+/// `Objects.requireNonNull(x)` or `x.getClass()` whose result is dropped
+fn is_null_check(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call {
+            target: Target::Static(owner),
+            name,
+            args,
+            ..
+        } => {
+            matches!(owner.as_str(), "Objects" | "java.util.Objects")
+                && name == "requireNonNull"
+                && args.len() == 1
+                && !calls(&args[0])
+        }
+        Expr::Call {
+            target: Target::Instance(object),
+            name,
+            args,
+            ..
+        } => name == "getClass" && args.is_empty() && !calls(object),
+        _ => false,
+    }
+}
+
+fn is_wide(descriptor: &str) -> bool {
+    matches!(descriptor, "J" | "D")
+}
+
+/// The slot of `iload_2` and the like, given the `_0` form of the instruction
+fn short_slot(instruction: &Instruction, zero: Instruction) -> u16 {
+    (instruction.opcode() - zero.opcode()) as u16
+}
+
+/// Shows int constants as the boolean or char the descriptor says they are
+pub fn coerce(value: Expr, descriptor: &str) -> Expr {
+    match (value, descriptor) {
+        (Expr::Int(0), "Z") => Expr::Text("false".to_string()),
+        (Expr::Int(1), "Z") => Expr::Text("true".to_string()),
+        (Expr::Int(c), "C") if (0x20..0x7f).contains(&c) || matches!(c, 8 | 9 | 10 | 12 | 13) => {
+            // the range checked above is ASCII
+            let c = char::from_u32(c as u32).unwrap();
+            Expr::Text(format!("'{}'", escape(&c.to_string(), '\'')))
+        }
+        (
+            Expr::Ternary {
+                condition,
+                then,
+                otherwise,
+            },
+            "Z",
+        ) => match (*then, *otherwise) {
+            (Expr::Int(1), Expr::Int(0)) => *condition,
+            (Expr::Int(0), Expr::Int(1)) => condition.negate(),
+            (then, otherwise) => Expr::Ternary {
+                condition,
+                then: Box::new(coerce(then, "Z")),
+                otherwise: Box::new(coerce(otherwise, "Z")),
+            },
+        },
+        (value, _) => value,
+    }
+}
+
+/// `a + b + c` from the parts of a string concatenation, starting with `""` when neither of
+/// the first two parts is a string literal so that it can't be read as an addition
+fn join_strings(mut parts: Vec<Expr>) -> Expr {
+    let string = |part: Option<&Expr>| matches!(part, Some(Expr::Str(_)));
+    if !string(parts.first()) && !string(parts.get(1)) {
+        parts.insert(0, Expr::Str(String::new()));
+    }
+    let mut parts = parts.into_iter();
+    // there's at least the empty string
+    let first = parts.next().unwrap();
+    parts.fold(first, |left, right| Expr::binary("+", left, right))
+}
+
+/// The concatenation a `new StringBuilder(...).append(...)...toString()` chain builds
+fn concat(receiver: &Expr) -> Option<Expr> {
+    let mut parts = Vec::new();
+    let mut current = receiver;
+    loop {
+        match current {
+            Expr::Call {
+                target: Target::Instance(inner),
+                name,
+                args,
+                ..
+            } if name == "append" && args.len() == 1 => {
+                parts.push(args[0].clone());
+                current = inner;
+            }
+            Expr::New { class, args }
+                if matches!(class.as_str(), "StringBuilder" | "StringBuffer")
+                    && args.len() <= 1 =>
+            {
+                parts.extend(args.iter().cloned());
+                break;
+            }
+            _ => return None,
+        }
+    }
+    parts.reverse();
+    Some(join_strings(parts))
+}
+
+pub fn decompile_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+    use crate::tools::decompile::decompiler::decompile;
+
+    #[test]
+    fn coerces_to_descriptors() {
+        assert_eq!(coerce(Expr::Int(1), "Z").to_source(), "true");
+        assert_eq!(coerce(Expr::Int(65), "C").to_source(), "'A'");
+        assert_eq!(coerce(Expr::Int(65), "I").to_source(), "65");
+        let condition = Expr::binary("<", Expr::Int(1), Expr::Int(2));
+        let ternary = Expr::Ternary {
+            condition: Box::new(condition.clone()),
+            then: Box::new(Expr::Int(0)),
+            otherwise: Box::new(Expr::Int(1)),
+        };
+        assert_eq!(coerce(ternary, "Z"), condition.negate());
+    }
+
+    #[test]
+    fn evaluates_duplicated_calls_once() {
+        let class_file = assemble(
+            ".class public Twice
+.super java/lang/Object
+.method public static f()I
+  .limit stack 1
+  iconst_1
+  ireturn
+.end method
+.method public static g()I
+  .limit stack 2
+  .limit locals 2
+  invokestatic Twice/f()I
+  dup
+  istore_0
+  istore_1
+  iconst_1
+  newarray int
+  dup
+  iconst_0
+  iload_0
+  iastore
+  arraylength
+  ireturn
+.end method
+",
+        )
+        .unwrap();
+        let source = decompile(&class_file).unwrap();
+        assert!(source.contains(
+            "        int i1 = Twice.f();
+        int i0 = i1;
+        return new int[]{i0}.length;
+"
+        ));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod ast;
+pub mod decompiler;
+pub mod expressions;
+pub mod structure;
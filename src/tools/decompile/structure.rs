@@ -0,0 +1,1499 @@
+use crate::analysis::cfg::{ControlFlowGraph, DominatorTree, NodeId};
+use crate::class_file::attribute::code::Code;
+use crate::tools::decompile::ast::{ends_with_jump, Case, Catch, Expr, LoopKind, Stmt, Target};
+use crate::tools::decompile::expressions::{
+    coerce, decompile_error, rebuild, BlockCode, End, MethodContext,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+
+// Turns the blocks of a method back into nested statements. Conditions that jump to the same
+// place are first merged into `&&` and `||`. Then the blocks are walked from the entry: a
+// natural loop becomes `while (true)` with the loop's exit as what follows it, a branch
+// becomes an `if` whose branches run up to the branch's immediate post-dominator, a switch
+// gets a case per target, and an exception table range becomes a `try` with its handlers as
+// the catches. Jumps to an enclosing loop's header or exit become `continue` and `break`, and
+// anything else that can't be nested becomes a `goto` to a labelled pc. Cleanup passes then
+// turn loops into `while` and `do`/`while` where their shape allows and fold the values of
+// conditional expressions back into `?:`.
+
+/// How deeply regions can nest before the method is given up on, to bound recursion
+const MAX_NESTING: usize = 512;
+
+/// The statements of a method's body
+pub fn structure(
+    cfg: &ControlFlowGraph,
+    code: &Code,
+    context: &mut MethodContext,
+) -> Result<Vec<Stmt>, io::Error> {
+    let rebuilt = rebuild(cfg, code, context)?;
+    let mut blocks = rebuilt.blocks;
+    let count = blocks.len();
+    let starts: Vec<u32> = cfg.blocks().iter().map(|block| block.start()).collect();
+    let coverage: Vec<Vec<usize>> = starts
+        .iter()
+        .map(|pc| {
+            (0..code.exception_table().len())
+                .filter(|i| covers(code, *i, *pc))
+                .collect()
+        })
+        .collect();
+    let mut handlers = HashSet::new();
+    for entry in code.exception_table() {
+        // every handler starts a block
+        handlers.insert(cfg.block_at(entry.handler_pc() as u32).unwrap());
+    }
+    merge_conditions(&mut blocks, &coverage, &handlers);
+
+    // the graph of normal edges, with a root before the entry and every handler and an exit
+    // after every return and throw
+    let root = count;
+    let exit = count + 1;
+    let mut successors = vec![Vec::new(); count + 2];
+    successors[root].push(0);
+    successors[root].extend(handlers.iter().copied());
+    for (node, block) in blocks.iter().enumerate() {
+        if let Some(block) = block {
+            successors[node] = match block.end {
+                End::Exit => vec![exit],
+                ref end => end.successors(),
+            };
+        }
+    }
+    let mut predecessors = vec![Vec::new(); count + 2];
+    for (node, targets) in successors.iter().enumerate() {
+        for target in targets {
+            if !predecessors[*target].contains(&node) {
+                predecessors[*target].push(node);
+            }
+        }
+    }
+    let dominators = DominatorTree::new(
+        root,
+        count + 2,
+        |node| successors[node].clone(),
+        |node| predecessors[node].clone(),
+    );
+    let post_dominators = DominatorTree::new(
+        exit,
+        count + 2,
+        |node| predecessors[node].clone(),
+        |node| successors[node].clone(),
+    );
+
+    let loops = find_loops(&blocks, &successors, &predecessors, &dominators);
+    let spans = try_spans(code, cfg, &blocks, &starts, &successors, context)?;
+    let mut structurer = Structurer {
+        blocks: &blocks,
+        successors: &successors,
+        starts: &starts,
+        post_dominators,
+        exit,
+        opened: vec![false; spans.len()],
+        loops,
+        spans,
+        emitted: vec![false; count],
+        catch_stores: HashSet::new(),
+        contexts: Vec::new(),
+        follows: Vec::new(),
+        next_id: 0,
+    };
+    let mut body = Vec::new();
+    structurer.emit(0, None, &mut body, false, 0)?;
+    // handlers of ranges that didn't become a try
+    for node in 0..count {
+        if blocks[node].is_some() && !structurer.emitted[node] {
+            body.push(Stmt::Comment(format!(
+                "exception handler at pc {}",
+                starts[node]
+            )));
+            structurer.emit(node, None, &mut body, false, 0)?;
+        }
+    }
+
+    let mut targets = HashSet::new();
+    for_each_stmt(&mut body, &mut |stmt| {
+        if let Stmt::Goto(pc) = stmt {
+            targets.insert(*pc);
+        }
+    });
+    strip_labels(&mut body, &targets);
+    simplify_loops(&mut body);
+    fold_ternaries(&mut body);
+    inline_stack_values(&mut body, &rebuilt.passthrough, &HashSet::new());
+    let return_descriptor = context.return_descriptor.clone();
+    let names = &mut *context.names;
+    for_each_stmt(&mut body, &mut |stmt| match stmt {
+        Stmt::Return(Some(value)) => {
+            *value = coerce(std::mem::replace(value, Expr::Null), &return_descriptor)
+        }
+        Stmt::Assign {
+            target:
+                Expr::Local {
+                    descriptor,
+                    type_name,
+                    ..
+                },
+            value: Expr::Cast { to, .. },
+            ..
+        } if *to == names.type_name(descriptor) => {
+            // a cast to the erasure of a generic local's type
+            *to = type_name.clone()
+        }
+        Stmt::Assign {
+            target: Expr::Local { descriptor, .. } | Expr::Field { descriptor, .. },
+            value,
+            ..
+        } => *value = coerce(std::mem::replace(value, Expr::Null), descriptor),
+        _ => {}
+    });
+    if body.last() == Some(&Stmt::Return(None)) {
+        body.pop();
+    }
+    Ok(body)
+}
+
+fn covers(code: &Code, entry: usize, pc: u32) -> bool {
+    let entry = &code.exception_table()[entry];
+    (entry.start_pc() as u32..entry.end_pc() as u32).contains(&pc)
+}
+
+/// Merges a branch into the one before it when the second only runs on one side of the first
+/// and both share a target, making `a || b` or `a && b`
+fn merge_conditions(
+    blocks: &mut [Option<BlockCode>],
+    coverage: &[Vec<usize>],
+    handlers: &HashSet<NodeId>,
+) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let mut predecessors = vec![Vec::new(); blocks.len()];
+        for (node, block) in blocks.iter().enumerate() {
+            if let Some(block) = block {
+                for successor in block.end.successors() {
+                    if !predecessors[successor].contains(&node) {
+                        predecessors[successor].push(node);
+                    }
+                }
+            }
+        }
+        for node in (0..blocks.len()).rev() {
+            let Some(BlockCode {
+                end:
+                    End::Branch {
+                        condition: first,
+                        taken,
+                        fall,
+                    },
+                ..
+            }) = &blocks[node]
+            else {
+                continue;
+            };
+            for (inner, outer, on_fall) in [(*fall, *taken, true), (*taken, *fall, false)] {
+                if inner <= node
+                    || predecessors[inner] != [node]
+                    || handlers.contains(&inner)
+                    || coverage[inner] != coverage[node]
+                {
+                    continue;
+                }
+                let Some(BlockCode {
+                    stmts,
+                    end:
+                        End::Branch {
+                            condition: second,
+                            taken: inner_taken,
+                            fall: inner_fall,
+                        },
+                    entry_depth: 0,
+                }) = &blocks[inner]
+                else {
+                    continue;
+                };
+                let (z, w) = (*inner_taken, *inner_fall);
+                if !stmts.is_empty() || [z, w].iter().any(|t| *t == node || *t == inner) {
+                    continue;
+                }
+                let (first, second) = (first.clone(), second.clone());
+                let end = match (on_fall, z == outer, w == outer) {
+                    (true, true, _) => End::Branch {
+                        condition: Expr::binary("||", first, second),
+                        taken: outer,
+                        fall: w,
+                    },
+                    (true, _, true) => End::Branch {
+                        condition: Expr::binary("&&", first.negate(), second),
+                        taken: z,
+                        fall: outer,
+                    },
+                    (false, true, _) => End::Branch {
+                        condition: Expr::binary("&&", first, second.negate()),
+                        taken: w,
+                        fall: outer,
+                    },
+                    (false, _, true) => End::Branch {
+                        condition: Expr::binary("&&", first, second),
+                        taken: z,
+                        fall: outer,
+                    },
+                    _ => continue,
+                };
+                // the outer block is a branch, checked above
+                blocks[node].as_mut().unwrap().end = end;
+                blocks[inner] = None;
+                changed = true;
+                break;
+            }
+        }
+    }
+}
+
+struct LoopInfo {
+    header: NodeId,
+    /// In order
+    blocks: Vec<NodeId>,
+    /// Where the loop goes when it's done
+    follow: Option<NodeId>,
+}
+
+impl LoopInfo {
+    fn contains(&self, node: NodeId) -> bool {
+        self.blocks.binary_search(&node).is_ok()
+    }
+}
+
+/// Natural loops over normal edges, outermost first. The follow is the header's way out
+/// if it has exactly one, else the first exit after the loop, else the first exit.
+fn find_loops(
+    blocks: &[Option<BlockCode>],
+    successors: &[Vec<NodeId>],
+    predecessors: &[Vec<NodeId>],
+    dominators: &DominatorTree,
+) -> Vec<LoopInfo> {
+    let count = blocks.len();
+    let mut loops = Vec::new();
+    for header in 0..count {
+        let latches: Vec<NodeId> = predecessors[header]
+            .iter()
+            .copied()
+            .filter(|from| *from < count && dominators.dominates(header, *from))
+            .collect();
+        if latches.is_empty() {
+            continue;
+        }
+        let mut body = HashSet::from([header]);
+        let mut work = latches;
+        while let Some(node) = work.pop() {
+            if node < count && body.insert(node) {
+                work.extend(predecessors[node].iter().copied());
+            }
+        }
+        let mut body: Vec<NodeId> = body.into_iter().collect();
+        body.sort_unstable();
+        let last = *body.last().unwrap();
+        let exits = |node: &NodeId| -> Vec<NodeId> {
+            successors[*node]
+                .iter()
+                .copied()
+                .filter(|s| *s < count && body.binary_search(s).is_err())
+                .collect()
+        };
+        let all: Vec<NodeId> = body.iter().flat_map(exits).collect();
+        let from_header = exits(&header);
+        let follow = match from_header.as_slice() {
+            [only] => Some(*only),
+            _ => all
+                .iter()
+                .copied()
+                .filter(|exit| *exit > last)
+                .min()
+                .or_else(|| all.iter().copied().min()),
+        };
+        loops.push(LoopInfo {
+            header,
+            blocks: body,
+            follow,
+        });
+    }
+    loops
+}
+
+#[derive(Clone)]
+struct TrySpan {
+    start: NodeId,
+    start_pc: u32,
+    end_pc: u32,
+    /// Handlers and the classes they catch, joined with `|` when one handler has several
+    catches: Vec<(NodeId, String)>,
+    follow: Option<NodeId>,
+}
+
+/// The try statements of the exception table: the ranges of each handler and type are merged,
+/// since code such as a `finally` block copied into the middle splits them, and handlers
+/// with the same range become the catches of one try. Outer ones come first.
+fn try_spans(
+    code: &Code,
+    cfg: &ControlFlowGraph,
+    blocks: &[Option<BlockCode>],
+    starts: &[u32],
+    successors: &[Vec<NodeId>],
+    context: &mut MethodContext,
+) -> Result<Vec<TrySpan>, io::Error> {
+    let mut ranges: Vec<(u16, u16, u32, u32)> = Vec::new();
+    for entry in code.exception_table() {
+        let (start, end) = (entry.start_pc() as u32, entry.end_pc() as u32);
+        // javac covers the start of a finally handler with itself
+        if (start..end).contains(&(entry.handler_pc() as u32)) {
+            continue;
+        }
+        match ranges.iter_mut().find(|(handler, class, ..)| {
+            *handler == entry.handler_pc() && *class == entry.catch_type()
+        }) {
+            Some(range) => {
+                range.2 = range.2.min(start);
+                range.3 = range.3.max(end);
+            }
+            None => ranges.push((entry.handler_pc(), entry.catch_type(), start, end)),
+        }
+    }
+
+    let mut spans: Vec<TrySpan> = Vec::new();
+    for (handler_pc, catch_type, start_pc, end_pc) in ranges {
+        let handler = cfg.block_at(handler_pc as u32).unwrap();
+        let Some(start) = cfg
+            .block_at(start_pc)
+            .filter(|start| blocks[*start].is_some())
+        else {
+            continue;
+        };
+        if (start_pc..end_pc).contains(&(handler_pc as u32)) {
+            continue;
+        }
+        let class = match catch_type {
+            0 => "Throwable".to_string(),
+            index => {
+                let name = context.constant_pool.class_name(index)?;
+                context.names.class(&name)
+            }
+        };
+        let span = match spans
+            .iter_mut()
+            .find(|span| span.start_pc == start_pc && span.end_pc == end_pc)
+        {
+            Some(span) => span,
+            None => {
+                spans.push(TrySpan {
+                    start,
+                    start_pc,
+                    end_pc,
+                    catches: Vec::new(),
+                    follow: None,
+                });
+                spans.last_mut().unwrap()
+            }
+        };
+        match span.catches.iter_mut().find(|(node, _)| *node == handler) {
+            Some((_, classes)) => *classes = format!("{classes} | {class}"),
+            None => span.catches.push((handler, class)),
+        }
+    }
+
+    for span in spans.iter_mut() {
+        let inside = |node: NodeId| (span.start_pc..span.end_pc).contains(&starts[node]);
+        let exits: Vec<NodeId> = (0..blocks.len())
+            .filter(|node| blocks[*node].is_some() && inside(*node))
+            .flat_map(|node| successors[node].iter().copied())
+            .filter(|node| *node < blocks.len())
+            .map(|node| skip_jumps(blocks, node))
+            .filter(|node| !inside(*node))
+            .collect();
+        span.follow = exits
+            .iter()
+            .copied()
+            .filter(|node| starts[*node] >= span.end_pc)
+            .min()
+            .or_else(|| exits.iter().copied().min());
+    }
+    spans.sort_by_key(|span| (span.start_pc, std::cmp::Reverse(span.end_pc)));
+    Ok(spans)
+}
+
+/// Where a chain of blocks that only jump ends up
+fn skip_jumps(blocks: &[Option<BlockCode>], mut node: NodeId) -> NodeId {
+    for _ in 0..blocks.len() {
+        match &blocks[node] {
+            Some(BlockCode {
+                stmts,
+                end: End::Jump(next),
+                ..
+            }) if stmts.is_empty() => node = *next,
+            _ => break,
+        }
+    }
+    node
+}
+
+enum Context {
+    Loop { id: usize, index: usize },
+    Switch { id: usize, follow: Option<NodeId> },
+}
+
+struct Structurer<'s> {
+    blocks: &'s [Option<BlockCode>],
+    /// Over normal edges, as for the dominator trees
+    successors: &'s [Vec<NodeId>],
+    starts: &'s [u32],
+    post_dominators: DominatorTree,
+    exit: NodeId,
+    loops: Vec<LoopInfo>,
+    spans: Vec<TrySpan>,
+    opened: Vec<bool>,
+    emitted: Vec<bool>,
+    /// Handlers whose first statement stores the exception in the catch's variable
+    catch_stores: HashSet<NodeId>,
+    contexts: Vec<Context>,
+    /// Where each enclosing region stops
+    follows: Vec<NodeId>,
+    next_id: usize,
+}
+
+impl Structurer<'_> {
+    /// Adds the statements from `node` up to `follow`. `at_header` is set when `node` is the
+    /// header of the loop just opened, which isn't a `continue` the first time.
+    fn emit(
+        &mut self,
+        node: NodeId,
+        follow: Option<NodeId>,
+        out: &mut Vec<Stmt>,
+        at_header: bool,
+        depth: usize,
+    ) -> Result<(), io::Error> {
+        if depth > MAX_NESTING {
+            return Err(decompile_error("control flow is nested too deeply"));
+        }
+        if let Some(follow) = follow {
+            self.follows.push(follow);
+        }
+        let result = self.emit_region(node, follow, out, at_header, depth);
+        if follow.is_some() {
+            self.follows.pop();
+        }
+        result
+    }
+
+    fn emit_region(
+        &mut self,
+        mut node: NodeId,
+        follow: Option<NodeId>,
+        out: &mut Vec<Stmt>,
+        mut at_header: bool,
+        depth: usize,
+    ) -> Result<(), io::Error> {
+        loop {
+            if Some(node) == follow {
+                return Ok(());
+            }
+            if !at_header {
+                if let Some(jump) = self.jump_to(node) {
+                    out.push(jump);
+                    return Ok(());
+                }
+            }
+
+            let new_loop = (0..self.loops.len())
+                .find(|l| self.loops[*l].header == node && !self.is_active(*l));
+            let span = (0..self.spans.len())
+                .find(|s| !self.opened[*s] && self.spans[*s].start == node)
+                .filter(|s| new_loop.is_none_or(|l| self.span_covers(*s, l)));
+            if let Some(span) = span {
+                match self.open_try(span, out, at_header, depth)? {
+                    Some(next) => node = next,
+                    None => return Ok(()),
+                }
+                at_header = false;
+                continue;
+            }
+            if let Some(index) = new_loop {
+                let id = self.new_id();
+                self.contexts.push(Context::Loop { id, index });
+                let mut body = Vec::new();
+                let result = self.emit(node, None, &mut body, true, depth + 1);
+                self.contexts.pop();
+                result?;
+                out.push(Stmt::Loop {
+                    id,
+                    kind: LoopKind::Infinite,
+                    body,
+                });
+                match self.loops[index].follow {
+                    Some(next) => node = next,
+                    None => return Ok(()),
+                }
+                at_header = false;
+                continue;
+            }
+
+            let blocks = self.blocks;
+            let Some(block) = &blocks[node] else {
+                return Err(decompile_error("jump to a merged block"));
+            };
+            self.emitted[node] = true;
+            out.push(Stmt::Label(self.starts[node]));
+            let skip = self.catch_stores.contains(&node) as usize;
+            out.extend(block.stmts.iter().skip(skip).cloned());
+            at_header = false;
+            match &block.end {
+                End::Exit => return Ok(()),
+                End::Jump(next) => node = *next,
+                End::Branch {
+                    condition,
+                    taken,
+                    fall,
+                } if taken == fall => {
+                    // the condition is still evaluated
+                    out.push(if_else(condition.clone(), Vec::new(), Vec::new()));
+                    node = *taken;
+                }
+                End::Branch {
+                    condition,
+                    taken,
+                    fall,
+                } => match self.merge(node) {
+                    Some(merge) => {
+                        let mut then = Vec::new();
+                        self.emit(*fall, Some(merge), &mut then, false, depth + 1)?;
+                        let mut otherwise = Vec::new();
+                        self.emit(*taken, Some(merge), &mut otherwise, false, depth + 1)?;
+                        push_if(out, condition.clone().negate(), then, otherwise);
+                        node = merge;
+                    }
+                    None => {
+                        // a side that leaves a loop or switch comes first
+                        if let Some(jump) = self
+                            .jump_to(*taken)
+                            .filter(|jump| !matches!(jump, Stmt::Goto(_)))
+                        {
+                            out.push(if_else(condition.clone(), vec![jump], Vec::new()));
+                            node = *fall;
+                            continue;
+                        }
+                        if let Some(jump) = self
+                            .jump_to(*fall)
+                            .filter(|jump| !matches!(jump, Stmt::Goto(_)))
+                        {
+                            out.push(if_else(condition.clone().negate(), vec![jump], Vec::new()));
+                            node = *taken;
+                            continue;
+                        }
+                        // the fall-through side is nested and the target follows it
+                        let mut then = Vec::new();
+                        self.emit(*fall, Some(*taken), &mut then, false, depth + 1)?;
+                        push_if(out, condition.clone().negate(), then, Vec::new());
+                        node = *taken;
+                    }
+                },
+                End::Switch {
+                    key,
+                    cases,
+                    default,
+                } => {
+                    let mut targets: BTreeMap<NodeId, Vec<Option<i32>>> = BTreeMap::new();
+                    // a tableswitch fills the gaps between its keys with the default
+                    for (key, target) in cases.iter().filter(|(_, target)| target != default) {
+                        targets.entry(*target).or_default().push(Some(*key));
+                    }
+                    targets.entry(*default).or_default().push(None);
+                    let merge = self
+                        .merge(node)
+                        .or_else(|| self.shared_follow(node, targets.keys()));
+                    let targets: Vec<(NodeId, Vec<Option<i32>>)> = targets
+                        .into_iter()
+                        .filter(|(target, _)| Some(*target) != merge)
+                        .collect();
+                    let id = self.new_id();
+                    self.contexts.push(Context::Switch { id, follow: merge });
+                    let mut switch_cases = Vec::new();
+                    for (i, (target, keys)) in targets.iter().enumerate() {
+                        // a case that doesn't break falls into the next one
+                        let next = targets.get(i + 1).map(|(next, _)| *next).or(merge);
+                        let mut body = Vec::new();
+                        if let Err(e) = self.emit(*target, next, &mut body, false, depth + 1) {
+                            self.contexts.pop();
+                            return Err(e);
+                        }
+                        switch_cases.push(Case {
+                            keys: keys.clone(),
+                            body,
+                        });
+                    }
+                    self.contexts.pop();
+                    out.push(Stmt::Switch {
+                        id,
+                        key: key.clone(),
+                        cases: switch_cases,
+                    });
+                    match merge {
+                        Some(merge) => node = merge,
+                        None => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+
+    fn new_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn is_active(&self, index: usize) -> bool {
+        self.contexts
+            .iter()
+            .any(|context| matches!(context, Context::Loop { index: i, .. } if *i == index))
+    }
+
+    fn span_covers(&self, span: usize, index: usize) -> bool {
+        let span = &self.spans[span];
+        self.loops[index]
+            .blocks
+            .iter()
+            .all(|node| (span.start_pc..span.end_pc).contains(&self.starts[*node]))
+    }
+
+    /// A `continue` or `break` when `node` is an enclosing loop's header or exit or a switch's
+    /// exit, or a `goto` when it's been emitted already
+    fn jump_to(&self, node: NodeId) -> Option<Stmt> {
+        let mut innermost_loop = true;
+        let mut innermost = true;
+        for context in self.contexts.iter().rev() {
+            match context {
+                Context::Loop { id, index } => {
+                    let info = &self.loops[*index];
+                    if node == info.header {
+                        return Some(Stmt::Continue(Some(*id).filter(|_| !innermost_loop)));
+                    }
+                    if Some(node) == info.follow {
+                        return Some(Stmt::Break(Some(*id).filter(|_| !innermost)));
+                    }
+                    innermost_loop = false;
+                }
+                Context::Switch { id, follow } => {
+                    if Some(node) == *follow {
+                        return Some(Stmt::Break(Some(*id).filter(|_| !innermost)));
+                    }
+                }
+            }
+            innermost = false;
+        }
+        match self.emitted[node] {
+            true => Some(Stmt::Goto(self.starts[node])),
+            false => None,
+        }
+    }
+
+    /// Where both sides of a branch or all the cases of a switch meet again: its immediate
+    /// post-dominator, if that's inside the innermost loop, cut short at the end of the
+    /// enclosing region
+    fn merge(&self, node: NodeId) -> Option<NodeId> {
+        let mut merge = self
+            .post_dominators
+            .immediate_dominator(node)
+            .filter(|merge| *merge != self.exit)?;
+        for follow in self.follows.iter().rev() {
+            if merge != *follow && self.post_dominators.dominates(merge, *follow) {
+                merge = *follow;
+                break;
+            }
+        }
+        let innermost = self
+            .contexts
+            .iter()
+            .rev()
+            .find_map(|context| match context {
+                Context::Loop { index, .. } => Some(&self.loops[*index]),
+                Context::Switch { .. } => None,
+            });
+        match innermost {
+            Some(info) if !info.contains(merge) && Some(merge) != info.follow => None,
+            _ => Some(merge),
+        }
+    }
+
+    /// Where the cases of a switch that some of them return from meet: the first block after
+    /// the switch that more than one case reaches without leaving the enclosing regions
+    fn shared_follow<'t>(
+        &self,
+        node: NodeId,
+        targets: impl Iterator<Item = &'t NodeId>,
+    ) -> Option<NodeId> {
+        let targets: Vec<NodeId> = targets.copied().collect();
+        let mut reached = vec![0; self.blocks.len()];
+        for target in targets.iter() {
+            let mut seen = HashSet::new();
+            let mut work = vec![*target];
+            while let Some(next) = work.pop() {
+                if next >= self.blocks.len()
+                    || self.follows.contains(&next)
+                    || self.jump_to(next).is_some()
+                    || !seen.insert(next)
+                {
+                    continue;
+                }
+                reached[next] += 1;
+                work.extend(self.successors[next].iter().copied());
+            }
+        }
+        let merge = (node + 1..self.blocks.len())
+            .find(|candidate| reached[*candidate] > 1 && !targets.contains(candidate))?;
+        let innermost = self
+            .contexts
+            .iter()
+            .rev()
+            .find_map(|context| match context {
+                Context::Loop { index, .. } => Some(&self.loops[*index]),
+                Context::Switch { .. } => None,
+            });
+        match innermost {
+            Some(info) if !info.contains(merge) => None,
+            _ => Some(merge),
+        }
+    }
+
+    /// Adds a try statement for a span, returning where it continues
+    fn open_try(
+        &mut self,
+        index: usize,
+        out: &mut Vec<Stmt>,
+        at_header: bool,
+        depth: usize,
+    ) -> Result<Option<NodeId>, io::Error> {
+        self.opened[index] = true;
+        let span = self.spans[index].clone();
+        let mut body = Vec::new();
+        self.emit(span.start, span.follow, &mut body, at_header, depth + 1)?;
+        let mut catches = Vec::new();
+        for (handler, class) in span.catches {
+            let name = self.catch_name(handler);
+            let mut body = Vec::new();
+            self.emit(handler, span.follow, &mut body, false, depth + 1)?;
+            catches.push(Catch { class, name, body });
+        }
+        // nothing follows a try that always returns or throws, unless something jumps there
+        let completes =
+            !ends_with_jump(&body) || catches.iter().any(|catch| !ends_with_jump(&catch.body));
+        let mut statement = Stmt::Try { body, catches };
+        let follow = span.follow.filter(|follow| {
+            let pc = self.starts[*follow];
+            let mut jumped = false;
+            for_each_stmt(std::slice::from_mut(&mut statement), &mut |stmt| {
+                jumped |= *stmt == Stmt::Goto(pc);
+            });
+            completes || jumped
+        });
+        out.push(statement);
+        Ok(follow)
+    }
+
+    /// The local a handler stores its exception in, or `e` for the exception itself
+    fn catch_name(&mut self, handler: NodeId) -> String {
+        let first = self.blocks[handler]
+            .as_ref()
+            .and_then(|block| block.stmts.first());
+        match first {
+            Some(Stmt::Assign {
+                target: Expr::Local { name, .. },
+                value: Expr::Caught,
+                ..
+            }) if !self.emitted[handler] => {
+                self.catch_stores.insert(handler);
+                name.clone()
+            }
+            _ => Expr::Caught.to_source(),
+        }
+    }
+}
+
+fn if_else(condition: Expr, then: Vec<Stmt>, otherwise: Vec<Stmt>) -> Stmt {
+    Stmt::If {
+        condition,
+        then,
+        otherwise,
+    }
+}
+
+/// Adds an if statement, leaving out an empty `then` by negating the condition and moving
+/// the `else` after it when `then` always jumps
+fn push_if(out: &mut Vec<Stmt>, condition: Expr, then: Vec<Stmt>, otherwise: Vec<Stmt>) {
+    let visible = |stmts: &[Stmt]| stmts.iter().any(|stmt| !matches!(stmt, Stmt::Label(_)));
+    if !visible(&then) && visible(&otherwise) {
+        out.extend(then);
+        return push_if(out, condition.negate(), otherwise, Vec::new());
+    }
+    if ends_with_jump(&then) {
+        out.push(if_else(condition, then, Vec::new()));
+        out.extend(otherwise);
+    } else {
+        out.push(if_else(condition, then, otherwise));
+    }
+}
+
+/// Calls `f` on every statement, outermost first
+fn for_each_stmt(stmts: &mut [Stmt], f: &mut impl FnMut(&mut Stmt)) {
+    for stmt in stmts.iter_mut() {
+        f(stmt);
+        for block in stmt.blocks_mut() {
+            for_each_stmt(block, f);
+        }
+    }
+}
+
+/// Calls `f` on every expression a statement evaluates, including those nested in it
+fn for_each_expr(stmts: &mut [Stmt], f: &mut impl FnMut(&mut Expr)) {
+    for_each_stmt(stmts, &mut |stmt| {
+        if let Stmt::Loop {
+            kind: LoopKind::DoWhile(condition),
+            ..
+        } = stmt
+        {
+            f(condition);
+        }
+        for expr in stmt.expressions_mut() {
+            f(expr);
+        }
+    });
+}
+
+/// How many times `expr` appears anywhere in the statements
+fn count_in(stmts: &mut [Stmt], expr: &Expr) -> usize {
+    let mut count = 0;
+    for_each_expr(stmts, &mut |e| count += e.count(expr));
+    count
+}
+
+fn strip_labels(stmts: &mut Vec<Stmt>, targets: &HashSet<u32>) {
+    stmts.retain(|stmt| !matches!(stmt, Stmt::Label(pc) if !targets.contains(pc)));
+    for stmt in stmts.iter_mut() {
+        for block in stmt.blocks_mut() {
+            strip_labels(block, targets);
+        }
+    }
+}
+
+/// Turns `while (true)` loops into `while` and `do`/`while` loops when they start by leaving
+/// or end by going around
+fn simplify_loops(stmts: &mut [Stmt]) {
+    for stmt in stmts.iter_mut() {
+        for block in stmt.blocks_mut() {
+            simplify_loops(block);
+        }
+        if let Stmt::Loop {
+            id,
+            kind: kind @ LoopKind::Infinite,
+            body,
+        } = stmt
+        {
+            if let Some(simplified) = simplify_loop(*id, body) {
+                *kind = simplified;
+            }
+        }
+    }
+}
+
+fn simplify_loop(id: usize, body: &mut Vec<Stmt>) -> Option<LoopKind> {
+    if body.last() == Some(&Stmt::Continue(None)) {
+        body.pop();
+    }
+    let n = body.len();
+    // `do { ... } while (c)` ends with `if (c) continue; break;`
+    if n >= 2 && body[n - 1] == Stmt::Break(None) && !continues(&mut body[..n - 2], id, true) {
+        if let Stmt::If {
+            then, otherwise, ..
+        } = &body[n - 2]
+        {
+            if then.as_slice() == [Stmt::Continue(None)] && otherwise.is_empty() {
+                body.pop();
+                let Some(Stmt::If { condition, .. }) = body.pop() else {
+                    unreachable!()
+                };
+                return Some(LoopKind::DoWhile(condition));
+            }
+        }
+    }
+    // `if (c) { ...; continue; } break;` leaves when c doesn't hold
+    if n >= 2 && body[n - 1] == Stmt::Break(None) {
+        if let Stmt::If {
+            then, otherwise, ..
+        } = &body[n - 2]
+        {
+            if otherwise.is_empty() && ends_with_jump(then) {
+                body.pop();
+                let Some(Stmt::If {
+                    condition,
+                    mut then,
+                    ..
+                }) = body.pop()
+                else {
+                    unreachable!()
+                };
+                if then.last() == Some(&Stmt::Continue(None)) {
+                    then.pop();
+                }
+                body.push(if_else(
+                    condition.negate(),
+                    vec![Stmt::Break(None)],
+                    Vec::new(),
+                ));
+                body.extend(then);
+            }
+        }
+    }
+    if let Some(Stmt::If {
+        then, otherwise, ..
+    }) = body.first()
+    {
+        if then.as_slice() == [Stmt::Break(None)] && otherwise.is_empty() {
+            let Stmt::If { condition, .. } = body.remove(0) else {
+                unreachable!()
+            };
+            if body.last() == Some(&Stmt::Continue(None)) {
+                body.pop();
+            }
+            return Some(LoopKind::While(condition.negate()));
+        }
+    }
+    None
+}
+
+/// Whether any of the statements continues the loop `id`, which they're directly inside when
+/// `direct` is set
+fn continues(stmts: &mut [Stmt], id: usize, direct: bool) -> bool {
+    stmts.iter_mut().any(|stmt| match stmt {
+        Stmt::Continue(None) => direct,
+        Stmt::Continue(Some(target)) => *target == id,
+        Stmt::Loop { body, .. } => continues(body, id, false),
+        stmt => stmt
+            .blocks_mut()
+            .into_iter()
+            .any(|block| continues(block, id, direct)),
+    })
+}
+
+/// Folds an if whose branches each only set the same stack value into `?:`
+fn fold_ternaries(stmts: &mut Vec<Stmt>) {
+    for stmt in stmts.iter_mut() {
+        for block in stmt.blocks_mut() {
+            fold_ternaries(block);
+        }
+        let Stmt::If {
+            condition,
+            then,
+            otherwise,
+        } = stmt
+        else {
+            continue;
+        };
+        if let (
+            [Stmt::Assign {
+                target: target @ Expr::StackVar(_),
+                value: first,
+                declare: None,
+            }],
+            [Stmt::Assign {
+                target: other,
+                value: second,
+                declare: None,
+            }],
+        ) = (then.as_mut_slice(), otherwise.as_mut_slice())
+        {
+            if target == other {
+                *stmt = Stmt::Assign {
+                    target: target.clone(),
+                    value: Expr::Ternary {
+                        condition: Box::new(condition.clone()),
+                        then: Box::new(std::mem::replace(first, Expr::Null)),
+                        otherwise: Box::new(std::mem::replace(second, Expr::Null)),
+                    },
+                    declare: None,
+                };
+            }
+        }
+    }
+    // `stack = a; if (c) stack = b;`, as from `x != null ? x : y`
+    let mut i = 0;
+    while i + 1 < stmts.len() {
+        if let (
+            Stmt::Assign {
+                target: target @ Expr::StackVar(_),
+                value: first,
+                declare: None,
+            },
+            Stmt::If {
+                condition,
+                then,
+                otherwise,
+            },
+        ) = (&stmts[i], &stmts[i + 1])
+        {
+            if let [Stmt::Assign {
+                target: other,
+                value: second,
+                declare: None,
+            }] = then.as_slice()
+            {
+                if other == target
+                    && otherwise.is_empty()
+                    && first.is_stable(None)
+                    && condition.count(target) == 0
+                {
+                    stmts[i] = Stmt::Assign {
+                        target: target.clone(),
+                        value: Expr::Ternary {
+                            condition: Box::new(condition.clone()),
+                            then: Box::new(second.clone()),
+                            otherwise: Box::new(first.clone()),
+                        },
+                        declare: None,
+                    };
+                    stmts.remove(i + 1);
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Replaces a stack value or temporary with what was assigned to it when the next statement
+/// is the only one that reads it, and `x++` used as a value with the increment. Stack values
+/// that pass between blocks are left alone if they're read in `outside`, the code that can
+/// run after these statements.
+fn inline_stack_values(
+    stmts: &mut Vec<Stmt>,
+    passthrough: &HashSet<usize>,
+    outside: &HashSet<usize>,
+) {
+    let mut later = outside.clone();
+    for stmt in stmts.iter_mut().rev() {
+        let mut after = later.clone();
+        if let Stmt::Loop { .. } = stmt {
+            stack_reads(std::slice::from_mut(stmt), &mut after);
+        }
+        for block in stmt.blocks_mut() {
+            inline_stack_values(block, passthrough, &after);
+        }
+        stack_reads(std::slice::from_mut(stmt), &mut later);
+    }
+    let passthrough: HashSet<usize> = passthrough.intersection(outside).copied().collect();
+    let passthrough = &passthrough;
+    let mut i = stmts.len().saturating_sub(1);
+    while i > 0 {
+        i -= 1;
+        if reuse_stored_local(stmts, i) {
+            continue;
+        }
+        if let Some((target, value, stores)) = array_initializer(&stmts[i..], passthrough) {
+            if inline(stmts, i + stores + 1, &target, value) {
+                stmts.drain(i..i + stores + 1);
+            }
+            continue;
+        }
+        let (target, value) = match &stmts[i] {
+            Stmt::Assign {
+                target: target @ Expr::StackVar(position),
+                value,
+                declare: None,
+            } if !passthrough.contains(position) => (target.clone(), value.clone()),
+            Stmt::Assign {
+                target: target @ Expr::Temp(_),
+                value,
+                declare: None,
+            } if evaluates_first(&stmts[i + 1], target) => (target.clone(), value.clone()),
+            Stmt::Assign {
+                target: target @ Expr::Temp(_),
+                value: variable,
+                declare: None,
+            } if i + 2 < stmts.len() && is_variable(variable) => {
+                // `temp = i; i++; ... temp ...` reads `i++`
+                let op = match step(&stmts[i + 1]) {
+                    Some((stepped, 1)) if stepped == variable => "++",
+                    Some((stepped, -1)) if stepped == variable => "--",
+                    _ => continue,
+                };
+                let value = Expr::Text(format!("{}{op}", variable.to_source()));
+                let target = target.clone();
+                if inline(stmts, i + 2, &target, value) {
+                    stmts.drain(i..i + 2);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if inline(stmts, i + 1, &target, value) {
+            stmts.remove(i);
+        }
+    }
+}
+
+/// A local or a field of `this` or of a class
+fn is_variable(expr: &Expr) -> bool {
+    match expr {
+        Expr::Local { .. } => true,
+        Expr::Field { object, .. } => object.as_deref().is_none_or(|o| *o == Expr::This),
+        _ => false,
+    }
+}
+
+/// Whether `expr` is the first thing a statement evaluates, so that nothing runs between
+/// a value saved to it and its use
+fn evaluates_first(stmt: &Stmt, expr: &Expr) -> bool {
+    fn leads(value: &Expr, expr: &Expr) -> bool {
+        match value {
+            _ if value == expr => true,
+            Expr::Field {
+                object: Some(value),
+                ..
+            }
+            | Expr::ArrayElement { array: value, .. }
+            | Expr::ArrayLength(value)
+            | Expr::Unary { operand: value, .. }
+            | Expr::Cast { value, .. }
+            | Expr::InstanceOf { value, .. }
+            | Expr::Binary { left: value, .. }
+            | Expr::Compare { left: value, .. }
+            | Expr::Ternary {
+                condition: value, ..
+            }
+            | Expr::Call {
+                target: Target::Instance(value),
+                ..
+            } => leads(value, expr),
+            Expr::Call { args, .. } | Expr::New { args, .. } => {
+                args.first().is_some_and(|arg| leads(arg, expr))
+            }
+            _ => false,
+        }
+    }
+    match stmt {
+        Stmt::Expr(value)
+        | Stmt::Throw(value)
+        | Stmt::Return(Some(value))
+        | Stmt::If {
+            condition: value, ..
+        }
+        | Stmt::Switch { key: value, .. }
+        | Stmt::Assign {
+            target: Expr::Field { object: None, .. } | Expr::Local { .. } | Expr::StackVar(_),
+            value,
+            ..
+        } => leads(value, expr),
+        Stmt::Assign {
+            target:
+                Expr::Field {
+                    object: Some(value),
+                    ..
+                }
+                | Expr::ArrayElement { array: value, .. },
+            ..
+        } => leads(value, expr),
+        _ => false,
+    }
+}
+
+/// The variable a statement adds a constant to, and the constant
+fn step(stmt: &Stmt) -> Option<(&Expr, i32)> {
+    match stmt {
+        Stmt::Increment { target, amount } => Some((target, *amount)),
+        Stmt::Assign {
+            target,
+            value: Expr::Binary { op, left, right },
+            declare: None,
+        } if **left == *target => match (*op, &**right) {
+            ("+", Expr::Int(amount)) => Some((target, *amount)),
+            ("-", Expr::Int(amount)) => Some((target, -amount)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Turns `temp = v; x = temp; use(temp);`, where `x` is a local and any other variables
+/// may be given `temp` too, into `x = v; use(x);`
+fn reuse_stored_local(stmts: &mut Vec<Stmt>, i: usize) -> bool {
+    let Stmt::Assign {
+        target: temp @ Expr::Temp(_),
+        declare: None,
+        ..
+    } = &stmts[i]
+    else {
+        return false;
+    };
+    let temp = temp.clone();
+    let mut local = None;
+    let mut rest = i + 1;
+    while let Some(Stmt::Assign {
+        target,
+        value,
+        declare: None,
+    }) = stmts.get(rest)
+    {
+        if *value != temp || !is_variable(target) {
+            break;
+        }
+        if let Expr::Local { .. } = target {
+            local = Some(target.clone());
+        }
+        rest += 1;
+    }
+    let Some(local) = local else {
+        return false;
+    };
+    let uses = count_in(&mut stmts[rest..], &temp);
+    if uses > 0 {
+        let head: usize = stmts[rest]
+            .expressions_mut()
+            .into_iter()
+            .map(|expr| expr.count(&temp))
+            .sum();
+        if head != uses {
+            return false;
+        }
+    }
+    let mut replace = |expr: &mut Expr| {
+        expr.replace(&mut |e| (*e == temp).then(|| local.clone()));
+    };
+    let last = rest.min(stmts.len() - 1);
+    for stmt in stmts[i + 1..=last].iter_mut() {
+        stmt.expressions_mut().into_iter().for_each(&mut replace);
+    }
+    if let Stmt::Assign { target, .. } = &mut stmts[i] {
+        *target = local.clone();
+    }
+    // the stores of the local itself are now `x = x`
+    let mut n = i + 1;
+    let mut end = rest;
+    while n < end {
+        match &stmts[n] {
+            Stmt::Assign { target, .. } if *target == local => {
+                stmts.remove(n);
+                end -= 1;
+            }
+            _ => n += 1,
+        }
+    }
+    true
+}
+
+/// A new array saved to a stack value or temp and then given each of its elements in order,
+/// as the saved value, the array with those elements and the number of stores
+fn array_initializer(stmts: &[Stmt], passthrough: &HashSet<usize>) -> Option<(Expr, Expr, usize)> {
+    let Some(Stmt::Assign {
+        target,
+        value:
+            Expr::NewArray {
+                element,
+                dimensions,
+                extra,
+            },
+        declare: None,
+    }) = stmts.first()
+    else {
+        return None;
+    };
+    match target {
+        Expr::StackVar(position) if !passthrough.contains(position) => {}
+        Expr::Temp(_) => {}
+        _ => return None,
+    }
+    let [Expr::Int(length)] = dimensions.as_slice() else {
+        return None;
+    };
+    let length = usize::try_from(*length).ok()?;
+    if length == 0 || length + 1 >= stmts.len() {
+        return None;
+    }
+    let mut values = Vec::new();
+    for (index, stmt) in stmts[1..=length].iter().enumerate() {
+        let Stmt::Assign {
+            target: Expr::ArrayElement { array, index: at },
+            value,
+            declare: None,
+        } = stmt
+        else {
+            return None;
+        };
+        if **array != *target || **at != Expr::Int(index as i32) || value.count(target) > 0 {
+            return None;
+        }
+        values.push(value.clone());
+    }
+    let initializer = Expr::ArrayInit {
+        element: element.clone(),
+        dimensions: extra + 1,
+        values,
+    };
+    Some((target.clone(), initializer, length))
+}
+
+/// Adds the positions of the stack values the statements use
+fn stack_reads(stmts: &mut [Stmt], reads: &mut HashSet<usize>) {
+    for_each_expr(stmts, &mut |expr| {
+        expr.visit(&mut |e| {
+            if let Expr::StackVar(position) = e {
+                reads.insert(*position);
+            }
+        })
+    });
+}
+
+/// Replaces the only read of `target` with `value` if that's in the head of statement `at`
+fn inline(stmts: &mut [Stmt], at: usize, target: &Expr, value: Expr) -> bool {
+    let head: usize = stmts[at]
+        .expressions_mut()
+        .into_iter()
+        .map(|expr| expr.count(target))
+        .sum();
+    if head != 1 || count_in(&mut stmts[at..], target) != 1 {
+        return false;
+    }
+    let mut value = Some(value);
+    for expr in stmts[at].expressions_mut() {
+        expr.replace(&mut |e| match e == target {
+            true => value.take(),
+            false => None,
+        });
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tools::decompile::ast::render;
+
+    #[test]
+    fn simplifies_loops() {
+        let condition = Expr::Text("c".to_string());
+        let call = Stmt::Expr(Expr::Text("f()".to_string()));
+        let mut body = vec![
+            if_else(
+                condition.clone(),
+                vec![call.clone(), Stmt::Continue(None)],
+                Vec::new(),
+            ),
+            Stmt::Break(None),
+        ];
+        assert_eq!(
+            simplify_loop(0, &mut body),
+            Some(LoopKind::While(condition.clone()))
+        );
+        assert_eq!(body, vec![call.clone()]);
+
+        let mut body = vec![
+            call.clone(),
+            if_else(condition.clone(), vec![Stmt::Continue(None)], Vec::new()),
+            Stmt::Break(None),
+        ];
+        assert_eq!(
+            simplify_loop(0, &mut body),
+            Some(LoopKind::DoWhile(condition.clone()))
+        );
+        assert_eq!(body, vec![call.clone()]);
+
+        // a continue in the body would skip the condition of a do/while
+        let mut body = vec![
+            if_else(condition.clone(), vec![Stmt::Continue(None)], Vec::new()),
+            call.clone(),
+            if_else(condition.clone(), vec![Stmt::Continue(None)], Vec::new()),
+            Stmt::Break(None),
+        ];
+        assert_eq!(simplify_loop(0, &mut body), None);
+    }
+
+    fn assign(target: Expr, value: Expr) -> Stmt {
+        Stmt::Assign {
+            target,
+            value,
+            declare: None,
+        }
+    }
+
+    #[test]
+    fn folds_array_initializers() {
+        let temp = Expr::Temp(0);
+        let element = |index| Expr::ArrayElement {
+            array: Box::new(temp.clone()),
+            index: Box::new(Expr::Int(index)),
+        };
+        let mut body = vec![
+            assign(
+                temp.clone(),
+                Expr::NewArray {
+                    element: "int".to_string(),
+                    dimensions: vec![Expr::Int(2)],
+                    extra: 0,
+                },
+            ),
+            assign(element(0), Expr::Int(7)),
+            assign(element(1), Expr::Int(8)),
+            Stmt::Return(Some(temp.clone())),
+        ];
+        inline_stack_values(&mut body, &HashSet::new(), &HashSet::new());
+        assert_eq!(body.len(), 1);
+        let mut out = String::new();
+        render(&body, 0, &HashSet::new(), &mut out);
+        assert_eq!(out, "return new int[]{7, 8};\n");
+
+        // an element missing from the stores is left as it is
+        let mut body = vec![
+            assign(
+                temp.clone(),
+                Expr::NewArray {
+                    element: "int".to_string(),
+                    dimensions: vec![Expr::Int(2)],
+                    extra: 0,
+                },
+            ),
+            assign(element(1), Expr::Int(8)),
+            Stmt::Return(Some(temp.clone())),
+        ];
+        inline_stack_values(&mut body, &HashSet::new(), &HashSet::new());
+        assert_eq!(body.len(), 3);
+    }
+
+    #[test]
+    fn reuses_stored_locals() {
+        let temp = Expr::Temp(0);
+        let local = Expr::Local {
+            slot: 1,
+            name: "line".to_string(),
+            descriptor: "Ljava/lang/String;".to_string(),
+            type_name: "String".to_string(),
+        };
+        let field = Expr::Field {
+            object: Some(Box::new(Expr::This)),
+            owner: "Reader".to_string(),
+            name: "last".to_string(),
+            descriptor: "Ljava/lang/String;".to_string(),
+        };
+        let read = Expr::Text("read()".to_string());
+        let mut body = vec![
+            assign(temp.clone(), read.clone()),
+            assign(field.clone(), temp.clone()),
+            assign(local.clone(), temp.clone()),
+            if_else(
+                Expr::binary("==", temp.clone(), Expr::Null),
+                vec![Stmt::Return(None)],
+                Vec::new(),
+            ),
+        ];
+        inline_stack_values(&mut body, &HashSet::new(), &HashSet::new());
+        let mut out = String::new();
+        render(&body, 0, &HashSet::new(), &mut out);
+        assert_eq!(
+            out,
+            "line = read();\nthis.last = line;\nif (line == null) {\n    return;\n}\n"
+        );
+    }
+}
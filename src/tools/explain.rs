@@ -0,0 +1,658 @@
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::{spec_names, FlagTarget};
+use crate::class_file::attribute::stack_map_table::{StackMapFrame, VerificationType};
+use crate::class_file::class_file::MAGIC_NUMBER;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::limits::ParseLimits;
+use std::fmt::Write;
+use std::io;
+use std::io::BufReader;
+
+// An annotated hex dump of a class file: every byte alongside what it means, for debugging
+// malformed input and for learning the format. Unlike `ClassFile` this walks the raw bytes so
+// it knows where each structure starts, and it parses attributes eagerly so that a broken one
+// is pinpointed rather than left for whoever reads it later.
+
+/// Bytes shown per line; longer structures continue on the following lines
+const BYTES_PER_LINE: usize = 8;
+
+/// Writes the annotated dump of `bytes` to `out`, e.g.
+/// `0008: 00 1D                    constant_pool_count = 29`. If the class file is malformed,
+/// everything before the problem is still written and the error names the offset it stopped at.
+pub fn explain(bytes: &[u8], out: &mut String) -> Result<(), io::Error> {
+    let mut explainer = Explainer {
+        bytes,
+        pos: 0,
+        end: bytes.len(),
+        indent: 0,
+        constant_pool: ConstantPool::new(),
+        out,
+    };
+    explainer.class()
+}
+
+struct Explainer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// The end of the structure being explained; reads past it are errors
+    end: usize,
+    indent: usize,
+    constant_pool: ConstantPool,
+    out: &'a mut String,
+}
+
+impl<'a> Explainer<'a> {
+    fn class(&mut self) -> Result<(), io::Error> {
+        let magic = self.u4()?;
+        self.line(0, "magic");
+        if magic != MAGIC_NUMBER {
+            return Err(self.error(0, "Invalid magic number"));
+        }
+        self.u2_line("minor_version")?;
+        self.u2_line("major_version")?;
+        self.constant_pool()?;
+
+        let start = self.pos;
+        let access_flags = self.u2()?;
+        self.line(
+            start,
+            &format!("access_flags = {}", flags(FlagTarget::Class, access_flags)),
+        );
+        self.class_line("this_class")?;
+        self.class_line("super_class")?;
+        let interfaces = self.u2_line("interfaces_count")?;
+        for _ in 0..interfaces {
+            self.class_line("interface")?;
+        }
+
+        for (kind, target) in [("field", FlagTarget::Field), ("method", FlagTarget::Method)] {
+            let count = self.u2_line(&format!("{kind}s_count"))?;
+            for i in 0..count {
+                self.heading(&format!("{kind} {i}"));
+                self.indent += 1;
+                self.member(target)?;
+                self.indent -= 1;
+            }
+        }
+        self.attributes(1)?;
+
+        if self.pos != self.bytes.len() {
+            return Err(self.error(
+                self.pos,
+                "Unexpected trailing bytes after the end of the class file",
+            ));
+        }
+        Ok(())
+    }
+
+    fn constant_pool(&mut self) -> Result<(), io::Error> {
+        let count = self.u2_line("constant_pool_count")?;
+        self.constant_pool.set_count(count);
+        while self.constant_pool.items().len() + 1 < count as usize {
+            let index = self.constant_pool.items().len() + 1;
+            let start = self.pos;
+            let mut constant_pool = std::mem::replace(&mut self.constant_pool, ConstantPool::new());
+            let parsed = self.parse(|reader| constant_pool.parse_item_from_class_file(reader));
+            self.constant_pool = constant_pool;
+            parsed?;
+            let text = describe_entry(&self.constant_pool, index as u16);
+            self.line(start, &format!("#{index} {text}"));
+        }
+        if self.constant_pool.items().len() + 1 > count.max(1) as usize {
+            return Err(self.error(
+                self.pos,
+                "Last constant pool entry runs past constant_pool_count",
+            ));
+        }
+        Ok(())
+    }
+
+    fn member(&mut self, target: FlagTarget) -> Result<(), io::Error> {
+        let start = self.pos;
+        let access_flags = self.u2()?;
+        self.line(
+            start,
+            &format!("access_flags = {}", flags(target, access_flags)),
+        );
+        self.utf8_line("name_index")?;
+        self.utf8_line("descriptor_index")?;
+        self.attributes(1)
+    }
+
+    /// A `u2` count of attributes (section 4.7). `depth` counts the attribute tables this one is
+    /// nested in, which only a Code attribute adds to.
+    fn attributes(&mut self, depth: usize) -> Result<(), io::Error> {
+        let count = self.u2_line("attributes_count")?;
+        if depth > ParseLimits::DEFAULT_MAX_NESTING {
+            return Err(self.error(self.pos, "Attributes are nested too deeply"));
+        }
+        for _ in 0..count {
+            let name = self.utf8_line("attribute_name_index")?;
+            let length = self.u4_line("attribute_length")? as usize;
+            let start = self.pos;
+            if length > self.end - start {
+                return Err(self.error(
+                    start,
+                    &format!(
+                        "attribute_length {length} is longer than the {} bytes left",
+                        self.end - start
+                    ),
+                ));
+            }
+            // the attribute's own fields may not read past its length
+            let outer_end = std::mem::replace(&mut self.end, start + length);
+            self.indent += 1;
+            self.attribute(name.as_deref().unwrap_or_default(), depth)?;
+            self.indent -= 1;
+            if self.pos != self.end {
+                return Err(self.error(
+                    self.pos,
+                    &format!(
+                        "{} bytes left over at the end of the attribute",
+                        self.end - self.pos
+                    ),
+                ));
+            }
+            self.end = outer_end;
+        }
+        Ok(())
+    }
+
+    fn attribute(&mut self, name: &str, depth: usize) -> Result<(), io::Error> {
+        match name {
+            "Code" => self.code(depth)?,
+            "ConstantValue" => {
+                let start = self.pos;
+                let index = self.u2()?;
+                let value = describe_entry(&self.constant_pool, index);
+                self.line(start, &format!("constantvalue_index = #{index} ({value})"));
+            }
+            "SourceFile" => {
+                self.utf8_line("sourcefile_index")?;
+            }
+            "Signature" => {
+                self.utf8_line("signature_index")?;
+            }
+            "Exceptions" => {
+                for _ in 0..self.u2_line("number_of_exceptions")? {
+                    self.class_line("exception")?;
+                }
+            }
+            "LineNumberTable" => {
+                for _ in 0..self.u2_line("line_number_table_length")? {
+                    let start = self.pos;
+                    let (start_pc, line_number) = (self.u2()?, self.u2()?);
+                    self.line(start, &format!("start_pc={start_pc} line={line_number}"));
+                }
+            }
+            "LocalVariableTable" | "LocalVariableTypeTable" => {
+                let length_name = match name {
+                    "LocalVariableTable" => "local_variable_table_length",
+                    _ => "local_variable_type_table_length",
+                };
+                for _ in 0..self.u2_line(length_name)? {
+                    let start = self.pos;
+                    let (start_pc, length) = (self.u2()?, self.u2()?);
+                    let (name_index, descriptor_index) = (self.u2()?, self.u2()?);
+                    let index = self.u2()?;
+                    self.line(
+                        start,
+                        &format!(
+                            "start_pc={start_pc} length={length} name={} type={} slot={index}",
+                            self.utf8_reference(name_index),
+                            self.utf8_reference(descriptor_index)
+                        ),
+                    );
+                }
+            }
+            "InnerClasses" => {
+                for _ in 0..self.u2_line("number_of_classes")? {
+                    let start = self.pos;
+                    let (inner, outer) = (self.u2()?, self.u2()?);
+                    let (name, access_flags) = (self.u2()?, self.u2()?);
+                    self.line(
+                        start,
+                        &format!(
+                            "inner={} outer={} name={} flags={}",
+                            self.class_reference(inner),
+                            self.class_reference(outer),
+                            self.utf8_reference(name),
+                            flags(FlagTarget::InnerClass, access_flags)
+                        ),
+                    );
+                }
+            }
+            "BootstrapMethods" => {
+                for i in 0..self.u2_line("num_bootstrap_methods")? {
+                    let start = self.pos;
+                    let method_ref = self.u2()?;
+                    let mut arguments = Vec::new();
+                    for _ in 0..self.u2()? {
+                        arguments.push(format!("#{}", self.u2()?));
+                    }
+                    self.line(
+                        start,
+                        &format!(
+                            "bootstrap {i} method=#{method_ref} arguments=[{}]",
+                            arguments.join(", ")
+                        ),
+                    );
+                }
+            }
+            "StackMapTable" => {
+                for _ in 0..self.u2_line("number_of_entries")? {
+                    let start = self.pos;
+                    let frame = self.parse(StackMapFrame::from)?;
+                    self.line(start, &describe_frame(&frame));
+                }
+            }
+            _ => {
+                let start = self.pos;
+                self.take(self.end - self.pos)?;
+                if self.pos > start {
+                    self.line(start, "info");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The Code attribute (section 4.7.3) with its instructions decoded one per line
+    fn code(&mut self, depth: usize) -> Result<(), io::Error> {
+        self.u2_line("max_stack")?;
+        self.u2_line("max_locals")?;
+        let code_length = self.u4_line("code_length")? as usize;
+        let code_start = self.pos;
+        let code = self.take(code_length)?;
+        self.pos = code_start;
+        let mut pc = 0;
+        while pc < code.len() {
+            let (instruction, length) = Instruction::decode(code, pc)
+                .map_err(|error| self.error(code_start + pc, &error.to_string()))?;
+            self.take(length)?;
+            self.line(
+                code_start + pc,
+                &format!("{pc}: {}", instruction_text(&instruction)),
+            );
+            pc += length;
+        }
+
+        for _ in 0..self.u2_line("exception_table_length")? {
+            let start = self.pos;
+            let (start_pc, end_pc) = (self.u2()?, self.u2()?);
+            let (handler_pc, catch_type) = (self.u2()?, self.u2()?);
+            let catch_type = match catch_type {
+                0 => "any".to_string(),
+                index => self.class_reference(index),
+            };
+            self.line(
+                start,
+                &format!("start_pc={start_pc} end_pc={end_pc} handler_pc={handler_pc} catch_type={catch_type}"),
+            );
+        }
+        self.attributes(depth + 1)
+    }
+
+    /// Runs one of the class file parsers over the bytes at the current position and moves past
+    /// what it used, so structures are explained exactly as `ClassFile` reads them
+    fn parse<T>(
+        &mut self,
+        parse: impl FnOnce(&mut BufReader<&'a [u8]>) -> Result<T, io::Error>,
+    ) -> Result<T, io::Error> {
+        let rest = &self.bytes[self.pos..self.end];
+        let mut reader = BufReader::new(rest);
+        let value = parse(&mut reader).map_err(|error| self.error(self.pos, &error.to_string()))?;
+        self.pos += rest.len() - reader.buffer().len() - reader.get_ref().len();
+        Ok(value)
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], io::Error> {
+        if length > self.end - self.pos {
+            return Err(self.error(
+                self.pos,
+                &format!(
+                    "Expected {length} more bytes but only {} are left",
+                    self.end - self.pos
+                ),
+            ));
+        }
+        self.pos += length;
+        Ok(&self.bytes[self.pos - length..self.pos])
+    }
+
+    fn u2(&mut self) -> Result<u16, io::Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u4(&mut self) -> Result<u32, io::Error> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u2_line(&mut self, name: &str) -> Result<u16, io::Error> {
+        let start = self.pos;
+        let value = self.u2()?;
+        self.line(start, &format!("{name} = {value}"));
+        Ok(value)
+    }
+
+    fn u4_line(&mut self, name: &str) -> Result<u32, io::Error> {
+        let start = self.pos;
+        let value = self.u4()?;
+        self.line(start, &format!("{name} = {value}"));
+        Ok(value)
+    }
+
+    /// A `u2` index of a CONSTANT_Utf8, returning the string if it resolves
+    fn utf8_line(&mut self, name: &str) -> Result<Option<String>, io::Error> {
+        let start = self.pos;
+        let index = self.u2()?;
+        self.line(start, &format!("{name} = {}", self.utf8_reference(index)));
+        Ok(self.constant_pool.utf8(index).ok())
+    }
+
+    /// A `u2` index of a CONSTANT_Class, where 0 means none (e.g. the super_class of
+    /// `java/lang/Object`)
+    fn class_line(&mut self, name: &str) -> Result<(), io::Error> {
+        let start = self.pos;
+        let index = self.u2()?;
+        let text = match index {
+            0 => "#0 (none)".to_string(),
+            index => self.class_reference(index),
+        };
+        self.line(start, &format!("{name} = {text}"));
+        Ok(())
+    }
+
+    fn utf8_reference(&self, index: u16) -> String {
+        match self.constant_pool.utf8(index) {
+            Ok(text) => format!("#{index} ({})", escape(&text)),
+            Err(_) => format!("#{index} (invalid)"),
+        }
+    }
+
+    fn class_reference(&self, index: u16) -> String {
+        match self.constant_pool.class_name(index) {
+            Ok(name) => format!("#{index} ({name})"),
+            Err(_) => format!("#{index} (invalid)"),
+        }
+    }
+
+    /// A line without bytes that introduces the lines under it
+    fn heading(&mut self, text: &str) {
+        let blank = "";
+        let width = BYTES_PER_LINE * 3 - 1;
+        let indent = "  ".repeat(self.indent);
+        let _ = writeln!(self.out, "{:04X}: {blank:width$}  {indent}{text}", self.pos);
+    }
+
+    /// Writes the bytes from `start` up to the current position next to `text`
+    fn line(&mut self, start: usize, text: &str) {
+        let width = BYTES_PER_LINE * 3 - 1;
+        let indent = "  ".repeat(self.indent);
+        for (i, chunk) in self.bytes[start..self.pos]
+            .chunks(BYTES_PER_LINE)
+            .enumerate()
+        {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02X}")).collect();
+            let offset = start + i * BYTES_PER_LINE;
+            let hex = hex.join(" ");
+            let _ = match i {
+                0 => writeln!(self.out, "{offset:04X}: {hex:width$}  {indent}{text}"),
+                _ => writeln!(self.out, "{offset:04X}: {hex}"),
+            };
+        }
+    }
+
+    fn error(&self, offset: usize, message: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("at offset 0x{offset:04X}: {message}"),
+        )
+    }
+}
+
+/// e.g. `0x0021 (ACC_PUBLIC, ACC_SUPER)`
+fn flags(target: FlagTarget, flags: u16) -> String {
+    format!("0x{flags:04X} ({})", spec_names(target, flags).join(", "))
+}
+
+/// A constant pool entry with its raw operands, e.g. `Methodref class=#6 nat=#15`. Entries can
+/// refer forwards, so references are left as indices rather than resolved.
+fn describe_entry(constant_pool: &ConstantPool, index: u16) -> String {
+    let Some(item) = constant_pool.get(index) else {
+        return "invalid".to_string();
+    };
+    match item.data() {
+        ConstantPoolData::Uninit | ConstantPoolData::Unusable => "invalid".to_string(),
+        ConstantPoolData::Utf8(utf8) => format!("Utf8 \"{}\"", escape(&utf8.value())),
+        ConstantPoolData::Integer(integer) => format!("Integer {}", integer.value()),
+        ConstantPoolData::Float(float) => format!("Float {:?}", float.value()),
+        ConstantPoolData::Long(long) => format!("Long {}", long.value()),
+        ConstantPoolData::Double(double) => format!("Double {:?}", double.value()),
+        ConstantPoolData::ClassInfo(class) => format!("Class name=#{}", class.name_index()),
+        ConstantPoolData::String(string) => format!("String string=#{}", string.string_index()),
+        ConstantPoolData::FieldRef(r) => format!(
+            "Fieldref class=#{} nat=#{}",
+            r.class_index(),
+            r.name_and_type_index()
+        ),
+        ConstantPoolData::MethodRef(r) => format!(
+            "Methodref class=#{} nat=#{}",
+            r.class_index(),
+            r.name_and_type_index()
+        ),
+        ConstantPoolData::InterfaceMethodRef(r) => format!(
+            "InterfaceMethodref class=#{} nat=#{}",
+            r.class_index(),
+            r.name_and_type_index()
+        ),
+        ConstantPoolData::NameAndType(nat) => format!(
+            "NameAndType name=#{} descriptor=#{}",
+            nat.name_index(),
+            nat.descriptor_index()
+        ),
+        ConstantPoolData::MethodHandle(handle) => format!(
+            "MethodHandle kind={} reference=#{}",
+            handle.reference_kind() as i8,
+            handle.reference_index()
+        ),
+        ConstantPoolData::MethodType(method_type) => {
+            format!("MethodType descriptor=#{}", method_type.descriptor_index())
+        }
+        ConstantPoolData::InvokeDynamic(indy) => format!(
+            "InvokeDynamic bootstrap={} nat=#{}",
+            indy.bootstrap_method_attr_index(),
+            indy.name_and_type_index()
+        ),
+    }
+}
+
+/// e.g. `getstatic #2`, `iinc 1, 1` or `goto 12`, with branch targets as absolute pcs
+fn instruction_text(instruction: &Instruction) -> String {
+    let mnemonic = instruction.mnemonic();
+    match instruction {
+        Instruction::Bipush(value) => format!("{mnemonic} {value}"),
+        Instruction::Sipush(value) => format!("{mnemonic} {value}"),
+        Instruction::Iinc { index, increment } => format!("{mnemonic} {index}, {increment}"),
+        Instruction::Invokeinterface { index, count } => format!("{mnemonic} #{index}, {count}"),
+        Instruction::Multianewarray { index, dimensions } => {
+            format!("{mnemonic} #{index}, {dimensions}")
+        }
+        Instruction::Newarray(array_type) => format!("{mnemonic} {}", array_type.name()),
+        Instruction::Tableswitch {
+            default,
+            low,
+            high,
+            targets,
+        } => {
+            let cases: Vec<String> = (*low..=*high)
+                .zip(targets)
+                .map(|(key, target)| format!("{key}: {target}"))
+                .collect();
+            format!("{mnemonic} {{ {}, default: {default} }}", cases.join(", "))
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            let cases: Vec<String> = pairs
+                .iter()
+                .map(|(key, target)| format!("{key}: {target}"))
+                .collect();
+            format!("{mnemonic} {{ {}, default: {default} }}", cases.join(", "))
+        }
+        _ => {
+            if let Some(index) = instruction.constant_index() {
+                format!("{mnemonic} #{index}")
+            } else if let Some(index) = instruction.local_index() {
+                format!("{mnemonic} {index}")
+            } else if let [target] = instruction.branch_targets()[..] {
+                format!("{mnemonic} {target}")
+            } else {
+                mnemonic.to_string()
+            }
+        }
+    }
+}
+
+/// e.g. `append offset_delta=7 locals=[int, #5]` (section 4.7.4)
+fn describe_frame(frame: &StackMapFrame) -> String {
+    let types = |types: &[VerificationType]| -> String {
+        let names: Vec<String> = types.iter().map(verification_type).collect();
+        format!("[{}]", names.join(", "))
+    };
+    let offset_delta = frame.offset_delta();
+    match frame {
+        StackMapFrame::Same { .. } => format!("same offset_delta={offset_delta}"),
+        StackMapFrame::SameExtended { .. } => {
+            format!("same_frame_extended offset_delta={offset_delta}")
+        }
+        StackMapFrame::SameLocals1StackItem { stack, .. } => format!(
+            "same_locals_1_stack_item offset_delta={offset_delta} stack={}",
+            types(&[*stack])
+        ),
+        StackMapFrame::SameLocals1StackItemExtended { stack, .. } => format!(
+            "same_locals_1_stack_item_extended offset_delta={offset_delta} stack={}",
+            types(&[*stack])
+        ),
+        StackMapFrame::Chop { absent, .. } => {
+            format!("chop offset_delta={offset_delta} absent={absent}")
+        }
+        StackMapFrame::Append { locals, .. } => format!(
+            "append offset_delta={offset_delta} locals={}",
+            types(locals)
+        ),
+        StackMapFrame::Full { locals, stack, .. } => format!(
+            "full_frame offset_delta={offset_delta} locals={} stack={}",
+            types(locals),
+            types(stack)
+        ),
+    }
+}
+
+fn verification_type(verification_type: &VerificationType) -> String {
+    match verification_type {
+        VerificationType::Top => "top".to_string(),
+        VerificationType::Integer => "int".to_string(),
+        VerificationType::Float => "float".to_string(),
+        VerificationType::Double => "double".to_string(),
+        VerificationType::Long => "long".to_string(),
+        VerificationType::Null => "null".to_string(),
+        VerificationType::UninitializedThis => "uninitialized_this".to_string(),
+        VerificationType::Object(index) => format!("#{index}"),
+        VerificationType::Uninitialized(offset) => format!("uninitialized({offset})"),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.escape_debug().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    fn hello_world() -> Vec<u8> {
+        std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap()
+    }
+
+    #[test]
+    fn explains_every_byte() {
+        let bytes = hello_world();
+        let mut out = String::new();
+        explain(&bytes, &mut out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        for expected in [
+            "0000: CA FE BA BE              magic",
+            "0008: 00 1D                    constant_pool_count = 29",
+            "000A: 0A 00 06 00 0F           #1 Methodref class=#6 nat=#15",
+            "0022: 01 00 06 3C 69 6E 69 74  #7 Utf8 \"<init>\"",
+            "002A: 3E",
+            "0137: 00 21                    access_flags = 0x0021 (ACC_PUBLIC, ACC_SUPER)",
+            "0172: 00 0C                      descriptor_index = #12 (([Ljava/lang/String;)V)",
+            "0184: B2 00 02                     0: getstatic #2",
+            "019D: 00 08 00 04                    start_pc=8 line=4",
+        ] {
+            assert!(lines.contains(&expected), "missing {expected:?} in\n{out}");
+        }
+        assert_eq!(
+            lines.last(),
+            Some(&"01A9: 00 0E                      sourcefile_index = #14 (HelloWorld.java)")
+        );
+    }
+
+    #[test]
+    fn reports_where_parsing_stopped() {
+        // the first instruction of main is replaced with an undefined opcode
+        let mut bytes = hello_world();
+        bytes[0x184] = 0xFF;
+        let mut out = String::new();
+        let error = explain(&bytes, &mut out).unwrap_err();
+        assert!(error.to_string().starts_with("at offset 0x0184: "));
+        assert!(out.ends_with("code_length = 9\n"));
+
+        // a truncated constant pool stops in the middle of #6
+        let mut out = String::new();
+        let error = explain(&bytes[..0x20], &mut out).unwrap_err();
+        assert!(error.to_string().starts_with("at offset 0x001F: "));
+        assert!(out.ends_with("#5 Class name=#21\n"));
+
+        // an attribute that claims more bytes than the class file has left
+        let mut bytes = hello_world();
+        bytes[0x1A5] = 0x01;
+        let error = explain(&bytes, &mut String::new()).unwrap_err();
+        assert!(error.to_string().starts_with("at offset 0x01A9: "));
+    }
+
+    #[test]
+    fn explains_switches_and_exception_tables() {
+        let class_file = assemble(
+            ".class public Switch
+.super java/lang/Object
+.method public static pick(I)I
+  .limit stack 1
+  .limit locals 1
+  .catch java/lang/RuntimeException from Start to End using End
+Start:
+  iload_0
+  tableswitch 0
+    One
+    default : End
+One:
+  iconst_1
+  ireturn
+End:
+  iconst_0
+  ireturn
+.end method
+",
+        )
+        .unwrap();
+        let mut out = String::new();
+        explain(&class_file.to_bytes(), &mut out).unwrap();
+        assert!(out.contains("  1: tableswitch { 0: 20, default: 22 }\n"));
+        assert!(out.contains("  start_pc=0 end_pc=22 handler_pc=22 catch_type=#"));
+        assert!(out.contains(" (java/lang/RuntimeException)\n"));
+    }
+}
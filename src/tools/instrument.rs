@@ -0,0 +1,458 @@
+use crate::analysis::frames::{compute_frames, recompute_frames, Frame, Interpreter, ValueType};
+use crate::analysis::hierarchy::{ClassHierarchy, OBJECT};
+use crate::analysis::verifier::falls_through;
+use crate::bytecode::encoder::{CodeElement, LabeledCode, LabeledHandler};
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::ACC_STATIC;
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+use crate::class_file::member_info::MethodInfo;
+use std::io;
+
+// Method entry and exit tracing by rewriting bytecode. Every method with code calls a static
+// hook first thing, again before each return instruction, and from a catch-all handler over
+// the whole body that rethrows whatever it caught, so exceptions thrown or passed on by the
+// method are seen too. max_stack and the stack map frames are recomputed afterwards.
+//
+// A handler can't be entered while `this` may still be uninitialized unless every frame it
+// covers is, which a stack map frame can't express for code on both sides of the call to the
+// superclass constructor (section 4.10.1.9). In constructors the handler only covers the code
+// after that call, so exceptions thrown before it aren't reported.
+
+/// The hook's first argument when a method is entered
+pub const ENTRY: i32 = 0;
+/// ... when it's about to return normally
+pub const RETURN: i32 = 1;
+/// ... when it's completing abruptly with an exception
+pub const THROW: i32 = 2;
+
+/// The descriptor the hook must have: the event, then the method as `class.name(descriptor)`
+/// with an internal class name, e.g. `com/example/Api.size()I`
+pub const HOOK_DESCRIPTOR: &str = "(ILjava/lang/String;)V";
+
+pub struct Instrumenter<'a> {
+    hook_class: String,
+    hook_method: String,
+    hierarchy: &'a dyn ClassHierarchy,
+}
+
+impl<'a> Instrumenter<'a> {
+    /// Calls `hook_class.hook_method`, which must be static with `HOOK_DESCRIPTOR`. The
+    /// hierarchy is needed to compute the new stack map frames; if it has the hook's class, the
+    /// hook is checked too.
+    pub fn new(
+        hook_class: &str,
+        hook_method: &str,
+        hierarchy: &'a dyn ClassHierarchy,
+    ) -> Result<Instrumenter<'a>, io::Error> {
+        check_hook(hook_class, hook_method, hierarchy)?;
+        Ok(Instrumenter {
+            hook_class: hook_class.to_string(),
+            hook_method: hook_method.to_string(),
+            hierarchy,
+        })
+    }
+
+    /// Instruments every method with code, returning how many there were. The hook's own
+    /// class is left alone so the hook doesn't end up calling itself.
+    pub fn instrument(&self, class_file: &mut ClassFile) -> Result<usize, io::Error> {
+        let class_name = class_file.class_name()?;
+        if class_name == self.hook_class {
+            return Ok(0);
+        }
+        let mut protected = Vec::with_capacity(class_file.methods().len());
+        for method in class_file.methods() {
+            let constant_pool = class_file.constant_pool();
+            protected.push(match method.code(constant_pool)? {
+                Some(code) if method.name(constant_pool)? == "<init>" => {
+                    initialized_after(class_file, method, &code, self.hierarchy)?
+                }
+                _ => Some(0),
+            });
+        }
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        let instrumented =
+            self.instrument_methods(class_file, &class_name, &protected, &mut builder);
+        // put the constant pool back even if a method couldn't be instrumented
+        class_file.set_constant_pool(builder.build());
+        let count = instrumented?;
+        if count > 0 {
+            recompute_frames(class_file, self.hierarchy)?;
+        }
+        Ok(count)
+    }
+
+    fn instrument_methods(
+        &self,
+        class_file: &mut ClassFile,
+        class_name: &str,
+        protected: &[Option<usize>],
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<usize, io::Error> {
+        let mut count = 0;
+        for (method, protected) in class_file.methods_mut().iter_mut().zip(protected) {
+            let constant_pool = builder.constant_pool();
+            let Some(i) = method.attributes().iter().position(
+                |attribute| matches!(attribute.name(constant_pool), Ok(name) if name == Code::NAME),
+            ) else {
+                continue;
+            };
+            let code = Code::from(&mut method.attributes()[i].reader())?;
+            let name = method.name(constant_pool)?;
+            let signature = format!("{class_name}.{name}{}", method.descriptor(constant_pool)?);
+            let code = self
+                .instrument_code(&code, &signature, *protected, builder)
+                .map_err(|e| io::Error::new(e.kind(), format!("{name}: {e}")))?;
+            let mut info = Vec::new();
+            code.write(&mut info);
+            let attribute = &mut method.attributes_mut()[i];
+            *attribute = AttributeInfo::new(attribute.attribute_name_index(), info);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// The code with the hook calls and the catch-all handler added, which covers the code
+    /// after the first `protected` instructions, or nothing if `None`. max_stack is left for
+    /// the frame computation to fix up.
+    fn instrument_code(
+        &self,
+        code: &Code,
+        signature: &str,
+        protected: Option<usize>,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<Code, io::Error> {
+        let mut labeled = LabeledCode::from_code(code, builder.constant_pool())?;
+        let hook = builder.add_method_ref(&self.hook_class, &self.hook_method, HOOK_DESCRIPTOR)?;
+        let method = builder.add_string(signature)?;
+        let call = |event: i32| {
+            let event = match event {
+                ENTRY => Instruction::Iconst0,
+                RETURN => Instruction::Iconst1,
+                _ => Instruction::Iconst2,
+            };
+            [
+                event,
+                Instruction::Ldc(method),
+                Instruction::Invokestatic(hook),
+            ]
+            .map(CodeElement::Instruction)
+        };
+
+        let start = labeled.new_label();
+        let end = labeled.new_label();
+        let handler = labeled.new_label();
+        let original = std::mem::take(labeled.elements_mut());
+        let elements = labeled.elements_mut();
+        elements.extend(call(ENTRY));
+        let mut instructions = 0;
+        let mut started = false;
+        for element in original {
+            if !started && protected == Some(instructions) {
+                elements.push(CodeElement::Label(start));
+                started = true;
+            }
+            if let CodeElement::Instruction(_) = element {
+                instructions += 1;
+            }
+            if matches!(
+                element,
+                CodeElement::Instruction(
+                    Instruction::Ireturn
+                        | Instruction::Lreturn
+                        | Instruction::Freturn
+                        | Instruction::Dreturn
+                        | Instruction::Areturn
+                        | Instruction::Return
+                )
+            ) {
+                elements.extend(call(RETURN));
+            }
+            elements.push(element);
+        }
+        if started {
+            elements.push(CodeElement::Label(end));
+            elements.push(CodeElement::Label(handler));
+            elements.extend(call(THROW));
+            elements.push(CodeElement::Instruction(Instruction::Athrow));
+            // last, so the method's own handlers still get their exceptions first
+            labeled
+                .exception_table_mut()
+                .push(LabeledHandler::new(start, end, handler, 0));
+        }
+        labeled.to_code(code.max_stack(), code.max_locals())
+    }
+}
+
+/// How many instructions of a constructor come before `this` is initialized, found by
+/// replaying the computed frames in code order as the verifier does. None if it happens in
+/// more than one place, since then there's no one point after which it's always done.
+fn initialized_after(
+    class_file: &ClassFile,
+    method: &MethodInfo,
+    code: &Code,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<Option<usize>, io::Error> {
+    let frames = compute_frames(class_file, method, code, hierarchy)?;
+    let class_name = class_file.class_name()?;
+    let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+    let interpreter = Interpreter::new(class_file.constant_pool(), &class_name, &instructions);
+    let uninitialized = |frame: &Frame| {
+        frame
+            .locals()
+            .iter()
+            .chain(frame.stack())
+            .any(|value| *value == ValueType::UninitializedThis)
+    };
+
+    let mut frame = Some(frames.initial().clone());
+    let mut calls = Vec::new();
+    for (i, (pc, instruction)) in instructions.iter().enumerate() {
+        if let Ok(j) = frames.frames().binary_search_by_key(pc, |(pc, _)| *pc) {
+            frame = Some(frames.frames()[j].1.clone());
+        }
+        // code after a jump that no frame starts again is unreachable
+        let Some(current) = frame.as_mut() else {
+            continue;
+        };
+        let before = uninitialized(current);
+        interpreter.execute(*pc, instruction, current)?;
+        if before && !uninitialized(current) {
+            calls.push(i + 1);
+        }
+        if !falls_through(instruction) {
+            frame = None;
+        }
+    }
+    Ok(match calls.as_slice() {
+        [] => Some(0),
+        [after] => Some(*after),
+        _ => None,
+    })
+}
+
+/// Looks the hook up as `invokestatic` would, in the class and then its superclasses
+/// (section 5.4.3.3). Nothing can be said once the search leaves the known classes, except
+/// that `java/lang/Object` has no such method.
+fn check_hook(
+    hook_class: &str,
+    hook_method: &str,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), io::Error> {
+    let hook = format!("{hook_class}.{hook_method}{HOOK_DESCRIPTOR}");
+    for class in hierarchy.super_classes(hook_class) {
+        if class != OBJECT && !hierarchy.contains(&class) {
+            return Ok(());
+        }
+        match hierarchy.member_access_flags(&class, hook_method, HOOK_DESCRIPTOR) {
+            Some(flags) if flags & ACC_STATIC != 0 => return Ok(()),
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("the hook {hook} isn't static"),
+                ))
+            }
+            None => {}
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("the hook {hook} doesn't exist"),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::hierarchy::ClassFileHierarchy;
+    use crate::analysis::verifier::verify_class;
+    use crate::assembler::assembler::assemble;
+
+    fn instrument(source: &str) -> (ClassFile, usize) {
+        let mut class_file = assemble(source).unwrap();
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add_class_file(&class_file).unwrap();
+        let count = Instrumenter::new("t/Trace", "event", &hierarchy)
+            .unwrap()
+            .instrument(&mut class_file)
+            .unwrap();
+        assert_eq!(verify_class(&class_file, &hierarchy), vec![]);
+        (class_file, count)
+    }
+
+    /// The hook calls in a method as (pc, event)
+    fn events(class_file: &ClassFile, name: &str, descriptor: &str) -> Vec<(u32, i32)> {
+        let constant_pool = class_file.constant_pool();
+        let code = class_file
+            .find_method(name, descriptor)
+            .unwrap()
+            .code(constant_pool)
+            .unwrap()
+            .unwrap();
+        let instructions = code.instructions().collect::<Result<Vec<_>, _>>().unwrap();
+        let mut events = Vec::new();
+        for window in instructions.windows(3) {
+            let [(pc, event), (_, Instruction::Ldc(method)), (_, Instruction::Invokestatic(hook))] =
+                window
+            else {
+                continue;
+            };
+            let hook = constant_pool.member_ref(*hook).unwrap();
+            assert_eq!(
+                hook,
+                ("t/Trace".into(), "event".into(), HOOK_DESCRIPTOR.into())
+            );
+            assert!(constant_pool.get(*method).is_some());
+            let event = match event {
+                Instruction::Iconst0 => ENTRY,
+                Instruction::Iconst1 => RETURN,
+                _ => THROW,
+            };
+            events.push((*pc, event));
+        }
+        events
+    }
+
+    #[test]
+    fn calls_the_hook_on_entry_returns_and_throws() {
+        let (class_file, count) = instrument(
+            "
+.class t/A
+.super java/lang/Object
+.method abstract run()V
+.end method
+.method static f(I)J
+    .limit stack 2
+    .limit locals 1
+    .catch java/lang/RuntimeException from Start to End using Handler
+Start:
+    iload_0
+    ifeq Zero
+    lconst_1
+    lreturn
+Zero:
+    lconst_0
+End:
+    lreturn
+Handler:
+    athrow
+.end method
+",
+        );
+        assert_eq!(count, 1);
+        assert_eq!(
+            events(&class_file, "f", "(I)J")
+                .iter()
+                .map(|(_, event)| *event)
+                .collect::<Vec<_>>(),
+            [ENTRY, RETURN, RETURN, THROW]
+        );
+
+        let constant_pool = class_file.constant_pool();
+        let method = class_file.find_method("f", "(I)J").unwrap();
+        let code = method.code(constant_pool).unwrap().unwrap();
+        // the method's own handler comes first, and the hook's arguments go on top of the long
+        // being returned
+        let table = code.exception_table();
+        assert_eq!(table.len(), 2);
+        assert_ne!(table[0].catch_type(), 0);
+        let catch_all = &table[1];
+        assert_eq!(catch_all.catch_type(), 0);
+        assert_eq!(catch_all.start_pc(), 6);
+        assert_eq!(catch_all.end_pc(), catch_all.handler_pc());
+        assert_eq!(code.max_stack(), 4);
+    }
+
+    #[test]
+    fn constructors_are_only_covered_once_this_is_initialized() {
+        let (class_file, _) = instrument(
+            "
+.class t/B
+.super java/lang/Object
+.method <init>(I)V
+    .limit stack 3
+    .limit locals 2
+    aload_0
+    new java/lang/Object
+    dup
+    invokespecial java/lang/Object/<init>()V
+    pop
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+",
+        );
+        let events = events(&class_file, "<init>", "(I)V");
+        assert_eq!(events.len(), 3);
+        let constant_pool = class_file.constant_pool();
+        let code = class_file
+            .find_method("<init>", "(I)V")
+            .unwrap()
+            .code(constant_pool)
+            .unwrap()
+            .unwrap();
+        // the range starts after the second invokespecial, which is at pc 15 after the six
+        // bytes of the entry call
+        let catch_all = &code.exception_table()[0];
+        assert_eq!(catch_all.start_pc(), 18);
+        assert_eq!(catch_all.start_pc() as u32, events[1].0);
+    }
+
+    #[test]
+    fn leaves_the_hook_class_alone() {
+        let (class_file, count) = instrument(
+            "
+.class t/Trace
+.super java/lang/Object
+.method static event(ILjava/lang/String;)V
+    return
+.end method
+",
+        );
+        assert_eq!(count, 0);
+        assert!(events(&class_file, "event", HOOK_DESCRIPTOR).is_empty());
+    }
+
+    #[test]
+    fn checks_hooks_it_can_see() {
+        let mut hierarchy = ClassFileHierarchy::new();
+        let hook = assemble(
+            "
+.class t/Trace
+.super java/lang/Object
+.method static hit()V
+    return
+.end method
+.method event(ILjava/lang/String;)V
+    return
+.end method
+",
+        )
+        .unwrap();
+        hierarchy.add_class_file(&hook).unwrap();
+        let error = Instrumenter::new("t/Trace", "hit", &hierarchy)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "the hook t/Trace.hit(ILjava/lang/String;)V doesn't exist"
+        );
+        let error = Instrumenter::new("t/Trace", "event", &hierarchy)
+            .err()
+            .unwrap();
+        assert!(error.to_string().ends_with("isn't static"), "{error}");
+
+        // found in a superclass, or in a class that isn't on the class path
+        let sub = assemble(".class t/Sub\n.super t/Base\n").unwrap();
+        let base = assemble(
+            ".class t/Base\n.super java/lang/Object\n.method static hit(ILjava/lang/String;)V\n    return\n.end method\n",
+        )
+        .unwrap();
+        hierarchy.add_class_file(&sub).unwrap();
+        hierarchy.add_class_file(&base).unwrap();
+        assert!(Instrumenter::new("t/Sub", "hit", &hierarchy).is_ok());
+        assert!(Instrumenter::new("t/Missing", "hit", &hierarchy).is_ok());
+    }
+}
@@ -0,0 +1,1032 @@
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::{
+    keywords, spec_names, FlagTarget, ACC_ABSTRACT, ACC_INTERFACE, ACC_STATIC, ACC_VARARGS,
+};
+use crate::class_file::attribute::attribute_info::{find_attribute, AttributeInfo};
+use crate::class_file::attribute::bootstrap_methods::BootstrapMethods;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::attribute::constant_value::ConstantValue;
+use crate::class_file::attribute::exceptions::Exceptions;
+use crate::class_file::attribute::inner_classes::{InnerClass, InnerClasses};
+use crate::class_file::attribute::line_number_table::LineNumberTable;
+use crate::class_file::attribute::local_variable_table::LocalVariableTable;
+use crate::class_file::attribute::source_file::SourceFile;
+use crate::class_file::attribute::stack_map_table::{
+    StackMapFrame, StackMapTable, VerificationType,
+};
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::constant_pool::types::method_handle::ReferenceKind;
+use crate::class_file::descriptor::{FieldType, MethodDescriptor};
+use crate::class_file::member_info::MemberInfo;
+use crate::util::file::read_u16;
+use std::io;
+
+/// Which parts of a class `javap` prints, mirroring the flags of the JDK tool
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JavapOptions {
+    verbose: bool,
+    code: bool,
+}
+
+impl JavapOptions {
+    /// `verbose` (`-v`) adds the version, flags, constant pool and all attributes;
+    /// `code` (`-c`) adds disassembled bytecode and is implied by `verbose`
+    pub fn new(verbose: bool, code: bool) -> JavapOptions {
+        JavapOptions {
+            verbose,
+            code: code || verbose,
+        }
+    }
+}
+
+/// Renders a class file the way `javap` does.
+///
+/// Broken constant pool references don't stop the output; they're shown as `<invalid #n>`
+/// so malformed class files can still be inspected.
+pub fn javap(class_file: &ClassFile, options: JavapOptions) -> Result<String, io::Error> {
+    let mut printer = Printer {
+        out: String::new(),
+        class_file,
+        constant_pool: class_file.constant_pool(),
+        options,
+    };
+    printer.class()?;
+    Ok(printer.out)
+}
+
+/// A switch key (or `default`) and its absolute jump target
+type SwitchCase = (String, u32);
+
+struct Printer<'a> {
+    out: String,
+    class_file: &'a ClassFile,
+    constant_pool: &'a ConstantPool,
+    options: JavapOptions,
+}
+
+impl Printer<'_> {
+    fn line(&mut self, indent: usize, text: &str) {
+        self.out.push_str(&" ".repeat(indent));
+        self.out.push_str(text.trim_end());
+        self.out.push('\n');
+    }
+
+    fn class(&mut self) -> Result<(), io::Error> {
+        let class_file = self.class_file;
+        let constant_pool = self.constant_pool;
+
+        if let Some(attribute) = class_file.find_attribute(SourceFile::NAME) {
+            let source_file = SourceFile::from(&mut attribute.reader())?;
+            let name = self.utf8_or_invalid(source_file.sourcefile_index());
+            self.line(
+                if self.options.verbose { 2 } else { 0 },
+                &format!("Compiled from \"{name}\""),
+            );
+        }
+
+        let declaration = class_declaration(class_file)?;
+        if !self.options.verbose {
+            self.line(0, &format!("{declaration} {{"));
+        } else {
+            self.line(0, &declaration);
+            self.line(2, &format!("minor version: {}", class_file.minor_version()));
+            self.line(2, &format!("major version: {}", class_file.major_version()));
+            self.line(2, &flags_line(FlagTarget::Class, class_file.access_flags()));
+            let this_class = class_file.this_class();
+            self.line(
+                2,
+                &format!(
+                    "this_class: {:<28}// {}",
+                    format!("#{this_class}"),
+                    self.resolve(this_class)
+                ),
+            );
+            let super_class = class_file.super_class();
+            let super_comment = match super_class {
+                0 => String::new(),
+                index => format!("// {}", self.resolve(index)),
+            };
+            self.line(
+                2,
+                &format!(
+                    "super_class: {:<27}{super_comment}",
+                    format!("#{super_class}")
+                ),
+            );
+            self.line(
+                2,
+                &format!(
+                    "interfaces: {}, fields: {}, methods: {}, attributes: {}",
+                    class_file.interfaces().len(),
+                    class_file.fields().len(),
+                    class_file.methods().len(),
+                    class_file.attributes().len()
+                ),
+            );
+            self.constant_pool_listing();
+            self.line(0, "{");
+        }
+
+        let mut first = true;
+        for field in class_file.fields() {
+            if !first {
+                self.line(0, "");
+            }
+            first = false;
+            self.member(field, FlagTarget::Field)?;
+        }
+        for method in class_file.methods() {
+            if !first {
+                self.line(0, "");
+            }
+            first = false;
+            self.member(method, FlagTarget::Method)?;
+        }
+        self.line(0, "}");
+
+        if self.options.verbose {
+            for attribute in class_file.attributes() {
+                self.attribute(0, attribute, constant_pool)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn constant_pool_listing(&mut self) {
+        self.line(0, "Constant pool:");
+        let constant_pool = self.constant_pool;
+        // the index column grows with the pool while the comments stay in place
+        let index_width = format!("#{}", constant_pool.items().len()).len().max(3) + 2;
+        let arguments_width = 19 - index_width;
+        for (i, item) in constant_pool.items().iter().enumerate() {
+            let index = format!("#{}", i + 1);
+            let (tag, arguments, resolved) = match item.data() {
+                ConstantPoolData::Uninit | ConstantPoolData::Unusable => continue,
+                ConstantPoolData::Utf8(utf8) => ("Utf8", escape(&utf8.value()), false),
+                ConstantPoolData::Integer(integer) => {
+                    ("Integer", integer.value().to_string(), false)
+                }
+                ConstantPoolData::Float(float) => {
+                    ("Float", java_float(float.value() as f64, "f"), false)
+                }
+                ConstantPoolData::Long(long) => ("Long", format!("{}l", long.value()), false),
+                ConstantPoolData::Double(double) => {
+                    ("Double", java_float(double.value(), "d"), false)
+                }
+                ConstantPoolData::ClassInfo(class) => {
+                    ("Class", format!("#{}", class.name_index()), true)
+                }
+                ConstantPoolData::String(string) => {
+                    ("String", format!("#{}", string.string_index()), true)
+                }
+                ConstantPoolData::FieldRef(r) => (
+                    "Fieldref",
+                    format!("#{}.#{}", r.class_index(), r.name_and_type_index()),
+                    true,
+                ),
+                ConstantPoolData::MethodRef(r) => (
+                    "Methodref",
+                    format!("#{}.#{}", r.class_index(), r.name_and_type_index()),
+                    true,
+                ),
+                ConstantPoolData::InterfaceMethodRef(r) => (
+                    "InterfaceMethodref",
+                    format!("#{}.#{}", r.class_index(), r.name_and_type_index()),
+                    true,
+                ),
+                ConstantPoolData::NameAndType(nat) => (
+                    "NameAndType",
+                    format!("#{}:#{}", nat.name_index(), nat.descriptor_index()),
+                    true,
+                ),
+                ConstantPoolData::MethodHandle(handle) => (
+                    "MethodHandle",
+                    format!(
+                        "{}:#{}",
+                        handle.reference_kind() as i8,
+                        handle.reference_index()
+                    ),
+                    true,
+                ),
+                ConstantPoolData::MethodType(method_type) => (
+                    "MethodType",
+                    format!("#{}", method_type.descriptor_index()),
+                    true,
+                ),
+                ConstantPoolData::InvokeDynamic(indy) => (
+                    "InvokeDynamic",
+                    format!(
+                        "#{}:#{}",
+                        indy.bootstrap_method_attr_index(),
+                        indy.name_and_type_index()
+                    ),
+                    true,
+                ),
+            };
+            let text = match resolved {
+                true => {
+                    // javap puts an extra space before MethodType descriptors
+                    let separator = if tag == "MethodType" { "  " } else { " " };
+                    format!(
+                        "{index:>index_width$} = {tag:<18} {arguments:<arguments_width$} //{separator}{}",
+                        self.resolve((i + 1) as u16)
+                    )
+                }
+                false => format!("{index:>index_width$} = {tag:<18} {arguments}"),
+            };
+            self.line(0, &text);
+        }
+    }
+
+    fn member(&mut self, member: &MemberInfo, target: FlagTarget) -> Result<(), io::Error> {
+        let constant_pool = self.constant_pool;
+        let declaration = member_declaration(self.class_file, member, target)?;
+        self.line(2, &format!("{declaration};"));
+
+        if self.options.verbose {
+            self.line(
+                4,
+                &format!("descriptor: {}", member.descriptor(constant_pool)?),
+            );
+            self.line(4, &flags_line(target, member.access_flags()));
+        }
+
+        for attribute in member.attributes() {
+            let name = attribute.name(constant_pool)?;
+            if name == Code::NAME {
+                if self.options.code {
+                    self.code(member, &Code::from(&mut attribute.reader())?)?;
+                }
+            } else if self.options.verbose {
+                self.attribute(4, attribute, constant_pool)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn code(&mut self, method: &MemberInfo, code: &Code) -> Result<(), io::Error> {
+        let constant_pool = self.constant_pool;
+        let verbose = self.options.verbose;
+        self.line(4, "Code:");
+        if verbose {
+            let descriptor = MethodDescriptor::parse(&method.descriptor(constant_pool)?)?;
+            let this_slot = if method.access_flags() & ACC_STATIC == 0 {
+                1
+            } else {
+                0
+            };
+            self.line(
+                6,
+                &format!(
+                    "stack={}, locals={}, args_size={}",
+                    code.max_stack(),
+                    code.max_locals(),
+                    descriptor.parameter_slots() + this_slot
+                ),
+            );
+        }
+
+        let pc_width = if verbose { 10 } else { 8 };
+        for (pc, text, cases) in self.instructions(code.code()) {
+            self.line(0, &format!("{pc:>pc_width$}: {text}"));
+            if !cases.is_empty() {
+                for (key, target) in cases {
+                    self.line(0, &format!("{key:>w$}: {target}", w = pc_width + 14));
+                }
+                self.line(0, &format!("{:>w$}", "}", w = pc_width + 3));
+            }
+        }
+
+        if !code.exception_table().is_empty() {
+            // -c output is indented two columns less than -v
+            let indent = pc_width - 4;
+            self.line(indent, "Exception table:");
+            self.line(indent + 3, "from    to  target type");
+            for entry in code.exception_table() {
+                let catch_type = match entry.catch_type() {
+                    0 => "any".to_string(),
+                    index => format!("Class {}", self.resolve(index)),
+                };
+                self.line(
+                    indent,
+                    &format!(
+                        "{:>8}{:>6}{:>6}   {catch_type}",
+                        entry.start_pc(),
+                        entry.end_pc(),
+                        entry.handler_pc()
+                    ),
+                );
+            }
+        }
+
+        if verbose {
+            for attribute in code.attributes() {
+                self.attribute(6, attribute, constant_pool)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The pc, text and switch cases of every instruction. Decoding stops at the first
+    /// malformed instruction, which is shown as `<message>`.
+    fn instructions(&self, code: &[u8]) -> Vec<(usize, String, Vec<SwitchCase>)> {
+        let mut lines = Vec::new();
+        let mut pc = 0;
+        while pc < code.len() {
+            match Instruction::decode(code, pc) {
+                Ok((instruction, length)) => {
+                    // the decoder folds `wide` into the instruction; only its length tells
+                    let wide = instruction.local_index().is_some() && length >= 4;
+                    let (text, cases) = self.instruction(&instruction, wide);
+                    lines.push((pc, text, cases));
+                    pc += length;
+                }
+                Err(error) => {
+                    lines.push((pc, format!("<{error}>"), Vec::new()));
+                    break;
+                }
+            }
+        }
+        lines
+    }
+
+    /// Formats an instruction, returning its text and switch cases
+    fn instruction(&self, instruction: &Instruction, wide: bool) -> (String, Vec<SwitchCase>) {
+        let mnemonic = if wide {
+            format!("wide {}", instruction.mnemonic())
+        } else {
+            instruction.mnemonic().to_string()
+        };
+        let with_comment = |operands: String, index: u16| -> String {
+            let text = format!("{mnemonic:<13} {operands}");
+            format!("{text:<33} // {}", self.describe(index))
+        };
+        let plain = |operands: String| format!("{mnemonic:<13} {operands}");
+
+        let text = match instruction {
+            Instruction::Bipush(value) => plain(value.to_string()),
+            Instruction::Sipush(value) => plain(value.to_string()),
+            Instruction::Iinc { index, increment } => plain(format!("{index}, {increment}")),
+            Instruction::Invokeinterface { index, count } => {
+                with_comment(format!("#{index},  {count}"), *index)
+            }
+            Instruction::Invokedynamic(index) => with_comment(format!("#{index},  0"), *index),
+            Instruction::Multianewarray { index, dimensions } => {
+                with_comment(format!("#{index},  {dimensions}"), *index)
+            }
+            Instruction::Newarray(array_type) => format!("{mnemonic:<14} {}", array_type.name()),
+            Instruction::Tableswitch {
+                default,
+                low,
+                high,
+                targets,
+            } => {
+                let mut cases: Vec<SwitchCase> = (*low..=*high)
+                    .zip(targets)
+                    .map(|(key, target)| (key.to_string(), *target))
+                    .collect();
+                cases.push(("default".to_string(), *default));
+                return (format!("{mnemonic:<13} {{ // {low} to {high}"), cases);
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                let mut cases: Vec<SwitchCase> = pairs
+                    .iter()
+                    .map(|(key, target)| (key.to_string(), *target))
+                    .collect();
+                cases.push(("default".to_string(), *default));
+                return (format!("{mnemonic:<13} {{ // {}", pairs.len()), cases);
+            }
+            _ => {
+                if let Some(index) = instruction.constant_index() {
+                    with_comment(format!("#{index}"), index)
+                } else if let Some(index) = instruction.local_index() {
+                    plain(index.to_string())
+                } else if let [target] = instruction.branch_targets()[..] {
+                    plain(target.to_string())
+                } else {
+                    mnemonic
+                }
+            }
+        };
+        (text, Vec::new())
+    }
+
+    fn attribute(
+        &mut self,
+        indent: usize,
+        attribute: &AttributeInfo,
+        constant_pool: &ConstantPool,
+    ) -> Result<(), io::Error> {
+        let name = attribute.name(constant_pool)?;
+        match name.as_str() {
+            SourceFile::NAME => {
+                let source_file = SourceFile::from(&mut attribute.reader())?;
+                let file_name = self.utf8_or_invalid(source_file.sourcefile_index());
+                self.line(indent, &format!("SourceFile: \"{file_name}\""));
+            }
+            ConstantValue::NAME => {
+                let value = ConstantValue::from(&mut attribute.reader())?;
+                let index = value.constantvalue_index();
+                self.line(indent, &format!("ConstantValue: {}", self.describe(index)));
+            }
+            Exceptions::NAME => {
+                let exceptions = Exceptions::from(&mut attribute.reader())?;
+                self.line(indent, "Exceptions:");
+                let names: Vec<String> = exceptions
+                    .exception_index_table()
+                    .iter()
+                    .map(|index| self.resolve(*index).replace('/', "."))
+                    .collect();
+                self.line(indent + 2, &format!("throws {}", names.join(", ")));
+            }
+            LineNumberTable::NAME => {
+                let table = LineNumberTable::from(&mut attribute.reader())?;
+                self.line(indent, "LineNumberTable:");
+                for entry in table.line_number_table() {
+                    self.line(
+                        indent + 2,
+                        &format!("line {}: {}", entry.line_number(), entry.start_pc()),
+                    );
+                }
+            }
+            LocalVariableTable::NAME | LocalVariableTable::TYPE_TABLE_NAME => {
+                let table = LocalVariableTable::from(&mut attribute.reader())?;
+                self.line(indent, &format!("{name}:"));
+                self.line(indent + 2, "Start  Length  Slot  Name   Signature");
+                for variable in table.local_variable_table() {
+                    self.line(
+                        indent,
+                        &format!(
+                            "{:>7}{:>8}{:>6} {:>5}   {}",
+                            variable.start_pc(),
+                            variable.length(),
+                            variable.index(),
+                            self.utf8_or_invalid(variable.name_index()),
+                            self.utf8_or_invalid(variable.descriptor_index())
+                        ),
+                    );
+                }
+            }
+            StackMapTable::NAME => {
+                let table = StackMapTable::from(&mut attribute.reader())?;
+                self.line(
+                    indent,
+                    &format!(
+                        "StackMapTable: number_of_entries = {}",
+                        table.entries().len()
+                    ),
+                );
+                for frame in table.entries() {
+                    self.stack_map_frame(indent + 2, frame);
+                }
+            }
+            BootstrapMethods::NAME => {
+                let bootstrap_methods = BootstrapMethods::from(&mut attribute.reader())?;
+                self.line(indent, "BootstrapMethods:");
+                for (i, method) in bootstrap_methods.bootstrap_methods().iter().enumerate() {
+                    let handle = method.bootstrap_method_ref();
+                    self.line(
+                        indent + 2,
+                        &format!("{i}: #{handle} {}", self.resolve(handle)),
+                    );
+                    self.line(indent + 4, "Method arguments:");
+                    for argument in method.bootstrap_arguments() {
+                        self.line(
+                            indent + 6,
+                            &format!("#{argument} {}", self.resolve(*argument)),
+                        );
+                    }
+                }
+            }
+            InnerClasses::NAME => {
+                let inner_classes = InnerClasses::from(&mut attribute.reader())?;
+                self.line(indent, "InnerClasses:");
+                for class in inner_classes.classes() {
+                    let text = self.inner_class(class);
+                    self.line(indent + 2, &text);
+                }
+            }
+            "Signature" => {
+                let index = read_u16(&mut attribute.reader())?;
+                self.line(
+                    indent,
+                    &format!(
+                        "Signature: {:<29}// {}",
+                        format!("#{index}"),
+                        self.resolve(index)
+                    ),
+                );
+            }
+            _ => {
+                self.line(
+                    indent,
+                    &format!(
+                        "{name}: length = 0x{:x} (unknown attribute)",
+                        attribute.info().len()
+                    ),
+                );
+                for chunk in attribute.info().chunks(16) {
+                    let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+                    self.line(indent + 1, &bytes.join(" "));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn stack_map_frame(&mut self, indent: usize, frame: &StackMapFrame) {
+        let kind = match frame {
+            StackMapFrame::Same { .. } => "same",
+            StackMapFrame::SameLocals1StackItem { .. } => "same_locals_1_stack_item",
+            StackMapFrame::SameLocals1StackItemExtended { .. } => {
+                "same_locals_1_stack_item_frame_extended"
+            }
+            StackMapFrame::Chop { .. } => "chop",
+            StackMapFrame::SameExtended { .. } => "same_frame_extended",
+            StackMapFrame::Append { .. } => "append",
+            StackMapFrame::Full { .. } => "full_frame",
+        };
+        self.line(
+            indent,
+            &format!("frame_type = {} /* {kind} */", frame.frame_type()),
+        );
+        // the short forms encode the offset delta in the frame type
+        if !matches!(
+            frame,
+            StackMapFrame::Same { .. } | StackMapFrame::SameLocals1StackItem { .. }
+        ) {
+            self.line(
+                indent + 2,
+                &format!("offset_delta = {}", frame.offset_delta()),
+            );
+        }
+        match frame {
+            StackMapFrame::SameLocals1StackItem { stack, .. }
+            | StackMapFrame::SameLocals1StackItemExtended { stack, .. } => {
+                let text = self.verification_types("stack", &[*stack]);
+                self.line(indent + 2, &text);
+            }
+            StackMapFrame::Append { locals, .. } => {
+                let text = self.verification_types("locals", locals);
+                self.line(indent + 2, &text);
+            }
+            StackMapFrame::Full { locals, stack, .. } => {
+                let text = self.verification_types("locals", locals);
+                self.line(indent + 2, &text);
+                let text = self.verification_types("stack", stack);
+                self.line(indent + 2, &text);
+            }
+            _ => {}
+        }
+    }
+
+    /// e.g. `locals = [ class "[Ljava/lang/String;", int ]`, or `stack = []` when empty
+    fn verification_types(&self, name: &str, types: &[VerificationType]) -> String {
+        if types.is_empty() {
+            return format!("{name} = []");
+        }
+        let types: Vec<String> = types
+            .iter()
+            .map(|t| match t {
+                VerificationType::Top => "top".to_string(),
+                VerificationType::Integer => "int".to_string(),
+                VerificationType::Float => "float".to_string(),
+                VerificationType::Double => "double".to_string(),
+                VerificationType::Long => "long".to_string(),
+                VerificationType::Null => "null".to_string(),
+                VerificationType::UninitializedThis => "this".to_string(),
+                VerificationType::Object(index) => format!("class {}", self.resolve(*index)),
+                VerificationType::Uninitialized(offset) => format!("uninitialized {offset}"),
+            })
+            .collect();
+        format!("{name} = [ {} ]", types.join(", "))
+    }
+
+    /// e.g. `public static #7= #5 of #3;  // Inner=class Outer$Inner of class Outer`
+    fn inner_class(&self, class: &InnerClass) -> String {
+        let flags = class.inner_class_access_flags();
+        let mut words: Vec<&str> = keywords(FlagTarget::InnerClass, flags)
+            .into_iter()
+            .filter(|keyword| match *keyword {
+                "public" | "private" | "protected" | "static" | "final" => true,
+                "abstract" => flags & ACC_INTERFACE == 0,
+                _ => false,
+            })
+            .collect();
+
+        let inner = class.inner_class_info_index();
+        let outer = class.outer_class_info_index();
+        let mut reference = format!("#{inner}");
+        let mut comment = format!("class {}", self.resolve(inner));
+        if class.inner_name_index() != 0 {
+            reference = format!("#{}= {reference}", class.inner_name_index());
+            comment = format!(
+                "{}={comment}",
+                self.utf8_or_invalid(class.inner_name_index())
+            );
+        }
+        if outer != 0 {
+            reference = format!("{reference} of #{outer}");
+            comment = format!("{comment} of class {}", self.resolve(outer));
+        }
+        reference.push(';');
+        words.push(&reference);
+        format!("{:<40}// {comment}", words.join(" "))
+    }
+
+    fn utf8_or_invalid(&self, index: u16) -> String {
+        self.constant_pool
+            .utf8(index)
+            .unwrap_or_else(|_| format!("<invalid #{index}>"))
+    }
+
+    /// The value of an entry as javap's constant pool comments show it, e.g.
+    /// `java/lang/Object."<init>":()V` for a Methodref
+    fn resolve(&self, index: u16) -> String {
+        resolve(self.constant_pool, index).unwrap_or_else(|_| format!("<invalid #{index}>"))
+    }
+
+    /// Like `resolve` but prefixed with the kind of entry, as in instruction comments
+    /// (e.g. `Method java/lang/Object."<init>":()V` or `int 42`)
+    fn describe(&self, index: u16) -> String {
+        let kind = match self.constant_pool.get(index).map(|item| item.data()) {
+            Some(ConstantPoolData::ClassInfo(_)) => "class",
+            Some(ConstantPoolData::String(_)) => "String",
+            Some(ConstantPoolData::Integer(_)) => "int",
+            Some(ConstantPoolData::Float(_)) => "float",
+            Some(ConstantPoolData::Long(_)) => "long",
+            Some(ConstantPoolData::Double(_)) => "double",
+            Some(ConstantPoolData::FieldRef(_)) => "Field",
+            Some(ConstantPoolData::MethodRef(_)) => "Method",
+            Some(ConstantPoolData::InterfaceMethodRef(_)) => "InterfaceMethod",
+            Some(ConstantPoolData::MethodHandle(_)) => "MethodHandle",
+            Some(ConstantPoolData::MethodType(_)) => "MethodType",
+            Some(ConstantPoolData::InvokeDynamic(_)) => "InvokeDynamic",
+            _ => return format!("<invalid #{index}>"),
+        };
+        // members of the class being printed are shown without their owner
+        if let Ok((class, name, descriptor)) = self.constant_pool.member_ref(index) {
+            if self.class_file.class_name().is_ok_and(|this| this == class) {
+                return format!("{kind} {}:{descriptor}", quote_name(&name));
+            }
+        }
+        format!("{kind} {}", self.resolve(index))
+    }
+}
+
+fn resolve(constant_pool: &ConstantPool, index: u16) -> Result<String, io::Error> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid constant pool index {index}"),
+        )
+    };
+    let item = constant_pool.get(index).ok_or_else(invalid)?;
+    Ok(match item.data() {
+        ConstantPoolData::Uninit | ConstantPoolData::Unusable => return Err(invalid()),
+        ConstantPoolData::Utf8(utf8) => escape(&utf8.value()),
+        ConstantPoolData::Integer(integer) => integer.value().to_string(),
+        ConstantPoolData::Float(float) => java_float(float.value() as f64, "f"),
+        ConstantPoolData::Long(long) => format!("{}l", long.value()),
+        ConstantPoolData::Double(double) => java_float(double.value(), "d"),
+        ConstantPoolData::ClassInfo(class) => quote_name(&constant_pool.utf8(class.name_index())?),
+        ConstantPoolData::String(string) => escape(&constant_pool.utf8(string.string_index())?),
+        ConstantPoolData::NameAndType(nat) => format!(
+            "{}:{}",
+            quote_name(&constant_pool.utf8(nat.name_index())?),
+            constant_pool.utf8(nat.descriptor_index())?
+        ),
+        ConstantPoolData::FieldRef(_)
+        | ConstantPoolData::MethodRef(_)
+        | ConstantPoolData::InterfaceMethodRef(_) => {
+            let (class, name, descriptor) = constant_pool.member_ref(index)?;
+            format!("{}.{}:{descriptor}", quote_name(&class), quote_name(&name))
+        }
+        ConstantPoolData::MethodHandle(handle) => format!(
+            "{} {}",
+            reference_kind_name(handle.reference_kind()),
+            resolve(constant_pool, handle.reference_index())?
+        ),
+        ConstantPoolData::MethodType(method_type) => {
+            constant_pool.utf8(method_type.descriptor_index())?
+        }
+        ConstantPoolData::InvokeDynamic(indy) => {
+            let (name, descriptor) = constant_pool.name_and_type(indy.name_and_type_index())?;
+            format!(
+                "#{}:{}:{descriptor}",
+                indy.bootstrap_method_attr_index(),
+                quote_name(&name)
+            )
+        }
+    })
+}
+
+fn reference_kind_name(kind: ReferenceKind) -> &'static str {
+    match kind {
+        ReferenceKind::Uninit => "REF_invalid",
+        ReferenceKind::GetField => "REF_getField",
+        ReferenceKind::GetStatic => "REF_getStatic",
+        ReferenceKind::PutField => "REF_putField",
+        ReferenceKind::PutStatic => "REF_putStatic",
+        ReferenceKind::InvokeVirtual => "REF_invokeVirtual",
+        ReferenceKind::InvokeStatic => "REF_invokeStatic",
+        ReferenceKind::InvokeSpecial => "REF_invokeSpecial",
+        ReferenceKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+        ReferenceKind::InvokeInterface => "REF_invokeInterface",
+    }
+}
+
+/// javap quotes names that aren't plain identifiers, such as `"<init>"` or `"[I"`
+fn quote_name(name: &str) -> String {
+    let plain = name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '/');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{name}\"")
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats a float or double like Java's `toString`, plus javap's `f`/`d` suffix
+fn java_float(value: f64, suffix: &str) -> String {
+    if value.is_nan() {
+        format!("NaN{suffix}")
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("{sign}Infinity{suffix}")
+    } else if suffix == "f" {
+        format!("{:?}{suffix}", value as f32)
+    } else {
+        format!("{value:?}{suffix}")
+    }
+}
+
+fn flags_line(target: FlagTarget, flags: u16) -> String {
+    format!(
+        "flags: (0x{flags:04x}) {}",
+        spec_names(target, flags).join(", ")
+    )
+}
+
+/// e.g. `public class Foo extends Bar implements Baz`
+fn class_declaration(class_file: &ClassFile) -> Result<String, io::Error> {
+    let flags = class_file.access_flags();
+    let interface = flags & ACC_INTERFACE != 0;
+    let mut words: Vec<String> = keywords(FlagTarget::Class, flags)
+        .into_iter()
+        .filter(|keyword| match *keyword {
+            "public" | "final" => true,
+            "abstract" => !interface,
+            _ => false,
+        })
+        .map(str::to_string)
+        .collect();
+    words.push(if interface { "interface" } else { "class" }.to_string());
+    words.push(class_file.class_name()?.replace('/', "."));
+
+    if let Some(super_class) = class_file.super_class_name()? {
+        if super_class != "java/lang/Object" {
+            words.push(format!("extends {}", super_class.replace('/', ".")));
+        }
+    }
+    let interfaces: Vec<String> = class_file
+        .interface_names()?
+        .iter()
+        .map(|name| name.replace('/', "."))
+        .collect();
+    if !interfaces.is_empty() {
+        let keyword = if interface { "extends" } else { "implements" };
+        words.push(format!("{keyword} {}", interfaces.join(",")));
+    }
+    Ok(words.join(" "))
+}
+
+/// e.g. `public static void main(java.lang.String[]) throws java.io.IOException`
+fn member_declaration(
+    class_file: &ClassFile,
+    member: &MemberInfo,
+    target: FlagTarget,
+) -> Result<String, io::Error> {
+    let constant_pool = class_file.constant_pool();
+    let flags = member.access_flags();
+    let interface = class_file.access_flags() & ACC_INTERFACE != 0;
+    let mut words: Vec<&str> = keywords(target, flags)
+        .into_iter()
+        .filter(|keyword| !matches!(*keyword, "bridge" | "varargs" | "synthetic" | "enum"))
+        .map(|keyword| {
+            if keyword == "strict" {
+                "strictfp"
+            } else {
+                keyword
+            }
+        })
+        .collect();
+    // instance methods with a body in an interface are default methods
+    if target == FlagTarget::Method && interface && flags & (ACC_ABSTRACT | ACC_STATIC) == 0 {
+        words.push("default");
+    }
+    let name = member.name(constant_pool)?;
+    let descriptor = member.descriptor(constant_pool)?;
+
+    if target == FlagTarget::Field {
+        let field_type = FieldType::parse(&descriptor)?.java_name();
+        words.push(&field_type);
+        return Ok(format!("{} {name}", words.join(" ")));
+    }
+
+    if name == "<clinit>" {
+        return Ok("static {}".to_string());
+    }
+    let descriptor = MethodDescriptor::parse(&descriptor)?;
+    let mut parameters: Vec<String> = descriptor
+        .parameters()
+        .iter()
+        .map(|parameter| parameter.java_name())
+        .collect();
+    if flags & ACC_VARARGS != 0 {
+        if let Some(last) = parameters.last_mut() {
+            if let Some(component) = last.strip_suffix("[]") {
+                *last = format!("{component}...");
+            }
+        }
+    }
+    let signature = if name == "<init>" {
+        format!(
+            "{}({})",
+            class_file.class_name()?.replace('/', "."),
+            parameters.join(", ")
+        )
+    } else {
+        let return_type = match descriptor.return_type() {
+            Some(return_type) => return_type.java_name(),
+            None => "void".to_string(),
+        };
+        format!("{return_type} {name}({})", parameters.join(", "))
+    };
+    words.push(&signature);
+
+    let mut declaration = words.join(" ");
+    if let Some(attribute) = find_attribute(member.attributes(), constant_pool, Exceptions::NAME) {
+        let exceptions = Exceptions::from(&mut attribute.reader())?;
+        let names = exceptions
+            .exception_index_table()
+            .iter()
+            .map(|index| Ok(constant_pool.class_name(*index)?.replace('/', ".")))
+            .collect::<Result<Vec<String>, io::Error>>()?;
+        declaration.push_str(&format!(" throws {}", names.join(", ")));
+    }
+    Ok(declaration)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    fn hello_world() -> ClassFile {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/helloworld/HelloWorld.class")
+            .unwrap();
+        class_file
+    }
+
+    #[test]
+    fn verbose_matches_javap() {
+        let output = javap(&hello_world(), JavapOptions::new(true, false)).unwrap();
+        let expected = [
+            "  Compiled from \"HelloWorld.java\"",
+            "public class HelloWorld",
+            "  flags: (0x0021) ACC_PUBLIC, ACC_SUPER",
+            "  this_class: #5                          // HelloWorld",
+            "  super_class: #6                         // java/lang/Object",
+            "  interfaces: 0, fields: 0, methods: 2, attributes: 1",
+            "   #1 = Methodref          #6.#15         // java/lang/Object.\"<init>\":()V",
+            "   #3 = String             #18            // Hello, World!",
+            "  #14 = Utf8               HelloWorld.java",
+            "  #15 = NameAndType        #7:#8          // \"<init>\":()V",
+            "  public static void main(java.lang.String[]);",
+            "    descriptor: ([Ljava/lang/String;)V",
+            "    flags: (0x0009) ACC_PUBLIC, ACC_STATIC",
+            "      stack=2, locals=1, args_size=1",
+            "         0: getstatic     #2                  // Field java/lang/System.out:Ljava/io/PrintStream;",
+            "         3: ldc           #3                  // String Hello, World!",
+            "        line 4: 8",
+            "SourceFile: \"HelloWorld.java\"",
+        ];
+        let lines: Vec<&str> = output.lines().collect();
+        for line in expected {
+            assert!(lines.contains(&line), "missing {line:?} in\n{output}");
+        }
+    }
+
+    #[test]
+    fn local_variable_tables_match_javap() {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/deadstore/DeadStore.class")
+            .unwrap();
+        let output = javap(&class_file, JavapOptions::new(true, false)).unwrap();
+        let expected = [
+            "        Start  Length  Slot  Name   Signature",
+            "            0      15     0     a   I",
+            "            4      11     2 total   I",
+            // names longer than the column still get a space before them
+            "           13       2     4 unused   J",
+        ];
+        let lines: Vec<&str> = output.lines().collect();
+        for line in expected {
+            assert!(lines.contains(&line), "missing {line:?} in\n{output}");
+        }
+    }
+
+    #[test]
+    fn code_only() {
+        let output = javap(&hello_world(), JavapOptions::new(false, true)).unwrap();
+        assert!(
+            output.starts_with("Compiled from \"HelloWorld.java\"\npublic class HelloWorld {\n")
+        );
+        assert!(output.contains("       8: return\n"));
+        assert!(!output.contains("Constant pool:"));
+        assert!(output.ends_with("}\n"));
+
+        let declarations = javap(&hello_world(), JavapOptions::new(false, false)).unwrap();
+        assert!(!declarations.contains("Code:"));
+    }
+
+    #[test]
+    fn switches_wide_and_exception_tables() {
+        let source = r#"
+.class public abstract Switch
+.implements java/lang/Runnable
+.method public static varargs f([I)I
+    .throws java/io/IOException
+Start:
+    iinc 300 1
+    iload_0
+    lookupswitch
+        -1 : Start
+        default : End
+End:
+    iconst_0
+    ireturn
+    .catch all from Start to End using End
+.end method
+.method public abstract g()V
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let output = javap(&class_file, JavapOptions::new(true, false)).unwrap();
+        let expected = [
+            "public abstract class Switch implements java.lang.Runnable",
+            "  public static int f(int...) throws java.io.IOException;",
+            "         0: wide iinc     300, 1",
+            "         7: lookupswitch  { // 1",
+            "                      -1: 0",
+            "                 default: 24",
+            "            }",
+            "        24: iconst_0",
+            "             0    24    24   any",
+            "      throws java.io.IOException",
+            "  public abstract void g();",
+        ];
+        let lines: Vec<&str> = output.lines().collect();
+        for line in expected {
+            assert!(lines.contains(&line), "missing {line:?} in\n{output}");
+        }
+    }
+
+    #[test]
+    fn malformed_code_and_references() {
+        let source = r#"
+.class Broken
+.method static f()V
+    ldc #200
+    .bytes cb
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let output = javap(&class_file, JavapOptions::new(false, true)).unwrap();
+        assert!(output.contains("// <invalid #200>"));
+        assert!(output.contains("2: <unknown opcode 0xcb at pc 2>"));
+    }
+}
@@ -0,0 +1,884 @@
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::{spec_names, FlagTarget};
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::bootstrap_methods::{BootstrapMethod, BootstrapMethods};
+use crate::class_file::attribute::code::{Code, ExceptionTableEntry};
+use crate::class_file::attribute::constant_value::ConstantValue;
+use crate::class_file::attribute::exceptions::Exceptions;
+use crate::class_file::attribute::inner_classes::{InnerClass, InnerClasses};
+use crate::class_file::attribute::line_number_table::{LineNumber, LineNumberTable};
+use crate::class_file::attribute::local_variable_table::{LocalVariable, LocalVariableTable};
+use crate::class_file::attribute::source_file::SourceFile;
+use crate::class_file::class_file::{ClassFile, MAGIC_NUMBER};
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
+use crate::class_file::constant_pool::types::class_info::ClassInfo;
+use crate::class_file::constant_pool::types::double::Double;
+use crate::class_file::constant_pool::types::field_ref::FieldRef;
+use crate::class_file::constant_pool::types::float::Float;
+use crate::class_file::constant_pool::types::integer::Integer;
+use crate::class_file::constant_pool::types::interface_method_ref::InterfaceMethodRef;
+use crate::class_file::constant_pool::types::invoke_dynamic::InvokeDynamic;
+use crate::class_file::constant_pool::types::jvm_string::JvmString;
+use crate::class_file::constant_pool::types::long::Long;
+use crate::class_file::constant_pool::types::method_handle::{MethodHandle, ReferenceKind};
+use crate::class_file::constant_pool::types::method_ref::MethodRef;
+use crate::class_file::constant_pool::types::method_type::MethodType;
+use crate::class_file::constant_pool::types::name_and_type::NameAndType;
+use crate::class_file::constant_pool::types::utf8::Utf8;
+use crate::class_file::member_info::MemberInfo;
+use crate::util::json::{json_error, JsonValue};
+use std::io;
+
+// The JSON form mirrors the class file structure (chapter 4) field by field, using the
+// specification's names. Fields named `resolved`, `flags`, `name` (next to a `name_index`)
+// and `instructions` are derived from the rest and ignored when loading; everything else is
+// needed to rebuild the exact same bytes.
+
+/// Converts a class file into its JSON form
+pub fn to_json(class_file: &ClassFile) -> JsonValue {
+    let constant_pool = class_file.constant_pool();
+    let interfaces: Vec<JsonValue> = class_file
+        .interfaces()
+        .iter()
+        .map(|index| class_reference(constant_pool, *index))
+        .collect();
+
+    JsonValue::object()
+        .with("magic", class_file.magic_number().into())
+        .with("minor_version", class_file.minor_version().into())
+        .with("major_version", class_file.major_version().into())
+        .with("constant_pool", constant_pool_json(constant_pool))
+        .with("access_flags", class_file.access_flags().into())
+        .with(
+            "flags",
+            spec_names(FlagTarget::Class, class_file.access_flags()).into(),
+        )
+        .with(
+            "this_class",
+            class_reference(constant_pool, class_file.this_class()),
+        )
+        .with(
+            "super_class",
+            class_reference(constant_pool, class_file.super_class()),
+        )
+        .with("interfaces", JsonValue::Array(interfaces))
+        .with(
+            "fields",
+            members_json(class_file.fields(), constant_pool, FlagTarget::Field),
+        )
+        .with(
+            "methods",
+            members_json(class_file.methods(), constant_pool, FlagTarget::Method),
+        )
+        .with(
+            "attributes",
+            attributes_json(class_file.attributes(), constant_pool),
+        )
+}
+
+/// Rebuilds a class file from the JSON produced by `to_json`
+pub fn from_json(json: &JsonValue) -> Result<ClassFile, io::Error> {
+    let magic = json.integer_field::<u32>("magic")?;
+    if magic != MAGIC_NUMBER {
+        return Err(json_error(&format!("invalid magic number 0x{magic:X}")));
+    }
+
+    let mut class_file = ClassFile::new();
+    class_file.set_version(
+        json.integer_field("major_version")?,
+        json.integer_field("minor_version")?,
+    );
+    class_file.set_constant_pool(constant_pool_from_json(json.array_field("constant_pool")?)?);
+    class_file.set_access_flags(json.integer_field("access_flags")?);
+    class_file.set_this_class(json.field("this_class")?.integer_field("index")?);
+    class_file.set_super_class(json.field("super_class")?.integer_field("index")?);
+    for interface in json.array_field("interfaces")? {
+        class_file
+            .interfaces_mut()
+            .push(interface.integer_field("index")?);
+    }
+    *class_file.fields_mut() = members_from_json(json.array_field("fields")?)?;
+    *class_file.methods_mut() = members_from_json(json.array_field("methods")?)?;
+    *class_file.attributes_mut() = attributes_from_json(json.array_field("attributes")?)?;
+    Ok(class_file)
+}
+
+fn class_reference(constant_pool: &ConstantPool, index: u16) -> JsonValue {
+    let reference = JsonValue::object().with("index", index.into());
+    match constant_pool.class_name(index) {
+        Ok(name) => reference.with("resolved", name.into()),
+        Err(_) => reference,
+    }
+}
+
+fn hex(bytes: &[u8]) -> JsonValue {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>()
+        .into()
+}
+
+fn from_hex(json: &JsonValue, key: &str) -> Result<Vec<u8>, io::Error> {
+    let text = json.str_field(key)?;
+    if text.len() % 2 != 0 || !text.is_ascii() {
+        return Err(json_error(&format!(
+            "field '{key}' must be an even number of hex digits"
+        )));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&text[i..i + 2], 16)
+                .map_err(|_| json_error(&format!("field '{key}' contains invalid hex")))
+        })
+        .collect()
+}
+
+fn constant_pool_json(constant_pool: &ConstantPool) -> JsonValue {
+    let mut entries = Vec::new();
+    for (i, item) in constant_pool.items().iter().enumerate() {
+        let index = (i + 1) as u16;
+        let entry = JsonValue::object()
+            .with("index", index.into())
+            .with("tag", item.tag().name().into());
+        let entry = match item.data() {
+            ConstantPoolData::Uninit | ConstantPoolData::Unusable => continue,
+            ConstantPoolData::Utf8(utf8) => {
+                let value = utf8.value();
+                let entry = entry.with("value", value.clone().into());
+                // keep the raw bytes when the value alone wouldn't encode back to them
+                if !Utf8::from_string(&value).is_ok_and(|encoded| encoded.bytes() == utf8.bytes()) {
+                    entry.with("bytes", hex(utf8.bytes()))
+                } else {
+                    entry
+                }
+            }
+            ConstantPoolData::Integer(integer) => entry.with("value", integer.value().into()),
+            ConstantPoolData::Float(float) => entry
+                .with("value", (float.value() as f64).into())
+                .with("bits", format!("0x{:08x}", float.bytes()).into()),
+            ConstantPoolData::Long(long) => entry.with("value", long.value().into()),
+            ConstantPoolData::Double(double) => entry.with("value", double.value().into()).with(
+                "bits",
+                format!("0x{:08x}{:08x}", double.high_bytes(), double.low_bytes()).into(),
+            ),
+            ConstantPoolData::ClassInfo(class) => {
+                entry.with("name_index", class.name_index().into())
+            }
+            ConstantPoolData::String(string) => {
+                entry.with("string_index", string.string_index().into())
+            }
+            ConstantPoolData::FieldRef(r) => entry
+                .with("class_index", r.class_index().into())
+                .with("name_and_type_index", r.name_and_type_index().into()),
+            ConstantPoolData::MethodRef(r) => entry
+                .with("class_index", r.class_index().into())
+                .with("name_and_type_index", r.name_and_type_index().into()),
+            ConstantPoolData::InterfaceMethodRef(r) => entry
+                .with("class_index", r.class_index().into())
+                .with("name_and_type_index", r.name_and_type_index().into()),
+            ConstantPoolData::NameAndType(nat) => entry
+                .with("name_index", nat.name_index().into())
+                .with("descriptor_index", nat.descriptor_index().into()),
+            ConstantPoolData::MethodHandle(handle) => entry
+                .with("reference_kind", (handle.reference_kind() as i64).into())
+                .with("reference_index", handle.reference_index().into()),
+            ConstantPoolData::MethodType(method_type) => {
+                entry.with("descriptor_index", method_type.descriptor_index().into())
+            }
+            ConstantPoolData::InvokeDynamic(indy) => entry
+                .with(
+                    "bootstrap_method_attr_index",
+                    indy.bootstrap_method_attr_index().into(),
+                )
+                .with("name_and_type_index", indy.name_and_type_index().into()),
+        };
+        let entry = match resolve(constant_pool, index) {
+            Some(resolved) => entry.with("resolved", resolved),
+            None => entry,
+        };
+        entries.push(entry);
+    }
+    JsonValue::Array(entries)
+}
+
+/// What an entry refers to, or None for entries that are values themselves or don't resolve
+fn resolve(constant_pool: &ConstantPool, index: u16) -> Option<JsonValue> {
+    let item = constant_pool.get(index)?;
+    Some(match item.data() {
+        ConstantPoolData::ClassInfo(_) => constant_pool.class_name(index).ok()?.into(),
+        ConstantPoolData::String(string) => constant_pool.utf8(string.string_index()).ok()?.into(),
+        ConstantPoolData::MethodType(method_type) => constant_pool
+            .utf8(method_type.descriptor_index())
+            .ok()?
+            .into(),
+        ConstantPoolData::FieldRef(_)
+        | ConstantPoolData::MethodRef(_)
+        | ConstantPoolData::InterfaceMethodRef(_) => {
+            let (class, name, descriptor) = constant_pool.member_ref(index).ok()?;
+            JsonValue::object()
+                .with("class", class.into())
+                .with("name", name.into())
+                .with("descriptor", descriptor.into())
+        }
+        ConstantPoolData::NameAndType(_) => {
+            let (name, descriptor) = constant_pool.name_and_type(index).ok()?;
+            JsonValue::object()
+                .with("name", name.into())
+                .with("descriptor", descriptor.into())
+        }
+        ConstantPoolData::InvokeDynamic(indy) => {
+            let (name, descriptor) = constant_pool
+                .name_and_type(indy.name_and_type_index())
+                .ok()?;
+            JsonValue::object()
+                .with("name", name.into())
+                .with("descriptor", descriptor.into())
+        }
+        ConstantPoolData::MethodHandle(handle) => resolve(constant_pool, handle.reference_index())?,
+        _ => return None,
+    })
+}
+
+fn constant_pool_from_json(entries: &[JsonValue]) -> Result<ConstantPool, io::Error> {
+    let mut constant_pool = ConstantPool::new();
+    for entry in entries {
+        let tag = entry.str_field("tag")?;
+        let u16_field = |key: &str| entry.integer_field::<u16>(key);
+        let data = match tag {
+            "Utf8" => match entry.get("bytes") {
+                Some(_) => ConstantPoolData::Utf8(Utf8::from_bytes(from_hex(entry, "bytes")?)?),
+                None => ConstantPoolData::Utf8(Utf8::from_string(entry.str_field("value")?)?),
+            },
+            "Integer" => {
+                ConstantPoolData::Integer(Integer::from_value(entry.integer_field("value")?))
+            }
+            "Float" => {
+                let bits = parse_bits(entry)? as u32;
+                ConstantPoolData::Float(Float::from_value(f32::from_bits(bits)))
+            }
+            "Long" => ConstantPoolData::Long(Long::from_value(entry.integer_field("value")?)),
+            "Double" => {
+                let bits = parse_bits(entry)?;
+                ConstantPoolData::Double(Double::from_value(f64::from_bits(bits)))
+            }
+            "Class" => {
+                ConstantPoolData::ClassInfo(ClassInfo::from_name_index(u16_field("name_index")?))
+            }
+            "String" => {
+                ConstantPoolData::String(JvmString::from_string_index(u16_field("string_index")?))
+            }
+            "Fieldref" => ConstantPoolData::FieldRef(FieldRef::from_indices(
+                u16_field("class_index")?,
+                u16_field("name_and_type_index")?,
+            )),
+            "Methodref" => ConstantPoolData::MethodRef(MethodRef::from_indices(
+                u16_field("class_index")?,
+                u16_field("name_and_type_index")?,
+            )),
+            "InterfaceMethodref" => {
+                ConstantPoolData::InterfaceMethodRef(InterfaceMethodRef::from_indices(
+                    u16_field("class_index")?,
+                    u16_field("name_and_type_index")?,
+                ))
+            }
+            "NameAndType" => ConstantPoolData::NameAndType(NameAndType::from_indices(
+                u16_field("name_index")?,
+                u16_field("descriptor_index")?,
+            )),
+            "MethodHandle" => ConstantPoolData::MethodHandle(MethodHandle::from_reference(
+                ReferenceKind::from_u8(entry.integer_field("reference_kind")?),
+                u16_field("reference_index")?,
+            )),
+            "MethodType" => ConstantPoolData::MethodType(MethodType::from_descriptor_index(
+                u16_field("descriptor_index")?,
+            )),
+            "InvokeDynamic" => ConstantPoolData::InvokeDynamic(InvokeDynamic::from_indices(
+                u16_field("bootstrap_method_attr_index")?,
+                u16_field("name_and_type_index")?,
+            )),
+            other => return Err(json_error(&format!("unknown constant pool tag '{other}'"))),
+        };
+
+        let expected = u16_field("index")?;
+        let index = constant_pool.push_item(ConstantPoolItem::from_data(data))?;
+        if index != expected {
+            return Err(json_error(&format!(
+                "constant pool entry {expected} would be placed at index {index}"
+            )));
+        }
+    }
+    Ok(constant_pool)
+}
+
+/// The exact bits of a Float or Double, written as a `0x` hex string
+fn parse_bits(entry: &JsonValue) -> Result<u64, io::Error> {
+    let bits = entry.str_field("bits")?;
+    bits.strip_prefix("0x")
+        .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+        .ok_or_else(|| json_error(&format!("invalid bits '{bits}'")))
+}
+
+fn members_json(
+    members: &[MemberInfo],
+    constant_pool: &ConstantPool,
+    target: FlagTarget,
+) -> JsonValue {
+    let members: Vec<JsonValue> = members
+        .iter()
+        .map(|member| {
+            let json = JsonValue::object()
+                .with("access_flags", member.access_flags().into())
+                .with("flags", spec_names(target, member.access_flags()).into())
+                .with("name_index", member.name_index().into());
+            let json = match member.name(constant_pool) {
+                Ok(name) => json.with("name", name.into()),
+                Err(_) => json,
+            };
+            let json = json.with("descriptor_index", member.descriptor_index().into());
+            let json = match member.descriptor(constant_pool) {
+                Ok(descriptor) => json.with("descriptor", descriptor.into()),
+                Err(_) => json,
+            };
+            json.with(
+                "attributes",
+                attributes_json(member.attributes(), constant_pool),
+            )
+        })
+        .collect();
+    JsonValue::Array(members)
+}
+
+fn members_from_json(members: &[JsonValue]) -> Result<Vec<MemberInfo>, io::Error> {
+    members
+        .iter()
+        .map(|member| {
+            Ok(MemberInfo::new(
+                member.integer_field("access_flags")?,
+                member.integer_field("name_index")?,
+                member.integer_field("descriptor_index")?,
+                attributes_from_json(member.array_field("attributes")?)?,
+            ))
+        })
+        .collect()
+}
+
+fn attributes_json(attributes: &[AttributeInfo], constant_pool: &ConstantPool) -> JsonValue {
+    JsonValue::Array(
+        attributes
+            .iter()
+            .map(|attribute| attribute_json(attribute, constant_pool))
+            .collect(),
+    )
+}
+
+fn attribute_json(attribute: &AttributeInfo, constant_pool: &ConstantPool) -> JsonValue {
+    let json = JsonValue::object().with("name_index", attribute.attribute_name_index().into());
+    let name = match attribute.name(constant_pool) {
+        Ok(name) => name,
+        Err(_) => return json.with("info", hex(attribute.info())),
+    };
+    let json = json.with("name", name.clone().into());
+
+    // attributes that don't decode, or don't encode back to the same bytes, are kept raw
+    match decode_attribute(&name, attribute, constant_pool) {
+        Some((fields, bytes)) if bytes == attribute.info() => match fields {
+            JsonValue::Object(entries) => entries
+                .into_iter()
+                .fold(json, |json, (key, value)| json.with(&key, value)),
+            _ => json,
+        },
+        _ => json.with("info", hex(attribute.info())),
+    }
+}
+
+/// Decodes a known attribute into its JSON fields, along with the bytes they encode to
+fn decode_attribute(
+    name: &str,
+    attribute: &AttributeInfo,
+    constant_pool: &ConstantPool,
+) -> Option<(JsonValue, Vec<u8>)> {
+    let mut reader = attribute.reader();
+    let mut bytes = Vec::new();
+    let json = match name {
+        Code::NAME => {
+            let code = Code::from(&mut reader).ok()?;
+            code.write(&mut bytes);
+            let exception_table: Vec<JsonValue> = code
+                .exception_table()
+                .iter()
+                .map(|entry| {
+                    JsonValue::object()
+                        .with("start_pc", entry.start_pc().into())
+                        .with("end_pc", entry.end_pc().into())
+                        .with("handler_pc", entry.handler_pc().into())
+                        .with("catch_type", entry.catch_type().into())
+                })
+                .collect();
+            JsonValue::object()
+                .with("max_stack", code.max_stack().into())
+                .with("max_locals", code.max_locals().into())
+                .with("code", hex(code.code()))
+                .with(
+                    "instructions",
+                    instructions_json(code.code(), constant_pool),
+                )
+                .with("exception_table", JsonValue::Array(exception_table))
+                .with(
+                    "attributes",
+                    attributes_json(code.attributes(), constant_pool),
+                )
+        }
+        ConstantValue::NAME => {
+            let value = ConstantValue::from(&mut reader).ok()?;
+            value.write(&mut bytes);
+            let json =
+                JsonValue::object().with("constantvalue_index", value.constantvalue_index().into());
+            match constant_pool
+                .get(value.constantvalue_index())
+                .map(|item| item.data())
+            {
+                Some(ConstantPoolData::Integer(v)) => json.with("resolved", v.value().into()),
+                Some(ConstantPoolData::Long(v)) => json.with("resolved", v.value().into()),
+                Some(ConstantPoolData::Float(v)) => {
+                    json.with("resolved", (v.value() as f64).into())
+                }
+                Some(ConstantPoolData::Double(v)) => json.with("resolved", v.value().into()),
+                _ => match resolve(constant_pool, value.constantvalue_index()) {
+                    Some(resolved) => json.with("resolved", resolved),
+                    None => json,
+                },
+            }
+        }
+        SourceFile::NAME => {
+            let source_file = SourceFile::from(&mut reader).ok()?;
+            source_file.write(&mut bytes);
+            let json =
+                JsonValue::object().with("sourcefile_index", source_file.sourcefile_index().into());
+            match constant_pool.utf8(source_file.sourcefile_index()) {
+                Ok(file_name) => json.with("resolved", file_name.into()),
+                Err(_) => json,
+            }
+        }
+        Exceptions::NAME => {
+            let exceptions = Exceptions::from(&mut reader).ok()?;
+            exceptions.write(&mut bytes);
+            let table: Vec<JsonValue> = exceptions
+                .exception_index_table()
+                .iter()
+                .map(|index| class_reference(constant_pool, *index))
+                .collect();
+            JsonValue::object().with("exception_index_table", JsonValue::Array(table))
+        }
+        LineNumberTable::NAME => {
+            let table = LineNumberTable::from(&mut reader).ok()?;
+            table.write(&mut bytes);
+            let entries: Vec<JsonValue> = table
+                .line_number_table()
+                .iter()
+                .map(|entry| {
+                    JsonValue::object()
+                        .with("start_pc", entry.start_pc().into())
+                        .with("line_number", entry.line_number().into())
+                })
+                .collect();
+            JsonValue::object().with("line_number_table", JsonValue::Array(entries))
+        }
+        LocalVariableTable::NAME | LocalVariableTable::TYPE_TABLE_NAME => {
+            let table = LocalVariableTable::from(&mut reader).ok()?;
+            table.write(&mut bytes);
+            let entries: Vec<JsonValue> = table
+                .local_variable_table()
+                .iter()
+                .map(|variable| {
+                    let json = JsonValue::object()
+                        .with("start_pc", variable.start_pc().into())
+                        .with("length", variable.length().into())
+                        .with("name_index", variable.name_index().into())
+                        .with("descriptor_index", variable.descriptor_index().into())
+                        .with("index", variable.index().into());
+                    match (
+                        constant_pool.utf8(variable.name_index()),
+                        constant_pool.utf8(variable.descriptor_index()),
+                    ) {
+                        (Ok(name), Ok(descriptor)) => json.with(
+                            "resolved",
+                            JsonValue::object()
+                                .with("name", name.into())
+                                .with("descriptor", descriptor.into()),
+                        ),
+                        _ => json,
+                    }
+                })
+                .collect();
+            JsonValue::object().with("local_variable_table", JsonValue::Array(entries))
+        }
+        BootstrapMethods::NAME => {
+            let bootstrap_methods = BootstrapMethods::from(&mut reader).ok()?;
+            bootstrap_methods.write(&mut bytes);
+            let methods: Vec<JsonValue> = bootstrap_methods
+                .bootstrap_methods()
+                .iter()
+                .map(|method| {
+                    JsonValue::object()
+                        .with("bootstrap_method_ref", method.bootstrap_method_ref().into())
+                        .with(
+                            "bootstrap_arguments",
+                            method.bootstrap_arguments().to_vec().into(),
+                        )
+                })
+                .collect();
+            JsonValue::object().with("bootstrap_methods", JsonValue::Array(methods))
+        }
+        InnerClasses::NAME => {
+            let inner_classes = InnerClasses::from(&mut reader).ok()?;
+            inner_classes.write(&mut bytes);
+            let classes: Vec<JsonValue> = inner_classes
+                .classes()
+                .iter()
+                .map(|class| {
+                    JsonValue::object()
+                        .with(
+                            "inner_class_info_index",
+                            class.inner_class_info_index().into(),
+                        )
+                        .with(
+                            "outer_class_info_index",
+                            class.outer_class_info_index().into(),
+                        )
+                        .with("inner_name_index", class.inner_name_index().into())
+                        .with(
+                            "inner_class_access_flags",
+                            class.inner_class_access_flags().into(),
+                        )
+                        .with(
+                            "flags",
+                            spec_names(FlagTarget::InnerClass, class.inner_class_access_flags())
+                                .into(),
+                        )
+                })
+                .collect();
+            JsonValue::object().with("classes", JsonValue::Array(classes))
+        }
+        _ => return None,
+    };
+    Some((json, bytes))
+}
+
+fn attributes_from_json(attributes: &[JsonValue]) -> Result<Vec<AttributeInfo>, io::Error> {
+    attributes.iter().map(attribute_from_json).collect()
+}
+
+fn attribute_from_json(json: &JsonValue) -> Result<AttributeInfo, io::Error> {
+    let name_index = json.integer_field("name_index")?;
+    if json.get("info").is_some() {
+        return Ok(AttributeInfo::new(name_index, from_hex(json, "info")?));
+    }
+
+    let mut info = Vec::new();
+    match json.str_field("name")? {
+        Code::NAME => {
+            let exception_table = json
+                .array_field("exception_table")?
+                .iter()
+                .map(|entry| {
+                    Ok(ExceptionTableEntry::new(
+                        entry.integer_field("start_pc")?,
+                        entry.integer_field("end_pc")?,
+                        entry.integer_field("handler_pc")?,
+                        entry.integer_field("catch_type")?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()?;
+            Code::new(
+                json.integer_field("max_stack")?,
+                json.integer_field("max_locals")?,
+                from_hex(json, "code")?,
+                exception_table,
+                attributes_from_json(json.array_field("attributes")?)?,
+            )
+            .write(&mut info);
+        }
+        ConstantValue::NAME => {
+            ConstantValue::new(json.integer_field("constantvalue_index")?).write(&mut info)
+        }
+        SourceFile::NAME => {
+            SourceFile::new(json.integer_field("sourcefile_index")?).write(&mut info)
+        }
+        Exceptions::NAME => {
+            let table = json
+                .array_field("exception_index_table")?
+                .iter()
+                .map(|class| class.integer_field("index"))
+                .collect::<Result<Vec<u16>, io::Error>>()?;
+            Exceptions::new(table).write(&mut info);
+        }
+        LineNumberTable::NAME => {
+            let table = json
+                .array_field("line_number_table")?
+                .iter()
+                .map(|entry| {
+                    Ok(LineNumber::new(
+                        entry.integer_field("start_pc")?,
+                        entry.integer_field("line_number")?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()?;
+            LineNumberTable::new(table).write(&mut info);
+        }
+        LocalVariableTable::NAME | LocalVariableTable::TYPE_TABLE_NAME => {
+            let table = json
+                .array_field("local_variable_table")?
+                .iter()
+                .map(|entry| {
+                    Ok(LocalVariable::new(
+                        entry.integer_field("start_pc")?,
+                        entry.integer_field("length")?,
+                        entry.integer_field("name_index")?,
+                        entry.integer_field("descriptor_index")?,
+                        entry.integer_field("index")?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()?;
+            LocalVariableTable::new(table).write(&mut info);
+        }
+        BootstrapMethods::NAME => {
+            let methods = json
+                .array_field("bootstrap_methods")?
+                .iter()
+                .map(|method| {
+                    let arguments = method
+                        .array_field("bootstrap_arguments")?
+                        .iter()
+                        .map(|argument| {
+                            argument
+                                .as_i64()
+                                .and_then(|index| u16::try_from(index).ok())
+                                .ok_or_else(|| json_error("invalid bootstrap argument index"))
+                        })
+                        .collect::<Result<Vec<u16>, io::Error>>()?;
+                    Ok(BootstrapMethod::new(
+                        method.integer_field("bootstrap_method_ref")?,
+                        arguments,
+                    ))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()?;
+            BootstrapMethods::new(methods).write(&mut info);
+        }
+        InnerClasses::NAME => {
+            let classes = json
+                .array_field("classes")?
+                .iter()
+                .map(|class| {
+                    Ok(InnerClass::new(
+                        class.integer_field("inner_class_info_index")?,
+                        class.integer_field("outer_class_info_index")?,
+                        class.integer_field("inner_name_index")?,
+                        class.integer_field("inner_class_access_flags")?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, io::Error>>()?;
+            InnerClasses::new(classes).write(&mut info);
+        }
+        other => {
+            return Err(json_error(&format!(
+                "attribute '{other}' needs an 'info' field"
+            )))
+        }
+    }
+    Ok(AttributeInfo::new(name_index, info))
+}
+
+/// Decoded instructions for reading; the `code` hex string is what gets loaded back
+fn instructions_json(code: &[u8], constant_pool: &ConstantPool) -> JsonValue {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        match Instruction::decode(code, pc) {
+            Ok((instruction, length)) => {
+                instructions.push(instruction_json(&instruction, pc, length, constant_pool));
+                pc += length;
+            }
+            Err(error) => {
+                instructions.push(
+                    JsonValue::object()
+                        .with("pc", pc.into())
+                        .with("error", error.to_string().into()),
+                );
+                break;
+            }
+        }
+    }
+    JsonValue::Array(instructions)
+}
+
+fn instruction_json(
+    instruction: &Instruction,
+    pc: usize,
+    length: usize,
+    constant_pool: &ConstantPool,
+) -> JsonValue {
+    let json = JsonValue::object()
+        .with("pc", pc.into())
+        .with("opcode", instruction.mnemonic().into());
+    // the decoder folds `wide` into the instruction; only its length tells
+    let json = if instruction.local_index().is_some() && length >= 4 {
+        json.with("wide", true.into())
+    } else {
+        json
+    };
+
+    let json = match instruction.constant_index() {
+        Some(index) => {
+            let json = json.with("constant", index.into());
+            match resolve(constant_pool, index) {
+                Some(resolved) => json.with("resolved", resolved),
+                None => json,
+            }
+        }
+        None => json,
+    };
+    match instruction {
+        Instruction::Bipush(value) => json.with("value", (*value as i64).into()),
+        Instruction::Sipush(value) => json.with("value", (*value as i64).into()),
+        Instruction::Iinc { index, increment } => json
+            .with("index", (*index).into())
+            .with("increment", (*increment as i64).into()),
+        Instruction::Invokeinterface { count, .. } => json.with("count", (*count as i64).into()),
+        Instruction::Multianewarray { dimensions, .. } => {
+            json.with("dimensions", (*dimensions as i64).into())
+        }
+        Instruction::Newarray(array_type) => json.with("type", array_type.name().into()),
+        Instruction::Tableswitch {
+            default,
+            low,
+            high,
+            targets,
+        } => json
+            .with("default", (*default).into())
+            .with("low", (*low).into())
+            .with("high", (*high).into())
+            .with("targets", targets.clone().into()),
+        Instruction::Lookupswitch { default, pairs } => {
+            let pairs: Vec<JsonValue> = pairs
+                .iter()
+                .map(|(key, target)| {
+                    JsonValue::object()
+                        .with("key", (*key).into())
+                        .with("target", (*target).into())
+                })
+                .collect();
+            json.with("default", (*default).into())
+                .with("pairs", JsonValue::Array(pairs))
+        }
+        _ => match (instruction.local_index(), &instruction.branch_targets()[..]) {
+            (Some(index), _) => json.with("index", index.into()),
+            (None, [target]) => json.with("target", (*target).into()),
+            _ => json,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    fn round_trip(class_file: &ClassFile) -> ClassFile {
+        let text = to_json(class_file).to_pretty_string();
+        from_json(&JsonValue::parse(&text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn hello_world_round_trip() {
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/helloworld/HelloWorld.class")
+            .unwrap();
+        let json = to_json(&class_file);
+        assert_eq!(
+            json.field("this_class")
+                .unwrap()
+                .str_field("resolved")
+                .unwrap(),
+            "HelloWorld"
+        );
+        let first = &json.array_field("constant_pool").unwrap()[0];
+        assert_eq!(first.str_field("tag").unwrap(), "Methodref");
+        assert_eq!(
+            first.field("resolved").unwrap().str_field("name").unwrap(),
+            "<init>"
+        );
+        let main = &json.array_field("methods").unwrap()[1];
+        let code = &main.array_field("attributes").unwrap()[0];
+        assert_eq!(code.str_field("code").unwrap(), "b200021203b60004b1");
+        let ldc = &code.array_field("instructions").unwrap()[1];
+        assert_eq!(ldc.str_field("opcode").unwrap(), "ldc");
+        assert_eq!(ldc.str_field("resolved").unwrap(), "Hello, World!");
+
+        assert_eq!(round_trip(&class_file).to_bytes(), class_file.to_bytes());
+    }
+
+    #[test]
+    fn unusual_values_round_trip() {
+        let source = r#"
+.class Values
+.constant nan Float NaN
+.field static final D D = -0.0
+.field static final J J = -9223372036854775808L
+.attribute Custom 0102
+.method static f(I)V
+    .limit stack 2
+    iload_0
+    tableswitch 0
+        A
+        default : A
+A:
+    wide iinc 0 -2
+    return
+.end method
+"#;
+        let class_file = assemble(source).unwrap();
+        let copy = round_trip(&class_file);
+        assert_eq!(copy.to_bytes(), class_file.to_bytes());
+
+        let json = to_json(&class_file);
+        let custom = &json.array_field("attributes").unwrap()[0];
+        assert_eq!(custom.str_field("info").unwrap(), "0102");
+        let code = &json.array_field("methods").unwrap()[0]
+            .array_field("attributes")
+            .unwrap()[0];
+        let instructions = code.array_field("instructions").unwrap();
+        assert_eq!(
+            instructions[1].array_field("targets").unwrap(),
+            &[JsonValue::Integer(20)]
+        );
+        assert_eq!(instructions[2].get("wide"), Some(&JsonValue::Bool(true)));
+    }
+
+    #[test]
+    fn invalid_utf8_keeps_raw_bytes() {
+        let mut class_file = assemble(".class A\n").unwrap();
+        let mut constant_pool = class_file.take_constant_pool();
+        constant_pool
+            .push_item(ConstantPoolItem::from_data(ConstantPoolData::Utf8(
+                Utf8::from_bytes(vec![0xff, 0x41]).unwrap(),
+            )))
+            .unwrap();
+        class_file.set_constant_pool(constant_pool);
+        assert_eq!(round_trip(&class_file).to_bytes(), class_file.to_bytes());
+    }
+
+    #[test]
+    fn load_errors() {
+        let class_file = assemble(".class A\n").unwrap();
+        let mut json = to_json(&class_file);
+        if let JsonValue::Object(entries) = &mut json {
+            entries.retain(|(key, _)| key != "methods");
+        }
+        let error = from_json(&json).unwrap_err();
+        assert!(error.to_string().contains("'methods'"));
+    }
+}
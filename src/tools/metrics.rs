@@ -0,0 +1,356 @@
+use crate::analysis::cfg::{ControlFlowGraph, EdgeKind};
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::member_info::MethodInfo;
+use crate::util::json::JsonValue;
+use std::collections::BTreeSet;
+use std::io;
+
+// Size and shape metrics for every method with code, for finding the methods that generated
+// code has blown up. Cyclomatic complexity is counted from the control-flow graph as one plus
+// the extra ways out of every block, with each exception handler one more way.
+
+/// The largest code array a method can have, since code_length must be below 65536 (section
+/// 4.7.3)
+pub const MAX_CODE_LENGTH: u32 = 65535;
+
+/// Methods whose code is at least this long are reported as close to the limit
+pub const NEAR_CODE_LIMIT: u32 = MAX_CODE_LENGTH * 9 / 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MethodMetrics {
+    class_name: String,
+    name: String,
+    descriptor: String,
+    code_length: u32,
+    max_stack: u16,
+    max_locals: u16,
+    complexity: u32,
+    handlers: u32,
+    calls: u32,
+}
+
+impl MethodMetrics {
+    /// The metrics of a method, or `None` if it's abstract or native
+    pub fn of(
+        class_file: &ClassFile,
+        method: &MethodInfo,
+    ) -> Result<Option<MethodMetrics>, io::Error> {
+        let constant_pool = class_file.constant_pool();
+        let Some(code) = method.code(constant_pool)? else {
+            return Ok(None);
+        };
+        let cfg = ControlFlowGraph::from_code(&code)?;
+        let mut complexity = 1;
+        for block in 0..cfg.blocks().len() {
+            let ways_out = cfg
+                .successors(block)
+                .iter()
+                .filter(|edge| !matches!(edge.kind(), EdgeKind::Exception(_)))
+                .count() as u32;
+            complexity += ways_out.saturating_sub(1);
+        }
+        let handlers: BTreeSet<u16> = code
+            .exception_table()
+            .iter()
+            .map(|entry| entry.handler_pc())
+            .collect();
+        complexity += handlers.len() as u32;
+        let calls = cfg
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions())
+            .filter(|(_, instruction)| {
+                matches!(
+                    instruction,
+                    Instruction::Invokevirtual(_)
+                        | Instruction::Invokespecial(_)
+                        | Instruction::Invokestatic(_)
+                        | Instruction::Invokeinterface { .. }
+                        | Instruction::Invokedynamic(_)
+                )
+            })
+            .count() as u32;
+        Ok(Some(MethodMetrics {
+            class_name: class_file.class_name()?,
+            name: method.name(constant_pool)?,
+            descriptor: method.descriptor(constant_pool)?,
+            code_length: code.code().len() as u32,
+            max_stack: code.max_stack(),
+            max_locals: code.max_locals(),
+            complexity,
+            handlers: code.exception_table().len() as u32,
+            calls,
+        }))
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// The size of the code array in bytes
+    pub fn code_length(&self) -> u32 {
+        self.code_length
+    }
+
+    pub fn max_stack(&self) -> u16 {
+        self.max_stack
+    }
+
+    pub fn max_locals(&self) -> u16 {
+        self.max_locals
+    }
+
+    pub fn complexity(&self) -> u32 {
+        self.complexity
+    }
+
+    /// Entries in the exception table
+    pub fn handlers(&self) -> u32 {
+        self.handlers
+    }
+
+    /// Invoke instructions, including invokedynamic
+    pub fn calls(&self) -> u32 {
+        self.calls
+    }
+
+    pub fn near_code_limit(&self) -> bool {
+        self.code_length >= NEAR_CODE_LIMIT
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::object()
+            .with("class", self.class_name.as_str().into())
+            .with("name", self.name.as_str().into())
+            .with("descriptor", self.descriptor.as_str().into())
+            .with("code_length", self.code_length.into())
+            .with("max_stack", self.max_stack.into())
+            .with("max_locals", self.max_locals.into())
+            .with("complexity", self.complexity.into())
+            .with("handlers", self.handlers.into())
+            .with("calls", self.calls.into())
+            .with("near_code_limit", self.near_code_limit().into())
+    }
+}
+
+/// The metrics of every method with code
+pub fn class_metrics(class_file: &ClassFile) -> Result<Vec<MethodMetrics>, io::Error> {
+    let mut metrics = Vec::new();
+    for method in class_file.methods() {
+        metrics.extend(MethodMetrics::of(class_file, method)?);
+    }
+    Ok(metrics)
+}
+
+/// A column to sort the report by
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Size,
+    Stack,
+    Locals,
+    Complexity,
+    Handlers,
+    Calls,
+    Name,
+}
+
+impl SortKey {
+    /// Parses a column name as it appears in the table header
+    pub fn parse(name: &str) -> Result<SortKey, io::Error> {
+        Ok(match name {
+            "size" => SortKey::Size,
+            "stack" => SortKey::Stack,
+            "locals" => SortKey::Locals,
+            "complexity" => SortKey::Complexity,
+            "handlers" => SortKey::Handlers,
+            "calls" => SortKey::Calls,
+            "method" => SortKey::Name,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Expected size, stack, locals, complexity, handlers, calls or method \
+                         but found {name}"
+                    ),
+                ))
+            }
+        })
+    }
+}
+
+/// Sorts by the key, largest first, or by class and method name for `SortKey::Name`. Ties are
+/// broken by name.
+pub fn sort(metrics: &mut [MethodMetrics], key: SortKey) {
+    metrics.sort_by(|a, b| {
+        let value = |m: &MethodMetrics| match key {
+            SortKey::Size => m.code_length,
+            SortKey::Stack => m.max_stack as u32,
+            SortKey::Locals => m.max_locals as u32,
+            SortKey::Complexity => m.complexity,
+            SortKey::Handlers => m.handlers,
+            SortKey::Calls => m.calls,
+            SortKey::Name => 0,
+        };
+        value(b)
+            .cmp(&value(a))
+            .then_with(|| a.class_name.cmp(&b.class_name))
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.descriptor.cmp(&b.descriptor))
+    });
+}
+
+/// One row per method in aligned columns, then the methods close to the code size limit
+pub fn report(metrics: &[MethodMetrics]) -> String {
+    let mut out = format!(
+        "{:>6} {:>5} {:>6} {:>10} {:>8} {:>5}  method\n",
+        "size", "stack", "locals", "complexity", "handlers", "calls"
+    );
+    for m in metrics {
+        out.push_str(&format!(
+            "{:>6} {:>5} {:>6} {:>10} {:>8} {:>5}  {}.{}{}\n",
+            m.code_length,
+            m.max_stack,
+            m.max_locals,
+            m.complexity,
+            m.handlers,
+            m.calls,
+            m.class_name,
+            m.name,
+            m.descriptor
+        ));
+    }
+    let near: Vec<&MethodMetrics> = metrics.iter().filter(|m| m.near_code_limit()).collect();
+    if !near.is_empty() {
+        out.push_str(&format!(
+            "\n{} methods within 10% of the {MAX_CODE_LENGTH} byte code limit:\n",
+            near.len()
+        ));
+        for m in near {
+            out.push_str(&format!(
+                "    {}.{}{} ({} bytes)\n",
+                m.class_name, m.name, m.descriptor, m.code_length
+            ));
+        }
+    }
+    out
+}
+
+pub fn to_json(metrics: &[MethodMetrics]) -> JsonValue {
+    JsonValue::Array(metrics.iter().map(MethodMetrics::to_json).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+
+    fn metrics(source: &str) -> Vec<MethodMetrics> {
+        class_metrics(&assemble(source).unwrap()).unwrap()
+    }
+
+    const SOURCE: &str = "
+.class T
+.method abstract a()V
+.end method
+.method static f(I)I
+    .limit stack 2
+    .catch java/lang/Exception from Start to End using Handler
+Start:
+    iload_0
+    tableswitch 0 1
+        One
+        Two
+        default: Other
+One:
+    invokestatic T/g()V
+    iconst_1
+    ireturn
+Two:
+    iconst_2
+    ireturn
+Other:
+    iload_0
+    ifeq One
+End:
+    iconst_0
+    ireturn
+Handler:
+    invokestatic T/g()V
+    iconst_m1
+    ireturn
+.end method
+.method static g()V
+    return
+.end method
+";
+
+    #[test]
+    fn counts_complexity_handlers_and_calls() {
+        let metrics = metrics(SOURCE);
+        assert_eq!(metrics.len(), 2);
+        let f = &metrics[0];
+        assert_eq!((f.name(), f.descriptor()), ("f", "(I)I"));
+        assert_eq!(f.max_stack(), 2);
+        assert_eq!(f.max_locals(), 1);
+        // three switch targets, one more way out of ifeq, and the handler
+        assert_eq!(f.complexity(), 5);
+        assert_eq!(f.handlers(), 1);
+        assert_eq!(f.calls(), 2);
+        assert!(!f.near_code_limit());
+        assert_eq!(metrics[1].complexity(), 1);
+        assert_eq!(metrics[1].code_length(), 1);
+    }
+
+    #[test]
+    fn sorts_and_reports() {
+        let mut metrics = metrics(SOURCE);
+        sort(&mut metrics, SortKey::parse("method").unwrap());
+        assert_eq!(metrics[0].name(), "f");
+        sort(&mut metrics, SortKey::Size);
+        assert_eq!(metrics[1].name(), "g");
+        let report = report(&metrics);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines[0],
+            "  size stack locals complexity handlers calls  method"
+        );
+        assert_eq!(
+            lines[2],
+            "     1     0      0          1        0     0  T.g()V"
+        );
+        assert_eq!(lines.len(), 3);
+        assert!(SortKey::parse("bytes").is_err());
+
+        let json = to_json(&metrics);
+        assert_eq!(json.as_array().unwrap()[1].str_field("name").unwrap(), "g");
+    }
+
+    #[test]
+    fn flags_methods_near_the_code_limit() {
+        assert_eq!(NEAR_CODE_LIMIT, 58981);
+        // the trailing return makes each method one byte longer than its nops
+        let method = |nops: usize| {
+            let body = "    nop\n".repeat(nops);
+            metrics(&format!(
+                ".class T\n.method static big()V\n{body}    return\n.end method\n"
+            ))
+        };
+        let below = method(58979);
+        assert_eq!(below[0].code_length(), 58980);
+        assert!(!below[0].near_code_limit());
+
+        let at = method(58980);
+        assert_eq!(at[0].code_length(), 58981);
+        assert!(at[0].near_code_limit());
+        assert!(report(&at).contains("1 methods within 10% of the 65535 byte code limit:"));
+    }
+}
@@ -0,0 +1,10 @@
+pub mod compat;
+pub mod decompile;
+pub mod explain;
+pub mod instrument;
+pub mod javap;
+pub mod json;
+pub mod metrics;
+pub mod optimize;
+pub mod remap;
+pub mod shrink;
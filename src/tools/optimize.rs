@@ -0,0 +1,863 @@
+use crate::analysis::cfg::{is_conditional, ControlFlowGraph};
+use crate::analysis::dataflow::{constant_condition, constant_result, solve, Liveness, Value};
+use crate::analysis::frames::{local_access, recompute_frames};
+use crate::analysis::hierarchy::ClassHierarchy;
+use crate::analysis::verifier::{falls_through, is_store};
+use crate::bytecode::encoder::{CodeElement, LabeledAttribute, LabeledCode};
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::access_flags::ACC_STATIC;
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::descriptor::MethodDescriptor;
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+// Peephole optimization of method bodies. Each method is decoded into a `LabeledCode` and the
+// passes run over it in turn, again and again until none of them changes anything, since one
+// pass's output is often another's input: a folded condition becomes a `goto`, which makes
+// code unreachable, whose removal leaves a jump to the next instruction. The code is then
+// laid out again and max_stack and the stack map frames are recomputed for the whole class.
+//
+// Every pass keeps labels where they are, so line numbers, local variable ranges and handler
+// ranges stay attached to the code around what was removed. Methods using `jsr` and `ret`
+// are left alone.
+
+/// The passes, in the order `Optimizer::new` runs them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pass {
+    /// Arithmetic, conversions and comparisons on constants become the constant they compute,
+    /// and conditional branches on constants become a `goto` or nothing
+    FoldConstants,
+    /// A branch whose target is a `goto` goes to where that `goto` does
+    CollapseGotoChains,
+    /// A `goto` to the next instruction is removed, and a conditional branch there only pops
+    /// its operands
+    RemoveJumpsToNext,
+    /// Instructions no path from the start of the method reaches, and handlers that only
+    /// cover those
+    RemoveUnreachableCode,
+    /// Stores to locals that are never read again only pop the value, which is then not
+    /// computed at all if it was a constant or a load
+    RemoveDeadStores,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 5] = [
+        Pass::FoldConstants,
+        Pass::CollapseGotoChains,
+        Pass::RemoveJumpsToNext,
+        Pass::RemoveUnreachableCode,
+        Pass::RemoveDeadStores,
+    ];
+
+    /// The name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::FoldConstants => "fold",
+            Pass::CollapseGotoChains => "gotos",
+            Pass::RemoveJumpsToNext => "jumps",
+            Pass::RemoveUnreachableCode => "unreachable",
+            Pass::RemoveDeadStores => "dead-stores",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        Pass::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+}
+
+/// Stops a method whose passes keep undoing each other, which none of them should
+const MAX_ROUNDS: usize = 32;
+
+pub struct Optimizer<'a> {
+    passes: Vec<Pass>,
+    hierarchy: &'a dyn ClassHierarchy,
+}
+
+impl<'a> Optimizer<'a> {
+    /// Runs every pass. The hierarchy is needed to compute the new stack map frames.
+    pub fn new(hierarchy: &'a dyn ClassHierarchy) -> Optimizer<'a> {
+        Optimizer {
+            passes: Pass::ALL.to_vec(),
+            hierarchy,
+        }
+    }
+
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    pub fn set_passes(&mut self, passes: Vec<Pass>) {
+        self.passes = passes;
+    }
+
+    /// Optimizes every method with code, returning how many of them changed
+    pub fn optimize(&self, class_file: &mut ClassFile) -> Result<usize, io::Error> {
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        let optimized = self.optimize_methods(class_file, &mut builder);
+        // put the constant pool back even if a method couldn't be optimized
+        class_file.set_constant_pool(builder.build());
+        let count = optimized?;
+        if count > 0 {
+            recompute_frames(class_file, self.hierarchy)?;
+        }
+        Ok(count)
+    }
+
+    fn optimize_methods(
+        &self,
+        class_file: &mut ClassFile,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<usize, io::Error> {
+        let mut count = 0;
+        for method in class_file.methods_mut() {
+            let constant_pool = builder.constant_pool();
+            let Some(i) = method.attributes().iter().position(
+                |attribute| matches!(attribute.name(constant_pool), Ok(name) if name == Code::NAME),
+            ) else {
+                continue;
+            };
+            let code = Code::from(&mut method.attributes()[i].reader())?;
+            let name = method.name(constant_pool)?;
+            let descriptor = MethodDescriptor::parse(&method.descriptor(constant_pool)?)?;
+            let parameter_slots = descriptor.parameter_slots()
+                + if method.access_flags() & ACC_STATIC == 0 {
+                    1
+                } else {
+                    0
+                };
+            let optimized = self
+                .optimize_code(&code, parameter_slots, builder)
+                .map_err(|e| io::Error::new(e.kind(), format!("{name}: {e}")))?;
+            let Some(code) = optimized else {
+                continue;
+            };
+            let mut info = Vec::new();
+            code.write(&mut info);
+            let attribute = &mut method.attributes_mut()[i];
+            *attribute = AttributeInfo::new(attribute.attribute_name_index(), info);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// The optimized code, or `None` if no pass changed it. max_stack is left for the frame
+    /// computation to fix up.
+    fn optimize_code(
+        &self,
+        code: &Code,
+        parameter_slots: u16,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<Option<Code>, io::Error> {
+        let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+        if instructions.iter().any(|(_, instruction)| {
+            matches!(
+                instruction,
+                Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_)
+            )
+        }) {
+            return Ok(None);
+        }
+
+        let mut labeled = LabeledCode::from_code(code, builder.constant_pool())?;
+        let (max_stack, max_locals) = (code.max_stack(), code.max_locals());
+        let mut changed = false;
+        for _ in 0..MAX_ROUNDS {
+            let mut round = false;
+            for pass in self.passes.iter() {
+                round |= match pass {
+                    Pass::FoldConstants => fold_constants(labeled.elements_mut(), builder)?,
+                    Pass::CollapseGotoChains => collapse_goto_chains(labeled.elements_mut()),
+                    Pass::RemoveJumpsToNext => remove_jumps_to_next(labeled.elements_mut()),
+                    Pass::RemoveUnreachableCode => remove_unreachable_code(&mut labeled),
+                    Pass::RemoveDeadStores => remove_dead_stores(
+                        &mut labeled,
+                        max_stack,
+                        max_locals,
+                        builder.constant_pool(),
+                    )?,
+                };
+            }
+            if !round {
+                break;
+            }
+            changed = true;
+        }
+        if !changed {
+            return Ok(None);
+        }
+        // code other passes left unreachable can't be given stack map frames
+        remove_unreachable_code(&mut labeled);
+        remove_unused_locals(&mut labeled, parameter_slots);
+        labeled.to_code(max_stack, max_locals).map(Some)
+    }
+}
+
+fn instruction(element: &CodeElement) -> Option<&Instruction> {
+    match element {
+        CodeElement::Instruction(instruction) => Some(instruction),
+        CodeElement::Label(_) => None,
+    }
+}
+
+/// How many values an instruction that can be folded takes from the stack, counting longs
+/// and doubles once
+fn operand_count(instruction: &Instruction) -> Option<usize> {
+    use Instruction::*;
+    Some(match instruction {
+        Ineg | Lneg | Fneg | Dneg | I2l | I2f | I2d | L2i | L2f | L2d | F2i | F2l | F2d | D2i
+        | D2l | D2f | I2b | I2c | I2s => 1,
+        Iadd | Isub | Imul | Idiv | Irem | Ishl | Ishr | Iushr | Iand | Ior | Ixor | Ladd
+        | Lsub | Lmul | Ldiv | Lrem | Lshl | Lshr | Lushr | Land | Lor | Lxor | Fadd | Fsub
+        | Fmul | Fdiv | Frem | Dadd | Dsub | Dmul | Ddiv | Drem | Lcmp | Fcmpl | Fcmpg | Dcmpl
+        | Dcmpg => 2,
+        Ifeq(_) | Ifne(_) | Iflt(_) | Ifge(_) | Ifgt(_) | Ifle(_) | Ifnull(_) | Ifnonnull(_) => 1,
+        IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_)
+        | IfAcmpeq(_) | IfAcmpne(_) => 2,
+        _ => return None,
+    })
+}
+
+/// The shortest instruction that pushes a constant
+fn push_constant(
+    value: Value,
+    builder: &mut ConstantPoolBuilder,
+) -> Result<Instruction, io::Error> {
+    use Instruction::*;
+    let ldc = |index: u16| match index {
+        0..=255 => Ldc(index),
+        _ => LdcW(index),
+    };
+    Ok(match value {
+        Value::Int(-1) => IconstM1,
+        Value::Int(0) => Iconst0,
+        Value::Int(1) => Iconst1,
+        Value::Int(2) => Iconst2,
+        Value::Int(3) => Iconst3,
+        Value::Int(4) => Iconst4,
+        Value::Int(5) => Iconst5,
+        Value::Int(value) => match (i8::try_from(value), i16::try_from(value)) {
+            (Ok(value), _) => Bipush(value),
+            (_, Ok(value)) => Sipush(value),
+            _ => ldc(builder.add_integer(value)?),
+        },
+        Value::Long(0) => Lconst0,
+        Value::Long(1) => Lconst1,
+        Value::Long(value) => Ldc2W(builder.add_long(value)?),
+        // compared by bits so that -0.0 keeps its sign
+        Value::Float(value) if value.to_bits() == 0f32.to_bits() => Fconst0,
+        Value::Float(1.0) => Fconst1,
+        Value::Float(2.0) => Fconst2,
+        Value::Float(value) => ldc(builder.add_float(value)?),
+        Value::Double(value) if value.to_bits() == 0f64.to_bits() => Dconst0,
+        Value::Double(1.0) => Dconst1,
+        Value::Double(value) => Ldc2W(builder.add_double(value)?),
+        Value::Null => AconstNull,
+        Value::Unknown | Value::NonNull => {
+            return Err(optimize_error("Only constants can be pushed"))
+        }
+    })
+}
+
+fn fold_constants(
+    elements: &mut Vec<CodeElement>,
+    builder: &mut ConstantPoolBuilder,
+) -> Result<bool, io::Error> {
+    let mut changed = false;
+    let mut i = 0;
+    while i < elements.len() {
+        let Some(count) = instruction(&elements[i]).and_then(operand_count) else {
+            i += 1;
+            continue;
+        };
+        // the operands have to be pushed right before, with nothing jumping in between
+        let constant_pool = builder.constant_pool();
+        let operands = match i.checked_sub(count) {
+            Some(start) => elements[start..i]
+                .iter()
+                .map(|element| {
+                    instruction(element)
+                        .filter(|instruction| {
+                            constant_result(constant_pool, std::slice::from_ref(instruction))
+                                .is_some()
+                        })
+                        .cloned()
+                })
+                .collect::<Option<Vec<_>>>(),
+            None => None,
+        };
+        let Some(mut operands) = operands else {
+            i += 1;
+            continue;
+        };
+        let operation = instruction(&elements[i]).unwrap().clone();
+        let replacement = if is_conditional(&operation) {
+            match constant_condition(constant_pool, &operands, &operation) {
+                Some(true) => vec![Instruction::Goto(operation.branch_targets()[0])],
+                Some(false) => Vec::new(),
+                None => {
+                    i += 1;
+                    continue;
+                }
+            }
+        } else {
+            operands.push(operation);
+            match constant_result(constant_pool, &operands) {
+                Some(value) => vec![push_constant(value, builder)?],
+                None => {
+                    i += 1;
+                    continue;
+                }
+            }
+        };
+        let start = i - count;
+        elements.splice(
+            start..=i,
+            replacement.into_iter().map(CodeElement::Instruction),
+        );
+        changed = true;
+        // the result may be an operand of what follows
+        i = start;
+    }
+    Ok(changed)
+}
+
+/// The index of the first instruction at or after each label
+fn label_targets(elements: &[CodeElement]) -> HashMap<u32, usize> {
+    let mut targets = HashMap::new();
+    let mut pending = Vec::new();
+    for (i, element) in elements.iter().enumerate() {
+        match element {
+            CodeElement::Label(label) => pending.push(label.id()),
+            CodeElement::Instruction(_) => {
+                targets.extend(pending.drain(..).map(|label| (label, i)));
+            }
+        }
+    }
+    targets
+}
+
+fn collapse_goto_chains(elements: &mut [CodeElement]) -> bool {
+    let targets = label_targets(elements);
+    let follow = |mut label: u32| {
+        let mut seen = HashSet::new();
+        while seen.insert(label) {
+            match targets.get(&label).and_then(|&i| instruction(&elements[i])) {
+                Some(Instruction::Goto(next) | Instruction::GotoW(next)) => label = *next,
+                _ => break,
+            }
+        }
+        label
+    };
+    let mut retargeted = Vec::new();
+    for (i, element) in elements.iter().enumerate() {
+        let Some(instruction) = instruction(element) else {
+            continue;
+        };
+        if instruction
+            .branch_targets()
+            .iter()
+            .any(|target| follow(*target) != *target)
+        {
+            retargeted.push((i, instruction.map_branch_targets(follow)));
+        }
+    }
+    let changed = !retargeted.is_empty();
+    for (i, instruction) in retargeted {
+        elements[i] = CodeElement::Instruction(instruction);
+    }
+    changed
+}
+
+fn remove_jumps_to_next(elements: &mut Vec<CodeElement>) -> bool {
+    let mut changed = false;
+    let mut i = 0;
+    while i < elements.len() {
+        let Some(instruction) = instruction(&elements[i]) else {
+            i += 1;
+            continue;
+        };
+        let jumps_to_next = match instruction {
+            Instruction::Goto(target) | Instruction::GotoW(target) => Some(*target),
+            instruction if is_conditional(instruction) => Some(instruction.branch_targets()[0]),
+            _ => None,
+        }
+        .is_some_and(|target| {
+            elements[i + 1..]
+                .iter()
+                .map_while(|element| match element {
+                    CodeElement::Label(label) => Some(label.id()),
+                    CodeElement::Instruction(_) => None,
+                })
+                .any(|label| label == target)
+        });
+        if !jumps_to_next {
+            i += 1;
+            continue;
+        }
+        let replacement = match instruction {
+            Instruction::Goto(_) | Instruction::GotoW(_) => None,
+            Instruction::IfIcmpeq(_)
+            | Instruction::IfIcmpne(_)
+            | Instruction::IfIcmplt(_)
+            | Instruction::IfIcmpge(_)
+            | Instruction::IfIcmpgt(_)
+            | Instruction::IfIcmple(_)
+            | Instruction::IfAcmpeq(_)
+            | Instruction::IfAcmpne(_) => Some(Instruction::Pop2),
+            _ => Some(Instruction::Pop),
+        };
+        match replacement {
+            Some(pop) => {
+                elements[i] = CodeElement::Instruction(pop);
+                i += 1;
+            }
+            None => {
+                elements.remove(i);
+            }
+        }
+        changed = true;
+    }
+    changed
+}
+
+fn remove_unreachable_code(labeled: &mut LabeledCode) -> bool {
+    let elements = labeled.elements();
+    let targets = label_targets(elements);
+    let positions = elements
+        .iter()
+        .enumerate()
+        .filter_map(|(i, element)| match element {
+            CodeElement::Label(label) => Some((label.id(), i)),
+            CodeElement::Instruction(_) => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let first = elements
+        .iter()
+        .position(|element| instruction(element).is_some());
+
+    let mut reachable = vec![false; elements.len()];
+    let mut pending = first.into_iter().collect::<Vec<_>>();
+    loop {
+        while let Some(i) = pending.pop() {
+            if reachable[i] {
+                continue;
+            }
+            reachable[i] = true;
+            // every instruction was decoded or placed with labels that exist
+            let instruction = instruction(&elements[i]).unwrap();
+            pending.extend(
+                instruction
+                    .branch_targets()
+                    .iter()
+                    .filter_map(|target| targets.get(target)),
+            );
+            if falls_through(instruction) {
+                pending.extend(
+                    (i + 1..elements.len()).find(|&j| self::instruction(&elements[j]).is_some()),
+                );
+            }
+        }
+        // a handler is reached if anything it covers is
+        for handler in labeled.exception_table() {
+            let range = positions[&handler.start().id()]..positions[&handler.end().id()];
+            if range.clone().any(|i| reachable[i]) {
+                pending.extend(
+                    targets
+                        .get(&handler.handler().id())
+                        .filter(|&&i| !reachable[i]),
+                );
+            }
+        }
+        if pending.is_empty() {
+            break;
+        }
+    }
+
+    let instructions = elements
+        .iter()
+        .filter(|element| instruction(element).is_some());
+    if instructions.count() == reachable.iter().filter(|&&reachable| reachable).count() {
+        return false;
+    }
+    let handlers = labeled
+        .exception_table()
+        .iter()
+        .filter(|handler| {
+            let range = positions[&handler.start().id()]..positions[&handler.end().id()];
+            range.clone().any(|i| reachable[i])
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    let mut i = 0;
+    labeled.elements_mut().retain(|element| {
+        i += 1;
+        instruction(element).is_none() || reachable[i - 1]
+    });
+    *labeled.exception_table_mut() = handlers;
+    true
+}
+
+/// Drops the LocalVariableTable and LocalVariableTypeTable entries of locals that no
+/// instruction touches any more, such as ones whose only store was dead. Parameters keep
+/// theirs, as they are always there.
+fn remove_unused_locals(labeled: &mut LabeledCode, parameter_slots: u16) {
+    let accessed = labeled
+        .elements()
+        .iter()
+        .filter_map(|element| local_access(instruction(element)?))
+        .map(|(index, _)| index)
+        .collect::<HashSet<_>>();
+    for attribute in labeled.attributes_mut() {
+        if let LabeledAttribute::LocalVariableTable { variables, .. } = attribute {
+            variables.retain(|local| {
+                local.index() < parameter_slots || accessed.contains(&local.index())
+            });
+        }
+    }
+}
+
+/// Turns stores nothing reads into pops, then removes pops of values that were only just
+/// pushed by an instruction that can't throw
+fn remove_dead_stores(
+    labeled: &mut LabeledCode,
+    max_stack: u16,
+    max_locals: u16,
+    constant_pool: &ConstantPool,
+) -> Result<bool, io::Error> {
+    // liveness needs pcs, and those only exist once the code is laid out
+    let code = labeled.to_code(max_stack, max_locals)?;
+    let instructions = code.instructions().collect::<Result<Vec<_>, _>>()?;
+    let indices = labeled
+        .elements()
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| instruction(element).is_some())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut changed = false;
+    // widened branches have no element of their own to map back to
+    if indices.len() == instructions.len() {
+        let cfg = ControlFlowGraph::from_code(&code)?;
+        let liveness = solve(&cfg, &Liveness)?;
+        let mut removed = Vec::new();
+        for ((pc, instruction), i) in instructions.iter().zip(indices) {
+            let Some((index, wide)) = local_access(instruction) else {
+                continue;
+            };
+            if !is_store(instruction)
+                || liveness.after(*pc).is_none_or(|live| live.contains(&index))
+            {
+                continue;
+            }
+            let elements = labeled.elements_mut();
+            match (instruction, wide) {
+                (Instruction::Iinc { .. }, _) => removed.push(i),
+                (_, true) => elements[i] = CodeElement::Instruction(Instruction::Pop2),
+                (_, false) => elements[i] = CodeElement::Instruction(Instruction::Pop),
+            }
+            changed = true;
+        }
+        for i in removed.into_iter().rev() {
+            labeled.elements_mut().remove(i);
+        }
+    }
+
+    let elements = labeled.elements_mut();
+    let mut i = 1;
+    while i < elements.len() {
+        let pushed = match (instruction(&elements[i - 1]), instruction(&elements[i])) {
+            (Some(pushed), Some(Instruction::Pop | Instruction::Pop2)) => pushed,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let slots = match pushed {
+            Instruction::Dup => Some(1),
+            Instruction::Dup2 => Some(2),
+            instruction => match local_access(instruction) {
+                Some((_, wide)) if !is_store(instruction) => Some(1 + wide as u8),
+                _ => match constant_result(constant_pool, std::slice::from_ref(instruction)) {
+                    Some(Value::Long(_) | Value::Double(_)) => Some(2),
+                    Some(_) => Some(1),
+                    None => None,
+                },
+            },
+        };
+        let popped = match elements[i] {
+            CodeElement::Instruction(Instruction::Pop2) => 2,
+            _ => 1,
+        };
+        if slots == Some(popped) {
+            elements.drain(i - 1..=i);
+            changed = true;
+            i = i.saturating_sub(1).max(1);
+        } else {
+            i += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn optimize_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::hierarchy::ClassFileHierarchy;
+    use crate::analysis::verifier::verify_class;
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::attribute::local_variable_table::LocalVariableTable;
+
+    fn optimize(source: &str, passes: &[Pass]) -> (ClassFile, usize) {
+        let mut class_file = assemble(source).unwrap();
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add_class_file(&class_file).unwrap();
+        let mut optimizer = Optimizer::new(&hierarchy);
+        optimizer.set_passes(passes.to_vec());
+        let count = optimizer.optimize(&mut class_file).unwrap();
+        assert_eq!(verify_class(&class_file, &hierarchy), vec![]);
+        (class_file, count)
+    }
+
+    fn instructions(class_file: &ClassFile, name: &str, descriptor: &str) -> Vec<Instruction> {
+        let code = class_file
+            .find_method(name, descriptor)
+            .unwrap()
+            .code(class_file.constant_pool())
+            .unwrap()
+            .unwrap();
+        code.instructions()
+            .map(|instruction| instruction.unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn folds_constants_and_removes_the_code_left_behind() {
+        let source = "
+.class t/A
+.super java/lang/Object
+.method static f()I
+    .limit stack 2
+    .limit locals 0
+    iconst_2
+    iconst_3
+    imul
+    ifeq Dead
+    goto Next
+Next:
+    ldc 100000
+    iconst_2
+    imul
+    ireturn
+Dead:
+    iconst_0
+    ireturn
+.end method
+.method static g()J
+    .limit stack 4
+    .limit locals 0
+    iconst_1
+    iconst_0
+    idiv
+    i2l
+    lconst_1
+    ladd
+    lreturn
+.end method
+";
+        let (class_file, count) = optimize(source, &Pass::ALL);
+        // division by zero throws, so g stays as it is
+        assert_eq!(count, 1);
+        let constant_pool = class_file.constant_pool();
+        let code = instructions(&class_file, "f", "()I");
+        let [Instruction::Ldc(index), Instruction::Ireturn] = code[..] else {
+            panic!("{code:?}");
+        };
+        assert!(matches!(
+            constant_result(constant_pool, &[Instruction::Ldc(index)]),
+            Some(Value::Int(200000))
+        ));
+        assert_eq!(
+            instructions(&class_file, "g", "()J"),
+            vec![
+                Instruction::Iconst1,
+                Instruction::Iconst0,
+                Instruction::Idiv,
+                Instruction::I2l,
+                Instruction::Lconst1,
+                Instruction::Ladd,
+                Instruction::Lreturn,
+            ]
+        );
+
+        // without the other passes the goto stays, but the dead block is still removed as
+        // nothing could give it frames
+        let (class_file, _) = optimize(source, &[Pass::FoldConstants]);
+        assert_eq!(
+            instructions(&class_file, "f", "()I"),
+            vec![
+                Instruction::Goto(3),
+                Instruction::Ldc(index),
+                Instruction::Ireturn
+            ]
+        );
+    }
+
+    #[test]
+    fn collapses_goto_chains_and_jumps_to_the_next_instruction() {
+        let (class_file, count) = optimize(
+            "
+.class t/A
+.super java/lang/Object
+.method static f(I)I
+    .limit stack 2
+    .limit locals 1
+    iload_0
+    ifeq First
+    iload_0
+    ifne Next
+Next:
+    iconst_1
+    ireturn
+First:
+    goto Second
+Second:
+    goto Last
+Last:
+    iconst_2
+    ireturn
+.end method
+.method static g()V
+    .limit stack 0
+    .limit locals 0
+    return
+.end method
+",
+            &[
+                Pass::CollapseGotoChains,
+                Pass::RemoveJumpsToNext,
+                Pass::RemoveUnreachableCode,
+            ],
+        );
+        assert_eq!(count, 1);
+        let code = instructions(&class_file, "f", "(I)I");
+        let Instruction::Ifeq(target) = code[1] else {
+            panic!("{code:?}");
+        };
+        assert_eq!(
+            code,
+            vec![
+                Instruction::Iload0,
+                Instruction::Ifeq(target),
+                Instruction::Iload0,
+                Instruction::Pop,
+                Instruction::Iconst1,
+                Instruction::Ireturn,
+                Instruction::Iconst2,
+                Instruction::Ireturn,
+            ]
+        );
+        // the gotos the branch went through are no longer reached
+        assert_eq!(target, 8);
+    }
+
+    #[test]
+    fn removes_dead_stores_unless_a_handler_reads_them() {
+        let (class_file, count) = optimize(
+            "
+.class t/A
+.super java/lang/Object
+.method static f(I)I
+    .limit stack 2
+    .limit locals 3
+    iconst_1
+    istore_1
+    iload_0
+    istore_1
+    iinc 2 1
+    iconst_2
+    istore_2
+Start:
+    iload_0
+    iconst_3
+    idiv
+    istore_2
+    iload_1
+End:
+    ireturn
+Handler:
+    pop
+    iload_2
+    ireturn
+.catch java/lang/ArithmeticException from Start to End using Handler
+.end method
+",
+            &[Pass::RemoveDeadStores],
+        );
+        assert_eq!(count, 1);
+        assert_eq!(
+            instructions(&class_file, "f", "(I)I"),
+            vec![
+                Instruction::Iload0,
+                Instruction::Istore1,
+                Instruction::Iconst2,
+                Instruction::Istore2,
+                Instruction::Iload0,
+                Instruction::Iconst3,
+                Instruction::Idiv,
+                Instruction::Istore2,
+                Instruction::Iload1,
+                Instruction::Ireturn,
+                Instruction::Pop,
+                Instruction::Iload2,
+                Instruction::Ireturn,
+            ]
+        );
+    }
+
+    #[test]
+    fn dead_stores_take_their_debug_entries_with_them() {
+        // javac -g output, where the dead `long unused` is the highest local
+        let mut class_file = ClassFile::new();
+        class_file
+            .read_file("tests/java/deadstore/DeadStore.class")
+            .unwrap();
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add_class_file(&class_file).unwrap();
+        assert_eq!(
+            Optimizer::new(&hierarchy)
+                .optimize(&mut class_file)
+                .unwrap(),
+            1
+        );
+        assert_eq!(verify_class(&class_file, &hierarchy), vec![]);
+
+        let constant_pool = class_file.constant_pool();
+        let code = class_file
+            .find_method("sum", "(II)I")
+            .unwrap()
+            .code(constant_pool)
+            .unwrap()
+            .unwrap();
+        let table = code
+            .attributes()
+            .iter()
+            .find(|attribute| attribute.name(constant_pool).unwrap() == LocalVariableTable::NAME)
+            .unwrap();
+        let table = LocalVariableTable::from(&mut table.reader()).unwrap();
+        let names = table
+            .local_variable_table()
+            .iter()
+            .map(|local| constant_pool.utf8(local.name_index()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "total", "twice"]);
+        assert_eq!(code.max_locals(), 4);
+    }
+}
@@ -0,0 +1,881 @@
+use crate::analysis::hierarchy::ClassHierarchy;
+use crate::class_file::attribute::attribute_info::AttributeInfo;
+use crate::class_file::attribute::code::Code;
+use crate::class_file::attribute::inner_classes::{InnerClass, InnerClasses};
+use crate::class_file::attribute::local_variable_table::{LocalVariable, LocalVariableTable};
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::ConstantPoolData;
+use crate::class_file::constant_pool::types::class_info::ClassInfo;
+use crate::class_file::constant_pool::types::field_ref::FieldRef;
+use crate::class_file::constant_pool::types::interface_method_ref::InterfaceMethodRef;
+use crate::class_file::constant_pool::types::method_ref::MethodRef;
+use crate::class_file::constant_pool::types::method_type::MethodType;
+use crate::class_file::constant_pool::types::name_and_type::NameAndType;
+use crate::class_file::limits::read_type_annotation_target;
+use crate::class_file::member_info::MemberInfo;
+use crate::class_file::signature::{ClassTypeSignature, Signature};
+use crate::util::file::{read_u16, read_u8};
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufReader, Read};
+
+// Renames classes, packages, fields and methods, e.g. to shade a library into another jar.
+// Constant pool indices are kept: entries that change are replaced in place and anything new
+// they need is appended, so code, stack map frames and bootstrap method arguments that refer
+// to them need no changes. Descriptors don't depend on whose member they describe, so every
+// NameAndType is rewritten in place and member references only get a new one when their
+// name changes. String constants are left alone, as are the names of lambda methods in
+// invokedynamic call sites. The original Utf8 entries stay in the constant pool unused.
+
+/// Renames read from a mapping file, one per line, with `#` starting a comment:
+///
+/// ```text
+/// package com/example/util shaded/example/util
+/// class com/example/Api shaded/example/Api
+/// field com/example/Api count total
+/// method com/example/Api size()I length
+/// ```
+///
+/// Class and package names can use dots as well. Packages include their subpackages, and
+/// inner classes follow their outer class unless they are mapped themselves.
+#[derive(Clone, Debug, Default)]
+pub struct Mapping {
+    classes: HashMap<String, String>,
+    /// longest first, so subpackages can be mapped somewhere else than their parents
+    packages: Vec<(String, String)>,
+    fields: HashMap<(String, String), String>,
+    methods: HashMap<(String, String, String), String>,
+}
+
+impl Mapping {
+    pub fn new() -> Mapping {
+        Mapping::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Mapping, io::Error> {
+        let mut mapping = Mapping::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let malformed = |expected: &str| {
+                remap_error(&format!(
+                    "line {}: expected {expected} but found '{}'",
+                    i + 1,
+                    line.trim()
+                ))
+            };
+            match words.as_slice() {
+                [] => {}
+                ["package", from, to] => mapping.add_package(&internal(from), &internal(to)),
+                ["class", from, to] => mapping.add_class(&internal(from), &internal(to)),
+                ["field", owner, name, new_name] => {
+                    mapping.add_field(&internal(owner), name, new_name)
+                }
+                ["method", owner, method, new_name] => {
+                    let (name, descriptor) =
+                        method
+                            .find('(')
+                            .map(|paren| method.split_at(paren))
+                            .ok_or_else(|| malformed("a method name followed by its descriptor"))?;
+                    mapping.add_method(&internal(owner), name, descriptor, new_name);
+                }
+                _ => {
+                    return Err(malformed(
+                        "'package <from> <to>', 'class <from> <to>', \
+                         'field <owner> <name> <new name>' or \
+                         'method <owner> <name><descriptor> <new name>'",
+                    ))
+                }
+            }
+        }
+        Ok(mapping)
+    }
+
+    /// Moves a package and its subpackages
+    pub fn add_package(&mut self, from: &str, to: &str) {
+        self.packages.retain(|(package, _)| package != from);
+        self.packages.push((from.to_string(), to.to_string()));
+        self.packages
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    }
+
+    pub fn add_class(&mut self, from: &str, to: &str) {
+        self.classes.insert(from.to_string(), to.to_string());
+    }
+
+    /// Renames a field declared by `owner`, wherever it's referred to through a subclass too
+    pub fn add_field(&mut self, owner: &str, name: &str, new_name: &str) {
+        self.fields
+            .insert((owner.to_string(), name.to_string()), new_name.to_string());
+    }
+
+    /// Renames a method of `owner` and the methods of subclasses that override it
+    pub fn add_method(&mut self, owner: &str, name: &str, descriptor: &str, new_name: &str) {
+        self.methods.insert(
+            (owner.to_string(), name.to_string(), descriptor.to_string()),
+            new_name.to_string(),
+        );
+    }
+
+    /// The new internal name of a class or array type
+    pub fn map_class(&self, name: &str) -> String {
+        if name.starts_with('[') {
+            return self
+                .map_signature(name)
+                .unwrap_or_else(|_| name.to_string());
+        }
+        if let Some(mapped) = self.classes.get(name) {
+            return mapped.clone();
+        }
+        if let Some((outer, inner)) = name.rsplit_once('$') {
+            let mapped_outer = self.map_class(outer);
+            if mapped_outer != outer {
+                return format!("{mapped_outer}${inner}");
+            }
+        }
+        if let Some((package, simple_name)) = name.rsplit_once('/') {
+            let mapped_package = self.map_package(package);
+            if mapped_package != package {
+                return format!("{mapped_package}/{simple_name}");
+            }
+        }
+        name.to_string()
+    }
+
+    /// The new name of a package, or the package itself if it isn't moved
+    pub fn map_package(&self, package: &str) -> String {
+        for (from, to) in &self.packages {
+            if package == from {
+                return to.clone();
+            }
+            if let Some(rest) = package
+                .strip_prefix(from.as_str())
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                return format!("{to}/{rest}");
+            }
+        }
+        package.to_string()
+    }
+
+    /// Renames the classes in a field or method descriptor or a generic signature (section
+    /// 4.7.9.1)
+    pub fn map_signature(&self, signature: &str) -> Result<String, io::Error> {
+        let mut signature = Signature::parse(signature)?;
+        signature.for_each_class_type_mut(&mut |class_type| self.map_class_type(class_type));
+        Ok(signature.to_string())
+    }
+
+    /// Inner classes of generic types only name themselves, so the binary names are tracked
+    /// to look them up
+    fn map_class_type(&self, class_type: &mut ClassTypeSignature) {
+        let mut binary_name = String::new();
+        let mut mapped_name = String::new();
+        for segment in class_type.segments_mut() {
+            if binary_name.is_empty() {
+                binary_name = segment.name().to_string();
+                mapped_name = self.map_class(&binary_name);
+                segment.set_name(mapped_name.clone());
+            } else {
+                binary_name = format!("{binary_name}${}", segment.name());
+                let mapped = self.map_class(&binary_name);
+                let name = match mapped.strip_prefix(&format!("{mapped_name}$")) {
+                    Some(inner) => inner.to_string(),
+                    None => simple_name(&mapped).to_string(),
+                };
+                segment.set_name(name);
+                mapped_name = mapped;
+            }
+        }
+    }
+
+    /// Moves a resource that isn't a class file along with its package. Service provider
+    /// files (`META-INF/services/<interface>`) are renamed after their interface and the
+    /// implementations they list are renamed too.
+    pub fn remap_resource(&self, name: &str, data: Vec<u8>) -> (String, Vec<u8>) {
+        if let Some(service) = name.strip_prefix("META-INF/services/") {
+            let renamed = format!("META-INF/services/{}", self.map_binary_name(service));
+            let Ok(text) = String::from_utf8(data.clone()) else {
+                return (renamed, data);
+            };
+            let mut out = String::with_capacity(text.len());
+            for line in text.split_inclusive('\n') {
+                let provider = line.split('#').next().unwrap_or_default().trim();
+                if provider.is_empty() {
+                    out.push_str(line);
+                } else {
+                    out.push_str(&line.replacen(provider, &self.map_binary_name(provider), 1));
+                }
+            }
+            return (renamed, out.into_bytes());
+        }
+        match name.rsplit_once('/') {
+            Some((package, file)) => (format!("{}/{file}", self.map_package(package)), data),
+            None => (name.to_string(), data),
+        }
+    }
+
+    /// Like `map_class` for a name with dots, as `Class.forName` takes them
+    fn map_binary_name(&self, name: &str) -> String {
+        self.map_class(&internal(name)).replace('/', ".")
+    }
+}
+
+/// Applies a mapping to class files. The names in the mapping are the original ones, and so
+/// are the names in the hierarchy, which is used to find the fields and methods that member
+/// references and overriding methods resolve to.
+pub struct Remapper<'a> {
+    mapping: &'a Mapping,
+    hierarchy: &'a dyn ClassHierarchy,
+}
+
+/// A constant pool entry to replace once everything that reads the original names is done
+enum Pending {
+    Class(String),
+    NameAndType(u16, String),
+    FieldRef(u16, String, String),
+    MethodRef(u16, String, String),
+    InterfaceMethodRef(u16, String, String),
+    MethodType(String),
+}
+
+impl<'a> Remapper<'a> {
+    pub fn new(mapping: &'a Mapping, hierarchy: &'a dyn ClassHierarchy) -> Remapper<'a> {
+        Remapper { mapping, hierarchy }
+    }
+
+    /// The new name of a field referred to through `owner`
+    pub fn field_name(&self, owner: &str, name: &str) -> String {
+        if self.mapping.fields.is_empty() {
+            return name.to_string();
+        }
+        self.owners(owner)
+            .find_map(|class| self.mapping.fields.get(&(class, name.to_string())))
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// The new name of a method referred to through or declared by `owner`. Constructors and
+    /// static initializers keep their names.
+    pub fn method_name(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        if self.mapping.methods.is_empty() || name == "<init>" || name == "<clinit>" {
+            return name.to_string();
+        }
+        self.owners(owner)
+            .find_map(|class| {
+                let key = (class, name.to_string(), descriptor.to_string());
+                self.mapping.methods.get(&key)
+            })
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// The class followed by its supertypes
+    fn owners(&self, owner: &str) -> impl Iterator<Item = String> {
+        std::iter::once(owner.to_string()).chain(self.hierarchy.all_supertypes(owner))
+    }
+
+    /// Renames everything in a class file in place
+    pub fn remap(&self, class_file: &mut ClassFile) -> Result<(), io::Error> {
+        let owner = class_file.class_name()?;
+        let pending = self.pending_entries(class_file.constant_pool())?;
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        let result = self.remap_with(class_file, &owner, pending, &mut builder);
+        // put the constant pool back even if something couldn't be remapped
+        class_file.set_constant_pool(builder.build());
+        result
+    }
+
+    fn remap_with(
+        &self,
+        class_file: &mut ClassFile,
+        owner: &str,
+        pending: Vec<(u16, Pending)>,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<(), io::Error> {
+        let fields = class_file
+            .fields()
+            .iter()
+            .map(|field| self.remap_member(field, owner, false, builder))
+            .collect::<Result<Vec<_>, _>>()?;
+        let methods = class_file
+            .methods()
+            .iter()
+            .map(|method| self.remap_member(method, owner, true, builder))
+            .collect::<Result<Vec<_>, _>>()?;
+        let attributes = self.remap_attributes(class_file.attributes(), builder)?;
+        *class_file.fields_mut() = fields;
+        *class_file.methods_mut() = methods;
+        *class_file.attributes_mut() = attributes;
+
+        // the class entries go last since the attributes above look up the original names
+        // through them
+        for (index, entry) in pending {
+            let data = match entry {
+                Pending::NameAndType(name_index, descriptor) => ConstantPoolData::NameAndType(
+                    NameAndType::from_indices(name_index, builder.add_utf8(&descriptor)?),
+                ),
+                Pending::Class(name) => ConstantPoolData::ClassInfo(ClassInfo::from_name_index(
+                    builder.add_utf8(&name)?,
+                )),
+                Pending::FieldRef(class_index, name, descriptor) => {
+                    let name_and_type = builder.add_name_and_type(&name, &descriptor)?;
+                    ConstantPoolData::FieldRef(FieldRef::from_indices(class_index, name_and_type))
+                }
+                Pending::MethodRef(class_index, name, descriptor) => {
+                    let name_and_type = builder.add_name_and_type(&name, &descriptor)?;
+                    ConstantPoolData::MethodRef(MethodRef::from_indices(class_index, name_and_type))
+                }
+                Pending::InterfaceMethodRef(class_index, name, descriptor) => {
+                    let name_and_type = builder.add_name_and_type(&name, &descriptor)?;
+                    ConstantPoolData::InterfaceMethodRef(InterfaceMethodRef::from_indices(
+                        class_index,
+                        name_and_type,
+                    ))
+                }
+                Pending::MethodType(descriptor) => ConstantPoolData::MethodType(
+                    MethodType::from_descriptor_index(builder.add_utf8(&descriptor)?),
+                ),
+            };
+            builder.replace(index, data)?;
+        }
+        Ok(())
+    }
+
+    /// The constant pool entries that name something that's renamed
+    fn pending_entries(
+        &self,
+        constant_pool: &ConstantPool,
+    ) -> Result<Vec<(u16, Pending)>, io::Error> {
+        let mut pending = Vec::new();
+        for (i, item) in constant_pool.items().iter().enumerate() {
+            let index = (i + 1) as u16;
+            let entry = match item.data() {
+                ConstantPoolData::ClassInfo(class_info) => {
+                    let name = constant_pool.utf8(class_info.name_index())?;
+                    let mapped = self.mapping.map_class(&name);
+                    (mapped != name).then_some(Pending::Class(mapped))
+                }
+                ConstantPoolData::NameAndType(name_and_type) => {
+                    let descriptor = constant_pool.utf8(name_and_type.descriptor_index())?;
+                    let mapped = self.mapping.map_signature(&descriptor)?;
+                    (mapped != descriptor)
+                        .then(|| Pending::NameAndType(name_and_type.name_index(), mapped))
+                }
+                ConstantPoolData::FieldRef(field_ref) => {
+                    let (owner, name, descriptor) = constant_pool.member_ref(index)?;
+                    let mapped = self.field_name(&owner, &name);
+                    if mapped != name {
+                        let descriptor = self.mapping.map_signature(&descriptor)?;
+                        Some(Pending::FieldRef(
+                            field_ref.class_index(),
+                            mapped,
+                            descriptor,
+                        ))
+                    } else {
+                        None
+                    }
+                }
+                ConstantPoolData::MethodRef(method_ref) => {
+                    let (owner, name, descriptor) = constant_pool.member_ref(index)?;
+                    let mapped = self.method_name(&owner, &name, &descriptor);
+                    if mapped != name {
+                        let descriptor = self.mapping.map_signature(&descriptor)?;
+                        Some(Pending::MethodRef(
+                            method_ref.class_index(),
+                            mapped,
+                            descriptor,
+                        ))
+                    } else {
+                        None
+                    }
+                }
+                ConstantPoolData::InterfaceMethodRef(method_ref) => {
+                    let (owner, name, descriptor) = constant_pool.member_ref(index)?;
+                    let mapped = self.method_name(&owner, &name, &descriptor);
+                    if mapped != name {
+                        let descriptor = self.mapping.map_signature(&descriptor)?;
+                        Some(Pending::InterfaceMethodRef(
+                            method_ref.class_index(),
+                            mapped,
+                            descriptor,
+                        ))
+                    } else {
+                        None
+                    }
+                }
+                ConstantPoolData::MethodType(method_type) => {
+                    let descriptor = constant_pool.utf8(method_type.descriptor_index())?;
+                    let mapped = self.mapping.map_signature(&descriptor)?;
+                    (mapped != descriptor).then_some(Pending::MethodType(mapped))
+                }
+                _ => None,
+            };
+            pending.extend(entry.map(|entry| (index, entry)));
+        }
+        Ok(pending)
+    }
+
+    fn remap_member(
+        &self,
+        member: &MemberInfo,
+        owner: &str,
+        is_method: bool,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<MemberInfo, io::Error> {
+        let name = member.name(builder.constant_pool())?;
+        let descriptor = member.descriptor(builder.constant_pool())?;
+        let mapped_name = if is_method {
+            self.method_name(owner, &name, &descriptor)
+        } else {
+            self.field_name(owner, &name)
+        };
+        let name_index = match mapped_name == name {
+            true => member.name_index(),
+            false => builder.add_utf8(&mapped_name)?,
+        };
+        let descriptor_index = self.remap_signature(member.descriptor_index(), builder)?;
+        let attributes = self.remap_attributes(member.attributes(), builder)?;
+        Ok(MemberInfo::new(
+            member.access_flags(),
+            name_index,
+            descriptor_index,
+            attributes,
+        ))
+    }
+
+    /// The index of a Utf8 entry with the descriptor or signature at `index` renamed
+    fn remap_signature(
+        &self,
+        index: u16,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<u16, io::Error> {
+        let signature = builder.constant_pool().utf8(index)?;
+        let mapped = self.mapping.map_signature(&signature)?;
+        match mapped == signature {
+            true => Ok(index),
+            false => builder.add_utf8(&mapped),
+        }
+    }
+
+    /// Attributes of a class, field, method or Code attribute that name classes or members
+    /// outside of Class and member reference entries
+    fn remap_attributes(
+        &self,
+        attributes: &[AttributeInfo],
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<Vec<AttributeInfo>, io::Error> {
+        let mut remapped = Vec::with_capacity(attributes.len());
+        for attribute in attributes {
+            let Ok(name) = attribute.name(builder.constant_pool()) else {
+                remapped.push(attribute.clone());
+                continue;
+            };
+            let reader = &mut attribute.reader();
+            let mut info = Vec::with_capacity(attribute.info().len());
+            match name.as_str() {
+                "Signature" => {
+                    let index = self.remap_signature(read_u16(reader)?, builder)?;
+                    info.extend_from_slice(&index.to_be_bytes());
+                }
+                Code::NAME => {
+                    let code = Code::from(reader)?;
+                    let attributes = self.remap_attributes(code.attributes(), builder)?;
+                    Code::new(
+                        code.max_stack(),
+                        code.max_locals(),
+                        code.code().to_vec(),
+                        code.exception_table().to_vec(),
+                        attributes,
+                    )
+                    .write(&mut info);
+                }
+                // the type table has signatures where the other has descriptors
+                LocalVariableTable::NAME | "LocalVariableTypeTable" => {
+                    let table = LocalVariableTable::from(reader)?;
+                    let mut variables = Vec::with_capacity(table.local_variable_table().len());
+                    for variable in table.local_variable_table() {
+                        variables.push(LocalVariable::new(
+                            variable.start_pc(),
+                            variable.length(),
+                            variable.name_index(),
+                            self.remap_signature(variable.descriptor_index(), builder)?,
+                            variable.index(),
+                        ));
+                    }
+                    LocalVariableTable::new(variables).write(&mut info);
+                }
+                InnerClasses::NAME => {
+                    let table = InnerClasses::from(reader)?;
+                    let mut classes = Vec::with_capacity(table.classes().len());
+                    for class in table.classes() {
+                        classes.push(InnerClass::new(
+                            class.inner_class_info_index(),
+                            class.outer_class_info_index(),
+                            self.remap_inner_name(class, builder)?,
+                            class.inner_class_access_flags(),
+                        ));
+                    }
+                    InnerClasses::new(classes).write(&mut info);
+                }
+                "EnclosingMethod" => {
+                    let class_index = read_u16(reader)?;
+                    let mut method_index = read_u16(reader)?;
+                    if method_index != 0 {
+                        let constant_pool = builder.constant_pool();
+                        let owner = constant_pool.class_name(class_index)?;
+                        let (name, descriptor) = constant_pool.name_and_type(method_index)?;
+                        let mapped_name = self.method_name(&owner, &name, &descriptor);
+                        if mapped_name != name {
+                            let descriptor = self.mapping.map_signature(&descriptor)?;
+                            method_index = builder.add_name_and_type(&mapped_name, &descriptor)?;
+                        }
+                    }
+                    info.extend_from_slice(&class_index.to_be_bytes());
+                    info.extend_from_slice(&method_index.to_be_bytes());
+                }
+                "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                    let count = read_u16(reader)?;
+                    info.extend_from_slice(&count.to_be_bytes());
+                    for _ in 0..count {
+                        self.remap_annotation(reader, &mut info, builder)?;
+                    }
+                }
+                "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                    let parameters = read_u8(reader)?;
+                    info.push(parameters);
+                    for _ in 0..parameters {
+                        let count = read_u16(reader)?;
+                        info.extend_from_slice(&count.to_be_bytes());
+                        for _ in 0..count {
+                            self.remap_annotation(reader, &mut info, builder)?;
+                        }
+                    }
+                }
+                "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                    let count = read_u16(reader)?;
+                    info.extend_from_slice(&count.to_be_bytes());
+                    for _ in 0..count {
+                        copy_type_annotation_target(reader, &mut info)?;
+                        self.remap_annotation(reader, &mut info, builder)?;
+                    }
+                }
+                "AnnotationDefault" => self.remap_element_value(reader, &mut info, builder)?,
+                _ => {
+                    remapped.push(attribute.clone());
+                    continue;
+                }
+            }
+            remapped.push(AttributeInfo::new(attribute.attribute_name_index(), info));
+        }
+        Ok(remapped)
+    }
+
+    /// The simple name of an inner class, which changes with its binary name unless it was
+    /// never derived from it
+    fn remap_inner_name(
+        &self,
+        class: &InnerClass,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<u16, io::Error> {
+        let index = class.inner_name_index();
+        if index == 0 {
+            return Ok(index);
+        }
+        let constant_pool = builder.constant_pool();
+        let binary_name = constant_pool.class_name(class.inner_class_info_index())?;
+        let name = constant_pool.utf8(index)?;
+        let mapped = self.mapping.map_class(&binary_name);
+        if mapped == binary_name || !binary_name.ends_with(&format!("${name}")) {
+            return Ok(index);
+        }
+        match simple_name(&mapped) {
+            mapped_name if mapped_name == name => Ok(index),
+            mapped_name => builder.add_utf8(mapped_name),
+        }
+    }
+
+    /// Copies an `annotation` structure (section 4.7.16) with its types renamed
+    fn remap_annotation(
+        &self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<(), io::Error> {
+        let type_index = self.remap_signature(read_u16(reader)?, builder)?;
+        out.extend_from_slice(&type_index.to_be_bytes());
+        let pairs = read_u16(reader)?;
+        out.extend_from_slice(&pairs.to_be_bytes());
+        for _ in 0..pairs {
+            out.extend_from_slice(&read_u16(reader)?.to_be_bytes()); // element_name_index
+            self.remap_element_value(reader, out, builder)?;
+        }
+        Ok(())
+    }
+
+    /// Copies an `element_value` structure (section 4.7.16.1), renaming enum constants along
+    /// with their types
+    fn remap_element_value(
+        &self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+        builder: &mut ConstantPoolBuilder,
+    ) -> Result<(), io::Error> {
+        let tag = read_u8(reader)?;
+        out.push(tag);
+        match tag {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b's' | b'S' | b'Z' => {
+                out.extend_from_slice(&read_u16(reader)?.to_be_bytes());
+            }
+            b'e' => {
+                let type_index = read_u16(reader)?;
+                let mut const_index = read_u16(reader)?;
+                let constant_pool = builder.constant_pool();
+                let descriptor = constant_pool.utf8(type_index)?;
+                let owner = descriptor
+                    .strip_prefix('L')
+                    .and_then(|owner| owner.strip_suffix(';'))
+                    .unwrap_or(&descriptor);
+                let name = constant_pool.utf8(const_index)?;
+                let mapped_name = self.field_name(owner, &name);
+                if mapped_name != name {
+                    const_index = builder.add_utf8(&mapped_name)?;
+                }
+                let type_index = self.remap_signature(type_index, builder)?;
+                out.extend_from_slice(&type_index.to_be_bytes());
+                out.extend_from_slice(&const_index.to_be_bytes());
+            }
+            b'c' => {
+                let index = self.remap_signature(read_u16(reader)?, builder)?;
+                out.extend_from_slice(&index.to_be_bytes());
+            }
+            b'@' => self.remap_annotation(reader, out, builder)?,
+            b'[' => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.remap_element_value(reader, out, builder)?;
+                }
+            }
+            tag => {
+                return Err(remap_error(&format!(
+                    "Unknown annotation element value tag {tag}"
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copies the `target_info` and `type_path` of a `type_annotation` (section 4.7.20), which
+/// only hold offsets and indices into tables
+pub(crate) fn copy_type_annotation_target(
+    reader: &mut BufReader<impl Read>,
+    out: &mut Vec<u8>,
+) -> Result<(), io::Error> {
+    out.extend_from_slice(&read_type_annotation_target(reader)?);
+    Ok(())
+}
+
+pub(crate) fn copy_bytes(
+    reader: &mut BufReader<impl Read>,
+    out: &mut Vec<u8>,
+    length: usize,
+) -> Result<(), io::Error> {
+    let start = out.len();
+    out.resize(start + length, 0);
+    reader.read_exact(&mut out[start..])
+}
+
+/// The name of a class without its package or outer classes
+fn simple_name(class_name: &str) -> &str {
+    let start = class_name.rfind(['/', '$']).map_or(0, |i| i + 1);
+    &class_name[start..]
+}
+
+/// Class names are written either way in mapping files
+fn internal(name: &str) -> String {
+    name.replace('.', "/")
+}
+
+fn remap_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::dependencies::class_dependencies;
+    use crate::analysis::hierarchy::ClassFileHierarchy;
+    use crate::analysis::verifier::verify_class;
+    use crate::assembler::assembler::assemble;
+
+    const MAPPING: &str = "
+# shade the library
+package lib shaded/lib
+package lib/internal shaded/impl
+class lib.Api shaded/Facade   # dots work too
+field lib/Base count total
+method lib/Base run()V execute
+";
+
+    #[test]
+    fn maps_class_names_and_signatures() {
+        let mapping = Mapping::parse(MAPPING).unwrap();
+        assert_eq!(mapping.map_class("lib/Api"), "shaded/Facade");
+        assert_eq!(
+            mapping.map_class("lib/Api$Builder"),
+            "shaded/Facade$Builder"
+        );
+        assert_eq!(mapping.map_class("lib/util/List"), "shaded/lib/util/List");
+        assert_eq!(mapping.map_class("lib/internal/Node"), "shaded/impl/Node");
+        assert_eq!(mapping.map_class("[[Llib/Api;"), "[[Lshaded/Facade;");
+        assert_eq!(mapping.map_class("library/Api"), "library/Api");
+        assert_eq!(
+            mapping
+                .map_signature("<T:Llib/Base;>(TT;[Llib/Api<*>.Builder<+Llib/X;>;)V^Llib/E;")
+                .unwrap(),
+            "<T:Lshaded/lib/Base;>(TT;[Lshaded/Facade<*>.Builder<+Lshaded/lib/X;>;)V^Lshaded/lib/E;"
+        );
+        assert!(mapping.map_signature("(Llib/Api").is_err());
+
+        let error = Mapping::parse("class a/B").unwrap_err();
+        assert!(error.to_string().starts_with("line 1: expected"));
+        assert!(Mapping::parse("method a/B run c").is_err());
+    }
+
+    #[test]
+    fn remaps_references_members_and_attributes() {
+        let base = assemble(
+            "
+.class public lib/Base
+.super java/lang/Object
+.field protected count I
+.method public run()V
+    return
+.end method
+",
+        )
+        .unwrap();
+        let mut class_file = assemble(
+            "
+.class public lib/Impl
+.super lib/Base
+.method public <init>()V
+    .limit stack 1
+    .limit locals 1
+    aload_0
+    invokespecial lib/Base/<init>()V
+    return
+.end method
+.method public run()V
+    .limit stack 2
+    .limit locals 1
+    aload_0
+    getfield lib/Impl/count I
+    pop
+    invokestatic lib/Api/create()Llib/Api;
+    pop
+    return
+.end method
+",
+        )
+        .unwrap();
+        // @lib/Marker(kind=lib/Kind.X) and a generic signature on the class
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        let annotations = builder.add_utf8("RuntimeVisibleAnnotations").unwrap();
+        let mut info = vec![0, 1];
+        for (i, value) in ["Llib/Marker;", "kind", "Llib/Kind;", "X"]
+            .iter()
+            .enumerate()
+        {
+            info.extend(builder.add_utf8(value).unwrap().to_be_bytes());
+            match i {
+                0 => info.extend([0, 1]),
+                1 => info.push(b'e'),
+                _ => {}
+            }
+        }
+        let signature = builder.add_utf8("Signature").unwrap();
+        let generic = builder.add_utf8("Llib/Base<Llib/Api;>;").unwrap();
+        class_file.set_constant_pool(builder.build());
+        class_file.attributes_mut().extend([
+            AttributeInfo::new(annotations, info),
+            AttributeInfo::new(signature, generic.to_be_bytes().to_vec()),
+        ]);
+
+        let mut hierarchy = ClassFileHierarchy::new();
+        hierarchy.add_class_file(&base).unwrap();
+        hierarchy.add_class_file(&class_file).unwrap();
+        let mapping = Mapping::parse(MAPPING).unwrap();
+        Remapper::new(&mapping, &hierarchy)
+            .remap(&mut class_file)
+            .unwrap();
+
+        assert_eq!(class_file.class_name().unwrap(), "shaded/lib/Impl");
+        assert_eq!(
+            class_file.super_class_name().unwrap().as_deref(),
+            Some("shaded/lib/Base")
+        );
+        // the override follows the method it overrides, the constructor keeps its name
+        assert!(class_file.find_method("execute", "()V").is_some());
+        assert!(class_file.find_method("run", "()V").is_none());
+        assert!(class_file.find_method("<init>", "()V").is_some());
+
+        let constant_pool = class_file.constant_pool();
+        let refs: Vec<(String, String, String)> = (1..constant_pool.count())
+            .filter_map(|index| constant_pool.member_ref(index).ok())
+            .collect();
+        for expected in [
+            ("shaded/lib/Base", "<init>", "()V"),
+            ("shaded/lib/Impl", "total", "I"),
+            ("shaded/Facade", "create", "()Lshaded/Facade;"),
+        ] {
+            let expected = (
+                expected.0.to_string(),
+                expected.1.to_string(),
+                expected.2.to_string(),
+            );
+            assert!(refs.contains(&expected), "{expected:?} is missing");
+        }
+        let dependencies = class_dependencies(&class_file).unwrap();
+        assert!(
+            dependencies.iter().all(|class| !class.starts_with("lib/")),
+            "{dependencies:?}"
+        );
+        assert!(dependencies.contains("shaded/lib/Marker"));
+        assert!(dependencies.contains("shaded/Facade"));
+
+        // the rewritten class still parses and verifies
+        let mut reread = ClassFile::new();
+        reread.read_slice(&class_file.to_bytes()).unwrap();
+        assert_eq!(verify_class(&reread, &hierarchy), vec![]);
+    }
+
+    #[test]
+    fn moves_resources_with_their_package() {
+        let mapping = Mapping::parse(MAPPING).unwrap();
+        assert_eq!(
+            mapping.remap_resource("lib/messages.properties", b"x=y".to_vec()),
+            (
+                "shaded/lib/messages.properties".to_string(),
+                b"x=y".to_vec()
+            )
+        );
+        assert_eq!(
+            mapping.remap_resource("META-INF/MANIFEST.MF", Vec::new()).0,
+            "META-INF/MANIFEST.MF"
+        );
+        let (name, data) = mapping.remap_resource(
+            "META-INF/services/lib.Api",
+            b"# providers\nlib.internal.ApiImpl\nother.Impl\n".to_vec(),
+        );
+        assert_eq!(name, "META-INF/services/shaded.Facade");
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "# providers\nshaded.impl.ApiImpl\nother.Impl\n"
+        );
+    }
+}
@@ -0,0 +1,809 @@
+use crate::bytecode::instruction::Instruction;
+use crate::class_file::attribute::attribute_info::{
+    read_attributes, write_attributes, AttributeInfo,
+};
+use crate::class_file::attribute::bootstrap_methods::BootstrapMethods;
+use crate::class_file::attribute::code::{Code, ExceptionTableEntry};
+use crate::class_file::attribute::constant_value::ConstantValue;
+use crate::class_file::attribute::exceptions::Exceptions;
+use crate::class_file::attribute::inner_classes::InnerClasses;
+use crate::class_file::attribute::line_number_table::LineNumberTable;
+use crate::class_file::attribute::local_variable_table::LocalVariableTable;
+use crate::class_file::attribute::source_file::SourceFile;
+use crate::class_file::attribute::stack_map_table::StackMapTable;
+use crate::class_file::class_file::ClassFile;
+use crate::class_file::constant_pool::constant_pool::ConstantPool;
+use crate::class_file::constant_pool::item::{ConstantPoolData, ConstantPoolItem};
+use crate::class_file::constant_pool::types::class_info::ClassInfo;
+use crate::class_file::constant_pool::types::field_ref::FieldRef;
+use crate::class_file::constant_pool::types::interface_method_ref::InterfaceMethodRef;
+use crate::class_file::constant_pool::types::invoke_dynamic::InvokeDynamic;
+use crate::class_file::constant_pool::types::jvm_string::JvmString;
+use crate::class_file::constant_pool::types::method_handle::MethodHandle;
+use crate::class_file::constant_pool::types::method_ref::MethodRef;
+use crate::class_file::constant_pool::types::method_type::MethodType;
+use crate::class_file::constant_pool::types::name_and_type::NameAndType;
+use crate::class_file::member_info::MemberInfo;
+use crate::tools::remap::{copy_bytes, copy_type_annotation_target};
+use crate::util::file::{read_u16, read_u8};
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+
+// Makes class files smaller for deployment: debug attributes are removed on request, then
+// every constant pool entry that nothing refers to is dropped (including those other tools
+// such as the remapper leave behind) and the rest are renumbered in their original order.
+// Since indices only ever go down, an `ldc` still fits and the code keeps its layout, so
+// stack map frames and offsets stay valid. References are found in a first pass over the
+// class and moved in a second; both understand every attribute of the Java 8 format (and the
+// nest and record attributes of later versions), and an attribute they don't know is an error since it could refer to anything.
+
+/// Which debug attributes to remove before compacting the constant pool
+pub struct Shrinker {
+    strip_line_numbers: bool,
+    strip_local_variables: bool,
+    strip_source: bool,
+}
+
+impl Shrinker {
+    /// A shrinker that keeps all attributes and only compacts the constant pool
+    pub fn new() -> Shrinker {
+        Shrinker {
+            strip_line_numbers: false,
+            strip_local_variables: false,
+            strip_source: false,
+        }
+    }
+
+    pub fn strip_line_numbers(&self) -> bool {
+        self.strip_line_numbers
+    }
+
+    /// Removes LineNumberTable attributes
+    pub fn set_strip_line_numbers(&mut self, strip_line_numbers: bool) {
+        self.strip_line_numbers = strip_line_numbers;
+    }
+
+    pub fn strip_local_variables(&self) -> bool {
+        self.strip_local_variables
+    }
+
+    /// Removes LocalVariableTable and LocalVariableTypeTable attributes
+    pub fn set_strip_local_variables(&mut self, strip_local_variables: bool) {
+        self.strip_local_variables = strip_local_variables;
+    }
+
+    pub fn strip_source(&self) -> bool {
+        self.strip_source
+    }
+
+    /// Removes SourceFile and SourceDebugExtension attributes, which name the source files
+    pub fn set_strip_source(&mut self, strip_source: bool) {
+        self.strip_source = strip_source;
+    }
+
+    /// Removes the debug attributes asked for and then the constants nothing uses. On error
+    /// the class may have lost its debug attributes but its constant pool is unchanged.
+    pub fn shrink(&self, class_file: &mut ClassFile) -> Result<(), io::Error> {
+        let constant_pool = class_file.take_constant_pool();
+        let result = self.strip_class(class_file, &constant_pool);
+        class_file.set_constant_pool(constant_pool);
+        result?;
+        compact_constant_pool(class_file)
+    }
+
+    fn strip_class(
+        &self,
+        class_file: &mut ClassFile,
+        constant_pool: &ConstantPool,
+    ) -> Result<(), io::Error> {
+        self.strip(class_file.attributes_mut(), constant_pool)?;
+        for field in class_file.fields_mut() {
+            self.strip(field.attributes_mut(), constant_pool)?;
+        }
+        for method in class_file.methods_mut() {
+            self.strip(method.attributes_mut(), constant_pool)?;
+        }
+        Ok(())
+    }
+
+    /// Removes stripped attributes from a table, and from any Code attribute in it
+    fn strip(
+        &self,
+        attributes: &mut Vec<AttributeInfo>,
+        constant_pool: &ConstantPool,
+    ) -> Result<(), io::Error> {
+        let mut kept = Vec::with_capacity(attributes.len());
+        for attribute in attributes.drain(..) {
+            // anything unnamed is left for compacting to report
+            let Ok(name) = attribute.name(constant_pool) else {
+                kept.push(attribute);
+                continue;
+            };
+            match name.as_str() {
+                LineNumberTable::NAME if self.strip_line_numbers => {}
+                LocalVariableTable::NAME | "LocalVariableTypeTable"
+                    if self.strip_local_variables => {}
+                SourceFile::NAME | "SourceDebugExtension" if self.strip_source => {}
+                Code::NAME => {
+                    let code = Code::from(&mut attribute.reader())?;
+                    let mut code_attributes = code.attributes().to_vec();
+                    self.strip(&mut code_attributes, constant_pool)?;
+                    let mut info = Vec::with_capacity(attribute.info().len());
+                    Code::new(
+                        code.max_stack(),
+                        code.max_locals(),
+                        code.code().to_vec(),
+                        code.exception_table().to_vec(),
+                        code_attributes,
+                    )
+                    .write(&mut info);
+                    kept.push(AttributeInfo::new(attribute.attribute_name_index(), info));
+                }
+                _ => kept.push(attribute),
+            }
+        }
+        *attributes = kept;
+        Ok(())
+    }
+}
+
+/// Drops the constant pool entries nothing in the class refers to, directly or through other
+/// entries, and renumbers the rest. The class is unchanged if this fails.
+pub fn compact_constant_pool(class_file: &mut ClassFile) -> Result<(), io::Error> {
+    let constant_pool = class_file.take_constant_pool();
+    match compact(class_file, &constant_pool) {
+        Ok(compacted) => class_file.set_constant_pool(compacted),
+        Err(error) => {
+            class_file.set_constant_pool(constant_pool);
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+fn compact(
+    class_file: &mut ClassFile,
+    constant_pool: &ConstantPool,
+) -> Result<ConstantPool, io::Error> {
+    let length = constant_pool.items().len();
+    let mut renumberer = Renumberer {
+        constant_pool,
+        renumbering: Renumbering::Mark(vec![false; length + 1]),
+    };
+    // marking writes every index back as it was, so a failure here leaves the class alone,
+    // and anything that could fail has been read by the time the indices are moved
+    renumberer.class(class_file)?;
+    let Renumbering::Mark(mut used) =
+        std::mem::replace(&mut renumberer.renumbering, Renumbering::Map(Vec::new()))
+    else {
+        unreachable!()
+    };
+
+    // entries used by other entries
+    let mut pending = (1..=length as u16)
+        .filter(|index| used[*index as usize])
+        .collect::<Vec<_>>();
+    while let Some(index) = pending.pop() {
+        for reference in references(constant_pool.items()[index as usize - 1].data()) {
+            if !renumberer.exists(reference) {
+                return Err(shrink_error(&format!(
+                    "Constant #{index} refers to #{reference}, which is not a constant pool entry"
+                )));
+            }
+            if !used[reference as usize] {
+                used[reference as usize] = true;
+                pending.push(reference);
+            }
+        }
+    }
+
+    let mut map = vec![0; length + 1];
+    let mut next = 1;
+    for index in 1..=length {
+        if used[index] {
+            map[index] = next;
+            next += constant_pool.items()[index - 1].tag().slots();
+        }
+    }
+    let mut compacted = ConstantPool::new();
+    for (i, item) in constant_pool.items().iter().enumerate() {
+        if used[i + 1] {
+            compacted.push_item(ConstantPoolItem::from_data(renumber_data(
+                item.data(),
+                &map,
+            )))?;
+        }
+    }
+
+    renumberer.renumbering = Renumbering::Map(map);
+    renumberer.class(class_file)?;
+    Ok(compacted)
+}
+
+/// The entries a constant pool entry refers to
+fn references(data: &ConstantPoolData) -> Vec<u16> {
+    match data {
+        ConstantPoolData::ClassInfo(class_info) => vec![class_info.name_index()],
+        ConstantPoolData::String(string) => vec![string.string_index()],
+        ConstantPoolData::FieldRef(field_ref) => {
+            vec![field_ref.class_index(), field_ref.name_and_type_index()]
+        }
+        ConstantPoolData::MethodRef(method_ref) => {
+            vec![method_ref.class_index(), method_ref.name_and_type_index()]
+        }
+        ConstantPoolData::InterfaceMethodRef(method_ref) => {
+            vec![method_ref.class_index(), method_ref.name_and_type_index()]
+        }
+        ConstantPoolData::NameAndType(name_and_type) => {
+            vec![name_and_type.name_index(), name_and_type.descriptor_index()]
+        }
+        ConstantPoolData::MethodHandle(method_handle) => vec![method_handle.reference_index()],
+        ConstantPoolData::MethodType(method_type) => vec![method_type.descriptor_index()],
+        // the bootstrap method is an index into the BootstrapMethods attribute
+        ConstantPoolData::InvokeDynamic(invoke_dynamic) => {
+            vec![invoke_dynamic.name_and_type_index()]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn renumber_data(data: &ConstantPoolData, map: &[u16]) -> ConstantPoolData {
+    let map = |index: u16| map[index as usize];
+    match data {
+        ConstantPoolData::ClassInfo(class_info) => {
+            ConstantPoolData::ClassInfo(ClassInfo::from_name_index(map(class_info.name_index())))
+        }
+        ConstantPoolData::String(string) => {
+            ConstantPoolData::String(JvmString::from_string_index(map(string.string_index())))
+        }
+        ConstantPoolData::FieldRef(field_ref) => {
+            ConstantPoolData::FieldRef(FieldRef::from_indices(
+                map(field_ref.class_index()),
+                map(field_ref.name_and_type_index()),
+            ))
+        }
+        ConstantPoolData::MethodRef(method_ref) => {
+            ConstantPoolData::MethodRef(MethodRef::from_indices(
+                map(method_ref.class_index()),
+                map(method_ref.name_and_type_index()),
+            ))
+        }
+        ConstantPoolData::InterfaceMethodRef(method_ref) => {
+            ConstantPoolData::InterfaceMethodRef(InterfaceMethodRef::from_indices(
+                map(method_ref.class_index()),
+                map(method_ref.name_and_type_index()),
+            ))
+        }
+        ConstantPoolData::NameAndType(name_and_type) => {
+            ConstantPoolData::NameAndType(NameAndType::from_indices(
+                map(name_and_type.name_index()),
+                map(name_and_type.descriptor_index()),
+            ))
+        }
+        ConstantPoolData::MethodHandle(method_handle) => {
+            ConstantPoolData::MethodHandle(MethodHandle::from_reference(
+                method_handle.reference_kind(),
+                map(method_handle.reference_index()),
+            ))
+        }
+        ConstantPoolData::MethodType(method_type) => ConstantPoolData::MethodType(
+            MethodType::from_descriptor_index(map(method_type.descriptor_index())),
+        ),
+        ConstantPoolData::InvokeDynamic(invoke_dynamic) => {
+            ConstantPoolData::InvokeDynamic(InvokeDynamic::from_indices(
+                invoke_dynamic.bootstrap_method_attr_index(),
+                map(invoke_dynamic.name_and_type_index()),
+            ))
+        }
+        data => data.clone(),
+    }
+}
+
+enum Renumbering {
+    /// Which entries are referred to, by index
+    Mark(Vec<bool>),
+    /// The new index of each entry that's kept, by old index
+    Map(Vec<u16>),
+}
+
+/// Walks everything in a class that holds a constant pool index, either to mark the entries
+/// in use or to move them to their new index
+struct Renumberer<'a> {
+    constant_pool: &'a ConstantPool,
+    renumbering: Renumbering,
+}
+
+impl Renumberer<'_> {
+    /// Index 0 means no entry wherever it's allowed, and is left as it is
+    fn index(&mut self, index: u16) -> Result<u16, io::Error> {
+        if index == 0 {
+            return Ok(0);
+        }
+        let exists = self.exists(index);
+        match &mut self.renumbering {
+            Renumbering::Mark(used) => {
+                if !exists {
+                    return Err(shrink_error(&format!(
+                        "#{index} is not a constant pool entry"
+                    )));
+                }
+                used[index as usize] = true;
+                Ok(index)
+            }
+            Renumbering::Map(map) => Ok(map[index as usize]),
+        }
+    }
+
+    fn exists(&self, index: u16) -> bool {
+        !matches!(
+            self.constant_pool.get(index).map(ConstantPoolItem::data),
+            None | Some(ConstantPoolData::Uninit | ConstantPoolData::Unusable)
+        )
+    }
+
+    fn copy_index(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), io::Error> {
+        let index = self.index(read_u16(reader)?)?;
+        out.extend_from_slice(&index.to_be_bytes());
+        Ok(())
+    }
+
+    fn class(&mut self, class_file: &mut ClassFile) -> Result<(), io::Error> {
+        let this_class = self.index(class_file.this_class())?;
+        class_file.set_this_class(this_class);
+        let super_class = self.index(class_file.super_class())?;
+        class_file.set_super_class(super_class);
+        for interface in class_file.interfaces_mut().iter_mut() {
+            *interface = self.index(*interface)?;
+        }
+        for field in class_file.fields_mut() {
+            *field = self.member(field)?;
+        }
+        for method in class_file.methods_mut() {
+            *method = self.member(method)?;
+        }
+        let attributes = self.attributes(class_file.attributes())?;
+        *class_file.attributes_mut() = attributes;
+        Ok(())
+    }
+
+    fn member(&mut self, member: &MemberInfo) -> Result<MemberInfo, io::Error> {
+        Ok(MemberInfo::new(
+            member.access_flags(),
+            self.index(member.name_index())?,
+            self.index(member.descriptor_index())?,
+            self.attributes(member.attributes())?,
+        ))
+    }
+
+    fn attributes(
+        &mut self,
+        attributes: &[AttributeInfo],
+    ) -> Result<Vec<AttributeInfo>, io::Error> {
+        attributes
+            .iter()
+            .map(|attribute| self.attribute(attribute))
+            .collect()
+    }
+
+    fn attribute(&mut self, attribute: &AttributeInfo) -> Result<AttributeInfo, io::Error> {
+        let name = attribute.name(self.constant_pool)?;
+        let name_index = self.index(attribute.attribute_name_index())?;
+        let reader = &mut attribute.reader();
+        let mut info = Vec::with_capacity(attribute.info().len());
+        let out = &mut info;
+        match name.as_str() {
+            LineNumberTable::NAME | "SourceDebugExtension" | "Synthetic" | "Deprecated" => {
+                out.extend_from_slice(attribute.info());
+                return Ok(AttributeInfo::new(name_index, info));
+            }
+            ConstantValue::NAME | SourceFile::NAME | "Signature" => self.copy_index(reader, out)?,
+            Exceptions::NAME => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.copy_index(reader, out)?;
+                }
+            }
+            // attributes added after Java 8, which turn up in classes converted from newer ones
+            "NestHost" => self.copy_index(reader, out)?,
+            "NestMembers" | "PermittedSubclasses" => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.copy_index(reader, out)?;
+                }
+            }
+            "Record" => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.copy_index(reader, out)?; // name_index
+                    self.copy_index(reader, out)?; // descriptor_index
+                    let attributes = self.attributes(&read_attributes(reader)?)?;
+                    write_attributes(&attributes, out);
+                }
+            }
+            "EnclosingMethod" => {
+                self.copy_index(reader, out)?; // class_index
+                self.copy_index(reader, out)?; // method_index
+            }
+            InnerClasses::NAME => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.copy_index(reader, out)?; // inner_class_info_index
+                    self.copy_index(reader, out)?; // outer_class_info_index
+                    self.copy_index(reader, out)?; // inner_name_index
+                    copy_bytes(reader, out, 2)?; // inner_class_access_flags
+                }
+            }
+            LocalVariableTable::NAME | "LocalVariableTypeTable" => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    copy_bytes(reader, out, 4)?; // start_pc, length
+                    self.copy_index(reader, out)?; // name_index
+                    self.copy_index(reader, out)?; // descriptor or signature
+                    copy_bytes(reader, out, 2)?; // index
+                }
+            }
+            BootstrapMethods::NAME => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.copy_index(reader, out)?; // bootstrap_method_ref
+                    let arguments = read_u16(reader)?;
+                    out.extend_from_slice(&arguments.to_be_bytes());
+                    for _ in 0..arguments {
+                        self.copy_index(reader, out)?;
+                    }
+                }
+            }
+            "MethodParameters" => {
+                let count = read_u8(reader)?;
+                out.push(count);
+                for _ in 0..count {
+                    self.copy_index(reader, out)?; // name_index
+                    copy_bytes(reader, out, 2)?; // access_flags
+                }
+            }
+            StackMapTable::NAME => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.stack_map_frame(reader, out)?;
+                }
+            }
+            Code::NAME => self.code(reader, out)?,
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.annotation(reader, out)?;
+                }
+            }
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                let parameters = read_u8(reader)?;
+                out.push(parameters);
+                for _ in 0..parameters {
+                    let count = read_u16(reader)?;
+                    out.extend_from_slice(&count.to_be_bytes());
+                    for _ in 0..count {
+                        self.annotation(reader, out)?;
+                    }
+                }
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    copy_type_annotation_target(reader, out)?;
+                    self.annotation(reader, out)?;
+                }
+            }
+            "AnnotationDefault" => self.element_value(reader, out)?,
+            name => {
+                return Err(shrink_error(&format!(
+                    "Unknown attribute {name} may refer to the constant pool"
+                )))
+            }
+        }
+        if !reader.fill_buf()?.is_empty() {
+            return Err(shrink_error(&format!(
+                "{name} attribute has bytes after its contents"
+            )));
+        }
+        Ok(AttributeInfo::new(name_index, info))
+    }
+
+    /// Rewrites the constant pool operands of the instructions in place; every one of them
+    /// directly follows the opcode
+    fn code(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), io::Error> {
+        let code = Code::from(reader)?;
+        let mut bytes = code.code().to_vec();
+        for instruction in code.instructions() {
+            let (pc, instruction) = instruction?;
+            let Some(index) = instruction.constant_index() else {
+                continue;
+            };
+            let index = self.index(index)?;
+            let operand = pc as usize + 1;
+            match instruction {
+                Instruction::Ldc(_) => bytes[operand] = index as u8,
+                _ => bytes[operand..operand + 2].copy_from_slice(&index.to_be_bytes()),
+            }
+        }
+        let mut exception_table = Vec::with_capacity(code.exception_table().len());
+        for entry in code.exception_table() {
+            exception_table.push(ExceptionTableEntry::new(
+                entry.start_pc(),
+                entry.end_pc(),
+                entry.handler_pc(),
+                self.index(entry.catch_type())?,
+            ));
+        }
+        let attributes = self.attributes(code.attributes())?;
+        Code::new(
+            code.max_stack(),
+            code.max_locals(),
+            bytes,
+            exception_table,
+            attributes,
+        )
+        .write(out);
+        Ok(())
+    }
+
+    /// Copies a stack_map_frame (section 4.7.4), whose Object types name classes
+    fn stack_map_frame(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), io::Error> {
+        let frame_type = read_u8(reader)?;
+        out.push(frame_type);
+        let (locals, stack) = match frame_type {
+            0..=63 => (0, 0),
+            64..=127 => (0, 1),
+            247 => {
+                copy_bytes(reader, out, 2)?;
+                (0, 1)
+            }
+            248..=251 => {
+                copy_bytes(reader, out, 2)?;
+                (0, 0)
+            }
+            252..=254 => {
+                copy_bytes(reader, out, 2)?;
+                (frame_type as u16 - 251, 0)
+            }
+            255 => {
+                copy_bytes(reader, out, 2)?;
+                let locals = read_u16(reader)?;
+                out.extend_from_slice(&locals.to_be_bytes());
+                for _ in 0..locals {
+                    self.verification_type(reader, out)?;
+                }
+                let stack = read_u16(reader)?;
+                out.extend_from_slice(&stack.to_be_bytes());
+                (0, stack)
+            }
+            _ => {
+                return Err(shrink_error(&format!(
+                    "Reserved stack map frame type {frame_type}"
+                )))
+            }
+        };
+        for _ in 0..locals + stack {
+            self.verification_type(reader, out)?;
+        }
+        Ok(())
+    }
+
+    fn verification_type(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), io::Error> {
+        let tag = read_u8(reader)?;
+        out.push(tag);
+        match tag {
+            0..=6 => Ok(()),
+            7 => self.copy_index(reader, out),
+            8 => copy_bytes(reader, out, 2), // offset of the `new`
+            tag => Err(shrink_error(&format!(
+                "Unknown verification type tag {tag}"
+            ))),
+        }
+    }
+
+    /// Copies an `annotation` structure (section 4.7.16)
+    fn annotation(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), io::Error> {
+        self.copy_index(reader, out)?; // type_index
+        let pairs = read_u16(reader)?;
+        out.extend_from_slice(&pairs.to_be_bytes());
+        for _ in 0..pairs {
+            self.copy_index(reader, out)?; // element_name_index
+            self.element_value(reader, out)?;
+        }
+        Ok(())
+    }
+
+    /// Copies an `element_value` structure (section 4.7.16.1)
+    fn element_value(
+        &mut self,
+        reader: &mut BufReader<impl Read>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), io::Error> {
+        let tag = read_u8(reader)?;
+        out.push(tag);
+        match tag {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b's' | b'S' | b'Z' | b'c' => {
+                self.copy_index(reader, out)?;
+            }
+            b'e' => {
+                self.copy_index(reader, out)?; // type_name_index
+                self.copy_index(reader, out)?; // const_name_index
+            }
+            b'@' => self.annotation(reader, out)?,
+            b'[' => {
+                let count = read_u16(reader)?;
+                out.extend_from_slice(&count.to_be_bytes());
+                for _ in 0..count {
+                    self.element_value(reader, out)?;
+                }
+            }
+            tag => {
+                return Err(shrink_error(&format!(
+                    "Unknown annotation element value tag {tag}"
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn shrink_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assembler::assemble;
+    use crate::class_file::constant_pool::builder::ConstantPoolBuilder;
+    use crate::tools::javap::{javap, JavapOptions};
+
+    const SOURCE: &str = "
+.source Secret.java
+.class t/Secret
+.super java/lang/Object
+.field static count J
+.method static f(I)J
+    .limit stack 4
+    .limit locals 1
+    .var 0 is secretName I from Start to End
+    .catch java/lang/RuntimeException from Start to End using Handler
+Start:
+    .line 10
+    ldc \"text\"
+    pop
+    getstatic t/Secret/count J
+    ldc2_w 5000000000
+    ladd
+End:
+    lreturn
+Handler:
+    .line 12
+    pop
+    lconst_0
+    lreturn
+.end method
+";
+
+    /// The disassembly without constant pool indices, which change, or the source file
+    fn disassembly(class_file: &ClassFile) -> Vec<String> {
+        javap(class_file, JavapOptions::new(false, true))
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with("Compiled from"))
+            .map(|line| {
+                line.split_whitespace()
+                    .filter(|word| !word.starts_with('#'))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    fn utf8s(class_file: &ClassFile) -> Vec<String> {
+        let constant_pool = class_file.constant_pool();
+        (1..=constant_pool.items().len() as u16)
+            .filter_map(|index| constant_pool.utf8(index).ok())
+            .collect()
+    }
+
+    #[test]
+    fn strips_debug_info_and_unused_constants() {
+        let mut class_file = assemble(SOURCE).unwrap();
+        // constants nothing refers to, one of them taking two slots
+        let mut builder = ConstantPoolBuilder::from_constant_pool(class_file.take_constant_pool());
+        builder.add_long(42).unwrap();
+        builder.add_class("t/Unused").unwrap();
+        class_file.set_constant_pool(builder.build());
+        let expected = disassembly(&class_file);
+        let count = class_file.constant_pool().items().len();
+
+        let mut shrinker = Shrinker::new();
+        shrinker.set_strip_line_numbers(true);
+        shrinker.set_strip_local_variables(true);
+        shrinker.set_strip_source(true);
+        shrinker.shrink(&mut class_file).unwrap();
+
+        let mut shrunk = ClassFile::new();
+        shrunk.read_slice(&class_file.to_bytes()).unwrap();
+        assert_eq!(disassembly(&shrunk), expected);
+        assert!(shrunk.attributes().is_empty());
+        let code = shrunk
+            .find_method("f", "(I)J")
+            .unwrap()
+            .code(shrunk.constant_pool())
+            .unwrap()
+            .unwrap();
+        assert!(code.attributes().is_empty());
+        assert_eq!(code.exception_table().len(), 1);
+
+        let utf8s = utf8s(&shrunk);
+        for removed in [
+            "Secret.java",
+            "SourceFile",
+            "secretName",
+            "LineNumberTable",
+            "LocalVariableTable",
+            "t/Unused",
+        ] {
+            assert!(!utf8s.iter().any(|utf8| utf8 == removed), "{removed}");
+        }
+        assert!(shrunk.constant_pool().items().len() < count - 8);
+    }
+
+    #[test]
+    fn compacting_keeps_javac_output_as_it_is() {
+        let bytes = std::fs::read("tests/java/helloworld/HelloWorld.class").unwrap();
+        let mut class_file = ClassFile::new();
+        class_file.read_slice(&bytes).unwrap();
+        Shrinker::new().shrink(&mut class_file).unwrap();
+        assert_eq!(class_file.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn unknown_attributes_are_refused() {
+        let source = "
+.class t/Custom
+.super java/lang/Object
+.attribute Vendor 0001
+";
+        let mut class_file = assemble(source).unwrap();
+        let bytes = class_file.to_bytes();
+        let mut shrinker = Shrinker::new();
+        shrinker.set_strip_source(true);
+        let error = shrinker.shrink(&mut class_file).unwrap_err();
+        assert!(
+            error.to_string().contains("Unknown attribute Vendor"),
+            "{error}"
+        );
+        assert_eq!(class_file.to_bytes(), bytes);
+    }
+}
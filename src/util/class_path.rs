@@ -0,0 +1,97 @@
+use crate::util::jar::JarFile;
+use std::io;
+use std::path::Path;
+
+/// The bytes of a class file found on a class path, and where they came from
+#[derive(Debug)]
+pub struct ClassPathEntry {
+    source: String,
+    data: Vec<u8>,
+}
+
+impl ClassPathEntry {
+    /// The file, or `jar!entry` for classes in a jar, for error messages
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Every class file in a class path entry: a jar, a directory searched recursively, or a
+/// single class file. Directories are read in name order so results are stable.
+pub fn read_class_path(path: &str) -> Result<Vec<ClassPathEntry>, io::Error> {
+    let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{path}: {e}"));
+    let mut entries = Vec::new();
+    if Path::new(path).is_dir() {
+        read_directory(Path::new(path), &mut entries).map_err(with_path)?;
+    } else if path.ends_with(".jar") {
+        let jar = JarFile::read_file(path).map_err(with_path)?;
+        for entry in jar.entries().iter().filter(|entry| entry.is_class_file()) {
+            entries.push(ClassPathEntry {
+                source: format!("{path}!{}", entry.name()),
+                data: entry.data().to_vec(),
+            });
+        }
+    } else {
+        entries.push(ClassPathEntry {
+            source: path.to_string(),
+            data: std::fs::read(path).map_err(with_path)?,
+        });
+    }
+    Ok(entries)
+}
+
+fn read_directory(directory: &Path, entries: &mut Vec<ClassPathEntry>) -> Result<(), io::Error> {
+    let mut paths = std::fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            read_directory(&path, entries)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "class")
+        {
+            entries.push(ClassPathEntry {
+                source: path.display().to_string(),
+                data: std::fs::read(&path)?,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_jars_directories_and_class_files() {
+        let sources = |path| {
+            read_class_path(path)
+                .unwrap()
+                .iter()
+                .map(|entry| entry.source().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sources("tests/java/compat/new"),
+            [
+                "tests/java/compat/new/compat/Api.class",
+                "tests/java/compat/new/compat/Base.class",
+                "tests/java/compat/new/compat/Fresh.class",
+                "tests/java/compat/new/compat/Service.class",
+                "tests/java/compat/new/compat/Stays.class",
+            ]
+        );
+        assert!(sources("tests/java/compat/old.jar")
+            .contains(&"tests/java/compat/old.jar!compat/Gone.class".to_string()));
+        let entries = read_class_path("tests/java/helloworld/HelloWorld.class").unwrap();
+        assert_eq!(&entries[0].data()[..4], &[0xCA, 0xFE, 0xBA, 0xBE]);
+        assert!(read_class_path("tests/java/missing.jar").is_err());
+    }
+}
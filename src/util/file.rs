@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io;
 use std::io::{BufReader, Read};
 
@@ -7,13 +6,82 @@ pub fn read_bytes(
     buffer: &mut [u8],
     n_bytes: usize,
 ) -> Result<(), io::Error> {
-    let bytes_read = reader.read(buffer)?;
+    // a single read() may return fewer bytes than requested (e.g. at the end of the
+    // BufReader's internal buffer) so keep reading until we have everything or hit EOF
+    let mut bytes_read = 0;
+    while bytes_read < n_bytes {
+        let n = reader.read(&mut buffer[bytes_read..n_bytes])?;
+        if n == 0 {
+            break;
+        }
+        bytes_read += n;
+    }
+
     if bytes_read != n_bytes {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Expected to read {n_bytes} bytes but instead only read {bytes_read}"),
-        ));
+        return Err(io::Error::other(format!(
+            "Expected to read {n_bytes} bytes but instead only read {bytes_read}"
+        )));
     }
 
     Ok(())
 }
+
+pub fn read_u8(reader: &mut BufReader<impl Read>) -> Result<u8, io::Error> {
+    let mut buffer: [u8; 1] = [0; 1];
+    read_bytes(reader, &mut buffer, 1)?;
+    Ok(buffer[0])
+}
+
+pub fn read_u16(reader: &mut BufReader<impl Read>) -> Result<u16, io::Error> {
+    let mut buffer: [u8; 2] = [0; 2];
+    read_bytes(reader, &mut buffer, 2)?;
+    Ok(u16::from_be_bytes(buffer))
+}
+
+pub fn read_u32(reader: &mut BufReader<impl Read>) -> Result<u32, io::Error> {
+    let mut buffer: [u8; 4] = [0; 4];
+    read_bytes(reader, &mut buffer, 4)?;
+    Ok(u32::from_be_bytes(buffer))
+}
+
+/// Reads a `u2` count followed by that many `u2` values (e.g. a class's interfaces)
+pub fn read_u16_list(reader: &mut BufReader<impl Read>) -> Result<Vec<u16>, io::Error> {
+    let count = read_u16(reader)?;
+    let mut values = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        values.push(read_u16(reader)?);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Hands out at most one byte per read() call
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn read_across_short_reads() {
+        let mut reader = BufReader::with_capacity(1, Trickle(&[0xCA, 0xFE, 0xBA, 0xBE]));
+        assert_eq!(read_u32(&mut reader).unwrap(), 0xCAFEBABE);
+    }
+
+    #[test]
+    fn read_past_end_fails() {
+        let bytes = [0x01];
+        let mut reader = BufReader::new(bytes.as_ref());
+        assert!(read_u16(&mut reader).is_err());
+    }
+}
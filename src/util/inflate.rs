@@ -0,0 +1,317 @@
+use std::io;
+
+// A DEFLATE decoder (RFC 1951), enough to read the entries of jar files.
+// Huffman codes are decoded canonically one bit at a time, the same way zlib's `puff` does.
+
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits for length codes 257..285 (section 3.2.5)
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits for distance codes 0..29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompresses a raw DEFLATE stream (no zlib or gzip header), refusing to produce more
+/// than `limit` bytes
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, io::Error> {
+    let mut inflater = Inflater {
+        input: data,
+        position: 0,
+        bit_buffer: 0,
+        bit_count: 0,
+        output: Vec::new(),
+        limit,
+    };
+    loop {
+        let last = inflater.bits(1)? == 1;
+        match inflater.bits(2)? {
+            0 => inflater.stored()?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflater.codes(&literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = inflater.dynamic_codes()?;
+                inflater.codes(&literals, &distances)?;
+            }
+            _ => return Err(inflate_error("invalid block type")),
+        }
+        if last {
+            return Ok(inflater.output);
+        }
+    }
+}
+
+fn inflate_error(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid deflate data: {message}"),
+    )
+}
+
+/// A canonical Huffman code: how many codes there are of each length, and the symbols
+/// ordered by code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, io::Error> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+
+        // an over-subscribed set of lengths can't be decoded; an incomplete one is allowed
+        let mut left: i32 = 1;
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(inflate_error("over-subscribed huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    // both tables are complete, so building them can't fail
+    let literals = Huffman::new(&lengths).unwrap();
+    let distances = Huffman::new(&[5; 30]).unwrap();
+    (literals, distances)
+}
+
+struct Inflater<'a> {
+    input: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+    output: Vec<u8>,
+    limit: usize,
+}
+
+impl Inflater<'_> {
+    /// Reads `count` bits, least significant first
+    fn bits(&mut self, count: u32) -> Result<u32, io::Error> {
+        while self.bit_count < count {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or_else(|| inflate_error("unexpected end of data"))?;
+            self.position += 1;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u32 << count) - 1);
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), io::Error> {
+        if self.output.len() >= self.limit {
+            return Err(inflate_error("output is larger than expected"));
+        }
+        self.output.push(byte);
+        Ok(())
+    }
+
+    /// Block type 0: LEN, NLEN and the bytes themselves, starting on a byte boundary
+    fn stored(&mut self) -> Result<(), io::Error> {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+        let header = self
+            .input
+            .get(self.position..self.position + 4)
+            .ok_or_else(|| inflate_error("unexpected end of data"))?;
+        let length = u16::from_le_bytes([header[0], header[1]]);
+        let complement = u16::from_le_bytes([header[2], header[3]]);
+        if length != !complement {
+            return Err(inflate_error(
+                "stored block length does not match its complement",
+            ));
+        }
+        self.position += 4;
+        let end = self.position + length as usize;
+        let bytes = self
+            .input
+            .get(self.position..end)
+            .ok_or_else(|| inflate_error("unexpected end of data"))?;
+        if self.output.len() + bytes.len() > self.limit {
+            return Err(inflate_error("output is larger than expected"));
+        }
+        self.output.extend_from_slice(bytes);
+        self.position = end;
+        Ok(())
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Result<u16, io::Error> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..=MAX_BITS {
+            code |= self.bits(1)? as i32;
+            let count = huffman.counts[length] as i32;
+            if code - count < first {
+                return Ok(huffman.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(inflate_error("invalid huffman code"))
+    }
+
+    /// Reads the code lengths of a dynamic block (section 3.2.7)
+    fn dynamic_codes(&mut self) -> Result<(Huffman, Huffman), io::Error> {
+        let literal_count = self.bits(5)? as usize + 257;
+        let distance_count = self.bits(5)? as usize + 1;
+        let code_length_count = self.bits(4)? as usize + 4;
+        if literal_count > 286 || distance_count > 30 {
+            return Err(inflate_error("too many length or distance codes"));
+        }
+
+        let mut code_lengths = [0u8; 19];
+        for position in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+            code_lengths[*position] = self.bits(3)? as u8;
+        }
+        let code_length_code = Huffman::new(&code_lengths)?;
+
+        let mut lengths = vec![0u8; literal_count + distance_count];
+        let mut i = 0;
+        while i < lengths.len() {
+            let symbol = self.decode(&code_length_code)?;
+            let (value, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *i
+                        .checked_sub(1)
+                        .and_then(|p| lengths.get(p))
+                        .ok_or_else(|| inflate_error("repeat with no previous length"))?;
+                    (previous, 3 + self.bits(2)? as usize)
+                }
+                17 => (0, 3 + self.bits(3)? as usize),
+                _ => (0, 11 + self.bits(7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(inflate_error("too many code lengths"));
+            }
+            lengths[i..i + repeat].fill(value);
+            i += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(inflate_error("missing end-of-block code"));
+        }
+
+        let literals = Huffman::new(&lengths[..literal_count])?;
+        let distances = Huffman::new(&lengths[literal_count..])?;
+        Ok((literals, distances))
+    }
+
+    /// Decodes literals and length/distance pairs until the end-of-block code
+    fn codes(&mut self, literals: &Huffman, distances: &Huffman) -> Result<(), io::Error> {
+        loop {
+            let symbol = self.decode(literals)? as usize;
+            match symbol {
+                0..=255 => self.push(symbol as u8)?,
+                256 => return Ok(()),
+                _ => {
+                    let index = symbol - 257;
+                    if index >= LENGTH_BASE.len() {
+                        return Err(inflate_error("invalid length code"));
+                    }
+                    let length = LENGTH_BASE[index] as usize
+                        + self.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                    let index = self.decode(distances)? as usize;
+                    if index >= DISTANCE_BASE.len() {
+                        return Err(inflate_error("invalid distance code"));
+                    }
+                    let distance = DISTANCE_BASE[index] as usize
+                        + self.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                    if distance > self.output.len() {
+                        return Err(inflate_error("distance is too far back"));
+                    }
+
+                    // the copy may overlap what it produces, so go byte by byte
+                    let start = self.output.len() - distance;
+                    for i in 0..length {
+                        let byte = self.output[start + i];
+                        self.push(byte)?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stored_block() {
+        let data = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert_eq!(inflate(&data, 100).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn fixed_and_dynamic_blocks() {
+        // raw deflate of b"hello hello hello hello" at level 9 (a fixed block)
+        let fixed = [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01];
+        assert_eq!(inflate(&fixed, 100).unwrap(), b"hello hello hello hello");
+
+        // zlib.compressobj(9, DEFLATED, -15, 9, Z_HUFFMAN_ONLY) picks a dynamic block here
+        let dynamic = [
+            0x05, 0xc1, 0x01, 0x01, 0x00, 0x00, 0x00, 0x80, 0x90, 0xad, 0xfa, 0x3f, 0x22, 0x10,
+            0xa1, 0x00, 0x06,
+        ];
+        assert_eq!(inflate(&dynamic, 100).unwrap(), b"aaabaabaaabbaaaaaaaa");
+    }
+
+    #[test]
+    fn rejects_bad_data() {
+        assert!(inflate(&[0x07], 100).is_err());
+        assert!(inflate(&[0x01, 0x03, 0x00, 0x00, 0x00], 100).is_err());
+        assert!(inflate(&[0xcb, 0x48], 100).is_err());
+        // over the limit
+        let data = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert!(inflate(&data, 2).is_err());
+    }
+}
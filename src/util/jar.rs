@@ -0,0 +1,356 @@
+use crate::class_file::limits::ParseLimits;
+use crate::util::inflate::inflate;
+use std::io;
+
+// Jar files are zip archives. Only what's needed to read them is supported: the central
+// directory, stored and deflated entries, and CRC checks. Zip64, encryption and
+// multi-disk archives are rejected. Written archives store every entry uncompressed.
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+
+/// Midnight on 1 January 1980 in MS-DOS date format, the earliest a zip entry can have
+const DOS_EPOCH: u16 = (1 << 5) | 1;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const FLAG_ENCRYPTED: u16 = 0x0001;
+
+/// A file inside a jar, already decompressed
+#[derive(Debug)]
+pub struct JarEntry {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl JarEntry {
+    pub fn new(name: String, data: Vec<u8>) -> JarEntry {
+        JarEntry { name, data }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn is_class_file(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+}
+
+#[derive(Debug)]
+pub struct JarFile {
+    entries: Vec<JarEntry>,
+}
+
+impl JarFile {
+    pub fn new(entries: Vec<JarEntry>) -> JarFile {
+        JarFile { entries }
+    }
+
+    pub fn read_file(file_path: &str) -> Result<JarFile, io::Error> {
+        JarFile::from_bytes(&std::fs::read(file_path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<JarFile, io::Error> {
+        let end = find_end_of_central_directory(bytes)?;
+        if le_u16(bytes, end + 4)? != 0 || le_u16(bytes, end + 6)? != 0 {
+            return Err(jar_error("multi-disk archives are not supported"));
+        }
+        let count = le_u16(bytes, end + 10)?;
+        let mut offset = le_u32(bytes, end + 16)? as usize;
+        if count == 0xffff || offset == 0xffff_ffff {
+            return Err(jar_error("zip64 archives are not supported"));
+        }
+
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            if le_u32(bytes, offset)? != CENTRAL_HEADER_SIGNATURE {
+                return Err(jar_error("bad central directory entry"));
+            }
+            let flags = le_u16(bytes, offset + 8)?;
+            let method = le_u16(bytes, offset + 10)?;
+            let crc = le_u32(bytes, offset + 16)?;
+            let compressed_size = le_u32(bytes, offset + 20)? as usize;
+            let size = le_u32(bytes, offset + 24)? as usize;
+            let name_length = le_u16(bytes, offset + 28)? as usize;
+            let extra_length = le_u16(bytes, offset + 30)? as usize;
+            let comment_length = le_u16(bytes, offset + 32)? as usize;
+            let local_offset = le_u32(bytes, offset + 42)? as usize;
+            let name_bytes = slice(bytes, offset + 46, name_length)?;
+            let name = String::from_utf8_lossy(name_bytes).into_owned();
+            offset += 46 + name_length + extra_length + comment_length;
+
+            if flags & FLAG_ENCRYPTED != 0 {
+                return Err(jar_error(&format!("{name} is encrypted")));
+            }
+            if name.ends_with('/') {
+                // directories have no contents
+                continue;
+            }
+
+            // the local header repeats the name and may have a different extra field
+            if le_u32(bytes, local_offset)? != LOCAL_HEADER_SIGNATURE {
+                return Err(jar_error(&format!("bad local header for {name}")));
+            }
+            let data_offset = local_offset
+                + 30
+                + le_u16(bytes, local_offset + 26)? as usize
+                + le_u16(bytes, local_offset + 28)? as usize;
+            // the declared size is untrusted, so bound what a class file may inflate to. Other
+            // entries are only copied through and may be as large as a zip allows.
+            if name.ends_with(".class") && size > ParseLimits::DEFAULT_MAX_FILE_SIZE {
+                return Err(jar_error(&format!(
+                    "{name} is larger than the maximum class file size of {} bytes",
+                    ParseLimits::DEFAULT_MAX_FILE_SIZE
+                )));
+            }
+            let compressed = slice(bytes, data_offset, compressed_size)?;
+            let data = match method {
+                METHOD_STORED => compressed.to_vec(),
+                METHOD_DEFLATED => {
+                    inflate(compressed, size).map_err(|e| jar_error(&format!("{name}: {e}")))?
+                }
+                _ => {
+                    return Err(jar_error(&format!(
+                        "{name} uses unsupported compression method {method}"
+                    )))
+                }
+            };
+            if data.len() != size || crc32(&data) != crc {
+                return Err(jar_error(&format!(
+                    "{name} is corrupt (size or CRC mismatch)"
+                )));
+            }
+            entries.push(JarEntry { name, data });
+        }
+        Ok(JarFile { entries })
+    }
+
+    pub fn entries(&self) -> &[JarEntry] {
+        &self.entries
+    }
+
+    pub fn find_entry(&self, name: &str) -> Option<&JarEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    pub fn write_file(&self, file_path: &str) -> Result<(), io::Error> {
+        std::fs::write(file_path, self.to_bytes()?)
+    }
+
+    /// The archive with every entry stored, in order, followed by the central directory
+    pub fn to_bytes(&self) -> Result<Vec<u8>, io::Error> {
+        if self.entries.len() >= 0xffff {
+            return Err(jar_error(
+                "too many entries for a zip archive without zip64",
+            ));
+        }
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for entry in &self.entries {
+            let name = entry.name.as_bytes();
+            let name_length = u16::try_from(name.len())
+                .map_err(|_| jar_error(&format!("{} has too long a name", entry.name)))?;
+            let size = u32::try_from(entry.data.len())
+                .ok()
+                .filter(|size| *size != 0xffff_ffff)
+                .ok_or_else(|| jar_error(&format!("{} is too large", entry.name)))?;
+            let offset = u32::try_from(out.len())
+                .map_err(|_| jar_error("archive is too large without zip64"))?;
+            let crc = crc32(&entry.data);
+
+            // version needed, flags, method, time and date are shared by both headers
+            let mut common = Vec::with_capacity(26);
+            common.extend_from_slice(&20u16.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+            common.extend_from_slice(&METHOD_STORED.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+            common.extend_from_slice(&DOS_EPOCH.to_le_bytes());
+            common.extend_from_slice(&crc.to_le_bytes());
+            common.extend_from_slice(&size.to_le_bytes());
+            common.extend_from_slice(&size.to_le_bytes());
+            common.extend_from_slice(&name_length.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+
+            out.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            out.extend_from_slice(&common);
+            out.extend_from_slice(name);
+            out.extend_from_slice(&entry.data);
+
+            central.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&common);
+            // comment length, disk number, internal and external attributes
+            central.extend_from_slice(&[0; 10]);
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name);
+        }
+        let central_offset = u32::try_from(out.len())
+            .map_err(|_| jar_error("archive is too large without zip64"))?;
+        let count = self.entries.len() as u16;
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&central_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        Ok(out)
+    }
+}
+
+fn jar_error(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid jar: {message}"),
+    )
+}
+
+fn slice(bytes: &[u8], offset: usize, length: usize) -> Result<&[u8], io::Error> {
+    offset
+        .checked_add(length)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| jar_error("unexpected end of file"))
+}
+
+fn le_u16(bytes: &[u8], offset: usize) -> Result<u16, io::Error> {
+    let b = slice(bytes, offset, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn le_u32(bytes: &[u8], offset: usize) -> Result<u32, io::Error> {
+    let b = slice(bytes, offset, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// The end of central directory record is last, followed only by an optional comment
+fn find_end_of_central_directory(bytes: &[u8]) -> Result<usize, io::Error> {
+    let last = bytes
+        .len()
+        .checked_sub(END_OF_CENTRAL_DIRECTORY_SIZE)
+        .ok_or_else(|| jar_error("file is too short"))?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    (first..=last)
+        .rev()
+        .find(|offset| le_u32(bytes, *offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| jar_error("no end of central directory record"))
+}
+
+/// CRC-32 as used by zip (reflected, polynomial 0xEDB88320)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Builds a single-entry archive with a stored file
+    fn stored_zip(name: &str, data: &[u8], crc: u32) -> Vec<u8> {
+        let mut zip = Vec::new();
+        zip.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        zip.extend_from_slice(&crc.to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(data);
+
+        let central = zip.len();
+        zip.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        zip.extend_from_slice(&crc.to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&[0; 12]);
+        zip.extend_from_slice(&0u32.to_le_bytes());
+        zip.extend_from_slice(name.as_bytes());
+        let central_size = zip.len() - central;
+
+        zip.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        zip.extend_from_slice(&(central_size as u32).to_le_bytes());
+        zip.extend_from_slice(&(central as u32).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn stored_entry() {
+        let jar = JarFile::from_bytes(&stored_zip("a.txt", b"hi", crc32(b"hi"))).unwrap();
+        assert_eq!(jar.entries().len(), 1);
+        assert_eq!(jar.find_entry("a.txt").unwrap().data(), b"hi");
+        assert!(!jar.entries()[0].is_class_file());
+    }
+
+    #[test]
+    fn deflated_entries() {
+        // built with `jar cf` from the compat fixtures
+        let jar = JarFile::read_file("tests/java/compat/old.jar").unwrap();
+        let entry = jar.find_entry("compat/Api.class").unwrap();
+        assert_eq!(
+            entry.data(),
+            std::fs::read("tests/java/compat/old/compat/Api.class").unwrap()
+        );
+        assert!(jar.find_entry("META-INF/MANIFEST.MF").is_some());
+    }
+
+    #[test]
+    fn writes_readable_archives() {
+        let jar = JarFile::new(vec![
+            JarEntry::new("a/B.class".to_string(), vec![0xca, 0xfe]),
+            JarEntry::new("empty.txt".to_string(), Vec::new()),
+        ]);
+        let read = JarFile::from_bytes(&jar.to_bytes().unwrap()).unwrap();
+        assert_eq!(read.entries().len(), 2);
+        assert_eq!(read.find_entry("a/B.class").unwrap().data(), [0xca, 0xfe]);
+        assert!(read.find_entry("empty.txt").unwrap().data().is_empty());
+    }
+
+    #[test]
+    fn rejects_corrupt_archives() {
+        assert!(JarFile::from_bytes(b"PK").is_err());
+        let error = JarFile::from_bytes(&stored_zip("a.txt", b"hi", 0)).unwrap_err();
+        assert!(error.to_string().contains("CRC"));
+    }
+
+    #[test]
+    fn rejects_oversized_class_files() {
+        // patch the uncompressed size in the central directory entry
+        let oversized = |name: &str| {
+            let mut zip = stored_zip(name, b"hi", crc32(b"hi"));
+            let central = 30 + name.len() + 2;
+            zip[central + 24..central + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+            JarFile::from_bytes(&zip).unwrap_err().to_string()
+        };
+        assert!(oversized("A.class").contains("larger than the maximum class file size"));
+        // other resources aren't held to the class file limit
+        assert!(oversized("a.txt").contains("corrupt"));
+    }
+}
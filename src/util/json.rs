@@ -0,0 +1,499 @@
+use std::io;
+
+/// A minimal JSON document model (RFC 8259).
+///
+/// Objects keep their keys in insertion order so output is stable and diffable.
+/// Integers and floats are kept apart so 64 bit values survive a round trip.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Starts an empty object, to be filled with `with`
+    pub fn object() -> JsonValue {
+        JsonValue::Object(Vec::new())
+    }
+
+    /// Builder-style helper for objects: `JsonValue::object().with("a", 1.into())`
+    pub fn with(mut self, key: &str, value: JsonValue) -> JsonValue {
+        if let JsonValue::Object(entries) = &mut self {
+            entries.push((key.to_string(), value));
+        }
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Integer(value) => Some(*value as f64),
+            JsonValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Looks up a required field, failing with a message naming the missing key
+    pub fn field(&self, key: &str) -> Result<&JsonValue, io::Error> {
+        self.get(key)
+            .ok_or_else(|| json_error(&format!("missing field '{key}'")))
+    }
+
+    /// A required integer field that must fit in `T`
+    pub fn integer_field<T: TryFrom<i64>>(&self, key: &str) -> Result<T, io::Error> {
+        let value = self
+            .field(key)?
+            .as_i64()
+            .ok_or_else(|| json_error(&format!("field '{key}' must be an integer")))?;
+        T::try_from(value)
+            .map_err(|_| json_error(&format!("field '{key}' is out of range: {value}")))
+    }
+
+    pub fn str_field(&self, key: &str) -> Result<&str, io::Error> {
+        self.field(key)?
+            .as_str()
+            .ok_or_else(|| json_error(&format!("field '{key}' must be a string")))
+    }
+
+    pub fn array_field(&self, key: &str) -> Result<&[JsonValue], io::Error> {
+        self.field(key)?
+            .as_array()
+            .ok_or_else(|| json_error(&format!("field '{key}' must be an array")))
+    }
+
+    pub fn parse(text: &str) -> Result<JsonValue, io::Error> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.chars.len() {
+            return Err(parser.error("trailing characters after the document"));
+        }
+        Ok(value)
+    }
+
+    /// Pretty-prints with two space indentation
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            JsonValue::Integer(value) => out.push_str(&value.to_string()),
+            JsonValue::Float(value) => {
+                if value.is_finite() {
+                    out.push_str(&format!("{value:?}"));
+                } else {
+                    // JSON has no NaN or infinity
+                    out.push_str("null");
+                }
+            }
+            JsonValue::String(value) => write_string(out, value),
+            JsonValue::Array(values) => {
+                if values.is_empty() {
+                    out.push_str("[]");
+                    return;
+                }
+                // short arrays of scalars stay on one line
+                if values.len() <= 16 && values.iter().all(JsonValue::is_scalar) {
+                    out.push('[');
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            out.push_str(", ");
+                        }
+                        value.write(out, indent);
+                    }
+                    out.push(']');
+                    return;
+                }
+                out.push_str("[\n");
+                for (i, value) in values.iter().enumerate() {
+                    out.push_str(&"  ".repeat(indent + 1));
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push(']');
+            }
+            JsonValue::Object(entries) => {
+                if entries.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+                out.push_str("{\n");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    out.push_str(&"  ".repeat(indent + 1));
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+            }
+        }
+    }
+
+    fn is_scalar(&self) -> bool {
+        !matches!(self, JsonValue::Array(_) | JsonValue::Object(_))
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> JsonValue {
+        JsonValue::Bool(value)
+    }
+}
+
+impl From<i64> for JsonValue {
+    fn from(value: i64) -> JsonValue {
+        JsonValue::Integer(value)
+    }
+}
+
+impl From<u16> for JsonValue {
+    fn from(value: u16) -> JsonValue {
+        JsonValue::Integer(value as i64)
+    }
+}
+
+impl From<u32> for JsonValue {
+    fn from(value: u32) -> JsonValue {
+        JsonValue::Integer(value as i64)
+    }
+}
+
+impl From<i32> for JsonValue {
+    fn from(value: i32) -> JsonValue {
+        JsonValue::Integer(value as i64)
+    }
+}
+
+impl From<usize> for JsonValue {
+    fn from(value: usize) -> JsonValue {
+        JsonValue::Integer(value as i64)
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> JsonValue {
+        JsonValue::Float(value)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> JsonValue {
+        JsonValue::String(value)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(values: Vec<T>) -> JsonValue {
+        JsonValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+pub fn json_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Deeply nested input shouldn't be able to overflow the stack
+const MAX_DEPTH: usize = 256;
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> io::Error {
+        json_error(&format!(
+            "invalid JSON at character {}: {message}",
+            self.position
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), io::Error> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => {
+                self.position -= 1;
+                Err(self.error(&format!("expected '{expected}'")))
+            }
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, io::Error> {
+        for expected in keyword.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error(&format!("expected '{keyword}'")));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, io::Error> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Parser::object),
+            Some('[') => self.nested(Parser::array),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('t') => self.keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.keyword("null", JsonValue::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(self.error(&format!("unexpected '{c}'"))),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Parser) -> Result<JsonValue, io::Error>,
+    ) -> Result<JsonValue, io::Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<JsonValue, io::Error> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(entries)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, io::Error> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(values)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',' or ']'"));
+                }
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, io::Error> {
+        self.expect('"')?;
+        // collect UTF-16 units so that \uXXXX surrogate pairs combine into one character
+        let mut units: Vec<u16> = Vec::new();
+        loop {
+            let c = self
+                .next()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            match c {
+                '"' => break,
+                '\\' => {
+                    let escaped = self
+                        .next()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    let unit = match escaped {
+                        '"' => '"' as u16,
+                        '\\' => '\\' as u16,
+                        '/' => '/' as u16,
+                        'b' => 0x08,
+                        'f' => 0x0C,
+                        'n' => '\n' as u16,
+                        'r' => '\r' as u16,
+                        't' => '\t' as u16,
+                        'u' => {
+                            let end = self.position + 4;
+                            let hex: String = self
+                                .chars
+                                .get(self.position..end)
+                                .unwrap_or(&[])
+                                .iter()
+                                .collect();
+                            self.position = end;
+                            u16::from_str_radix(&hex, 16)
+                                .map_err(|_| self.error("invalid unicode escape"))?
+                        }
+                        _ => return Err(self.error(&format!("unknown escape '\\{escaped}'"))),
+                    };
+                    units.push(unit);
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("control character in string")),
+                c => {
+                    let mut buffer = [0; 2];
+                    units.extend_from_slice(c.encode_utf16(&mut buffer));
+                }
+            }
+        }
+        String::from_utf16(&units).map_err(|_| self.error("string contains an unpaired surrogate"))
+    }
+
+    fn number(&mut self) -> Result<JsonValue, io::Error> {
+        let start = self.position;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || "+-.eE".contains(c)) {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        if !text.contains(['.', 'e', 'E']) {
+            if let Ok(value) = text.parse::<i64>() {
+                return Ok(JsonValue::Integer(value));
+            }
+        }
+        text.parse::<f64>()
+            .map(JsonValue::Float)
+            .map_err(|_| self.error(&format!("invalid number '{text}'")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let value = JsonValue::object()
+            .with("name", "caf\u{e9} \"quoted\"\n".into())
+            .with("big", JsonValue::Integer(i64::MIN))
+            .with("float", 1.5.into())
+            .with("list", vec![1i64, 2, 3].into())
+            .with(
+                "nested",
+                JsonValue::object().with("empty", JsonValue::Array(Vec::new())),
+            )
+            .with("null", JsonValue::Null)
+            .with("flag", true.into());
+        let text = value.to_pretty_string();
+        assert_eq!(JsonValue::parse(&text).unwrap(), value);
+        assert!(text.contains("\"list\": [1, 2, 3]"));
+    }
+
+    #[test]
+    fn parse_escapes_and_numbers() {
+        let value =
+            JsonValue::parse(r#" {"a": "\ud83d\ude00\u00e9\/", "b": -12e2, "c": [ ] } "#).unwrap();
+        assert_eq!(value.str_field("a").unwrap(), "\u{1F600}\u{e9}/");
+        assert_eq!(value.get("b"), Some(&JsonValue::Float(-1200.0)));
+        assert_eq!(value.array_field("c").unwrap().len(), 0);
+        assert!(value.integer_field::<u16>("b").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(JsonValue::parse("").is_err());
+        assert!(JsonValue::parse("{\"a\" 1}").is_err());
+        assert!(JsonValue::parse("[1, 2").is_err());
+        assert!(JsonValue::parse("\"\\x\"").is_err());
+        assert!(JsonValue::parse("[1] 2").is_err());
+        assert!(JsonValue::parse(&"[".repeat(1000)).is_err());
+    }
+}
@@ -1 +1,5 @@
+pub mod class_path;
 pub mod file;
+pub mod inflate;
+pub mod jar;
+pub mod json;
@@ -0,0 +1,29 @@
+package compat;
+
+public class Api extends Base implements Runnable {
+    public static final int LIMIT = 20;
+    public static final String NAME = "api";
+    public long count;
+    public final String label;
+    long total;
+
+    public Api() {
+        label = "";
+    }
+
+    public void run() {}
+
+    public long changed(int x) {
+        return x;
+    }
+
+    void hidden() {}
+
+    public final void overridable() {}
+
+    public void helper() {}
+
+    public void added() {}
+
+    private void secret2() {}
+}
@@ -0,0 +1,5 @@
+package compat;
+
+public class Base {
+    public void pulledUp() {}
+}
@@ -0,0 +1,9 @@
+package compat;
+
+public interface Service {
+    void call();
+
+    void extra();
+
+    default void fallback() {}
+}
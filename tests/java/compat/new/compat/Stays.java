@@ -0,0 +1,3 @@
+package compat;
+
+public final class Stays {}
@@ -0,0 +1,29 @@
+package compat;
+
+public class Api extends Base implements Runnable, java.io.Serializable {
+    public static final int LIMIT = 10;
+    public static final String NAME = "api";
+    public int count;
+    public String label;
+    protected long total;
+
+    public Api() {}
+
+    public void run() {}
+
+    public void removed() {}
+
+    public int changed(int x) {
+        return x;
+    }
+
+    public void hidden() {}
+
+    public void overridable() {}
+
+    public static void helper() {}
+
+    public void pulledUp() {}
+
+    private void secret() {}
+}
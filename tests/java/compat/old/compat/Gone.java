@@ -0,0 +1,3 @@
+package compat;
+
+public class Gone {}
@@ -0,0 +1,5 @@
+package compat;
+
+public interface Service {
+    void call();
+}
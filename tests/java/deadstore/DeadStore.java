@@ -0,0 +1,12 @@
+public class DeadStore {
+    static int sum(int a, int b) {
+        int total = a + b;
+        int twice = total * 2;
+        long unused = 7L;
+        return twice;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(sum(2, 3));
+    }
+}